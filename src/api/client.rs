@@ -3,15 +3,160 @@
 //! This module provides a client for communicating with the Z.AI API,
 //! supporting streaming responses for real-time translation.
 
+use crate::api::filter::{FilterChain, UnescapeFilter};
+use crate::api::transport::{self, ByteStream, ChatTransport, HttpTransport};
+use crate::channel::channel::STREAM_CHANNEL_CAPACITY;
 use crate::error::{Result, TranslationError};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use crate::utils::metrics::TokenUsage;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+/// Default Z.AI API base URL (coding plan endpoint).
+pub const DEFAULT_BASE_URL: &str = "https://api.z.ai/api/coding/paas/v4";
+
+/// Longest wait for the provider to start responding, or to send more of
+/// a response, until one is configured.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Attempts a request gets in all until a number is configured.
+pub const DEFAULT_REQUEST_ATTEMPTS: u32 = 3;
+
+/// Longest wait before a retry.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Unreadable chunks a response may skip before the user is warned.
+pub const MAX_MALFORMED_CHUNKS: usize = 3;
+
+/// Model used for translation requests.
+pub const DEFAULT_MODEL: &str = "glm-4.7";
+
+/// Models offered in the settings; any other can be typed in.
+pub const KNOWN_MODELS: &[&str] = &[
+    "glm-4.7",
+    "glm-4.6",
+    "glm-4.5",
+    "glm-4.5-air",
+    "glm-4.5-flash",
+    "glm-4-flash",
+];
+
+/// Checks whether the API host accepts TCP connections.
+///
+/// Used as a cheap connectivity probe; it never sends a request or spends tokens.
+pub async fn probe_connectivity(base_url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(base_url) else {
+        return false;
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+
+    matches!(
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            tokio::net::TcpStream::connect((host, port)),
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+/// Checks `input` as the base URL of an OpenAI-compatible API.
+///
+/// Returns the URL without trailing slashes, or [`DEFAULT_BASE_URL`] when
+/// `input` is empty.
+pub fn normalize_base_url(input: &str) -> Result<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(DEFAULT_BASE_URL.to_string());
+    }
+    let url = reqwest::Url::parse(input).map_err(|e| {
+        TranslationError::ConfigError(format!("\"{}\" is not a valid URL: {}", input, e))
+    })?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(TranslationError::ConfigError(format!(
+            "\"{}\" is not an http:// or https:// URL",
+            input
+        )));
+    }
+    Ok(input.trim_end_matches('/').to_string())
+}
+
+/// Host of `base_url`, naming the provider in messages.
+fn provider_name(base_url: &str) -> String {
+    reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| base_url.to_string())
+}
+
+/// Opens a connection to the provider on the shared HTTP client.
+///
+/// Sends a `HEAD` request for `base_url`, so the next request finds the
+/// address resolved and the TLS session established in the pool. It never
+/// spends tokens, and any HTTP status counts as success.
+pub async fn preconnect(base_url: &str) -> Result<()> {
+    transport::shared_client()
+        .head(base_url)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(TranslationError::from)?;
+    Ok(())
+}
+
+/// Sender of a chat message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    System,
+    User,
+    /// The model; as the last message, the start of its answer that it
+    /// continues (prefill)
+    Assistant,
+}
+
+impl Role {
+    /// Name of the role on the wire.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+impl Serialize for Role {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Role {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        match name.as_str() {
+            "system" => Ok(Role::System),
+            "user" => Ok(Role::User),
+            "assistant" => Ok(Role::Assistant),
+            _ => Err(serde::de::Error::unknown_variant(
+                &name,
+                &["system", "user", "assistant"],
+            )),
+        }
+    }
+}
 
 /// A chat message in the API request/response.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatMessage {
-    /// Role of the message sender (e.g., "user", "assistant")
-    pub role: String,
+    /// Role of the message sender
+    pub role: Role,
     /// Content of the message
     pub content: String,
 }
@@ -25,8 +170,15 @@ pub struct ChatRequest {
     pub messages: Vec<ChatMessage>,
     /// Whether to stream the response
     pub stream: bool,
-    /// Thinking configuration for the model
+    /// Thinking configuration for the model, omitted from the JSON when `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking: Option<ThinkingConfig>,
+    /// Output token limit, omitted to use the provider default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature, omitted to use the provider default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
 }
 
 /// Configuration for model thinking behavior.
@@ -37,6 +189,81 @@ pub struct ThinkingConfig {
     pub thinking_type: String,
 }
 
+/// How the `thinking` field is sent with chat requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThinkingMode {
+    /// Send `{"type": "enabled"}`
+    Enabled,
+    /// Send `{"type": "disabled"}`
+    Disabled,
+    /// Leave the field out of the request entirely
+    Omit,
+}
+
+impl ThinkingMode {
+    /// All modes, in display order.
+    pub const ALL: [ThinkingMode; 3] = [
+        ThinkingMode::Enabled,
+        ThinkingMode::Disabled,
+        ThinkingMode::Omit,
+    ];
+
+    /// Returns the default mode for an API base URL.
+    ///
+    /// Z.AI understands the field; other OpenAI-compatible servers may
+    /// reject unknown fields with a 400, so the field is omitted for them.
+    pub fn default_for(base_url: &str) -> Self {
+        if base_url.contains("z.ai") || base_url.contains("bigmodel.cn") {
+            ThinkingMode::Enabled
+        } else {
+            ThinkingMode::Omit
+        }
+    }
+
+    /// Resolves the effective mode: per-request override, then the
+    /// configured setting, then the provider default.
+    pub fn resolve(
+        request_override: Option<ThinkingMode>,
+        configured: Option<ThinkingMode>,
+        base_url: &str,
+    ) -> Self {
+        request_override
+            .or(configured)
+            .unwrap_or_else(|| Self::default_for(base_url))
+    }
+
+    /// Converts the mode into the request field.
+    pub fn to_config(self) -> Option<ThinkingConfig> {
+        match self {
+            ThinkingMode::Enabled => Some(ThinkingConfig {
+                thinking_type: "enabled".to_string(),
+            }),
+            ThinkingMode::Disabled => Some(ThinkingConfig {
+                thinking_type: "disabled".to_string(),
+            }),
+            ThinkingMode::Omit => None,
+        }
+    }
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            ThinkingMode::Enabled => "Enabled",
+            ThinkingMode::Disabled => "Disabled",
+            ThinkingMode::Omit => "Omit field",
+        }
+    }
+
+    /// Short identifier used in logs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThinkingMode::Enabled => "enabled",
+            ThinkingMode::Disabled => "disabled",
+            ThinkingMode::Omit => "omitted",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 pub struct ChatResponse {
@@ -61,47 +288,255 @@ pub struct Choice {
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
+    #[serde(default)]
     pub total_tokens: u32,
+    /// Breakdown of the completion, only sent by some providers
+    #[serde(default)]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompletionTokensDetails {
+    #[serde(default)]
+    pub reasoning_tokens: Option<u32>,
+}
+
+impl Usage {
+    pub fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            prompt: self.prompt_tokens,
+            completion: self.completion_tokens,
+            reasoning: self
+                .completion_tokens_details
+                .as_ref()
+                .and_then(|details| details.reasoning_tokens),
+        }
+    }
+}
+
+/// What a response says besides its content.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseNote {
+    /// A piece of the model's reasoning, which some providers stream apart
+    /// from the content
+    Reasoning(String),
+    /// Tokens the request used
+    Usage(TokenUsage),
+    /// More than [`MAX_MALFORMED_CHUNKS`] chunks of the response couldn't
+    /// be read and were skipped, so its text may be incomplete
+    MalformedChunks {
+        /// Host of the provider
+        provider: String,
+    },
+    /// The request failed before its response started and is tried again
+    Retrying {
+        /// The attempt about to be made, from 2
+        attempt: u32,
+        max_attempts: u32,
+    },
+}
+
+/// How requests failing before their response started are retried.
+///
+/// Only errors that may well pass are retried, see
+/// [`TranslationError::is_transient`]; a rejected API key fails at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first one included
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for every further one
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_REQUEST_ATTEMPTS,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry`, from 1.
+    ///
+    /// The second half of the wait is random, so clients that failed
+    /// together don't all come back at the same moment.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let full = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(MAX_RETRY_DELAY);
+        let jitter = RandomState::new().hash_one(retry) as f64 / u64::MAX as f64;
+        full / 2 + (full / 2).mul_f64(jitter)
+    }
 }
 
+/// Where the responses of a client send their [`ResponseNote`]s.
+///
+/// A response sends its notes to the receiver opened last before it
+/// started. Responses started while none is open send none.
+#[derive(Debug, Clone, Default)]
+pub struct NoteSlot(Arc<Mutex<Option<UnboundedSender<ResponseNote>>>>);
+
+impl NoteSlot {
+    /// Receives the notes of the responses started from now on.
+    pub fn open(&self) -> UnboundedReceiver<ResponseNote> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        *crate::lock_mutex!(self.0) = Some(tx);
+        rx
+    }
+
+    fn sender(&self) -> Option<UnboundedSender<ResponseNote>> {
+        crate::lock_mutex!(self.0)
+            .clone()
+            .filter(|tx| !tx.is_closed())
+    }
+}
+
+/// One event of a streamed response.
+///
+/// Gateways in front of other providers don't all send the same shape, so
+/// every field may be missing: a usage-only last chunk has no choices, and
+/// a final choice may come without a delta.
 #[derive(Debug, Deserialize)]
 pub struct StreamChunk {
     #[allow(dead_code)]
+    #[serde(default)]
     pub id: String,
-    #[allow(dead_code)]
-    pub object: String,
-    #[allow(dead_code)]
-    pub created: u64,
-    #[allow(dead_code)]
-    pub model: String,
+    #[serde(default)]
     pub choices: Vec<StreamChoice>,
+    /// Sent with the last chunk by providers that report it
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct StreamChoice {
-    #[allow(dead_code)]
-    pub index: u32,
+    /// The new text, sent as `message` by some gateways
+    #[serde(default, alias = "message")]
     pub delta: Delta,
-    #[allow(dead_code)]
+    #[serde(default)]
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// The message that ends a stream, given the last `finish_reason` seen.
+///
+/// A stream that stopped at the token limit is reported as
+/// [`TranslationError::Truncated`], and one stopped by the provider's content
+/// filter as [`TranslationError::ContentFiltered`], instead of the empty
+/// completion chunk.
+fn completion(finish_reason: Option<&str>) -> Result<String> {
+    match finish_reason {
+        Some("length") => {
+            tracing::warn!("Stream stopped at the output token limit");
+            Err(TranslationError::Truncated)
+        }
+        // Z.AI reports its safety filter as "sensitive"
+        Some(reason @ ("content_filter" | "sensitive")) => {
+            tracing::warn!(reason, "Provider declined the content");
+            Err(TranslationError::ContentFiltered(reason.to_string()))
+        }
+        _ => Ok(String::new()),
+    }
+}
+
+/// Reads a whole non-streamed response, returning its content,
+/// `finish_reason` and usage.
+async fn read_response(
+    mut body: ByteStream,
+) -> Result<(String, Option<String>, Option<TokenUsage>)> {
+    use futures_util::StreamExt;
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.next().await {
+        bytes.extend_from_slice(&chunk?);
+    }
+    let response: ChatResponse = serde_json::from_slice(&bytes)?;
+    let usage = response.usage.as_ref().map(Usage::token_usage);
+    let choice = response
+        .choices
+        .into_iter()
+        .next()
+        .ok_or_else(|| TranslationError::ApiError("The response has no choices".to_string()))?;
+    Ok((choice.message.content, choice.finish_reason, usage))
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Delta {
-    #[allow(dead_code)]
-    #[serde(default)]
-    pub role: Option<String>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "text_or_parts")]
     pub content: Option<String>,
+    /// The model's reasoning, kept apart from the content
+    #[serde(default, deserialize_with = "text_or_parts")]
+    pub reasoning_content: Option<String>,
+}
+
+/// Reads a text field sent either as a string or as an array of parts.
+///
+/// The text of the parts is joined, leaving out parts of other types such
+/// as images.
+fn text_or_parts<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Text {
+        Whole(String),
+        Parts(Vec<Part>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Part {
+        Bare(String),
+        Typed {
+            #[serde(rename = "type", default)]
+            kind: Option<String>,
+            #[serde(default)]
+            text: Option<String>,
+        },
+    }
+
+    Ok(match Option::<Text>::deserialize(deserializer)? {
+        None => None,
+        Some(Text::Whole(text)) => Some(text),
+        Some(Text::Parts(parts)) => Some(
+            parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    Part::Bare(text) => Some(text),
+                    Part::Typed { kind, text } => {
+                        matches!(kind.as_deref(), None | Some("text" | "output_text"))
+                            .then_some(text)
+                            .flatten()
+                    }
+                })
+                .collect(),
+        ),
+    })
 }
 
 /// Z.AI API client for streaming chat completions.
 #[derive(Clone)]
 pub struct ApiClient {
-    #[allow(dead_code)]
-    client: Client,
+    transport: Arc<dyn ChatTransport>,
     api_key: String,
     base_url: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    model: String,
+    /// Whether responses are streamed, or read in one piece
+    streaming: bool,
+    /// Capacity of the channels a response is streamed through
+    stream_capacity: usize,
+    /// Decode escape sequences the provider leaves in the content
+    unescape_content: bool,
+    /// Pass the model's reasoning on instead of dropping it
+    keep_reasoning: bool,
+    /// Longest wait for the response to start or for more of it
+    timeout: Duration,
+    retry: RetryPolicy,
+    notes: NoteSlot,
 }
 
 impl ApiClient {
@@ -117,156 +552,479 @@ impl ApiClient {
         }
 
         ApiClient {
-            client: Client::new(),
+            transport: Arc::new(HttpTransport::new(DEFAULT_BASE_URL, &api_key)),
             api_key,
-            base_url: "https://api.z.ai/api/coding/paas/v4".to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            max_tokens: None,
+            temperature: None,
+            model: DEFAULT_MODEL.to_string(),
+            streaming: true,
+            stream_capacity: STREAM_CHANNEL_CAPACITY,
+            unescape_content: false,
+            keep_reasoning: true,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            retry: RetryPolicy::default(),
+            notes: NoteSlot::default(),
         }
     }
 
+    /// Sends requests to the OpenAI-compatible endpoint under `base_url`
+    /// instead of [`DEFAULT_BASE_URL`]; trailing slashes are ignored.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        self.transport = Arc::new(HttpTransport::new(base_url, &self.api_key));
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    /// Sends requests through `transport` instead of HTTP.
+    #[cfg(test)]
+    pub fn with_transport(mut self, transport: Arc<dyn ChatTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Limits the length of responses, `None` for the provider default.
+    pub fn with_max_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Samples responses at `temperature`, `None` for the provider default.
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Sends requests to `model` instead of [`DEFAULT_MODEL`].
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// The model requests are sent to.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Asks for whole responses in one round trip instead of streams when
+    /// `streaming` is false.
+    ///
+    /// [`Self::stream_chat`] still returns a channel; it yields the whole
+    /// content as a single chunk.
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// Decodes escape sequences such as a literal `\n` in the content of
+    /// responses, for providers that send them encoded.
+    pub fn with_unescape_content(mut self, unescape: bool) -> Self {
+        self.unescape_content = unescape;
+        self
+    }
+
+    /// Drops the model's reasoning as soon as it is read when `keep` is
+    /// false, instead of sending it as a [`ResponseNote::Reasoning`].
+    pub fn with_reasoning(mut self, keep: bool) -> Self {
+        self.keep_reasoning = keep;
+        self
+    }
+
+    /// Fails requests the provider doesn't start answering within
+    /// `timeout`, and responses it then sends nothing more of for as long.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries requests that fail before their response started as
+    /// `retry` allows.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Where responses send their reasoning and usage.
+    pub fn notes(&self) -> &NoteSlot {
+        &self.notes
+    }
+
+    /// Streams responses through channels of `capacity` chunks instead of
+    /// [`STREAM_CHANNEL_CAPACITY`].
+    ///
+    /// Cached results are sent without waiting, so the capacity must leave
+    /// room for a translation, its keyword analysis and the completion signal.
+    #[cfg(test)]
+    pub fn with_stream_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity >= 3, "stream capacity {} is too small", capacity);
+        self.stream_capacity = capacity;
+        self
+    }
+
+    /// Capacity of the channels a response is streamed through.
+    pub fn stream_capacity(&self) -> usize {
+        self.stream_capacity
+    }
+
     /// Streams chat completion responses from the API.
     ///
     /// # Arguments
     ///
     /// * `messages` - List of chat messages to send to the API
+    /// * `thinking` - How the `thinking` field is sent
     ///
     /// # Returns
     ///
-    /// A receiver channel that yields streaming chunks of the response
+    /// A stream of the response's chunks, which can be stopped with
+    /// [`ChatStream::abort`]. While it is full, the response body is not
+    /// read any further.
     pub async fn stream_chat(
         &self,
         messages: Vec<ChatMessage>,
-    ) -> tokio::sync::mpsc::UnboundedReceiver<Result<String>> {
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        thinking: ThinkingMode,
+    ) -> ChatStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.stream_capacity);
 
         let request = ChatRequest {
-            model: "glm-4.7".to_string(),
+            model: self.model.clone(),
             messages,
-            stream: true,
-            thinking: Some(ThinkingConfig {
-                thinking_type: "enabled".to_string(),
-            }),
+            stream: self.streaming,
+            thinking: thinking.to_config(),
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
         };
 
         let url = format!("{}/chat/completions", self.base_url);
-        let api_key = self.api_key.clone();
-
-        tracing::info!("Starting streaming chat request to: {}", url);
-
-        tokio::spawn(async move {
-            let client = Client::new();
-            match client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", api_key))
-                .header("Content-Type", "application/json")
-                .json(&request)
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    let status = response.status();
-                    tracing::debug!("Received response with status: {}", status);
-
-                    if !status.is_success() {
-                        tracing::error!("API returned error status: {}", status);
-                        let _ = tx.send(Err(TranslationError::ApiError(format!(
-                            "API error: {}",
-                            status
-                        ))));
-                        return;
-                    }
+        let transport = self.transport.clone();
+        let provider = provider_name(&self.base_url);
+        let notes = self.notes.sender();
+        let keep_reasoning = self.keep_reasoning;
+        let timeout = self.timeout;
+        let retry = self.retry;
+        let cancel = CancellationToken::new();
 
-                    let mut stream = response.bytes_stream();
-                    let mut buffer = Vec::new();
-
-                    use futures_util::StreamExt;
-
-                    while let Some(chunk_result) = stream.next().await {
-                        match chunk_result {
-                            Ok(chunk) => {
-                                buffer.extend_from_slice(&chunk);
-
-                                // Convert buffer to string and split by lines
-                                let data = String::from_utf8_lossy(&buffer);
-                                let lines: Vec<&str> = data.lines().collect();
-
-                                // Process all lines except the last one (might be incomplete)
-                                for (i, line) in lines.iter().enumerate() {
-                                    // Skip the last line as it might be incomplete
-                                    if i == lines.len() - 1 {
-                                        continue;
-                                    }
-
-                                    let line = line.trim();
-                                    if line.is_empty() {
-                                        continue;
-                                    }
-
-                                    // Check for stream completion marker
-                                    if line == "data: [DONE]" {
-                                        tracing::debug!("Stream completed");
-                                        let _ = tx.send(Ok(String::new()));
-                                        return;
-                                    }
-
-                                    // Try to extract translation content from JSON
-                                    if let Some(json_str) = line.strip_prefix("data: ") {
-                                        match serde_json::from_str::<StreamChunk>(json_str) {
-                                            Ok(parsed_chunk) => {
-                                                if let Some(choice) = parsed_chunk.choices.first()
-                                                    && let Some(content) = &choice.delta.content
-                                                {
-                                                    tracing::trace!(
-                                                        "Sending translation: {} bytes",
-                                                        content.len()
-                                                    );
-                                                    let _ = tx.send(Ok(content.clone()));
-                                                }
-                                            }
-                                            Err(_) => {
-                                                // Skip incomplete or invalid JSON
-                                                continue;
-                                            }
-                                        }
-                                    }
-                                }
-
-                                // Keep only the last incomplete line in buffer
-                                if let Some(last_line_start) = data.rfind('\n') {
-                                    if last_line_start > 0 && last_line_start < buffer.len() {
-                                        let remaining_len = data[last_line_start + 1..].len();
-                                        buffer = buffer.split_off(buffer.len() - remaining_len);
-                                    } else {
-                                        buffer.clear();
-                                    }
-                                }
-                            }
+        tracing::info!(
+            thinking = thinking.as_str(),
+            model = %self.model,
+            streaming = self.streaming,
+            "Starting chat request to: {}",
+            url
+        );
+
+        tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                        // The response is dropped with the future reading it
+                        tracing::info!("Chat request cancelled");
+                        let _ = tx.send(Err(TranslationError::Cancelled)).await;
+                    }
+                    _ = async {
+                        match open(&*transport, &request, timeout, retry, &provider, notes.as_ref()).await {
+                            Ok(body) => respond(body, provider, request.stream, notes, keep_reasoning, tx.clone()).await,
                             Err(e) => {
-                                tracing::error!("Stream error: {}", e);
-                                let _ = tx.send(Err(TranslationError::StreamError(format!(
-                                    "Stream error: {}",
-                                    e
-                                ))));
-                                return;
+                                let _ = tx.send(Err(e)).await;
                             }
                         }
+                    } => {}
+                }
+            }
+        });
+
+        let rx = if self.unescape_content {
+            unescape_stream(rx, self.stream_capacity)
+        } else {
+            rx
+        };
+        ChatStream { rx, cancel }
+    }
+}
+
+/// A chat response streaming in.
+///
+/// Dropping it stops the request, like [`ChatStream::abort`].
+#[derive(Debug)]
+pub struct ChatStream {
+    rx: tokio::sync::mpsc::Receiver<Result<String>>,
+    cancel: CancellationToken,
+}
+
+impl ChatStream {
+    /// The next chunk of the response.
+    ///
+    /// The response ends with an empty chunk when it is complete, or with
+    /// an error; [`TranslationError::Cancelled`] once it was aborted.
+    pub async fn recv(&mut self) -> Option<Result<String>> {
+        self.rx.recv().await
+    }
+
+    /// Stops the request at once: the response body isn't read any further
+    /// and the connection is dropped.
+    pub fn abort(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Token stopping the request when cancelled, e.g. from another task.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for ChatStream {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// Ends `body` with an error once nothing arrived for `timeout`.
+fn stall_guarded(body: ByteStream, timeout: Duration) -> ByteStream {
+    use futures_util::StreamExt;
+
+    futures_util::stream::unfold(Some(body), move |body| async move {
+        let mut body = body?;
+        match tokio::time::timeout(timeout, body.next()).await {
+            Ok(Some(chunk)) => Some((chunk, Some(body))),
+            Ok(None) => None,
+            Err(_) => {
+                tracing::warn!(?timeout, "The response stalled");
+                let stalled =
+                    TranslationError::StreamError("timed out waiting for data".to_string());
+                Some((Err(stalled), None))
+            }
+        }
+    })
+    .boxed()
+}
+
+/// Sends `request` through `transport` until its response starts, retrying
+/// as `retry` allows with a note of every retry.
+async fn open(
+    transport: &dyn ChatTransport,
+    request: &ChatRequest,
+    timeout: Duration,
+    retry: RetryPolicy,
+    provider: &str,
+    notes: Option<&UnboundedSender<ResponseNote>>,
+) -> Result<ByteStream> {
+    let mut attempt = 1;
+    loop {
+        let error = match tokio::time::timeout(timeout, transport.send(request)).await {
+            Ok(Ok(body)) => return Ok(stall_guarded(body, timeout)),
+            Ok(Err(e)) => e,
+            Err(_) => {
+                tracing::warn!(provider, ?timeout, "No response from the provider");
+                return Err(TranslationError::StreamError(
+                    "timed out waiting for a response".to_string(),
+                ));
+            }
+        };
+        if attempt >= retry.max_attempts || !error.is_transient() {
+            return Err(error);
+        }
+        let delay = retry.delay(attempt);
+        attempt += 1;
+        tracing::warn!(provider, error = %error, attempt, ?delay, "Retrying the request");
+        if let Some(notes) = notes {
+            let _ = notes.send(ResponseNote::Retrying {
+                attempt,
+                max_attempts: retry.max_attempts,
+            });
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Passes the response `stream` on to `tx`, read as a stream of events if
+/// `streaming`.
+async fn respond(
+    mut stream: ByteStream,
+    provider: String,
+    streaming: bool,
+    notes: Option<UnboundedSender<ResponseNote>>,
+    keep_reasoning: bool,
+    tx: tokio::sync::mpsc::Sender<Result<String>>,
+) {
+    if !streaming {
+        match read_response(stream).await {
+            Ok((content, finish_reason, usage)) => {
+                if let Some(notes) = &notes
+                    && let Some(usage) = usage
+                {
+                    let _ = notes.send(ResponseNote::Usage(usage));
+                }
+                if !content.is_empty() && tx.send(Ok(content)).await.is_err() {
+                    return;
+                }
+                let _ = tx.send(completion(finish_reason.as_deref())).await;
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+            }
+        }
+        return;
+    }
+    let mut lines = SseLines::default();
+    let mut finish_reason: Option<String> = None;
+    let mut malformed = 0;
+    let mut body_ended = false;
+
+    use futures_util::StreamExt;
+
+    loop {
+        let line = match lines.next_line() {
+            Some(line) => line,
+            // A last line without a line break
+            None if body_ended => match lines.rest() {
+                Some(line) => line,
+                None => break,
+            },
+            None => {
+                match stream.next().await {
+                    Some(Ok(bytes)) => lines.feed(&bytes),
+                    Some(Err(e)) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
                     }
-                    tracing::debug!("Stream ended naturally");
-                    let _ = tx.send(Ok(String::new()));
+                    None => body_ended = true,
                 }
-                Err(e) => {
-                    tracing::error!("Request error: {}", e);
-                    let _ = tx.send(Err(TranslationError::NetworkError(e)));
+                continue;
+            }
+        };
+
+        let Some(data) = line.trim().strip_prefix("data:") else {
+            continue;
+        };
+        let data = data.trim_start();
+
+        // Check for stream completion marker
+        if data == "[DONE]" {
+            tracing::debug!("Stream completed");
+            let _ = tx.send(completion(finish_reason.as_deref())).await;
+            return;
+        }
+
+        let parsed_chunk = match serde_json::from_str::<StreamChunk>(data) {
+            Ok(parsed_chunk) => parsed_chunk,
+            Err(e) => {
+                malformed += 1;
+                tracing::warn!(provider, error = %e, "Skipping a response chunk that can't be read");
+                tracing::trace!("Unreadable chunk: {}", data);
+                if malformed == MAX_MALFORMED_CHUNKS + 1
+                    && let Some(notes) = &notes
+                {
+                    let _ = notes.send(ResponseNote::MalformedChunks {
+                        provider: provider.clone(),
+                    });
                 }
+                continue;
             }
-        });
+        };
+        if let Some(choice) = parsed_chunk.choices.first()
+            && let Some(reason) = &choice.finish_reason
+        {
+            finish_reason = Some(reason.clone());
+        }
+        if let Some(notes) = &notes {
+            if keep_reasoning
+                && let Some(choice) = parsed_chunk.choices.first()
+                && let Some(reasoning) = &choice.delta.reasoning_content
+                && !reasoning.is_empty()
+            {
+                let _ = notes.send(ResponseNote::Reasoning(reasoning.clone()));
+            }
+            if let Some(usage) = &parsed_chunk.usage {
+                let _ = notes.send(ResponseNote::Usage(usage.token_usage()));
+            }
+        }
+        if let Some(choice) = parsed_chunk.choices.first()
+            && let Some(content) = &choice.delta.content
+            && !content.is_empty()
+        {
+            tracing::trace!("Sending translation: {} bytes", content.len());
+            if tx.send(Ok(content.clone())).await.is_err() {
+                tracing::debug!("Stream receiver dropped");
+                return;
+            }
+        }
+    }
+    tracing::debug!("Stream ended naturally");
+    let _ = tx.send(completion(finish_reason.as_deref())).await;
+}
 
-        rx
+/// Splits the bytes of a response body into lines.
+///
+/// Lines are only decoded once complete, so a character split across
+/// chunks of the body arrives whole.
+#[derive(Debug, Default)]
+struct SseLines {
+    buffer: Vec<u8>,
+}
+
+impl SseLines {
+    fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
     }
+
+    /// The next complete line, without its line break.
+    fn next_line(&mut self) -> Option<String> {
+        let end = self.buffer.iter().position(|&byte| byte == b'\n')?;
+        let line: Vec<u8> = self.buffer.drain(..=end).collect();
+        Some(String::from_utf8_lossy(&line[..end]).into_owned())
+    }
+
+    /// What is left after the last line break, once the body ended.
+    fn rest(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let rest = std::mem::take(&mut self.buffer);
+        Some(String::from_utf8_lossy(&rest).into_owned())
+    }
+}
+
+/// Passes the chunks of `raw` on with their escape sequences decoded.
+///
+/// An escape split across chunks is completed before it is passed on, and
+/// dropping the returned receiver closes `raw` as well.
+fn unescape_stream(
+    mut raw: tokio::sync::mpsc::Receiver<Result<String>>,
+    capacity: usize,
+) -> tokio::sync::mpsc::Receiver<Result<String>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+    tokio::spawn(async move {
+        let mut filters = FilterChain::default().with(UnescapeFilter::default());
+        while let Some(result) = raw.recv().await {
+            match result {
+                Ok(chunk) if !chunk.is_empty() => {
+                    if let Some(text) = filters.process(&chunk)
+                        && tx.send(Ok(text)).await.is_err()
+                    {
+                        return;
+                    }
+                }
+                result => {
+                    // Completion or error, release a held back escape first
+                    if let Some(text) = filters.finish()
+                        && tx.send(Ok(text)).await.is_err()
+                    {
+                        return;
+                    }
+                    if tx.send(result).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    rx
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn test_api_client_creation() {
@@ -275,14 +1033,55 @@ mod tests {
         assert!(client.base_url.contains("api.z.ai"));
     }
 
+    #[test]
+    fn test_base_url_is_normalized() {
+        assert_eq!(normalize_base_url("").unwrap(), DEFAULT_BASE_URL);
+        assert_eq!(normalize_base_url("  ").unwrap(), DEFAULT_BASE_URL);
+        assert_eq!(
+            normalize_base_url(" https://api.example.com/v1/ ").unwrap(),
+            "https://api.example.com/v1"
+        );
+        assert_eq!(
+            normalize_base_url("http://localhost:8080//").unwrap(),
+            "http://localhost:8080"
+        );
+        assert!(matches!(
+            normalize_base_url("api.example.com/v1"),
+            Err(TranslationError::ConfigError(_))
+        ));
+        assert!(matches!(
+            normalize_base_url("ftp://example.com"),
+            Err(TranslationError::ConfigError(_))
+        ));
+
+        let client = ApiClient::new("test_key".to_string()).with_base_url("http://localhost/v1/");
+        assert_eq!(client.base_url, "http://localhost/v1");
+    }
+
+    #[test]
+    fn test_role_wire_format() {
+        for (role, name) in [
+            (Role::System, "system"),
+            (Role::User, "user"),
+            (Role::Assistant, "assistant"),
+        ] {
+            let json = serde_json::to_string(&role).unwrap();
+            assert_eq!(json, format!("\"{}\"", name));
+            assert_eq!(serde_json::from_str::<Role>(&json).unwrap(), role);
+        }
+        assert!(serde_json::from_str::<Role>("\"User\"").is_err());
+        assert!(serde_json::from_str::<Role>("\"tool\"").is_err());
+    }
+
     #[test]
     fn test_chat_message_serialization() {
         let msg = ChatMessage {
-            role: "user".to_string(),
+            role: Role::User,
             content: "Hello".to_string(),
         };
 
         let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(json, r#"{"role":"user","content":"Hello"}"#);
         let deserialized: ChatMessage = serde_json::from_str(&json).unwrap();
 
         assert_eq!(msg.role, deserialized.role);
@@ -294,19 +1093,97 @@ mod tests {
         let request = ChatRequest {
             model: "glm-4.7".to_string(),
             messages: vec![ChatMessage {
-                role: "user".to_string(),
+                role: Role::User,
                 content: "test".to_string(),
             }],
             stream: true,
             thinking: Some(ThinkingConfig {
                 thinking_type: "enabled".to_string(),
             }),
+            max_tokens: None,
+            temperature: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("glm-4.7"));
         assert!(json.contains("user"));
         assert!(json.contains("test"));
+        assert!(!json.contains("max_tokens"));
+        assert!(!json.contains("temperature"));
+    }
+
+    #[test]
+    fn test_max_tokens_serialization() {
+        let request = ChatRequest {
+            model: "glm-4.7".to_string(),
+            messages: Vec::new(),
+            stream: true,
+            thinking: None,
+            max_tokens: Some(8192),
+            temperature: Some(0.5),
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["max_tokens"], 8192);
+        assert_eq!(json["temperature"], 0.5);
+    }
+
+    fn request_with_thinking(mode: ThinkingMode) -> serde_json::Value {
+        let request = ChatRequest {
+            model: "glm-4.7".to_string(),
+            messages: Vec::new(),
+            stream: true,
+            thinking: mode.to_config(),
+            max_tokens: None,
+            temperature: None,
+        };
+        serde_json::to_value(&request).unwrap()
+    }
+
+    #[test]
+    fn test_thinking_enabled_serialization() {
+        let json = request_with_thinking(ThinkingMode::Enabled);
+        assert_eq!(json["thinking"]["type"], "enabled");
+    }
+
+    #[test]
+    fn test_thinking_disabled_serialization() {
+        let json = request_with_thinking(ThinkingMode::Disabled);
+        assert_eq!(json["thinking"]["type"], "disabled");
+    }
+
+    #[test]
+    fn test_thinking_omit_serialization() {
+        let json = request_with_thinking(ThinkingMode::Omit);
+        assert!(json.get("thinking").is_none());
+        assert!(!serde_json::to_string(&json).unwrap().contains("thinking"));
+    }
+
+    #[test]
+    fn test_thinking_mode_resolution() {
+        assert_eq!(
+            ThinkingMode::default_for(DEFAULT_BASE_URL),
+            ThinkingMode::Enabled
+        );
+        assert_eq!(
+            ThinkingMode::default_for("http://localhost:8080/v1"),
+            ThinkingMode::Omit
+        );
+        assert_eq!(
+            ThinkingMode::resolve(
+                Some(ThinkingMode::Disabled),
+                Some(ThinkingMode::Enabled),
+                DEFAULT_BASE_URL
+            ),
+            ThinkingMode::Disabled
+        );
+        assert_eq!(
+            ThinkingMode::resolve(None, Some(ThinkingMode::Omit), DEFAULT_BASE_URL),
+            ThinkingMode::Omit
+        );
+        assert_eq!(
+            ThinkingMode::resolve(None, None, "https://example.com/v1"),
+            ThinkingMode::Omit
+        );
     }
 
     #[test]
@@ -330,4 +1207,325 @@ mod tests {
         assert_eq!(chunk.choices.len(), 1);
         assert_eq!(chunk.choices[0].delta.content, Some("Hello".to_string()));
     }
+
+    #[test]
+    fn test_finish_reason_length_is_truncation() {
+        let json = r#"{
+            "id": "test",
+            "object": "chat.completion.chunk",
+            "created": 1234567890,
+            "model": "glm-4.7",
+            "choices": [{"index": 0, "delta": {}, "finish_reason": "length"}]
+        }"#;
+        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
+        let reason = chunk.choices[0].finish_reason.as_deref();
+        assert_eq!(reason, Some("length"));
+
+        assert!(matches!(
+            completion(reason),
+            Err(TranslationError::Truncated)
+        ));
+        assert_eq!(completion(Some("stop")).unwrap(), "");
+        assert_eq!(completion(None).unwrap(), "");
+    }
+
+    #[test]
+    fn test_content_filter_is_reported() {
+        for reason in ["content_filter", "sensitive"] {
+            assert!(matches!(
+                completion(Some(reason)),
+                Err(TranslationError::ContentFiltered(r)) if r == reason
+            ));
+        }
+    }
+
+    #[test]
+    fn test_variant_chunk_shapes() {
+        // Chunk, content read from it and finish reason
+        let corpus = [
+            (
+                r#"{"choices":[{"delta":{"content":"Hallo"}}]}"#,
+                Some("Hallo"),
+                None,
+            ),
+            (
+                r#"{"choices":[{"message":{"content":"Hallo"}}]}"#,
+                Some("Hallo"),
+                None,
+            ),
+            (
+                r#"{"choices":[{"delta":{"content":[{"type":"text","text":"Hal"},{"type":"text","text":"lo"}]}}]}"#,
+                Some("Hallo"),
+                None,
+            ),
+            (
+                r#"{"choices":[{"delta":{"content":[{"type":"image_url","image_url":{"url":"x"}},{"type":"output_text","text":"Hallo"}]}}]}"#,
+                Some("Hallo"),
+                None,
+            ),
+            (
+                r#"{"choices":[{"delta":{"content":["Hal",{"text":"lo"}]}}]}"#,
+                Some("Hallo"),
+                None,
+            ),
+            (r#"{"choices":[{"delta":{"content":null}}]}"#, None, None),
+            (
+                r#"{"choices":[{"finish_reason":"stop"}]}"#,
+                None,
+                Some("stop"),
+            ),
+            (
+                r#"{"choices":[],"usage":{"prompt_tokens":1,"completion_tokens":2}}"#,
+                None,
+                None,
+            ),
+            (
+                r#"{"object":"chat.completion.chunk","system_fingerprint":"fp"}"#,
+                None,
+                None,
+            ),
+        ];
+        for (json, content, finish_reason) in corpus {
+            let chunk: StreamChunk = serde_json::from_str(json).expect(json);
+            let choice = chunk.choices.first();
+            assert_eq!(
+                choice.and_then(|choice| choice.delta.content.as_deref()),
+                content,
+                "{}",
+                json
+            );
+            assert_eq!(
+                choice.and_then(|choice| choice.finish_reason.as_deref()),
+                finish_reason,
+                "{}",
+                json
+            );
+        }
+
+        for json in [
+            r#"{"choices":[{"delta":{"content":5}}]}"#,
+            r#"{"choices":"none"}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<StreamChunk>(json).is_err(),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_lines_are_split_as_bytes() {
+        let mut lines = SseLines::default();
+        // A line break right at the end of a chunk, and a character split
+        // across chunks
+        lines.feed(b"data: a\n");
+        assert_eq!(lines.next_line().as_deref(), Some("data: a"));
+        let text = "data: ü\n".as_bytes();
+        lines.feed(&text[..7]);
+        assert_eq!(lines.next_line(), None);
+        lines.feed(&text[7..]);
+        lines.feed(b"data: [DONE]");
+        assert_eq!(lines.next_line().as_deref(), Some("data: ü"));
+        assert_eq!(lines.next_line(), None);
+        assert_eq!(lines.rest().as_deref(), Some("data: [DONE]"));
+        assert_eq!(lines.rest(), None);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_malformed_chunks_are_noted() {
+        let mut script = vec![transport::ScriptStep::Bytes(transport::sse_delta(
+            Some("Hallo"),
+            None,
+        ))];
+        for _ in 0..=MAX_MALFORMED_CHUNKS {
+            script.push(transport::ScriptStep::Bytes(b"data: {broken\n\n".to_vec()));
+        }
+        script.push(transport::ScriptStep::Bytes(b"data: [DONE]\n\n".to_vec()));
+        let client = ApiClient::new("test_key".to_string())
+            .with_transport(Arc::new(transport::ScriptedTransport::new(script)));
+        let mut notes = client.notes().open();
+
+        let (content, end) = receive(&client).await;
+        assert_eq!(content, "Hallo");
+        assert_eq!(end.unwrap(), "");
+        assert_eq!(
+            notes.try_recv().unwrap(),
+            ResponseNote::MalformedChunks {
+                provider: "api.z.ai".to_string()
+            }
+        );
+        assert!(notes.try_recv().is_err());
+    }
+
+    /// Content and end of a response to `client`.
+    async fn receive(client: &ApiClient) -> (String, Result<String>) {
+        let mut rx = client.stream_chat(Vec::new(), ThinkingMode::Disabled).await;
+        let mut content = String::new();
+        loop {
+            match rx.recv().await.expect("a completion signal") {
+                Ok(chunk) if !chunk.is_empty() => content.push_str(&chunk),
+                end => return (content, end),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_is_unescaped_when_enabled() {
+        let transport = Arc::new(transport::ScriptedTransport::with_chunks(
+            &[r"Zeile\", r"nZwei \u{e", r"9} C:\Temp\"],
+            "length",
+        ));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport.clone());
+
+        let (content, end) = receive(&client.clone().with_unescape_content(true)).await;
+        // The backslash ending the response is released before the end
+        assert_eq!(content, "Zeile\nZwei é C:\\Temp\\");
+        assert!(matches!(end, Err(TranslationError::Truncated)));
+
+        let (content, _) = receive(&client).await;
+        assert_eq!(content, r"Zeile\nZwei \u{e9} C:\Temp\");
+    }
+
+    #[tokio::test]
+    async fn test_abort_ends_a_stalled_response() {
+        let transport = Arc::new(transport::ScriptedTransport::new(vec![
+            transport::ScriptStep::Bytes(transport::sse_delta(Some("Hal"), None)),
+            transport::ScriptStep::Stall,
+        ]));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport);
+
+        let mut stream = client.stream_chat(Vec::new(), ThinkingMode::Disabled).await;
+        assert_eq!(stream.recv().await.unwrap().unwrap(), "Hal");
+        stream.abort();
+        let end = tokio::time::timeout(Duration::from_secs(5), stream.recv())
+            .await
+            .expect("the abort was not noticed");
+        assert!(matches!(end, Some(Err(TranslationError::Cancelled))));
+        // The task ended, closing the channel
+        assert!(stream.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_response_times_out() {
+        let transport = Arc::new(transport::ScriptedTransport::new(vec![
+            transport::ScriptStep::Bytes(transport::sse_delta(Some("Hallo"), None)),
+            transport::ScriptStep::Stall,
+        ]));
+        let client = ApiClient::new("test_key".to_string())
+            .with_transport(transport)
+            .with_timeout(Duration::from_millis(100));
+
+        let (content, end) = receive(&client).await;
+        assert_eq!(content, "Hallo");
+        assert!(
+            matches!(end, Err(TranslationError::StreamError(ref m)) if m == "timed out waiting for data")
+        );
+    }
+
+    /// Retries after a millisecond or two.
+    fn quick_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let transport = Arc::new(
+            transport::ScriptedTransport::with_chunks(&["Hallo"], "stop")
+                .rejecting_first(&[503, 429]),
+        );
+        let client = ApiClient::new("test_key".to_string())
+            .with_transport(transport.clone())
+            .with_retry(quick_retry(3));
+        let mut notes = client.notes().open();
+
+        let (content, end) = receive(&client).await;
+        assert_eq!(content, "Hallo");
+        assert_eq!(end.unwrap(), "");
+        assert_eq!(transport.requests().len(), 3);
+        for attempt in [2, 3] {
+            assert_eq!(
+                notes.try_recv().unwrap(),
+                ResponseNote::Retrying {
+                    attempt,
+                    max_attempts: 3
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_give_up() {
+        let transport = Arc::new(
+            transport::ScriptedTransport::with_chunks(&["Hallo"], "stop")
+                .rejecting_first(&[502, 502, 502]),
+        );
+        let client = ApiClient::new("test_key".to_string())
+            .with_transport(transport.clone())
+            .with_retry(quick_retry(3));
+
+        let (content, end) = receive(&client).await;
+        assert_eq!(content, "");
+        assert!(
+            matches!(end, Err(TranslationError::HttpStatus(status)) if status == reqwest::StatusCode::BAD_GATEWAY)
+        );
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_is_not_retried() {
+        let transport = Arc::new(
+            transport::ScriptedTransport::with_chunks(&["Hallo"], "stop").rejecting_first(&[401]),
+        );
+        let client = ApiClient::new("test_key".to_string())
+            .with_transport(transport.clone())
+            .with_retry(quick_retry(3));
+        let mut notes = client.notes().open();
+
+        let (_, end) = receive(&client).await;
+        assert!(matches!(end, Err(TranslationError::HttpStatus(status)) if status.as_u16() == 401));
+        assert_eq!(transport.requests().len(), 1);
+        assert!(notes.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_with_jitter() {
+        let retry = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_secs(2),
+        };
+        for (n, full) in [(1, 2), (2, 4), (3, 8), (9, 30)] {
+            let delay = retry.delay(n);
+            let full = Duration::from_secs(full);
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "retry {}: {:?}",
+                n,
+                delay
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_stops_reading_the_response() {
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let transport = Arc::new(transport::ScriptedTransport::new(vec![
+            transport::ScriptStep::Bytes(transport::sse_delta(Some("Hal"), None)),
+            transport::ScriptStep::Gate(gate.clone()),
+            transport::ScriptStep::Bytes(transport::sse_delta(Some("lo"), None)),
+            transport::ScriptStep::Bytes(b"data: [DONE]\n\n".to_vec()),
+        ]));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport.clone());
+
+        let mut stream = client.stream_chat(Vec::new(), ThinkingMode::Disabled).await;
+        assert_eq!(stream.recv().await.unwrap().unwrap(), "Hal");
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        gate.notify_one();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(transport.delivered(), 1);
+    }
 }
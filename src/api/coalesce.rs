@@ -0,0 +1,255 @@
+//! One response shared by identical requests made at the same time.
+//!
+//! Every text translation goes through `Translator::translate`: the
+//! translation of each tab, queued translations run from the offline queue,
+//! retried list items and both stages of a pivot translation. Two of them
+//! can ask for the same translation while it is still streaming, e.g. the
+//! same text translated in two tabs. Only the first of them is sent; the
+//! others follow its response instead, receiving the chunks that arrived so
+//! far first and then the rest as it comes, so each gets the whole text. The
+//! response is cached once, by the request that was sent.
+//!
+//! Batches of structured values are sent by `Translator::translate_values`,
+//! which doesn't coalesce.
+//!
+//! The request that was sent streams to its own receiver as before and
+//! passes every chunk on through its [`Followers`]. Followers never hold the
+//! response up: their chunks queue without a limit, which costs no more than
+//! the received text kept for late followers anyway.
+
+use crate::error::Result;
+use crate::lock_mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, Receiver, UnboundedSender};
+
+/// Responses in flight, by the cache key of their request.
+///
+/// Clones share the responses, so translators made for different sessions
+/// coalesce with each other when given clones of one coalescer.
+#[derive(Debug, Clone, Default)]
+pub struct Coalescer {
+    in_flight: Arc<Mutex<HashMap<String, Arc<Mutex<Broadcast>>>>>,
+}
+
+/// Outcome of [`Coalescer::join`].
+#[derive(Debug)]
+pub enum Joined {
+    /// Nothing was in flight; the caller sends the request and passes its
+    /// response on
+    Leader(Followers),
+    /// The response of the same request in flight
+    Follower(Receiver<Result<String>>),
+}
+
+/// A response streaming to its followers.
+#[derive(Debug, Default)]
+struct Broadcast {
+    /// Chunks received so far, for followers joining late
+    received: Vec<String>,
+    followers: Vec<UnboundedSender<Result<String>>>,
+}
+
+impl Broadcast {
+    /// A receiver of the response from its start, with room for
+    /// `capacity` chunks.
+    fn subscribe(&mut self, capacity: usize) -> Receiver<Result<String>> {
+        let (queue_tx, mut queue) = mpsc::unbounded_channel();
+        for chunk in &self.received {
+            let _ = queue_tx.send(Ok(chunk.clone()));
+        }
+        self.followers.push(queue_tx);
+
+        let (tx, rx) = mpsc::channel(capacity);
+        tokio::spawn(async move {
+            while let Some(item) = queue.recv().await {
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+}
+
+impl Coalescer {
+    /// Joins the response to the request `key` if one is in flight, or
+    /// registers the caller as the one sending it.
+    ///
+    /// A follower's receiver has room for `capacity` chunks.
+    pub fn join(&self, key: &str, capacity: usize) -> Joined {
+        let mut in_flight = lock_mutex!(self.in_flight);
+        if let Some(broadcast) = in_flight.get(key) {
+            tracing::info!("Following the response of the same request in flight");
+            return Joined::Follower(lock_mutex!(broadcast).subscribe(capacity));
+        }
+        let broadcast = Arc::new(Mutex::new(Broadcast::default()));
+        in_flight.insert(key.to_string(), broadcast.clone());
+        Joined::Leader(Followers {
+            shared: Some(Shared {
+                coalescer: self.clone(),
+                key: key.to_string(),
+                broadcast,
+            }),
+        })
+    }
+
+    /// Number of responses in flight.
+    pub fn len(&self) -> usize {
+        lock_mutex!(self.in_flight).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The requests following a response, held by the one that was sent.
+///
+/// Dropped before the response ended, it lets nobody join anymore and
+/// closes the followers' receivers.
+#[derive(Debug, Default)]
+pub struct Followers {
+    /// `None` for a response nobody can join
+    shared: Option<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    coalescer: Coalescer,
+    key: String,
+    broadcast: Arc<Mutex<Broadcast>>,
+}
+
+impl Shared {
+    fn unlist(&self) {
+        lock_mutex!(self.coalescer.in_flight).remove(&self.key);
+    }
+}
+
+impl Followers {
+    /// Followers of a response nobody can join.
+    pub fn none() -> Self {
+        Followers::default()
+    }
+
+    /// Whether another request follows the response.
+    pub fn is_followed(&self) -> bool {
+        self.shared
+            .as_ref()
+            .is_some_and(|shared| !lock_mutex!(shared.broadcast).followers.is_empty())
+    }
+
+    /// Passes an item of the response on: a chunk, or the completion
+    /// signal or error that ends it.
+    pub fn pass(&mut self, item: &Result<String>) {
+        match item {
+            Ok(chunk) if !chunk.is_empty() => {
+                let Some(shared) = &self.shared else {
+                    return;
+                };
+                let mut broadcast = lock_mutex!(shared.broadcast);
+                broadcast
+                    .followers
+                    .retain(|follower| follower.send(Ok(chunk.clone())).is_ok());
+                broadcast.received.push(chunk.clone());
+            }
+            end => {
+                let Some(shared) = self.shared.take() else {
+                    return;
+                };
+                // Unlisted first, so nobody joins a finished response
+                shared.unlist();
+                let followers = std::mem::take(&mut lock_mutex!(shared.broadcast).followers);
+                for follower in followers {
+                    let copy = match end {
+                        Ok(end) => Ok(end.clone()),
+                        Err(e) => Err(e.replicate()),
+                    };
+                    let _ = follower.send(copy);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Followers {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            shared.unlist();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TranslationError;
+
+    fn leader(joined: Joined) -> Followers {
+        match joined {
+            Joined::Leader(followers) => followers,
+            Joined::Follower(_) => panic!("joined a response in flight"),
+        }
+    }
+
+    fn follower(joined: Joined) -> Receiver<Result<String>> {
+        match joined {
+            Joined::Follower(rx) => rx,
+            Joined::Leader(_) => panic!("nothing to join"),
+        }
+    }
+
+    async fn collect(mut rx: Receiver<Result<String>>) -> (String, Option<Result<String>>) {
+        let mut text = String::new();
+        while let Some(item) = rx.recv().await {
+            match item {
+                Ok(chunk) if !chunk.is_empty() => text.push_str(&chunk),
+                end => return (text, Some(end)),
+            }
+        }
+        (text, None)
+    }
+
+    #[tokio::test]
+    async fn test_late_follower_gets_the_prefix() {
+        let coalescer = Coalescer::default();
+        let mut followers = leader(coalescer.join("key", 8));
+        let early = follower(coalescer.join("key", 8));
+
+        followers.pass(&Ok("Hal".to_string()));
+        let late = follower(coalescer.join("key", 8));
+        assert_eq!(coalescer.len(), 1);
+        followers.pass(&Ok("lo".to_string()));
+        followers.pass(&Err(TranslationError::Truncated));
+        assert!(coalescer.is_empty());
+
+        for rx in [early, late] {
+            let (text, end) = collect(rx).await;
+            assert_eq!(text, "Hallo");
+            assert!(matches!(end, Some(Err(TranslationError::Truncated))));
+        }
+        // A finished response is not joined
+        leader(coalescer.join("key", 8));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_response_closes_the_followers() {
+        let coalescer = Coalescer::default();
+        let mut followers = leader(coalescer.join("key", 8));
+        let rx = follower(coalescer.join("key", 8));
+        followers.pass(&Ok("Hal".to_string()));
+        drop(followers);
+
+        assert!(coalescer.is_empty());
+        let (text, end) = collect(rx).await;
+        assert_eq!(text, "Hal");
+        assert!(end.is_none());
+
+        // Nobody can join a response that isn't shared
+        let mut followers = Followers::none();
+        followers.pass(&Ok("Hal".to_string()));
+        followers.pass(&Ok(String::new()));
+        assert!(coalescer.is_empty());
+    }
+}
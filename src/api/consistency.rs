@@ -0,0 +1,609 @@
+//! Consistent terminology across the chunks of a long text.
+//!
+//! Translated chunk by chunk, a document may get a term one way in its
+//! second chunk and another way in its seventh. [`TermConsistency`] reads
+//! the terms of every finished chunk and how its translation renders them:
+//! glossary terms whose translation it contains, quoted terms paired in
+//! order with the quoted text of the translation, and capitalized phrases
+//! such as names that the translation keeps as they are. The terms are
+//! kept in a table of limited size, where a new term replaces the one
+//! seen in the fewest chunks and longest ago, and the terms of the table
+//! occurring in the next chunk go into its prompt.
+//!
+//! Nothing is asked of the model. Where a chunk has a term of the table
+//! but no rendering of it could be read, only whether the translation has
+//! the rendering of the table is known. [`TermConsistency::report`] lists
+//! the terms that still ended up with more than one rendering, with where
+//! each occurred.
+
+use crate::utils::chunker::Chunk;
+use crate::utils::glossary::{self, TermMatcher};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Terms kept in the table until a limit is configured.
+pub const DEFAULT_MAX_TERMS: usize = 40;
+
+/// Longest quoted text taken for a term, in words.
+const MAX_QUOTED_WORDS: usize = 5;
+
+/// Longest quoted text taken for a term, in characters.
+const MAX_QUOTED_CHARS: usize = 60;
+
+/// Marks opening a quote, each with the mark closing it.
+const QUOTES: &[(char, char)] = &[
+    ('"', '"'),
+    ('“', '”'),
+    ('„', '“'),
+    ('«', '»'),
+    ('»', '«'),
+    ('「', '」'),
+    ('『', '』'),
+];
+
+/// Marks after which a capitalized word may only be the start of a
+/// sentence.
+const SENTENCE_ENDS: &[char] = &['.', '!', '?', ':', '\n', '。', '！', '？'];
+
+/// Where a term occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    /// Index of the chunk, from 0
+    pub chunk: usize,
+    /// Byte range in the whole source text
+    pub range: Range<usize>,
+}
+
+/// One way a term was translated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendering {
+    /// The translation, `None` where it wasn't the one of the table but
+    /// couldn't be read either
+    pub target: Option<String>,
+    pub occurrences: Vec<Occurrence>,
+}
+
+/// A term translated more than one way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency {
+    pub source: String,
+    /// In order of their first occurrence
+    pub renderings: Vec<Rendering>,
+}
+
+impl Inconsistency {
+    /// The renderings in a line, e.g. `load balancer: "Lastverteiler"
+    /// (chunks 2, 3), "Load Balancer" (chunk 7)`.
+    pub fn summary(&self) -> String {
+        let renderings: Vec<String> = self
+            .renderings
+            .iter()
+            .map(|rendering| {
+                let mut chunks: Vec<String> = Vec::new();
+                for occurrence in &rendering.occurrences {
+                    let chunk = (occurrence.chunk + 1).to_string();
+                    if chunks.last() != Some(&chunk) {
+                        chunks.push(chunk);
+                    }
+                }
+                format!(
+                    "{} (chunk{} {})",
+                    rendering.target.as_ref().map_or(
+                        "something else".to_string(),
+                        |target| format!("\"{}\"", target)
+                    ),
+                    if chunks.len() == 1 { "" } else { "s" },
+                    chunks.join(", ")
+                )
+            })
+            .collect();
+        format!("{}: {}", self.source, renderings.join(", "))
+    }
+}
+
+/// A term of the table.
+#[derive(Debug, Clone)]
+struct Entry {
+    rendering: String,
+    /// Chunks the term was seen in
+    uses: u32,
+    /// Index of the last of them
+    last_seen: usize,
+}
+
+/// A term of a chunk and how its translation rendered it.
+#[derive(Debug, Clone, PartialEq)]
+struct Candidate {
+    source: String,
+    /// Byte range in the source of the chunk
+    range: Range<usize>,
+    target: String,
+}
+
+/// Terms of the chunks of one document translated so far.
+#[derive(Debug)]
+pub struct TermConsistency {
+    glossary: Arc<TermMatcher>,
+    max_terms: usize,
+    table: BTreeMap<String, Entry>,
+    renderings: BTreeMap<String, Vec<Rendering>>,
+    chunks: usize,
+}
+
+impl TermConsistency {
+    /// Tracks the terms of a document, keeping up to `max_terms` of them
+    /// for the prompts.
+    pub fn new(max_terms: usize) -> Self {
+        TermConsistency {
+            glossary: Arc::new(TermMatcher::default()),
+            max_terms,
+            table: BTreeMap::new(),
+            renderings: BTreeMap::new(),
+            chunks: 0,
+        }
+    }
+
+    /// Also reads the terms of the glossary of the target language.
+    pub fn with_glossary(mut self, glossary: Arc<TermMatcher>) -> Self {
+        self.glossary = glossary;
+        self
+    }
+
+    /// Terms of the table occurring in `source`, the text of the next
+    /// chunk, with their renderings, the most used first.
+    pub fn prompt_terms(&self, source: &str) -> Vec<(String, String)> {
+        let mut terms: Vec<(&String, &Entry)> = self
+            .table
+            .iter()
+            .filter(|(term, _)| !find_term(source, term).is_empty())
+            .collect();
+        terms.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.uses));
+        terms
+            .into_iter()
+            .map(|(term, entry)| (term.clone(), entry.rendering.clone()))
+            .collect()
+    }
+
+    /// Learns the terms of `chunk` of `text` from its `translation`.
+    ///
+    /// The chunks are recorded in order.
+    pub fn record(&mut self, text: &str, chunk: &Chunk, translation: &str) {
+        let index = self.chunks;
+        self.chunks += 1;
+        let source = &text[chunk.range.clone()];
+
+        let mut found: BTreeMap<String, (Option<String>, Vec<Range<usize>>)> = BTreeMap::new();
+        for candidate in candidates(source, translation, &self.glossary) {
+            let (_, ranges) = found
+                .entry(candidate.source)
+                .or_insert_with(|| (Some(candidate.target), Vec::new()));
+            // A quoted name is found twice
+            if !ranges.contains(&candidate.range) {
+                ranges.push(candidate.range);
+            }
+        }
+        // Terms of the table without a rendering read from this chunk
+        let lowercase_translation = translation.to_lowercase();
+        for (term, entry) in &self.table {
+            if found.contains_key(term) {
+                continue;
+            }
+            let ranges = find_term(source, term);
+            if ranges.is_empty() {
+                continue;
+            }
+            let kept =
+                !find_term(&lowercase_translation, &entry.rendering.to_lowercase()).is_empty();
+            found.insert(
+                term.clone(),
+                (kept.then(|| entry.rendering.clone()), ranges),
+            );
+        }
+
+        for (term, (target, ranges)) in found {
+            let occurrences = ranges
+                .into_iter()
+                .map(|range| Occurrence {
+                    chunk: index,
+                    range: chunk.range.start + range.start..chunk.range.start + range.end,
+                })
+                .collect();
+            self.note(&term, target.clone(), occurrences);
+            self.learn(term, target, index);
+        }
+    }
+
+    /// The terms that were translated more than one way.
+    pub fn report(&self) -> Vec<Inconsistency> {
+        self.renderings
+            .iter()
+            .filter(|(_, renderings)| renderings.len() > 1)
+            .map(|(source, renderings)| Inconsistency {
+                source: source.clone(),
+                renderings: renderings.clone(),
+            })
+            .collect()
+    }
+
+    /// Adds `occurrences` of `term` rendered as `target` to the report.
+    fn note(&mut self, term: &str, target: Option<String>, occurrences: Vec<Occurrence>) {
+        let renderings = self.renderings.entry(term.to_string()).or_default();
+        let same = |rendering: &&mut Rendering| match (&rendering.target, &target) {
+            (Some(a), Some(b)) => a.to_lowercase() == b.to_lowercase(),
+            (a, b) => a == b,
+        };
+        match renderings.iter_mut().find(same) {
+            Some(rendering) => rendering.occurrences.extend(occurrences),
+            None => renderings.push(Rendering {
+                target,
+                occurrences,
+            }),
+        }
+    }
+
+    /// Counts a use of `term` in the chunk `index`, adding it to the table
+    /// with `target` if it is new.
+    fn learn(&mut self, term: String, target: Option<String>, index: usize) {
+        if let Some(entry) = self.table.get_mut(&term) {
+            entry.uses += 1;
+            entry.last_seen = index;
+            return;
+        }
+        let Some(rendering) = target else {
+            return;
+        };
+        self.table.insert(
+            term,
+            Entry {
+                rendering,
+                uses: 1,
+                last_seen: index,
+            },
+        );
+        if self.table.len() > self.max_terms {
+            let rarest = self
+                .table
+                .iter()
+                .min_by_key(|(_, entry)| (entry.uses, entry.last_seen))
+                .map(|(term, _)| term.clone());
+            if let Some(rarest) = rarest {
+                self.table.remove(&rarest);
+            }
+        }
+    }
+}
+
+/// The terms of `source` whose rendering `translation` shows.
+fn candidates(source: &str, translation: &str, glossary: &TermMatcher) -> Vec<Candidate> {
+    let lowercase_translation = translation.to_lowercase();
+    let mut candidates = Vec::new();
+
+    for found in glossary.find(source) {
+        if find_term(&lowercase_translation, &found.term.target.to_lowercase()).is_empty() {
+            continue;
+        }
+        for range in found.ranges {
+            candidates.push(Candidate {
+                source: found.term.source.clone(),
+                range,
+                target: found.term.target.clone(),
+            });
+        }
+    }
+
+    // Quotes can only be paired while both texts have as many
+    let source_quotes = quoted(source);
+    let translation_quotes = quoted(translation);
+    if source_quotes.len() == translation_quotes.len() {
+        for (range, target) in source_quotes.into_iter().zip(translation_quotes) {
+            let (term, target) = (&source[range.clone()], &translation[target]);
+            if is_term_like(term) && is_term_like(target) {
+                candidates.push(Candidate {
+                    source: term.to_string(),
+                    range,
+                    target: target.to_string(),
+                });
+            }
+        }
+    }
+
+    for range in capitalized_phrases(source) {
+        let phrase = &source[range.clone()];
+        if !find_term(translation, phrase).is_empty() {
+            candidates.push(Candidate {
+                source: phrase.to_string(),
+                range,
+                target: phrase.to_string(),
+            });
+        }
+    }
+    candidates
+}
+
+/// Byte ranges of the whole-word occurrences of `term` in `text`.
+fn find_term(text: &str, term: &str) -> Vec<Range<usize>> {
+    if term.is_empty() {
+        return Vec::new();
+    }
+    text.match_indices(term)
+        .map(|(start, _)| start..start + term.len())
+        .filter(|range| glossary::is_whole_word(text, range))
+        .collect()
+}
+
+/// Byte ranges of the quoted texts of `text`, without the quotes and the
+/// spaces inside them.
+fn quoted(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut at = 0;
+    while let Some((offset, open, close)) = text[at..].char_indices().find_map(|(i, c)| {
+        QUOTES
+            .iter()
+            .find(|(open, _)| *open == c)
+            .map(|&(open, close)| (i, open, close))
+    }) {
+        let start = at + offset + open.len_utf8();
+        let Some(length) = text[start..].find(close) else {
+            break;
+        };
+        let inner = &text[start..start + length];
+        if !inner.contains('\n') && !inner.trim().is_empty() {
+            let leading = inner.len() - inner.trim_start().len();
+            spans.push(start + leading..start + leading + inner.trim().len());
+        }
+        at = start + length + close.len_utf8();
+    }
+    spans
+}
+
+/// Whether a quoted text is short enough to be a term, not a quotation.
+fn is_term_like(text: &str) -> bool {
+    text.chars().count() <= MAX_QUOTED_CHARS && text.split_whitespace().count() <= MAX_QUOTED_WORDS
+}
+
+/// Byte ranges of the words of `text`: letters and digits, with hyphens
+/// and apostrophes between them.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let is_inner = |c: char| matches!(c, '-' | '\'' | '’');
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match start {
+            None if c.is_alphanumeric() => start = Some(index),
+            Some(_) if c.is_alphanumeric() || is_inner(c) => {}
+            Some(word_start) => {
+                let word = text[word_start..index].trim_end_matches(is_inner);
+                words.push(word_start..word_start + word.len());
+                start = None;
+            }
+            None => {}
+        }
+    }
+    words
+}
+
+/// Byte ranges of the runs of capitalized words of `text` that don't only
+/// start a sentence, such as names.
+///
+/// A capitalized word starting a sentence is left out of the run, and
+/// single words need two characters.
+fn capitalized_phrases(text: &str) -> Vec<Range<usize>> {
+    let mut phrases = Vec::new();
+    let mut run: Vec<Range<usize>> = Vec::new();
+    let mut run_starts_sentence = false;
+    let mut flush = |run: &mut Vec<Range<usize>>, starts_sentence: bool| {
+        let words = &run[usize::from(starts_sentence).min(run.len())..];
+        if let (Some(first), Some(last)) = (words.first(), words.last())
+            && (words.len() > 1 || text[first.clone()].chars().count() > 1)
+        {
+            phrases.push(first.start..last.end);
+        }
+        run.clear();
+    };
+
+    let mut previous_end = 0;
+    for word in words(text) {
+        let gap = &text[previous_end..word.start];
+        let sentence_start = previous_end == 0 || gap.contains(SENTENCE_ENDS);
+        previous_end = word.end;
+        let capitalized = text[word.clone()]
+            .chars()
+            .next()
+            .is_some_and(char::is_uppercase);
+        if capitalized && !run.is_empty() && gap.chars().all(|c| c == ' ') {
+            run.push(word);
+            continue;
+        }
+        flush(&mut run, run_starts_sentence);
+        if capitalized {
+            run.push(word);
+            run_starts_sentence = sentence_start;
+        }
+    }
+    flush(&mut run, run_starts_sentence);
+    phrases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::glossary::Term;
+
+    fn texts(text: &str, ranges: Vec<Range<usize>>) -> Vec<&str> {
+        ranges.into_iter().map(|range| &text[range]).collect()
+    }
+
+    /// Records `chunks` of source text and translation as one document,
+    /// returning the document.
+    fn record(consistency: &mut TermConsistency, chunks: &[(&str, &str)]) -> String {
+        let mut text = String::new();
+        for (source, translation) in chunks {
+            let start = text.len();
+            text.push_str(source);
+            let chunk = Chunk {
+                range: start..text.len(),
+                context: None,
+            };
+            consistency.record(&text, &chunk, translation);
+            text.push_str("\n\n");
+        }
+        text
+    }
+
+    #[test]
+    fn test_terms_are_read_from_a_chunk() {
+        let text = "We deploy the \"load balancer\" first. Then Project Phoenix starts.";
+        assert_eq!(texts(text, quoted(text)), ["load balancer"]);
+        assert_eq!(texts(text, capitalized_phrases(text)), ["Project Phoenix"]);
+        assert_eq!(
+            texts("«  Rate-Limiter »", quoted("«  Rate-Limiter »")),
+            ["Rate-Limiter"]
+        );
+
+        let names = "The Kubernetes Cluster of O'Brien, and I. Mark said: Hello.";
+        assert_eq!(
+            texts(names, capitalized_phrases(names)),
+            ["Kubernetes Cluster", "O'Brien"]
+        );
+
+        let glossary = TermMatcher::new(vec![Term {
+            source: "deploy".to_string(),
+            target: "ausrollen".to_string(),
+            language: "Deutsch".to_string(),
+        }]);
+        let translation =
+            "Wir rollen zuerst den „Lastverteiler“ aus. Dann startet Project Phoenix.";
+        let candidates = candidates(text, translation, &glossary);
+        let pairs: Vec<(&str, &str)> = candidates
+            .iter()
+            .map(|candidate| (candidate.source.as_str(), candidate.target.as_str()))
+            .collect();
+        // The glossary term's translation is inflected, so it isn't found
+        assert_eq!(
+            pairs,
+            [
+                ("load balancer", "Lastverteiler"),
+                ("Project Phoenix", "Project Phoenix")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quotes_are_paired_only_when_they_match_up() {
+        let source = "The \"cache\" and the \"queue\" and \"a quotation that is far too long\".";
+        let translation = "Der „Cache“, die „Warteschlange“ und „ein Zitat, das viel zu lang ist“.";
+        let pairs: Vec<String> = candidates(source, translation, &TermMatcher::default())
+            .into_iter()
+            .map(|candidate| format!("{}={}", candidate.source, candidate.target))
+            .collect();
+        assert_eq!(pairs, ["cache=Cache", "queue=Warteschlange"]);
+
+        // One quote less in the translation and nothing is paired
+        let translation = "Der Cache, die „Warteschlange“ und „ein Zitat“.";
+        assert!(candidates(source, translation, &TermMatcher::default()).is_empty());
+    }
+
+    #[test]
+    fn test_earlier_terms_go_into_the_prompt() {
+        let mut consistency = TermConsistency::new(DEFAULT_MAX_TERMS);
+        record(
+            &mut consistency,
+            &[(
+                "Set up the \"load balancer\" for Project Phoenix.",
+                "Richte den „Lastverteiler“ für Project Phoenix ein.",
+            )],
+        );
+        assert_eq!(
+            consistency.prompt_terms("The load balancer of Project Phoenix is down."),
+            [
+                ("Project Phoenix".to_string(), "Project Phoenix".to_string()),
+                ("load balancer".to_string(), "Lastverteiler".to_string()),
+            ]
+        );
+        // Only terms of the next chunk
+        assert!(consistency.prompt_terms("Nothing in common.").is_empty());
+    }
+
+    #[test]
+    fn test_rare_terms_make_room() {
+        let mut consistency = TermConsistency::new(2);
+        record(
+            &mut consistency,
+            &[
+                ("Ask \"Alpha\" please.", "Frag „Alpha“ bitte."),
+                ("Ask \"Beta\" please.", "Frag „Beta“ bitte."),
+                ("Ask \"Alpha\" again.", "Frag „Alpha“ nochmal."),
+                ("Ask \"Gamma\" please.", "Frag „Gamma“ bitte."),
+            ],
+        );
+        // Beta was seen in fewer chunks than Alpha
+        let terms: Vec<String> = consistency
+            .prompt_terms("Alpha, Beta, Gamma")
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect();
+        assert_eq!(terms, ["Alpha", "Gamma"]);
+
+        // Of terms seen as often, the one seen longest ago goes
+        record(
+            &mut consistency,
+            &[("Ask \"Delta\" please.", "Frag „Delta“ bitte.")],
+        );
+        let terms: Vec<String> = consistency
+            .prompt_terms("Alpha, Beta, Gamma, Delta")
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect();
+        assert_eq!(terms, ["Alpha", "Delta"]);
+    }
+
+    #[test]
+    fn test_report_lists_terms_with_several_renderings() {
+        let mut consistency = TermConsistency::new(DEFAULT_MAX_TERMS);
+        let text = record(
+            &mut consistency,
+            &[
+                (
+                    "The \"load balancer\" of Project Phoenix.",
+                    "Der „Lastverteiler“ von Project Phoenix.",
+                ),
+                (
+                    "Restart the \"load balancer\".",
+                    "Starte den „lastverteiler“ neu.",
+                ),
+                ("Nothing here.", "Nichts hier."),
+                (
+                    "Then the \"load balancer\" of Project Phoenix.",
+                    "Dann der „Load Balancer“ des Projekts Phönix.",
+                ),
+            ],
+        );
+
+        let report = consistency.report();
+        assert_eq!(report.len(), 2);
+        let balancer = &report[1];
+        assert_eq!(balancer.source, "load balancer");
+        assert_eq!(balancer.renderings.len(), 2);
+        // Renderings differing in case only are the same
+        let chunks: Vec<usize> = balancer.renderings[0]
+            .occurrences
+            .iter()
+            .map(|occurrence| occurrence.chunk)
+            .collect();
+        assert_eq!(chunks, [0, 1]);
+        let later = &balancer.renderings[1].occurrences[0];
+        assert_eq!(later.chunk, 3);
+        assert_eq!(&text[later.range.clone()], "load balancer");
+        assert_eq!(
+            balancer.summary(),
+            r#"load balancer: "Lastverteiler" (chunks 1, 2), "Load Balancer" (chunk 4)"#
+        );
+
+        // The name wasn't kept, and what it became can't be told
+        assert_eq!(
+            report[0].summary(),
+            r#"Project Phoenix: "Project Phoenix" (chunk 1), something else (chunk 4)"#
+        );
+    }
+}
@@ -0,0 +1,859 @@
+//! Post-processing of streamed model output.
+//!
+//! Filters see the response chunk by chunk, as it arrives, and decide what
+//! to pass on. A filter that cannot decide yet, e.g. because a placeholder
+//! is only half received, holds the text back and releases it with a later
+//! chunk or when the stream completes. Filters are combined in a
+//! [`FilterChain`], which the translator applies between the client stream
+//! and the consumer.
+
+use crate::utils::links;
+use std::borrow::Cow;
+
+/// What a filter does with a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterOutput {
+    /// Pass the chunk on unchanged
+    Pass,
+    /// Pass this text on instead of the chunk
+    Emit(String),
+    /// Pass nothing on for now, the chunk is held back
+    Hold,
+}
+
+/// A transformation of streamed text that works across chunk boundaries.
+///
+/// Whatever the chunk boundaries are, the concatenated output must be the
+/// same as if the whole response had arrived in one chunk.
+pub trait StreamFilter: Send {
+    /// Takes the next chunk of the response.
+    fn on_chunk(&mut self, chunk: &str) -> FilterOutput;
+
+    /// Releases the text still held back once the response is complete.
+    fn on_complete(&mut self) -> Option<String>;
+}
+
+/// Filters applied one after the other, each to the output of the previous.
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn StreamFilter>>,
+}
+
+impl FilterChain {
+    /// Appends `filter` to the end of the chain.
+    pub fn with(mut self, filter: impl StreamFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Runs `chunk` through the filters starting at `first`.
+    fn feed(&mut self, first: usize, chunk: &str) -> Option<String> {
+        let mut text = Cow::Borrowed(chunk);
+        for filter in &mut self.filters[first..] {
+            match filter.on_chunk(&text) {
+                FilterOutput::Pass => {}
+                FilterOutput::Emit(emitted) => text = Cow::Owned(emitted),
+                FilterOutput::Hold => return None,
+            }
+            if text.is_empty() {
+                return None;
+            }
+        }
+        Some(text.into_owned())
+    }
+
+    /// Runs a chunk through every filter, returning the text to pass on.
+    pub fn process(&mut self, chunk: &str) -> Option<String> {
+        if chunk.is_empty() {
+            return None;
+        }
+        self.feed(0, chunk)
+    }
+
+    /// Flushes the filters in order, running what each one releases through
+    /// the filters after it.
+    pub fn finish(&mut self) -> Option<String> {
+        let mut output = String::new();
+        for i in 0..self.filters.len() {
+            if let Some(flushed) = self.filters[i].on_complete()
+                && let Some(text) = self.feed(i + 1, &flushed)
+            {
+                output.push_str(&text);
+            }
+        }
+        (!output.is_empty()).then_some(output)
+    }
+}
+
+/// Labels models put in front of the translation on the same line.
+const PREAMBLE_LABELS: [&str; 6] = [
+    "translation:",
+    "translated text:",
+    "翻译：",
+    "翻译:",
+    "译文：",
+    "译文:",
+];
+
+/// Openings of a chatty line that introduces the translation.
+const PREAMBLE_INTROS: [&str; 8] = [
+    "here is",
+    "here's",
+    "sure",
+    "certainly",
+    "of course",
+    "below is",
+    "the following is",
+    "以下是",
+];
+
+/// Longest first line held back while it may still be a preamble.
+const MAX_PREAMBLE_LEN: usize = 200;
+
+/// Whether `line` is a complete preamble line, such as "Here is the
+/// translation:".
+fn is_preamble_line(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    PREAMBLE_INTROS.iter().any(|intro| line.starts_with(intro))
+        && (line.ends_with(':') || line.ends_with('：'))
+}
+
+/// State of a [`PreambleFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PreambleState {
+    /// The start of the response is held back until it is known whether it
+    /// is a preamble
+    Deciding,
+    /// A preamble was dropped, the whitespace after it is dropped too,
+    /// except for the indentation of a new line when `line_start`
+    SkippingBlank { line_start: bool },
+    /// Everything is passed on
+    Done,
+}
+
+/// Drops a chatty preamble such as "Here is the translation:" or a
+/// "Translation:" label from the start of a response.
+///
+/// Only the start of the response is held back, and only while it still
+/// reads like the beginning of a preamble; a response starting with "Eins"
+/// is passed on with its first chunk.
+pub struct PreambleFilter {
+    state: PreambleState,
+    held: String,
+}
+
+impl PreambleFilter {
+    /// Creates a filter for the translation of `source`.
+    ///
+    /// If the source itself begins like a preamble, its translation will
+    /// too, so nothing is dropped.
+    pub fn for_source(source: &str) -> Self {
+        let first_line = source.trim_start().lines().next().unwrap_or_default();
+        let lowered = first_line.to_lowercase();
+        let state = if is_preamble_line(first_line)
+            || PREAMBLE_LABELS
+                .iter()
+                .any(|label| lowered.starts_with(label))
+        {
+            PreambleState::Done
+        } else {
+            PreambleState::Deciding
+        };
+        PreambleFilter {
+            state,
+            held: String::new(),
+        }
+    }
+
+    /// Decides about the held text if possible, returning what to pass on.
+    fn decide(&mut self, complete: bool) -> Option<String> {
+        let start = self.held.trim_start();
+        let lowered = start.to_lowercase();
+
+        if let Some(label) = PREAMBLE_LABELS
+            .iter()
+            .find(|label| lowered.starts_with(*label))
+        {
+            // Lowercasing keeps the length of these labels, so the label
+            // ends at the same byte in `start`
+            let rest = start[label.len()..].to_string();
+            self.held.clear();
+            self.state = PreambleState::SkippingBlank { line_start: false };
+            return self.skip_blank(&rest);
+        }
+
+        let could_start_preamble = PREAMBLE_INTROS
+            .iter()
+            .chain(PREAMBLE_LABELS.iter())
+            .any(|prefix| lowered.starts_with(prefix) || prefix.starts_with(lowered.as_str()));
+
+        if let Some((first_line, rest)) = start.split_once('\n')
+            && is_preamble_line(first_line)
+        {
+            let rest = rest.to_string();
+            self.held.clear();
+            self.state = PreambleState::SkippingBlank { line_start: true };
+            return self.skip_blank(&rest);
+        }
+
+        let undecided =
+            could_start_preamble && !start.contains('\n') && self.held.len() <= MAX_PREAMBLE_LEN;
+        if undecided && !complete {
+            return None;
+        }
+        self.state = PreambleState::Done;
+        Some(std::mem::take(&mut self.held))
+    }
+
+    /// Drops the whitespace after a preamble, holding it back until the
+    /// translation starts.
+    fn skip_blank(&mut self, text: &str) -> Option<String> {
+        let PreambleState::SkippingBlank { line_start } = self.state else {
+            return Some(text.to_string());
+        };
+        self.held.push_str(text);
+        let trimmed = self.held.trim_start();
+        if trimmed.is_empty() {
+            return None;
+        }
+        // Keep the indentation of the line the translation starts on
+        let blank = &self.held[..self.held.len() - trimmed.len()];
+        let indent_start = match blank.rfind('\n') {
+            Some(i) => i + 1,
+            None if line_start => 0,
+            None => blank.len(),
+        };
+        let output = self.held[indent_start..].to_string();
+        self.held.clear();
+        self.state = PreambleState::Done;
+        Some(output)
+    }
+}
+
+impl StreamFilter for PreambleFilter {
+    fn on_chunk(&mut self, chunk: &str) -> FilterOutput {
+        match self.state {
+            PreambleState::Done => FilterOutput::Pass,
+            PreambleState::SkippingBlank { .. } => match self.skip_blank(chunk) {
+                Some(text) => FilterOutput::Emit(text),
+                None => FilterOutput::Hold,
+            },
+            PreambleState::Deciding => {
+                self.held.push_str(chunk);
+                match self.decide(false) {
+                    Some(text) => FilterOutput::Emit(text),
+                    None => FilterOutput::Hold,
+                }
+            }
+        }
+    }
+
+    fn on_complete(&mut self) -> Option<String> {
+        match self.state {
+            PreambleState::Deciding => self.decide(true).filter(|text| !text.is_empty()),
+            PreambleState::SkippingBlank { .. } | PreambleState::Done => None,
+        }
+    }
+}
+
+/// Opening bracket of a placeholder such as `⟦C1⟧`.
+const PLACEHOLDER_OPEN: char = '⟦';
+/// Closing bracket of a placeholder.
+const PLACEHOLDER_CLOSE: char = '⟧';
+/// Longest text between the brackets of a placeholder.
+const MAX_PLACEHOLDER_LEN: usize = 8;
+
+/// Kind letter of the placeholders for inline code.
+const CODE_KIND: char = 'C';
+/// Kind letter of the placeholders for links.
+const URL_KIND: char = 'U';
+
+/// Formats the placeholder numbered `n` (1-based) of a `kind` of span.
+fn placeholder(kind: char, n: usize) -> String {
+    format!("{}{}{}{}", PLACEHOLDER_OPEN, kind, n, PLACEHOLDER_CLOSE)
+}
+
+/// Whether `text` contains placeholders made by [`mask_code_spans`].
+pub fn has_placeholders(text: &str) -> bool {
+    text.contains(&format!("{}{}", PLACEHOLDER_OPEN, CODE_KIND))
+}
+
+/// Whether `text` contains placeholders made by [`mask_urls`].
+pub fn has_url_placeholders(text: &str) -> bool {
+    text.contains(&format!("{}{}", PLACEHOLDER_OPEN, URL_KIND))
+}
+
+/// Replaces inline code spans (`` `like this` ``) with numbered
+/// placeholders, so the model cannot translate them.
+///
+/// Fenced code blocks are left alone. Nothing is masked if the text already
+/// contains a placeholder bracket.
+///
+/// # Returns
+///
+/// The masked text and the spans, the first one replacing `⟦C1⟧`
+pub fn mask_code_spans(text: &str) -> (String, Vec<String>) {
+    if text.contains(PLACEHOLDER_OPEN) {
+        return (text.to_string(), Vec::new());
+    }
+
+    let mut masked = String::with_capacity(text.len());
+    let mut spans = Vec::new();
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            masked.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(open) = rest.find('`') {
+            let after = &rest[open + 1..];
+            let Some(close) = after.find('`').filter(|&close| close > 0) else {
+                break;
+            };
+            spans.push(rest[open..open + close + 2].to_string());
+            masked.push_str(&rest[..open]);
+            masked.push_str(&placeholder(CODE_KIND, spans.len()));
+            rest = &after[close + 1..];
+        }
+        masked.push_str(rest);
+    }
+    (masked, spans)
+}
+
+/// Replaces URLs and email addresses with numbered placeholders, so the
+/// model cannot translate or reformat them.
+///
+/// Every occurrence of the same link gets the same placeholder. In a
+/// Markdown link only the target is masked, its label is translated.
+/// Nothing is masked if the text already contains a link placeholder.
+///
+/// # Returns
+///
+/// The masked text and the distinct links, the first one replacing `⟦U1⟧`
+pub fn mask_urls(text: &str) -> (String, Vec<String>) {
+    if has_url_placeholders(text) {
+        return (text.to_string(), Vec::new());
+    }
+
+    let mut masked = String::with_capacity(text.len());
+    let mut urls: Vec<String> = Vec::new();
+    let mut end = 0;
+    for link in links::find_links(text) {
+        let url = &text[link.range.clone()];
+        let n = match urls.iter().position(|known| known == url) {
+            Some(i) => i + 1,
+            None => {
+                urls.push(url.to_string());
+                urls.len()
+            }
+        };
+        masked.push_str(&text[end..link.range.start]);
+        masked.push_str(&placeholder(URL_KIND, n));
+        end = link.range.end;
+    }
+    masked.push_str(&text[end..]);
+    (masked, urls)
+}
+
+/// Puts the code spans masked by [`mask_code_spans`] and the links masked
+/// by [`mask_urls`] back in place of their placeholders.
+///
+/// A placeholder split across chunks is held back until its closing
+/// bracket arrives. Unknown placeholders are passed on as they are.
+pub struct PlaceholderFilter {
+    spans: Vec<String>,
+    urls: Vec<String>,
+    held: String,
+}
+
+impl PlaceholderFilter {
+    /// Creates a filter restoring `spans`, as returned by [`mask_code_spans`].
+    pub fn new(spans: Vec<String>) -> Self {
+        PlaceholderFilter {
+            spans,
+            urls: Vec::new(),
+            held: String::new(),
+        }
+    }
+
+    /// Also restores `urls`, as returned by [`mask_urls`].
+    pub fn with_urls(mut self, urls: Vec<String>) -> Self {
+        self.urls = urls;
+        self
+    }
+
+    /// Masks the spans and links of `text` with the placeholders this
+    /// filter restores, e.g. output the model is to resume.
+    ///
+    /// Code spans are masked in order, one occurrence each, then every
+    /// occurrence of the links, longest first.
+    pub fn mask(&self, text: &str) -> String {
+        let mut masked = text.to_string();
+        for (i, span) in self.spans.iter().enumerate() {
+            masked = masked.replacen(span.as_str(), &placeholder(CODE_KIND, i + 1), 1);
+        }
+        let mut urls: Vec<(usize, &String)> = self.urls.iter().enumerate().collect();
+        urls.sort_by_key(|(_, url)| std::cmp::Reverse(url.len()));
+        for (i, url) in urls {
+            masked = masked.replace(url.as_str(), &placeholder(URL_KIND, i + 1));
+        }
+        masked
+    }
+
+    /// The span a complete placeholder body such as `C1` stands for.
+    fn span(&self, body: &str) -> Option<&str> {
+        let mut chars = body.chars();
+        let spans = match chars.next()? {
+            CODE_KIND => &self.spans,
+            URL_KIND => &self.urls,
+            _ => return None,
+        };
+        let n: usize = chars.as_str().parse().ok()?;
+        spans.get(n.checked_sub(1)?).map(String::as_str)
+    }
+
+    /// Restores the placeholders in `text`, returning the restored text and
+    /// the unfinished placeholder at its end.
+    fn restore<'a>(&self, text: &'a str) -> (String, &'a str) {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(open) = rest.find(PLACEHOLDER_OPEN) {
+            output.push_str(&rest[..open]);
+            let after = &rest[open + PLACEHOLDER_OPEN.len_utf8()..];
+            match after.find(PLACEHOLDER_CLOSE) {
+                Some(close) if close <= MAX_PLACEHOLDER_LEN => {
+                    let end = close + PLACEHOLDER_CLOSE.len_utf8();
+                    match self.span(&after[..close]) {
+                        Some(span) => output.push_str(span),
+                        None => {
+                            output.push_str(&rest[open..open + PLACEHOLDER_OPEN.len_utf8() + end])
+                        }
+                    }
+                    rest = &after[end..];
+                }
+                None if after.len() <= MAX_PLACEHOLDER_LEN => return (output, &rest[open..]),
+                _ => {
+                    output.push(PLACEHOLDER_OPEN);
+                    rest = after;
+                }
+            }
+        }
+        output.push_str(rest);
+        (output, "")
+    }
+}
+
+impl StreamFilter for PlaceholderFilter {
+    fn on_chunk(&mut self, chunk: &str) -> FilterOutput {
+        if self.held.is_empty() && !chunk.contains(PLACEHOLDER_OPEN) {
+            return FilterOutput::Pass;
+        }
+        let text = std::mem::take(&mut self.held) + chunk;
+        let (output, unfinished) = self.restore(&text);
+        self.held = unfinished.to_string();
+        if output.is_empty() {
+            FilterOutput::Hold
+        } else {
+            FilterOutput::Emit(output)
+        }
+    }
+
+    fn on_complete(&mut self) -> Option<String> {
+        (!self.held.is_empty()).then(|| std::mem::take(&mut self.held))
+    }
+}
+
+/// Decodes escape sequences some providers leave in their content deltas,
+/// such as a literal `\n` for a line break or `\*` for an asterisk.
+///
+/// Decoded are `\n`, `\t`, `\"`, `\\`, the markdown escapes `\*`, `\_`
+/// and `` \` ``, and `\u{XXXX}` when it names a valid character. Any other
+/// backslash is kept, so a path like `C:\Users` survives. An escape split
+/// across chunks is held back until its end arrives.
+#[derive(Default)]
+pub struct UnescapeFilter {
+    held: String,
+}
+
+/// What a backslash at the start of a text starts.
+enum Escape {
+    /// A complete escape of this many bytes, decoded
+    Decoded(char, usize),
+    /// Not an escape, the backslash is kept
+    Literal,
+    /// The text ends before it is known
+    Unfinished,
+}
+
+/// Reads the escape at the start of `text`, which begins with a backslash.
+fn read_escape(text: &str) -> Escape {
+    let Some(c) = text[1..].chars().next() else {
+        return Escape::Unfinished;
+    };
+    let decoded = match c {
+        'n' => '\n',
+        't' => '\t',
+        '"' | '\\' | '*' | '_' | '`' => c,
+        'u' => return read_unicode_escape(text),
+        _ => return Escape::Literal,
+    };
+    Escape::Decoded(decoded, 2)
+}
+
+/// Reads a `\u{XXXX}` escape at the start of `text`.
+fn read_unicode_escape(text: &str) -> Escape {
+    let rest = &text[2..];
+    if rest.is_empty() {
+        return Escape::Unfinished;
+    }
+    let Some(digits) = rest.strip_prefix('{') else {
+        return Escape::Literal;
+    };
+    let hex_len = digits
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(digits.len());
+    if hex_len > 6 {
+        return Escape::Literal;
+    }
+    match digits[hex_len..].chars().next() {
+        None => Escape::Unfinished,
+        Some('}') if hex_len > 0 => u32::from_str_radix(&digits[..hex_len], 16)
+            .ok()
+            .and_then(char::from_u32)
+            .map_or(Escape::Literal, |c| Escape::Decoded(c, hex_len + 4)),
+        Some(_) => Escape::Literal,
+    }
+}
+
+impl UnescapeFilter {
+    /// Decodes the escapes in `text`, returning the decoded text and the
+    /// unfinished escape at its end.
+    fn decode(text: &str) -> (String, &str) {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(backslash) = rest.find('\\') {
+            output.push_str(&rest[..backslash]);
+            rest = &rest[backslash..];
+            match read_escape(rest) {
+                Escape::Decoded(c, len) => {
+                    output.push(c);
+                    rest = &rest[len..];
+                }
+                Escape::Literal => {
+                    output.push('\\');
+                    rest = &rest[1..];
+                }
+                // At most `\u{` and six digits
+                Escape::Unfinished => return (output, rest),
+            }
+        }
+        output.push_str(rest);
+        (output, "")
+    }
+}
+
+impl StreamFilter for UnescapeFilter {
+    fn on_chunk(&mut self, chunk: &str) -> FilterOutput {
+        if self.held.is_empty() && !chunk.contains('\\') {
+            return FilterOutput::Pass;
+        }
+        let text = std::mem::take(&mut self.held) + chunk;
+        let (output, unfinished) = Self::decode(&text);
+        self.held = unfinished.to_string();
+        if output.is_empty() {
+            FilterOutput::Hold
+        } else {
+            FilterOutput::Emit(output)
+        }
+    }
+
+    fn on_complete(&mut self) -> Option<String> {
+        // An escape cut off by the end of the response is kept as written
+        (!self.held.is_empty()).then(|| std::mem::take(&mut self.held))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `text` through `filter` in chunks of `size` characters.
+    fn run(filter: &mut dyn StreamFilter, text: &str, size: usize) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut output = String::new();
+        for chunk in chars.chunks(size) {
+            let chunk: String = chunk.iter().collect();
+            match filter.on_chunk(&chunk) {
+                FilterOutput::Pass => output.push_str(&chunk),
+                FilterOutput::Emit(text) => output.push_str(&text),
+                FilterOutput::Hold => {}
+            }
+        }
+        output.push_str(&filter.on_complete().unwrap_or_default());
+        output
+    }
+
+    /// Checks that every chunk size gives the same output as one chunk.
+    fn assert_boundaries<F: StreamFilter>(make: impl Fn() -> F, input: &str, expected: &str) {
+        let len = input.chars().count().max(1);
+        for size in 1..=len {
+            assert_eq!(
+                run(&mut make(), input, size),
+                expected,
+                "chunks of {} characters of {:?}",
+                size,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_preamble_lines_are_dropped() {
+        let make = || PreambleFilter::for_source("Hello");
+        assert_boundaries(make, "Here is the translation:\n\nHallo", "Hallo");
+        assert_boundaries(
+            make,
+            "Sure! Here's the German translation:\nHallo\nWelt",
+            "Hallo\nWelt",
+        );
+        assert_boundaries(make, "以下是翻译：\n你好", "你好");
+        assert_boundaries(make, "  Translation: Hallo", "Hallo");
+        assert_boundaries(make, "译文：\n\n  你好", "  你好");
+        assert_boundaries(make, "Here is the translation:\n    Hallo", "    Hallo");
+    }
+
+    #[test]
+    fn test_translations_are_kept() {
+        let make = || PreambleFilter::for_source("Hello");
+        assert_boundaries(make, "Hallo Welt", "Hallo Welt");
+        assert_boundaries(make, "Sure, das geht.\nDanke", "Sure, das geht.\nDanke");
+        assert_boundaries(make, "Here is", "Here is");
+        assert_boundaries(make, "Hier ist es:\nJa", "Hier ist es:\nJa");
+        assert_boundaries(make, "", "");
+    }
+
+    #[test]
+    fn test_preamble_filter_passes_unrelated_start_at_once() {
+        let mut filter = PreambleFilter::for_source("One two");
+        assert_eq!(
+            filter.on_chunk("Eins "),
+            FilterOutput::Emit("Eins ".to_string())
+        );
+        assert_eq!(filter.on_chunk("zwei"), FilterOutput::Pass);
+        assert_eq!(filter.on_complete(), None);
+    }
+
+    #[test]
+    fn test_preamble_like_source_is_kept() {
+        let make = || PreambleFilter::for_source("Here is the list:\n- milk");
+        assert_boundaries(
+            make,
+            "Hier ist die Liste:\n- Milch",
+            "Hier ist die Liste:\n- Milch",
+        );
+        assert_boundaries(
+            make,
+            "Here is the list:\n- Milch",
+            "Here is the list:\n- Milch",
+        );
+    }
+
+    #[test]
+    fn test_long_first_line_is_not_held_forever() {
+        let line = format!("Sure {}", "x".repeat(MAX_PREAMBLE_LEN));
+        let mut filter = PreambleFilter::for_source("Hello");
+        assert!(matches!(filter.on_chunk(&line), FilterOutput::Emit(text) if text == line));
+    }
+
+    #[test]
+    fn test_mask_code_spans() {
+        let (masked, spans) =
+            mask_code_spans("Call `run()` then `stop`, not ``.\n```\nlet `x`\n```");
+        assert_eq!(masked, "Call ⟦C1⟧ then ⟦C2⟧, not ``.\n```\nlet `x`\n```");
+        assert_eq!(spans, vec!["`run()`", "`stop`"]);
+        assert!(has_placeholders(&masked));
+
+        let (masked, spans) = mask_code_spans("An ⟦odd⟧ `text`");
+        assert_eq!(masked, "An ⟦odd⟧ `text`");
+        assert!(spans.is_empty());
+        assert!(!has_placeholders("plain"));
+    }
+
+    #[test]
+    fn test_placeholders_are_restored_across_boundaries() {
+        let spans = vec!["`run()`".to_string(), "`stop`".to_string()];
+        let make = || PlaceholderFilter::new(spans.clone());
+        assert_boundaries(
+            make,
+            "Rufe ⟦C1⟧ auf, dann ⟦C2⟧.",
+            "Rufe `run()` auf, dann `stop`.",
+        );
+        assert_boundaries(make, "⟦C2⟧⟦C1⟧", "`stop``run()`");
+        // Unknown, unfinished and overlong placeholders are kept
+        assert_boundaries(make, "⟦C9⟧ und ⟦C0⟧", "⟦C9⟧ und ⟦C0⟧");
+        assert_boundaries(make, "Ende ⟦C1", "Ende ⟦C1");
+        assert_boundaries(
+            make,
+            "⟦ein sehr langer Text⟧ ⟦C1⟧",
+            "⟦ein sehr langer Text⟧ `run()`",
+        );
+    }
+
+    #[test]
+    fn test_mask_urls() {
+        // Trailing punctuation and parentheses stay in the text
+        let (masked, urls) = mask_urls(
+            "See https://example.com/docs. (Or https://example.com/faq) Mail help@example.com!",
+        );
+        assert_eq!(masked, "See ⟦U1⟧. (Or ⟦U2⟧) Mail ⟦U3⟧!");
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/docs",
+                "https://example.com/faq",
+                "help@example.com"
+            ]
+        );
+        assert!(has_url_placeholders(&masked));
+        assert!(!has_placeholders(&masked));
+
+        // The label of a Markdown link is translated, its target is kept
+        let (masked, urls) = mask_urls("Read [the guide](https://example.com/guide).");
+        assert_eq!(masked, "Read [the guide](⟦U1⟧).");
+        assert_eq!(urls, vec!["https://example.com/guide"]);
+
+        // The same link twice gets one placeholder
+        let (masked, urls) = mask_urls(
+            "https://example.com/a and https://example.com/b, again https://example.com/a",
+        );
+        assert_eq!(masked, "⟦U1⟧ and ⟦U2⟧, again ⟦U1⟧");
+        assert_eq!(urls.len(), 2);
+
+        let (masked, urls) = mask_urls("Already ⟦U1⟧ https://example.com");
+        assert_eq!(masked, "Already ⟦U1⟧ https://example.com");
+        assert!(urls.is_empty());
+    }
+
+    #[test]
+    fn test_urls_are_restored_across_boundaries() {
+        let text = "Lies [die Anleitung](https://example.com/guide) oder https://example.com/guide. Code `a`";
+        let (masked, spans) = mask_code_spans(text);
+        let (masked, urls) = mask_urls(&masked);
+        assert_eq!(masked, "Lies [die Anleitung](⟦U1⟧) oder ⟦U1⟧. Code ⟦C1⟧");
+
+        let make = || PlaceholderFilter::new(spans.clone()).with_urls(urls.clone());
+        assert_boundaries(make, &masked, text);
+        // The model moved and repeated the placeholders
+        assert_boundaries(
+            make,
+            "⟦U1⟧：[指南](⟦U1⟧)，⟦C1⟧",
+            "https://example.com/guide：[指南](https://example.com/guide)，`a`",
+        );
+    }
+
+    /// Checks that `input` split in two at every byte gives `expected`.
+    fn assert_splits<F: StreamFilter>(make: impl Fn() -> F, input: &str, expected: &str) {
+        for split in (1..input.len()).filter(|&i| input.is_char_boundary(i)) {
+            let mut filter = make();
+            let mut output = String::new();
+            for chunk in [&input[..split], &input[split..]] {
+                match filter.on_chunk(chunk) {
+                    FilterOutput::Pass => output.push_str(chunk),
+                    FilterOutput::Emit(text) => output.push_str(&text),
+                    FilterOutput::Hold => {}
+                }
+            }
+            output.push_str(&filter.on_complete().unwrap_or_default());
+            assert_eq!(output, expected, "split at byte {} of {:?}", split, input);
+        }
+    }
+
+    #[test]
+    fn test_escapes_are_decoded_across_boundaries() {
+        let cases = [
+            (r"Erste Zeile\nZweite\tZeile", "Erste Zeile\nZweite\tZeile"),
+            (r#"Er sagte \"Hallo\"."#, "Er sagte \"Hallo\"."),
+            (
+                r"\*\*fett\*\* und \_kursiv\_ \`x\`",
+                "**fett** und _kursiv_ `x`",
+            ),
+            (r"Caf\u{e9} \u{1F600}!", "Café 😀!"),
+            (
+                r"Ein \\ Backslash, \\n bleibt",
+                r"Ein \ Backslash, \n bleibt",
+            ),
+            ("中文\\n第二行", "中文\n第二行"),
+        ];
+        for (input, expected) in cases {
+            assert_boundaries(UnescapeFilter::default, input, expected);
+            assert_splits(UnescapeFilter::default, input, expected);
+        }
+    }
+
+    #[test]
+    fn test_genuine_backslashes_survive() {
+        let inputs = [
+            r"C:\Users\Admin\Documents",
+            r"\d+ matches digits, \w+ words",
+            r"\u{D800} \u{110000} \u{} \u{zz} \u1234 \u{1234567}",
+            r"ends with a backslash \",
+            r"ends with \u{4F6",
+        ];
+        for input in inputs {
+            assert_boundaries(UnescapeFilter::default, input, input);
+            assert_splits(UnescapeFilter::default, input, input);
+        }
+        // Text without backslashes is passed on as it is
+        assert_eq!(
+            UnescapeFilter::default().on_chunk("Hallo"),
+            FilterOutput::Pass
+        );
+    }
+
+    #[test]
+    fn test_output_is_masked_like_the_source() {
+        let filter =
+            PlaceholderFilter::new(vec!["`a`".to_string(), "`a`".to_string()]).with_urls(vec![
+                "https://example.com".to_string(),
+                "https://example.com/guide".to_string(),
+            ]);
+        assert_eq!(
+            filter.mask("`a` und `a`, https://example.com/guide oder https://example.com"),
+            "⟦C1⟧ und ⟦C2⟧, ⟦U2⟧ oder ⟦U1⟧"
+        );
+    }
+
+    #[test]
+    fn test_chain_flushes_into_later_filters() {
+        let spans = vec!["`x`".to_string()];
+        let make_chain = || {
+            FilterChain::default()
+                .with(PreambleFilter::for_source("Set `x`"))
+                .with(PlaceholderFilter::new(spans.clone()))
+        };
+        for size in 1..=40 {
+            let mut chain = make_chain();
+            let input = "Here is the translation:\n⟦C1⟧ setzen";
+            let chars: Vec<char> = input.chars().collect();
+            let mut output = String::new();
+            for chunk in chars.chunks(size) {
+                let chunk: String = chunk.iter().collect();
+                output.push_str(&chain.process(&chunk).unwrap_or_default());
+            }
+            output.push_str(&chain.finish().unwrap_or_default());
+            assert_eq!(output, "`x` setzen", "chunks of {} characters", size);
+        }
+
+        // A response that is only a possible preamble is flushed through
+        // the placeholder filter on completion
+        let mut chain = make_chain();
+        assert_eq!(chain.process("Sure ⟦C1⟧"), None);
+        assert_eq!(chain.finish().as_deref(), Some("Sure `x`"));
+        assert_eq!(FilterChain::default().process("a").as_deref(), Some("a"));
+    }
+}
@@ -1,2 +1,9 @@
 pub mod client;
+pub mod coalesce;
+pub mod consistency;
+pub mod filter;
+pub mod prompt;
+pub mod request;
+pub mod session;
 pub mod translator;
+pub mod transport;
@@ -0,0 +1,133 @@
+//! Parameters of a single translation request.
+//!
+//! A request is captured when the translation starts, so it can be queued,
+//! replayed, and logged independently of later UI changes.
+
+use crate::api::client::ThinkingMode;
+use crate::api::prompt::PromptContext;
+use crate::api::translator::is_short_input;
+use crate::utils::code::CodeLanguage;
+use crate::utils::list::ListDocument;
+use crate::utils::logger::Logger;
+use crate::utils::provenance::Provenance;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Everything needed to run (or re-run) a translation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranslationRequest {
+    pub source_text: String,
+    pub target_language: String,
+    pub enable_keyword_analysis: bool,
+    pub thinking: ThinkingMode,
+    /// Code language when only comments and strings are translated
+    #[serde(default)]
+    pub code_language: Option<CodeLanguage>,
+    /// Translate numbered lists item by item, keeping their numbering
+    #[serde(default)]
+    pub list_mode: bool,
+    /// Language to translate through first, `None` to translate directly
+    #[serde(default)]
+    pub pivot_language: Option<String>,
+    /// Domain and audience hints
+    #[serde(default)]
+    pub context: PromptContext,
+    /// Offer alternatives when the source is short
+    #[serde(default)]
+    pub show_alternatives: bool,
+    /// Output token limit, `None` for the provider default
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature, `None` for the provider default
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Sent on the short text fast path: without thinking, to the fast
+    /// model if one is set, and read in one round trip
+    #[serde(default)]
+    pub fast_path: bool,
+}
+
+/// Temperature of the first retry when the provider default was used.
+const RETRY_TEMPERATURE: f32 = 0.3;
+
+impl TranslationRequest {
+    /// The same request sampled at a lower temperature, for retrying a
+    /// response that got stuck repeating itself.
+    pub fn with_lower_temperature(mut self) -> Self {
+        self.temperature = Some(
+            self.temperature
+                .map_or(RETRY_TEMPERATURE, |temperature| temperature / 2.0),
+        );
+        self
+    }
+
+    /// Whether the request is a plain translation of at most `max_chars`
+    /// characters, which the fast path can take.
+    ///
+    /// Code, list, pivot and alternatives translations need their own
+    /// prompts and keep the regular path.
+    pub fn suits_fast_path(&self, max_chars: usize) -> bool {
+        let is_list = self.list_mode && ListDocument::parse(&self.source_text).is_some();
+        let offers_alternatives = self.show_alternatives && is_short_input(&self.source_text);
+        self.code_language.is_none()
+            && self.pivot_language.is_none()
+            && !is_list
+            && !offers_alternatives
+            && self.source_text.chars().count() <= max_chars
+    }
+
+    /// The same request on the fast path.
+    pub fn on_fast_path(mut self) -> Self {
+        self.fast_path = true;
+        self.thinking = ThinkingMode::Disabled;
+        self
+    }
+}
+
+/// A request whose translation is streaming, recorded when it starts.
+///
+/// Whatever runs when the translation ends reads the language and options
+/// from here, so changing them in the UI meanwhile doesn't relabel the
+/// result in the log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InFlightRequest {
+    pub request: TranslationRequest,
+    pub started_at: Instant,
+    /// Cache exclusion rule matching the request, whose translation is
+    /// then neither cached nor logged
+    pub excluded_by: Option<String>,
+    /// Model and prompt the translation is made with
+    pub provenance: Provenance,
+}
+
+impl InFlightRequest {
+    /// Records `request`, translated with `model`.
+    pub fn new(request: TranslationRequest, model: &str) -> Self {
+        InFlightRequest {
+            provenance: Provenance::of(&request, model),
+            request,
+            started_at: Instant::now(),
+            excluded_by: None,
+        }
+    }
+
+    /// Marks the request as matching the cache exclusion rule `rule`.
+    pub fn with_exclusion(mut self, rule: Option<String>) -> Self {
+        self.excluded_by = rule;
+        self
+    }
+
+    /// Logs the finished translation with the parameters it was requested with.
+    pub fn log_completion(&self, logger: &Logger, translation: &str, reasoning: Option<&str>) {
+        logger.log(
+            "Auto-detected",
+            &self.request.target_language,
+            &self.request.source_text,
+            translation,
+            self.request.thinking.as_str(),
+            &self.request.context,
+            Some(&self.provenance),
+            reasoning,
+        );
+    }
+}
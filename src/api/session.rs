@@ -0,0 +1,1031 @@
+//! Frontend-independent translation sessions.
+//!
+//! A [`TranslationSession`] runs a [`TranslationRequest`] end to end: it picks
+//! the translation mode, follows the stream, watches its throughput, handles
+//! cancellation and summarizes the request into metrics. Frontends only turn
+//! the resulting [`StreamEvent`]s into whatever they display, so the egui app
+//! and any other frontend share the same semantics.
+
+use crate::api::client::{ResponseNote, ThinkingMode};
+use crate::api::consistency::Inconsistency;
+use crate::api::request::TranslationRequest;
+use crate::api::translator::{Alternative, Translator, is_short_input};
+use crate::error::{Result, TranslationError};
+use crate::utils::chunker::TextChunker;
+use crate::utils::list::{ListDocument, ListTranslation};
+use crate::utils::metrics::{RequestKind, RequestMetrics, RequestOutcome, ThroughputMeter};
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, stream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver};
+use tokio::sync::{Notify, oneshot};
+
+/// Something that happened while a request ran.
+///
+/// Every request ends with exactly one of `Completed`, `Truncated`,
+/// `Cancelled` or `Failed`, followed by its `Metrics`.
+#[derive(Debug)]
+pub enum StreamEvent {
+    /// A chunk of the response text
+    Chunk(String),
+    /// A chunk of the model's reasoning, never part of the response text
+    Reasoning(String),
+    /// Alternatives offered for a short input, the first one is the translation
+    Alternatives(Vec<Alternative>),
+    /// Per-item result of a list translation
+    List(ListTranslation),
+    /// Intermediate text of a translation through a pivot language
+    Pivot(String),
+    /// Terms of a chunked translation that got more than one translation
+    Inconsistencies(Vec<Inconsistency>),
+    /// The translation comes from the legacy cache, made before translations
+    /// were kept per profile, so possibly by another model
+    LegacyCache,
+    /// Rolling characters per second, published every second
+    Throughput(f64),
+    /// The stream has stayed below the floor for longer than the grace period
+    SlowStream { floor_cps: f64 },
+    /// Chunks of the response from `provider` couldn't be read and were
+    /// skipped, so the text may be incomplete
+    MalformedChunks { provider: String },
+    /// The request failed with a transient error and is sent again, as
+    /// attempt `attempt` of `max_attempts`
+    Retrying { attempt: u32, max_attempts: u32 },
+    /// The response is complete
+    Completed,
+    /// The response stopped at the output limit and can be continued
+    Truncated,
+    /// The request was cancelled with [`TranslationSession::cancel`]
+    Cancelled,
+    /// The request failed
+    Failed(TranslationError),
+    /// Summary of the finished request
+    Metrics(RequestMetrics),
+}
+
+/// Longest plain translation sent as one request, in characters.
+///
+/// Longer texts are translated in chunks of this size with their terms kept
+/// consistent, see [`Translator::translate_chunked`].
+const CHUNK_CHARS: usize = 6_000;
+
+/// Tunables of a session that are not part of a single request.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionOptions {
+    /// Throughput below which a stream counts as slow, in characters per second
+    pub slow_stream_floor_cps: f64,
+    /// How long a stream may stay slow before it is reported
+    pub slow_stream_grace: Duration,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        SessionOptions {
+            slow_stream_floor_cps: 5.0,
+            slow_stream_grace: Duration::from_secs(15),
+        }
+    }
+}
+
+/// Cancellation of one request.
+#[derive(Default)]
+struct CancelToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // Stores a permit, so a request that isn't waiting right now still sees it
+        self.notify.notify_one();
+    }
+
+    async fn cancelled(&self) {
+        if !self.cancelled.load(Ordering::SeqCst) {
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Runs translation requests against one provider and cache.
+pub struct TranslationSession {
+    translator: Arc<Translator>,
+    options: SessionOptions,
+    /// Token of the running request
+    cancel: Mutex<Arc<CancelToken>>,
+}
+
+impl TranslationSession {
+    /// Creates a session that sends requests through `translator`.
+    pub fn new(translator: Translator, options: SessionOptions) -> Self {
+        TranslationSession {
+            translator: Arc::new(translator),
+            options,
+            cancel: Mutex::new(Arc::new(CancelToken::default())),
+        }
+    }
+
+    /// The translator requests go through, e.g. to promote an alternative.
+    pub fn translator(&self) -> &Translator {
+        &self.translator
+    }
+
+    /// Cancels the running request, which then ends with
+    /// [`StreamEvent::Cancelled`].
+    pub fn cancel(&self) {
+        crate::lock_mutex!(self.cancel).cancel();
+    }
+
+    /// Starts a new request, replacing the cancel token of the previous one.
+    fn next_token(&self) -> Arc<CancelToken> {
+        let token = Arc::new(CancelToken::default());
+        *crate::lock_mutex!(self.cancel) = token.clone();
+        token
+    }
+
+    /// Translates `request`, picking plain, code, list, alternatives or pivot
+    /// mode from it.
+    ///
+    /// List mode only applies when the source has a numbered list. A pivot
+    /// language only applies to plain translations into another language.
+    /// Plain translations longer than a chunk are sent in chunks, unless
+    /// keyword analysis is on.
+    ///
+    /// With `partial`, the request continues a translation that stopped at
+    /// the output limit and only the new text is streamed.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn translate(
+        &self,
+        request: TranslationRequest,
+        partial: Option<String>,
+    ) -> BoxStream<'static, StreamEvent> {
+        let TranslationRequest {
+            source_text,
+            target_language,
+            enable_keyword_analysis,
+            thinking,
+            code_language,
+            list_mode,
+            pivot_language,
+            context,
+            show_alternatives,
+            max_tokens: _,
+            temperature: _,
+            fast_path,
+        } = request;
+
+        let mut alternatives_rx = None;
+        let mut list_rx = None;
+        let mut pivot_rx = None;
+        let mut inconsistencies_rx = None;
+        let mut legacy_cache = false;
+        let list = list_mode
+            .then(|| ListDocument::parse(&source_text))
+            .flatten();
+        let pivot_language = pivot_language.filter(|pivot| *pivot != target_language);
+        // Opened before the translator sends the request
+        let notes_rx = self.translator.notes().open();
+        let chunked = partial.is_none()
+            && !enable_keyword_analysis
+            && source_text.chars().count() > CHUNK_CHARS;
+        // Only plain translations stream text that can be continued
+        let continuable =
+            code_language.is_none() && list.is_none() && pivot_language.is_none() && !chunked;
+        let language = target_language.clone();
+        let stream_rx = match (code_language, partial, list) {
+            (_, Some(partial), _) => self.translator.continue_translation(
+                source_text,
+                target_language,
+                enable_keyword_analysis,
+                thinking,
+                context,
+                partial,
+            ),
+            (Some(code_language), None, _) => self.translator.translate_code(
+                source_text,
+                code_language,
+                target_language,
+                thinking,
+                context,
+            ),
+            (None, None, Some(document)) => {
+                let (stream_rx, list) = self.translator.translate_list(
+                    source_text,
+                    document,
+                    target_language,
+                    thinking,
+                    context,
+                );
+                list_rx = Some(list);
+                stream_rx
+            }
+            (None, None, None) if show_alternatives && is_short_input(&source_text) => {
+                let (stream_rx, alternatives) = self.translator.translate_alternatives(
+                    source_text,
+                    target_language,
+                    thinking,
+                    context,
+                );
+                alternatives_rx = Some(alternatives);
+                stream_rx
+            }
+            (None, None, None) => match pivot_language {
+                Some(pivot_language) => {
+                    let (stream_rx, pivot) = self.translator.translate_pivot(
+                        source_text,
+                        pivot_language,
+                        target_language,
+                        enable_keyword_analysis,
+                        thinking,
+                        context,
+                    );
+                    pivot_rx = Some(pivot);
+                    stream_rx
+                }
+                None if chunked => {
+                    let (stream_rx, inconsistencies) = self.translator.translate_chunked(
+                        source_text,
+                        &TextChunker::new(CHUNK_CHARS),
+                        target_language,
+                        thinking,
+                        context,
+                    );
+                    inconsistencies_rx = Some(inconsistencies);
+                    stream_rx
+                }
+                None => {
+                    legacy_cache = self.translator.cached_in_legacy(
+                        &source_text,
+                        &target_language,
+                        enable_keyword_analysis,
+                        &context,
+                    );
+                    self.translator.translate(
+                        source_text,
+                        target_language,
+                        enable_keyword_analysis,
+                        thinking,
+                        context,
+                    )
+                }
+            },
+        };
+
+        self.follow(
+            stream_rx,
+            Follow {
+                kind: RequestKind::Translation,
+                model: self.translator.model().to_string(),
+                fast_path,
+                language,
+                thinking,
+                alternatives_rx,
+                list_rx,
+                pivot_rx,
+                inconsistencies_rx,
+                legacy_cache,
+                continuable,
+                notes_rx,
+            },
+        )
+    }
+
+    /// Explains the word choices, grammar and idioms of `translation`.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn explain(
+        &self,
+        source_text: String,
+        translation: String,
+        target_language: String,
+        thinking: ThinkingMode,
+    ) -> BoxStream<'static, StreamEvent> {
+        let notes_rx = self.translator.notes().open();
+        let stream_rx =
+            self.translator
+                .explain(source_text, translation, target_language.clone(), thinking);
+        self.follow(
+            stream_rx,
+            Follow {
+                kind: RequestKind::Explanation,
+                model: self.translator.model().to_string(),
+                fast_path: false,
+                language: target_language,
+                thinking,
+                alternatives_rx: None,
+                list_rx: None,
+                pivot_rx: None,
+                inconsistencies_rx: None,
+                legacy_cache: false,
+                continuable: false,
+                notes_rx,
+            },
+        )
+    }
+
+    /// Asks which stretches of `translation` the model is least sure of.
+    ///
+    /// The chunks make up the raw answer, for
+    /// [`parse_confidence_check`](crate::api::translator::parse_confidence_check).
+    /// Must be called within a Tokio runtime.
+    pub fn check_confidence(
+        &self,
+        source_text: String,
+        translation: String,
+        target_language: String,
+        thinking: ThinkingMode,
+    ) -> BoxStream<'static, StreamEvent> {
+        let notes_rx = self.translator.notes().open();
+        let stream_rx = self.translator.check_confidence(
+            source_text,
+            translation,
+            target_language.clone(),
+            thinking,
+        );
+        self.follow(
+            stream_rx,
+            Follow {
+                kind: RequestKind::Confidence,
+                model: self.translator.model().to_string(),
+                fast_path: false,
+                language: target_language,
+                thinking,
+                alternatives_rx: None,
+                list_rx: None,
+                pivot_rx: None,
+                inconsistencies_rx: None,
+                legacy_cache: false,
+                continuable: false,
+                notes_rx,
+            },
+        )
+    }
+
+    /// Follows a response stream on a background task, turning it into events.
+    fn follow(
+        &self,
+        stream_rx: Receiver<Result<String>>,
+        follow: Follow,
+    ) -> BoxStream<'static, StreamEvent> {
+        let (tx, rx) = mpsc::channel(self.translator.stream_capacity());
+        tokio::spawn(run(stream_rx, follow, self.next_token(), self.options, tx));
+        stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        })
+        .boxed()
+    }
+}
+
+/// How a response stream is followed.
+struct Follow {
+    kind: RequestKind,
+    /// Model the request was sent to
+    model: String,
+    /// The request was sent on the short text fast path
+    fast_path: bool,
+    language: String,
+    thinking: ThinkingMode,
+    alternatives_rx: Option<oneshot::Receiver<Vec<Alternative>>>,
+    list_rx: Option<oneshot::Receiver<ListTranslation>>,
+    pivot_rx: Option<oneshot::Receiver<String>>,
+    inconsistencies_rx: Option<oneshot::Receiver<Vec<Inconsistency>>>,
+    /// The response is a translation from the legacy cache
+    legacy_cache: bool,
+    /// Whether a truncated response can be continued
+    continuable: bool,
+    /// Reasoning and usage of the response
+    notes_rx: UnboundedReceiver<ResponseNote>,
+}
+
+/// Drives one request until it ends, then sends its metrics.
+///
+/// Chunks wait for room in `tx`, which holds back reading the response.
+/// Throughput and slow stream notices are dropped while `tx` is full; the
+/// next tick sends fresh ones.
+async fn run(
+    mut stream_rx: Receiver<Result<String>>,
+    mut follow: Follow,
+    cancel: Arc<CancelToken>,
+    options: SessionOptions,
+    tx: Sender<StreamEvent>,
+) {
+    let mut meter = ThroughputMeter::new(Instant::now())
+        .with_kind(follow.kind)
+        .with_fast_path(follow.fast_path);
+    let mut throughput_tick = tokio::time::interval(Duration::from_secs(1));
+    if follow.legacy_cache {
+        let _ = tx.send(StreamEvent::LegacyCache).await;
+    }
+    let mut tokens = None;
+
+    let (outcome, event) = loop {
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                tracing::info!("Request cancelled by user");
+                break (RequestOutcome::Cancelled, StreamEvent::Cancelled);
+            }
+            // Publish the rolling throughput and watch for a crawling stream
+            _ = throughput_tick.tick() => {
+                let now = Instant::now();
+                if let Some(rate) = meter.rate(now) {
+                    let _ = tx.try_send(StreamEvent::Throughput(rate));
+                }
+                if meter.check_slow(now, options.slow_stream_floor_cps, options.slow_stream_grace) {
+                    tracing::warn!(
+                        "Stream below {} chars/s for {:?}",
+                        options.slow_stream_floor_cps,
+                        options.slow_stream_grace
+                    );
+                    let _ = tx.try_send(StreamEvent::SlowStream {
+                        floor_cps: options.slow_stream_floor_cps,
+                    });
+                }
+            }
+            Some(note) = follow.notes_rx.recv() => match note {
+                ResponseNote::Reasoning(text) => {
+                    let _ = tx.send(StreamEvent::Reasoning(text)).await;
+                }
+                ResponseNote::Usage(usage) => tokens = Some(usage),
+                ResponseNote::MalformedChunks { provider } => {
+                    let _ = tx.send(StreamEvent::MalformedChunks { provider }).await;
+                }
+                ResponseNote::Retrying { attempt, max_attempts } => {
+                    let _ = tx.send(StreamEvent::Retrying { attempt, max_attempts }).await;
+                }
+            },
+            result = stream_rx.recv() => match result {
+                Some(Ok(chunk)) if chunk.is_empty() => {
+                    if let Some(rx) = follow.alternatives_rx.as_mut()
+                        && let Ok(alternatives) = rx.try_recv()
+                    {
+                        let _ = tx.send(StreamEvent::Alternatives(alternatives)).await;
+                    }
+                    if let Some(rx) = follow.list_rx.as_mut()
+                        && let Ok(list) = rx.try_recv()
+                    {
+                        let _ = tx.send(StreamEvent::List(list)).await;
+                    }
+                    if let Some(rx) = follow.pivot_rx.as_mut()
+                        && let Ok(pivot) = rx.try_recv()
+                    {
+                        let _ = tx.send(StreamEvent::Pivot(pivot)).await;
+                    }
+                    if let Some(rx) = follow.inconsistencies_rx.as_mut()
+                        && let Ok(inconsistencies) = rx.try_recv()
+                        && !inconsistencies.is_empty()
+                    {
+                        let _ = tx.send(StreamEvent::Inconsistencies(inconsistencies)).await;
+                    }
+                    break (RequestOutcome::Completed, StreamEvent::Completed);
+                }
+                Some(Ok(chunk)) => {
+                    meter.record(chunk.chars().count(), Instant::now());
+                    let _ = tx.send(StreamEvent::Chunk(chunk)).await;
+                }
+                Some(Err(TranslationError::Truncated))
+                    if follow.continuable && follow.alternatives_rx.is_none() =>
+                {
+                    break (RequestOutcome::Completed, StreamEvent::Truncated);
+                }
+                Some(Err(e)) => {
+                    tracing::error!("Request error: {}", e);
+                    break (RequestOutcome::Failed, StreamEvent::Failed(e));
+                }
+                None => {
+                    tracing::info!("Response stream ended without completing");
+                    let e = TranslationError::StreamError("The response ended unexpectedly".to_string());
+                    break (RequestOutcome::Failed, StreamEvent::Failed(e));
+                }
+            }
+        }
+    };
+
+    // Notes sent just before the response ended
+    while let Ok(note) = follow.notes_rx.try_recv() {
+        match note {
+            ResponseNote::Reasoning(text) => {
+                let _ = tx.send(StreamEvent::Reasoning(text)).await;
+            }
+            ResponseNote::Usage(usage) => tokens = Some(usage),
+            ResponseNote::MalformedChunks { provider } => {
+                let _ = tx.send(StreamEvent::MalformedChunks { provider }).await;
+            }
+            ResponseNote::Retrying {
+                attempt,
+                max_attempts,
+            } => {
+                let _ = tx
+                    .send(StreamEvent::Retrying {
+                        attempt,
+                        max_attempts,
+                    })
+                    .await;
+            }
+        }
+    }
+    let _ = tx.send(event).await;
+    let mut metrics = meter.finish(
+        Instant::now(),
+        &follow.model,
+        &follow.language,
+        follow.thinking.as_str(),
+        outcome,
+    );
+    metrics.tokens = tokens;
+    let _ = tx.send(StreamEvent::Metrics(metrics)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::client::ApiClient;
+    use crate::api::prompt::PromptContext;
+    use crate::api::transport::{ScriptStep, ScriptedTransport, sse_delta};
+    use crate::utils::cache::{Namespace, TranslationCache, fingerprint};
+
+    fn session(
+        transport: Arc<ScriptedTransport>,
+        name: &str,
+    ) -> (TranslationSession, Arc<TranslationCache>) {
+        let cache_file = std::env::temp_dir().join(format!("test_session_{}.json", name));
+        let _ = std::fs::remove_file(&cache_file);
+        let _ = std::fs::remove_file(cache_file.with_extension("journal"));
+        let cache = Arc::new(TranslationCache::new(cache_file));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport);
+        (
+            TranslationSession::new(
+                Translator::with_client(client, cache.clone()),
+                SessionOptions::default(),
+            ),
+            cache,
+        )
+    }
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest {
+            source_text: text.to_string(),
+            target_language: "Deutsch".to_string(),
+            enable_keyword_analysis: false,
+            thinking: ThinkingMode::Disabled,
+            code_language: None,
+            list_mode: false,
+            pivot_language: None,
+            context: PromptContext::default(),
+            show_alternatives: false,
+            max_tokens: None,
+            temperature: None,
+            fast_path: false,
+        }
+    }
+
+    /// Collects the events, leaving out the timing-dependent throughput.
+    async fn collect_events(stream: BoxStream<'static, StreamEvent>) -> Vec<StreamEvent> {
+        stream
+            .filter(|event| std::future::ready(!matches!(event, StreamEvent::Throughput(_))))
+            .collect()
+            .await
+    }
+
+    /// A line-oriented frontend: reads lines, prints each translation.
+    async fn cli_frontend(session: &TranslationSession, input: &str) -> String {
+        let mut output = String::new();
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let mut stream = session.translate(request(line), None);
+            while let Some(event) = stream.next().await {
+                match event {
+                    StreamEvent::Chunk(chunk) => output.push_str(&chunk),
+                    StreamEvent::Completed => output.push('\n'),
+                    StreamEvent::Failed(e) => output.push_str(&format!("error: {}\n", e)),
+                    _ => {}
+                }
+            }
+        }
+        output
+    }
+
+    /// What a graphical frontend keeps on screen.
+    #[derive(Default)]
+    struct ViewState {
+        translation: String,
+        translating: bool,
+        error: Option<String>,
+        metrics: Option<RequestMetrics>,
+    }
+
+    /// A view-model frontend: applies events to on-screen state.
+    async fn view_frontend(session: &TranslationSession, text: &str) -> ViewState {
+        let mut view = ViewState {
+            translating: true,
+            ..ViewState::default()
+        };
+        let mut stream = session.translate(request(text), None);
+        while let Some(event) = stream.next().await {
+            match event {
+                StreamEvent::Chunk(chunk) => view.translation.push_str(&chunk),
+                StreamEvent::Completed | StreamEvent::Truncated | StreamEvent::Cancelled => {
+                    view.translating = false
+                }
+                StreamEvent::Failed(e) => {
+                    view.translating = false;
+                    view.error = Some(e.to_string());
+                }
+                StreamEvent::Metrics(metrics) => view.metrics = Some(metrics),
+                _ => {}
+            }
+        }
+        view
+    }
+
+    #[tokio::test]
+    async fn test_frontends_share_session_semantics() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hal", "lo"], "stop"));
+        let (session, cache) = session(transport.clone(), "frontends");
+
+        let output = cli_frontend(&session, "Hello\n\nGood morning\n").await;
+        assert_eq!(output, "Hallo\nHallo\n");
+        assert_eq!(transport.requests().len(), 2);
+
+        // The graphical frontend gets the cached translation from the same session
+        let view = view_frontend(&session, "Hello").await;
+        assert_eq!(view.translation, "Hallo");
+        assert!(!view.translating);
+        assert_eq!(view.error, None);
+        let metrics = view.metrics.unwrap();
+        assert_eq!(metrics.outcome, RequestOutcome::Completed);
+        assert_eq!(metrics.total_chars, 5);
+        assert_eq!(transport.requests().len(), 2);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_fast_path_reads_the_whole_response_in_one_round_trip() {
+        let body = serde_json::json!({
+            "id": "test",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "fast",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hallo" },
+                "finish_reason": "stop",
+            }],
+        });
+        let transport = Arc::new(ScriptedTransport::new(vec![ScriptStep::Bytes(
+            body.to_string().into_bytes(),
+        )]));
+        let cache_file = std::env::temp_dir().join("test_session_fast_path.json");
+        let _ = std::fs::remove_file(&cache_file);
+        let _ = std::fs::remove_file(cache_file.with_extension("journal"));
+        let cache = Arc::new(TranslationCache::new(cache_file));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport.clone());
+        let translator = Translator::with_client(client, cache.clone())
+            .with_model("fast")
+            .with_streaming(false);
+        let session = TranslationSession::new(translator, SessionOptions::default());
+
+        let fast = request("Hello").on_fast_path();
+        let events = collect_events(session.translate(fast.clone(), None)).await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "Hallo"));
+        assert!(matches!(events[1], StreamEvent::Completed));
+        let StreamEvent::Metrics(metrics) = &events[2] else {
+            panic!("expected metrics, got {:?}", events[2]);
+        };
+        assert!(metrics.fast_path);
+        assert_eq!(metrics.model, "fast");
+
+        let sent = &transport.requests()[0];
+        assert_eq!(sent["stream"], false);
+        assert_eq!(sent["model"], "fast");
+        assert_eq!(sent["thinking"]["type"], "disabled");
+
+        // Cached like a streamed translation
+        let events = collect_events(session.translate(fast, None)).await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "Hallo"));
+        assert_eq!(transport.requests().len(), 1);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_every_request_ends_with_outcome_then_metrics() {
+        let transport = Arc::new(ScriptedTransport::new(vec![
+            ScriptStep::Bytes(sse_delta(Some("Hal"), None)),
+            ScriptStep::Fail("connection reset".to_string()),
+        ]));
+        let (session, cache) = session(transport, "failure");
+
+        let events = collect_events(session.translate(request("Hello"), None)).await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "Hal"));
+        assert!(matches!(
+            &events[1],
+            StreamEvent::Failed(TranslationError::StreamError(_))
+        ));
+        assert!(
+            matches!(&events[2], StreamEvent::Metrics(m) if m.outcome == RequestOutcome::Failed)
+        );
+        assert_eq!(events.len(), 3);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_truncated_translation_can_be_continued() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hal"], "length"));
+        let (session, cache) = session(transport, "truncated");
+
+        let events = collect_events(session.translate(request("Hello"), None)).await;
+        assert!(matches!(events[1], StreamEvent::Truncated));
+
+        // Code translations can't be continued, so the limit is an error
+        let mut code = request("// Hello");
+        code.code_language = Some(crate::utils::code::CodeLanguage::Rust);
+        let events = collect_events(session.translate(code, None)).await;
+        assert!(events.iter().any(|e| matches!(e, StreamEvent::Failed(_))));
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_list_mode_reports_the_items() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["⟦1⟧ Eins\n⟦2⟧ Zwei"],
+            "stop",
+        ));
+        let (session, cache) = session(transport.clone(), "list");
+
+        let mut list = request("1. One\n2. Two");
+        list.list_mode = true;
+        let events = collect_events(session.translate(list, None)).await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "1. Eins\n2. Zwei"));
+        assert!(matches!(&events[1], StreamEvent::List(list) if !list.is_mismatched()));
+        assert!(matches!(events[2], StreamEvent::Completed));
+
+        // Without a list the text is translated as usual
+        let mut plain = request("One");
+        plain.list_mode = true;
+        collect_events(session.translate(plain, None)).await;
+        let requests = transport.requests();
+        assert_eq!(
+            requests[1]["messages"][1]["content"],
+            "Translate the following text to Deutsch:\n\nOne"
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_long_text_is_translated_in_chunks() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Absatz."], "stop"));
+        let (session, cache) = session(transport.clone(), "chunked");
+        let paragraph = |word: &str| word.repeat(CHUNK_CHARS / 8).trim().to_string();
+        let text = format!("{}\n\n{}", paragraph("Word "), paragraph("Text "));
+
+        let events = collect_events(session.translate(request(&text), None)).await;
+        let translation: String = events
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Chunk(chunk) => Some(chunk.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(translation, "Absatz.\n\nAbsatz.");
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, StreamEvent::Completed))
+        );
+        assert_eq!(transport.requests().len(), 2);
+
+        // Keyword analysis needs the whole text in one request
+        let mut analysed = request(&text);
+        analysed.enable_keyword_analysis = true;
+        collect_events(session.translate(analysed, None)).await;
+        assert_eq!(transport.requests().len(), 3);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_pivot_streams_the_second_stage() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hallo"], "stop"));
+        let (session, cache) = session(transport.clone(), "pivot");
+        cache.set("안녕", "English", false, "Hello".to_string(), None);
+
+        let mut pivot = request("안녕");
+        pivot.pivot_language = Some("English".to_string());
+        let events = collect_events(session.translate(pivot, None)).await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "Hallo"));
+        assert!(matches!(&events[1], StreamEvent::Pivot(text) if text == "Hello"));
+        assert!(matches!(events[2], StreamEvent::Completed));
+
+        // Translating into the pivot language itself goes direct
+        let mut direct = request("Hi");
+        direct.pivot_language = Some("Deutsch".to_string());
+        let events = collect_events(session.translate(direct, None)).await;
+        assert!(
+            !events
+                .iter()
+                .any(|event| matches!(event, StreamEvent::Pivot(_)))
+        );
+        assert_eq!(
+            transport.requests()[1]["messages"][1]["content"],
+            "Translate the following text to Deutsch:\n\nHi"
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_a_stalled_request() {
+        let transport = Arc::new(ScriptedTransport::new(vec![
+            ScriptStep::Bytes(sse_delta(Some("Hal"), None)),
+            ScriptStep::Stall,
+        ]));
+        let (session, cache) = session(transport, "cancel");
+
+        let mut stream = session.translate(request("Hello"), None);
+        loop {
+            match stream.next().await {
+                Some(StreamEvent::Chunk(_)) => break,
+                Some(_) => continue,
+                None => panic!("stream ended before the first chunk"),
+            }
+        }
+        session.cancel();
+
+        let rest = tokio::time::timeout(Duration::from_secs(5), collect_events(stream))
+            .await
+            .expect("cancel was not noticed");
+        assert!(matches!(rest[0], StreamEvent::Cancelled));
+        assert!(
+            matches!(&rest[1], StreamEvent::Metrics(m) if m.outcome == RequestOutcome::Cancelled)
+        );
+        assert_eq!(cache.get("Hello", "Deutsch", false), None);
+        cache.clear();
+    }
+
+    /// A stream that reasons before translating, then reports its usage.
+    fn reasoning_transport() -> Arc<ScriptedTransport> {
+        let chunk = |choices: serde_json::Value, usage: serde_json::Value| {
+            let chunk = serde_json::json!({
+                "id": "test",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": "test",
+                "choices": choices,
+                "usage": usage,
+            });
+            ScriptStep::Bytes(format!("data: {}\n\n", chunk).into_bytes())
+        };
+        let reasoning = |text: &str| {
+            chunk(
+                serde_json::json!([{ "index": 0, "delta": { "reasoning_content": text } }]),
+                serde_json::Value::Null,
+            )
+        };
+        let usage = chunk(
+            serde_json::json!([]),
+            serde_json::json!({
+                "prompt_tokens": 20,
+                "completion_tokens": 30,
+                "total_tokens": 50,
+                "completion_tokens_details": { "reasoning_tokens": 25 },
+            }),
+        );
+        Arc::new(ScriptedTransport::new(vec![
+            reasoning("SECRET-THOUGHT: a greeting, "),
+            reasoning("so SECRET-THOUGHT Hallo."),
+            ScriptStep::Bytes(sse_delta(Some("Hallo"), None)),
+            ScriptStep::Bytes(sse_delta(None, Some("stop"))),
+            usage,
+            ScriptStep::Bytes(b"data: [DONE]\n\n".to_vec()),
+        ]))
+    }
+
+    async fn translate_with_reasoning(
+        keep: bool,
+        name: &str,
+    ) -> (Vec<StreamEvent>, Arc<TranslationCache>) {
+        let cache_file = std::env::temp_dir().join(format!("test_session_{}.json", name));
+        let _ = std::fs::remove_file(&cache_file);
+        let _ = std::fs::remove_file(cache_file.with_extension("journal"));
+        let cache = Arc::new(TranslationCache::new(cache_file));
+        let client = ApiClient::new("test_key".to_string()).with_transport(reasoning_transport());
+        let translator = Translator::with_client(client, cache.clone()).with_reasoning(keep);
+        let session = TranslationSession::new(translator, SessionOptions::default());
+        (
+            collect_events(session.translate(request("Hello"), None)).await,
+            cache,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_is_kept_apart_from_the_translation() {
+        let (events, cache) = translate_with_reasoning(true, "reasoning_kept").await;
+        let reasoning: String = events
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Reasoning(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            reasoning,
+            "SECRET-THOUGHT: a greeting, so SECRET-THOUGHT Hallo."
+        );
+        let translation: String = events
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Chunk(chunk) => Some(chunk.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(translation, "Hallo");
+        assert_eq!(cache.get("Hello", "Deutsch", false).unwrap().0, "Hallo");
+
+        let Some(StreamEvent::Metrics(metrics)) = events.last() else {
+            panic!("no metrics: {:?}", events);
+        };
+        let tokens = metrics.tokens.unwrap();
+        assert_eq!((tokens.prompt, tokens.completion), (20, 30));
+        assert_eq!(tokens.reasoning, Some(25));
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_discarded_reasoning_is_not_retained() {
+        let (events, cache) = translate_with_reasoning(false, "reasoning_discarded").await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "Hallo"));
+        // Nothing that came out of the request holds any of it
+        let retained = format!("{:?} {:?}", events, cache.get("Hello", "Deutsch", false));
+        assert!(!retained.contains("SECRET-THOUGHT"), "{}", retained);
+        // Still counted
+        let Some(StreamEvent::Metrics(metrics)) = events.last() else {
+            panic!("no metrics: {:?}", events);
+        };
+        assert_eq!(metrics.tokens.unwrap().reasoning, Some(25));
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_explanation_is_attributed_separately() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Because."], "stop"));
+        let (session, cache) = session(transport, "explain");
+
+        let events = collect_events(session.explain(
+            "Hello".to_string(),
+            "Hallo".to_string(),
+            "Deutsch".to_string(),
+            ThinkingMode::Disabled,
+        ))
+        .await;
+        let Some(StreamEvent::Metrics(metrics)) = events.last() else {
+            panic!("no metrics: {:?}", events);
+        };
+        assert_eq!(metrics.kind, RequestKind::Explanation);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_confidence_check_is_attributed_separately() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["1. \"Hallo\" — informal"],
+            "stop",
+        ));
+        let (session, cache) = session(transport, "confidence");
+
+        let events = collect_events(session.check_confidence(
+            "Hello".to_string(),
+            "Hallo".to_string(),
+            "Deutsch".to_string(),
+            ThinkingMode::Disabled,
+        ))
+        .await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk.starts_with("1.")));
+        let Some(StreamEvent::Metrics(metrics)) = events.last() else {
+            panic!("no metrics: {:?}", events);
+        };
+        assert_eq!(metrics.kind, RequestKind::Confidence);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_legacy_cache_hit_is_announced() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hallo"], "stop"));
+        let (_, cache) = session(transport.clone(), "legacy");
+        cache.set("Hello", "Deutsch", false, "Hallo (alt)".to_string(), None);
+        let profile = cache.scoped(Namespace::Profile(fingerprint("https://a", "m1", None)));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport.clone());
+        let session = TranslationSession::new(
+            Translator::with_client(client, Arc::new(profile)),
+            SessionOptions::default(),
+        );
+
+        let events = collect_events(session.translate(request("Hello"), None)).await;
+        assert!(matches!(events[0], StreamEvent::LegacyCache));
+        assert!(matches!(&events[1], StreamEvent::Chunk(chunk) if chunk == "Hallo (alt)"));
+        assert!(transport.requests().is_empty());
+
+        let events = collect_events(session.translate(request("Good morning"), None)).await;
+        assert!(matches!(&events[0], StreamEvent::Chunk(chunk) if chunk == "Hallo"));
+        cache.clear();
+    }
+}
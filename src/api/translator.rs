@@ -3,10 +3,27 @@
 //! This module provides high-level translation functionality,
 //! wrapping the API client with translation-specific logic.
 
-use crate::api::client::{ApiClient, ChatMessage};
-use crate::error::Result;
-use crate::utils::cache::TranslationCache;
+use crate::api::client::{ApiClient, ChatMessage, NoteSlot, RetryPolicy, Role, ThinkingMode};
+use crate::api::coalesce::{Coalescer, Followers, Joined};
+use crate::api::consistency::{DEFAULT_MAX_TERMS, Inconsistency, TermConsistency};
+use crate::api::filter::{self, FilterChain, PlaceholderFilter, PreambleFilter};
+use crate::api::prompt::PromptContext;
+use crate::error::{Result, TranslationError};
+use crate::utils::alignment;
+use crate::utils::cache::{CacheBusy, TranslationCache};
+use crate::utils::chunker::TextChunker;
+use crate::utils::code::{self, CodeLanguage};
+use crate::utils::glossary::{Term, TermMatcher};
+use crate::utils::list::{ListDocument, ListTranslation};
+use crate::utils::pdf;
+use crate::utils::repetition::{DEFAULT_MAX_REPEATS, RepetitionDetector};
+use crate::utils::romanize::{self, Scheme};
+use crate::utils::structured::ValueBatch;
+use std::collections::HashSet;
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
 
 /// Parses translation response to extract translation and optional keyword analysis
 fn parse_translation_and_keywords(
@@ -40,76 +57,486 @@ fn parse_translation_and_keywords(
     (response.to_string(), None)
 }
 
-/// Translator service for handling translation requests.
-pub struct Translator {
-    client: ApiClient,
-    cache: Arc<TranslationCache>,
+/// A cached translation as the chunks of a stream, ending with the
+/// completion signal.
+fn cached_chunks(translation: String, keyword_analysis: Option<String>) -> Vec<String> {
+    let mut chunks = vec![translation];
+    chunks.extend(keyword_analysis);
+    chunks.push(String::new());
+    chunks
 }
 
-impl Translator {
-    /// Creates a new translator with the given API key and cache.
-    ///
-    /// # Arguments
-    ///
-    /// * `api_key` - The Z.AI API key for authentication
-    /// * `cache` - Translation cache for storing/retrieving translations
-    pub fn new(api_key: String, cache: Arc<TranslationCache>) -> Self {
-        tracing::info!("Creating translator with API key");
-        Translator {
-            client: ApiClient::new(api_key),
-            cache,
+/// Maximum number of alternatives requested for a short input.
+const MAX_ALTERNATIVES: usize = 3;
+
+/// One of several translations offered for a short, ambiguous input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternative {
+    pub text: String,
+    /// One-line explanation of when this translation applies
+    pub gloss: String,
+}
+
+/// Whether the input is short enough to ask for alternatives (under six words).
+///
+/// Scripts written without spaces count roughly two characters per word.
+pub fn is_short_input(text: &str) -> bool {
+    let words: usize = text
+        .split_whitespace()
+        .map(|word| {
+            let wide = word
+                .chars()
+                .filter(|c| matches!(c, '\u{2E80}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}' | '\u{F900}'..='\u{FAFF}'))
+                .count();
+            wide.div_ceil(2).max(1)
+        })
+        .sum();
+    (1..6).contains(&words)
+}
+
+/// Similarity above which a translation looks like it mostly kept the source.
+const UNTRANSLATED_SIMILARITY: f64 = 0.6;
+
+/// Whether `translation` is suspiciously similar to `source`, as when the
+/// model copied much of the text instead of translating it.
+///
+/// Compares the sets of adjacent character pairs, ignoring whitespace and
+/// case, so it works for scripts without spaces. Sources under a dozen
+/// characters are never flagged, since names and numbers stay the same.
+pub fn looks_untranslated(source: &str, translation: &str) -> bool {
+    fn pairs(text: &str) -> HashSet<(char, char)> {
+        let chars: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect();
+        chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+
+    let source_pairs = pairs(source);
+    let translation_pairs = pairs(translation);
+    if source_pairs.len() < 12 || translation_pairs.is_empty() {
+        return false;
+    }
+    let shared = source_pairs.intersection(&translation_pairs).count();
+    let similarity = 2.0 * shared as f64 / (source_pairs.len() + translation_pairs.len()) as f64;
+    similarity > UNTRANSLATED_SIMILARITY
+}
+
+/// Phrases of a model declining the request instead of translating it,
+/// lowercase with straight apostrophes.
+const REFUSAL_PATTERNS: [&str; 15] = [
+    "i'm sorry, but i can't",
+    "i'm sorry, but i cannot",
+    "i can't help with",
+    "i cannot help with",
+    "i can't assist with",
+    "i cannot assist with",
+    "i can't translate",
+    "i cannot translate",
+    "i'm unable to translate",
+    "i am unable to translate",
+    "i won't translate",
+    "抱歉，我无法",
+    "抱歉，我不能",
+    "我无法翻译",
+    "我不能翻译",
+];
+
+/// Responses longer than this are translations, not refusals.
+const MAX_REFUSAL_CHARS: usize = 400;
+
+/// Whether `response` is the model declining to translate `source`.
+///
+/// Only short responses are checked, and a phrase the source contains as
+/// well is taken to be its translation.
+fn is_refusal(source: &str, response: &str) -> bool {
+    if response.chars().count() > MAX_REFUSAL_CHARS {
+        return false;
+    }
+    let normalize = |text: &str| text.to_lowercase().replace('’', "'");
+    let response = normalize(response);
+    let source = normalize(source);
+    REFUSAL_PATTERNS
+        .iter()
+        .any(|pattern| response.contains(pattern) && !source.contains(pattern))
+}
+
+/// Cache key text for the alternatives of `text`, kept apart from plain translations.
+fn alternatives_cache_key(text: &str) -> String {
+    format!("[alternatives]\n{}", text)
+}
+
+/// Language of the user interface, which explanations are written in.
+const EXPLANATION_LANGUAGE: &str = "English";
+
+/// Cache key text for the explanation of `translation`, kept apart from
+/// plain translations so an explanation is never served as one.
+fn explanation_cache_key(text: &str, translation: &str) -> String {
+    format!("[explanation]\n{}\n[translation]\n{}", text, translation)
+}
+
+/// Builds the messages asking why `text` was translated as `translation`.
+fn explanation_messages(text: &str, translation: &str, target_language: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: Role::System,
+            content: format!(
+                "You are a language teacher explaining a translation to a learner.
+
+## Core Task
+Explain how the source text was rendered in the translation, so the learner understands why it reads the way it does.
+
+## What to Cover
+- Notable word choices, and why they fit better than the obvious alternatives
+- Grammar points where the two languages differ
+- Idioms and set phrases, and how their meaning was carried over
+
+## Output Format
+Write in {} as a short list of points, each quoting the relevant phrase. Do NOT translate the text again or suggest a different translation unless the given one is wrong.",
+                EXPLANATION_LANGUAGE
+            ),
+        },
+        ChatMessage {
+            role: Role::User,
+            content: format!(
+                "Source text:\n\n{}\n\nTranslation into {}:\n\n{}",
+                text, target_language, translation
+            ),
+        },
+    ]
+}
+
+/// Builds the messages asking which phrase of `text` became `word` in
+/// `translation`.
+fn alignment_messages(text: &str, translation: &str, word: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: Role::System,
+            content: "You align translations with their source text. Answer with a substring copied exactly from the source text and nothing else: no quotes, no explanation.".to_string(),
+        },
+        ChatMessage {
+            role: Role::User,
+            content: format!(
+                "Source text:\n\n{}\n\nTranslation:\n\n{}\n\nIn this translation pair, which source phrase corresponds to '{}'? Answer with the exact source substring only.",
+                text, translation, word
+            ),
+        },
+    ]
+}
+
+/// Cache key text for the romanization of `text`, kept apart from plain
+/// translations.
+fn romanization_cache_key(text: &str, scheme: Scheme) -> String {
+    format!("[romanization:{}]\n{}", scheme.key(), text)
+}
+
+/// Builds the messages asking for the romanization of each of `runs`.
+fn romanization_messages(runs: &[&str], scheme: Scheme) -> Vec<ChatMessage> {
+    let style = match scheme {
+        Scheme::Pinyin => {
+            "Hanyu Pinyin with tone marks (nǐ hǎo), words written together as in dictionaries and proper names capitalized"
+        }
+        Scheme::Hepburn => {
+            "modified Hepburn romaji with macrons for long vowels (Tōkyō), particles written as pronounced (wa, e, o) and proper names capitalized"
+        }
+    };
+    let numbered: Vec<String> = runs
+        .iter()
+        .enumerate()
+        .map(|(i, run)| format!("{}. {}", i + 1, run))
+        .collect();
+    vec![
+        ChatMessage {
+            role: Role::System,
+            content: format!(
+                "You romanize {} text so that a reader who can't read the script can say it aloud. Use {}, reading each character as it is pronounced in context.
+
+Answer with one numbered line per input line, using the same numbers, containing only the romanization. Do NOT translate, explain or add anything else.",
+                scheme.language(),
+                style
+            ),
+        },
+        ChatMessage {
+            role: Role::User,
+            content: numbered.join("\n"),
+        },
+    ]
+}
+
+/// Most stretches a confidence check asks for.
+const MAX_UNCERTAIN_SPANS: usize = 5;
+
+/// A stretch of a translation the model is unsure of, as it answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UncertainSpan {
+    /// Text quoted from the translation
+    pub text: String,
+    /// Why the model is unsure of it, possibly empty
+    pub reason: String,
+}
+
+/// Where an [`UncertainSpan`] is in the translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LowConfidence {
+    /// Byte range in the translation
+    pub range: Range<usize>,
+    pub reason: String,
+}
+
+/// Cache key text for the confidence check of `translation`, kept apart
+/// from plain translations and explanations.
+fn confidence_cache_key(text: &str, translation: &str) -> String {
+    format!("[confidence]\n{}\n[translation]\n{}", text, translation)
+}
+
+/// Builds the messages asking which stretches of `translation` the model is
+/// least confident about.
+fn confidence_messages(text: &str, translation: &str, target_language: &str) -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: Role::System,
+            content: format!(
+                "You review a translation for a reader who wants to know where to look twice.
+
+## Core Task
+List the stretches of the translation you are least confident about: ambiguous source wording, idioms, terms with several accepted renderings, or places where meaning may have been lost. List at most {} and only those you have a real doubt about.
+
+## Output Format
+One numbered line per stretch, quoting it exactly as it appears in the translation, followed by an em dash and a brief reason in {}:
+1. \"<exact text from the translation>\" — <reason>
+
+If you are confident about the whole translation, answer NONE. Output ONLY the numbered lines, with no introduction or commentary.",
+                MAX_UNCERTAIN_SPANS, EXPLANATION_LANGUAGE
+            ),
+        },
+        ChatMessage {
+            role: Role::User,
+            content: format!(
+                "Source text:\n\n{}\n\nTranslation into {}:\n\n{}",
+                text, target_language, translation
+            ),
+        },
+    ]
+}
+
+/// The closing mark of a quote opened with `open`, if it is a quote mark.
+fn closing_quote(open: char) -> Option<char> {
+    Some(match open {
+        '"' | '\'' | '`' => open,
+        '“' => '”',
+        '„' => '“',
+        '‘' => '’',
+        '«' => '»',
+        '「' => '」',
+        '『' => '』',
+        _ => return None,
+    })
+}
+
+/// Splits `"quoted text" — reason` into the quote and the reason.
+///
+/// The quote may itself hold dashes, so it ends at the first closing mark
+/// followed by a separator. Unquoted text is split at the first separator.
+fn split_quoted(content: &str) -> (String, String) {
+    let content = content.trim_start_matches('*');
+    if let Some(open) = content.chars().next()
+        && let Some(close) = closing_quote(open)
+    {
+        let quoted = &content[open.len_utf8()..];
+        for (end, _) in quoted.match_indices(close) {
+            let rest = quoted[end + close.len_utf8()..]
+                .trim_start_matches('*')
+                .trim_start();
+            if rest.is_empty() || rest.starts_with(['—', '–', '-', ':', '：', '(']) {
+                let reason = rest.trim_start_matches(['—', '–', '-', ':', '：']).trim();
+                let reason = reason
+                    .strip_prefix('(')
+                    .and_then(|reason| reason.strip_suffix(')'))
+                    .unwrap_or(reason);
+                return (quoted[..end].trim().to_string(), reason.trim().to_string());
+            }
         }
     }
+    split_gloss(content)
+}
 
-    /// Translates text to the target language using streaming.
-    /// Checks cache first before making API call.
-    ///
-    /// # Arguments
-    ///
-    /// * `text` - The source text to translate
-    /// * `target_language` - The target language name
-    /// * `enable_keyword_analysis` - Whether to enable keyword analysis
-    ///
-    /// # Returns
-    ///
-    /// A receiver channel that yields streaming chunks of the translation
-    pub fn translate(
-        &self,
-        text: String,
-        target_language: String,
-        enable_keyword_analysis: bool,
-    ) -> tokio::sync::mpsc::UnboundedReceiver<Result<String>> {
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+/// Parses the numbered list of a confidence check (`1. "text" — reason`).
+///
+/// Lines that don't follow the format are skipped, so an empty or malformed
+/// answer gives no spans rather than wrong ones.
+fn parse_uncertain_spans(response: &str) -> Vec<UncertainSpan> {
+    let mut spans: Vec<UncertainSpan> = Vec::new();
+    for line in response.lines() {
+        let Some((_, content)) = split_numbered(line.trim()) else {
+            continue;
+        };
+        let (text, reason) = split_quoted(content);
+        if text.is_empty() || spans.iter().any(|span| span.text == text) {
+            continue;
+        }
+        spans.push(UncertainSpan { text, reason });
+        if spans.len() == MAX_UNCERTAIN_SPANS {
+            break;
+        }
+    }
+    spans
+}
 
-        tracing::info!(
-            target_language = %target_language,
-            text_length = text.len(),
-            enable_keyword_analysis = %enable_keyword_analysis,
-            "Starting translation"
-        );
+/// The stretches of `translation` a confidence check answered with
+/// `response` points out, none if the answer can't be read.
+pub fn parse_confidence_check(response: &str, translation: &str) -> Vec<LowConfidence> {
+    locate_uncertain(translation, &parse_uncertain_spans(response))
+}
 
-        // Check cache based on current keyword analysis setting
-        // Cache key includes source text, target language, and keyword analysis bool
-        let cache = self.cache.clone();
-        if let Some((cached_translation, cached_keyword_analysis)) =
-            cache.get(&text, &target_language, enable_keyword_analysis)
+/// Finds the spans of a confidence check in `translation`.
+///
+/// Spans are looked up like alignment answers, so one quoted with other
+/// marks or in other case is still found; spans that aren't there, or
+/// overlap one found before, are left out. The result is in text order.
+fn locate_uncertain(translation: &str, spans: &[UncertainSpan]) -> Vec<LowConfidence> {
+    let mut found: Vec<LowConfidence> = Vec::new();
+    for span in spans {
+        let Some(alignment) = alignment::locate(translation, &span.text) else {
+            tracing::debug!("Uncertain span not found in the translation");
+            continue;
+        };
+        let range = alignment.range;
+        if found
+            .iter()
+            .any(|other| other.range.start < range.end && range.start < other.range.end)
         {
-            tracing::info!("Using cached translation");
-            // Send cached result in chunks to simulate streaming
-            let _ = tx.send(Ok(cached_translation));
-            if let Some(keyword_analysis) = cached_keyword_analysis {
-                let _ = tx.send(Ok(keyword_analysis));
+            continue;
+        }
+        found.push(LowConfidence {
+            range,
+            reason: span.reason.clone(),
+        });
+    }
+    found.sort_by_key(|low| low.range.start);
+    found
+}
+
+/// Splits a numbered line such as `2. text` or `2) text` into its number and content.
+fn split_numbered(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim_start_matches("**");
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let number = line[..digits].parse().ok()?;
+    let rest = line[digits..].strip_prefix(['.', ')', '、', '．', ':'])?;
+    let rest = rest.trim().trim_start_matches("**").trim();
+    (!rest.is_empty()).then_some((number, rest))
+}
+
+/// Strips markdown emphasis and quotes the model may wrap a translation in.
+fn clean_alternative_text(text: &str) -> String {
+    text.trim()
+        .trim_matches(|c: char| matches!(c, '*' | '"' | '“' | '”' | '「' | '」'))
+        .trim()
+        .to_string()
+}
+
+/// Splits the content of a numbered line into the translation and its gloss.
+fn split_gloss(content: &str) -> (String, String) {
+    for separator in [" — ", " – ", " - ", "—", "："] {
+        if let Some((text, gloss)) = content.split_once(separator) {
+            return (clean_alternative_text(text), gloss.trim().to_string());
+        }
+    }
+    // "text (gloss)"
+    if let Some(stripped) = content.strip_suffix(')')
+        && let Some((text, gloss)) = stripped.rsplit_once(" (")
+    {
+        return (clean_alternative_text(text), gloss.trim().to_string());
+    }
+    (clean_alternative_text(content), String::new())
+}
+
+/// Parses a numbered list of alternatives (`1. text — gloss`).
+///
+/// Tolerates preambles, markdown emphasis, other separators, and glosses on
+/// the following line. If the response does not follow the format at all,
+/// the whole response is returned as a single translation without a gloss.
+fn parse_alternatives(response: &str) -> Vec<Alternative> {
+    let mut alternatives: Vec<Alternative> = Vec::new();
+
+    for line in response.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some((number, content)) = split_numbered(trimmed) {
+            // Numbering must run 1, 2, 3…, anything else is not our list
+            if number != alternatives.len() + 1 {
+                break;
             }
-            let _ = tx.send(Ok(String::new())); // Signal completion
-            return rx;
+            let (text, gloss) = split_gloss(content);
+            alternatives.push(Alternative { text, gloss });
+        } else if let Some(last) = alternatives.last_mut()
+            && last.gloss.is_empty()
+            && (line.starts_with(char::is_whitespace) || trimmed.starts_with(['—', '–', '-', '(']))
+        {
+            // Gloss on its own (indented or dashed) line
+            last.gloss = trimmed
+                .trim_start_matches(['—', '–', '-', '('])
+                .trim_end_matches(')')
+                .trim()
+                .to_string();
+        }
+    }
+
+    alternatives.retain(|alternative| !alternative.text.is_empty());
+    if alternatives.is_empty() {
+        let text = response.trim();
+        if text.is_empty() {
+            return Vec::new();
         }
+        return vec![Alternative {
+            text: text.to_string(),
+            gloss: String::new(),
+        }];
+    }
+
+    let mut seen = Vec::new();
+    alternatives.retain(|alternative| {
+        let key = alternative.text.to_lowercase();
+        let duplicate = seen.contains(&key);
+        seen.push(key);
+        !duplicate
+    });
+    alternatives.truncate(MAX_ALTERNATIVES);
+    alternatives
+}
+
+/// Formats alternatives in the numbered format understood by [`parse_alternatives`].
+fn format_alternatives(alternatives: &[Alternative]) -> String {
+    alternatives
+        .iter()
+        .enumerate()
+        .map(|(i, alternative)| {
+            if alternative.gloss.is_empty() {
+                format!("{}. {}", i + 1, alternative.text)
+            } else {
+                format!("{}. {} — {}", i + 1, alternative.text, alternative.gloss)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-        // Build messages with system prompt
-        let mut messages = Vec::new();
+/// Builds the system and user messages of a plain translation request.
+fn translation_messages(
+    text: &str,
+    target_language: &str,
+    enable_keyword_analysis: bool,
+    context: &PromptContext,
+) -> Vec<ChatMessage> {
+    // Build messages with system prompt
+    let mut messages = Vec::new();
 
-        // Always use a system prompt for better translation quality
-        let system_prompt = if enable_keyword_analysis {
-            "You are a senior professional translator with deep expertise across multiple domains including technology, science, business, and academia.
+    // Always use a system prompt for better translation quality
+    let system_prompt = if enable_keyword_analysis {
+        "You are a senior professional translator with deep expertise across multiple domains including technology, science, business, and academia.
 
 ## Core Task
 Translate the provided text to the target language while maintaining accuracy, fluency, and contextual appropriateness.
@@ -156,8 +583,8 @@ The API endpoint uses asynchronous processing to handle high-throughput requests
 - Avoid unnecessary complexity in definitions
 - List terms alphabetically
 - Maximum 5-7 terms per text (most important ones only)"
-        } else {
-            "You are a professional translator with native-level proficiency in both source and target languages.
+    } else {
+        "You are a professional translator with native-level proficiency in both source and target languages.
 
 ## Core Task
 Translate the provided text to the target language with the highest possible accuracy and naturalness.
@@ -189,61 +616,2579 @@ Translate the provided text to the target language with the highest possible acc
 
 ## Output Format
 Provide ONLY the translated text with NO additional commentary, explanations, or ANY formatting markers including brackets like [Translation]. Do NOT include any section headers, labels, or structural markers. Output ONLY the pure translated text."
-        };
+    };
 
-        messages.push(ChatMessage {
-            role: "system".to_string(),
-            content: system_prompt.to_string(),
-        });
+    // Text extracted from a PDF keeps its page breaks
+    let page_section = if text.lines().any(pdf::is_page_marker) {
+        "\n\n## Page Markers\nLines such as \"— page 3 —\" mark where a page of the original document begins. Copy each of them unchanged, on its own line, at the same place in the translation."
+    } else {
+        ""
+    };
 
-        let user_prompt = format!(
-            "Translate the following text to {}:\n\n{}",
-            target_language, text
-        );
+    // Inline code and links masked by `translation_filters`
+    let placeholder_section = match (
+        filter::has_placeholders(text),
+        filter::has_url_placeholders(text),
+    ) {
+        (true, true) => {
+            "\n\n## Placeholders\nMarkers such as ⟦C1⟧ stand for inline code, markers such as ⟦U1⟧ for links and email addresses. Copy each of them unchanged to the matching place in the translation."
+        }
+        (true, false) => {
+            "\n\n## Placeholders\nMarkers such as ⟦C1⟧ stand for inline code. Copy each of them unchanged to the matching place in the translation."
+        }
+        (false, true) => {
+            "\n\n## Placeholders\nMarkers such as ⟦U1⟧ stand for links and email addresses. Copy each of them unchanged to the matching place in the translation."
+        }
+        (false, false) => "",
+    };
 
-        messages.push(ChatMessage {
-            role: "user".to_string(),
-            content: user_prompt,
-        });
+    messages.push(ChatMessage {
+        role: Role::System,
+        content: format!(
+            "{}{}{}{}",
+            system_prompt,
+            page_section,
+            placeholder_section,
+            context.system_section()
+        ),
+    });
 
-        let client = self.client.clone();
-        let cache_for_storage = cache.clone();
-        let text_for_cache = text.clone();
-        let lang_for_cache = target_language.clone();
-        let enable_keyword_analysis_for_cache = enable_keyword_analysis;
+    let user_prompt = format!(
+        "Translate the following text to {}:\n\n{}",
+        target_language, text
+    );
 
-        tokio::spawn(async move {
-            let mut stream_rx = client.stream_chat(messages).await;
-            let mut full_response = String::new();
+    messages.push(ChatMessage {
+        role: Role::User,
+        content: user_prompt,
+    });
 
-            while let Some(result) = stream_rx.recv().await {
-                match &result {
-                    Ok(chunk) if !chunk.is_empty() => {
-                        full_response.push_str(chunk);
-                    }
-                    _ => {}
+    messages
+}
+
+/// Masks the inline code and links of `text` and builds the filters
+/// cleaning up its translation.
+///
+/// # Returns
+///
+/// The text to send to the model and the filters for the response
+fn translation_filters(text: &str) -> (String, FilterChain) {
+    let (masked, placeholders) = mask_source(text);
+    let filters = FilterChain::default()
+        .with(PreambleFilter::for_source(text))
+        .with(placeholders);
+    (masked, filters)
+}
+
+/// Masks the code spans and links of `text`, returning the masked text and
+/// the filter putting them back.
+fn mask_source(text: &str) -> (String, PlaceholderFilter) {
+    let (masked, spans) = filter::mask_code_spans(text);
+    // Links inside code spans are already masked with them
+    let (masked, urls) = filter::mask_urls(&masked);
+    (masked, PlaceholderFilter::new(spans).with_urls(urls))
+}
+
+/// Cache scope of a translation made through `pivot_language`, kept apart
+/// from direct translations into the same language.
+fn pivot_cache_scope(
+    context: &PromptContext,
+    target_language: &str,
+    pivot_language: &str,
+) -> String {
+    format!(
+        "{}|pivot={}",
+        context.cache_scope(target_language),
+        pivot_language
+    )
+}
+
+/// Translator service for handling translation requests.
+#[derive(Clone)]
+pub struct Translator {
+    client: ApiClient,
+    cache: Arc<TranslationCache>,
+    /// Consecutive repeats after which a response is stopped, `None` to
+    /// never stop one
+    max_repeats: Option<usize>,
+    /// Shares a response between identical requests made at the same time
+    coalescer: Coalescer,
+}
+
+impl Translator {
+    /// Creates a new translator with the given API key and cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `api_key` - The Z.AI API key for authentication
+    /// * `cache` - Translation cache for storing/retrieving translations
+    pub fn new(api_key: String, cache: Arc<TranslationCache>) -> Self {
+        tracing::info!("Creating translator with API key");
+        Translator::with_client(ApiClient::new(api_key), cache)
+    }
+
+    /// Creates a translator that sends its requests through `client`.
+    pub fn with_client(client: ApiClient, cache: Arc<TranslationCache>) -> Self {
+        Translator {
+            client,
+            cache,
+            max_repeats: Some(DEFAULT_MAX_REPEATS),
+            coalescer: Coalescer::default(),
+        }
+    }
+
+    /// Shares responses with the translators given clones of `coalescer`,
+    /// instead of only with clones of this one.
+    pub fn with_coalescer(mut self, coalescer: Coalescer) -> Self {
+        self.coalescer = coalescer;
+        self
+    }
+
+    /// Limits the length of responses, `None` for the provider default.
+    pub fn with_max_tokens(mut self, max_tokens: Option<u32>) -> Self {
+        self.client = self.client.with_max_tokens(max_tokens);
+        self
+    }
+
+    /// Samples responses at `temperature`, `None` for the provider default.
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.client = self.client.with_temperature(temperature);
+        self
+    }
+
+    /// Sends requests to the API under `base_url` instead of the default
+    /// endpoint.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.client = self.client.with_base_url(base_url);
+        self
+    }
+
+    /// Sends requests to `model` instead of the default model.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.client = self.client.with_model(model);
+        self
+    }
+
+    /// Reads responses in one piece instead of streaming them when
+    /// `streaming` is false.
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.client = self.client.with_streaming(streaming);
+        self
+    }
+
+    /// Decodes escape sequences the provider leaves in the content of
+    /// responses when `unescape` is true.
+    pub fn with_unescape_content(mut self, unescape: bool) -> Self {
+        self.client = self.client.with_unescape_content(unescape);
+        self
+    }
+
+    /// The model requests are sent to.
+    pub fn model(&self) -> &str {
+        self.client.model()
+    }
+
+    /// Drops the model's reasoning as soon as it is read when `keep` is
+    /// false.
+    pub fn with_reasoning(mut self, keep: bool) -> Self {
+        self.client = self.client.with_reasoning(keep);
+        self
+    }
+
+    /// Fails requests the provider doesn't answer, or stops sending more
+    /// of the response for, within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = self.client.with_timeout(timeout);
+        self
+    }
+
+    /// Sends requests failing with a transient error again, as `retry`
+    /// allows.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.client = self.client.with_retry(retry);
+        self
+    }
+
+    /// Where responses send their reasoning and usage.
+    pub fn notes(&self) -> &NoteSlot {
+        self.client.notes()
+    }
+
+    /// Stops responses once they repeat the same text more than
+    /// `max_repeats` times in a row, `None` to let them run.
+    pub fn with_repetition_limit(mut self, max_repeats: Option<usize>) -> Self {
+        self.max_repeats = max_repeats;
+        self
+    }
+
+    /// Whether [`Self::translate`] would answer from the legacy namespace
+    /// of the cache, with a translation possibly made by another model.
+    pub fn cached_in_legacy(
+        &self,
+        text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+        context: &PromptContext,
+    ) -> bool {
+        self.cache
+            .lookup(
+                text,
+                &context.cache_scope(target_language),
+                enable_keyword_analysis,
+            )
+            .is_some_and(|hit| hit.legacy)
+    }
+
+    /// Capacity of the channels translations are streamed through.
+    pub fn stream_capacity(&self) -> usize {
+        self.client.stream_capacity()
+    }
+
+    /// Translates text to the target language using streaming.
+    /// Checks cache first before making API call.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source text to translate
+    /// * `target_language` - The target language name
+    /// * `enable_keyword_analysis` - Whether to enable keyword analysis
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// A receiver channel that yields streaming chunks of the translation
+    pub fn translate(
+        &self,
+        text: String,
+        target_language: String,
+        enable_keyword_analysis: bool,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+
+        tracing::info!(
+            target_language = %target_language,
+            text_length = text.len(),
+            enable_keyword_analysis = %enable_keyword_analysis,
+            thinking = thinking.as_str(),
+            domain = %context.domain,
+            audience = %context.audience,
+            "Starting translation"
+        );
+
+        // Check cache based on current keyword analysis setting
+        // Cache key includes source text, target language (scoped to the prompt hints),
+        // and keyword analysis bool
+        let cache_language = context.cache_scope(&target_language);
+        match self
+            .cache
+            .try_lookup(&text, &cache_language, enable_keyword_analysis)
+        {
+            Ok(Some(hit)) => {
+                tracing::info!("Using cached translation");
+                // The fresh channel has room for all of them, so `try_send`
+                // never fails here
+                for chunk in cached_chunks(hit.translation, hit.keyword_analysis) {
+                    let _ = tx.try_send(Ok(chunk));
                 }
-                let _ = tx.send(result);
+                return rx;
             }
-
-            // Store in cache after successful translation
-            if !full_response.is_empty() {
-                let (translation, keyword_analysis) = parse_translation_and_keywords(
-                    &full_response,
-                    enable_keyword_analysis_for_cache,
-                );
-                cache_for_storage.set(
-                    &text_for_cache,
-                    &lang_for_cache,
-                    enable_keyword_analysis_for_cache,
-                    translation,
-                    keyword_analysis,
-                );
+            Ok(None) => {}
+            Err(CacheBusy) => {
+                // A write holds the shard, look up on the runtime instead of
+                // waiting for it here
+                let translator = self.clone();
+                tokio::spawn(async move {
+                    let cached = translator
+                        .cache
+                        .get_async(&text, &cache_language, enable_keyword_analysis)
+                        .await;
+                    let mut stream = match cached {
+                        Some((translation, keyword_analysis)) => {
+                            tracing::info!("Using cached translation");
+                            for chunk in cached_chunks(translation, keyword_analysis) {
+                                let _ = tx.send(Ok(chunk)).await;
+                            }
+                            return;
+                        }
+                        None => translator.request_translation(
+                            text,
+                            target_language,
+                            cache_language,
+                            enable_keyword_analysis,
+                            thinking,
+                            &context,
+                        ),
+                    };
+                    // Dropping `stream` once the receiver is gone aborts the request
+                    loop {
+                        let result = tokio::select! {
+                            result = stream.recv() => result,
+                            _ = tx.closed() => None,
+                        };
+                        let Some(result) = result else {
+                            break;
+                        };
+                        if tx.send(result).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                return rx;
             }
+        }
+        self.request_translation(
+            text,
+            target_language,
+            cache_language,
+            enable_keyword_analysis,
+            thinking,
+            &context,
+        )
+    }
 
-            tracing::debug!("Translation stream completed");
-        });
-
-        rx
+    /// Streams a translation of `text` from the provider.
+    fn request_translation(
+        &self,
+        text: String,
+        target_language: String,
+        cache_language: String,
+        enable_keyword_analysis: bool,
+        thinking: ThinkingMode,
+        context: &PromptContext,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        // The same text may already be on its way, e.g. from another tab or
+        // a pivot stage
+        let key = self
+            .cache
+            .key(&text, &cache_language, enable_keyword_analysis);
+        let followers = match self.coalescer.join(&key, self.client.stream_capacity()) {
+            Joined::Leader(followers) => followers,
+            Joined::Follower(rx) => return rx,
+        };
+        let (masked, filters) = translation_filters(&text);
+        let messages =
+            translation_messages(&masked, &target_language, enable_keyword_analysis, context);
+        self.stream_translation(
+            messages,
+            filters,
+            thinking,
+            text,
+            cache_language,
+            enable_keyword_analysis,
+            String::new(),
+            followers,
+        )
+    }
+
+    /// Continues a translation that stopped at the output limit.
+    ///
+    /// `partial` is sent as the start of the model's answer, which it
+    /// resumes. Only the continuation is streamed. Once it completes,
+    /// `partial` and the continuation are cached together as the translation
+    /// of `text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `partial` - Everything translated so far
+    pub fn continue_translation(
+        &self,
+        text: String,
+        target_language: String,
+        enable_keyword_analysis: bool,
+        thinking: ThinkingMode,
+        context: PromptContext,
+        partial: String,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        tracing::info!(
+            partial_length = partial.len(),
+            "Continuing truncated translation"
+        );
+
+        let (masked, placeholders) = mask_source(&text);
+        let mut messages =
+            translation_messages(&masked, &target_language, enable_keyword_analysis, &context);
+        // Prefilled as the start of the answer, so the model resumes
+        // the text instead of translating it again. It is masked like the
+        // source, so the model keeps writing placeholders.
+        messages.push(ChatMessage {
+            role: Role::Assistant,
+            content: placeholders.mask(&partial),
+        });
+        // The continuation starts mid-text, so it has no preamble to drop
+        let filters = FilterChain::default().with(placeholders);
+
+        let cache_language = context.cache_scope(&target_language);
+        self.stream_translation(
+            messages,
+            filters,
+            thinking,
+            text,
+            cache_language,
+            enable_keyword_analysis,
+            partial,
+            Followers::none(),
+        )
+    }
+
+    /// Explains the word choices, grammar and idioms of a translation.
+    ///
+    /// The explanation is cached under its own key, made of the source text
+    /// and the translation, so it never replaces the cached translation.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source text
+    /// * `translation` - The translation to explain
+    /// * `target_language` - The language `translation` is in
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    ///
+    /// # Returns
+    ///
+    /// A receiver channel that yields streaming chunks of the explanation
+    pub fn explain(
+        &self,
+        text: String,
+        translation: String,
+        target_language: String,
+        thinking: ThinkingMode,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        tracing::info!(
+            target_language = %target_language,
+            text_length = text.len(),
+            translation_length = translation.len(),
+            "Starting explanation"
+        );
+
+        let cache_text = explanation_cache_key(&text, &translation);
+        if let Some((cached, _)) = self.cache.get(&cache_text, EXPLANATION_LANGUAGE, false) {
+            tracing::info!("Using cached explanation");
+            let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+            let _ = tx.try_send(Ok(cached));
+            let _ = tx.try_send(Ok(String::new()));
+            return rx;
+        }
+
+        let messages = explanation_messages(&text, &translation, &target_language);
+        self.stream_translation(
+            messages,
+            FilterChain::default(),
+            thinking,
+            cache_text,
+            EXPLANATION_LANGUAGE.to_string(),
+            false,
+            String::new(),
+            Followers::none(),
+        )
+    }
+
+    /// Asks which stretches of a translation the model is least sure of.
+    ///
+    /// The raw answer is streamed and cached like an explanation, under its
+    /// own key; [`parse_confidence_check`] turns it into spans.
+    pub fn check_confidence(
+        &self,
+        text: String,
+        translation: String,
+        target_language: String,
+        thinking: ThinkingMode,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        tracing::info!(
+            target_language = %target_language,
+            translation_length = translation.len(),
+            "Starting confidence check"
+        );
+
+        let cache_text = confidence_cache_key(&text, &translation);
+        if let Some((cached, _)) = self.cache.get(&cache_text, EXPLANATION_LANGUAGE, false) {
+            tracing::info!("Using cached confidence check");
+            let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+            let _ = tx.try_send(Ok(cached));
+            let _ = tx.try_send(Ok(String::new()));
+            return rx;
+        }
+
+        let messages = confidence_messages(&text, &translation, &target_language);
+        self.stream_translation(
+            messages,
+            FilterChain::default(),
+            thinking,
+            cache_text,
+            EXPLANATION_LANGUAGE.to_string(),
+            false,
+            String::new(),
+            Followers::none(),
+        )
+    }
+
+    /// Asks which phrase of `text` was translated as `word` of `translation`.
+    ///
+    /// The answer is short, so it is collected rather than streamed, and it
+    /// is not cached here. Dropping the future closes the request.
+    ///
+    /// # Returns
+    ///
+    /// The model's answer, which should be a substring of `text`
+    pub async fn align(&self, text: &str, translation: &str, word: &str) -> Result<String> {
+        tracing::info!(word_length = word.len(), "Starting alignment");
+        let mut rx = self
+            .client
+            .stream_chat(
+                alignment_messages(text, translation, word),
+                ThinkingMode::Disabled,
+            )
+            .await;
+        let mut answer = String::new();
+        loop {
+            match rx.recv().await {
+                Some(Ok(chunk)) if chunk.is_empty() => return Ok(answer),
+                Some(Ok(chunk)) => answer.push_str(&chunk),
+                Some(Err(e)) => return Err(e),
+                None => {
+                    return Err(TranslationError::StreamError(
+                        "The response ended unexpectedly".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Romanizes the Chinese characters and kana of `text` by `scheme`,
+    /// keeping everything else, such as Latin words, as it is.
+    ///
+    /// The result is cached under the text and scheme.
+    pub async fn romanize(&self, text: &str, scheme: Scheme) -> Result<String> {
+        let cache_text = romanization_cache_key(text, scheme);
+        if let Some((cached, _)) = self.cache.get_async(&cache_text, scheme.key(), false).await {
+            tracing::info!("Using cached romanization");
+            return Ok(cached);
+        }
+        let runs = romanize::split_runs(text);
+        let cjk_runs = romanize::cjk_runs(&runs);
+        if cjk_runs.is_empty() {
+            return Ok(text.to_string());
+        }
+
+        tracing::info!(
+            runs = cjk_runs.len(),
+            scheme = scheme.key(),
+            "Starting romanization"
+        );
+        let mut rx = self
+            .client
+            .stream_chat(
+                romanization_messages(&cjk_runs, scheme),
+                ThinkingMode::Disabled,
+            )
+            .await;
+        let mut answer = String::new();
+        loop {
+            match rx.recv().await {
+                Some(Ok(chunk)) if chunk.is_empty() => break,
+                Some(Ok(chunk)) => answer.push_str(&chunk),
+                Some(Err(e)) => return Err(e),
+                None => {
+                    return Err(TranslationError::StreamError(
+                        "The response ended unexpectedly".to_string(),
+                    ));
+                }
+            }
+        }
+
+        let mut lines: Vec<(usize, String)> = answer
+            .lines()
+            .filter_map(|line| split_numbered(line.trim()))
+            .map(|(number, content)| (number, content.trim().to_string()))
+            .collect();
+        lines.sort_by_key(|(number, _)| *number);
+        let romanized: Vec<String> = lines.into_iter().map(|(_, content)| content).collect();
+        let romanization = romanize::assemble(&runs, &romanized).ok_or_else(|| {
+            TranslationError::TranslationFailed(format!(
+                "the romanization has {} lines instead of {}",
+                romanized.len(),
+                cjk_runs.len()
+            ))
+        })?;
+        self.cache
+            .set_async(&cache_text, scheme.key(), false, romanization.clone(), None)
+            .await;
+        Ok(romanization)
+    }
+
+    /// Streams a translation and caches it once the response is complete.
+    ///
+    /// The response is passed through `filters`, and what they hold back is
+    /// flushed before the completion signal or an error. `prefix` is output
+    /// from earlier requests that the response continues; it is cached
+    /// together with the response. Truncated or failed responses are never
+    /// cached, and neither are refusals: a response that reads like the model
+    /// declining the request ends with [`TranslationError::ContentFiltered`]
+    /// instead of the completion signal.
+    ///
+    /// A response that gets stuck repeating itself is stopped, closing the
+    /// provider stream, and ends with [`TranslationError::RepetitionDetected`].
+    /// Once the receiver is dropped, e.g. by a cancelled session, and no
+    /// other request follows the response, the request is aborted.
+    ///
+    /// Everything sent to the receiver is passed on to `followers` too.
+    #[allow(clippy::too_many_arguments)]
+    fn stream_translation(
+        &self,
+        messages: Vec<ChatMessage>,
+        mut filters: FilterChain,
+        thinking: ThinkingMode,
+        text: String,
+        cache_language: String,
+        enable_keyword_analysis: bool,
+        prefix: String,
+        mut followers: Followers,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+        let client = self.client.clone();
+        let cache = self.cache.clone();
+        let mut detector = self.max_repeats.map(RepetitionDetector::new);
+
+        tokio::spawn(async move {
+            let mut stream_rx = client.stream_chat(messages, thinking).await;
+            let mut full_response = prefix;
+
+            loop {
+                let result = tokio::select! {
+                    result = stream_rx.recv() => result,
+                    _ = tx.closed(), if !followers.is_followed() => {
+                        tracing::info!("Translation abandoned, aborting the request");
+                        stream_rx.abort();
+                        break;
+                    }
+                };
+                let Some(result) = result else {
+                    break;
+                };
+                match result {
+                    Ok(chunk) if !chunk.is_empty() => {
+                        if let Some(text) = filters.process(&chunk) {
+                            full_response.push_str(&text);
+                            let repeated_chars =
+                                detector.as_mut().and_then(|detector| detector.push(&text));
+                            let chunk = Ok(text);
+                            followers.pass(&chunk);
+                            let _ = tx.send(chunk).await;
+                            if let Some(repeated_chars) = repeated_chars {
+                                tracing::warn!(
+                                    repeated_chars,
+                                    "Response stuck in a loop, stopping it"
+                                );
+                                let end =
+                                    Err(TranslationError::RepetitionDetected { repeated_chars });
+                                followers.pass(&end);
+                                let _ = tx.send(end).await;
+                                // Dropping the stream closes the request
+                                break;
+                            }
+                        }
+                    }
+                    result => {
+                        // Completion or error, release what the filters hold back first
+                        if let Some(text) = filters.finish() {
+                            full_response.push_str(&text);
+                            let chunk = Ok(text);
+                            followers.pass(&chunk);
+                            let _ = tx.send(chunk).await;
+                        }
+                        let result = match result {
+                            Ok(_) if is_refusal(&text, &full_response) => {
+                                tracing::warn!(
+                                    reason = "refusal text",
+                                    "Provider declined the content"
+                                );
+                                Err(TranslationError::ContentFiltered(
+                                    "refusal text".to_string(),
+                                ))
+                            }
+                            result => result,
+                        };
+                        // Cached before the completion signal, so it is there
+                        // as soon as the receiver sees the translation finish
+                        if result.is_ok() && !full_response.is_empty() {
+                            let (translation, keyword_analysis) = parse_translation_and_keywords(
+                                &full_response,
+                                enable_keyword_analysis,
+                            );
+                            cache
+                                .set_async(
+                                    &text,
+                                    &cache_language,
+                                    enable_keyword_analysis,
+                                    translation,
+                                    keyword_analysis,
+                                )
+                                .await;
+                        }
+                        followers.pass(&result);
+                        let _ = tx.send(result).await;
+                    }
+                }
+            }
+
+            tracing::debug!("Translation stream completed");
+        });
+
+        rx
+    }
+
+    /// Translates through a pivot language, `text` into `pivot_language` and
+    /// that into `target_language`.
+    ///
+    /// Only the second stage is streamed. Each stage is looked up in and
+    /// stored to the cache like any translation, and the combined result is
+    /// also cached under the real pair, scoped with the pivot language. The
+    /// pivot text is sent on the oneshot receiver just before the completion
+    /// signal. Errors are wrapped in [`TranslationError::PivotStage`] to say
+    /// which stage failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source text to translate
+    /// * `pivot_language` - The language translated through
+    /// * `target_language` - The target language name
+    /// * `enable_keyword_analysis` - Whether to enable keyword analysis in the second stage
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// The stream of the second stage and a receiver for the pivot text
+    pub fn translate_pivot(
+        &self,
+        text: String,
+        pivot_language: String,
+        target_language: String,
+        enable_keyword_analysis: bool,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> (
+        tokio::sync::mpsc::Receiver<Result<String>>,
+        oneshot::Receiver<String>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+        let (pivot_tx, pivot_rx) = oneshot::channel();
+
+        tracing::info!(
+            pivot_language = %pivot_language,
+            target_language = %target_language,
+            text_length = text.len(),
+            "Starting translation through a pivot language"
+        );
+
+        let cache_language = pivot_cache_scope(&context, &target_language, &pivot_language);
+        if let Some((cached_translation, cached_keyword_analysis)) =
+            self.cache
+                .get(&text, &cache_language, enable_keyword_analysis)
+        {
+            tracing::info!("Using cached pivot translation");
+            let _ = tx.try_send(Ok(cached_translation));
+            if let Some(keyword_analysis) = cached_keyword_analysis {
+                let _ = tx.try_send(Ok(keyword_analysis));
+            }
+            // The first stage is cached on its own and may have been evicted
+            if let Some((pivot_text, _)) =
+                self.cache
+                    .get(&text, &context.cache_scope(&pivot_language), false)
+            {
+                let _ = pivot_tx.send(pivot_text);
+            }
+            let _ = tx.try_send(Ok(String::new()));
+            return (rx, pivot_rx);
+        }
+
+        let mut first_stage = self.translate(
+            text.clone(),
+            pivot_language.clone(),
+            false,
+            thinking,
+            context.clone(),
+        );
+        let translator = self.clone();
+
+        tokio::spawn(async move {
+            let stage_error = |stage: String, e: TranslationError| TranslationError::PivotStage {
+                stage,
+                source: Box::new(e),
+            };
+            let ended_early =
+                || TranslationError::StreamError("The response ended unexpectedly".to_string());
+
+            let first = format!("Stage 1 (into {})", pivot_language);
+            let mut pivot_text = String::new();
+            loop {
+                match first_stage.recv().await {
+                    Some(Ok(chunk)) if chunk.is_empty() => break,
+                    Some(Ok(chunk)) => pivot_text.push_str(&chunk),
+                    Some(Err(e)) => {
+                        let _ = tx.send(Err(stage_error(first, e))).await;
+                        return;
+                    }
+                    None => {
+                        let _ = tx.send(Err(stage_error(first, ended_early()))).await;
+                        return;
+                    }
+                }
+            }
+            tracing::debug!(
+                pivot_length = pivot_text.len(),
+                "First pivot stage completed"
+            );
+
+            let second = format!("Stage 2 ({} into {})", pivot_language, target_language);
+            let mut second_stage = translator.translate(
+                pivot_text.clone(),
+                target_language,
+                enable_keyword_analysis,
+                thinking,
+                context,
+            );
+            let mut full_response = String::new();
+            loop {
+                match second_stage.recv().await {
+                    Some(Ok(chunk)) if chunk.is_empty() => break,
+                    Some(Ok(chunk)) => {
+                        full_response.push_str(&chunk);
+                        let _ = tx.send(Ok(chunk)).await;
+                    }
+                    Some(Err(e)) => {
+                        let _ = tx.send(Err(stage_error(second, e))).await;
+                        return;
+                    }
+                    None => {
+                        let _ = tx.send(Err(stage_error(second, ended_early()))).await;
+                        return;
+                    }
+                }
+            }
+
+            if !full_response.is_empty() {
+                let (translation, keyword_analysis) =
+                    parse_translation_and_keywords(&full_response, enable_keyword_analysis);
+                translator.cache.set(
+                    &text,
+                    &cache_language,
+                    enable_keyword_analysis,
+                    translation,
+                    keyword_analysis,
+                );
+            }
+            let _ = pivot_tx.send(pivot_text);
+            let _ = tx.send(Ok(String::new())).await;
+            tracing::debug!("Pivot translation completed");
+        });
+
+        (rx, pivot_rx)
+    }
+
+    /// Translates a long text chunk by chunk, keeping its terms consistent.
+    ///
+    /// The chunks of `chunker` are translated one after another like any
+    /// translation, so each is looked up in and stored to the cache on its
+    /// own. The terms read from the finished chunks by [`TermConsistency`]
+    /// go into the prompt of each next chunk that has them, next to the
+    /// glossary terms of `context`. Whitespace around a chunk is kept from
+    /// the source. Just before the completion signal, the terms that still
+    /// got more than one translation are sent on the oneshot receiver.
+    ///
+    /// Keyword analysis is not asked for, and an error in any chunk ends
+    /// the translation.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source text to translate
+    /// * `chunker` - How the text is split into requests
+    /// * `target_language` - The target language name
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// The stream of the joined translation and a receiver for the terms
+    /// translated inconsistently
+    pub fn translate_chunked(
+        &self,
+        text: String,
+        chunker: &TextChunker,
+        target_language: String,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> (
+        tokio::sync::mpsc::Receiver<Result<String>>,
+        oneshot::Receiver<Vec<Inconsistency>>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+        let (report_tx, report_rx) = oneshot::channel();
+        let chunks = chunker.chunks(&text);
+
+        tracing::info!(
+            target_language = %target_language,
+            text_length = text.len(),
+            chunks = chunks.len(),
+            "Starting chunked translation"
+        );
+
+        let glossary = TermMatcher::new(
+            context
+                .terms
+                .iter()
+                .map(|(source, target)| Term {
+                    source: source.clone(),
+                    target: target.clone(),
+                    language: target_language.clone(),
+                })
+                .collect(),
+        );
+        let mut consistency =
+            TermConsistency::new(DEFAULT_MAX_TERMS).with_glossary(Arc::new(glossary));
+        let translator = self.clone();
+
+        tokio::spawn(async move {
+            for (index, chunk) in chunks.iter().enumerate() {
+                let source = &text[chunk.range.clone()];
+                let trimmed = source.trim();
+                let leading = &source[..source.len() - source.trim_start().len()];
+                let trailing = &source[source.trim_end().len()..];
+                if trimmed.is_empty() {
+                    let _ = tx.send(Ok(source.to_string())).await;
+                    continue;
+                }
+                if !leading.is_empty() {
+                    let _ = tx.send(Ok(leading.to_string())).await;
+                }
+
+                let mut chunk_context = context.clone();
+                chunk_context.consistent_terms = consistency.prompt_terms(trimmed);
+                let mut stream = translator.translate(
+                    trimmed.to_string(),
+                    target_language.clone(),
+                    false,
+                    thinking,
+                    chunk_context,
+                );
+                let mut translation = String::new();
+                loop {
+                    // Dropping `stream` once the receiver is gone aborts the request
+                    let result = tokio::select! {
+                        result = stream.recv() => result,
+                        _ = tx.closed() => return,
+                    };
+                    match result {
+                        Some(Ok(part)) if part.is_empty() => break,
+                        Some(Ok(part)) => {
+                            translation.push_str(&part);
+                            let _ = tx.send(Ok(part)).await;
+                        }
+                        Some(Err(e)) => {
+                            let _ = tx.send(Err(e)).await;
+                            return;
+                        }
+                        None => {
+                            let _ = tx
+                                .send(Err(TranslationError::StreamError(
+                                    "The response ended unexpectedly".to_string(),
+                                )))
+                                .await;
+                            return;
+                        }
+                    }
+                }
+                tracing::debug!(chunk = index, "Chunk translated");
+
+                consistency.record(&text, chunk, &translation);
+                if !trailing.is_empty() {
+                    let _ = tx.send(Ok(trailing.to_string())).await;
+                }
+            }
+
+            let _ = report_tx.send(consistency.report());
+            let _ = tx.send(Ok(String::new())).await;
+            tracing::debug!("Chunked translation completed");
+        });
+
+        (rx, report_rx)
+    }
+
+    /// Translates a short, ambiguous input into up to three alternatives.
+    ///
+    /// The response is collected and parsed before anything is sent: the
+    /// first alternative is delivered as a single chunk on the returned
+    /// stream, and the full list on the oneshot receiver just before the
+    /// completion signal.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source text to translate
+    /// * `target_language` - The target language name
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// The stream of the primary translation and a receiver for the alternatives
+    pub fn translate_alternatives(
+        &self,
+        text: String,
+        target_language: String,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> (
+        tokio::sync::mpsc::Receiver<Result<String>>,
+        oneshot::Receiver<Vec<Alternative>>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+        let (alternatives_tx, alternatives_rx) = oneshot::channel();
+
+        tracing::info!(
+            target_language = %target_language,
+            text_length = text.len(),
+            "Starting translation with alternatives"
+        );
+
+        let cache_text = alternatives_cache_key(&text);
+        let cache_language = context.cache_scope(&target_language);
+        if let Some((cached, _)) = self.cache.get(&cache_text, &cache_language, false) {
+            tracing::info!("Using cached alternatives");
+            let alternatives = parse_alternatives(&cached);
+            if let Some(first) = alternatives.first() {
+                let _ = tx.try_send(Ok(first.text.clone()));
+            }
+            let _ = alternatives_tx.send(alternatives);
+            let _ = tx.try_send(Ok(String::new()));
+            return (rx, alternatives_rx);
+        }
+
+        let messages = vec![
+            ChatMessage {
+                role: Role::System,
+                content: format!(
+                    "You are a professional translator. The text to translate is a short word or phrase that may be ambiguous.
+
+## Core Task
+Give up to {} distinct translations into the target language, covering the most likely different meanings or usages. If the text is unambiguous, give only one.
+
+## Output Format
+One numbered line per translation, followed by an em dash and a one-line gloss in the target language explaining when it applies:
+1. <translation> — <gloss>
+2. <translation> — <gloss>
+
+Output ONLY the numbered lines, with no introduction or commentary.{}",
+                    MAX_ALTERNATIVES,
+                    context.system_section()
+                ),
+            },
+            ChatMessage {
+                role: Role::User,
+                content: format!(
+                    "Translate the following text to {}:\n\n{}",
+                    target_language, text
+                ),
+            },
+        ];
+
+        let client = self.client.clone();
+        let cache = self.cache.clone();
+
+        tokio::spawn(async move {
+            let mut stream_rx = client.stream_chat(messages, thinking).await;
+            let mut full_response = String::new();
+
+            while let Some(result) = stream_rx.recv().await {
+                match result {
+                    Ok(chunk) if chunk.is_empty() => break,
+                    Ok(chunk) => full_response.push_str(&chunk),
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+            }
+
+            let alternatives = parse_alternatives(&full_response);
+            tracing::debug!(count = alternatives.len(), "Parsed alternatives");
+            if let Some(first) = alternatives.first() {
+                cache.set(
+                    &cache_text,
+                    &cache_language,
+                    false,
+                    format_alternatives(&alternatives),
+                    None,
+                );
+                let _ = tx.send(Ok(first.text.clone())).await;
+            }
+
+            let _ = alternatives_tx.send(alternatives);
+            let _ = tx.send(Ok(String::new())).await;
+        });
+
+        (rx, alternatives_rx)
+    }
+
+    /// Makes the chosen alternative the cached translation of `text`.
+    ///
+    /// The plain translation entry is replaced, and the chosen alternative is
+    /// moved to the front of the cached alternatives so it is offered first
+    /// next time.
+    pub fn promote_alternative(
+        &self,
+        text: &str,
+        target_language: &str,
+        context: &PromptContext,
+        alternatives: &[Alternative],
+        index: usize,
+    ) {
+        if index >= alternatives.len() {
+            return;
+        }
+
+        let mut reordered = alternatives.to_vec();
+        let chosen = reordered.remove(index);
+        let cache_language = context.cache_scope(target_language);
+        self.cache
+            .set(text, &cache_language, false, chosen.text.clone(), None);
+        reordered.insert(0, chosen);
+        self.cache.set(
+            &alternatives_cache_key(text),
+            &cache_language,
+            false,
+            format_alternatives(&reordered),
+            None,
+        );
+    }
+
+    /// Translates only the comments and string literals of a code snippet.
+    ///
+    /// The translatable spans are sent as one batch of numbered segments and
+    /// spliced back into the code, which is delivered as a single chunk once
+    /// the response is complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source code
+    /// * `language` - The code's language, or `Auto` to detect it
+    /// * `target_language` - The target language name
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// A receiver channel that yields the reassembled code
+    pub fn translate_code(
+        &self,
+        text: String,
+        language: CodeLanguage,
+        target_language: String,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+
+        let language = match language {
+            CodeLanguage::Auto => code::detect_language(&text),
+            other => other,
+        };
+        let segments = code::extract_segments(&text, language);
+
+        tracing::info!(
+            target_language = %target_language,
+            language = language.label(),
+            segments = segments.len(),
+            "Starting code translation"
+        );
+
+        if segments.is_empty() {
+            tracing::info!("No comments or strings to translate");
+            let _ = tx.try_send(Ok(text));
+            let _ = tx.try_send(Ok(String::new()));
+            return rx;
+        }
+
+        // Namespace the cache entry so it never collides with a plain translation
+        let cache_text = format!("[code:{}]\n{}", language.label(), text);
+        let cache_language = context.cache_scope(&target_language);
+        if let Some((cached, _)) = self.cache.get(&cache_text, &cache_language, false) {
+            tracing::info!("Using cached code translation");
+            let _ = tx.try_send(Ok(cached));
+            let _ = tx.try_send(Ok(String::new()));
+            return rx;
+        }
+
+        let messages = vec![
+            ChatMessage {
+                role: Role::System,
+                content: format!(
+                    "You translate comments and string literals extracted from source code.
+
+## Input Format
+Each line is one segment, prefixed with a marker such as ⟦1⟧.
+
+## Rules
+- Translate every segment independently into the target language
+- Keep every marker exactly as given and output one segment per line
+- Keep code identifiers, placeholders like {{}} or %s, and URLs unchanged
+- Never add quotes, line breaks, or commentary
+
+## Output Format
+Output ONLY the translated segments, each prefixed with its original marker.{}",
+                    context.system_section()
+                ),
+            },
+            ChatMessage {
+                role: Role::User,
+                content: format!(
+                    "Translate the following segments to {}:\n\n{}",
+                    target_language,
+                    code::build_batch(&text, &segments)
+                ),
+            },
+        ];
+
+        let client = self.client.clone();
+        let cache = self.cache.clone();
+
+        tokio::spawn(async move {
+            let mut stream_rx = client.stream_chat(messages, thinking).await;
+            let mut full_response = String::new();
+
+            while let Some(result) = stream_rx.recv().await {
+                match result {
+                    Ok(chunk) if chunk.is_empty() => break,
+                    Ok(chunk) => full_response.push_str(&chunk),
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+            }
+
+            let translations = code::parse_batch(&full_response);
+            if translations.len() < segments.len() {
+                tracing::warn!(
+                    expected = segments.len(),
+                    received = translations.len(),
+                    "Some code segments were not translated, keeping originals"
+                );
+            }
+
+            let translated = code::reassemble(&text, &segments, &translations);
+            if !translations.is_empty() {
+                cache.set(
+                    &cache_text,
+                    &cache_language,
+                    false,
+                    translated.clone(),
+                    None,
+                );
+            }
+
+            let _ = tx.send(Ok(translated)).await;
+            let _ = tx.send(Ok(String::new())).await;
+            tracing::debug!("Code translation completed");
+        });
+
+        rx
+    }
+
+    /// Translates a numbered list item by item, keeping its numbering.
+    ///
+    /// The item bodies and the text between them are sent as one batch of
+    /// numbered segments and put back behind the original numbers. The list
+    /// is delivered as a single chunk once the response is complete, and the
+    /// per-segment result on the oneshot receiver just before the completion
+    /// signal, so items that could not be mapped back can be retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The source text
+    /// * `document` - `text` parsed into items
+    /// * `target_language` - The target language name
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// The stream of the reassembled list and a receiver for the per-segment result
+    pub fn translate_list(
+        &self,
+        text: String,
+        document: ListDocument,
+        target_language: String,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> (
+        tokio::sync::mpsc::Receiver<Result<String>>,
+        oneshot::Receiver<ListTranslation>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+        let (list_tx, list_rx) = oneshot::channel();
+
+        tracing::info!(
+            target_language = %target_language,
+            segments = document.segments().len(),
+            "Starting list translation"
+        );
+
+        // The raw response is cached, so a cache hit maps back the same way
+        let cache_text = format!("[list]\n{}", text);
+        let cache_language = context.cache_scope(&target_language);
+        if let Some((cached, _)) = self.cache.get(&cache_text, &cache_language, false) {
+            tracing::info!("Using cached list translation");
+            let list = ListTranslation::new(document, &cached);
+            let _ = tx.try_send(Ok(list.text()));
+            let _ = list_tx.send(list);
+            let _ = tx.try_send(Ok(String::new()));
+            return (rx, list_rx);
+        }
+
+        let messages = vec![
+            ChatMessage {
+                role: Role::System,
+                content: format!(
+                    "You translate the items of a numbered list and the text around them.
+
+## Input Format
+Each segment starts on a new line with a marker such as ⟦1⟧. A segment may continue on the following lines until the next marker.
+
+## Rules
+- Translate every segment into the target language, keeping its line breaks
+- Keep every marker exactly as given, in the same order, and never merge, split, renumber or skip segments
+- Do not add list numbers; they are restored from the original
+- Never add commentary
+
+## Output Format
+Output ONLY the translated segments, each starting with its original marker.{}",
+                    context.system_section()
+                ),
+            },
+            ChatMessage {
+                role: Role::User,
+                content: format!(
+                    "Translate the following segments to {}:\n\n{}",
+                    target_language,
+                    document.build_batch()
+                ),
+            },
+        ];
+
+        let client = self.client.clone();
+        let cache = self.cache.clone();
+
+        tokio::spawn(async move {
+            let mut stream_rx = client.stream_chat(messages, thinking).await;
+            let mut full_response = String::new();
+
+            while let Some(result) = stream_rx.recv().await {
+                match result {
+                    Ok(chunk) if chunk.is_empty() => break,
+                    Ok(chunk) => full_response.push_str(&chunk),
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+            }
+
+            let list = ListTranslation::new(document, &full_response);
+            if list.is_mismatched() {
+                tracing::warn!(
+                    missing = list.missing().len(),
+                    unexpected = list.unexpected(),
+                    "List response could not be mapped back onto the items"
+                );
+            } else {
+                cache.set(&cache_text, &cache_language, false, full_response, None);
+            }
+
+            let _ = tx.send(Ok(list.text())).await;
+            let _ = list_tx.send(list);
+            let _ = tx.send(Ok(String::new())).await;
+            tracing::debug!("List translation completed");
+        });
+
+        (rx, list_rx)
+    }
+
+    /// Translates string values of a JSON or YAML document.
+    ///
+    /// The values of `batch` are sent as numbered segments with their
+    /// interpolation tokens masked. The raw response is delivered as a
+    /// single chunk once complete, to be mapped back with
+    /// [`ValueBatch::results`]; it is cached only if every value came back.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch` - The selected values
+    /// * `target_language` - The target language name
+    /// * `thinking` - How the `thinking` field is sent to the provider
+    /// * `context` - Optional domain and audience hints for the prompt
+    ///
+    /// # Returns
+    ///
+    /// A receiver channel that yields the response
+    pub fn translate_values(
+        &self,
+        batch: &ValueBatch,
+        target_language: String,
+        thinking: ThinkingMode,
+        context: PromptContext,
+    ) -> tokio::sync::mpsc::Receiver<Result<String>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.client.stream_capacity());
+        let text = batch.text();
+
+        tracing::info!(
+            target_language = %target_language,
+            chars = text.len(),
+            "Starting document value translation"
+        );
+
+        let cache_text = format!("[values]\n{}", text);
+        let cache_language = context.cache_scope(&target_language);
+        if let Some((cached, _)) = self.cache.get(&cache_text, &cache_language, false) {
+            tracing::info!("Using cached value translation");
+            let _ = tx.try_send(Ok(cached));
+            let _ = tx.try_send(Ok(String::new()));
+            return rx;
+        }
+
+        let messages = vec![
+            ChatMessage {
+                role: Role::System,
+                content: format!(
+                    "You translate the string values of a localization file.
+
+## Input Format
+Each segment starts on a new line with a marker such as ⟦1⟧ and is one user interface string.
+
+## Rules
+- Translate every segment independently into the target language
+- Keep every marker exactly as given, in the same order, and never merge, split or skip segments
+- Keep every placeholder such as ⟦V1⟧ exactly once, moving it where the grammar needs it
+- Keep line breaks, and never add quotes or commentary
+
+## Output Format
+Output ONLY the translated segments, each starting with its original marker.{}",
+                    context.system_section()
+                ),
+            },
+            ChatMessage {
+                role: Role::User,
+                content: format!(
+                    "Translate the following segments to {}:\n\n{}",
+                    target_language, text
+                ),
+            },
+        ];
+
+        let client = self.client.clone();
+        let cache = self.cache.clone();
+        let batch = batch.clone();
+
+        tokio::spawn(async move {
+            let mut stream_rx = client.stream_chat(messages, thinking).await;
+            let mut full_response = String::new();
+
+            while let Some(result) = stream_rx.recv().await {
+                match result {
+                    Ok(chunk) if chunk.is_empty() => break,
+                    Ok(chunk) => full_response.push_str(&chunk),
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        return;
+                    }
+                }
+            }
+
+            let complete = batch
+                .results(&full_response)
+                .iter()
+                .all(|(_, result)| result.is_ok());
+            if complete {
+                cache.set(
+                    &cache_text,
+                    &cache_language,
+                    false,
+                    full_response.clone(),
+                    None,
+                );
+            } else {
+                tracing::warn!("Some document values could not be mapped back");
+            }
+
+            let _ = tx.send(Ok(full_response)).await;
+            let _ = tx.send(Ok(String::new())).await;
+            tracing::debug!("Document value translation completed");
+        });
+
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::transport::{ScriptStep, ScriptedTransport, sse_delta};
+    use crate::utils::structured::StructuredDocument;
+
+    type TranslationStream = tokio::sync::mpsc::Receiver<Result<String>>;
+
+    fn texts(alternatives: &[Alternative]) -> Vec<&str> {
+        alternatives.iter().map(|a| a.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_is_short_input() {
+        assert!(is_short_input("bank"));
+        assert!(is_short_input("掛ける"));
+        assert!(is_short_input("run out of steam"));
+        assert!(!is_short_input(""));
+        assert!(!is_short_input("   "));
+        assert!(!is_short_input("the quick brown fox jumps over"));
+        assert!(!is_short_input("我今天下午要去银行取一些钱"));
+    }
+
+    #[test]
+    fn test_looks_untranslated() {
+        let source = "오늘 날씨가 정말 좋네요. 산책하러 갈까요?";
+        assert!(looks_untranslated(source, source));
+        assert!(looks_untranslated(
+            source,
+            "오늘 날씨가 정말 좋네요. 산책하러 vamos?"
+        ));
+        assert!(!looks_untranslated(
+            source,
+            "O tempo está ótimo hoje. Vamos passear?"
+        ));
+        assert!(!looks_untranslated(
+            "The quick brown fox jumps over the lazy dog",
+            "Der schnelle braune Fuchs springt über den faulen Hund"
+        ));
+        // Short texts like names are left alone
+        assert!(!looks_untranslated("Berlin", "Berlin"));
+    }
+
+    #[test]
+    fn test_parse_well_formed_alternatives() {
+        let response = "1. 银行 — 金融机构\n2. 河岸 — 河流的边缘\n3. 储存 — 动词，存放";
+        let alternatives = parse_alternatives(response);
+        assert_eq!(texts(&alternatives), vec!["银行", "河岸", "储存"]);
+        assert_eq!(alternatives[1].gloss, "河流的边缘");
+    }
+
+    #[test]
+    fn test_parse_tolerates_variations() {
+        // Preamble, markdown, other separators and numbering styles
+        let response = "Here are the options:\n\n1) **bank** - financial institution\n2) \"shore\" (edge of a river)\n3、堤 ：embankment";
+        let alternatives = parse_alternatives(response);
+        assert_eq!(texts(&alternatives), vec!["bank", "shore", "堤"]);
+        assert_eq!(alternatives[0].gloss, "financial institution");
+        assert_eq!(alternatives[1].gloss, "edge of a river");
+        assert_eq!(alternatives[2].gloss, "embankment");
+    }
+
+    #[test]
+    fn test_parse_gloss_on_next_line() {
+        let response =
+            "1. hang\n   to hang something on a hook\n2. call\n   - to make a phone call";
+        let alternatives = parse_alternatives(response);
+        assert_eq!(texts(&alternatives), vec!["hang", "call"]);
+        assert_eq!(alternatives[0].gloss, "to hang something on a hook");
+        assert_eq!(alternatives[1].gloss, "to make a phone call");
+    }
+
+    #[test]
+    fn test_parse_falls_back_to_single_translation() {
+        let alternatives = parse_alternatives("  The bank is closed.\n");
+        assert_eq!(texts(&alternatives), vec!["The bank is closed."]);
+        assert!(alternatives[0].gloss.is_empty());
+
+        // Numbers that are not our list
+        let response = "2. Mai ist ein Feiertag";
+        assert_eq!(texts(&parse_alternatives(response)), vec![response]);
+        assert_eq!(
+            texts(&parse_alternatives("1990 was a good year")),
+            vec!["1990 was a good year"]
+        );
+
+        assert!(parse_alternatives("   ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_limits_and_deduplicates() {
+        let response = "1. bank — a\n2. Bank — b\n3. shore — c\n4. store — d\n5. rely — e";
+        let alternatives = parse_alternatives(response);
+        assert_eq!(texts(&alternatives), vec!["bank", "shore", "store"]);
+
+        // Numbering restarts: the second list is ignored
+        let response = "1. bank — a\n2. shore — b\n1. nonsense";
+        assert_eq!(texts(&parse_alternatives(response)), vec!["bank", "shore"]);
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let alternatives = vec![
+            Alternative {
+                text: "银行".to_string(),
+                gloss: "金融机构".to_string(),
+            },
+            Alternative {
+                text: "河岸".to_string(),
+                gloss: String::new(),
+            },
+        ];
+        assert_eq!(
+            parse_alternatives(&format_alternatives(&alternatives)),
+            alternatives
+        );
+    }
+
+    fn scripted_translator(
+        transport: Arc<ScriptedTransport>,
+        name: &str,
+    ) -> (Translator, Arc<TranslationCache>) {
+        let cache_file = std::env::temp_dir().join(format!("test_translator_{}.json", name));
+        let _ = std::fs::remove_file(&cache_file);
+        let _ = std::fs::remove_file(cache_file.with_extension("journal"));
+        let cache = Arc::new(TranslationCache::new(cache_file));
+        let client = ApiClient::new("test_key".to_string()).with_transport(transport);
+        (Translator::with_client(client, cache.clone()), cache)
+    }
+
+    fn translate(translator: &Translator, text: &str, context: PromptContext) -> TranslationStream {
+        translator.translate(
+            text.to_string(),
+            "Deutsch".to_string(),
+            false,
+            ThinkingMode::Disabled,
+            context,
+        )
+    }
+
+    async fn collect(mut rx: TranslationStream) -> Vec<Result<String>> {
+        let mut results = Vec::new();
+        while let Some(result) = rx.recv().await {
+            results.push(result);
+        }
+        results
+    }
+
+    fn chunks(results: &[Result<String>]) -> Vec<&str> {
+        results
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .map(String::as_str)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_translate_uses_cache() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Network"], "stop"));
+        let (translator, cache) = scripted_translator(transport.clone(), "cache_hit");
+        cache.set("Hello", "Deutsch", false, "Hallo".to_string(), None);
+
+        let results = collect(translate(&translator, "Hello", PromptContext::default())).await;
+
+        assert_eq!(chunks(&results), vec!["Hallo", ""]);
+        assert!(transport.requests().is_empty());
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_caches_completed_stream() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hal", "lo"], "stop"));
+        let (translator, cache) = scripted_translator(transport, "cache_store");
+
+        let results = collect(translate(&translator, "Hello", PromptContext::default())).await;
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(
+            cache.get("Hello", "Deutsch", false),
+            Some(("Hallo".to_string(), None))
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_does_not_cache_failed_stream() {
+        let transport = Arc::new(ScriptedTransport::new(vec![
+            ScriptStep::Bytes(sse_delta(Some("Hal"), None)),
+            ScriptStep::Fail("connection reset".to_string()),
+        ]));
+        let (translator, cache) = scripted_translator(transport, "cache_error");
+
+        let results = collect(translate(&translator, "Hello", PromptContext::default())).await;
+
+        assert_eq!(chunks(&results), vec!["Hal"]);
+        assert!(matches!(
+            results.last(),
+            Some(Err(TranslationError::StreamError(_)))
+        ));
+        assert_eq!(cache.get("Hello", "Deutsch", false), None);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_translations_share_one_request() {
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let transport = Arc::new(ScriptedTransport::new(vec![
+            ScriptStep::Bytes(sse_delta(Some("Hal"), None)),
+            ScriptStep::Gate(gate.clone()),
+            ScriptStep::Bytes(sse_delta(Some("lo"), None)),
+            ScriptStep::Bytes(sse_delta(None, Some("stop"))),
+            ScriptStep::Bytes(b"data: [DONE]\n\n".to_vec()),
+        ]));
+        let (translator, cache) = scripted_translator(transport.clone(), "coalesce");
+
+        let mut first = translate(&translator, "Hello", PromptContext::default());
+        let mut streams = vec![translate(
+            &translator.clone(),
+            "Hello",
+            PromptContext::default(),
+        )];
+        assert_eq!(first.recv().await.unwrap().unwrap(), "Hal");
+        // Joining after the first chunk still gets it
+        for _ in 0..3 {
+            streams.push(translate(&translator, "Hello", PromptContext::default()));
+        }
+        gate.notify_one();
+
+        let mut outputs = vec![format!("Hal{}", chunks(&collect(first).await).concat())];
+        for stream in streams {
+            let results = collect(stream).await;
+            assert!(results.iter().all(|r| r.is_ok()));
+            outputs.push(chunks(&results).concat());
+        }
+        assert_eq!(outputs, vec!["Hallo"; 5]);
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(
+            cache.get("Hello", "Deutsch", false),
+            Some(("Hallo".to_string(), None))
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_translation_aborts_the_request() {
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let transport = Arc::new(ScriptedTransport::new(vec![
+            ScriptStep::Bytes(sse_delta(Some("Hal"), None)),
+            ScriptStep::Gate(gate.clone()),
+            ScriptStep::Bytes(sse_delta(Some("lo"), None)),
+            ScriptStep::Bytes(sse_delta(None, Some("stop"))),
+            ScriptStep::Bytes(b"data: [DONE]\n\n".to_vec()),
+        ]));
+        let (translator, cache) = scripted_translator(transport.clone(), "abandoned");
+
+        let mut rx = translate(&translator, "Hello", PromptContext::default());
+        assert_eq!(rx.recv().await.unwrap().unwrap(), "Hal");
+        // What a cancelled session does with the stream
+        drop(rx);
+
+        // The response stays listed for followers until the request stops
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !translator.coalescer.is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the abandoned request kept waiting for the response");
+        gate.notify_one();
+        assert_eq!(transport.delivered(), 1);
+        assert_eq!(cache.get("Hello", "Deutsch", false), None);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_forwards_chunks_in_order() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["Eins ", "zwei ", "drei"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport, "chunk_order");
+
+        let results = collect(translate(
+            &translator,
+            "One two three",
+            PromptContext::default(),
+        ))
+        .await;
+
+        assert_eq!(chunks(&results), vec!["Eins ", "zwei ", "drei", ""]);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_builds_prompt_from_context() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hallo"], "stop"));
+        let (translator, cache) = scripted_translator(transport.clone(), "prompt");
+
+        let context = PromptContext::new("legal", "children");
+        collect(translate(&translator, "Hello", context)).await;
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request["stream"], true);
+        assert_eq!(request["thinking"]["type"], "disabled");
+        assert_eq!(request["messages"][0]["role"], "system");
+        let system = request["messages"][0]["content"].as_str().unwrap();
+        assert!(system.contains("The text belongs to the legal domain."));
+        assert!(system.contains("The translation is intended for: children."));
+        assert_eq!(request["messages"][1]["role"], "user");
+        assert_eq!(
+            request["messages"][1]["content"],
+            "Translate the following text to Deutsch:\n\nHello"
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_masks_code_and_filters_response() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["Here is the trans", "lation:\n\n", "Rufe ⟦C", "1⟧ auf"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "filters");
+
+        let results = collect(translate(
+            &translator,
+            "Call `run()`",
+            PromptContext::default(),
+        ))
+        .await;
+
+        assert_eq!(chunks(&results), vec!["Rufe ", "`run()` auf", ""]);
+        let request = &transport.requests()[0];
+        assert!(
+            request["messages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("## Placeholders")
+        );
+        assert_eq!(
+            request["messages"][1]["content"],
+            "Translate the following text to Deutsch:\n\nCall ⟦C1⟧"
+        );
+        assert_eq!(
+            cache.get("Call `run()`", "Deutsch", false),
+            Some(("Rufe `run()` auf".to_string(), None))
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_keeps_links_verbatim() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["Siehe [die Anleitung](⟦U", "1⟧) oder ⟦U2⟧."],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "links");
+        let source = "See [the guide](https://example.com/user-guide) or mail help@example.com.";
+
+        let results = collect(translate(&translator, source, PromptContext::default())).await;
+
+        assert_eq!(
+            chunks(&results).concat(),
+            "Siehe [die Anleitung](https://example.com/user-guide) oder help@example.com."
+        );
+        let request = &transport.requests()[0];
+        assert!(
+            request["messages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .contains("⟦U1⟧ stand for links")
+        );
+        assert_eq!(
+            request["messages"][1]["content"],
+            "Translate the following text to Deutsch:\n\nSee [the guide](⟦U1⟧) or mail ⟦U2⟧."
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_list_keeps_numbering() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["⟦1⟧ Öffnen\n", "⟦3⟧ Trinken"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "list");
+        let text = "3) Open\n4) Pour\n5) Drink";
+        let document = ListDocument::parse(text).unwrap();
+
+        let (rx, list_rx) = translator.translate_list(
+            text.to_string(),
+            document,
+            "Deutsch".to_string(),
+            ThinkingMode::Disabled,
+            PromptContext::default(),
+        );
+        let results = collect(rx).await;
+
+        assert_eq!(chunks(&results), vec!["3) Öffnen\n4) Pour\n5) Trinken", ""]);
+        let list = list_rx.await.unwrap();
+        assert_eq!(list.missing(), vec![1]);
+        assert_eq!(
+            transport.requests()[0]["messages"][1]["content"],
+            "Translate the following segments to Deutsch:\n\n⟦1⟧ Open\n⟦2⟧ Pour\n⟦3⟧ Drink"
+        );
+        // An incomplete mapping is not cached
+        assert_eq!(
+            cache.get(&format!("[list]\n{}", text), "Deutsch", false),
+            None
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_values_masks_tokens() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["⟦1⟧ Hallo ⟦V1⟧!\n", "⟦2⟧ Speichern"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "values");
+        let document =
+            StructuredDocument::parse(r#"{"greeting": "Hello {{name}}!", "save": "Save"}"#)
+                .unwrap();
+        let batch = ValueBatch::new(&document, &[0, 1]);
+
+        let rx = translator.translate_values(
+            &batch,
+            "Deutsch".to_string(),
+            ThinkingMode::Disabled,
+            PromptContext::default(),
+        );
+        let results = collect(rx).await;
+
+        let response = chunks(&results)[0];
+        assert_eq!(
+            batch.results(response),
+            vec![
+                (0, Ok("Hallo {{name}}!".to_string())),
+                (1, Ok("Speichern".to_string()))
+            ]
+        );
+        assert_eq!(
+            transport.requests()[0]["messages"][1]["content"],
+            "Translate the following segments to Deutsch:\n\n⟦1⟧ Hello ⟦V1⟧!\n⟦2⟧ Save"
+        );
+        assert!(
+            cache
+                .get(&format!("[values]\n{}", batch.text()), "Deutsch", false)
+                .is_some()
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_chunked_keeps_terms_consistent() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["Richte den „Lastverteiler“ ein."],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "chunked");
+        // The second chunk was translated before, another way
+        cache.set(
+            "The load balancer is down.",
+            "Deutsch|consistent=load balancer=Lastverteiler",
+            false,
+            "Der Load Balancer ist ausgefallen.".to_string(),
+            None,
+        );
+
+        let (rx, report_rx) = translator.translate_chunked(
+            "Set up the \"load balancer\" first.\n\nThe load balancer is down.\n\nRestart the load balancer."
+                .to_string(),
+            &TextChunker::new(40),
+            "Deutsch".to_string(),
+            ThinkingMode::Disabled,
+            PromptContext::default(),
+        );
+        let results = collect(rx).await;
+
+        assert_eq!(
+            chunks(&results).concat(),
+            "Richte den „Lastverteiler“ ein.\n\nDer Load Balancer ist ausgefallen.\n\nRichte den „Lastverteiler“ ein."
+        );
+        assert_eq!(chunks(&results).last(), Some(&""));
+        // The term read from the first chunk went into the prompt of the last
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        let system = |request: &serde_json::Value| {
+            request["messages"][0]["content"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert!(!system(&requests[0]).contains("Lastverteiler"));
+        assert!(system(&requests[1]).contains("- load balancer → Lastverteiler"));
+
+        let report: Vec<String> = report_rx
+            .await
+            .unwrap()
+            .iter()
+            .map(Inconsistency::summary)
+            .collect();
+        assert_eq!(
+            report,
+            ["load balancer: \"Lastverteiler\" (chunks 1, 3), something else (chunk 2)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_translate_pivot_chains_cached_stages() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Olá"], "stop"));
+        let (translator, cache) = scripted_translator(transport.clone(), "pivot");
+        cache.set("안녕", "English", false, "Hello".to_string(), None);
+
+        let translate = || {
+            translator.translate_pivot(
+                "안녕".to_string(),
+                "English".to_string(),
+                "Português".to_string(),
+                false,
+                ThinkingMode::Disabled,
+                PromptContext::default(),
+            )
+        };
+        let (rx, pivot_rx) = translate();
+        let results = collect(rx).await;
+
+        assert_eq!(chunks(&results), vec!["Olá", ""]);
+        assert_eq!(pivot_rx.await.unwrap(), "Hello");
+        // Only the second stage reached the provider, with the pivot text
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0]["messages"][1]["content"],
+            "Translate the following text to Português:\n\nHello"
+        );
+        assert_eq!(
+            cache.get("Hello", "Português", false),
+            Some(("Olá".to_string(), None))
+        );
+        assert_eq!(
+            cache.get("안녕", "Português|pivot=English", false),
+            Some(("Olá".to_string(), None))
+        );
+        // A direct translation doesn't pick up the pivot result
+        assert_eq!(cache.get("안녕", "Português", false), None);
+
+        // The combined result is served from the cache
+        let (rx, pivot_rx) = translate();
+        assert_eq!(chunks(&collect(rx).await), vec!["Olá", ""]);
+        assert_eq!(pivot_rx.await.unwrap(), "Hello");
+        assert_eq!(transport.requests().len(), 1);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_translate_pivot_reports_the_failed_stage() {
+        let transport = Arc::new(ScriptedTransport::new(vec![ScriptStep::Fail(
+            "connection reset".to_string(),
+        )]));
+        let (translator, cache) = scripted_translator(transport, "pivot_error");
+
+        let (rx, pivot_rx) = translator.translate_pivot(
+            "안녕".to_string(),
+            "English".to_string(),
+            "Português".to_string(),
+            false,
+            ThinkingMode::Disabled,
+            PromptContext::default(),
+        );
+        let results = collect(rx).await;
+
+        assert!(matches!(
+            results.last(),
+            Some(Err(TranslationError::PivotStage { stage, .. })) if stage == "Stage 1 (into English)"
+        ));
+        assert!(pivot_rx.await.is_err());
+        assert_eq!(cache.get("안녕", "Português|pivot=English", false), None);
+        cache.clear();
+    }
+
+    #[test]
+    fn test_is_refusal() {
+        assert!(is_refusal(
+            "How do I pick a lock?",
+            "I’m sorry, but I can’t help with that request."
+        ));
+        assert!(is_refusal("如何开锁？", "抱歉，我无法协助翻译此内容。"));
+        // The same phrase in the source is just being translated
+        assert!(!is_refusal(
+            "I can't help with the move on Sunday.",
+            "I can't help with the move on Sunday."
+        ));
+        assert!(!is_refusal("Hello", "Hallo"));
+        let long = format!(
+            "I can't translate idioms literally. {}",
+            "Text. ".repeat(80)
+        );
+        assert!(!is_refusal("Source", &long));
+    }
+
+    #[tokio::test]
+    async fn test_refusals_are_reported_and_not_cached() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hal"], "content_filter"));
+        let (translator, cache) = scripted_translator(transport, "content_filter");
+        let results = collect(translate(&translator, "Hello", PromptContext::default())).await;
+        assert_eq!(chunks(&results), vec!["Hal"]);
+        assert!(matches!(
+            results.last(),
+            Some(Err(TranslationError::ContentFiltered(reason))) if reason == "content_filter"
+        ));
+        assert_eq!(cache.get("Hello", "Deutsch", false), None);
+
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["I'm sorry, but I can't ", "help with that."],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport, "refusal_text");
+        let results = collect(translate(&translator, "Hello", PromptContext::default())).await;
+        assert!(matches!(
+            results.last(),
+            Some(Err(TranslationError::ContentFiltered(reason))) if reason == "refusal text"
+        ));
+        assert_eq!(cache.get("Hello", "Deutsch", false), None);
+        cache.clear();
+    }
+
+    #[test]
+    fn test_page_markers_are_kept() {
+        let context = PromptContext::default();
+        let messages = translation_messages("Plain text", "Deutsch", false, &context);
+        assert!(!messages[0].content.contains("Page Markers"));
+
+        let text = format!(
+            "{}\n\nFirst page.\n\n{}\n\nSecond page.",
+            pdf::page_marker(1),
+            pdf::page_marker(2)
+        );
+        let messages = translation_messages(&text, "Deutsch", false, &context);
+        assert!(messages[0].content.contains("## Page Markers"));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_not_cached() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Hal"], "length"));
+        let (translator, cache) = scripted_translator(transport, "truncated");
+
+        let results = collect(translate(&translator, "Hello", PromptContext::default())).await;
+
+        assert!(matches!(
+            results.last(),
+            Some(Err(TranslationError::Truncated))
+        ));
+        assert_eq!(cache.get("Hello", "Deutsch", false), None);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_looping_response_is_stopped() {
+        let sentence = "Der Ausschuss prüft den Vorschlag nächste Woche. ";
+        let mut script = vec!["Zusammenfassung:\n"];
+        script.extend([sentence; 200]);
+        let transport = Arc::new(ScriptedTransport::with_chunks(&script, "length"));
+        let (translator, cache) = scripted_translator(transport.clone(), "looping");
+
+        let results = collect(translate(&translator, "Summary", PromptContext::default())).await;
+
+        let Some(Err(TranslationError::RepetitionDetected { repeated_chars })) = results.last()
+        else {
+            panic!("expected a repetition error, got {:?}", results.last());
+        };
+        let streamed = chunks(&results).concat();
+        let kept: String = streamed
+            .chars()
+            .take(streamed.chars().count() - repeated_chars)
+            .collect();
+        assert_eq!(kept, format!("Zusammenfassung:\n{}", sentence));
+        // The response was abandoned long before its end
+        assert!(
+            transport.delivered() < 100,
+            "read {}",
+            transport.delivered()
+        );
+        assert_eq!(cache.get("Summary", "Deutsch", false), None);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_repetition_limit_can_be_turned_off() {
+        let script = ["Na na na na, na na na na, hey hey hey, goodbye\n"; 12];
+        let transport = Arc::new(ScriptedTransport::with_chunks(&script, "stop"));
+        let (translator, cache) = scripted_translator(transport, "loop_allowed");
+        let translator = translator.with_repetition_limit(None);
+
+        let results = collect(translate(&translator, "Chorus", PromptContext::default())).await;
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(chunks(&results).concat(), script.concat());
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_continuation_prefills_the_partial_translation() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&[" Welt"], "stop"));
+        let (translator, cache) = scripted_translator(transport.clone(), "prefill");
+
+        let rx = translator.continue_translation(
+            "Hello world".to_string(),
+            "Deutsch".to_string(),
+            false,
+            ThinkingMode::Disabled,
+            PromptContext::default(),
+            "Hallo".to_string(),
+        );
+        let results = collect(rx).await;
+
+        assert_eq!(chunks(&results), vec![" Welt", ""]);
+        let requests = transport.requests();
+        let messages = requests[0]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["role"], "user");
+        assert_eq!(messages[2]["role"], "assistant");
+        assert_eq!(messages[2]["content"], "Hallo");
+        assert_eq!(
+            cache.get("Hello world", "Deutsch", false),
+            Some(("Hallo Welt".to_string(), None))
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_continuation_is_masked_and_kept_whole() {
+        // Would be dropped as a preamble at the start of a response
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["Translation: ⟦C2⟧ aus."],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "prefill_masked");
+
+        let rx = translator.continue_translation(
+            "Run `make` on https://example.com. Translation: `done`.".to_string(),
+            "Deutsch".to_string(),
+            false,
+            ThinkingMode::Disabled,
+            PromptContext::default(),
+            "Führe `make` auf https://example.com aus. ".to_string(),
+        );
+        let results = collect(rx).await;
+
+        assert_eq!(chunks(&results), vec!["Translation: `done` aus.", ""]);
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0]["messages"][2]["content"],
+            "Führe ⟦C1⟧ auf ⟦U1⟧ aus. "
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_explanation_is_cached_apart_from_translation() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["\"Hallo\" is ", "the usual greeting."],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "explain_cache");
+        cache.set("Hello", "Deutsch", false, "Hallo".to_string(), None);
+
+        let explain = || {
+            translator.explain(
+                "Hello".to_string(),
+                "Hallo".to_string(),
+                "Deutsch".to_string(),
+                ThinkingMode::Disabled,
+            )
+        };
+        let results = collect(explain()).await;
+        assert_eq!(
+            chunks(&results),
+            vec!["\"Hallo\" is ", "the usual greeting.", ""]
+        );
+
+        // The translation is untouched and the explanation is served from its own entry
+        assert_eq!(
+            cache.get("Hello", "Deutsch", false),
+            Some(("Hallo".to_string(), None))
+        );
+        let results = collect(explain()).await;
+        assert_eq!(
+            chunks(&results),
+            vec!["\"Hallo\" is the usual greeting.", ""]
+        );
+        assert_eq!(transport.requests().len(), 1);
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_romanization_keeps_latin_and_is_cached() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["1. wǒ yòng\n", "2. xiě dàimǎ"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "romanize");
+
+        let text = "我用Rust写代码";
+        let romanized = translator.romanize(text, Scheme::Pinyin).await.unwrap();
+        assert_eq!(romanized, "wǒ yòng Rust xiě dàimǎ");
+        // Only the Chinese runs are sent
+        assert_eq!(
+            transport.requests()[0]["messages"][1]["content"],
+            "1. 我用\n2. 写代码"
+        );
+
+        // Served from the cache, and apart from a translation of the text
+        assert_eq!(
+            translator.romanize(text, Scheme::Pinyin).await.unwrap(),
+            romanized
+        );
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(cache.get(text, "Deutsch", false), None);
+        assert_eq!(
+            translator
+                .romanize("plain text", Scheme::Pinyin)
+                .await
+                .unwrap(),
+            "plain text"
+        );
+        cache.clear();
+    }
+
+    fn span(text: &str, reason: &str) -> UncertainSpan {
+        UncertainSpan {
+            text: text.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_uncertain_spans() {
+        let response = "1. \"Es regnet in Strömen\" — idiom, a literal rendering is also possible\n2. “Bank” – could be the river bank\n3) **「上手い」** (colloquial)";
+        assert_eq!(
+            parse_uncertain_spans(response),
+            vec![
+                span(
+                    "Es regnet in Strömen",
+                    "idiom, a literal rendering is also possible"
+                ),
+                span("Bank", "could be the river bank"),
+                span("上手い", "colloquial"),
+            ]
+        );
+        // Dashes inside the quote stay part of it
+        assert_eq!(
+            parse_uncertain_spans("1. \"well-known – sort of\" - hedged"),
+            vec![span("well-known – sort of", "hedged")]
+        );
+        // An unquoted span is split at the first separator
+        assert_eq!(
+            parse_uncertain_spans("1. Strömen — plural form"),
+            vec![span("Strömen", "plural form")]
+        );
+    }
+
+    #[test]
+    fn test_parse_uncertain_spans_tolerates_bad_answers() {
+        assert!(parse_uncertain_spans("").is_empty());
+        assert!(parse_uncertain_spans("NONE").is_empty());
+        assert!(parse_uncertain_spans("I am confident about everything.").is_empty());
+        // Unnumbered and empty lines are skipped, duplicates dropped
+        let response = "Here you go:\n- \"Hallo\" — greeting\n1. \"\" — empty\n2. \"Welt\" — ok\n3. \"Welt\" — again";
+        assert_eq!(parse_uncertain_spans(response), vec![span("Welt", "ok")]);
+        let many: String = (1..=9)
+            .map(|i| format!("{}. \"word{}\" — reason\n", i, i))
+            .collect();
+        assert_eq!(parse_uncertain_spans(&many).len(), MAX_UNCERTAIN_SPANS);
+    }
+
+    #[test]
+    fn test_locate_uncertain_survives_quoting_differences() {
+        let translation = "Er sagte: „Es regnet in Strömen“, und ging nach Hause.";
+        let found = locate_uncertain(
+            translation,
+            &[
+                span("nach Hause.", "home or house"),
+                // Other quote marks than the translation
+                span("\"Es regnet in Strömen\"", "idiom"),
+                span("Es regnet", "overlaps the idiom"),
+                span("völlig erfunden", "not in the translation"),
+            ],
+        );
+        let texts: Vec<&str> = found
+            .iter()
+            .map(|low| &translation[low.range.clone()])
+            .collect();
+        assert_eq!(texts, vec!["Es regnet in Strömen", "nach Hause"]);
+        assert_eq!(found[0].reason, "idiom");
+
+        let translation = "He said “see you” and left.";
+        let found = parse_confidence_check("1. \"He said \"see you\" and\" — tone", translation);
+        assert_eq!(found.len(), 1);
+        assert!(translation[found[0].range.clone()].contains("see you"));
+        assert!(parse_confidence_check("garbage", translation).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confidence_check_is_cached_apart() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["1. \"Strömen\" — ", "idiom"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "confidence");
+        cache.set(
+            "Hello",
+            "Deutsch",
+            false,
+            "Es regnet in Strömen".to_string(),
+            None,
+        );
+
+        let check = || {
+            translator.check_confidence(
+                "It's raining cats and dogs".to_string(),
+                "Es regnet in Strömen".to_string(),
+                "Deutsch".to_string(),
+                ThinkingMode::Disabled,
+            )
+        };
+        let results = collect(check()).await;
+        let response = chunks(&results).concat();
+        let found = parse_confidence_check(&response, "Es regnet in Strömen");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range, 13..21);
+        let system = transport.requests()[0]["messages"][0]["content"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(system.contains("at most 5"));
+
+        // Served from its own entry the second time
+        let results = collect(check()).await;
+        assert_eq!(chunks(&results).concat(), response);
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(
+            cache.get("Hello", "Deutsch", false).unwrap().0,
+            "Es regnet in Strömen"
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_align_asks_for_the_source_phrase() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &["cats and ", "dogs"],
+            "stop",
+        ));
+        let (translator, cache) = scripted_translator(transport.clone(), "align");
+
+        let answer = translator
+            .align(
+                "It's raining cats and dogs",
+                "Es regnet in Strömen",
+                "Strömen",
+            )
+            .await
+            .unwrap();
+        assert_eq!(answer, "cats and dogs");
+        let prompt = transport.requests()[0]["messages"][1]["content"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(prompt.contains("It's raining cats and dogs"));
+        assert!(prompt.contains("which source phrase corresponds to 'Strömen'?"));
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_explanation_prompt_includes_source_and_translation() {
+        let transport = Arc::new(ScriptedTransport::with_chunks(&["Because."], "stop"));
+        let (translator, cache) = scripted_translator(transport.clone(), "explain_prompt");
+
+        collect(translator.explain(
+            "It's raining cats and dogs".to_string(),
+            "Es regnet in Strömen".to_string(),
+            "Deutsch".to_string(),
+            ThinkingMode::Disabled,
+        ))
+        .await;
+
+        let requests = transport.requests();
+        let system = requests[0]["messages"][0]["content"].as_str().unwrap();
+        assert!(system.contains("Idioms"));
+        assert!(system.contains("Write in English"));
+        assert_eq!(
+            requests[0]["messages"][1]["content"],
+            "Source text:\n\nIt's raining cats and dogs\n\nTranslation into Deutsch:\n\nEs regnet in Strömen"
+        );
+
+        // A different translation of the same text gets its own explanation
+        assert_eq!(
+            cache
+                .get(
+                    &explanation_cache_key("It's raining cats and dogs", "Es regnet stark"),
+                    EXPLANATION_LANGUAGE,
+                    false
+                )
+                .map(|(explanation, _)| explanation),
+            None
+        );
+        cache.clear();
+    }
+
+    #[tokio::test]
+    async fn test_large_translation_is_streamed_with_bounded_buffering() {
+        // 10 MiB in 1 KiB chunks
+        const CHUNK_LEN: usize = 1024;
+        const CHUNKS: usize = 10 * 1024;
+        const CAPACITY: usize = 8;
+
+        let chunk = "x".repeat(CHUNK_LEN);
+        let transport = Arc::new(ScriptedTransport::with_chunks(
+            &vec![chunk.as_str(); CHUNKS],
+            "stop",
+        ));
+        let (mut translator, cache) = scripted_translator(transport.clone(), "bounded");
+        translator.client = translator.client.with_stream_capacity(CAPACITY);
+        // The filler text is one long loop
+        let translator = translator.with_repetition_limit(None);
+
+        let mut rx = translate(&translator, "Large", PromptContext::default());
+        let mut received = 0;
+        let mut received_len = 0;
+        let mut peak_queued = 0;
+        let mut peak_read_ahead = 0;
+        while let Some(result) = rx.recv().await {
+            let chunk = result.unwrap();
+            if chunk.is_empty() {
+                break;
+            }
+            received += 1;
+            received_len += chunk.len();
+
+            // Let the network and translator tasks run as far ahead as they can
+            for _ in 0..4 {
+                tokio::task::yield_now().await;
+            }
+            peak_queued = peak_queued.max(rx.len());
+            peak_read_ahead = peak_read_ahead.max(transport.delivered() - received);
+        }
+
+        assert_eq!(received, CHUNKS);
+        assert_eq!(received_len, CHUNK_LEN * CHUNKS);
+        assert!(peak_queued <= CAPACITY, "{} chunks queued", peak_queued);
+        // Two full channels, a chunk waiting to be sent by each task, and the
+        // finish reason and [DONE] steps
+        assert!(
+            peak_read_ahead <= 2 * CAPACITY + 4,
+            "read {} chunks ahead",
+            peak_read_ahead
+        );
+        cache.clear();
     }
 }
@@ -0,0 +1,245 @@
+//! Transports that carry chat requests to a provider.
+//!
+//! [`ApiClient`](crate::api::client::ApiClient) only parses the SSE bytes a
+//! transport returns, so the whole translation pipeline can be driven by a
+//! scripted transport in tests instead of the network.
+
+use crate::api::client::ChatRequest;
+use crate::error::{Result, TranslationError};
+use futures_util::StreamExt;
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use reqwest::Client;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Raw response body, as it arrives.
+pub type ByteStream = BoxStream<'static, Result<Vec<u8>>>;
+
+/// Sends a chat request and returns the streamed response body.
+pub trait ChatTransport: Send + Sync {
+    /// Sends `request`, failing if the provider doesn't accept it.
+    fn send(&self, request: &ChatRequest) -> BoxFuture<'static, Result<ByteStream>>;
+}
+
+/// Redirects followed before a request fails.
+const MAX_REDIRECTS: usize = 5;
+
+/// Time given to opening a connection until one is configured.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The shared client and the connect timeout it was built with.
+static CLIENT: LazyLock<Mutex<(Duration, Client)>> = LazyLock::new(|| {
+    Mutex::new((
+        DEFAULT_CONNECT_TIMEOUT,
+        build_client(DEFAULT_CONNECT_TIMEOUT),
+    ))
+});
+
+fn build_client(connect_timeout: Duration) -> Client {
+    Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .connect_timeout(connect_timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// HTTP client shared by every transport.
+///
+/// Clones share one connection pool, so a connection opened by
+/// [`preconnect`](crate::api::client::preconnect) is reused by the next
+/// request while it is idle for less than the pool timeout (90 s). It
+/// follows at most [`MAX_REDIRECTS`] redirects, pages fetched for
+/// translation included, and gives up opening a connection after the
+/// timeout set with [`set_connect_timeout`].
+pub fn shared_client() -> Client {
+    crate::lock_mutex!(CLIENT).1.clone()
+}
+
+/// Gives up opening connections after `timeout` from now on.
+///
+/// A different timeout replaces the shared client, and with it the idle
+/// connections of its pool.
+pub fn set_connect_timeout(timeout: Duration) {
+    let mut client = crate::lock_mutex!(CLIENT);
+    if client.0 != timeout {
+        tracing::info!(?timeout, "Connect timeout changed");
+        *client = (timeout, build_client(timeout));
+    }
+}
+
+/// Sends requests to an OpenAI-compatible HTTP endpoint.
+pub struct HttpTransport {
+    client: Client,
+    url: String,
+    api_key: String,
+}
+
+impl HttpTransport {
+    /// Creates a transport for the chat completions endpoint under `base_url`.
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        HttpTransport {
+            client: shared_client(),
+            url: format!("{}/chat/completions", base_url),
+            api_key: api_key.to_string(),
+        }
+    }
+}
+
+impl ChatTransport for HttpTransport {
+    fn send(&self, request: &ChatRequest) -> BoxFuture<'static, Result<ByteStream>> {
+        let pending = self
+            .client
+            .post(&self.url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send();
+
+        Box::pin(async move {
+            let response = pending.await.map_err(|e| {
+                tracing::error!("Request error: {}", e);
+                TranslationError::from(e)
+            })?;
+
+            let status = response.status();
+            tracing::debug!("Received response with status: {}", status);
+            if !status.is_success() {
+                tracing::error!("API returned error status: {}", status);
+                return Err(TranslationError::HttpStatus(status));
+            }
+
+            let stream = response.bytes_stream().map(|chunk| {
+                chunk.map(|bytes| bytes.to_vec()).map_err(|e| {
+                    tracing::error!("Stream error: {}", e);
+                    TranslationError::StreamError(format!("Stream error: {}", e))
+                })
+            });
+            Ok(stream.boxed())
+        })
+    }
+}
+
+/// Transport that replays a fixed response and records the requests.
+#[cfg(test)]
+pub struct ScriptedTransport {
+    script: Vec<ScriptStep>,
+    requests: std::sync::Mutex<Vec<serde_json::Value>>,
+    /// Statuses the next requests are rejected with, in order
+    rejections: std::sync::Mutex<Vec<reqwest::StatusCode>>,
+    /// Steps read from the response bodies so far
+    delivered: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+/// One step of a scripted response.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub enum ScriptStep {
+    /// A chunk of the response body
+    Bytes(Vec<u8>),
+    /// The connection fails mid-stream
+    Fail(String),
+    /// The connection stays open without sending anything more
+    Stall,
+    /// The response waits until the gate is notified
+    Gate(std::sync::Arc<tokio::sync::Notify>),
+}
+
+#[cfg(test)]
+impl ScriptedTransport {
+    /// Creates a transport that answers every request with `script`.
+    pub fn new(script: Vec<ScriptStep>) -> Self {
+        ScriptedTransport {
+            script,
+            requests: std::sync::Mutex::new(Vec::new()),
+            rejections: Default::default(),
+            delivered: Default::default(),
+        }
+    }
+
+    /// Rejects the first requests with `statuses`, one each, before
+    /// answering with the script.
+    pub fn rejecting_first(self, statuses: &[u16]) -> Self {
+        *crate::lock_mutex!(self.rejections) = statuses
+            .iter()
+            .map(|&status| reqwest::StatusCode::from_u16(status).unwrap())
+            .collect();
+        self
+    }
+
+    /// Creates a transport that streams `chunks` as content deltas, then
+    /// finishes with `finish_reason` and `[DONE]`.
+    pub fn with_chunks(chunks: &[&str], finish_reason: &str) -> Self {
+        let mut script: Vec<ScriptStep> = chunks
+            .iter()
+            .map(|chunk| ScriptStep::Bytes(sse_delta(Some(chunk), None)))
+            .collect();
+        script.push(ScriptStep::Bytes(sse_delta(None, Some(finish_reason))));
+        script.push(ScriptStep::Bytes(b"data: [DONE]\n\n".to_vec()));
+        Self::new(script)
+    }
+
+    /// Request bodies sent so far, as JSON.
+    pub fn requests(&self) -> Vec<serde_json::Value> {
+        crate::lock_mutex!(self.requests).clone()
+    }
+
+    /// Number of steps read from the response bodies so far.
+    pub fn delivered(&self) -> usize {
+        self.delivered.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+impl ChatTransport for ScriptedTransport {
+    fn send(&self, request: &ChatRequest) -> BoxFuture<'static, Result<ByteStream>> {
+        crate::lock_mutex!(self.requests).push(serde_json::to_value(request).unwrap());
+        let mut rejections = crate::lock_mutex!(self.rejections);
+        if !rejections.is_empty() {
+            let status = rejections.remove(0);
+            return Box::pin(async move { Err(TranslationError::HttpStatus(status)) });
+        }
+        drop(rejections);
+        let steps = self.script.clone().into_iter();
+        let delivered = self.delivered.clone();
+        Box::pin(async move {
+            let stream = futures_util::stream::unfold(steps, |mut steps| async move {
+                loop {
+                    match steps.next()? {
+                        ScriptStep::Bytes(bytes) => return Some((Ok(bytes), steps)),
+                        ScriptStep::Fail(message) => {
+                            return Some((Err(TranslationError::StreamError(message)), steps));
+                        }
+                        ScriptStep::Stall => futures_util::future::pending::<()>().await,
+                        ScriptStep::Gate(gate) => gate.notified().await,
+                    }
+                }
+            })
+            .inspect(move |_| {
+                delivered.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            });
+            Ok(stream.boxed())
+        })
+    }
+}
+
+/// Encodes one SSE event, with a content delta unless `content` is `None`.
+#[cfg(test)]
+pub fn sse_delta(content: Option<&str>, finish_reason: Option<&str>) -> Vec<u8> {
+    let delta = match content {
+        Some(content) => serde_json::json!({ "content": content }),
+        None => serde_json::json!({}),
+    };
+    let chunk = serde_json::json!({
+        "id": "test",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "test",
+        "choices": [{
+            "index": 0,
+            "delta": delta,
+            "finish_reason": finish_reason,
+        }],
+    });
+    format!("data: {}\n\n", chunk).into_bytes()
+}
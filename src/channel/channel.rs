@@ -3,19 +3,206 @@
 //! This module defines message types used to communicate translation
 //! and TTS progress and results from background tasks to the UI thread.
 
+use crate::api::translator::Alternative;
 use crate::services::audio::PlaybackState;
+use crate::utils::list::ListTranslation;
+use crate::utils::metrics::RequestMetrics;
+use crate::utils::retention::{Report, Usage};
+use crate::utils::romanize::Scheme;
+use crate::utils::structured::ValueError;
+use crate::utils::version::Release;
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// Capacity of the channels carrying response chunks from the provider to
+/// the translator and from the translator to its consumer.
+///
+/// When a channel is full the task feeding it waits in `send`, so the
+/// response body is read no faster than the translation is consumed and a
+/// stalled consumer holds at most this many chunks per channel.
+pub const STREAM_CHANNEL_CAPACITY: usize = 64;
+
+/// Capacity of the channel carrying messages from background tasks to the UI.
+///
+/// Background tasks wait in `send` while it is full. The UI empties it every
+/// frame in `process_messages`, so it only fills up while a frame is late.
+pub const UI_CHANNEL_CAPACITY: usize = 256;
+
+/// Which text a TTS conversion is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtsTarget {
+    Source,
+    Translation,
+}
+
+/// A step of getting a text's audio ready.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TtsUpdate {
+    /// The audio was already cached, at this path
+    Cached(String),
+    /// Nothing was cached, the conversion started
+    Started,
+    /// The conversion finished, the audio is at this path
+    Completed(String),
+    /// The conversion failed
+    Failed(String),
+    /// The conversion was cancelled from the task list
+    Cancelled,
+}
+
+/// What a warning about a translation is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// The stream stayed below the throughput floor
+    SlowStream,
+    /// Chunks of the response couldn't be read and were skipped
+    MalformedResponse,
+    /// The translation doesn't have as many lines as the source
+    LineCount,
+    /// A glossary term wasn't used in the translation
+    GlossaryMiss,
+    /// Redacted values are missing from the translation
+    Redaction,
+    /// Part of the response was removed before it was shown
+    Sanitization,
+    /// A term of a chunked translation got more than one translation
+    Terminology,
+}
+
+impl WarningKind {
+    /// Short name shown next to the warning.
+    pub fn label(self) -> &'static str {
+        match self {
+            WarningKind::SlowStream => "Slow stream",
+            WarningKind::MalformedResponse => "Malformed response",
+            WarningKind::LineCount => "Line count",
+            WarningKind::GlossaryMiss => "Glossary",
+            WarningKind::Redaction => "Redaction",
+            WarningKind::Sanitization => "Sanitization",
+            WarningKind::Terminology => "Terminology",
+        }
+    }
+}
+
+/// A problem with a translation that doesn't stop it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub text: String,
+}
 
 /// Messages sent from background tasks to the UI.
 #[derive(Debug, Clone)]
 pub enum UiMessage {
     /// A chunk of translation text has been received
     UpdateTranslation(String),
+    /// A chunk of the model's reasoning, apart from the translation
+    UpdateReasoning(String),
+    /// Alternatives offered for a short input, the first one is the translation
+    Alternatives(Vec<Alternative>),
+    /// Per-item result of a list translation
+    ListTranslated(ListTranslation),
+    /// The provider declined to translate the text, with the reason
+    TranslationRefused(String),
+    /// Intermediate text of a translation through a pivot language
+    PivotText(String),
+    /// The translation comes from the legacy cache
+    LegacyCache,
+    /// A list item retried on its own has been translated
+    ListItemTranslated(usize, String),
+    /// Retrying a list item failed
+    ListItemFailed(usize, String),
     /// An error occurred during translation
     Error(String),
+    /// Translation failed because the service could not be reached
+    Offline(String),
+    /// Result of an update check, the newer release if there is one
+    UpdateChecked(Result<Option<Release>, String>),
+    /// Result of a background connectivity probe
+    ConnectivityChecked(bool),
+    /// The connection to the provider was opened ahead of the first request
+    ConnectionWarmed,
+    /// The translation request failed with a transient error and is sent
+    /// again, this being the attempt number
+    Retrying(u32),
     /// Translation has completed successfully
     TranslationComplete,
+    /// Translation stopped at the output limit and can be continued
+    TranslationTruncated,
+    /// Translation was stopped because the model kept repeating itself, the
+    /// last this many characters received are the repeats
+    TranslationLooped(usize),
     /// Translation was cancelled by the user
     TranslationCancelled,
+    /// A chunk of the explanation of the translation has been received
+    UpdateExplanation(String),
+    /// The explanation has completed successfully
+    ExplanationComplete,
+    /// The explanation request failed
+    ExplanationFailed(String),
+    /// The explanation was cancelled by the user
+    ExplanationCancelled,
+    /// Answer, or why there is none, to which source phrase became a word
+    /// of the translation
+    Aligned {
+        /// [`crate::utils::alignment::pair_hash`] of the source and translation
+        pair: u64,
+        word: String,
+        result: Result<String, String>,
+    },
+    /// Raw answer of a confidence check, or why there is none
+    ConfidenceChecked {
+        /// [`crate::utils::alignment::pair_hash`] of the source and translation
+        pair: u64,
+        result: Result<String, String>,
+    },
+    /// Romanization of the source text, or why there is none
+    Romanized {
+        /// The source text as typed
+        source: String,
+        scheme: Scheme,
+        result: Result<String, String>,
+    },
+    /// The completion hook run after a translation failed
+    HookFailed(String),
+    /// Text extracted from an opened PDF, with page markers
+    PdfExtracted(String),
+    /// No text could be taken from an opened PDF
+    PdfFailed(String),
+    /// Bytes of a linked web page received so far
+    PageProgress(usize),
+    /// Readable text of a linked web page, headed by its title
+    PageExtracted(String),
+    /// A linked web page could not be fetched or had no text
+    PageFailed(String),
+    /// The history was exported as a translation memory
+    TmxExported {
+        path: PathBuf,
+        units: usize,
+        skipped: usize,
+    },
+    /// Exporting the history failed
+    TmxExportFailed(String),
+    /// The texts were stripped from the translation log, with the number
+    /// of entries purged
+    LogPurged(Result<usize, String>),
+    /// Per-value result of translating values of a JSON or YAML document
+    ValuesTranslated(Vec<(usize, Result<String, ValueError>)>),
+    /// Translating values of a JSON or YAML document failed
+    ValuesFailed(String),
+    /// Old files were cleaned up, from "Clean now" if `manual`
+    StorageCleaned { report: Report, manual: bool },
+    /// Space taken by the app's files
+    StorageUsage(Vec<Usage>),
+    /// Rolling characters per second of the running translation
+    Throughput(f64),
+    /// Metrics of a finished translation or explanation request
+    TranslationMetrics(RequestMetrics),
+    /// Problem with the running translation that doesn't stop it
+    Warning { text: String, kind: WarningKind },
     #[allow(dead_code)]
     /// Request to start TTS for source text
     RequestSourceTts(String),
@@ -25,21 +212,126 @@ pub enum UiMessage {
     #[allow(dead_code)]
     /// Request to stop audio playback
     StopPlayback,
-    /// TTS conversion started for source text
-    SourceTtsStarted,
-    /// TTS conversion started for translation text
-    TranslationTtsStarted,
-    /// TTS conversion completed for source text
-    SourceTtsCompleted(String),
-    /// TTS conversion completed for translation text
-    TranslationTtsCompleted(String),
-    /// TTS conversion failed
-    TtsFailed(String),
+    /// Progress of getting the audio of the source or the translation
+    Tts(TtsTarget, TtsUpdate),
     #[allow(dead_code)]
     /// Audio playback state changed
     PlaybackStateChanged(PlaybackState),
 }
 
+impl UiMessage {
+    /// Whether the message ends the running translation, successfully or not.
+    ///
+    /// Everything else, warnings included, arrives while it keeps running.
+    pub fn ends_translation(&self) -> bool {
+        matches!(
+            self,
+            UiMessage::TranslationComplete
+                | UiMessage::TranslationTruncated
+                | UiMessage::TranslationLooped(_)
+                | UiMessage::TranslationCancelled
+                | UiMessage::TranslationRefused(_)
+                | UiMessage::Error(_)
+                | UiMessage::Offline(_)
+        )
+    }
+
+    /// Whether the message comes from the stream of the running translation.
+    ///
+    /// These are shown in the tab the translation was started in, even
+    /// after another tab was selected.
+    pub fn belongs_to_translation(&self) -> bool {
+        self.ends_translation()
+            || matches!(
+                self,
+                UiMessage::UpdateTranslation(_)
+                    | UiMessage::UpdateReasoning(_)
+                    | UiMessage::Alternatives(_)
+                    | UiMessage::ListTranslated(_)
+                    | UiMessage::PivotText(_)
+                    | UiMessage::LegacyCache
+                    | UiMessage::TranslationMetrics(_)
+                    | UiMessage::Warning {
+                        kind: WarningKind::SlowStream
+                            | WarningKind::MalformedResponse
+                            | WarningKind::Terminology,
+                        ..
+                    }
+            )
+    }
+}
+
+/// The UI side of the message channel.
+///
+/// Owned directly by the app and only used from the UI thread.
+pub struct UiChannel {
+    tx: Sender<UiMessage>,
+    rx: Receiver<UiMessage>,
+    /// Messages the UI thread sent to itself, kept apart so it never waits
+    /// on the bounded channel
+    local: Vec<UiMessage>,
+    capacity: usize,
+}
+
+impl Default for UiChannel {
+    fn default() -> Self {
+        UiChannel::with_capacity(UI_CHANNEL_CAPACITY)
+    }
+}
+
+impl UiChannel {
+    /// Creates a channel holding up to `capacity` messages from background tasks.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (tx, rx) = mpsc::channel(capacity);
+        UiChannel {
+            tx,
+            rx,
+            local: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Returns a sender for background tasks.
+    ///
+    /// Async tasks should `send(..).await`, blocking threads `blocking_send`.
+    pub fn sender(&self) -> Sender<UiMessage> {
+        self.tx.clone()
+    }
+
+    /// Sends a message from the UI thread to itself (handled next frame).
+    pub fn send(&mut self, msg: UiMessage) {
+        self.local.push(msg);
+    }
+
+    /// Drains all pending messages without blocking.
+    ///
+    /// Messages the UI thread sent to itself come first. Draining frees the
+    /// channel, so background tasks waiting in `send` resume.
+    ///
+    /// If the channel has been disconnected, the pair is recreated so that
+    /// new tasks can report again; messages from tasks holding the old
+    /// sender are lost.
+    pub fn drain(&mut self) -> Vec<UiMessage> {
+        let mut messages = std::mem::take(&mut self.local);
+        loop {
+            match self.rx.try_recv() {
+                Ok(msg) => messages.push(msg),
+                // The channel holds a sender itself, so a closed receiver
+                // reports `Empty` rather than `Disconnected`
+                Err(TryRecvError::Empty) if !self.rx.is_closed() => break,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => {
+                    tracing::error!("UI message channel disconnected, recreating it");
+                    let (tx, rx) = mpsc::channel(self.capacity);
+                    self.tx = tx;
+                    self.rx = rx;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +363,140 @@ mod tests {
             _ => panic!("Clone failed"),
         }
     }
+
+    #[test]
+    fn test_drain_collects_pending_messages() {
+        let mut channel = UiChannel::default();
+        let tx = channel.sender();
+        tx.try_send(UiMessage::TranslationComplete).unwrap();
+        channel.send(UiMessage::TranslationCancelled);
+
+        let messages = channel.drain();
+        assert!(matches!(
+            &messages[..],
+            [
+                UiMessage::TranslationCancelled,
+                UiMessage::TranslationComplete
+            ]
+        ));
+        assert!(channel.drain().is_empty());
+    }
+
+    #[test]
+    fn test_warnings_keep_the_translation_running() {
+        let warning = UiMessage::Warning {
+            text: "Stream unusually slow".to_string(),
+            kind: WarningKind::SlowStream,
+        };
+        assert!(!warning.ends_translation());
+        assert!(UiMessage::Error("failed".to_string()).ends_translation());
+        assert!(UiMessage::TranslationComplete.ends_translation());
+
+        // Pumped like the app does: partial output and the busy state
+        // survive warnings arriving between the chunks
+        let mut channel = UiChannel::default();
+        let tx = channel.sender();
+        for msg in [
+            UiMessage::UpdateTranslation("Hal".to_string()),
+            warning.clone(),
+            UiMessage::UpdateTranslation("lo".to_string()),
+            warning,
+        ] {
+            tx.try_send(msg).unwrap();
+        }
+        let mut translation = String::new();
+        let mut warnings = Vec::new();
+        let mut busy = true;
+        for msg in channel.drain() {
+            busy &= !msg.ends_translation();
+            match msg {
+                UiMessage::UpdateTranslation(chunk) => translation.push_str(&chunk),
+                UiMessage::Warning { text, kind } => warnings.push(Warning { kind, text }),
+                _ => {}
+            }
+        }
+        assert_eq!(translation, "Hallo");
+        assert_eq!(warnings.len(), 2);
+        assert!(busy);
+    }
+
+    #[test]
+    fn test_stream_messages_belong_to_translation() {
+        assert!(UiMessage::UpdateTranslation("Hal".to_string()).belongs_to_translation());
+        assert!(UiMessage::TranslationCancelled.belongs_to_translation());
+        assert!(
+            UiMessage::Warning {
+                text: "slow".to_string(),
+                kind: WarningKind::SlowStream,
+            }
+            .belongs_to_translation()
+        );
+
+        // Warnings raised by the app itself, and the explanation, don't
+        assert!(
+            !UiMessage::Warning {
+                text: "lines".to_string(),
+                kind: WarningKind::LineCount,
+            }
+            .belongs_to_translation()
+        );
+        assert!(!UiMessage::UpdateExplanation("Weil".to_string()).belongs_to_translation());
+        assert!(!UiMessage::Throughput(12.0).belongs_to_translation());
+        assert!(!UiMessage::Retrying(2).belongs_to_translation());
+    }
+
+    #[tokio::test]
+    async fn test_full_channel_holds_back_senders_until_drained() {
+        let mut channel = UiChannel::with_capacity(4);
+        let tx = channel.sender();
+        let producer = tokio::spawn(async move {
+            for i in 0..100 {
+                tx.send(UiMessage::UpdateTranslation(i.to_string()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut received = Vec::new();
+        while received.len() < 100 {
+            tokio::task::yield_now().await;
+            let messages = channel.drain();
+            assert!(messages.len() <= 4, "drained {} messages", messages.len());
+            received.extend(messages);
+        }
+        producer.await.unwrap();
+
+        // The UI thread's own messages never wait for the channel
+        for _ in 0..10 {
+            channel.send(UiMessage::TranslationComplete);
+        }
+        assert_eq!(channel.drain().len(), 10);
+        assert!(matches!(&received[99], UiMessage::UpdateTranslation(s) if s == "99"));
+    }
+
+    #[test]
+    fn test_drain_recovers_from_disconnected_channel() {
+        let mut channel = UiChannel::default();
+        let old_tx = channel.sender();
+        old_tx
+            .try_send(UiMessage::UpdateTranslation("before".to_string()))
+            .unwrap();
+        channel.rx.close();
+
+        // Messages sent before the close are still delivered, then the pair is recreated
+        let messages = channel.drain();
+        assert!(matches!(&messages[..], [UiMessage::UpdateTranslation(s)] if s == "before"));
+        assert!(channel.drain().is_empty());
+
+        // Old senders are cut off, new ones work
+        assert!(old_tx.try_send(UiMessage::TranslationComplete).is_err());
+        channel
+            .sender()
+            .try_send(UiMessage::TranslationComplete)
+            .unwrap();
+        assert!(matches!(
+            &channel.drain()[..],
+            [UiMessage::TranslationComplete]
+        ));
+    }
 }
@@ -3,6 +3,7 @@
 //! This module defines all error types that can occur during translation operations,
 //! using the `thiserror` crate for automatic trait implementations.
 
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Main error type for translation operations.
@@ -12,9 +13,14 @@ pub enum TranslationError {
     #[error("API error: {0}")]
     ApiError(String),
 
-    /// Network-related errors from reqwest
+    /// The API answered with an error status
+    #[error("API error: {0}")]
+    HttpStatus(reqwest::StatusCode),
+
+    /// Network-related errors from reqwest, shared by the receivers of
+    /// the same response
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(#[source] Arc<reqwest::Error>),
 
     /// JSON serialization/deserialization errors
     #[error("Serialization error: {0}")]
@@ -26,7 +32,6 @@ pub enum TranslationError {
 
     /// Configuration errors
     #[error("Configuration error: {0}")]
-    #[allow(dead_code)]
     ConfigError(String),
 
     /// IO errors
@@ -38,12 +43,118 @@ pub enum TranslationError {
     #[allow(dead_code)]
     InvalidApiKey,
 
+    /// The provider stopped at its output limit before the text was complete
+    #[error("Output truncated at the length limit")]
+    Truncated,
+
+    /// The model kept repeating itself and the response was stopped; the
+    /// last `repeated_chars` characters of the response are the repeats
+    #[error("Output repetition detected — stopped early")]
+    RepetitionDetected { repeated_chars: usize },
+
+    /// The provider declined to translate the content
+    #[error("The provider declined to translate this content ({0})")]
+    ContentFiltered(String),
+
+    /// The request was aborted before its response ended
+    #[error("Request cancelled")]
+    Cancelled,
+
+    /// One stage of a translation through a pivot language failed
+    #[error("{stage} failed: {source}")]
+    PivotStage {
+        stage: String,
+        source: Box<TranslationError>,
+    },
+
     /// General translation failure
     #[error("Translation failed: {0}")]
     #[allow(dead_code)]
     TranslationFailed(String),
 }
 
+impl TranslationError {
+    /// Why the provider declined the content, if that is what happened.
+    pub fn refusal_reason(&self) -> Option<&str> {
+        match self {
+            TranslationError::ContentFiltered(reason) => Some(reason),
+            TranslationError::PivotStage { source, .. } => source.refusal_reason(),
+            _ => None,
+        }
+    }
+
+    /// A copy of the error for another receiver of the same response.
+    ///
+    /// Serialization and IO errors can't be copied and become a
+    /// [`TranslationError::TranslationFailed`] with their message; network
+    /// errors are shared, so the copy still tells whether it is offline.
+    pub fn replicate(&self) -> TranslationError {
+        match self {
+            TranslationError::ApiError(message) => TranslationError::ApiError(message.clone()),
+            TranslationError::HttpStatus(status) => TranslationError::HttpStatus(*status),
+            TranslationError::StreamError(message) => {
+                TranslationError::StreamError(message.clone())
+            }
+            TranslationError::ConfigError(message) => {
+                TranslationError::ConfigError(message.clone())
+            }
+            TranslationError::InvalidApiKey => TranslationError::InvalidApiKey,
+            TranslationError::Truncated => TranslationError::Truncated,
+            TranslationError::Cancelled => TranslationError::Cancelled,
+            TranslationError::RepetitionDetected { repeated_chars } => {
+                TranslationError::RepetitionDetected {
+                    repeated_chars: *repeated_chars,
+                }
+            }
+            TranslationError::ContentFiltered(reason) => {
+                TranslationError::ContentFiltered(reason.clone())
+            }
+            TranslationError::PivotStage { stage, source } => TranslationError::PivotStage {
+                stage: stage.clone(),
+                source: Box::new(source.replicate()),
+            },
+            TranslationError::TranslationFailed(message) => {
+                TranslationError::TranslationFailed(message.clone())
+            }
+            TranslationError::NetworkError(e) => TranslationError::NetworkError(e.clone()),
+            TranslationError::SerializationError(e) => {
+                TranslationError::TranslationFailed(e.to_string())
+            }
+            TranslationError::IoError(e) => TranslationError::TranslationFailed(e.to_string()),
+        }
+    }
+
+    /// Whether the error means the service could not be reached at all.
+    pub fn is_offline(&self) -> bool {
+        match self {
+            TranslationError::NetworkError(e) => e.is_connect() || e.is_timeout(),
+            TranslationError::PivotStage { source, .. } => source.is_offline(),
+            _ => false,
+        }
+    }
+
+    /// Whether the same request may well succeed a moment later: the
+    /// provider was busy or failed itself, or it couldn't be reached.
+    ///
+    /// A request that timed out once connected isn't transient, as the
+    /// provider may already be working on it.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            TranslationError::HttpStatus(status) => {
+                *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            TranslationError::NetworkError(e) => e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for TranslationError {
+    fn from(e: reqwest::Error) -> Self {
+        TranslationError::NetworkError(Arc::new(e))
+    }
+}
+
 /// Type alias for Results using `TranslationError`.
 pub type Result<T> = std::result::Result<T, TranslationError>;
 
@@ -58,6 +169,79 @@ mod tests {
 
         let err = TranslationError::InvalidApiKey;
         assert_eq!(err.to_string(), "Invalid API key");
+
+        let err = TranslationError::Truncated;
+        assert_eq!(err.to_string(), "Output truncated at the length limit");
+
+        let err = TranslationError::PivotStage {
+            stage: "Stage 1 (into English)".to_string(),
+            source: Box::new(TranslationError::Truncated),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Stage 1 (into English) failed: Output truncated at the length limit"
+        );
+    }
+
+    #[test]
+    fn test_refusal_reason_looks_through_pivot_stages() {
+        let refused = TranslationError::ContentFiltered("content_filter".to_string());
+        assert_eq!(refused.refusal_reason(), Some("content_filter"));
+        let staged = TranslationError::PivotStage {
+            stage: "Stage 2 (English into Deutsch)".to_string(),
+            source: Box::new(refused),
+        };
+        assert_eq!(staged.refusal_reason(), Some("content_filter"));
+        assert_eq!(TranslationError::Truncated.refusal_reason(), None);
+    }
+
+    #[test]
+    fn test_replicate() {
+        let staged = TranslationError::PivotStage {
+            stage: "Stage 1 (into English)".to_string(),
+            source: Box::new(TranslationError::ContentFiltered("policy".to_string())),
+        };
+        let copy = staged.replicate();
+        assert_eq!(copy.to_string(), staged.to_string());
+        assert_eq!(copy.refusal_reason(), Some("policy"));
+
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
+        let copy = TranslationError::from(io_err).replicate();
+        assert_eq!(copy.to_string(), "Translation failed: file not found");
+    }
+
+    #[tokio::test]
+    async fn test_replicated_network_error_is_offline() {
+        // Nothing listens on port 1
+        let e = reqwest::Client::new()
+            .get("http://127.0.0.1:1")
+            .send()
+            .await
+            .unwrap_err();
+        let error = TranslationError::from(e);
+        assert!(error.is_offline());
+        assert!(error.replicate().is_offline());
+        assert_eq!(error.replicate().to_string(), error.to_string());
+    }
+
+    #[test]
+    fn test_api_errors_are_not_offline() {
+        assert!(!TranslationError::ApiError("500".to_string()).is_offline());
+        assert!(!TranslationError::StreamError("reset".to_string()).is_offline());
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        use reqwest::StatusCode;
+
+        let status = |code| TranslationError::HttpStatus(StatusCode::from_u16(code).unwrap());
+        assert!(status(429).is_transient());
+        assert!(status(502).is_transient());
+        assert!(status(503).is_transient());
+        assert!(!status(401).is_transient());
+        assert!(!status(400).is_transient());
+        assert!(!TranslationError::Truncated.is_transient());
+        assert_eq!(status(429).to_string(), "API error: 429 Too Many Requests");
     }
 
     #[test]
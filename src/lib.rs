@@ -0,0 +1,12 @@
+//! Library half of the AI Translation Tool.
+//!
+//! The binary only sets up logging and the window; everything else lives
+//! here, so integration tests under `tests/` can drive the translation
+//! pipeline the same way the app does.
+
+pub mod api;
+pub mod channel;
+pub mod error;
+pub mod services;
+pub mod ui;
+pub mod utils;
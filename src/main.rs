@@ -19,41 +19,114 @@
 //! RUST_LOG=debug ./ai-translate
 //! RUST_LOG=ai_translate=trace ./ai-translate
 //! ```
+//!
+//! Files named on the command line are opened. If the app is already
+//! running they are handed to that window instead; `--new-instance` starts
+//! a second window anyway, which saves nothing.
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod api;
-mod channel;
-mod error;
-mod services;
-mod ui;
-mod utils;
-
+use ai_translate::ui::TranslateApp;
+use ai_translate::utils::diagnostics::TraceBuffer;
+use ai_translate::utils::instance::{self, Acquired, CommandLine, InstanceLock, Launch};
+use ai_translate::utils::layout;
 use eframe::egui;
-use ui::TranslateApp;
+use std::io;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
 
 fn main() -> Result<(), eframe::Error> {
-    // Initialize tracing with RUST_LOG support
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // Initialize tracing with RUST_LOG support, keeping recent output for
+    // diagnostic bundles; closed spans report how long startup steps took
+    let trace_buffer = TraceBuffer::default();
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
         )
+        .with(tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(trace_buffer.clone()),
+        )
         .init();
 
+    let command_line = CommandLine::parse(std::env::args().skip(1));
+    // Held until the window closes
+    let mut _lock: Option<InstanceLock> = None;
+    let mut launch = Launch {
+        files: command_line.files.clone(),
+        ..Default::default()
+    };
+    if command_line.new_instance {
+        tracing::warn!(
+            "Starting another instance on request; settings, cache and history won't be saved"
+        );
+        launch.persist = false;
+    } else {
+        match instance::acquire(&instance::lock_path()) {
+            Ok(Acquired::Primary(lock)) => {
+                match lock.listen() {
+                    Ok(forwarded) => launch.forwarded = Some(forwarded),
+                    Err(e) => tracing::warn!("Later launches can't reach this instance: {}", e),
+                }
+                _lock = Some(lock);
+            }
+            Ok(Acquired::Secondary(endpoint)) => {
+                match instance::forward(&endpoint, &command_line.forwarded_args()) {
+                    Ok(()) => {
+                        tracing::info!(
+                            "Handed over to the running instance (pid {})",
+                            endpoint.pid
+                        );
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "The running instance (pid {}) didn't answer, starting without saving: {}",
+                            endpoint.pid,
+                            e
+                        );
+                        launch.persist = false;
+                    }
+                }
+            }
+            // Held by an instance that can't be reached, e.g. one still
+            // starting up
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                tracing::warn!(
+                    "Another instance runs but can't be reached, starting without saving: {}",
+                    e
+                );
+                launch.persist = false;
+            }
+            // Whether another instance runs is unknown, so start as before
+            Err(e) => tracing::warn!("Failed to lock {:?}: {}", instance::lock_path(), e),
+        }
+    }
+
     tracing::info!("Starting AI Translate Tool");
 
-    let options = eframe::NativeOptions {
+    let mut options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([900.0, 600.0])
-            .with_min_inner_size([800.0, 500.0])
+            .with_min_inner_size(layout::MIN_WINDOW_SIZE)
             .with_app_id("ai-translate"),
         ..Default::default()
     };
+    if !launch.persist {
+        options.persistence_path = Some(instance::scratch_dir());
+    }
 
     eframe::run_native(
         "AI Translate Tool",
         options,
-        Box::new(|cc| Ok(Box::new(TranslateApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(TranslateApp::new(cc, trace_buffer, launch)))),
     )
 }
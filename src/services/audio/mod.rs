@@ -4,10 +4,13 @@
 //! and controlling audio playback.
 
 mod player;
+pub mod validate;
 
-pub use player::{AudioPlayer, PlaybackState};
+pub use player::{AudioPlayer, PlaybackProgress, PlaybackState, PlaybackVolume, SKIP_STEP};
 
 use crate::lock_mutex;
+use crate::utils::loading::{self, LoadGate};
+use crate::utils::paths;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -35,12 +38,35 @@ struct AudioCacheEntry {
     timestamp: i64,
 }
 
+/// Audio cache entries removed by [`AudioCache::take_all`].
+///
+/// The audio files stay on disk until [`Self::purge`], so the removal can be
+/// undone with [`AudioCache::restore`].
+pub struct AudioCacheTombstone {
+    entries: HashMap<String, AudioCacheEntry>,
+}
+
+impl AudioCacheTombstone {
+    /// Permanently deletes the audio files.
+    pub fn purge(self) {
+        for entry in self.entries.values() {
+            if entry.audio_path.exists()
+                && let Err(e) = fs::remove_file(&entry.audio_path)
+            {
+                tracing::warn!("Failed to remove audio file {:?}: {}", entry.audio_path, e);
+            }
+        }
+    }
+}
+
 /// Audio cache manager with 100-entry limit
 pub struct AudioCache {
     cache: Arc<Mutex<HashMap<String, AudioCacheEntry>>>,
     cache_dir: PathBuf,
     index_file: PathBuf,
     max_entries: usize,
+    /// Opens once the index is loaded
+    loaded: LoadGate,
 }
 
 impl AudioCache {
@@ -50,6 +76,32 @@ impl AudioCache {
     ///
     /// * `cache_dir` - Directory to store audio cache files
     pub fn new(cache_dir: PathBuf) -> Self {
+        let audio_cache = Self::unloaded(cache_dir);
+        let cache = Self::load_cache_from_index(&audio_cache.index_file, &audio_cache.cache_dir);
+        *lock_mutex!(audio_cache.cache) = cache;
+        audio_cache.loaded.open();
+        audio_cache
+    }
+
+    /// Creates an audio cache whose index is loaded on a background thread.
+    ///
+    /// Until it is, lookups miss and writes wait for it.
+    pub fn load_in_background(cache_dir: PathBuf) -> Self {
+        let audio_cache = Self::unloaded(cache_dir);
+        let cache = audio_cache.cache.clone();
+        let index_file = audio_cache.index_file.clone();
+        let cache_dir = audio_cache.cache_dir.clone();
+        let loaded = audio_cache.loaded.clone();
+        loading::spawn("audio-cache", move || {
+            let entries = Self::load_cache_from_index(&index_file, &cache_dir);
+            *lock_mutex!(cache) = entries;
+            loaded.open();
+        });
+        audio_cache
+    }
+
+    /// An audio cache without entries whose index hasn't been loaded.
+    fn unloaded(cache_dir: PathBuf) -> Self {
         tracing::info!("Initializing audio cache at: {:?}", cache_dir);
 
         let index_file = cache_dir.join("cache_index.json");
@@ -57,14 +109,12 @@ impl AudioCache {
         // Create cache directory if it doesn't exist
         let _ = fs::create_dir_all(&cache_dir);
 
-        // Load cache from index file
-        let cache = Self::load_cache_from_index(&index_file, &cache_dir);
-
         AudioCache {
-            cache: Arc::new(Mutex::new(cache)),
+            cache: Arc::new(Mutex::new(HashMap::new())),
             cache_dir,
             index_file,
             max_entries: 100,
+            loaded: LoadGate::closed(),
         }
     }
 
@@ -116,6 +166,7 @@ impl AudioCache {
             return;
         }
 
+        self.loaded.wait();
         let key = Self::generate_key(text);
         let entry = AudioCacheEntry {
             audio_path: audio_path.clone(),
@@ -148,7 +199,9 @@ impl AudioCache {
     }
 
     /// Clears all entries from the cache
+    #[allow(dead_code)]
     pub fn clear(&self) {
+        self.loaded.wait();
         let cache = lock_mutex!(self.cache);
 
         tracing::info!("Clearing audio cache ({} entries)", cache.len());
@@ -170,16 +223,51 @@ impl AudioCache {
         }
     }
 
+    /// Removes all entries from the cache but keeps their audio files.
+    pub fn take_all(&self) -> AudioCacheTombstone {
+        self.loaded.wait();
+        let entries = std::mem::take(&mut *lock_mutex!(self.cache));
+        tracing::info!("Removed {} entries from the audio cache", entries.len());
+        self.save_cache_index();
+        AudioCacheTombstone { entries }
+    }
+
+    /// Puts back entries removed by [`Self::take_all`], keeping newer ones.
+    pub fn restore(&self, tombstone: AudioCacheTombstone) {
+        self.loaded.wait();
+        {
+            let mut cache = lock_mutex!(self.cache);
+            for (key, entry) in tombstone.entries {
+                if entry.audio_path.exists() {
+                    cache.entry(key).or_insert(entry);
+                }
+            }
+            tracing::info!("Restored audio cache to {} entries", cache.len());
+        }
+        self.save_cache_index();
+    }
+
+    /// Folder the audio files are kept in
+    pub fn dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     /// Gets the number of entries in the cache
     pub fn len(&self) -> usize {
         lock_mutex!(self.cache).len()
     }
 
+    /// Whether the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        lock_mutex!(self.cache).is_empty()
+    }
+
     /// Loads cache from index file
     fn load_cache_from_index(
         index_file: &Path,
         _cache_dir: &Path,
     ) -> HashMap<String, AudioCacheEntry> {
+        let _span = tracing::info_span!("load_audio_cache").entered();
         let mut cache = HashMap::new();
 
         if !index_file.exists() {
@@ -301,12 +389,7 @@ impl AudioCache {
 
 impl Default for AudioCache {
     fn default() -> Self {
-        let cache_dir = dirs::cache_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("ai-translate")
-            .join("audio");
-
-        Self::new(cache_dir)
+        Self::new(paths::audio_cache_dir())
     }
 }
 
@@ -324,6 +407,28 @@ mod tests {
         assert_eq!(key1, key3);
     }
 
+    #[test]
+    fn test_loading_in_background() {
+        let dir = std::env::temp_dir().join("test_audio_cache_background");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = AudioCache::new(dir.clone());
+        for text in ["one", "two"] {
+            let path = cache.get_new_audio_path(text);
+            fs::write(&path, b"RIFF").unwrap();
+            cache.set(text, path);
+        }
+
+        let loading = AudioCache::load_in_background(dir.clone());
+        let path = loading.get_new_audio_path("three");
+        fs::write(&path, b"RIFF").unwrap();
+        // The write waits for the index instead of replacing it
+        loading.set("three", path);
+        assert_eq!(loading.len(), 3);
+        assert!(loading.get("one").is_some());
+        assert_eq!(AudioCache::new(dir.clone()).len(), 3);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_audio_player_creation() {
         let player = AudioPlayer::new();
@@ -1,12 +1,18 @@
 //! Cross-platform audio player module.
 //!
 //! This module provides audio playback functionality for different platforms.
-//! It supports Windows, macOS, and Linux with appropriate audio players.
+//! Audio is decoded in-process with rodio (WAV, MP3, OGG/Vorbis, FLAC via
+//! symphonia); when no output device can be opened it falls back to the
+//! platform's command-line audio players.
 
 use crate::lock_mutex;
+use rodio::Source;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
 /// Playback state for audio player
@@ -17,6 +23,8 @@ pub enum PlaybackState {
     Idle,
     /// Audio is currently playing
     Playing(String),
+    /// Playback is paused and can be resumed where it stopped
+    Paused(String),
     #[allow(dead_code)]
     /// Playback completed successfully
     Completed,
@@ -24,18 +32,285 @@ pub enum PlaybackState {
     Failed(String),
 }
 
-/// Audio player for playing WAV files
+impl PlaybackState {
+    /// Whether audio is loaded for playback, playing or paused.
+    pub fn is_active(&self) -> bool {
+        matches!(self, PlaybackState::Playing(_) | PlaybackState::Paused(_))
+    }
+
+    /// The state after pausing, only playing audio can be paused.
+    pub fn paused(self) -> Self {
+        match self {
+            PlaybackState::Playing(path) => PlaybackState::Paused(path),
+            state => state,
+        }
+    }
+
+    /// The state after resuming, only paused audio can be resumed.
+    pub fn resumed(self) -> Self {
+        match self {
+            PlaybackState::Paused(path) => PlaybackState::Playing(path),
+            state => state,
+        }
+    }
+}
+
+/// How far playback has got through the audio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackProgress {
+    pub position: Duration,
+    pub duration: Duration,
+}
+
+impl PlaybackProgress {
+    /// Time left until the end of the audio.
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.position)
+    }
+}
+
+/// How far the skip buttons move playback.
+pub const SKIP_STEP: Duration = Duration::from_secs(5);
+
+/// How far before the end a seek lands at most, so the audio finishes by
+/// playing out instead of ending in the middle of the seek.
+const SEEK_END_MARGIN: Duration = Duration::from_millis(250);
+
+/// Where a seek to `target` lands in audio of `duration`.
+pub fn clamp_seek(target: Duration, duration: Option<Duration>) -> Duration {
+    match duration {
+        Some(duration) => target.min(duration.saturating_sub(SEEK_END_MARGIN)),
+        None => target,
+    }
+}
+
+/// Length of the WAV file at `path`, from its header.
+///
+/// Preferred to the length the decoder reports, which can be off by a
+/// rounding error for WAV files.
+fn wav_duration(path: &Path) -> Option<Duration> {
+    let mut file = File::open(path).ok()?;
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff).ok()?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return None;
+    }
+    let mut byte_rate = None;
+    loop {
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).ok()?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        match &header[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                file.read_exact(&mut fmt).ok()?;
+                byte_rate = Some(u32::from_le_bytes([fmt[8], fmt[9], fmt[10], fmt[11]]));
+                file.seek(SeekFrom::Current(
+                    i64::from(size) - 16 + i64::from(size % 2),
+                ))
+                .ok()?;
+            }
+            b"data" => {
+                let byte_rate = byte_rate.filter(|rate| *rate > 0)?;
+                return Some(Duration::from_secs_f64(
+                    f64::from(size) / f64::from(byte_rate),
+                ));
+            }
+            // Chunks are padded to an even length
+            _ => {
+                file.seek(SeekFrom::Current(i64::from(size) + i64::from(size % 2)))
+                    .ok()?;
+            }
+        }
+    }
+}
+
+/// Opens the audio file at `path` for decoding, whatever its extension.
+fn decode(path: &Path) -> Result<rodio::Decoder<BufReader<File>>, Box<dyn std::error::Error>> {
+    let file = File::open(path)?;
+    let source = rodio::Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("Unsupported or corrupt audio file: {}", e))?;
+    Ok(source)
+}
+
+/// Listening level applied at playback time, independent of the synthesized audio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackVolume {
+    /// Volume from 0.0 to 1.0
+    pub level: f32,
+    pub muted: bool,
+}
+
+impl Default for PlaybackVolume {
+    fn default() -> Self {
+        PlaybackVolume {
+            level: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl PlaybackVolume {
+    /// The volume to apply, 0.0 when muted.
+    pub fn effective(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.level.clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Builds the arguments for a command-line player, applying `volume` where
+/// the player supports it (see [`player_supports_volume`]).
+#[cfg(not(windows))]
+fn player_args(player: &str, file_path: &str, volume: f32) -> Vec<String> {
+    match player {
+        "ffplay" => vec![
+            "-nodisp".to_string(),
+            "-autoexit".to_string(),
+            "-volume".to_string(),
+            format!("{}", (volume * 100.0).round() as u32),
+            file_path.to_string(),
+        ],
+        "paplay" => vec![
+            format!("--volume={}", (volume * 65536.0).round() as u32),
+            file_path.to_string(),
+        ],
+        "afplay" => vec![
+            "-v".to_string(),
+            format!("{:.2}", volume),
+            file_path.to_string(),
+        ],
+        "aplay" => vec!["-q".to_string(), file_path.to_string()],
+        _ => vec![file_path.to_string()],
+    }
+}
+
+/// Whether a command-line player accepts a volume flag.
+#[cfg(not(windows))]
+fn player_supports_volume(player: &str) -> bool {
+    matches!(player, "ffplay" | "paplay" | "afplay")
+}
+
+/// Command-line players tried in order when no output device is available.
+#[cfg(target_os = "macos")]
+const FALLBACK_PLAYERS: [&str; 2] = ["afplay", "ffplay"];
+#[cfg(not(any(windows, target_os = "macos")))]
+const FALLBACK_PLAYERS: [&str; 3] = ["aplay", "paplay", "ffplay"];
+
+/// Audio player for playing local audio files
 pub struct AudioPlayer {
+    /// Handle to the rodio output stream, `None` if no device is available
+    output: OnceLock<Option<rodio::OutputStreamHandle>>,
+    /// The device being opened, until [`Self::output`] first needs it
+    opening: Mutex<Option<Receiver<Option<rodio::OutputStreamHandle>>>>,
+    /// Sink for in-process playback
+    sink: Arc<Mutex<Option<rodio::Sink>>>,
+    /// Child process for the command-line fallback
     current_process: Arc<Mutex<Option<std::process::Child>>>,
     state: Arc<Mutex<PlaybackState>>,
+    /// Length of the audio being played, if known
+    duration: Arc<Mutex<Option<Duration>>>,
+    volume: Arc<Mutex<PlaybackVolume>>,
+    /// Whether the listening level can be adjusted beyond muting
+    volume_adjustable: OnceLock<bool>,
+}
+
+/// Starts opening the default audio output device on a dedicated thread.
+///
+/// The rodio `OutputStream` is not `Send`, so it is kept alive on its own
+/// parked thread and only the (thread-safe) handle is sent back.
+fn open_output_device() -> Receiver<Option<rodio::OutputStreamHandle>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let spawned = std::thread::Builder::new()
+        .name("audio-output".to_string())
+        .spawn(move || match rodio::OutputStream::try_default() {
+            Ok((_stream, handle)) => {
+                let _ = tx.send(Some(handle));
+                // Keep the stream alive for the lifetime of the process
+                loop {
+                    std::thread::park();
+                }
+            }
+            Err(e) => {
+                tracing::warn!("No audio output device available: {}", e);
+                let _ = tx.send(None);
+            }
+        });
+
+    // The sender went with the thread, so receiving fails right away
+    if let Err(e) = spawned {
+        tracing::warn!("Failed to spawn audio output thread: {}", e);
+    }
+    rx
 }
 
 impl AudioPlayer {
     /// Creates a new audio player
+    ///
+    /// The output device is opened in the background and only waited for
+    /// on first use.
     pub fn new() -> Self {
         AudioPlayer {
+            output: OnceLock::new(),
+            opening: Mutex::new(Some(open_output_device())),
+            sink: Arc::new(Mutex::new(None)),
             current_process: Arc::new(Mutex::new(None)),
             state: Arc::new(Mutex::new(PlaybackState::Idle)),
+            duration: Arc::new(Mutex::new(None)),
+            volume: Arc::new(Mutex::new(PlaybackVolume::default())),
+            volume_adjustable: OnceLock::new(),
+        }
+    }
+
+    /// The output device, waiting for it to be opened if necessary.
+    fn output(&self) -> Option<&rodio::OutputStreamHandle> {
+        self.output
+            .get_or_init(|| {
+                let _span = tracing::info_span!("open_audio_output").entered();
+                lock_mutex!(self.opening)
+                    .take()
+                    .and_then(|opening| opening.recv().ok())
+                    .flatten()
+            })
+            .as_ref()
+    }
+
+    /// Whether the command-line player that would be used accepts a volume.
+    #[cfg(windows)]
+    fn fallback_supports_volume() -> bool {
+        true
+    }
+
+    #[cfg(not(windows))]
+    fn fallback_supports_volume() -> bool {
+        FALLBACK_PLAYERS
+            .iter()
+            .find(|player| Self::which_command(player).is_ok())
+            .is_some_and(|player| player_supports_volume(player))
+    }
+
+    /// Whether the volume slider has any effect on this system.
+    pub fn volume_adjustable(&self) -> bool {
+        *self.volume_adjustable.get_or_init(|| {
+            if self.output().is_some() {
+                true
+            } else {
+                tracing::info!("Falling back to command-line audio players");
+                Self::fallback_supports_volume()
+            }
+        })
+    }
+
+    /// Sets the listening level, applied immediately to in-process playback.
+    ///
+    /// Command-line players pick up the new level on the next playback.
+    pub fn set_volume(&self, volume: PlaybackVolume) {
+        *lock_mutex!(self.volume) = volume;
+        if let Some(sink) = lock_mutex!(self.sink).as_ref() {
+            sink.set_volume(volume.effective());
         }
     }
 
@@ -49,9 +324,78 @@ impl AudioPlayer {
         matches!(self.get_state(), PlaybackState::Playing(_))
     }
 
+    /// Length of the audio being played, if known.
+    pub fn duration(&self) -> Option<Duration> {
+        *lock_mutex!(self.duration)
+    }
+
+    /// Position of playback in the audio, known for in-process playback.
+    pub fn position(&self) -> Option<Duration> {
+        lock_mutex!(self.sink).as_ref().map(rodio::Sink::get_pos)
+    }
+
+    /// Position and length of the audio, while both are known.
+    pub fn progress(&self) -> Option<PlaybackProgress> {
+        if !self.get_state().is_active() {
+            return None;
+        }
+        let duration = self.duration()?;
+        let position = self.position()?.min(duration);
+        Some(PlaybackProgress { position, duration })
+    }
+
+    /// Moves playback to `target`, clamped to the audio, returning where it
+    /// landed.
+    ///
+    /// Paused audio stays paused.
+    pub fn seek(&self, target: Duration) -> Result<Duration, Box<dyn std::error::Error>> {
+        if !self.get_state().is_active() {
+            return Err("Nothing is playing".into());
+        }
+        let sink = lock_mutex!(self.sink);
+        let Some(sink) = sink.as_ref() else {
+            return Err("Seeking needs in-process playback".into());
+        };
+        let position = clamp_seek(target, self.duration());
+        sink.try_seek(position)
+            .map_err(|e| format!("Can't seek in this audio: {}", e))?;
+        Ok(position)
+    }
+
+    /// Pauses playing audio or resumes paused audio, in-process playback only.
+    pub fn toggle_pause(&self) {
+        let sink = lock_mutex!(self.sink);
+        let Some(sink) = sink.as_ref() else {
+            return;
+        };
+        let mut state = lock_mutex!(self.state);
+        match &*state {
+            PlaybackState::Playing(_) => {
+                sink.pause();
+                *state = state.clone().paused();
+            }
+            PlaybackState::Paused(_) => {
+                sink.play();
+                *state = state.clone().resumed();
+            }
+            _ => {}
+        }
+    }
+
     /// Updates playback state if playback has finished
     pub fn update_state_if_finished(&self) {
         if self.is_playing() {
+            {
+                let mut sink = lock_mutex!(self.sink);
+                if sink.as_ref().is_some_and(|s| s.empty()) {
+                    tracing::info!("Audio playback finished");
+                    *sink = None;
+                    *lock_mutex!(self.state) = PlaybackState::Idle;
+                    *lock_mutex!(self.duration) = None;
+                    return;
+                }
+            }
+
             let mut process = lock_mutex!(self.current_process);
             if let Some(mut child) = process.take() {
                 // Try to check if process has finished
@@ -68,6 +412,11 @@ impl AudioPlayer {
 
     /// Stops the current playback if any
     pub fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Stop in-process playback
+        if let Some(sink) = lock_mutex!(self.sink).take() {
+            sink.stop();
+        }
+
         // Kill current process if running
         {
             let mut process = lock_mutex!(self.current_process);
@@ -102,32 +451,53 @@ impl AudioPlayer {
         }
 
         // Update state
-        *self.state.lock().expect("State mutex poisoned") = PlaybackState::Idle;
+        *lock_mutex!(self.state) = PlaybackState::Idle;
+        *lock_mutex!(self.duration) = None;
 
         Ok(())
     }
 
     /// Plays the specified audio file
     pub fn play(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.play_file(Path::new(file_path))
+    }
+
+    /// Plays an arbitrary local audio file (WAV, MP3, OGG, FLAC).
+    ///
+    /// Unlike the TTS flow this does not consult or update the audio cache,
+    /// so it can be used for user-supplied recordings.
+    pub fn play_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
         // Check if file exists
-        if !Path::new(file_path).exists() {
-            return Err(format!("Audio file not found: {}", file_path).into());
+        if !path.exists() {
+            return Err(format!("Audio file not found: {}", path.display()).into());
         }
 
+        // Decoded even for the command-line players, so a file none could
+        // play fails here instead of in a player process
+        let source = decode(path)?;
+
         // Stop any currently playing audio
         self.stop()?;
 
-        // Start playback based on platform
-        let child = self.play_audio(file_path)?;
-
-        // Store process and update state
-        {
-            let mut process = lock_mutex!(self.current_process);
-            *process = Some(child);
+        let volume = *lock_mutex!(self.volume);
+        if let Some(handle) = self.output() {
+            let duration = wav_duration(path).or_else(|| source.total_duration());
+            let sink = rodio::Sink::try_new(handle)?;
+            sink.set_volume(volume.effective());
+            sink.append(source);
+            *lock_mutex!(self.sink) = Some(sink);
+            *lock_mutex!(self.duration) = duration;
+        } else if volume.muted && !self.volume_adjustable() {
+            // The player cannot be silenced, so don't start it at all
+            tracing::info!("Playback muted, skipping command-line player");
+            return Ok(());
+        } else {
+            // Start playback based on platform
+            let child = self.play_audio(&path.to_string_lossy(), volume.effective())?;
+            *lock_mutex!(self.current_process) = Some(child);
         }
 
-        *self.state.lock().expect("State mutex poisoned") =
-            PlaybackState::Playing(file_path.to_string());
+        *lock_mutex!(self.state) = PlaybackState::Playing(path.to_string_lossy().to_string());
 
         Ok(())
     }
@@ -135,18 +505,14 @@ impl AudioPlayer {
     /// Waits for the current playback to complete
     #[allow(dead_code)]
     pub fn wait_for_completion(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let process_opt = self
-            .current_process
-            .lock()
-            .expect("Process mutex poisoned")
-            .take();
+        let process_opt = lock_mutex!(self.current_process).take();
 
         if let Some(mut child) = process_opt {
             let status = child.wait()?;
             if status.success() {
-                *self.state.lock().expect("State mutex poisoned") = PlaybackState::Completed;
+                *lock_mutex!(self.state) = PlaybackState::Completed;
             } else {
-                *self.state.lock().expect("State mutex poisoned") =
+                *lock_mutex!(self.state) =
                     PlaybackState::Failed("Playback process exited with error".to_string());
             }
         }
@@ -158,20 +524,16 @@ impl AudioPlayer {
     fn play_audio(
         &self,
         file_path: &str,
+        volume: f32,
     ) -> Result<std::process::Child, Box<dyn std::error::Error>> {
         #[cfg(windows)]
         {
-            self.play_windows(file_path)
+            self.play_windows(file_path, volume)
         }
 
-        #[cfg(target_os = "macos")]
+        #[cfg(not(windows))]
         {
-            self.play_macos(file_path)
-        }
-
-        #[cfg(not(any(windows, target_os = "macos")))]
-        {
-            self.play_linux(file_path)
+            self.try_play_audio_players(&FALLBACK_PLAYERS, file_path, volume)
         }
     }
 
@@ -179,6 +541,7 @@ impl AudioPlayer {
     fn play_windows(
         &self,
         file_path: &str,
+        volume: f32,
     ) -> Result<std::process::Child, Box<dyn std::error::Error>> {
         tracing::info!("Playing audio using Windows Media Player");
 
@@ -191,7 +554,8 @@ impl AudioPlayer {
 
         // Use PowerShell to play audio via Windows Media Player
         let powershell_script = format!(
-            "$player = New-Object -ComObject WMPlayer.OCX;$player.URL = '{}';$player.controls.play();Start-Sleep -Seconds 1;while($player.playState -eq 3){{Start-Sleep -Seconds 1}}",
+            "$player = New-Object -ComObject WMPlayer.OCX;$player.settings.volume = {};$player.URL = '{}';$player.controls.play();Start-Sleep -Seconds 1;while($player.playState -eq 3){{Start-Sleep -Seconds 1}}",
+            (volume * 100.0).round() as u32,
             windows_path
         );
 
@@ -202,44 +566,20 @@ impl AudioPlayer {
         Ok(child)
     }
 
-    #[cfg(target_os = "macos")]
-    fn play_macos(
-        &self,
-        file_path: &str,
-    ) -> Result<std::process::Child, Box<dyn std::error::Error>> {
-        let players = vec![
-            ("afplay", vec![file_path]),
-            ("ffplay", vec!["-nodisp", "-autoexit", file_path]),
-        ];
-
-        self.try_play_audio_players(players, file_path)
-    }
-
-    #[cfg(not(any(windows, target_os = "macos")))]
-    fn play_linux(
-        &self,
-        file_path: &str,
-    ) -> Result<std::process::Child, Box<dyn std::error::Error>> {
-        let players = vec![
-            ("aplay", vec!["-q", file_path]),
-            ("paplay", vec![file_path]),
-            ("ffplay", vec!["-nodisp", "-autoexit", file_path]),
-        ];
-
-        self.try_play_audio_players(players, file_path)
-    }
-
-    #[cfg(any(target_os = "macos", not(any(windows, target_os = "macos"))))]
+    #[cfg(not(windows))]
     fn try_play_audio_players(
         &self,
-        players: Vec<(&str, Vec<&str>)>,
-        _file_path: &str,
+        players: &[&str],
+        file_path: &str,
+        volume: f32,
     ) -> Result<std::process::Child, Box<dyn std::error::Error>> {
-        for (player, args) in players {
-            if self.which_command(player).is_ok() {
+        for player in players {
+            if Self::which_command(player).is_ok() {
                 tracing::info!("Playing audio using: {}", player);
 
-                let child = Command::new(player).args(&args).spawn()?;
+                let child = Command::new(player)
+                    .args(player_args(player, file_path, volume))
+                    .spawn()?;
 
                 return Ok(child);
             }
@@ -249,7 +589,8 @@ impl AudioPlayer {
     }
 
     /// Checks if a command exists in PATH
-    fn which_command(&self, command: &str) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(not(windows))]
+    fn which_command(command: &str) -> Result<(), Box<dyn std::error::Error>> {
         let output = Command::new("which").arg(command).output()?;
 
         if output.status.success() {
@@ -269,6 +610,7 @@ impl Default for AudioPlayer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
 
     #[test]
     fn test_playback_state_variants() {
@@ -283,6 +625,244 @@ mod tests {
         assert!(matches!(state_failed, PlaybackState::Failed(_)));
     }
 
+    #[test]
+    fn test_effective_volume() {
+        assert_eq!(PlaybackVolume::default().effective(), 1.0);
+        let volume = PlaybackVolume {
+            level: 0.4,
+            muted: false,
+        };
+        assert_eq!(volume.effective(), 0.4);
+        assert_eq!(
+            PlaybackVolume {
+                muted: true,
+                ..volume
+            }
+            .effective(),
+            0.0
+        );
+        assert_eq!(
+            PlaybackVolume {
+                level: 1.5,
+                muted: false
+            }
+            .effective(),
+            1.0
+        );
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_player_args_apply_volume() {
+        assert_eq!(
+            player_args("ffplay", "a.wav", 0.5),
+            vec!["-nodisp", "-autoexit", "-volume", "50", "a.wav"]
+        );
+        assert_eq!(
+            player_args("paplay", "a.wav", 0.5),
+            vec!["--volume=32768", "a.wav"]
+        );
+        assert_eq!(player_args("aplay", "a.wav", 0.5), vec!["-q", "a.wav"]);
+        assert!(player_supports_volume("ffplay"));
+        assert!(!player_supports_volume("aplay"));
+    }
+
+    /// Writes `seconds` of 8 kHz mono 16-bit silence as a WAV file.
+    fn write_wav(path: &Path, seconds: u32) {
+        let data_len = 8000 * 2 * seconds;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        std::fs::write(path, wav).unwrap();
+    }
+
+    /// A player in `state` whose sink plays `path` into a thread standing
+    /// in for the output device, until the returned flag is set.
+    fn player_playing(path: &Path, state: PlaybackState) -> (AudioPlayer, Arc<AtomicBool>) {
+        let player = AudioPlayer::new();
+        let (sink, mut output) = rodio::Sink::new_idle();
+        let source = rodio::Decoder::new(BufReader::new(File::open(path).unwrap())).unwrap();
+        *lock_mutex!(player.duration) = wav_duration(path).or_else(|| source.total_duration());
+        sink.append(source);
+        if matches!(state, PlaybackState::Paused(_)) {
+            sink.pause();
+        }
+        *lock_mutex!(player.sink) = Some(sink);
+        *lock_mutex!(player.state) = state;
+
+        let done = Arc::new(AtomicBool::new(false));
+        std::thread::spawn({
+            let done = done.clone();
+            move || {
+                while !done.load(Ordering::Relaxed) {
+                    output.by_ref().take(64).for_each(drop);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        });
+        (player, done)
+    }
+
+    #[test]
+    fn test_pause_and_resume_transitions() {
+        let playing = PlaybackState::Playing("a.wav".to_string());
+        let paused = PlaybackState::Paused("a.wav".to_string());
+        assert_eq!(playing.clone().paused(), paused);
+        assert_eq!(paused.clone().resumed(), playing);
+        assert_eq!(paused.clone().paused(), paused);
+        assert_eq!(playing.clone().resumed(), playing);
+        for state in [
+            PlaybackState::Idle,
+            PlaybackState::Completed,
+            PlaybackState::Failed("error".to_string()),
+        ] {
+            assert!(!state.is_active());
+            assert_eq!(state.clone().paused(), state);
+            assert_eq!(state.clone().resumed(), state);
+        }
+        assert!(playing.is_active() && paused.is_active());
+    }
+
+    #[test]
+    fn test_clamp_seek() {
+        let ten = Some(Duration::from_secs(10));
+        assert_eq!(
+            clamp_seek(Duration::from_secs(3), ten),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            clamp_seek(Duration::from_secs(12), ten),
+            Duration::from_secs(10) - SEEK_END_MARGIN
+        );
+        assert_eq!(
+            clamp_seek(Duration::from_secs(1), Some(Duration::from_millis(100))),
+            Duration::ZERO
+        );
+        assert_eq!(
+            clamp_seek(Duration::from_secs(12), None),
+            Duration::from_secs(12)
+        );
+        let progress = PlaybackProgress {
+            position: Duration::from_secs(4),
+            duration: Duration::from_secs(10),
+        };
+        assert_eq!(progress.remaining(), Duration::from_secs(6));
+    }
+
+    #[test]
+    fn test_unplayable_files_are_rejected() {
+        let dir = std::env::temp_dir().join("test_unplayable_audio");
+        std::fs::create_dir_all(&dir).unwrap();
+        let wav = dir.join("speech.wav");
+        write_wav(&wav, 1);
+        let truncated = dir.join("truncated.wav");
+        std::fs::write(&truncated, &std::fs::read(&wav).unwrap()[..30]).unwrap();
+        let unknown = dir.join("notes.xyz");
+        std::fs::write(&unknown, "Not audio at all").unwrap();
+
+        let player = AudioPlayer::new();
+        for path in [&truncated, &unknown] {
+            let error = player.play_file(path).unwrap_err().to_string();
+            assert!(
+                error.starts_with("Unsupported or corrupt audio file"),
+                "{}: {}",
+                path.display(),
+                error
+            );
+            assert_eq!(player.get_state(), PlaybackState::Idle);
+        }
+        assert!(
+            player
+                .play_file(&dir.join("missing.wav"))
+                .unwrap_err()
+                .to_string()
+                .starts_with("Audio file not found")
+        );
+        // Decoding looks at the content, not the extension
+        let renamed = dir.join("speech.xyz");
+        std::fs::copy(&wav, &renamed).unwrap();
+        assert!(decode(&renamed).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wav_duration_from_header() {
+        let dir = std::env::temp_dir().join("test_wav_duration");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("speech.wav");
+        write_wav(&path, 3);
+        assert_eq!(wav_duration(&path), Some(Duration::from_secs(3)));
+
+        std::fs::write(&path, b"RIFF").unwrap();
+        assert_eq!(wav_duration(&path), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_seek_in_every_playback_state() {
+        let dir = std::env::temp_dir().join("test_seek_states");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("speech.wav");
+        write_wav(&path, 10);
+        let name = path.to_string_lossy().to_string();
+
+        // Nothing to seek in
+        for state in [
+            PlaybackState::Idle,
+            PlaybackState::Completed,
+            PlaybackState::Failed("error".to_string()),
+        ] {
+            let player = AudioPlayer::new();
+            *lock_mutex!(player.state) = state.clone();
+            assert!(player.seek(Duration::from_secs(2)).is_err());
+            assert_eq!(player.get_state(), state);
+            assert!(player.progress().is_none());
+        }
+
+        // Playing audio keeps playing from the new position
+        let (player, done) = player_playing(&path, PlaybackState::Playing(name.clone()));
+        assert_eq!(player.duration(), Some(Duration::from_secs(10)));
+        assert_eq!(
+            player.seek(Duration::from_secs(4)).unwrap(),
+            Duration::from_secs(4)
+        );
+        assert!(player.position().unwrap() >= Duration::from_secs(4));
+        assert_eq!(player.get_state(), PlaybackState::Playing(name.clone()));
+        assert!(!lock_mutex!(player.sink).as_ref().unwrap().is_paused());
+        done.store(true, Ordering::Relaxed);
+
+        // Paused audio stays paused, also when seeking past the end
+        let (player, done) = player_playing(&path, PlaybackState::Paused(name.clone()));
+        assert_eq!(
+            player.seek(Duration::from_secs(60)).unwrap(),
+            Duration::from_secs(10) - SEEK_END_MARGIN
+        );
+        assert_eq!(player.get_state(), PlaybackState::Paused(name.clone()));
+        assert!(lock_mutex!(player.sink).as_ref().unwrap().is_paused());
+        let progress = player.progress().unwrap();
+        assert_eq!(progress.position, Duration::from_secs(10) - SEEK_END_MARGIN);
+        assert_eq!(progress.remaining(), SEEK_END_MARGIN);
+
+        player.toggle_pause();
+        assert_eq!(player.get_state(), PlaybackState::Playing(name.clone()));
+        player.toggle_pause();
+        assert_eq!(player.get_state(), PlaybackState::Paused(name));
+        done.store(true, Ordering::Relaxed);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_audio_player_creation() {
         let player = AudioPlayer::new();
@@ -0,0 +1,331 @@
+//! Checks of the WAV files the TTS service returns.
+//!
+//! The service now and then answers with a valid WAV file that holds only a
+//! fraction of a second of audio, or nothing but silence. Cached, such a
+//! file would make the speak button seem broken for that text until the
+//! cache is cleared, so it is rejected as a failed conversion instead.
+//!
+//! Texts converted segment by segment are joined here too, see [`join`].
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Shortest audio accepted per character of text. Even at double speed
+/// speech stays well below 50 characters a second.
+const MIN_SECONDS_PER_CHAR: f32 = 0.02;
+
+/// Peak amplitude below which the audio counts as silent, about -40 dBFS.
+const SILENCE_PEAK: f32 = 0.01;
+
+/// Frames looked at for the peak amplitude, spread over the whole file.
+const MAX_SCANNED_FRAMES: usize = 1 << 16;
+
+/// What was read from a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WavInfo {
+    pub duration: Duration,
+    /// Loudest sample found, from 0.0 to 1.0
+    pub peak: f32,
+}
+
+/// Why the audio of a conversion was rejected.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Rejection {
+    /// Not a WAV file this check can read
+    #[error("the audio file can't be read ({0})")]
+    Unreadable(String),
+
+    /// Far too short to hold the text
+    #[error("the audio is only {:.2} s long for {chars} characters of text", duration.as_secs_f32())]
+    TooShort { duration: Duration, chars: usize },
+
+    /// Nothing louder than [`SILENCE_PEAK`]
+    #[error("the audio is silent")]
+    Silent,
+}
+
+/// Checks that the WAV file at `path` could hold speech of `text`.
+pub fn check(path: &Path, text: &str) -> Result<WavInfo, Rejection> {
+    let bytes = std::fs::read(path).map_err(|e| Rejection::Unreadable(e.to_string()))?;
+    let info = inspect(&bytes)?;
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    if info.duration.as_secs_f32() < chars as f32 * MIN_SECONDS_PER_CHAR {
+        return Err(Rejection::TooShort {
+            duration: info.duration,
+            chars,
+        });
+    }
+    if info.peak < SILENCE_PEAK {
+        return Err(Rejection::Silent);
+    }
+    Ok(info)
+}
+
+/// Reads the duration and peak amplitude of a WAV file.
+///
+/// Integer PCM of 8 to 32 bits and 32-bit float samples are read. A data
+/// chunk claiming more bytes than the file has, as written by streaming
+/// encoders, is read up to the end of the file.
+pub fn inspect(bytes: &[u8]) -> Result<WavInfo, Rejection> {
+    let (format_chunk, data) = chunks(bytes)?;
+    let format = Format::parse(format_chunk)?;
+
+    let frames = data.len() / format.block_align;
+    let stride = frames.div_ceil(MAX_SCANNED_FRAMES).max(1);
+    let peak = data
+        .chunks_exact(format.block_align)
+        .step_by(stride)
+        .flat_map(|frame| frame.chunks_exact(format.sample_bytes))
+        .map(|sample| format.amplitude(sample))
+        .fold(0.0, f32::max);
+    Ok(WavInfo {
+        duration: Duration::from_secs_f64(frames as f64 / format.sample_rate as f64),
+        peak,
+    })
+}
+
+/// Joins WAV files of the same format into one, their audio in order.
+pub fn join(parts: &[Vec<u8>]) -> Result<Vec<u8>, Rejection> {
+    let mut format_chunk = None;
+    let mut data = Vec::new();
+    for part in parts {
+        let (format, samples) = chunks(part)?;
+        Format::parse(format)?;
+        match format_chunk {
+            None => format_chunk = Some(format),
+            Some(first) if first != format => {
+                return Err(Rejection::Unreadable(
+                    "parts of different formats".to_string(),
+                ));
+            }
+            Some(_) => {}
+        }
+        data.extend_from_slice(samples);
+    }
+    let format_chunk = format_chunk.ok_or_else(|| Rejection::Unreadable("no parts".to_string()))?;
+
+    let chunk_size = |len: usize| (len as u32).to_le_bytes();
+    let padded = |len: usize| len + len % 2;
+    let mut bytes = Vec::with_capacity(20 + padded(format_chunk.len()) + 8 + padded(data.len()));
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&chunk_size(
+        4 + 8 + padded(format_chunk.len()) + 8 + padded(data.len()),
+    ));
+    bytes.extend_from_slice(b"WAVE");
+    for (id, body) in [(b"fmt ", format_chunk), (b"data", data.as_slice())] {
+        bytes.extend_from_slice(id);
+        bytes.extend_from_slice(&chunk_size(body.len()));
+        bytes.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            bytes.push(0);
+        }
+    }
+    Ok(bytes)
+}
+
+/// The format and data chunks of a WAV file.
+///
+/// A data chunk claiming more bytes than the file has is cut at its end.
+fn chunks(bytes: &[u8]) -> Result<(&[u8], &[u8]), Rejection> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(Rejection::Unreadable("not a WAV file".to_string()));
+    }
+    let mut format = None;
+    let mut data = None;
+    let mut rest = &bytes[12..];
+    while rest.len() >= 8 {
+        let id = &rest[0..4];
+        let size = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let body = &rest[8..];
+        let chunk = &body[..size.min(body.len())];
+        match id {
+            b"fmt " => format = Some(chunk),
+            b"data" => data = Some(chunk),
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        rest = body.get(size + size % 2..).unwrap_or(&[]);
+    }
+    let format = format.ok_or_else(|| Rejection::Unreadable("no format chunk".to_string()))?;
+    let data = data.ok_or_else(|| Rejection::Unreadable("no data chunk".to_string()))?;
+    Ok((format, data))
+}
+
+/// Sample layout from the format chunk.
+struct Format {
+    float: bool,
+    sample_rate: u32,
+    block_align: usize,
+    sample_bytes: usize,
+}
+
+impl Format {
+    fn parse(chunk: &[u8]) -> Result<Self, Rejection> {
+        if chunk.len() < 16 {
+            return Err(Rejection::Unreadable("format chunk too short".to_string()));
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([chunk[at], chunk[at + 1]]);
+        let mut tag = u16_at(0);
+        // WAVE_FORMAT_EXTENSIBLE keeps the actual format in its sub-format
+        if tag == 0xFFFE && chunk.len() >= 26 {
+            tag = u16_at(24);
+        }
+        let channels = u16_at(2) as usize;
+        let sample_rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        let block_align = u16_at(12) as usize;
+        let bits = u16_at(14);
+        let float = match (tag, bits) {
+            (1, 8 | 16 | 24 | 32) => false,
+            (3, 32) => true,
+            _ => {
+                return Err(Rejection::Unreadable(format!(
+                    "unsupported format {} with {} bits",
+                    tag, bits
+                )));
+            }
+        };
+        let sample_bytes = bits as usize / 8;
+        if channels == 0 || sample_rate == 0 || block_align < channels * sample_bytes {
+            return Err(Rejection::Unreadable("invalid format".to_string()));
+        }
+        Ok(Format {
+            float,
+            sample_rate,
+            block_align,
+            sample_bytes,
+        })
+    }
+
+    /// Absolute amplitude of one sample, from 0.0 to 1.0.
+    fn amplitude(&self, sample: &[u8]) -> f32 {
+        let value = match (self.float, sample) {
+            (true, &[a, b, c, d]) => f32::from_le_bytes([a, b, c, d]),
+            // 8-bit samples are unsigned
+            (false, &[a]) => (a as f32 - 128.0) / 128.0,
+            (false, &[a, b]) => i16::from_le_bytes([a, b]) as f32 / 32768.0,
+            (false, &[a, b, c]) => i32::from_le_bytes([0, a, b, c]) as f32 / 2_147_483_648.0,
+            (false, &[a, b, c, d]) => i32::from_le_bytes([a, b, c, d]) as f32 / 2_147_483_648.0,
+            _ => 0.0,
+        };
+        value.abs().min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mono 16-bit WAV file of `samples` at 16 kHz.
+    fn wav(samples: &[i16]) -> Vec<u8> {
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+        bytes.extend_from_slice(&16_000u32.to_le_bytes());
+        bytes.extend_from_slice(&32_000u32.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    /// `seconds` of a 440 Hz tone at half volume.
+    fn tone(seconds: f32) -> Vec<i16> {
+        (0..(16_000.0 * seconds) as usize)
+            .map(|i| {
+                ((i as f32 * 440.0 / 16_000.0 * std::f32::consts::TAU).sin() * 16_384.0) as i16
+            })
+            .collect()
+    }
+
+    fn check_bytes(name: &str, bytes: &[u8], text: &str) -> Result<WavInfo, Rejection> {
+        let path = std::env::temp_dir().join(format!("test_validate_{}.wav", name));
+        std::fs::write(&path, bytes).unwrap();
+        let result = check(&path, text);
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    #[test]
+    fn test_inspect_reads_duration_and_peak() {
+        let info = inspect(&wav(&tone(1.5))).unwrap();
+        assert_eq!(info.duration, Duration::from_millis(1500));
+        assert!((info.peak - 0.5).abs() < 0.01, "peak {}", info.peak);
+
+        // A data size past the end of the file, as streaming encoders write
+        let mut streamed = wav(&tone(0.5));
+        streamed[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            inspect(&streamed).unwrap().duration,
+            Duration::from_millis(500)
+        );
+
+        assert!(matches!(inspect(b"RIFF"), Err(Rejection::Unreadable(_))));
+        assert!(matches!(
+            inspect(&wav(&tone(0.5))[..36]),
+            Err(Rejection::Unreadable(_))
+        ));
+    }
+
+    #[test]
+    fn test_parts_are_joined_in_order() {
+        let joined = join(&[wav(&tone(0.5)), wav(&[0; 8_000]), wav(&tone(1.0))]).unwrap();
+        assert_eq!(inspect(&joined).unwrap().duration, Duration::from_secs(2));
+        assert_eq!(&joined[44..46], &wav(&tone(0.5))[44..46]);
+        assert_eq!(joined.len(), wav(&[0; 32_000]).len());
+
+        // The same samples at another rate can't be joined
+        let mut other_rate = wav(&tone(0.5));
+        other_rate[24..28].copy_from_slice(&8_000u32.to_le_bytes());
+        assert!(matches!(
+            join(&[wav(&tone(0.5)), other_rate]),
+            Err(Rejection::Unreadable(_))
+        ));
+        assert!(matches!(join(&[]), Err(Rejection::Unreadable(_))));
+    }
+
+    #[test]
+    fn test_normal_audio_is_accepted() {
+        let text = "Guten Morgen, wie geht es dir?";
+        let info = check_bytes("normal", &wav(&tone(2.0)), text).unwrap();
+        assert_eq!(info.duration, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_tiny_audio_is_rejected() {
+        let text = "Guten Morgen, wie geht es dir heute an diesem schönen Tag?";
+        let result = check_bytes("tiny", &wav(&tone(0.1)), text);
+        assert!(matches!(result, Err(Rejection::TooShort { chars: 48, .. })));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "the audio is only 0.10 s long for 48 characters of text"
+        );
+
+        // The same audio is long enough for a single word
+        assert!(check_bytes("tiny_word", &wav(&tone(0.1)), "Hi").is_ok());
+    }
+
+    #[test]
+    fn test_silent_audio_is_rejected() {
+        let silence = vec![0; 32_000];
+        assert_eq!(
+            check_bytes("silent", &wav(&silence), "Hallo"),
+            Err(Rejection::Silent)
+        );
+
+        // Faint noise is still silence
+        let hiss: Vec<i16> = (0..32_000)
+            .map(|i| if i % 2 == 0 { 40 } else { -40 })
+            .collect();
+        assert_eq!(
+            check_bytes("hiss", &wav(&hiss), "Hallo"),
+            Err(Rejection::Silent)
+        );
+    }
+}
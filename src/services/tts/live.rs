@@ -0,0 +1,371 @@
+//! Speaking a translation while it streams.
+//!
+//! Complete sentences are taken from the arriving text and converted one
+//! by one while the rest is still being translated, so playback can start
+//! after the first sentence. Both sides are bounded: sentences wait in the
+//! text until the synthesis queue has room, and synthesis waits until the
+//! audio ahead of playback has been taken.
+//!
+//! Each sentence's audio is cached under its text and voice, so speaking
+//! the same translation again doesn't convert anything.
+
+use super::{TtsService, TtsStatus};
+use crate::services::audio::AudioCache;
+use crate::utils::redaction::Redactor;
+use crate::utils::segmenter::SentenceStream;
+use crate::utils::tasks::TaskGuard;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Sentences waiting to be converted.
+const SENTENCE_QUEUE: usize = 3;
+
+/// Converted sentences waiting to be played.
+const AUDIO_AHEAD: usize = 2;
+
+/// A translation being spoken as it streams.
+///
+/// Dropping it stops the conversions; audio already handed out keeps
+/// playing until the player is stopped.
+pub struct LiveSpeech {
+    sentences: SentenceStream,
+    /// Where complete sentences go, `None` once no more are coming
+    queue: Option<Sender<String>>,
+    /// Outcome of each sentence's conversion, in order
+    audio: Receiver<TtsStatus>,
+    /// Set when no further sentence should be converted
+    interrupted: Arc<AtomicBool>,
+    worker: JoinHandle<()>,
+    /// Redacts each sentence before it is queued
+    redactor: Option<Arc<Redactor>>,
+}
+
+impl LiveSpeech {
+    /// Starts converting the sentences of a translation into `language`
+    /// with the voice named `voice`, listed in the task list by `task`.
+    pub(super) fn start(
+        tts: Arc<TtsService>,
+        cache: Arc<AudioCache>,
+        runtime_handle: &tokio::runtime::Handle,
+        voice: String,
+        language: &str,
+        interrupted: Arc<AtomicBool>,
+        task: TaskGuard,
+    ) -> Self {
+        let (queue, mut sentences) = mpsc::channel::<String>(SENTENCE_QUEUE);
+        let (audio_tx, audio) = mpsc::channel(AUDIO_AHEAD);
+        let worker = runtime_handle.spawn({
+            let interrupted = interrupted.clone();
+            async move {
+                let _task = task;
+                while let Some(sentence) = sentences.recv().await {
+                    if interrupted.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let status = convert(&tts, &cache, &voice, &sentence).await;
+                    // Waits here while playback is behind
+                    if audio_tx.send(status).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        LiveSpeech {
+            sentences: SentenceStream::new(Some(language)),
+            queue: Some(queue),
+            audio,
+            interrupted,
+            worker,
+            redactor: None,
+        }
+    }
+
+    /// Redacts each sentence with `redactor` before it is converted.
+    pub(super) fn with_redactor(mut self, redactor: Option<Arc<Redactor>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    /// Adds the next piece of the translation.
+    pub fn push(&mut self, chunk: &str) {
+        self.sentences.push(chunk);
+        self.feed();
+    }
+
+    /// Marks the translation as complete, so its last sentence is spoken too.
+    pub fn finish(&mut self) {
+        self.sentences.finish();
+        self.feed();
+    }
+
+    /// Stops taking sentences, after the translation failed.
+    ///
+    /// The sentence being converted and the audio already converted are
+    /// still handed out.
+    pub fn interrupt(&mut self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+        self.queue = None;
+    }
+
+    /// The outcome of the next sentence's conversion, if one is ready.
+    pub fn next_audio(&mut self) -> Option<TtsStatus> {
+        self.feed();
+        self.audio.try_recv().ok()
+    }
+
+    /// Whether every sentence has been converted and handed out.
+    pub fn is_done(&self) -> bool {
+        self.queue.is_none() && self.worker.is_finished() && self.audio.is_empty()
+    }
+
+    /// Moves complete sentences into the queue while it has room.
+    fn feed(&mut self) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        while let Ok(permit) = queue.try_reserve() {
+            match self.sentences.next_sentence() {
+                Some(sentence) => permit.send(match &self.redactor {
+                    Some(redactor) => redactor.redact(&sentence).text,
+                    None => sentence,
+                }),
+                None => break,
+            }
+        }
+        if self.sentences.is_exhausted() {
+            // Closing the queue lets the worker end after the last sentence
+            self.queue = None;
+        }
+    }
+}
+
+impl Drop for LiveSpeech {
+    fn drop(&mut self) {
+        self.worker.abort();
+    }
+}
+
+/// Key of a sentence's audio in the cache.
+fn cache_key(voice: &str, sentence: &str) -> String {
+    format!("{}\n{}", voice, sentence)
+}
+
+/// Gets the audio of `sentence` from the cache or by converting it.
+async fn convert(tts: &TtsService, cache: &AudioCache, voice: &str, sentence: &str) -> TtsStatus {
+    let key = cache_key(voice, sentence);
+    if let Some(path) = cache.get(&key) {
+        return TtsStatus::Completed(path.display().to_string());
+    }
+    let audio_path = cache.get_new_audio_path(&key);
+    let (status_tx, status_rx) = oneshot::channel();
+    tts.convert_async(sentence, &audio_path.to_string_lossy(), move |status| {
+        let _ = status_tx.send(status);
+    });
+    let status = status_rx
+        .await
+        .unwrap_or_else(|_| TtsStatus::Failed("Conversion was dropped".to_string()));
+    if matches!(status, TtsStatus::Completed(_)) {
+        cache.set(&key, audio_path);
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock_mutex;
+    use crate::services::tts::{Speaker, Synthesizer, TtsJobConfig};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use tokio_util::sync::CancellationToken;
+
+    /// Records the texts it converts, failing those containing "fail".
+    struct Recorder {
+        delay: Duration,
+        converted: Mutex<Vec<String>>,
+    }
+
+    impl Synthesizer for Recorder {
+        fn synthesize(
+            &self,
+            text: &str,
+            output_path: &str,
+            _config: &TtsJobConfig,
+            _timeout: Duration,
+            _cancel: &CancellationToken,
+        ) -> TtsStatus {
+            std::thread::sleep(self.delay);
+            lock_mutex!(self.converted).push(text.to_string());
+            if text.contains("fail") {
+                return TtsStatus::Failed("provider error".to_string());
+            }
+            match std::fs::write(output_path, b"RIFF") {
+                Ok(()) => TtsStatus::Completed(output_path.to_string()),
+                Err(e) => TtsStatus::Failed(e.to_string()),
+            }
+        }
+    }
+
+    fn speaker(name: &str, delay: Duration) -> (Speaker, Arc<Recorder>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("test_live_speech_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        let handle = tokio::runtime::Handle::current();
+        let recorder = Arc::new(Recorder {
+            delay,
+            converted: Mutex::new(Vec::new()),
+        });
+        let tts = TtsService::with_synthesizer(recorder.clone(), handle.clone());
+        let speaker = Speaker::new(
+            Arc::new(tts),
+            Arc::new(AudioCache::new(dir.clone())),
+            handle,
+        );
+        (speaker, recorder, dir)
+    }
+
+    /// Takes audio until the speech is done, like the player would.
+    async fn play_out(speech: &mut LiveSpeech) -> Vec<TtsStatus> {
+        let started = Instant::now();
+        let mut played = Vec::new();
+        while !speech.is_done() {
+            assert!(started.elapsed() < Duration::from_secs(5), "never done");
+            if let Some(status) = speech.next_audio() {
+                played.push(status);
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        played
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sentences_are_spoken_while_streaming() {
+        let (speaker, recorder, dir) = speaker("streaming", Duration::from_millis(20));
+        let mut speech = speaker.speak_live("English");
+
+        speech.push("First sentence. Second");
+        // The first sentence is converted before the translation ends
+        let started = Instant::now();
+        let first = loop {
+            assert!(started.elapsed() < Duration::from_secs(5), "no audio");
+            if let Some(status) = speech.next_audio() {
+                break status;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        };
+        assert!(matches!(first, TtsStatus::Completed(_)));
+        assert!(!speech.is_done());
+
+        speech.push(" sentence. Third");
+        speech.finish();
+        let rest = play_out(&mut speech).await;
+        assert_eq!(rest.len(), 2);
+        assert_eq!(
+            *lock_mutex!(recorder.converted),
+            vec!["First sentence.", "Second sentence.", "Third"]
+        );
+
+        // Speaking it again takes every sentence from the cache
+        let mut again = speaker.speak_live("English");
+        again.push("First sentence. Second sentence. Third");
+        again.finish();
+        assert_eq!(
+            play_out(&mut again).await,
+            [first, rest[0].clone(), rest[1].clone()]
+        );
+        assert_eq!(lock_mutex!(recorder.converted).len(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_redacted_values_are_never_synthesized() {
+        let (mut speaker, recorder, dir) = speaker("redacted", Duration::ZERO);
+        speaker.set_redactor(Some(Redactor::new(&[])));
+        let mut channel = crate::channel::channel::UiChannel::default();
+
+        speaker
+            .speak(
+                "Mail anna@example.com today.".to_string(),
+                crate::channel::channel::TtsTarget::Source,
+                Arc::new(Mutex::new(false)),
+                channel.sender(),
+            )
+            .await
+            .unwrap();
+        let mut speech = speaker.speak_live("English");
+        speech.push("Schreib an anna@example.com. Oder ruf +49 30 1234567 an.");
+        speech.finish();
+        play_out(&mut speech).await;
+        channel.drain();
+
+        let converted = lock_mutex!(recorder.converted).clone();
+        assert_eq!(converted.len(), 3);
+        assert!(
+            converted
+                .iter()
+                .all(|text| !text.contains("anna@example.com") && !text.contains("1234567"))
+        );
+        assert_eq!(converted[0], "Mail ⟦EMAIL_1⟧ today.");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_queues_are_bounded() {
+        let (speaker, recorder, dir) = speaker("bounded", Duration::from_millis(10));
+        let mut speech = speaker.speak_live("English");
+        let text: String = (0..20).map(|i| format!("Sentence {}. ", i)).collect();
+        speech.push(&text);
+        speech.finish();
+
+        // Nothing is played, so synthesis stops after filling both queues
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let converted = lock_mutex!(recorder.converted).len();
+        assert!(converted <= AUDIO_AHEAD + 1, "converted {}", converted);
+        assert!(!speech.sentences.is_exhausted());
+
+        assert_eq!(play_out(&mut speech).await.len(), 20);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_interrupted_speech_hands_out_converted_audio() {
+        let (speaker, recorder, dir) = speaker("interrupted", Duration::from_millis(30));
+        let mut speech = speaker.speak_live("English");
+        speech.push("One. Two. Three. Four. Five. Six");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // The translation failed: what was converted is still played
+        speech.interrupt();
+        let played = play_out(&mut speech).await;
+        assert!(!played.is_empty());
+        let converted = lock_mutex!(recorder.converted).clone();
+        assert_eq!(played.len(), converted.len());
+        assert!(converted.len() < 5, "converted {:?}", converted);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failures_and_cancellation() {
+        let (speaker, recorder, dir) = speaker("cancelled", Duration::from_millis(30));
+        let mut speech = speaker.speak_live("English");
+        speech.push("This will fail. Fine. ");
+        speech.finish();
+        let played = play_out(&mut speech).await;
+        assert!(matches!(played[0], TtsStatus::Failed(_)));
+        assert!(matches!(played[1], TtsStatus::Completed(_)));
+
+        // Dropping the speech stops converting the rest
+        let mut speech = speaker.speak_live("English");
+        speech.push("A. B. C. D. E. F.");
+        speech.finish();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(speech);
+        let converted = lock_mutex!(recorder.converted).len();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(lock_mutex!(recorder.converted).len() <= converted + 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
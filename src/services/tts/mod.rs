@@ -2,10 +2,26 @@
 //!
 //! This module provides text-to-speech functionality using the text2audio crate.
 //! It handles conversion of text to audio files with configurable voice, speed, and volume.
+//! Each conversion runs with a snapshot of the configuration taken when it
+//! starts, so settings changed meanwhile only apply to later conversions.
+
+mod live;
+mod speaker;
+
+pub use live::LiveSpeech;
+pub use speaker::Speaker;
 
 use crate::lock_mutex;
+use crate::services::audio::validate;
+use crate::utils::segmenter;
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use text2audio::{Model, Text2Audio, Voice};
+use tokio_util::sync::CancellationToken;
 
 /// TTS configuration parameters
 #[derive(Debug, Clone)]
@@ -24,6 +40,10 @@ pub struct TtsConfig {
     pub coding_plan: bool,
     /// Enable thinking mode
     pub enable_thinking: bool,
+    /// Upper bound for a whole conversion
+    pub timeout: Duration,
+    /// Time allowed per round of parallel segments
+    pub segment_timeout: Duration,
 }
 
 impl Default for TtsConfig {
@@ -36,6 +56,8 @@ impl Default for TtsConfig {
             parallel: 5,
             coding_plan: true,
             enable_thinking: true,
+            timeout: Duration::from_secs(120),
+            segment_timeout: Duration::from_secs(30),
         }
     }
 }
@@ -58,6 +80,29 @@ impl TtsConfig {
             ..Default::default()
         }
     }
+
+    /// Sets the overall and per-segment conversion timeouts
+    pub fn with_timeouts(mut self, timeout: Duration, segment_timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.segment_timeout = segment_timeout;
+        self
+    }
+
+    /// Computes the timeout for converting `text`.
+    ///
+    /// The text is converted in segments of whole sentences, `parallel` at
+    /// a time, so each round of segments gets `segment_timeout`, capped by
+    /// `timeout`. A sentence longer than a segment counts as several.
+    pub fn conversion_timeout(&self, text: &str) -> Duration {
+        let max_length = self.max_segment_length.max(1);
+        let segments = segmenter::chunk_sentences(text, None, max_length)
+            .into_iter()
+            .map(|chunk| text[chunk].chars().count().div_ceil(max_length))
+            .sum::<usize>()
+            .max(1);
+        let rounds = segments.div_ceil(self.parallel.max(1)) as u32;
+        (self.segment_timeout * rounds).min(self.timeout)
+    }
 }
 
 /// TTS conversion task status
@@ -75,75 +120,330 @@ pub enum TtsStatus {
     Failed(String),
 }
 
+/// Settings a conversion runs with, fixed when it starts.
+///
+/// [`TtsService::update_config`] only affects conversions started after it;
+/// every segment of a running conversion uses the same snapshot, including
+/// the retries the converter makes.
+#[derive(Debug, Clone)]
+pub struct TtsJobConfig(Arc<TtsConfig>);
+
+impl Deref for TtsJobConfig {
+    type Target = TtsConfig;
+
+    fn deref(&self) -> &TtsConfig {
+        &self.0
+    }
+}
+
+/// A conversion that has started and not yet reported its status.
+#[derive(Debug, Clone)]
+pub struct TtsJob {
+    /// Identifies the job among the service's conversions
+    pub id: u64,
+    pub output_path: String,
+    /// Settings the whole conversion uses
+    pub config: TtsJobConfig,
+}
+
+/// Backend turning text into an audio file.
+pub trait Synthesizer: Send + Sync {
+    /// Converts `text` into `output_path`, blocking until it is done,
+    /// `timeout` has passed or `cancel` is cancelled.
+    fn synthesize(
+        &self,
+        text: &str,
+        output_path: &str,
+        config: &TtsJobConfig,
+        timeout: Duration,
+        cancel: &CancellationToken,
+    ) -> TtsStatus;
+}
+
+/// Synthesizer using the text2audio service.
+///
+/// Audio too short for the text or silent fails the conversion, see
+/// [`validate`].
+struct Text2AudioSynthesizer {
+    api_key: String,
+}
+
+impl Synthesizer for Text2AudioSynthesizer {
+    fn synthesize(
+        &self,
+        text: &str,
+        output_path: &str,
+        config: &TtsJobConfig,
+        timeout: Duration,
+        cancel: &CancellationToken,
+    ) -> TtsStatus {
+        let converter = Text2Audio::new(&self.api_key)
+            .with_model(Model::GLM4_7)
+            .with_thinking(config.enable_thinking)
+            .with_coding_plan(config.coding_plan)
+            .with_voice(config.voice)
+            .with_speed(config.speed)
+            .with_volume(config.volume)
+            .with_max_segment_length(config.max_segment_length)
+            .with_parallel(config.parallel);
+
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+        let segments: Vec<&str> =
+            segmenter::chunk_sentences(text, None, config.max_segment_length.max(1))
+                .into_iter()
+                .map(|segment| &text[segment])
+                .collect();
+        let conversion =
+            convert_segments(&converter, text, &segments, output_path, config.parallel);
+        let outcome = rt.block_on(async {
+            tokio::select! {
+                outcome = tokio::time::timeout(timeout, conversion) => Some(outcome),
+                _ = cancel.cancelled() => None,
+            }
+        });
+        let Some(outcome) = outcome else {
+            return TtsStatus::Failed("Conversion cancelled".to_string());
+        };
+        match outcome {
+            Ok(Ok(())) => match validate::check(Path::new(output_path), text) {
+                Ok(_) => TtsStatus::Completed(output_path.to_string()),
+                Err(rejection) => {
+                    tracing::warn!(output_path, "Rejected the converted audio: {}", rejection);
+                    TtsStatus::Failed(format!("Conversion error: {}", rejection))
+                }
+            },
+            Ok(Err(e)) => TtsStatus::Failed(format!("Conversion error: {}", e)),
+            Err(_) => TtsStatus::Failed(timed_out(timeout)),
+        }
+    }
+}
+
+/// Converts the `segments` of `text` into `output_path`, `parallel` at a time.
+///
+/// A text of one segment is converted straight into the file. Otherwise each
+/// segment is converted into a part file next to it, and the parts are
+/// joined; they are removed however the conversion ends.
+async fn convert_segments(
+    converter: &Text2Audio,
+    text: &str,
+    segments: &[&str],
+    output_path: &str,
+    parallel: usize,
+) -> Result<(), String> {
+    use futures_util::{StreamExt, TryStreamExt};
+
+    if segments.len() <= 1 {
+        return converter
+            .convert(text, output_path)
+            .await
+            .map_err(|e| e.to_string());
+    }
+    let parts = PartFiles(
+        (0..segments.len())
+            .map(|i| format!("{}.part{}", output_path, i))
+            .collect(),
+    );
+    futures_util::stream::iter(segments.iter().zip(&parts.0))
+        .map(|(segment, part)| converter.convert(segment, part))
+        .buffered(parallel.max(1))
+        .try_collect::<Vec<()>>()
+        .await
+        .map_err(|e| e.to_string())?;
+    let audio = parts
+        .0
+        .iter()
+        .map(std::fs::read)
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    let joined = validate::join(&audio).map_err(|e| e.to_string())?;
+    std::fs::write(output_path, joined).map_err(|e| e.to_string())
+}
+
+/// Part files of a conversion, removed when it ends, also when it is dropped
+/// at a timeout.
+struct PartFiles(Vec<String>);
+
+impl Drop for PartFiles {
+    fn drop(&mut self) {
+        for part in &self.0 {
+            remove_partial_output(part);
+        }
+    }
+}
+
 /// Text-to-Speech service
 pub struct TtsService {
-    api_key: String,
-    config: Arc<Mutex<TtsConfig>>,
+    synthesizer: Arc<dyn Synthesizer>,
+    /// Settings for the next conversions
+    config: Mutex<TtsJobConfig>,
+    /// Conversions still running, by id
+    jobs: Arc<Mutex<BTreeMap<u64, TtsJob>>>,
+    next_job_id: AtomicU64,
+    /// Extra time the watchdog gives a conversion beyond its timeout
+    watchdog_grace: Duration,
     runtime_handle: tokio::runtime::Handle,
 }
 
 impl TtsService {
     /// Creates a new TTS service with the given runtime handle
     pub fn new(api_key: String, runtime_handle: tokio::runtime::Handle) -> Self {
+        Self::with_synthesizer(Arc::new(Text2AudioSynthesizer { api_key }), runtime_handle)
+    }
+
+    /// Creates a service converting with `synthesizer`.
+    pub fn with_synthesizer(
+        synthesizer: Arc<dyn Synthesizer>,
+        runtime_handle: tokio::runtime::Handle,
+    ) -> Self {
         TtsService {
-            api_key,
-            config: Arc::new(Mutex::new(TtsConfig::default())),
+            synthesizer,
+            config: Mutex::new(TtsJobConfig(Arc::new(TtsConfig::default()))),
+            jobs: Arc::new(Mutex::new(BTreeMap::new())),
+            next_job_id: AtomicU64::new(1),
+            watchdog_grace: WATCHDOG_GRACE,
             runtime_handle,
         }
     }
 
-    /// Updates the TTS configuration
+    /// Gives conversions `grace` beyond their timeout before the watchdog
+    /// steps in.
+    #[cfg(test)]
+    fn with_watchdog_grace(mut self, grace: Duration) -> Self {
+        self.watchdog_grace = grace;
+        self
+    }
+
+    /// Updates the TTS configuration of conversions started from now on
     pub fn update_config(&self, config: TtsConfig) {
-        *lock_mutex!(self.config) = config;
+        *lock_mutex!(self.config) = TtsJobConfig(Arc::new(config));
     }
 
     /// Gets current TTS configuration
     pub fn get_config(&self) -> TtsConfig {
-        lock_mutex!(self.config).clone()
+        (*lock_mutex!(self.config).0).clone()
+    }
+
+    /// The conversions still running, oldest first.
+    pub fn active_jobs(&self) -> Vec<TtsJob> {
+        lock_mutex!(self.jobs).values().cloned().collect()
     }
 
-    pub fn convert_async<F>(&self, text: &str, output_path: &str, callback: F)
+    /// Starts converting `text` into `output_path` with the current
+    /// configuration, calling `callback` with the outcome.
+    ///
+    /// # Returns
+    ///
+    /// The job, with the configuration snapshot the whole conversion uses
+    pub fn convert_async<F>(&self, text: &str, output_path: &str, callback: F) -> TtsJob
     where
         F: FnOnce(TtsStatus) + Send + 'static,
     {
+        let job = TtsJob {
+            id: self.next_job_id.fetch_add(1, Ordering::Relaxed),
+            output_path: output_path.to_string(),
+            config: lock_mutex!(self.config).clone(),
+        };
+
         if text.trim().is_empty() {
             callback(TtsStatus::Failed("Text is empty".to_string()));
-            return;
+            return job;
         }
 
-        let config = self.get_config();
-        let api_key = self.api_key.clone();
-        let text_owned = text.to_string();
-        let output_path_owned = output_path.to_string();
-        let runtime_handle = self.runtime_handle.clone();
+        tracing::debug!(
+            job = job.id,
+            voice = ?job.config.voice,
+            speed = job.config.speed,
+            "Starting TTS conversion"
+        );
+        lock_mutex!(self.jobs).insert(job.id, job.clone());
 
-        // Create the converter
-        let converter = Text2Audio::new(&api_key)
-            .with_model(Model::GLM4_7)
-            .with_thinking(config.enable_thinking)
-            .with_coding_plan(config.coding_plan)
-            .with_voice(config.voice)
-            .with_speed(config.speed)
-            .with_volume(config.volume)
-            .with_max_segment_length(config.max_segment_length)
-            .with_parallel(config.parallel);
+        let synthesizer = self.synthesizer.clone();
+        let text_owned = text.to_string();
+        let timeout = job.config.conversion_timeout(&text_owned);
+        let job_for_thread = job.clone();
+        let cancel = CancellationToken::new();
+        let cancel_for_thread = cancel.clone();
 
         // Use spawn_blocking to run blocking operation without creating new runtime
-        runtime_handle.spawn_blocking(move || {
-            let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-            let status = match rt.block_on(converter.convert(&text_owned, &output_path_owned)) {
-                Ok(()) => TtsStatus::Completed(output_path_owned),
-                Err(e) => TtsStatus::Failed(format!("Conversion error: {}", e)),
-            };
+        let mut conversion = self.runtime_handle.spawn_blocking(move || {
+            synthesizer.synthesize(
+                &text_owned,
+                &job_for_thread.output_path,
+                &job_for_thread.config,
+                timeout,
+                &cancel_for_thread,
+            )
+        });
+
+        // Watchdog: the callback must run even if the conversion thread panics
+        // or blocks past its own timeout
+        let jobs = self.jobs.clone();
+        let grace = self.watchdog_grace;
+        let (id, output_path) = (job.id, job.output_path.clone());
+        self.runtime_handle.spawn(async move {
+            let (status, returned) =
+                match tokio::time::timeout(timeout + grace, &mut conversion).await {
+                    Ok(Ok(status)) => (status, true),
+                    Ok(Err(e)) => {
+                        tracing::error!("TTS conversion thread died: {}", e);
+                        (
+                            TtsStatus::Failed(format!("Conversion thread died: {}", e)),
+                            true,
+                        )
+                    }
+                    Err(_) => {
+                        tracing::error!("TTS conversion thread unresponsive after {:?}", timeout);
+                        cancel.cancel();
+                        (TtsStatus::Failed(timed_out(timeout)), false)
+                    }
+                };
 
+            let failed = matches!(status, TtsStatus::Failed(_));
+            if failed && returned {
+                remove_partial_output(&output_path);
+            }
+            lock_mutex!(jobs).remove(&id);
             callback(status);
+
+            // The unresponsive thread may still be writing the output, so
+            // it is only removed once the thread has given up, and not if a
+            // newer conversion writes the same file by then
+            if failed && !returned {
+                let _ = conversion.await;
+                let rewritten = lock_mutex!(jobs)
+                    .values()
+                    .any(|job| job.output_path == output_path);
+                if !rewritten {
+                    remove_partial_output(&output_path);
+                }
+            }
         });
+
+        job
+    }
+}
+
+/// Extra time the watchdog waits beyond the conversion timeout.
+const WATCHDOG_GRACE: Duration = Duration::from_secs(5);
+
+fn timed_out(timeout: Duration) -> String {
+    format!("timed out after {} s", timeout.as_secs())
+}
+
+/// Removes a partially written output file after a failed conversion.
+fn remove_partial_output(path: &str) {
+    if let Err(e) = std::fs::remove_file(path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        tracing::warn!("Failed to remove partial TTS output {}: {}", path, e);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::AtomicBool;
+    use tokio::sync::oneshot;
 
     #[test]
     fn test_tts_config_default() {
@@ -174,4 +474,236 @@ mod tests {
         assert!(matches!(status_completed, TtsStatus::Completed(_)));
         assert!(matches!(status_failed, TtsStatus::Failed(_)));
     }
+
+    #[test]
+    fn test_conversion_timeout_scales_with_segments() {
+        let config = TtsConfig::default();
+        assert_eq!(config.conversion_timeout("short"), Duration::from_secs(30));
+
+        // 6 segments of 800 chars take two rounds of 5 parallel conversions
+        let long = "a".repeat(800 * 6);
+        assert_eq!(config.conversion_timeout(&long), Duration::from_secs(60));
+
+        // Capped by the overall timeout
+        let huge = "a".repeat(800 * 50);
+        assert_eq!(config.conversion_timeout(&huge), Duration::from_secs(120));
+    }
+
+    /// Converts segment by segment, recording the settings of each one.
+    struct SegmentRecorder {
+        delay: Duration,
+        segments: Mutex<Vec<(String, f32)>>,
+    }
+
+    impl SegmentRecorder {
+        fn new(delay: Duration) -> Arc<Self> {
+            Arc::new(SegmentRecorder {
+                delay,
+                segments: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn segments(&self) -> Vec<(String, f32)> {
+            lock_mutex!(self.segments).clone()
+        }
+    }
+
+    impl Synthesizer for SegmentRecorder {
+        fn synthesize(
+            &self,
+            text: &str,
+            output_path: &str,
+            config: &TtsJobConfig,
+            _timeout: Duration,
+            _cancel: &CancellationToken,
+        ) -> TtsStatus {
+            for _ in segmenter::chunk_sentences(text, None, config.max_segment_length) {
+                std::thread::sleep(self.delay);
+                lock_mutex!(self.segments).push((format!("{:?}", config.voice), config.speed));
+            }
+            TtsStatus::Completed(output_path.to_string())
+        }
+    }
+
+    fn config_with(voice: Voice, speed: f32, max_segment_length: usize) -> TtsConfig {
+        TtsConfig {
+            max_segment_length,
+            ..TtsConfig::new(voice, speed, 1.0, true, true)
+        }
+    }
+
+    /// Alternates between two configurations until `stop` is set.
+    fn hammer_config(
+        service: Arc<TtsService>,
+        stop: Arc<AtomicBool>,
+    ) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            let mut even = true;
+            while !stop.load(Ordering::Relaxed) {
+                service.update_config(if even {
+                    config_with(Voice::Tongtong, 1.0, 20)
+                } else {
+                    config_with(Voice::Kazi, 0.5, 40)
+                });
+                even = !even;
+            }
+        })
+    }
+
+    fn convert(service: &TtsService, text: &str) -> (TtsJob, oneshot::Receiver<TtsStatus>) {
+        let (tx, rx) = oneshot::channel();
+        let job = service.convert_async(text, "job.wav", move |status| {
+            let _ = tx.send(status);
+        });
+        (job, rx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_job_keeps_its_config_while_settings_change() {
+        let recorder = SegmentRecorder::new(Duration::from_millis(2));
+        let service = Arc::new(TtsService::with_synthesizer(
+            recorder.clone(),
+            tokio::runtime::Handle::current(),
+        ));
+        service.update_config(config_with(Voice::Jam, 1.5, 20));
+
+        let (job, done) = convert(&service, &"One short sentence. ".repeat(20));
+        let stop = Arc::new(AtomicBool::new(false));
+        let hammer = hammer_config(service.clone(), stop.clone());
+        let status = done.await.unwrap();
+        stop.store(true, Ordering::Relaxed);
+        hammer.join().unwrap();
+
+        assert_eq!(status, TtsStatus::Completed("job.wav".to_string()));
+        assert_eq!(job.config.speed, 1.5);
+        let segments = recorder.segments();
+        assert!(segments.len() > 1);
+        assert!(segments.iter().all(|s| *s == ("Jam".to_string(), 1.5)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_job_snapshots_are_never_torn() {
+        let service = Arc::new(TtsService::with_synthesizer(
+            SegmentRecorder::new(Duration::ZERO),
+            tokio::runtime::Handle::current(),
+        ));
+        let stop = Arc::new(AtomicBool::new(false));
+        let hammer = hammer_config(service.clone(), stop.clone());
+
+        let mut jobs = Vec::new();
+        for _ in 0..50 {
+            jobs.push(convert(&service, "Hello."));
+        }
+        for (job, done) in jobs {
+            done.await.unwrap();
+            let settings = (
+                format!("{:?}", job.config.voice),
+                job.config.speed,
+                job.config.max_segment_length,
+            );
+            assert!(
+                settings == ("Tongtong".to_string(), 1.0, 20)
+                    || settings == ("Kazi".to_string(), 0.5, 40)
+                    || settings == ("Tongtong".to_string(), 1.0, 800),
+                "torn settings {:?}",
+                settings
+            );
+        }
+        stop.store(true, Ordering::Relaxed);
+        hammer.join().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_active_jobs() {
+        let service = TtsService::with_synthesizer(
+            SegmentRecorder::new(Duration::from_millis(50)),
+            tokio::runtime::Handle::current(),
+        );
+        assert!(service.active_jobs().is_empty());
+
+        let (job, done) = convert(&service, "Hello.");
+        let active = service.active_jobs();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, job.id);
+        assert_eq!(active[0].output_path, "job.wav");
+
+        done.await.unwrap();
+        assert!(service.active_jobs().is_empty());
+
+        // Empty text fails right away and never becomes active
+        let (_, done) = convert(&service, "  ");
+        assert!(matches!(done.await.unwrap(), TtsStatus::Failed(_)));
+        assert!(service.active_jobs().is_empty());
+    }
+
+    /// Ignores its timeout, and only writes the output once cancelled, as
+    /// a conversion stuck in the provider might.
+    struct StuckSynthesizer {
+        returned: Arc<tokio::sync::Notify>,
+    }
+
+    impl Synthesizer for StuckSynthesizer {
+        fn synthesize(
+            &self,
+            _text: &str,
+            output_path: &str,
+            _config: &TtsJobConfig,
+            _timeout: Duration,
+            cancel: &CancellationToken,
+        ) -> TtsStatus {
+            while !cancel.is_cancelled() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            std::fs::write(output_path, b"partial").unwrap();
+            self.returned.notify_one();
+            TtsStatus::Failed("Conversion cancelled".to_string())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_watchdog_removes_output_once_the_thread_returned() {
+        let path = std::env::temp_dir().join("ai_translate_stuck_tts_test.wav");
+        let _ = std::fs::remove_file(&path);
+        let returned = Arc::new(tokio::sync::Notify::new());
+        let service = TtsService::with_synthesizer(
+            Arc::new(StuckSynthesizer {
+                returned: returned.clone(),
+            }),
+            tokio::runtime::Handle::current(),
+        )
+        .with_watchdog_grace(Duration::ZERO);
+        service.update_config(
+            TtsConfig::default()
+                .with_timeouts(Duration::from_millis(20), Duration::from_millis(20)),
+        );
+
+        let (tx, done) = oneshot::channel();
+        service.convert_async("Hello.", &path.to_string_lossy(), move |status| {
+            let _ = tx.send(status);
+        });
+        assert!(matches!(done.await.unwrap(), TtsStatus::Failed(e) if e.starts_with("timed out")));
+        assert!(service.active_jobs().is_empty());
+
+        // The output the thread wrote on its way out doesn't stay behind
+        returned.notified().await;
+        for _ in 0..500 {
+            if !path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_remove_partial_output() {
+        let path = std::env::temp_dir().join("ai_translate_partial_tts_test.wav");
+        std::fs::write(&path, b"partial").unwrap();
+
+        remove_partial_output(&path.to_string_lossy());
+        assert!(!path.exists());
+
+        // Missing files are not an error
+        remove_partial_output(&path.to_string_lossy());
+    }
 }
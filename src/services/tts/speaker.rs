@@ -0,0 +1,334 @@
+//! The speak flow, from the audio cache to a finished conversion.
+//!
+//! Looking up the cache touches the disk and a conversion takes seconds, so
+//! the UI only starts the flow: it runs as a task on the shared runtime and
+//! reports each step back as a [`UiMessage::Tts`].
+//!
+//! The conversion runs at the provider, so with redaction on the sensitive
+//! values of a text are replaced by their placeholders before it is sent,
+//! as for a translation.
+
+use super::{LiveSpeech, TtsService, TtsStatus};
+use crate::channel::channel::{TtsTarget, TtsUpdate, UiMessage};
+use crate::lock_mutex;
+use crate::services::audio::AudioCache;
+use crate::utils::redaction::Redactor;
+use crate::utils::tasks::{self, TaskKind, TaskRegistry};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Speaks texts through the audio cache and the TTS service.
+pub struct Speaker {
+    tts: Arc<TtsService>,
+    cache: Arc<AudioCache>,
+    runtime_handle: tokio::runtime::Handle,
+    /// Where each flow is listed while it runs
+    tasks: TaskRegistry,
+    /// Finds the values kept from the provider, `None` with redaction off
+    redactor: Option<Arc<Redactor>>,
+}
+
+impl Speaker {
+    pub fn new(
+        tts: Arc<TtsService>,
+        cache: Arc<AudioCache>,
+        runtime_handle: tokio::runtime::Handle,
+    ) -> Self {
+        Speaker {
+            tts,
+            cache,
+            runtime_handle,
+            tasks: TaskRegistry::default(),
+            redactor: None,
+        }
+    }
+
+    /// Lists the running flows in `tasks`.
+    pub fn with_tasks(mut self, tasks: TaskRegistry) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Redacts the texts spoken from now on with `redactor`, `None` to send
+    /// them as they are.
+    pub fn set_redactor(&mut self, redactor: Option<Redactor>) {
+        self.redactor = redactor.map(Arc::new);
+    }
+
+    /// Gets the audio of `text` ready in the background, reporting to `ui_tx`.
+    ///
+    /// Cached audio is reported as [`TtsUpdate::Cached`] right away,
+    /// otherwise the text is converted and cached. Once `cancel` is set
+    /// nothing more is reported. Cancelling the flow from the task list sets
+    /// `cancel` and reports [`TtsUpdate::Cancelled`].
+    pub fn speak(
+        &self,
+        text: String,
+        target: TtsTarget,
+        cancel: Arc<Mutex<bool>>,
+        ui_tx: Sender<UiMessage>,
+    ) -> JoinHandle<()> {
+        let text = match &self.redactor {
+            Some(redactor) => redactor.redact(&text).text,
+            None => text,
+        };
+        let tts = self.tts.clone();
+        let cache = self.cache.clone();
+        let label = format!("{:?}: {}", target, tasks::label_text(&text));
+        let guard = {
+            let (cancel, ui_tx) = (cancel.clone(), ui_tx.clone());
+            self.tasks
+                .register_cancellable(TaskKind::Speech, label, move || {
+                    *lock_mutex!(cancel) = true;
+                    let _ = ui_tx.try_send(UiMessage::Tts(target, TtsUpdate::Cancelled));
+                })
+        };
+        self.runtime_handle.spawn(async move {
+            let _guard = guard;
+            let cancelled = || *lock_mutex!(cancel);
+            let report = |update: TtsUpdate| {
+                let ui_tx = ui_tx.clone();
+                async move {
+                    let _ = ui_tx.send(UiMessage::Tts(target, update)).await;
+                }
+            };
+
+            if cancelled() {
+                tracing::info!("{:?} TTS cancelled before start", target);
+                return;
+            }
+            if let Some(audio_path) = cache.get(&text) {
+                tracing::info!("{:?} audio already cached: {:?}", target, audio_path);
+                report(TtsUpdate::Cached(audio_path.display().to_string())).await;
+                return;
+            }
+
+            tracing::info!(
+                "Starting TTS conversion for {:?} (length: {})",
+                target,
+                text.len()
+            );
+            let audio_path = cache.get_new_audio_path(&text);
+            report(TtsUpdate::Started).await;
+
+            // The status is handed back here so the message can wait for
+            // room in the UI channel
+            let (status_tx, status_rx) = oneshot::channel();
+            tts.convert_async(&text, &audio_path.to_string_lossy(), move |status| {
+                let _ = status_tx.send(status);
+            });
+            let Ok(status) = status_rx.await else {
+                return;
+            };
+
+            if cancelled() {
+                tracing::info!("{:?} TTS cancelled", target);
+                return;
+            }
+            match status {
+                TtsStatus::Completed(path) => {
+                    cache.set(&text, audio_path);
+                    tracing::info!("{:?} TTS completed: {}", target, path);
+                    report(TtsUpdate::Completed(path)).await;
+                }
+                TtsStatus::Failed(err) => {
+                    tracing::error!("{:?} TTS failed: {}", target, err);
+                    report(TtsUpdate::Failed(err)).await;
+                }
+                TtsStatus::Idle | TtsStatus::Converting => {}
+            }
+        })
+    }
+
+    /// Starts speaking a translation into `language` sentence by sentence
+    /// as it streams, with the current voice.
+    ///
+    /// Cancelling it from the task list stops converting further sentences.
+    pub fn speak_live(&self, language: &str) -> LiveSpeech {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let task = self.tasks.register_cancellable(
+            TaskKind::Speech,
+            format!("Speaking while translating → {}", language),
+            {
+                let interrupted = interrupted.clone();
+                move || interrupted.store(true, Ordering::Relaxed)
+            },
+        );
+        LiveSpeech::start(
+            self.tts.clone(),
+            self.cache.clone(),
+            &self.runtime_handle,
+            format!("{:?}", self.tts.get_config().voice),
+            language,
+            interrupted,
+            task,
+        )
+        .with_redactor(self.redactor.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::channel::UiChannel;
+    use crate::services::tts::{Synthesizer, TtsJobConfig};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+    use tokio_util::sync::CancellationToken;
+
+    /// Writes a tiny file after a long pause, like a slow provider.
+    struct SlowSynthesizer {
+        delay: Duration,
+        /// Every text it was asked to convert
+        spoken: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Synthesizer for SlowSynthesizer {
+        fn synthesize(
+            &self,
+            text: &str,
+            output_path: &str,
+            _config: &TtsJobConfig,
+            _timeout: Duration,
+            _cancel: &CancellationToken,
+        ) -> TtsStatus {
+            lock_mutex!(self.spoken).push(text.to_string());
+            std::thread::sleep(self.delay);
+            match std::fs::write(output_path, b"RIFF") {
+                Ok(()) => TtsStatus::Completed(output_path.to_string()),
+                Err(e) => TtsStatus::Failed(e.to_string()),
+            }
+        }
+    }
+
+    fn slow_speaker(name: &str, delay: Duration) -> (Speaker, Arc<Mutex<Vec<String>>>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("test_speaker_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        let handle = tokio::runtime::Handle::current();
+        let spoken = Arc::new(Mutex::new(Vec::new()));
+        let synthesizer = SlowSynthesizer {
+            delay,
+            spoken: spoken.clone(),
+        };
+        let tts = TtsService::with_synthesizer(Arc::new(synthesizer), handle.clone());
+        let speaker = Speaker::new(
+            Arc::new(tts),
+            Arc::new(AudioCache::new(dir.clone())),
+            handle,
+        );
+        (speaker, spoken, dir)
+    }
+
+    /// The TTS updates among `messages`.
+    fn tts_updates(messages: Vec<UiMessage>) -> Vec<TtsUpdate> {
+        messages
+            .into_iter()
+            .filter_map(|msg| match msg {
+                UiMessage::Tts(TtsTarget::Translation, update) => Some(update),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_messages_keep_flowing_during_synthesis() {
+        let (speaker, _, dir) = slow_speaker("slow", Duration::from_millis(500));
+        let mut channel = UiChannel::default();
+        let no_cancel = Arc::new(Mutex::new(false));
+
+        let started = Instant::now();
+        speaker.speak(
+            "Hallo Welt".to_string(),
+            TtsTarget::Translation,
+            no_cancel.clone(),
+            channel.sender(),
+        );
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        // Frames go on, and other messages get through, while it converts
+        let mut updates = Vec::new();
+        let mut frames = 0;
+        while !matches!(updates.last(), Some(TtsUpdate::Completed(_))) {
+            assert!(started.elapsed() < Duration::from_secs(5), "no result");
+            channel
+                .sender()
+                .send(UiMessage::Throughput(frames as f64))
+                .await
+                .unwrap();
+            let messages = channel.drain();
+            assert!(
+                messages
+                    .iter()
+                    .any(|msg| matches!(msg, UiMessage::Throughput(n) if *n == frames as f64))
+            );
+            updates.extend(tts_updates(messages));
+            frames += 1;
+            tokio::time::sleep(Duration::from_millis(16)).await;
+        }
+        assert!(frames >= 20, "only {} frames", frames);
+        let Some(TtsUpdate::Completed(path)) = updates.pop() else {
+            unreachable!()
+        };
+        assert_eq!(updates, vec![TtsUpdate::Started]);
+
+        // The second time the audio comes from the cache
+        speaker
+            .speak(
+                "Hallo Welt".to_string(),
+                TtsTarget::Translation,
+                no_cancel,
+                channel.sender(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(tts_updates(channel.drain()), vec![TtsUpdate::Cached(path)]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancelled_conversion_reports_nothing_more() {
+        let (speaker, _, dir) = slow_speaker("cancel", Duration::from_millis(200));
+        let mut channel = UiChannel::default();
+        let cancel = Arc::new(Mutex::new(false));
+
+        let task = speaker.speak(
+            "Hallo".to_string(),
+            TtsTarget::Translation,
+            cancel.clone(),
+            channel.sender(),
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        *lock_mutex!(cancel) = true;
+        task.await.unwrap();
+
+        assert_eq!(tts_updates(channel.drain()), vec![TtsUpdate::Started]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_redacted_values_are_never_synthesized() {
+        let (mut speaker, spoken, dir) = slow_speaker("redacted", Duration::ZERO);
+        speaker.set_redactor(Some(Redactor::new(&[])));
+        let mut channel = UiChannel::default();
+
+        speaker
+            .speak(
+                "Mail anna@example.com or call +49 30 1234567.".to_string(),
+                TtsTarget::Source,
+                Arc::new(Mutex::new(false)),
+                channel.sender(),
+            )
+            .await
+            .unwrap();
+        channel.drain();
+
+        assert_eq!(
+            *lock_mutex!(spoken),
+            vec!["Mail ⟦EMAIL_1⟧ or call ⟦PHONE_1⟧.".to_string()]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
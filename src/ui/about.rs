@@ -0,0 +1,194 @@
+//! About window: version, build, where the app keeps its files, and the
+//! third-party software it ships with.
+
+use crate::utils::logger::LogDetail;
+use crate::utils::version::{GIT_HASH, Release, VERSION};
+use egui::*;
+use std::path::Path;
+
+/// Third-party software and fonts the app is built with, and their licenses.
+const ACKNOWLEDGEMENTS: &[(&str, &str)] = &[
+    ("egui / eframe", "MIT OR Apache-2.0"),
+    ("tokio", "MIT"),
+    ("reqwest", "MIT OR Apache-2.0"),
+    ("serde / serde_json", "MIT OR Apache-2.0"),
+    ("rodio", "MIT OR Apache-2.0"),
+    ("rfd", "MIT"),
+    ("arboard", "MIT OR Apache-2.0"),
+    ("image", "MIT OR Apache-2.0"),
+    ("ab_glyph", "Apache-2.0"),
+    ("regex", "MIT OR Apache-2.0"),
+    ("chrono", "MIT OR Apache-2.0"),
+    ("pdf-extract", "MIT"),
+    ("spellbook", "MPL-2.0"),
+    ("Noto Serif KR font", "SIL Open Font License 1.1"),
+    ("DejaVu Sans font", "Bitstream Vera / DejaVu license"),
+];
+
+/// Files and folders shown in the About window.
+pub struct AboutPaths<'a> {
+    pub config: &'a Path,
+    pub translation_cache: &'a Path,
+    pub audio_cache: &'a Path,
+    /// Translation log, `None` when it could not be opened
+    pub log: Option<&'a Path>,
+    /// How much the translation log keeps, shown next to it
+    pub log_detail: LogDetail,
+}
+
+/// State of the About window.
+#[derive(Default)]
+pub struct AboutWindow {
+    open: bool,
+}
+
+impl AboutWindow {
+    /// Opens or closes the window.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Opens the window, e.g. to show the notes of a newer release.
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Shows the window, returning whether "Check for updates" was clicked.
+    ///
+    /// The button is only offered when `update_checks` are enabled in the
+    /// settings, and is disabled while a check is `checking`. The notes of
+    /// a newer `latest` release are shown under it.
+    pub fn ui(
+        &mut self,
+        ctx: &Context,
+        paths: &AboutPaths,
+        update_checks: bool,
+        checking: bool,
+        latest: Option<&Release>,
+    ) -> bool {
+        if !self.open {
+            return false;
+        }
+
+        let mut check = false;
+        let mut open = true;
+        Window::new("ℹAbout")
+            .id(Id::new("about"))
+            .open(&mut open)
+            .default_width(460.0)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading(format!("AI Translate {}", VERSION));
+                ui.label(
+                    RichText::new(format!(
+                        "Commit {} · {} build",
+                        GIT_HASH,
+                        if cfg!(debug_assertions) {
+                            "debug"
+                        } else {
+                            "release"
+                        }
+                    ))
+                    .weak(),
+                );
+                ui.hyperlink(env!("CARGO_PKG_REPOSITORY"));
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    if update_checks {
+                        let button = ui.add_enabled(!checking, Button::new("🔄 Check for updates"));
+                        if button.clicked() {
+                            check = true;
+                        }
+                        if checking {
+                            ui.spinner();
+                        }
+                    } else {
+                        ui.label(
+                            RichText::new("Update checks are off, turn them on in Settings.")
+                                .size(12.0)
+                                .weak(),
+                        );
+                    }
+                });
+                if let Some(release) = latest {
+                    ui.add_space(4.0);
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(format!("What's new in {}", release.tag)).strong());
+                        ui.hyperlink_to("Release page", &release.url);
+                    });
+                    let notes = release
+                        .notes
+                        .as_deref()
+                        .filter(|notes| !notes.trim().is_empty())
+                        .unwrap_or("No release notes.");
+                    ScrollArea::vertical()
+                        .id_salt("about_release_notes")
+                        .max_height(180.0)
+                        .show(ui, |ui| {
+                            ui.label(RichText::new(notes).size(12.0));
+                        });
+                }
+                ui.separator();
+
+                ui.label(RichText::new("Files").strong());
+                Grid::new("about_paths")
+                    .num_columns(2)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        let mut row = |label: &str, path: Option<&Path>| {
+                            ui.label(label);
+                            match path {
+                                Some(path) => {
+                                    let text = path.display().to_string();
+                                    let label = Label::new(RichText::new(&text).monospace())
+                                        .sense(Sense::click());
+                                    if ui.add(label).on_hover_text("Click to copy").clicked() {
+                                        ui.ctx().copy_text(text);
+                                    }
+                                }
+                                None => {
+                                    ui.label(RichText::new("not available").weak());
+                                }
+                            }
+                            ui.end_row();
+                        };
+                        row("Settings", Some(paths.config));
+                        row("Translation cache", Some(paths.translation_cache));
+                        row("Audio cache", Some(paths.audio_cache));
+                        row("Translation log", paths.log);
+                        ui.label("Log detail");
+                        ui.label(paths.log_detail.label());
+                        ui.end_row();
+                    });
+                ui.separator();
+
+                ui.label(RichText::new("Acknowledgements").strong());
+                ui.label(
+                    RichText::new(format!(
+                        "Licensed under {}. Built with:",
+                        env!("CARGO_PKG_LICENSE")
+                    ))
+                    .size(12.0),
+                );
+                ScrollArea::vertical()
+                    .id_salt("about_licenses")
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        Grid::new("about_licenses")
+                            .num_columns(2)
+                            .spacing([12.0, 2.0])
+                            .show(ui, |ui| {
+                                for (name, license) in ACKNOWLEDGEMENTS {
+                                    ui.label(RichText::new(*name).size(12.0));
+                                    ui.label(RichText::new(*license).size(12.0).weak());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        self.open = open;
+        check
+    }
+}
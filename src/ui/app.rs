@@ -1,44 +1,242 @@
-use crate::api::translator::Translator;
-use crate::channel::channel::UiMessage;
+use crate::api::client::{self, RetryPolicy, ThinkingMode};
+use crate::api::coalesce::Coalescer;
+use crate::api::consistency::Inconsistency;
+use crate::api::prompt::PromptContext;
+use crate::api::request::{InFlightRequest, TranslationRequest};
+use crate::api::session::{SessionOptions, StreamEvent, TranslationSession};
+use crate::api::translator::{self, Alternative, Translator, looks_untranslated};
+use crate::api::transport;
+use crate::channel::channel::{TtsTarget, TtsUpdate, UiChannel, UiMessage, Warning, WarningKind};
+use crate::error::TranslationError;
 use crate::lock_mutex;
-use crate::services::audio::{AudioCache, AudioPlayer};
-use crate::services::tts::{TtsConfig, TtsService};
-use crate::ui::display::DisplayPanel;
+use crate::services::audio::{AudioCache, AudioCacheTombstone, AudioPlayer};
+use crate::services::tts::{LiveSpeech, Speaker, TtsService, TtsStatus};
+use crate::ui::about::{AboutPaths, AboutWindow};
+use crate::ui::compare::CompareAction;
+use crate::ui::display::{self, AlignmentView, DisplayActions, DisplayPanel};
+use crate::ui::glossary::{GlossaryAction, GlossaryWindow};
+use crate::ui::history::{HistoryAction, HistoryPanel};
+use crate::ui::palette::CommandPalette;
+use crate::ui::pdf_preview::{PdfPreview, PdfPreviewAction};
+use crate::ui::redaction::{RedactionDecision, RedactionPreview};
 use crate::ui::settings::{SettingsChange, SettingsConfig, SettingsPanel, ThemePreference};
-use crate::ui::sidebar::Sidebar;
-use crate::ui::theme::Theme;
-use crate::utils::cache::TranslationCache;
-use crate::utils::config::AppConfig;
+use crate::ui::sidebar::{self, RomanizationState, Sidebar, SidebarActions};
+use crate::ui::status_bar::StatusBar;
+use crate::ui::structured::{StructuredAction, StructuredWindow};
+use crate::ui::tasks::tasks_ui;
+use crate::ui::theme::{self, Theme};
+use crate::ui::toast::{ToastAction, Toasts};
+use crate::utils::actions::{Action, ActionRegistry};
+use crate::utils::alignment::{self, Aligner};
+use crate::utils::announce::Announcer;
+use crate::utils::cache::{self, Namespace, TranslationCache};
+use crate::utils::cache_rules::CacheRules;
+use crate::utils::config::{AppConfig, SourcePanelLayout};
+use crate::utils::config_watch::{self, ConfigWatcher};
+use crate::utils::diagnostics::{self, BundleInputs, TraceBuffer};
+use crate::utils::dialogs::{self, Dialog, DialogCategory};
+use crate::utils::documents::{self, DocumentId, Documents, StoredDocument, StoredDocuments};
+use crate::utils::glossary::{Glossary, Term};
+use crate::utils::glyphs;
+use crate::utils::history::{self, HistoryEntry};
+use crate::utils::hooks::CompletionHook;
+use crate::utils::instance::{self, Forwarded, Launch};
+use crate::utils::langid;
+use crate::utils::layout::{Arrangement, LayoutPlan, Tab};
 use crate::utils::logger::Logger;
+use crate::utils::metrics::{RequestKind, RequestMetrics, RequestOutcome};
+use crate::utils::offline_queue::{OfflineQueue, QueuedTranslation};
+use crate::utils::paths::{self, AppPaths};
+use crate::utils::pdf;
+use crate::utils::practice::{Grade, PracticeStats};
+use crate::utils::pricing::{self, Estimate, OutputRatios};
+use crate::utils::provenance::Provenance;
+use crate::utils::redaction::{Redaction, Redactor};
+use crate::utils::retention::{self, Report, StorageDirs, Usage};
+use crate::utils::romanize::Scheme;
+use crate::utils::sanitize::{self, CleanReport};
+use crate::utils::share;
+use crate::utils::structured::{Format, StructuredDocument, ValueBatch};
+use crate::utils::tasks::{self, TaskGuard, TaskKind, TaskRegistry};
+use crate::utils::tmx;
+use crate::utils::undo::{UndoId, UndoManager};
+use crate::utils::version::{self, Release};
+use crate::utils::view_state::{ViewState, ViewStates};
+use crate::utils::webpage;
 use eframe::egui;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use std::time::{Duration, Instant, SystemTime};
 
-/// Enum representing the type of TTS (source or translation)
-enum TtsType {
-    Source,
-    Translation,
+/// Data removed by a destructive action, kept until its undo window passes
+enum Deletion {
+    /// Backup file written before the translation cache was cleared
+    TranslationCache(PathBuf),
+    AudioCache(AudioCacheTombstone),
+    OfflineQueue(Vec<QueuedTranslation>),
+    GlossaryTerm(Term),
+}
+
+impl Deletion {
+    /// Makes the deletion permanent
+    fn finalize(self) {
+        match self {
+            Deletion::TranslationCache(backup_file) => {
+                if let Err(e) = std::fs::remove_file(&backup_file) {
+                    tracing::warn!("Failed to remove cache backup {:?}: {}", backup_file, e);
+                }
+            }
+            Deletion::AudioCache(tombstone) => tombstone.purge(),
+            // Already removed from the queue and glossary files
+            Deletion::OfflineQueue(_) | Deletion::GlossaryTerm(_) => {}
+        }
+    }
+}
+
+/// What the widgets show for a tab, kept in the tab while another is active
+#[derive(Default)]
+struct DocumentState {
+    display: DisplayPanel,
+    current_request: Option<TranslationRequest>,
+    redaction: Option<Redaction>,
+    viewed_entry: Option<(HistoryEntry, ViewState)>,
 }
 
 pub struct TranslateApp {
     config: AppConfig,
+    /// Settings both stored copies hold, `None` while they need syncing
+    persisted: Option<AppConfig>,
+    /// Follows the configuration file while the watch option is on
+    config_watcher: Option<ConfigWatcher>,
+    /// Settings file of an older version, offered for import
+    legacy_config: Option<PathBuf>,
     sidebar: Sidebar,
     display: DisplayPanel,
     theme: Theme,
     settings: SettingsPanel,
+    toasts: Toasts,
+    status_bar: StatusBar,
+    /// Layout of the last frame, `None` before the first
+    layout: Option<LayoutPlan>,
+    /// Tab shown when the window is arranged in tabs
+    tab: Tab,
+    /// Text extracted from a PDF, shown for correction
+    pdf_preview: PdfPreview,
+    /// Values of an opened JSON or YAML document
+    structured: StructuredWindow,
+    /// Values about to be redacted, waiting for approval
+    redaction_preview: RedactionPreview,
+    /// Searchable translation history
+    history: HistoryPanel,
+    /// Scroll position and open sections of history entries
+    view_states: Arc<Mutex<ViewStates>>,
+    /// History entry on display and its current view
+    viewed_entry: Option<(HistoryEntry, ViewState)>,
+    /// Open tabs; the state of the active one is in the fields above
+    documents: Documents<DocumentState>,
+    /// Tab the running translation streams into
+    streaming_document: Option<DocumentId>,
+    /// Tab swapped in while a message of its stream is handled
+    background: Option<DocumentId>,
+    /// Tab waiting for the user to confirm closing it mid-translation
+    closing_document: Option<DocumentId>,
+    /// Mandated translations of terms
+    glossary: Glossary,
+    glossary_window: GlossaryWindow,
+    about: AboutWindow,
+    /// Everything buttons, shortcuts and the command palette can run
+    actions: Rc<ActionRegistry<TranslateApp>>,
+    palette: CommandPalette,
+    /// Reads out the progress of translations to screen readers
+    announcer: Announcer,
+    /// An update check is running
+    checking_for_updates: bool,
+    /// Newer release found by the last update check
+    latest_release: Option<Release>,
+    /// Space taken by the app's files, shown in the settings
+    storage_usage: Vec<Usage>,
     logger: Option<Arc<Logger>>,
     cache: Arc<TranslationCache>,
-    translator: Option<Arc<Translator>>,
+    /// Shared by every session, so identical text translations made at the
+    /// same time, e.g. in two tabs or by a queued run, are sent once
+    coalescer: Coalescer,
+    /// Session of the current translation
+    session: Option<Arc<TranslationSession>>,
     is_translating: bool,
-    cancel_requested: Arc<Mutex<bool>>,
-    ui_tx: UnboundedSender<UiMessage>,
-    ui_rx: Arc<Mutex<Option<UnboundedReceiver<UiMessage>>>>,
+    /// Parameters of the current translation request
+    current_request: Option<TranslationRequest>,
+    /// Values redacted from the current request, restored for display
+    redaction: Option<Redaction>,
+    /// The streaming request as it was started, until its stream ends
+    in_flight: Option<InFlightRequest>,
+    /// Request that failed while offline, offered for queueing
+    offline_request: Option<TranslationRequest>,
+    /// Translations requested while offline
+    offline_queue: OfflineQueue,
+    /// Self-grades given in practice mode
+    practice_stats: PracticeStats,
+    /// Output tokens per input token of the language pairs translated
+    output_ratios: OutputRatios,
+    /// Source text generation and target language the shown cost estimate
+    /// was made for
+    cost_estimated_for: Option<(u64, String)>,
+    /// Language pair of the translation in flight, with the source text
+    /// generation when it is the text in the sidebar
+    priced_translation: Option<(String, Option<u64>)>,
+    /// Whether the last connectivity probe succeeded
+    queue_online: bool,
+    /// When the last connectivity probe was started
+    last_probe: Option<Instant>,
+    probe_in_flight: bool,
+    /// Whether a translation has finished since launch
+    translated_since_launch: bool,
+    /// Whether "Run all" is working through the offline queue
+    running_queue: bool,
+    /// Play the translation audio once it is ready, for auto-speak
+    auto_play_translation: bool,
+    /// The translation being spoken sentence by sentence as it streams
+    live_speech: Option<LiveSpeech>,
+    /// When the app was created, until its first interactive frame
+    startup: Option<Instant>,
+    /// Whether the fonts are installed, which happens on the first frame
+    fonts_installed: bool,
+    /// Soft-deleted data that can still be restored
+    undo: UndoManager<Deletion>,
+    /// Recent tracing output for diagnostic bundles
+    trace_buffer: TraceBuffer,
+    /// Whether an explanation of the translation is streaming
+    is_explaining: bool,
+    /// Session of the explanation, cancelled without touching the translation
+    explain_session: Option<Arc<TranslationSession>>,
+    /// Session of the running confidence check
+    confidence_session: Option<Arc<TranslationSession>>,
+    /// Source phrases found for words of translations, and the pace of
+    /// the requests
+    aligner: Aligner,
+    /// Running alignment request, aborted when another word is clicked
+    alignment_task: Option<tokio::task::JoinHandle<()>>,
+    /// Running romanization of the source text, aborted when another
+    /// text is romanized
+    romanization_task: Option<tokio::task::JoinHandle<()>>,
+    /// Background jobs running right now
+    tasks: TaskRegistry,
+    ui_channel: UiChannel,
+    /// Files named by later launches of the app
+    forwarded: Option<Forwarded>,
+    /// Folder of a second instance's files, deleted at exit; nothing is
+    /// saved for good then
+    scratch_dir: Option<PathBuf>,
     _runtime: tokio::runtime::Runtime, // Prefixed with _ to silence unused warning
     runtime_handle: tokio::runtime::Handle,
 
     // TTS components
     tts_service: Arc<TtsService>,
     audio_cache: Arc<AudioCache>,
+    /// Runs the speak flow off the UI thread
+    speaker: Speaker,
     audio_player: Arc<AudioPlayer>,
     // Independent TTS cancellation flags
     source_tts_cancel_requested: Arc<Mutex<bool>>,
@@ -46,79 +244,567 @@ pub struct TranslateApp {
 }
 
 impl TranslateApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let config = cc
-            .storage
-            .map(AppConfig::from_storage)
-            .unwrap_or_else(|| AppConfig::load_or_default(&cc.egui_ctx));
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        trace_buffer: TraceBuffer,
+        launch: Launch,
+    ) -> Self {
+        let started = Instant::now();
+        let _span = tracing::info_span!("startup").entered();
+        let (config, resync_config) = AppConfig::load_synced(cc.storage);
+        let persisted = (!resync_config).then(|| config.clone());
 
         let theme = Theme {
             dark: config.dark_theme,
             font_size: config.font_size,
+            custom_font: config.custom_font_path.clone(),
         };
 
-        theme.setup_fonts(&cc.egui_ctx);
+        // Fonts are installed by the first frame, see `install_fonts`
+        let mut toasts = Toasts::default();
         theme.apply_style(&cc.egui_ctx);
         theme.set_visuals(&cc.egui_ctx);
 
         let mut sidebar = Sidebar::default();
         sidebar.set_api_key(config.api_key.clone());
         sidebar.set_target_language(config.target_language.clone());
+        sidebar.set_recent_languages(config.recent_languages.clone());
+        sidebar.set_prompt_hints(config.prompt_domain.clone(), config.prompt_audience.clone());
+        sidebar.set_proficiencies(config.proficiencies());
+        sidebar.set_spellcheck(config.spellcheck_enabled, &config.spellcheck_language);
+        sidebar.set_romanize_source(config.romanize_source);
+        sidebar.set_layout(
+            config.sidebar_collapsed,
+            config.sidebar_width,
+            config.sidebar_auto_collapse,
+        );
 
-        let settings = SettingsPanel::new(SettingsConfig {
-            font_size: config.font_size,
-            dark_theme: config.dark_theme,
-            tts_voice: config.tts_voice.clone(),
-            tts_speed: config.tts_speed,
-            tts_volume: config.tts_volume,
-            enable_keyword_analysis: config.enable_keyword_analysis,
-            think_enable: config.think_enable,
-            coding_plan: config.coding_plan,
-        });
+        let settings = SettingsPanel::new(SettingsConfig::from(&config));
 
-        let logger = Logger::new("translations.log").ok().map(Arc::new);
-        let cache = Arc::new(TranslationCache::default());
-        let audio_cache = Arc::new(AudioCache::default());
-        let audio_player = Arc::new(AudioPlayer::new());
+        // A second instance keeps its files apart from the running one's
+        let scratch_dir = (!launch.persist).then(instance::scratch_dir);
+        if let Some(dir) = &scratch_dir {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                tracing::warn!("Failed to create {:?}: {}", dir, e);
+            }
+            toasts.warning(
+                "Another window of the app is open, so nothing changed in this one will be saved",
+            );
+        }
 
-        let (ui_tx, ui_rx) = mpsc::unbounded_channel();
+        // The log is archived before it is opened for writing
+        let log_path = match &scratch_dir {
+            Some(dir) => dir.join("translations.log"),
+            None => PathBuf::from("translations.log"),
+        };
+        if let Some(max_mb) = config.log_rotate_mb
+            && scratch_dir.is_none()
+        {
+            let max_bytes = u64::from(max_mb) * 1024 * 1024;
+            for path in [log_path.clone(), Logger::metrics_path_for(&log_path)] {
+                if let Err(e) = retention::rotate_log(&path, &paths::log_archive_dir(), max_bytes) {
+                    tracing::warn!("Failed to archive {:?}: {}", path, e);
+                }
+            }
+        }
+        let logger = Logger::new(&log_path.to_string_lossy()).ok().map(|logger| {
+            logger.set_detail(config.log_detail);
+            Arc::new(logger)
+        });
+        // Read as empty until loaded, so they don't hold up the window
+        let (cache, audio_cache) = match &scratch_dir {
+            Some(dir) => (
+                TranslationCache::load_in_background(dir.join("translation_cache.json")),
+                AudioCache::load_in_background(dir.join("audio")),
+            ),
+            None => (
+                TranslationCache::load_in_background(TranslationCache::default_path()),
+                AudioCache::load_in_background(paths::audio_cache_dir()),
+            ),
+        };
+        let cache = Arc::new(cache);
+        let audio_cache = Arc::new(audio_cache);
+        let audio_player = Arc::new(AudioPlayer::new());
 
         let rt = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
         let runtime_handle = rt.handle().clone();
 
         // Initialize TTS service with API key and runtime handle
         let tts_service = Arc::new(TtsService::new(config.api_key.clone(), runtime_handle.clone()));
+        let tasks = TaskRegistry::default();
+        let speaker = Speaker::new(
+            tts_service.clone(),
+            audio_cache.clone(),
+            runtime_handle.clone(),
+        )
+        .with_tasks(tasks.clone());
 
         // Configure TTS service
-        let tts_config = TtsConfig::new(
-            AppConfig::parse_voice(&config.tts_voice),
-            config.tts_speed,
-            config.tts_volume,
-            config.coding_plan,
-            config.think_enable,
+        tts_service.update_config(config.tts_config());
+        audio_player.set_volume(config.playback_volume());
+
+        let glossary = match &scratch_dir {
+            // Starts from a copy, so the terms are there but changes stay apart
+            Some(dir) => {
+                let file = dir.join("glossary.json");
+                let _ = std::fs::copy(Glossary::default_path(), &file);
+                Glossary::new(file)
+            }
+            None => Glossary::default(),
+        };
+        let (queue_path, stats_path, ratios_path) = match &scratch_dir {
+            Some(dir) => (
+                dir.join("offline_queue.json"),
+                dir.join("practice_stats.json"),
+                dir.join("output_ratios.json"),
+            ),
+            None => (
+                OfflineQueue::default_path(),
+                PracticeStats::default_path(),
+                OutputRatios::default_path(),
+            ),
+        };
+        let view_states = Arc::new(Mutex::new(ViewStates::new(match &scratch_dir {
+            Some(dir) => dir.join("history_views.json"),
+            None => ViewStates::default_path(),
+        })));
+        let offline_queue = OfflineQueue::new(queue_path, config.offline_queue_limit);
+        let practice_stats = PracticeStats::new(stats_path);
+
+        let documents = Documents::new(
+            String::new(),
+            config.target_language.clone(),
+            DocumentState::default(),
         );
-        tts_service.update_config(tts_config);
 
-        TranslateApp {
+        let ui_channel = UiChannel::default();
+        if let Some(forwarded) = &launch.forwarded {
+            forwarded.wake(&cc.egui_ctx);
+        }
+        transport::set_connect_timeout(Duration::from_secs(config.connect_timeout_secs));
+        dialogs::set_recent_dirs(config.recent_dirs.clone());
+        // Warm up the connection pool for the first translation; queued
+        // offline translations mean the provider was unreachable last time
+        if config.preconnect_on_startup && !config.api_key.is_empty() && offline_queue.is_empty() {
+            let ui_tx = ui_channel.sender();
+            let base_url = config.base_url();
+            runtime_handle.spawn(async move {
+                match client::preconnect(&base_url).await {
+                    Ok(()) => {
+                        tracing::info!("Pre-connected to the provider");
+                        let _ = ui_tx.send(UiMessage::ConnectionWarmed).await;
+                    }
+                    Err(e) => tracing::warn!("Pre-connect failed: {}", e),
+                }
+            });
+        }
+
+        let mut app = TranslateApp {
             _runtime: rt,
             config,
+            persisted,
+            config_watcher: None,
+            legacy_config: None,
             sidebar,
             display: DisplayPanel::default(),
             theme,
             settings,
+            toasts,
+            status_bar: StatusBar::default(),
+            layout: None,
+            tab: Tab::default(),
+            pdf_preview: PdfPreview::default(),
+            structured: StructuredWindow::default(),
+            redaction_preview: RedactionPreview::default(),
+            history: HistoryPanel::default(),
+            view_states,
+            viewed_entry: None,
+            documents,
+            streaming_document: None,
+            background: None,
+            closing_document: None,
+            glossary,
+            glossary_window: GlossaryWindow::default(),
+            about: AboutWindow::default(),
+            actions: Rc::new(TranslateApp::action_registry()),
+            palette: CommandPalette::default(),
+            announcer: Announcer::default(),
+            checking_for_updates: false,
+            latest_release: None,
+            storage_usage: Vec::new(),
             logger,
             cache,
-            translator: None,
+            coalescer: Coalescer::default(),
+            session: None,
             is_translating: false,
-            cancel_requested: Arc::new(Mutex::new(false)),
-            ui_tx,
-            ui_rx: Arc::new(Mutex::new(Some(ui_rx))),
+            current_request: None,
+            redaction: None,
+            in_flight: None,
+            offline_request: None,
+            offline_queue,
+            practice_stats,
+            output_ratios: OutputRatios::new(ratios_path),
+            cost_estimated_for: None,
+            priced_translation: None,
+            queue_online: false,
+            last_probe: None,
+            probe_in_flight: false,
+            translated_since_launch: false,
+            running_queue: false,
+            auto_play_translation: false,
+            live_speech: None,
+            startup: Some(started),
+            fonts_installed: false,
+            undo: UndoManager::default(),
+            trace_buffer,
+            is_explaining: false,
+            explain_session: None,
+            confidence_session: None,
+            aligner: Aligner::default(),
+            alignment_task: None,
+            romanization_task: None,
+            tasks,
+            ui_channel,
+            forwarded: launch.forwarded,
+            scratch_dir,
             runtime_handle,
+            speaker,
             tts_service,
             audio_cache,
             audio_player,
             source_tts_cancel_requested: Arc::new(Mutex::new(false)),
             translation_tts_cancel_requested: Arc::new(Mutex::new(false)),
+        };
+        // Old files belong to the running instance, which cleans them itself
+        if app.scratch_dir.is_none() {
+            app.clean_storage(false);
+        }
+        app.set_config_watch(&cc.egui_ctx, app.config.watch_config_file);
+        app.update_speech_redaction();
+        if app.scratch_dir.is_none() {
+            app.legacy_config = AppPaths::get().legacy_config_file.clone();
+        }
+        app.configure_display();
+        if app.scratch_dir.is_none()
+            && let Some(stored) = cc.storage.and_then(StoredDocuments::load)
+        {
+            app.restore_documents(stored);
+        }
+        for path in launch.files {
+            app.open_file(path);
+        }
+        app.forget_deleted_views();
+        app
+    }
+
+    /// Shows a loading frame and installs the fonts
+    ///
+    /// They are parsed when the next frame starts, so the window appears
+    /// before that instead of after.
+    fn install_fonts(&mut self, ctx: &egui::Context) {
+        let _span = tracing::info_span!("install_fonts").entered();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.centered_and_justified(|ui| ui.spinner());
+        });
+        if let Err(e) = self.theme.setup_fonts(ctx) {
+            self.toasts
+                .warning(format!("Using the bundled fonts only. {}", e));
+        }
+        self.fonts_installed = true;
+        ctx.request_repaint();
+    }
+
+    /// Logs how long the first interactive frame took to appear
+    fn finish_startup(&mut self) {
+        let Some(started) = self.startup.take() else {
+            return;
+        };
+        tracing::info!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "First interactive frame"
+        );
+        // The output device was opened in the background meanwhile
+        self.display.set_playback_volume(
+            self.config.playback_volume(),
+            self.audio_player.volume_adjustable(),
+        );
+    }
+
+    /// Drops the remembered views of entries no longer in the history
+    fn forget_deleted_views(&self) {
+        let Some(log_path) = self.logger.as_ref().map(|l| l.path().to_path_buf()) else {
+            return;
+        };
+        let view_states = self.view_states.clone();
+        self.runtime_handle.spawn_blocking(move || {
+            if lock_mutex!(view_states).is_empty() {
+                return;
+            }
+            match history::load(&log_path) {
+                Ok(entries) => lock_mutex!(view_states).retain_entries(&entries),
+                Err(e) => tracing::warn!("Failed to read the history: {}", e),
+            }
+        });
+    }
+
+    /// Remembers how the history entry on display was left
+    fn leave_history_entry(&mut self) {
+        if let Some((entry, view)) = self.viewed_entry.take() {
+            lock_mutex!(self.view_states).set(&entry, view);
+        }
+    }
+
+    /// Applies the display settings to the display of the active tab
+    fn configure_display(&mut self) {
+        let config = &self.config;
+        self.display.set_practice_mode(config.practice_mode);
+        self.display
+            .set_show_pronunciation(config.show_pronunciation_for(&config.target_language));
+        self.display.set_auto_font(
+            config.auto_font_source,
+            config.auto_font_translation,
+            config.script_font_scales.clone(),
+        );
+        self.display.set_smooth_typing(config.smooth_typing);
+        self.display.set_reasoning_open(!config.reasoning_collapsed);
+        self.display.set_practice_summary(
+            self.practice_stats
+                .summary(chrono::Local::now().date_naive()),
+        );
+        self.display.set_playback_volume(
+            config.playback_volume(),
+            self.audio_player.volume_adjustable(),
+        );
+    }
+
+    /// Exchanges the state of the widgets with the state kept in tab `id`,
+    /// returning whether there is such a tab
+    fn swap_document_state(&mut self, id: DocumentId) -> bool {
+        let Some(document) = self.documents.get_mut(id) else {
+            return false;
+        };
+        let parked = &mut document.parked;
+        std::mem::swap(&mut self.display, &mut parked.display);
+        std::mem::swap(&mut self.current_request, &mut parked.current_request);
+        std::mem::swap(&mut self.redaction, &mut parked.redaction);
+        std::mem::swap(&mut self.viewed_entry, &mut parked.viewed_entry);
+        true
+    }
+
+    /// Stops what belongs to the active tab's display and keeps its state
+    /// in the tab
+    ///
+    /// A running translation keeps streaming into the tab.
+    fn leave_active_document(&mut self) {
+        self.stop_audio();
+        self.cancel_source_tts();
+        self.cancel_translation_tts();
+        self.cancel_explanation();
+        self.display.set_explaining(false);
+        self.cancel_confidence_check();
+
+        let id = self.documents.active_id();
+        let document = self.documents.active_mut();
+        document.source_text = self.sidebar.get_source_text();
+        document.target_language = self.sidebar.get_target_language();
+        self.swap_document_state(id);
+    }
+
+    /// Shows the state kept in the active tab
+    fn enter_active_document(&mut self) {
+        let id = self.documents.active_id();
+        self.swap_document_state(id);
+        let document = self.documents.active_mut();
+        self.sidebar
+            .set_source_text(std::mem::take(&mut document.source_text));
+        self.sidebar
+            .set_target_language(std::mem::take(&mut document.target_language));
+        self.configure_display();
+        // Restored tabs have their translation but not the request yet,
+        // nor the values to keep out of follow-ups
+        if self.current_request.is_none() && !self.display.translation().is_empty() {
+            self.set_sidebar_request();
+        }
+    }
+
+    /// Switches to tab `id`
+    fn select_document(&mut self, id: DocumentId) {
+        if id == self.documents.active_id() {
+            return;
+        }
+        self.leave_active_document();
+        self.documents.select(id);
+        self.enter_active_document();
+    }
+
+    /// Opens an empty tab next to the active one and switches to it
+    fn new_document(&mut self) {
+        let id = self.documents.open(self.sidebar.get_target_language());
+        self.select_document(id);
+        self.tab = Tab::Input;
+    }
+
+    /// Closes tab `id`, once confirmed if its translation is streaming
+    fn request_close_document(&mut self, id: DocumentId) {
+        if self.streaming_document == Some(id) {
+            self.closing_document = Some(id);
+        } else {
+            self.close_document(id);
+        }
+    }
+
+    /// Closes tab `id`, cancelling its translation
+    fn close_document(&mut self, id: DocumentId) {
+        if self.documents.len() == 1 {
+            return;
+        }
+        if self.streaming_document == Some(id) {
+            self.cancel_translation();
+        }
+        let active = id == self.documents.active_id();
+        if active {
+            self.leave_active_document();
+        }
+        if let Some(document) = self.documents.close(id)
+            && let Some((entry, view)) = document.parked.viewed_entry
+        {
+            lock_mutex!(self.view_states).set(&entry, view);
+        }
+        if active {
+            self.enter_active_document();
+        }
+    }
+
+    /// Reopens the tabs stored at the last exit
+    fn restore_documents(&mut self, stored: StoredDocuments) {
+        let parked = |document: StoredDocument| {
+            let mut display = DisplayPanel::default();
+            display.set_input(document.source_text.clone());
+            display.set_target_language(&document.target_language);
+            display.update_translation(document.translation);
+            (
+                document.source_text,
+                document.target_language,
+                DocumentState {
+                    display,
+                    ..DocumentState::default()
+                },
+            )
+        };
+        let mut stored_documents = stored.documents.into_iter().map(parked);
+        let Some((source_text, target_language, state)) = stored_documents.next() else {
+            return;
+        };
+        let mut documents = Documents::new(source_text, target_language, state);
+        let mut ids = vec![documents.active_id()];
+        for (source_text, target_language, state) in stored_documents {
+            ids.push(documents.push(source_text, target_language, state));
+        }
+        documents.select(ids[stored.active.min(ids.len() - 1)]);
+        tracing::info!(tabs = documents.len(), "Restored the tabs");
+        self.documents = documents;
+        self.enter_active_document();
+    }
+
+    /// The open tabs, to store for the next start
+    fn stored_documents(&self) -> StoredDocuments {
+        let active = self.documents.active_id();
+        let documents = self
+            .documents
+            .iter()
+            .map(|document| match document.id == active {
+                true => StoredDocument {
+                    source_text: self.sidebar.get_source_text(),
+                    target_language: self.sidebar.get_target_language(),
+                    translation: self.display.translation().as_str().to_owned(),
+                },
+                false => StoredDocument {
+                    source_text: document.source_text.clone(),
+                    target_language: document.target_language.clone(),
+                    translation: document.parked.display.translation().as_str().to_owned(),
+                },
+            })
+            .collect();
+        StoredDocuments {
+            documents,
+            active: self
+                .documents
+                .iter()
+                .position(|document| document.id == active)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Strip of the open tabs, above the central panel
+    fn documents_ui(&mut self, ctx: &egui::Context) {
+        let active = self.documents.active_id();
+        let mut selected = None;
+        let mut closed = None;
+        let mut opened = false;
+        egui::TopBottomPanel::top("documents").show(ctx, |ui| {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for document in self.documents.iter() {
+                        let mut title = match document.id == active {
+                            true => documents::title(&self.sidebar.get_source_text()),
+                            false => documents::title(&document.source_text),
+                        };
+                        if self.streaming_document == Some(document.id) {
+                            title = format!("⏳ {}", title);
+                        }
+                        if ui.selectable_label(document.id == active, title).clicked() {
+                            selected = Some(document.id);
+                        }
+                        if self.documents.len() > 1
+                            && ui.small_button("✕").on_hover_text("Close tab").clicked()
+                        {
+                            closed = Some(document.id);
+                        }
+                        ui.separator();
+                    }
+                    opened = self.action_button(ui, "tab.new", "➕");
+                });
+            });
+        });
+        if let Some(id) = selected {
+            self.select_document(id);
+        }
+        if let Some(id) = closed {
+            self.request_close_document(id);
+        }
+        if opened {
+            self.new_document();
+        }
+    }
+
+    /// Asks whether to close a tab whose translation is still streaming
+    fn close_confirmation_ui(&mut self, ctx: &egui::Context) {
+        let Some(id) = self.closing_document else {
+            return;
+        };
+        let mut close = None;
+        egui::Window::new("⚠Close Tab")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    "The translation in this tab is still streaming. Closing the tab cancels it.",
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Close tab").clicked() {
+                        close = Some(true);
+                    }
+                    if ui.button("Keep open").clicked() {
+                        close = Some(false);
+                    }
+                });
+            });
+        if let Some(close) = close {
+            self.closing_document = None;
+            if close {
+                self.close_document(id);
+            }
         }
     }
 
@@ -128,7 +814,240 @@ impl TranslateApp {
             return;
         }
 
+        // JSON and YAML get their string values translated instead
+        if self.sidebar.code_mode().is_none()
+            && let Some(document) = StructuredDocument::parse(&self.sidebar.get_source_text())
+        {
+            tracing::info!(
+                format = document.format.label(),
+                values = document.values().len(),
+                "Source text is a structured document"
+            );
+            self.structured.open("Source text".to_string(), document);
+            return;
+        }
+        if self.confirm_same_language() {
+            return;
+        }
+        self.translate_source(api_key);
+    }
+
+    /// Asks before translating a text that already seems to be in the
+    /// target language, returning whether it asked
+    fn confirm_same_language(&mut self) -> bool {
+        let target_language = self.sidebar.get_target_language();
+        let Some(guess) = langid::detect(&self.sidebar.get_source_text())
+            .filter(|guess| guess.is_confident() && guess.language == target_language)
+        else {
+            return false;
+        };
+        tracing::info!(
+            language = guess.language,
+            confidence = guess.confidence,
+            "Source text is already in the target language"
+        );
+        // The language translated into last, or a sensible other one
+        let alternative = self
+            .config
+            .recent_languages
+            .iter()
+            .find(|language| **language != target_language)
+            .cloned()
+            .unwrap_or_else(|| {
+                let fallback = if target_language == "中文" {
+                    "English"
+                } else {
+                    "中文"
+                };
+                fallback.to_string()
+            });
+        self.display
+            .confirm_same_language(target_language, alternative);
+        true
+    }
+
+    /// Translates the source text as it is, whatever it looks like
+    fn translate_source(&mut self, api_key: String) {
+        self.start_romanization();
+        let target_language = self.sidebar.get_target_language();
+        self.config.record_recent_language(&target_language);
+        self.sidebar
+            .set_recent_languages(self.config.recent_languages.clone());
+
+        let mut request = self.sidebar_request();
+        if let Some(max_chars) = self.config.fast_path_chars
+            && request.suits_fast_path(max_chars as usize)
+        {
+            tracing::debug!("Short text, taking the fast path");
+            request = request.on_fast_path();
+        }
+        if !self.config.redact_sensitive {
+            self.redaction = None;
+            self.run_translation(api_key, request, None);
+            return;
+        }
+        let redaction = Redactor::new(&self.config.redaction_patterns).redact(&request.source_text);
+        if !redaction.is_empty() && self.config.redaction_preview {
+            self.redaction_preview.open(request, redaction);
+        } else {
+            self.send_redacted(api_key, request, redaction);
+        }
+    }
+
+    /// Translates `request` with the values of `redaction` replaced by
+    /// placeholders, restoring them in the translation as it arrives
+    fn send_redacted(
+        &mut self,
+        api_key: String,
+        mut request: TranslationRequest,
+        redaction: Redaction,
+    ) {
+        tracing::info!(
+            values = redaction.values.len(),
+            "Redacted sensitive values from the source text"
+        );
+        self.apply_redaction(&mut request, redaction);
+        self.run_translation(api_key, request, None);
+    }
+
+    /// Puts the placeholders of `redaction` into `request` and keeps its
+    /// values for the output
+    fn apply_redaction(&mut self, request: &mut TranslationRequest, redaction: Redaction) {
+        request.context.terms = self
+            .glossary
+            .matcher(&request.target_language)
+            .prompt_terms(&redaction.text);
+        request.source_text = redaction.text.clone();
+        self.redaction = (!redaction.is_empty()).then_some(redaction);
+    }
+
+    /// Makes the sidebar's text the current request, redacted as if it had
+    /// been sent, so follow-ups on a translation shown without a request
+    /// keep the sensitive values from the provider too
+    fn set_sidebar_request(&mut self) {
+        let mut request = self.sidebar_request();
+        self.redaction = None;
+        if self.config.redact_sensitive {
+            let redaction =
+                Redactor::new(&self.config.redaction_patterns).redact(&request.source_text);
+            self.apply_redaction(&mut request, redaction);
+        }
+        self.current_request = Some(request);
+    }
+
+    /// `text` with the redacted values of the current request put back
+    fn reveal(&self, text: &str) -> String {
+        self.redaction
+            .as_ref()
+            .map_or_else(|| text.to_string(), |redaction| redaction.restore(text))
+    }
+
+    /// `text` with the redacted values of the current request replaced by
+    /// their placeholders again, before it is logged or sent
+    fn conceal(&self, text: &str) -> String {
+        self.redaction
+            .as_ref()
+            .map_or_else(|| text.to_string(), |redaction| redaction.conceal(text))
+    }
+
+    /// Name of the cache exclusion rule matching `request`, if any
+    ///
+    /// Rules are matched against the source text as typed, not redacted.
+    fn cache_rule(&self, request: &TranslationRequest) -> Option<String> {
+        CacheRules::new(&self.config.cache_exclusions)
+            .matching(&self.reveal(&request.source_text), &request.target_language)
+            .map(str::to_string)
+    }
+
+    /// Shows the end of the translation held back in case it was the start
+    /// of a placeholder
+    fn finish_redacted_stream(&mut self) {
+        if let Some(redaction) = &mut self.redaction {
+            let rest = redaction.finish_stream();
+            if !rest.is_empty() {
+                if let Some(speech) = &mut self.live_speech {
+                    speech.push(&rest);
+                }
+                self.display.update_translation(rest);
+            }
+        }
+    }
+
+    /// Request for the text and options currently set in the sidebar
+    fn sidebar_request(&self) -> TranslationRequest {
+        TranslationRequest {
+            source_text: self.sidebar.get_source_text(),
+            target_language: self.sidebar.get_target_language(),
+            enable_keyword_analysis: self.config.enable_keyword_analysis,
+            thinking: ThinkingMode::resolve(
+                self.sidebar.thinking_override(),
+                self.config.chat_thinking,
+                &self.config.base_url(),
+            ),
+            code_language: self.sidebar.code_mode(),
+            list_mode: self.sidebar.list_mode(),
+            pivot_language: self
+                .config
+                .pivot_enabled
+                .then(|| self.config.pivot_language.clone()),
+            context: PromptContext {
+                terms: self
+                    .glossary
+                    .matcher(&self.sidebar.get_target_language())
+                    .prompt_terms(&self.sidebar.get_source_text()),
+                ..self.config.prompt_context_for(
+                    &self.sidebar.get_target_language(),
+                    self.sidebar.prompt_context(),
+                )
+            },
+            show_alternatives: self.config.show_alternatives,
+            max_tokens: self.config.max_tokens,
+            temperature: None,
+            fast_path: false,
+        }
+    }
+
+    /// Shows a translation from the history as if it had just been made
+    fn load_history_entry(&mut self, entry: HistoryEntry) {
+        if self.is_translating {
+            return;
+        }
+        self.stop_audio();
+        self.cancel_source_tts();
+        self.cancel_translation_tts();
+        self.cancel_explanation();
+        self.cancel_confidence_check();
+
+        self.leave_history_entry();
+
+        self.sidebar.set_source_text(entry.source_text.clone());
+        self.sidebar
+            .set_target_language(entry.target_language.clone());
+        self.set_sidebar_request();
+
+        self.display.clear_translation();
+        self.display.set_input(entry.source_text.clone());
+        self.display.set_target_language(&entry.target_language);
+        self.display.update_translation(entry.translation.clone());
+        let view = lock_mutex!(self.view_states)
+            .get(&entry)
+            .unwrap_or_default();
+        self.display.restore_view(view.clone());
+        self.viewed_entry = Some((entry, view));
+    }
+
+    /// Runs a translation request through the streaming pipeline
+    ///
+    /// With `partial`, the request continues a translation that stopped at
+    /// the output limit and the new text is appended to it.
+    fn run_translation(
+        &mut self,
+        api_key: String,
+        request: TranslationRequest,
+        partial: Option<String>,
+    ) {
         tracing::info!("Starting new translation");
+        self.leave_history_entry();
 
         // Stop all audio activities when starting new translation
         tracing::info!("Stopping audio playback...");
@@ -142,192 +1061,1517 @@ impl TranslateApp {
 
         tracing::info!("All audio activities stopped for new translation");
 
-        // Reset cancel flag
-        *lock_mutex!(self.cancel_requested) = false;
-
-        let translator = Arc::new(Translator::new(api_key, self.cache.clone()));
-        self.translator = Some(translator.clone());
+        // The explanation belongs to the previous translation
+        self.cancel_explanation();
+        self.auto_play_translation = false;
+        if self.config.speak_while_translating
+            && !self.running_queue
+            && self.config.auto_speak_for(&request.target_language)
+        {
+            self.live_speech = Some(self.speaker.speak_live(&request.target_language));
+        }
 
-        let source_text = self.sidebar.get_source_text();
-        let target_language = self.sidebar.get_target_language();
+        let session = Arc::new(self.new_session(api_key, &request));
+        self.session = Some(session.clone());
 
         tracing::debug!(
-            source_length = source_text.len(),
-            target_language = %target_language,
+            source_length = request.source_text.len(),
+            target_language = %request.target_language,
+            thinking = request.thinking.as_str(),
             "Translation parameters"
         );
 
-        self.display.clear_translation();
-        self.is_translating = true;
-        self.display.set_translating(true);
-        self.display.set_input(source_text.clone());
-
-        let ui_tx = self.ui_tx.clone();
-        let handle = self.runtime_handle.clone();
-        let cancel_flag = self.cancel_requested.clone();
-
-        let enable_keyword_analysis = self.config.enable_keyword_analysis;
-        handle.spawn(async move {
-            let mut stream_rx =
-                translator.translate(source_text, target_language, enable_keyword_analysis);
-
-            loop {
-                tokio::select! {
-                    // Check cancel flag continuously
-                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(100)), if {
-                        *cancel_flag.lock().expect("Cancel flag mutex poisoned")
-                    } => {
-                        tracing::info!("Translation cancelled by user");
-                        let _ = ui_tx.send(UiMessage::TranslationCancelled);
-                        break;
-                    }
-                    // Receive stream data
-                    result = stream_rx.recv() => {
-                        match result {
-                            Some(Ok(chunk)) => {
-                                if chunk.is_empty() {
-                                    let _ = ui_tx.send(UiMessage::TranslationComplete);
-                                    break;
-                                }
-                                let _ = ui_tx.send(UiMessage::UpdateTranslation(chunk));
-                            }
-                            Some(Err(e)) => {
-                                tracing::error!("Translation error: {}", e);
-                                let _ = ui_tx.send(UiMessage::Error(e.to_string()));
-                                break;
-                            }
-                            None => {
-                                // Stream closed
-                                tracing::info!("Translation stream ended");
-                                break;
-                            }
-                        }
-                    }
+        self.cancel_confidence_check();
+        self.current_request = Some(request.clone());
+        self.in_flight = Some(
+            InFlightRequest::new(request.clone(), self.model_for(&request))
+                .with_exclusion(self.cache_rule(&request)),
+        );
+        if partial.is_some() {
+            self.display.set_truncated(false);
+            self.display.set_translation_audio_path(None);
+        } else {
+            self.display.clear_translation();
+            self.display.set_input(self.reveal(&request.source_text));
+            self.display.set_target_language(&request.target_language);
+        }
+        // A fast path response arrives whole and is shown at once
+        self.display.set_instant(request.fast_path);
+        self.is_translating = true;
+        self.display.set_translating(true);
+        self.streaming_document = Some(
+            self.background
+                .unwrap_or_else(|| self.documents.active_id()),
+        );
+        // In narrow windows the result is what to look at next
+        if self.background.is_none() {
+            self.tab = Tab::Result;
+        }
+        self.status_bar.start_request();
+        self.announcer.started(Instant::now());
+        let source_text = self.reveal(&request.source_text);
+        let same_text = self.background.is_none() && source_text == self.sidebar.get_source_text();
+        let generation = same_text.then(|| self.sidebar.source_generation());
+        self.priced_translation = Some((
+            pricing::language_pair(&source_text, &request.target_language),
+            generation,
+        ));
+
+        let label = format!(
+            "→ {}: {}",
+            request.target_language,
+            tasks::label_text(&self.reveal(&request.source_text))
+        );
+        let task = self
+            .tasks
+            .register_cancellable(TaskKind::Translation, label, {
+                let session = session.clone();
+                move || session.cancel()
+            });
+        let events = {
+            let _guard = self.runtime_handle.enter();
+            session.translate(request, partial)
+        };
+        self.forward_events(events, task, |event| match event {
+            StreamEvent::Chunk(chunk) => Some(UiMessage::UpdateTranslation(chunk)),
+            StreamEvent::Reasoning(text) => Some(UiMessage::UpdateReasoning(text)),
+            StreamEvent::Alternatives(alternatives) => Some(UiMessage::Alternatives(alternatives)),
+            StreamEvent::List(list) => Some(UiMessage::ListTranslated(list)),
+            StreamEvent::Pivot(text) => Some(UiMessage::PivotText(text)),
+            StreamEvent::LegacyCache => Some(UiMessage::LegacyCache),
+            StreamEvent::Throughput(rate) => Some(UiMessage::Throughput(rate)),
+            StreamEvent::Retrying { attempt, .. } => Some(UiMessage::Retrying(attempt)),
+            StreamEvent::SlowStream { floor_cps } => Some(UiMessage::Warning {
+                text: format!(
+                    "Stream unusually slow (under {} chars/s). Consider cancelling and retrying.",
+                    floor_cps
+                ),
+                kind: WarningKind::SlowStream,
+            }),
+            StreamEvent::MalformedChunks { provider } => Some(UiMessage::Warning {
+                text: format!(
+                    "Parts of the response from {} couldn't be read and were left out. The translation may be incomplete.",
+                    provider
+                ),
+                kind: WarningKind::MalformedResponse,
+            }),
+            StreamEvent::Inconsistencies(inconsistencies) => Some(UiMessage::Warning {
+                text: format!(
+                    "Some terms were translated more than one way: {}",
+                    inconsistencies
+                        .iter()
+                        .map(Inconsistency::summary)
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                ),
+                kind: WarningKind::Terminology,
+            }),
+            StreamEvent::Completed => Some(UiMessage::TranslationComplete),
+            StreamEvent::Truncated => Some(UiMessage::TranslationTruncated),
+            StreamEvent::Cancelled => Some(UiMessage::TranslationCancelled),
+            StreamEvent::Failed(TranslationError::RepetitionDetected { repeated_chars }) => {
+                Some(UiMessage::TranslationLooped(repeated_chars))
+            }
+            StreamEvent::Failed(e) if e.is_offline() => Some(UiMessage::Offline(e.to_string())),
+            StreamEvent::Failed(e) if e.refusal_reason().is_some() => Some(
+                UiMessage::TranslationRefused(e.refusal_reason().unwrap_or_default().to_string()),
+            ),
+            StreamEvent::Failed(e) => Some(UiMessage::Error(e.to_string())),
+            StreamEvent::Metrics(_) => None,
+        });
+    }
+
+    /// Model `request` is sent to
+    fn model_for(&self, request: &TranslationRequest) -> &str {
+        match self.config.fast_path_model.as_str() {
+            fast_model if request.fast_path && !fast_model.is_empty() => fast_model,
+            _ => self.config.chat_model(),
+        }
+    }
+
+    /// Estimates the cost of translating a large source text, when the
+    /// regular model has prices
+    ///
+    /// Large texts never take the fast path, so the regular model's prices
+    /// apply. The estimate is made again only once the text or the target
+    /// language changed.
+    fn update_cost_estimate(&mut self) {
+        let target_language = self.sidebar.get_target_language();
+        let key = (self.sidebar.source_generation(), target_language);
+        if self.cost_estimated_for.as_ref() == Some(&key) {
+            return;
+        }
+        let source = self.sidebar.get_source_text();
+        let estimate = self
+            .config
+            .model_pricing
+            .get(self.config.chat_model())
+            .filter(|pricing| pricing.is_set())
+            .filter(|_| source.chars().count() >= pricing::MIN_ESTIMATED_CHARS)
+            .map(|pricing| {
+                let pair = pricing::language_pair(&source, &key.1);
+                Estimate::new(
+                    &source,
+                    pricing,
+                    self.output_ratios.ratio(&pair),
+                    self.config.output_ratio,
+                )
+            });
+        self.sidebar.set_cost_estimate(estimate);
+        self.cost_estimated_for = Some(key);
+    }
+
+    /// Learns the output ratio of the language pair from a completed
+    /// translation, and shows what it cost next to its estimate
+    fn learn_cost(&mut self, metrics: &RequestMetrics) {
+        let Some((pair, generation)) = self.priced_translation.take() else {
+            return;
+        };
+        if metrics.outcome != RequestOutcome::Completed {
+            return;
+        }
+        let Some(tokens) = metrics.tokens else {
+            return;
+        };
+        self.output_ratios.record(&pair, tokens);
+        let estimated_for = self
+            .cost_estimated_for
+            .as_ref()
+            .map(|(generation, _)| *generation);
+        if let Some(pricing) = self.config.model_pricing.get(&metrics.model)
+            && pricing.is_set()
+            && generation.is_some()
+            && generation == estimated_for
+        {
+            let actual = pricing.cost(tokens.prompt, tokens.completion);
+            tracing::info!(
+                pair,
+                actual,
+                currency = pricing.currency,
+                "Translation cost"
+            );
+            self.sidebar.set_actual_cost(actual);
+        }
+    }
+
+    /// Creates a session for the configured provider
+    ///
+    /// Unless the cache is shared, the session reads and writes the cache
+    /// of its provider, model and temperature.
+    fn new_session(&self, api_key: String, request: &TranslationRequest) -> TranslationSession {
+        let model = self.model_for(request);
+        let base_url = self.config.base_url();
+        let cache = if self.config.shared_cache {
+            self.cache.clone()
+        } else {
+            Arc::new(self.cache.scoped(Namespace::Profile(cache::fingerprint(
+                &base_url,
+                model,
+                request.temperature,
+            ))))
+        };
+        let cache = cache.with_provenance(Provenance::of(request, model));
+        let cache = match self.cache_rule(request) {
+            Some(rule) => {
+                tracing::info!(rule, "Translation excluded from the cache");
+                cache.without_writes()
+            }
+            None => cache,
+        };
+        let translator = Translator::new(api_key, Arc::new(cache))
+            .with_base_url(&base_url)
+            .with_model(model)
+            .with_streaming(!request.fast_path)
+            .with_max_tokens(request.max_tokens)
+            .with_temperature(request.temperature)
+            .with_repetition_limit(self.config.repetition_limit)
+            .with_unescape_content(self.config.unescape_content)
+            .with_reasoning(!self.config.discard_reasoning)
+            .with_timeout(Duration::from_secs(self.config.request_timeout_secs))
+            .with_retry(RetryPolicy {
+                max_attempts: self.config.request_attempts,
+                ..RetryPolicy::default()
+            })
+            .with_coalescer(self.coalescer.clone());
+        TranslationSession::new(
+            translator,
+            SessionOptions {
+                slow_stream_floor_cps: self.config.slow_stream_floor_cps,
+                slow_stream_grace: Duration::from_secs(self.config.slow_stream_grace_secs),
+            },
+        )
+    }
+
+    /// Passes the events of a session on to the UI
+    ///
+    /// Metrics are shown in the status bar and logged, those of translations
+    /// by the UI along with their warnings. Every other event is turned into
+    /// a message by `to_message`. The session's `task` stays
+    /// listed until its events end.
+    fn forward_events(
+        &self,
+        mut events: BoxStream<'static, StreamEvent>,
+        task: TaskGuard,
+        to_message: fn(StreamEvent) -> Option<UiMessage>,
+    ) {
+        let ui_tx = self.ui_channel.sender();
+        let logger = self.logger.clone();
+
+        self.runtime_handle.spawn(async move {
+            let _task = task;
+            while let Some(event) = events.next().await {
+                let msg = match event {
+                    StreamEvent::Metrics(metrics) => {
+                        if let Some(logger) = &logger
+                            && metrics.kind != RequestKind::Translation
+                        {
+                            logger.log_metrics(&metrics);
+                        }
+                        Some(UiMessage::TranslationMetrics(metrics))
+                    }
+                    event => to_message(event),
+                };
+                if let Some(msg) = msg {
+                    let _ = ui_tx.send(msg).await;
+                }
+            }
+        });
+    }
+
+    /// Tells the user about a problem with the current translation that
+    /// doesn't stop it, keeping it with the translation
+    fn warn(&mut self, kind: WarningKind, text: impl Into<String>) {
+        let text = text.into();
+        tracing::warn!(kind = ?kind, "{}", text);
+        self.toasts.warning(text.clone());
+        self.display.add_warning(Warning { kind, text });
+    }
+
+    /// Asks for the rest of a translation that stopped at the output limit
+    fn continue_translation(&mut self) {
+        let api_key = self.sidebar.get_api_key();
+        if self.is_translating || api_key.is_empty() {
+            return;
+        }
+
+        if let Some(request) = self.current_request.clone() {
+            let partial = self.conceal(self.display.translation().as_str());
+            self.run_translation(api_key, request, Some(partial));
+        }
+    }
+
+    /// Translates a response that got stuck repeating itself again, at a
+    /// lower temperature
+    fn retry_lower_temperature(&mut self) {
+        let api_key = self.sidebar.get_api_key();
+        if self.is_translating || api_key.is_empty() {
+            return;
+        }
+        if let Some(request) = self.current_request.clone() {
+            let request = request.with_lower_temperature();
+            tracing::info!(
+                temperature = request.temperature,
+                "Retrying the looping translation"
+            );
+            self.run_translation(api_key, request, None);
+        }
+    }
+
+    /// Retries a declined translation once with the translation-only
+    /// framing, returning whether a retry started
+    fn retry_refused(&mut self) -> bool {
+        let api_key = self.sidebar.get_api_key();
+        if !self.config.retry_refusals || api_key.is_empty() {
+            return false;
+        }
+        let Some(mut request) = self.current_request.clone() else {
+            return false;
+        };
+        if request.context.translation_only {
+            return false;
+        }
+
+        tracing::info!("Retrying the declined translation as translation only");
+        request.context.translation_only = true;
+        self.toasts
+            .info("The provider declined the text, retrying once for translation only");
+        self.run_translation(api_key, request, None);
+        true
+    }
+
+    /// Asks for an explanation of the current translation
+    fn start_explanation(&mut self) {
+        let api_key = self.sidebar.get_api_key();
+        if self.is_translating || self.is_explaining || api_key.is_empty() {
+            return;
+        }
+        let Some(request) = self.current_request.clone() else {
+            return;
+        };
+        let translation = self.conceal(self.display.translation().as_str());
+        if translation.is_empty() {
+            return;
+        }
+
+        tracing::info!("Starting explanation");
+        self.is_explaining = true;
+        self.display.start_explanation();
+
+        let session = Arc::new(self.new_session(api_key, &request));
+        self.explain_session = Some(session.clone());
+        let task = self.tasks.register_cancellable(
+            TaskKind::Explanation,
+            tasks::label_text(self.display.translation().as_str()),
+            {
+                let session = session.clone();
+                move || session.cancel()
+            },
+        );
+        let events = {
+            let _guard = self.runtime_handle.enter();
+            session.explain(
+                request.source_text,
+                translation,
+                request.target_language,
+                request.thinking,
+            )
+        };
+        self.forward_events(events, task, |event| match event {
+            StreamEvent::Chunk(chunk) => Some(UiMessage::UpdateExplanation(chunk)),
+            StreamEvent::Completed => Some(UiMessage::ExplanationComplete),
+            StreamEvent::Cancelled => Some(UiMessage::ExplanationCancelled),
+            StreamEvent::Failed(e) => Some(UiMessage::ExplanationFailed(e.to_string())),
+            _ => None,
+        });
+    }
+
+    /// Stops the running explanation, keeping the translation
+    fn cancel_explanation(&mut self) {
+        if self.is_explaining {
+            tracing::info!("Cancelling explanation");
+            if let Some(session) = &self.explain_session {
+                session.cancel();
+            }
+        }
+    }
+
+    /// Asks which parts of the current translation the model is unsure of,
+    /// to underline them
+    fn start_confidence_check(&mut self) {
+        let api_key = self.sidebar.get_api_key();
+        if api_key.is_empty() {
+            return;
+        }
+        let Some(request) = self.current_request.clone() else {
+            return;
+        };
+        let translation = self.conceal(self.display.translation().as_str());
+        if translation.trim().is_empty() {
+            return;
+        }
+
+        tracing::info!("Starting confidence check");
+        self.cancel_confidence_check();
+        self.display.start_confidence_check();
+        let session = Arc::new(self.new_session(api_key, &request));
+        self.confidence_session = Some(session.clone());
+        let task = self.tasks.register_cancellable(
+            TaskKind::Confidence,
+            tasks::label_text(self.display.translation().as_str()),
+            {
+                let session = session.clone();
+                move || session.cancel()
+            },
+        );
+        let pair = alignment::pair_hash(&request.source_text, &translation);
+        let mut events = {
+            let _guard = self.runtime_handle.enter();
+            session.check_confidence(
+                request.source_text,
+                translation,
+                request.target_language,
+                request.thinking,
+            )
+        };
+        let ui_tx = self.ui_channel.sender();
+        let logger = self.logger.clone();
+        self.runtime_handle.spawn(async move {
+            let _task = task;
+            let mut response = String::new();
+            let mut result = Err("the check was cancelled".to_string());
+            while let Some(event) = events.next().await {
+                match event {
+                    StreamEvent::Chunk(chunk) => response.push_str(&chunk),
+                    StreamEvent::Completed => result = Ok(std::mem::take(&mut response)),
+                    StreamEvent::Failed(e) => result = Err(e.to_string()),
+                    // Logged apart from the translation it checks
+                    StreamEvent::Metrics(metrics) => {
+                        if let Some(logger) = &logger {
+                            logger.log_metrics(&metrics);
+                        }
+                        let _ = ui_tx.send(UiMessage::TranslationMetrics(metrics)).await;
+                    }
+                    _ => {}
+                }
+            }
+            let _ = ui_tx
+                .send(UiMessage::ConfidenceChecked { pair, result })
+                .await;
+        });
+    }
+
+    /// Stops the running confidence check, its answer is no longer wanted
+    fn cancel_confidence_check(&mut self) {
+        if let Some(session) = self.confidence_session.take() {
+            tracing::info!("Cancelling confidence check");
+            session.cancel();
+        }
+    }
+
+    /// Looks up which source phrase became `word` of the current translation
+    ///
+    /// A known answer is shown at once. Otherwise the request waits for its
+    /// turn, replacing the one still running or waiting.
+    fn align_word(&mut self, word: String) {
+        let api_key = self.sidebar.get_api_key();
+        let Some(request) = &self.current_request else {
+            return;
+        };
+        if let Some(task) = self.alignment_task.take() {
+            task.abort();
+        }
+        let source = request.source_text.clone();
+        let translation = self.conceal(self.display.translation().as_str());
+        let pair = alignment::pair_hash(&source, &translation);
+        if let Some(answer) = self.aligner.cached(pair, &word) {
+            let answer = answer.to_string();
+            self.show_alignment(word, &answer);
+            return;
+        }
+        if api_key.is_empty() {
+            return;
+        }
+
+        tracing::info!("Aligning a word of the translation");
+        let session = self.new_session(api_key, request);
+        self.display
+            .set_alignment(Some(AlignmentView::Pending { word: word.clone() }));
+        let delay = self.aligner.next_slot(Instant::now());
+        let ui_tx = self.ui_channel.sender();
+        self.alignment_task = Some(self.runtime_handle.spawn(async move {
+            tokio::time::sleep(delay).await;
+            let result = session
+                .translator()
+                .align(&source, &translation, &word)
+                .await
+                .map_err(|e| e.to_string());
+            let _ = ui_tx.send(UiMessage::Aligned { pair, word, result }).await;
+        }));
+    }
+
+    /// Romanizes the Chinese or Japanese source text in the sidebar, when
+    /// the option is on
+    ///
+    /// Sources in other scripts, and sources already romanized, are left
+    /// alone. Sensitive values are redacted as for a translation.
+    fn start_romanization(&mut self) {
+        let source = self.sidebar.get_source_text();
+        let scheme = Scheme::for_text(&source).filter(|_| self.config.romanize_source);
+        let Some(scheme) = scheme else {
+            self.sidebar.clear_romanization();
+            return;
+        };
+        let api_key = self.sidebar.get_api_key();
+        if self.sidebar.has_romanization(&source) || api_key.is_empty() {
+            return;
+        }
+        if let Some(task) = self.romanization_task.take() {
+            task.abort();
+        }
+
+        tracing::info!(scheme = scheme.key(), "Romanizing the source text");
+        let redaction = self
+            .config
+            .redact_sensitive
+            .then(|| Redactor::new(&self.config.redaction_patterns).redact(&source));
+        let text = redaction
+            .as_ref()
+            .map_or_else(|| source.clone(), |redaction| redaction.text.clone());
+        let translator = self
+            .new_session(api_key, &self.sidebar_request())
+            .translator()
+            .clone();
+        self.sidebar
+            .set_romanization(source.clone(), scheme, RomanizationState::Pending);
+        let ui_tx = self.ui_channel.sender();
+        self.romanization_task = Some(self.runtime_handle.spawn(async move {
+            let result = translator
+                .romanize(&text, scheme)
+                .await
+                .map(|romanized| match &redaction {
+                    Some(redaction) => redaction.restore(&romanized),
+                    None => romanized,
+                })
+                .map_err(|e| e.to_string());
+            let _ = ui_tx
+                .send(UiMessage::Romanized {
+                    source,
+                    scheme,
+                    result,
+                })
+                .await;
+        }));
+    }
+
+    /// Highlights the phrase the model answered for `word` in the source
+    fn show_alignment(&mut self, word: String, answer: &str) {
+        let Some(request) = &self.current_request else {
+            return;
+        };
+        // Located in the source as it is shown
+        let source = &self.reveal(&request.source_text);
+        let view = match alignment::locate(source, &self.reveal(answer)) {
+            Some(found) => AlignmentView::Found {
+                word,
+                phrase: source[found.range.clone()].to_string(),
+                range: found.range,
+                exact: found.exact,
+            },
+            None => AlignmentView::Failed {
+                word,
+                reason: "the answer isn't in the source text".to_string(),
+            },
+        };
+        self.display.set_alignment(Some(view));
+    }
+
+    /// Looks up the latest release in the background
+    ///
+    /// Failures are only logged; the check is a convenience and the app
+    /// works the same without it.
+    fn check_for_updates(&mut self) {
+        if !self.config.check_for_updates || self.checking_for_updates {
+            return;
+        }
+        self.checking_for_updates = true;
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn(async move {
+            let result = version::check_for_update().await.map_err(|e| {
+                tracing::warn!("Update check failed: {}", e);
+                e.to_string()
+            });
+            let _ = ui_tx.send(UiMessage::UpdateChecked(result)).await;
+        });
+    }
+
+    /// Opens a file named on the command line in the window for its kind
+    fn open_file(&mut self, path: PathBuf) {
+        let is_document = path
+            .extension()
+            .and_then(|extension| Format::from_extension(&extension.to_string_lossy()))
+            .is_some();
+        if pdf::is_pdf(&path) {
+            self.open_pdf(path);
+        } else if is_document {
+            self.open_document(path);
+        } else {
+            self.toasts.warning(format!(
+                "Can't open {}: only PDF, JSON and YAML files are supported",
+                path.display()
+            ));
+        }
+    }
+
+    /// Opens the files of later launches and brings the window to the front
+    fn open_forwarded(&mut self, ctx: &egui::Context) {
+        let Some(forwarded) = &self.forwarded else {
+            return;
+        };
+        let launches: Vec<Vec<String>> = forwarded.try_iter().collect();
+        if launches.is_empty() {
+            return;
+        }
+        tracing::info!("Another launch handed over {} time(s)", launches.len());
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        for path in launches.into_iter().flatten() {
+            self.open_file(PathBuf::from(path));
+        }
+    }
+
+    /// Opens a JSON or YAML file in the document window
+    fn open_document(&mut self, path: PathBuf) {
+        let file_name = path.file_name().map_or_else(
+            || "Document".to_string(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        let format = path
+            .extension()
+            .and_then(|extension| Format::from_extension(&extension.to_string_lossy()));
+        let document = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                match format {
+                    Some(format) => StructuredDocument::parse_as(&text, format),
+                    None => StructuredDocument::parse(&text),
+                }
+                .ok_or_else(|| "no string values found".to_string())
+            });
+        match document {
+            Ok(document) => self.structured.open(file_name, document),
+            Err(e) => {
+                tracing::warn!("Failed to open {}: {}", path.display(), e);
+                self.toasts
+                    .error(format!("Could not open {}: {}", file_name, e));
+            }
+        }
+    }
+
+    /// Translates the selected values of the open JSON or YAML document
+    fn translate_values(&mut self, batch: ValueBatch) {
+        // The values go out as they are, without redaction
+        if self.config.redact_sensitive {
+            self.toasts.warning(
+                "Document values can't be redacted. Translate the document as text, or turn off redaction.",
+            );
+            return;
+        }
+        let request = self.sidebar_request();
+        let session = self.new_session(self.sidebar.get_api_key(), &request);
+        let stop = Arc::new(tokio::sync::Notify::new());
+        let task = self.tasks.register_cancellable(
+            TaskKind::Batch,
+            format!("{} values → {}", batch.len(), request.target_language),
+            {
+                let stop = stop.clone();
+                move || stop.notify_one()
+            },
+        );
+
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn(async move {
+            let mut rx = session.translator().translate_values(
+                &batch,
+                request.target_language,
+                request.thinking,
+                request.context,
+            );
+            let mut response = String::new();
+            let msg = loop {
+                let received = tokio::select! {
+                    received = rx.recv() => received,
+                    _ = stop.notified() => break UiMessage::ValuesFailed("Cancelled".to_string()),
+                };
+                match received {
+                    Some(Ok(chunk)) if chunk.is_empty() => {
+                        break UiMessage::ValuesTranslated(batch.results(&response));
+                    }
+                    Some(Ok(chunk)) => {
+                        response.push_str(&chunk);
+                        if chunk.contains('⟦') {
+                            task.set_progress(batch.progress(&response));
+                        }
+                    }
+                    Some(Err(e)) => break UiMessage::ValuesFailed(e.to_string()),
+                    None => {
+                        break UiMessage::ValuesFailed(
+                            "The response ended unexpectedly".to_string(),
+                        );
+                    }
+                }
+            };
+            let _ = ui_tx.send(msg).await;
+        });
+    }
+
+    /// Extracts the text of a PDF in the background for the preview
+    fn open_pdf(&mut self, path: PathBuf) {
+        let file_name = path.file_name().map_or_else(
+            || "PDF".to_string(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        let task = self.tasks.register(TaskKind::Extraction, file_name.clone());
+        self.pdf_preview.start_extraction(file_name);
+
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn_blocking(move || {
+            let _task = task;
+            let msg = match pdf::extract(&path) {
+                Ok(text) => UiMessage::PdfExtracted(text),
+                Err(e) => UiMessage::PdfFailed(e.to_string()),
+            };
+            let _ = ui_tx.blocking_send(msg);
+        });
+    }
+
+    /// Runs the completion hook, if enabled, for the translation of
+    /// `in_flight` just finished, in the background
+    ///
+    /// The outcome goes to the log; only failures are shown.
+    fn run_completion_hook(&mut self, in_flight: &InFlightRequest) {
+        if !self.config.completion_hook_enabled || self.config.completion_hook.trim().is_empty() {
+            return;
+        }
+        let hook = CompletionHook {
+            command: self.config.completion_hook.clone(),
+            timeout: Duration::from_secs(self.config.completion_hook_timeout_secs.max(1)),
+        };
+        let source = self.reveal(&in_flight.request.source_text);
+        let translation = self.display.translation().as_str().to_string();
+        let target_language = in_flight.request.target_language.clone();
+        let task = self.tasks.register(TaskKind::Hook, target_language.clone());
+
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn_blocking(move || {
+            let _task = task;
+            if let Err(e) = hook.run(&source, &translation, &target_language) {
+                tracing::warn!("Completion hook failed: {}", e);
+                let _ = ui_tx.blocking_send(UiMessage::HookFailed(e.to_string()));
+            }
+        });
+    }
+
+    /// Fetches the page at `url` in the background and shows its text in
+    /// the preview
+    fn open_url(&mut self, url: String) {
+        let host = webpage::host(&url).to_string();
+        let task = self.tasks.register(TaskKind::Extraction, host.clone());
+        self.pdf_preview.start_extraction(host);
+
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn(async move {
+            let _task = task;
+            let progress_tx = ui_tx.clone();
+            let fetched = webpage::fetch(&url, |received| {
+                // Dropped while the UI is behind, the next one follows
+                let _ = progress_tx.try_send(UiMessage::PageProgress(received));
+            })
+            .await;
+            let msg = match fetched {
+                Ok(article) => UiMessage::PageExtracted(article.source_text()),
+                Err(e) => UiMessage::PageFailed(e.to_string()),
+            };
+            let _ = ui_tx.send(msg).await;
+        });
+    }
+
+    /// Writes the whole history to `path` as a TMX translation memory.
+    fn export_tmx(&mut self, path: PathBuf, skip_unknown_source: bool) {
+        let Some(logger) = self.logger.clone() else {
+            return;
+        };
+        let file_name = path.file_name().map_or_else(
+            || "TMX".to_string(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        let task = self.tasks.register(TaskKind::Export, file_name);
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn_blocking(move || {
+            let _task = task;
+            // Entries still queued for the log belong in the export
+            logger.flush();
+            let exported = history::load(logger.path()).and_then(|entries| {
+                let export = tmx::export(&entries, skip_unknown_source);
+                std::fs::write(&path, &export.xml).map(|()| export)
+            });
+            let msg = match exported {
+                Ok(export) => UiMessage::TmxExported {
+                    path,
+                    units: export.units,
+                    skipped: export.skipped,
+                },
+                Err(e) => UiMessage::TmxExportFailed(e.to_string()),
+            };
+            let _ = ui_tx.blocking_send(msg);
+        });
+    }
+
+    /// Strips the texts from the translation log in the background
+    fn purge_log_content(&mut self) {
+        let Some(logger) = self.logger.clone() else {
+            self.toasts.error("The translation log is not available");
+            return;
+        };
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn_blocking(move || {
+            let result = logger.purge_content().map_err(|e| e.to_string());
+            let _ = ui_tx.blocking_send(UiMessage::LogPurged(result));
+        });
+    }
+
+    /// Cleans text pasted into a source text box before the box receives it
+    ///
+    /// Typed text is left alone, only paste events are rewritten.
+    fn clean_source_pastes(&mut self, ctx: &egui::Context) {
+        if !self.config.sanitize_source_text {
+            return;
+        }
+        let source_focused = ctx.memory(|mem| {
+            mem.focused().is_some_and(|id| {
+                id == egui::Id::new(sidebar::SOURCE_TEXT_ID)
+                    || id == egui::Id::new(display::SOURCE_EDIT_ID)
+            })
+        });
+        if !source_focused {
+            return;
+        }
+
+        let mut report = CleanReport::default();
+        ctx.input_mut(|input| {
+            for event in &mut input.events {
+                if let egui::Event::Paste(text) = event {
+                    let (cleaned, cleaned_report) = sanitize::clean_source_text(text);
+                    *text = cleaned;
+                    report.merge(cleaned_report);
+                }
+            }
+        });
+        self.notify_cleaned(report);
+    }
+
+    /// Tells the user what was removed from the source text
+    fn notify_cleaned(&mut self, report: CleanReport) {
+        if let Some(summary) = report.summary() {
+            tracing::info!("Cleaned source text: {}", summary);
+            self.toasts.info(summary);
+        }
+    }
+
+    /// Hints at pivoting when a direct translation mostly repeats the source
+    fn suggest_pivot(&mut self) {
+        let Some(request) = &self.current_request else {
+            return;
+        };
+        if self.config.pivot_enabled
+            || request.code_language.is_some()
+            || request.target_language == self.config.pivot_language
+        {
+            return;
+        }
+        let source = self.reveal(&request.source_text);
+        if looks_untranslated(&source, self.display.translation().as_str()) {
+            tracing::info!("Translation is unusually similar to its source");
+            self.toasts.info(format!(
+                "The translation is very close to the source text. Translating via {} (Settings → Pivot via) may work better.",
+                self.config.pivot_language
+            ));
+        }
+    }
+
+    /// Warns when the translation has characters no loaded font can display
+    fn check_glyph_coverage(&mut self, ctx: &egui::Context) {
+        let font_id = egui::FontId::proportional(self.theme.font_size);
+        let coverage = glyphs::font_coverage(ctx, &font_id, self.display.translation().as_str());
+        if coverage.missing > 0 {
+            tracing::warn!(
+                missing = coverage.missing,
+                checked = coverage.checked,
+                scripts = ?coverage.scripts,
+                "Translation has characters without a glyph"
+            );
+        }
+        self.display
+            .set_font_warning(coverage.is_poor().then(|| coverage.summary()));
+    }
+
+    /// Switches to another custom font, or back to the bundled fonts only
+    ///
+    /// If the new font can't be loaded, the previous one stays in use.
+    fn set_custom_font(&mut self, ctx: &egui::Context, path: Option<PathBuf>) {
+        let previous = std::mem::replace(&mut self.theme.custom_font, path.clone());
+        match self.theme.setup_fonts(ctx) {
+            Ok(()) => {
+                if let Some(name) = path.as_ref().and_then(|path| path.file_name()) {
+                    self.toasts
+                        .info(format!("Loaded font {}", name.to_string_lossy()));
+                }
+                self.config.custom_font_path = path.clone();
+                self.settings.custom_font_path = path;
+                // The next translation is checked against the new font set
+                self.display.set_font_warning(None);
+            }
+            Err(e) => {
+                self.toasts.error(e);
+                self.theme.custom_font = previous;
+                let _ = self.theme.setup_fonts(ctx);
+            }
+        }
+    }
+
+    /// Renders the source and translation as an image and puts it on the
+    /// clipboard, or saves it as a PNG file where that isn't possible
+    fn share_image(&mut self, ctx: &egui::Context) {
+        let shared = share::render_image(
+            self.display.input_text(),
+            self.display.translation().as_str(),
+            self.display.target_language(),
+            &self.theme.share_fonts(),
+            &self.theme.share_style(&ctx.style().visuals),
+        );
+        let shortened = if shared.shortened {
+            " The text was too long and has been shortened."
+        } else {
+            ""
+        };
+
+        let (width, height) = shared.image.dimensions();
+        let copied = arboard::Clipboard::new().and_then(|mut clipboard| {
+            clipboard.set_image(arboard::ImageData {
+                width: width as usize,
+                height: height as usize,
+                bytes: shared.image.as_raw().into(),
+            })
+        });
+        match copied {
+            Ok(()) => self
+                .toasts
+                .info(format!("Copied the translation as an image.{}", shortened)),
+            Err(e) => {
+                tracing::info!("Image not copied, saving it instead: {}", e);
+                let dir = dirs::download_dir()
+                    .or_else(dirs::home_dir)
+                    .unwrap_or_else(std::env::temp_dir);
+                let path = dir.join(format!(
+                    "translation-{}.png",
+                    chrono::Local::now().format("%Y%m%d-%H%M%S")
+                ));
+                let saved = share::encode_png(&shared.image).and_then(|bytes| {
+                    std::fs::write(&path, bytes)
+                        .map_err(|e| format!("Could not save {}: {}", path.display(), e))
+                });
+                match saved {
+                    Ok(()) => self.toasts.info(format!(
+                        "Images can't be copied here, saved {}.{}",
+                        path.display(),
+                        shortened
+                    )),
+                    Err(e) => self.toasts.error(e),
+                }
+            }
+        }
+    }
+
+    /// Counts a practice self-grade and refreshes the comprehension rate
+    fn record_practice_grade(&mut self, grade: Grade) {
+        let today = chrono::Local::now().date_naive();
+        self.practice_stats.record(grade, today);
+        self.display
+            .set_practice_summary(self.practice_stats.summary(today));
+    }
+
+    /// Makes the chosen alternative the translation and remembers the choice
+    fn promote_alternative(&mut self, index: usize) {
+        self.display.promote_alternative(index);
+        if let Some(session) = &self.session
+            && let Some(request) = &self.current_request
+        {
+            // Cached as they were received
+            let alternatives: Vec<Alternative> = self
+                .display
+                .alternatives()
+                .iter()
+                .map(|alternative| Alternative {
+                    text: self.conceal(&alternative.text),
+                    gloss: self.conceal(&alternative.gloss),
+                })
+                .collect();
+            session.translator().promote_alternative(
+                &request.source_text,
+                &request.target_language,
+                &request.context,
+                &alternatives,
+                index,
+            );
+        }
+    }
+
+    /// Translates a list item that could not be mapped back on its own
+    ///
+    /// The result replaces the item in the translation, the rest of the list
+    /// is kept.
+    fn retry_list_item(&mut self, segment: usize) {
+        let (Some(session), Some(request), Some(source)) = (
+            self.session.clone(),
+            self.current_request.clone(),
+            self.display.list_source(segment).map(str::to_string),
+        ) else {
+            return;
+        };
+        self.display.start_list_retry(segment);
+        let stop = Arc::new(tokio::sync::Notify::new());
+        let task = self.tasks.register_cancellable(
+            TaskKind::Batch,
+            format!("Item {}: {}", segment + 1, tasks::label_text(&source)),
+            {
+                let stop = stop.clone();
+                move || stop.notify_one()
+            },
+        );
+
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn(async move {
+            let _task = task;
+            let mut rx = session.translator().translate(
+                source,
+                request.target_language,
+                false,
+                request.thinking,
+                request.context,
+            );
+            let mut translation = String::new();
+            let msg = loop {
+                let received = tokio::select! {
+                    received = rx.recv() => received,
+                    _ = stop.notified() => {
+                        break UiMessage::ListItemFailed(segment, "Cancelled".to_string());
+                    }
+                };
+                match received {
+                    Some(Ok(chunk)) if chunk.is_empty() => {
+                        break UiMessage::ListItemTranslated(segment, translation);
+                    }
+                    Some(Ok(chunk)) => translation.push_str(&chunk),
+                    Some(Err(e)) => break UiMessage::ListItemFailed(segment, e.to_string()),
+                    None => {
+                        break UiMessage::ListItemFailed(
+                            segment,
+                            "The response ended unexpectedly".to_string(),
+                        );
+                    }
+                }
+            };
+            let _ = ui_tx.send(msg).await;
+        });
+    }
+
+    /// Replaces the configuration with the previously saved version
+    fn restore_config(&mut self, ctx: &egui::Context) {
+        let config = match AppConfig::restore_backup() {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("Failed to restore config: {}", e);
+                self.toasts
+                    .error(format!("Could not restore the previous config: {}", e));
+                return;
+            }
+        };
+
+        self.apply_config(ctx, config);
+        tracing::info!("Restored previous config");
+        self.toasts.info("Previous config restored");
+    }
+
+    /// Offers to import the settings file an older version kept in the
+    /// working directory
+    fn legacy_config_ui(&mut self, ctx: &egui::Context) {
+        let Some(legacy_path) = &self.legacy_config else {
+            return;
+        };
+        let mut import = None;
+        egui::Window::new("⚙Import Settings")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Found settings from an older version here: {}",
+                    legacy_path.display()
+                ));
+                ui.label("Import them? They replace the current settings.");
+                ui.horizontal(|ui| {
+                    if ui.button("Import").clicked() {
+                        import = Some(true);
+                    }
+                    if ui.button("Not now").clicked() {
+                        import = Some(false);
+                    }
+                });
+            });
+        match import {
+            Some(true) => {
+                self.legacy_config = None;
+                self.import_legacy_config(ctx);
+            }
+            Some(false) => {
+                tracing::info!("Settings of the older version not imported");
+                self.legacy_config = None;
+            }
+            None => {}
+        }
+    }
+
+    /// Replaces the settings with those of an older version
+    fn import_legacy_config(&mut self, ctx: &egui::Context) {
+        let config = match AppConfig::import_legacy() {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("Failed to import legacy config: {}", e);
+                self.toasts
+                    .error(format!("Could not import the settings: {}", e));
+                return;
+            }
+        };
+        if let Some(watcher) = &mut self.config_watcher {
+            // Written by the app, not an edit to apply
+            watcher.remember_file();
+        }
+
+        self.apply_config(ctx, config);
+        self.toasts.info("Settings of the older version imported");
+    }
+
+    /// Replaces the settings with the configuration file, e.g. after it was
+    /// edited by hand while the app was running
+    fn reload_config_from_file(&mut self, ctx: &egui::Context) {
+        let config = match AppConfig::load_file() {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!("Failed to reload config: {}", e);
+                self.toasts
+                    .error(format!("Could not reload the config file: {}", e));
+                return;
+            }
+        };
+
+        self.apply_config(ctx, config);
+        // The stored snapshot is outdated now
+        self.persisted = None;
+        tracing::info!("Reloaded config from file");
+        self.toasts.info("Config reloaded from file");
+    }
+
+    /// Applies `config` to every part of the UI and makes it the current config
+    fn apply_config(&mut self, ctx: &egui::Context, config: AppConfig) {
+        self.theme.font_size = config.font_size;
+        self.theme.dark = config.dark_theme;
+        self.theme.apply_style(ctx);
+        self.theme.set_visuals(ctx);
+
+        self.sidebar.set_api_key(config.api_key.clone());
+        self.sidebar.set_base_url(config.api_base_url.clone());
+        transport::set_connect_timeout(Duration::from_secs(config.connect_timeout_secs));
+        dialogs::set_recent_dirs(config.recent_dirs.clone());
+        self.sidebar
+            .set_target_language(config.target_language.clone());
+        self.sidebar
+            .set_recent_languages(config.recent_languages.clone());
+        self.sidebar
+            .set_prompt_hints(config.prompt_domain.clone(), config.prompt_audience.clone());
+        self.sidebar
+            .set_spellcheck(config.spellcheck_enabled, &config.spellcheck_language);
+        self.sidebar.set_layout(
+            config.sidebar_collapsed,
+            config.sidebar_width,
+            config.sidebar_auto_collapse,
+        );
+        self.sidebar.set_proficiencies(config.proficiencies());
+        self.sidebar.set_romanize_source(config.romanize_source);
+        self.display
+            .set_show_pronunciation(config.show_pronunciation_for(&config.target_language));
+        self.display.set_auto_font(
+            config.auto_font_source,
+            config.auto_font_translation,
+            config.script_font_scales.clone(),
+        );
+        self.display.set_smooth_typing(config.smooth_typing);
+        self.display.set_reasoning_open(!config.reasoning_collapsed);
+        self.settings.reload(SettingsConfig::from(&config));
+        if let Some(logger) = &self.logger {
+            logger.set_detail(config.log_detail);
+        }
+        self.tts_service.update_config(config.tts_config());
+        self.audio_player.set_volume(config.playback_volume());
+        self.display.set_playback_volume(
+            config.playback_volume(),
+            self.audio_player.volume_adjustable(),
+        );
+
+        self.config = config;
+        self.update_speech_redaction();
+        // Prices may have changed
+        self.cost_estimated_for = None;
+        if self.config.watch_config_file != self.config_watcher.is_some() {
+            self.set_config_watch(ctx, self.config.watch_config_file);
+        }
+    }
+
+    /// Starts or stops following the configuration file for changes made
+    /// outside the app
+    fn set_config_watch(&mut self, ctx: &egui::Context, enabled: bool) {
+        self.config_watcher = None;
+        // A second instance doesn't save, so it has nothing to follow
+        if !enabled || self.scratch_dir.is_some() {
+            return;
+        }
+        match ConfigWatcher::start(AppConfig::config_path(), ctx.clone()) {
+            Ok(watcher) => self.config_watcher = Some(watcher),
+            Err(e) => {
+                tracing::warn!("Failed to watch the config file: {}", e);
+                self.toasts
+                    .warning(format!("Could not watch the config file: {}", e));
+            }
+        }
+    }
+
+    /// Applies the configuration file once it was changed outside the app
+    fn apply_edited_config(&mut self, ctx: &egui::Context) {
+        let Some(edited) = self.config_watcher.as_mut().and_then(ConfigWatcher::poll) else {
+            return;
+        };
+        let config = match edited {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring the edited config file: {}", e);
+                self.toasts.error(format!(
+                    "The config file is invalid, the current settings are kept: {}",
+                    e
+                ));
+                return;
+            }
+        };
+        let changed = config_watch::changed_settings(&self.config, &config);
+        if changed.is_empty() {
+            return;
+        }
+
+        tracing::info!(?changed, "Applying the edited config file");
+        let font = config.custom_font_path.clone();
+        self.apply_config(ctx, config);
+        if font != self.theme.custom_font {
+            self.set_custom_font(ctx, font);
+        }
+        // The file holds these settings already, saving would only reformat it
+        self.persisted = Some(self.config.clone());
+        self.toasts.info(format!(
+            "Config file changed: {}",
+            config_watch::summary(&changed)
+        ));
+    }
+
+    /// Applies the profile of the selected target language, or the global
+    /// settings for a language without one
+    fn apply_language_profile(&mut self) {
+        let language = &self.config.target_language;
+        tracing::debug!(
+            language = %language,
+            has_profile = self.config.language_profile(language).is_some(),
+            "Applying language preferences"
+        );
+        self.display
+            .set_show_pronunciation(self.config.show_pronunciation_for(language));
+        self.tts_service.update_config(self.config.tts_config());
+    }
+
+    /// Starts the next queued translation, if "Run all" is active
+    fn run_next_queued(&mut self) {
+        if !self.running_queue || self.is_translating {
+            return;
+        }
+
+        let api_key = self.sidebar.get_api_key();
+        match self.offline_queue.front().cloned() {
+            // Queued requests are kept as typed and redacted again as they
+            // are sent, so their translation gets the values back
+            Some(request) if !api_key.is_empty() && self.config.redact_sensitive => {
+                let redaction =
+                    Redactor::new(&self.config.redaction_patterns).redact(&request.source_text);
+                self.send_redacted(api_key, request, redaction)
+            }
+            Some(request) if !api_key.is_empty() => {
+                self.redaction = None;
+                self.run_translation(api_key, request, None)
+            }
+            _ => self.running_queue = false,
+        }
+    }
+
+    /// Settles the queued translation that ended, moving on to the next one
+    /// if it finished and stopping the run if it failed
+    fn advance_queue(&mut self, finished: bool) {
+        if self.running_queue {
+            if self.offline_queue.settle_front(finished) {
+                self.run_next_queued();
+            } else {
+                self.running_queue = false;
+            }
+        }
+    }
+
+    /// Probes connectivity in the background while translations are queued
+    fn probe_connectivity_if_due(&mut self) {
+        const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+        if self.offline_queue.is_empty()
+            || self.probe_in_flight
+            || self
+                .last_probe
+                .is_some_and(|at| at.elapsed() < PROBE_INTERVAL)
+        {
+            return;
+        }
+
+        self.probe_in_flight = true;
+        self.last_probe = Some(Instant::now());
+        let ui_tx = self.ui_channel.sender();
+        let base_url = self.config.base_url();
+        self.runtime_handle.spawn(async move {
+            let online = client::probe_connectivity(&base_url).await;
+            let _ = ui_tx.send(UiMessage::ConnectivityChecked(online)).await;
+        });
+    }
+
+    /// Renders the "queued translations ready" banner
+    fn queue_banner_ui(&mut self, ctx: &egui::Context) {
+        if !self.queue_online || self.running_queue || self.offline_queue.is_empty() {
+            return;
+        }
+
+        egui::TopBottomPanel::top("offline_queue_banner").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let count = self.offline_queue.len();
+                ui.label(format!(
+                    "📥 {} queued translation{} ready to run",
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ));
+                if ui.button("▶ Run all").clicked() {
+                    self.running_queue = true;
+                    self.run_next_queued();
                 }
-            }
+                if ui.button("🗑 Discard").clicked() {
+                    let entries = self.offline_queue.take_all();
+                    self.soft_delete(
+                        Deletion::OfflineQueue(entries),
+                        "Queued translations discarded",
+                    );
+                }
+            });
         });
     }
 
     pub fn cancel_translation(&mut self) {
         if self.is_translating {
             tracing::info!("Cancelling translation");
-            *self
-                .cancel_requested
-                .lock()
-                .expect("Cancel flag mutex poisoned") = true;
+            if let Some(session) = &self.session {
+                session.cancel();
+            }
         }
     }
 
     /// Starts TTS conversion for source text
     pub fn start_source_tts(&mut self, text: String) {
-        self.start_tts(text, TtsType::Source);
+        self.start_tts(text, TtsTarget::Source);
     }
 
-    /// Starts TTS conversion
-    fn start_tts(&mut self, text: String, tts_type: TtsType) {
+    /// Starts getting the audio of `text`, from the cache or by converting it
+    ///
+    /// Everything after the click runs in the background, the result arrives
+    /// as a `UiMessage::Tts`.
+    fn start_tts(&mut self, text: String, target: TtsTarget) {
         if text.trim().is_empty() {
             tracing::warn!("Cannot start TTS for empty text");
             return;
         }
 
-        let (cancel_flag, tts_type_name) = match tts_type {
-            TtsType::Source => (&self.source_tts_cancel_requested, "Source"),
-            TtsType::Translation => (&self.translation_tts_cancel_requested, "Translation"),
+        let cancel_flag = match target {
+            TtsTarget::Source => &self.source_tts_cancel_requested,
+            TtsTarget::Translation => &self.translation_tts_cancel_requested,
         };
-
-        // Check if audio is already cached
-        if let Some(audio_path) = self.audio_cache.get(&text) {
-            tracing::info!("{} audio already cached: {:?}", tts_type_name, audio_path);
-            match tts_type {
-                TtsType::Source => {
-                    self.display
-                        .set_source_audio_path(Some(audio_path.display().to_string()));
-                }
-                TtsType::Translation => {
-                    self.display
-                        .set_translation_audio_path(Some(audio_path.display().to_string()));
-                }
-            }
-            return;
-        }
-
-        tracing::info!(
-            "Starting TTS conversion for {} (length: {})",
-            tts_type_name,
-            text.len()
-        );
-
-        // Reset cancel flag and get new audio file path
         *lock_mutex!(cancel_flag) = false;
-        let audio_path = self.audio_cache.get_new_audio_path(&text);
 
-        // Update UI state and send start message
-        match tts_type {
-            TtsType::Source => {
-                self.display.set_source_tts_converting(true);
-                let _ = self.ui_tx.send(UiMessage::SourceTtsStarted);
-            }
-            TtsType::Translation => {
-                self.display.set_translation_tts_converting(true);
-                let _ = self.ui_tx.send(UiMessage::TranslationTtsStarted);
-            }
+        // Converting until the task reports otherwise, so it can be cancelled
+        match target {
+            TtsTarget::Source => self.display.set_source_tts_converting(true),
+            TtsTarget::Translation => self.display.set_translation_tts_converting(true),
         }
 
-        // Start async conversion
-        let tts_service = self.tts_service.clone();
-        let audio_cache = self.audio_cache.clone();
-        let text_clone = text.clone();
-        let ui_tx = self.ui_tx.clone();
-        let cancel_flag = cancel_flag.clone();
-        let tts_type_clone = tts_type;
-
-        let handle = self.runtime_handle.clone();
-
-        handle.spawn(async move {
-            // Check if cancellation was requested before starting
-            if *lock_mutex!(cancel_flag) {
-                tracing::info!("{} TTS cancelled before start", tts_type_name);
-                return;
-            }
+        self.speaker
+            .speak(text, target, cancel_flag.clone(), self.ui_channel.sender());
+    }
 
-            let audio_path_str = audio_path.to_string_lossy().to_string();
-
-            // Perform conversion
-            let text_for_cache = text_clone.clone();
-            let audio_path_for_cache = audio_path.clone();
-            let ui_tx_clone = ui_tx.clone();
-            let cancel_flag_clone = cancel_flag.clone();
-            tts_service.convert_async(&text_clone, &audio_path_str, move |status| {
-                // Check if cancellation was requested
-                if *lock_mutex!(cancel_flag_clone) {
-                    tracing::info!("{} TTS cancelled", tts_type_name);
-                    return;
-                }
-
-                match status {
-                    crate::services::tts::TtsStatus::Completed(path) => {
-                        // Store in cache
-                        audio_cache.set(&text_for_cache, audio_path_for_cache);
-                        tracing::info!("{} TTS completed: {}", tts_type_name, path);
-                        let msg = match tts_type_clone {
-                            TtsType::Source => UiMessage::SourceTtsCompleted(path),
-                            TtsType::Translation => UiMessage::TranslationTtsCompleted(path),
-                        };
-                        let _ = ui_tx_clone.send(msg);
+    /// Shows the progress of a TTS conversion
+    fn on_tts_update(&mut self, target: TtsTarget, update: TtsUpdate) {
+        match update {
+            TtsUpdate::Started => tracing::info!("{:?} TTS started", target),
+            TtsUpdate::Cached(path) | TtsUpdate::Completed(path) => match target {
+                TtsTarget::Source => {
+                    self.display.set_source_tts_converting(false);
+                    self.display.set_source_audio_path(Some(path));
+                }
+                TtsTarget::Translation => {
+                    self.display.set_translation_tts_converting(false);
+                    self.display.set_translation_audio_path(Some(path.clone()));
+                    if std::mem::take(&mut self.auto_play_translation) {
+                        self.play_audio(path);
                     }
-                    crate::services::tts::TtsStatus::Failed(err) => {
-                        tracing::error!("{} TTS failed: {}", tts_type_name, err);
-                        let _ = ui_tx_clone.send(UiMessage::TtsFailed(err));
+                }
+            },
+            TtsUpdate::Cancelled => {
+                tracing::info!("{:?} TTS cancelled from the task list", target);
+                match target {
+                    TtsTarget::Source => self.display.set_source_tts_converting(false),
+                    TtsTarget::Translation => {
+                        self.display.set_translation_tts_converting(false);
+                        self.auto_play_translation = false;
                     }
-                    _ => {}
                 }
-            });
-        });
+            }
+            TtsUpdate::Failed(err) => match target {
+                TtsTarget::Source => {
+                    self.display.set_source_tts_converting(false);
+                    self.toasts.error_with_action(
+                        format!("Source speech failed: {}", err),
+                        "Retry",
+                        ToastAction::RetrySourceTts,
+                    );
+                }
+                TtsTarget::Translation => {
+                    self.display.set_translation_tts_converting(false);
+                    self.auto_play_translation = false;
+                    self.toasts.error_with_action(
+                        format!("Translation speech failed: {}", err),
+                        "Retry",
+                        ToastAction::RetryTranslationTts,
+                    );
+                }
+            },
+        }
     }
 
     /// Starts TTS conversion for translation text
     pub fn start_translation_tts(&mut self, text: String) {
-        self.start_tts(text, TtsType::Translation);
+        self.start_tts(text, TtsTarget::Translation);
+    }
+
+    /// Keeps the sensitive values out of speech too while redaction is on,
+    /// as the conversion runs at the provider
+    fn update_speech_redaction(&mut self) {
+        self.speaker.set_redactor(
+            self.config
+                .redact_sensitive
+                .then(|| Redactor::new(&self.config.redaction_patterns)),
+        );
+    }
+
+    /// Speaks the source text shown in the UI
+    fn speak_source(&mut self) {
+        // The editable layout speaks the live text rather than the last translated one
+        let source_text = if self.config.source_panel_layout == SourcePanelLayout::Editable {
+            self.sidebar.get_source_text()
+        } else {
+            self.display.input_text().to_string()
+        };
+        if !source_text.trim().is_empty() {
+            self.start_source_tts(source_text);
+        }
+    }
+
+    /// Speaks the current translation
+    fn speak_translation(&mut self) {
+        let translation_text = self.display.translation().as_str().to_owned();
+        if !translation_text.trim().is_empty() {
+            self.start_translation_tts(translation_text);
+        }
     }
 
     /// Plays audio file
@@ -335,9 +2579,10 @@ impl TranslateApp {
         tracing::info!("Playing audio: {}", audio_path);
 
         // Check if this audio is currently playing (Stop button clicked)
-        if matches!(self.audio_player.get_state(), crate::services::audio::PlaybackState::Playing(ref p) if p == &audio_path)
+        if matches!(self.audio_player.get_state(), crate::services::audio::PlaybackState::Playing(ref p) | crate::services::audio::PlaybackState::Paused(ref p) if p == &audio_path)
         {
             tracing::info!("Stopping audio playback: {}", audio_path);
+            self.live_speech = None;
             if let Err(e) = self.audio_player.stop() {
                 tracing::warn!("Failed to stop playback: {}", e);
             }
@@ -347,7 +2592,7 @@ impl TranslateApp {
         }
 
         // Stop any currently playing audio
-        if self.audio_player.is_playing()
+        if self.audio_player.get_state().is_active()
             && let Err(e) = self.audio_player.stop()
         {
             tracing::warn!("Failed to stop current playback: {}", e);
@@ -373,9 +2618,67 @@ impl TranslateApp {
         }
     }
 
+    /// Plays the next sentence of the translation being spoken as it
+    /// streams, once the previous one has finished
+    fn play_live_speech(&mut self, ctx: &egui::Context) {
+        let Some(speech) = &mut self.live_speech else {
+            return;
+        };
+        // A paused sentence holds back the rest
+        if self.audio_player.get_state().is_active() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        }
+        match speech.next_audio() {
+            Some(TtsStatus::Completed(path)) => self.play_audio(path),
+            Some(TtsStatus::Failed(err)) => {
+                tracing::warn!("Speaking a sentence failed: {}", err);
+                self.interrupt_live_speech();
+                self.toasts
+                    .warning(format!("Stopped speaking while translating: {}", err));
+            }
+            Some(TtsStatus::Idle | TtsStatus::Converting) => {}
+            None if speech.is_done() => {
+                tracing::info!("Finished speaking while translating");
+                self.live_speech = None;
+                return;
+            }
+            None => {}
+        }
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+
+    /// Stops queueing sentences of the translation being spoken, which
+    /// ended early; what was already converted is still played
+    fn interrupt_live_speech(&mut self) {
+        if let Some(speech) = &mut self.live_speech {
+            speech.interrupt();
+        }
+    }
+
+    /// Plays an arbitrary local audio file, e.g. the user's own recording
+    pub fn play_local_file(&mut self, path: String) {
+        tracing::info!("Playing local audio file: {}", path);
+
+        match self.audio_player.play_file(Path::new(&path)) {
+            Ok(()) => {
+                self.display
+                    .set_playback_state(crate::services::audio::PlaybackState::Playing(path));
+            }
+            Err(e) => {
+                tracing::error!("Failed to play local audio file: {}", e);
+                self.display.stop_compare_loop();
+                self.display
+                    .set_playback_state(crate::services::audio::PlaybackState::Idle);
+                self.toasts.error(format!("Cannot play audio: {}", e));
+            }
+        }
+    }
+
     /// Stops audio playback
     pub fn stop_audio(&mut self) {
-        if self.audio_player.is_playing() {
+        self.live_speech = None;
+        if self.audio_player.get_state().is_active() {
             tracing::info!("Stopping audio playback");
             if let Err(e) = self.audio_player.stop() {
                 tracing::warn!("Failed to stop playback: {}", e);
@@ -386,14 +2689,14 @@ impl TranslateApp {
     }
 
     /// Cancels TTS conversion
-    fn cancel_tts(&mut self, tts_type: TtsType) {
+    fn cancel_tts(&mut self, tts_type: TtsTarget) {
         let (is_converting, cancel_flag, tts_type_name) = match tts_type {
-            TtsType::Source => (
+            TtsTarget::Source => (
                 self.display.is_source_converting(),
                 &self.source_tts_cancel_requested,
                 "Source",
             ),
-            TtsType::Translation => (
+            TtsTarget::Translation => (
                 self.display.is_translation_converting(),
                 &self.translation_tts_cancel_requested,
                 "Translation",
@@ -405,10 +2708,10 @@ impl TranslateApp {
             *lock_mutex!(cancel_flag) = true;
             // Clear converting state immediately
             match tts_type {
-                TtsType::Source => {
+                TtsTarget::Source => {
                     self.display.set_source_tts_converting(false);
                 }
-                TtsType::Translation => {
+                TtsTarget::Translation => {
                     self.display.set_translation_tts_converting(false);
                 }
             }
@@ -417,75 +2720,634 @@ impl TranslateApp {
 
     /// Cancels source TTS conversion
     pub fn cancel_source_tts(&mut self) {
-        self.cancel_tts(TtsType::Source);
+        self.cancel_tts(TtsTarget::Source);
     }
 
     /// Cancels translation TTS conversion
     pub fn cancel_translation_tts(&mut self) {
-        self.cancel_tts(TtsType::Translation);
+        self.cancel_tts(TtsTarget::Translation);
     }
 
     /// Clears audio cache
     pub fn clear_audio_cache(&mut self) {
         tracing::info!("Clearing audio cache");
-        self.audio_cache.clear();
+        let tombstone = self.audio_cache.take_all();
         self.display.set_source_audio_path(None);
         self.display.set_translation_audio_path(None);
+        self.soft_delete(Deletion::AudioCache(tombstone), "Audio cache cleared");
     }
 
     /// Clears translation cache
     pub fn clear_translation_cache(&mut self) {
         tracing::info!("Clearing translation cache");
-        self.cache.clear();
+        match self.cache.clear_with_backup() {
+            Ok(backup_file) => self.soft_delete(
+                Deletion::TranslationCache(backup_file),
+                "Translation cache cleared",
+            ),
+            Err(e) => {
+                tracing::error!("Failed to back up translation cache: {}", e);
+                self.toasts
+                    .error(format!("Cache not cleared, backup failed: {}", e));
+            }
+        }
+    }
+
+    /// Writes a diagnostic bundle for bug reports and shows where it went
+    fn create_diagnostic_bundle(&mut self, strip_text: bool) {
+        let inputs = BundleInputs {
+            config: &self.config,
+            log_files: self
+                .logger
+                .as_ref()
+                .map(|logger| (logger.path().to_path_buf(), logger.metrics_path())),
+            trace_lines: self.trace_buffer.lines(),
+            translation_cache_entries: self.cache.len(),
+            audio_cache_files: self.audio_cache.len(),
+            offline_queue_len: self.offline_queue.len(),
+            strip_text,
+        };
+        match diagnostics::write_bundle(&paths::diagnostics_dir(), &inputs) {
+            Ok(path) => {
+                self.toasts
+                    .info(format!("Diagnostic bundle saved to {}", path.display()));
+                if !self.config.cache_exclusions.is_empty() {
+                    self.toasts.warning(
+                        "The bundle's config includes the Never Cache rules, check them before sharing it",
+                    );
+                }
+                self.refresh_storage_usage();
+            }
+            Err(e) => {
+                tracing::error!("Failed to write diagnostic bundle: {}", e);
+                self.toasts
+                    .error(format!("Failed to create diagnostic bundle: {}", e));
+            }
+        }
+    }
+
+    /// Compresses and deletes old diagnostics and logs in the background,
+    /// then measures what the app's files take
+    ///
+    /// Runs at startup and from "Clean now", which is `manual` and reports
+    /// what was done.
+    fn clean_storage(&self, manual: bool) {
+        let policy = self.config.retention_policy();
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn_blocking(move || {
+            let dirs = StorageDirs::default();
+            let report = retention::run(&dirs, &policy, SystemTime::now());
+            let _ = ui_tx.blocking_send(UiMessage::StorageCleaned { report, manual });
+            let _ = ui_tx.blocking_send(UiMessage::StorageUsage(retention::usage(&dirs)));
+        });
+    }
+
+    /// Measures what the app's files take in the background
+    fn refresh_storage_usage(&self) {
+        let ui_tx = self.ui_channel.sender();
+        self.runtime_handle.spawn_blocking(move || {
+            let usage = retention::usage(&StorageDirs::default());
+            let _ = ui_tx.blocking_send(UiMessage::StorageUsage(usage));
+        });
+    }
+
+    /// Hands removed data to the undo manager and offers an "Undo" toast
+    fn soft_delete(&mut self, deletion: Deletion, message: &str) {
+        let id = self.undo.push(deletion, Instant::now());
+        self.toasts
+            .info_with_action(message, "Undo", ToastAction::Undo(id), self.undo.window());
+    }
+
+    /// Restores soft-deleted data
+    fn undo_deletion(&mut self, id: UndoId) {
+        match self.undo.undo(id) {
+            Some(Deletion::TranslationCache(backup_file)) => {
+                if let Err(e) = self.cache.restore_backup(&backup_file) {
+                    tracing::error!("Failed to restore translation cache: {}", e);
+                    self.toasts
+                        .error(format!("Could not restore the translation cache: {}", e));
+                }
+            }
+            Some(Deletion::AudioCache(tombstone)) => self.audio_cache.restore(tombstone),
+            Some(Deletion::OfflineQueue(entries)) => self.offline_queue.restore(entries),
+            Some(Deletion::GlossaryTerm(term)) => {
+                if let Err(e) = self.glossary.add(term) {
+                    self.toasts
+                        .error(format!("Could not restore the glossary term: {}", e));
+                }
+            }
+            None => self.toasts.warning("Too late to undo"),
+        }
     }
 
     fn process_messages(&mut self, ctx: &egui::Context) {
+        // The log is written on its own thread, its failures surface here once
+        if let Some(logger) = &self.logger
+            && let Some(err) = logger.take_write_failure()
+        {
+            self.toasts
+                .warning(format!("The translation history can't be written: {}", err));
+        }
+
         // Collect all messages first to avoid borrowing issues
-        let messages: Vec<UiMessage> = {
-            let mut rx_opt = self.ui_rx.lock().unwrap();
-            if let Some(rx) = rx_opt.as_mut() {
-                let mut msgs = Vec::new();
-                while let Ok(msg) = rx.try_recv() {
-                    msgs.push(msg);
-                }
-                msgs
-            } else {
-                Vec::new()
-            }
-        };
+        let messages = self.ui_channel.drain();
 
         // Process collected messages
         for msg in messages {
+            // The stream goes to the tab it was started in
+            let background = self
+                .streaming_document
+                .filter(|id| *id != self.documents.active_id() && msg.belongs_to_translation());
+            // Busy until the translation ends, warnings arrive while it runs
+            if msg.ends_translation() {
+                self.is_translating = false;
+                self.streaming_document = None;
+            }
+            if let Some(id) = background {
+                if !self.swap_document_state(id) {
+                    // The tab was closed, cancelling its translation
+                    if msg.ends_translation() {
+                        self.in_flight = None;
+                        self.running_queue = false;
+                    }
+                    continue;
+                }
+                self.background = Some(id);
+            }
+            self.announcer.message(
+                &msg,
+                || self.display.translation().as_str().chars().count(),
+                Instant::now(),
+            );
             match msg {
                 UiMessage::UpdateTranslation(chunk) => {
+                    let chunk = match &mut self.redaction {
+                        Some(redaction) => redaction.restore_chunk(&chunk),
+                        None => chunk,
+                    };
+                    if let Some(speech) = &mut self.live_speech {
+                        speech.push(&chunk);
+                    }
                     self.display.update_translation(chunk);
                     ctx.request_repaint();
                 }
+                UiMessage::UpdateReasoning(text) => {
+                    self.display.append_reasoning(&text);
+                    ctx.request_repaint();
+                }
+                UiMessage::Alternatives(alternatives) => {
+                    let alternatives = alternatives
+                        .into_iter()
+                        .map(|alternative| Alternative {
+                            text: self.reveal(&alternative.text),
+                            gloss: self.reveal(&alternative.gloss),
+                        })
+                        .collect();
+                    self.display.set_alternatives(alternatives);
+                    ctx.request_repaint();
+                }
+                UiMessage::ListTranslated(list) => {
+                    self.display.set_list(list);
+                    ctx.request_repaint();
+                }
+                UiMessage::LegacyCache => {
+                    self.display.set_legacy_cache(true);
+                }
+                UiMessage::PivotText(text) => {
+                    if let Some(language) = self
+                        .current_request
+                        .as_ref()
+                        .and_then(|request| request.pivot_language.clone())
+                    {
+                        self.display.set_pivot(language, self.reveal(&text));
+                        ctx.request_repaint();
+                    }
+                }
+                UiMessage::ListItemTranslated(segment, translation) => {
+                    self.display.set_list_item(segment, translation);
+                    ctx.request_repaint();
+                }
+                UiMessage::ListItemFailed(segment, err) => {
+                    tracing::warn!("Retrying list item failed: {}", err);
+                    self.display.list_retry_failed(segment);
+                    self.toasts
+                        .warning(format!("Could not translate the item: {}", err));
+                    ctx.request_repaint();
+                }
                 UiMessage::Error(err) => {
                     tracing::error!("UI received translation error: {}", err);
-                    self.is_translating = false;
+                    self.finish_redacted_stream();
+                    self.interrupt_live_speech();
+                    self.in_flight = None;
+                    self.display.set_translating(false);
+                    if self.running_queue {
+                        self.toasts
+                            .error(format!("Stopped running the queued translations: {}", err));
+                    }
+                    self.display.set_error(err);
+                    self.advance_queue(false);
+                    ctx.request_repaint();
+                }
+                UiMessage::TranslationRefused(reason) => {
+                    tracing::warn!(reason = %reason, "Provider declined the translation");
+                    self.interrupt_live_speech();
+                    self.in_flight = None;
+                    self.display.set_translating(false);
+                    self.display.set_refused(true);
+                    if !self.retry_refused() {
+                        self.advance_queue(true);
+                    }
+                    ctx.request_repaint();
+                }
+                UiMessage::Offline(err) => {
+                    tracing::warn!("Translation failed while offline: {}", err);
+                    self.in_flight = None;
                     self.display.set_translating(false);
                     self.display.set_error(err);
+                    self.queue_online = false;
+                    if self.running_queue {
+                        // The request is still at the front of the queue
+                        self.running_queue = false;
+                    } else {
+                        self.offline_request = self.current_request.clone().map(|mut request| {
+                            request.source_text = self.reveal(&request.source_text);
+                            request
+                        });
+                        self.toasts.error_with_action(
+                            "You appear to be offline",
+                            "Queue for later",
+                            ToastAction::QueueForLater,
+                        );
+                    }
+                    ctx.request_repaint();
+                }
+                UiMessage::ConnectionWarmed => {
+                    self.status_bar.set_connection_warm();
+                    ctx.request_repaint();
+                }
+                UiMessage::UpdateChecked(result) => {
+                    self.checking_for_updates = false;
+                    match result {
+                        Ok(Some(release)) => {
+                            self.toasts.info_with_action(
+                                format!("Version {} is available", release.tag),
+                                "What's new",
+                                ToastAction::OpenReleasePage,
+                                Duration::from_secs(15),
+                            );
+                            self.latest_release = Some(release);
+                        }
+                        Ok(None) => self.toasts.info(format!(
+                            "AI Translate {} is the latest version",
+                            version::VERSION
+                        )),
+                        // Already logged, a failed check is not worth a toast
+                        Err(_) => {}
+                    }
+                }
+                UiMessage::ConnectivityChecked(online) => {
+                    tracing::debug!("Connectivity probe: {}", online);
+                    self.probe_in_flight = false;
+                    self.queue_online = online;
                     ctx.request_repaint();
                 }
                 UiMessage::TranslationComplete => {
                     tracing::info!("Translation completed successfully");
-                    self.is_translating = false;
+                    self.finish_redacted_stream();
+                    if let Some(speech) = &mut self.live_speech {
+                        speech.finish();
+                    }
                     self.display.set_translating(false);
+                    // Code keeps its punctuation; the cache keeps the raw output
+                    if self.config.normalize_typography
+                        && let Some(in_flight) = &self.in_flight
+                        && in_flight.request.code_language.is_none()
+                    {
+                        self.display.normalize_typography(
+                            &in_flight.request.target_language,
+                            self.config.quote_style,
+                        );
+                    }
+                    let translation = self.conceal(self.display.translation().as_str());
+                    let missing = self
+                        .redaction
+                        .as_ref()
+                        .map_or(0, |redaction| redaction.missing(&translation).len());
+                    if missing > 0 {
+                        tracing::debug!(
+                            missing,
+                            "Redacted values were dropped from the translation"
+                        );
+                        self.warn(
+                            WarningKind::Redaction,
+                            "Some redacted values are missing from the translation, check it against the source",
+                        );
+                    }
 
-                    if let Some(logger) = &self.logger {
-                        logger.log(
-                            "Auto-detected",
-                            &self.config.target_language,
-                            &self.sidebar.get_source_text(),
-                            &self.display.translation,
+                    // Labeled with the request as started, not the current sidebar
+                    let in_flight = self.in_flight.take();
+                    let excluded_by = in_flight.as_ref().and_then(|f| f.excluded_by.clone());
+                    if let Some(logger) = &self.logger
+                        && let Some(in_flight) = &in_flight
+                        && excluded_by.is_none()
+                    {
+                        tracing::debug!(
+                            elapsed_ms = in_flight.started_at.elapsed().as_millis() as u64,
+                            target_language = %in_flight.request.target_language,
+                            "Logging the finished translation"
                         );
+                        let reasoning = Some(self.display.reasoning()).filter(|r| !r.is_empty());
+                        in_flight.log_completion(logger, &translation, reasoning);
+                    }
+                    // Translations kept out of the cache stay out of the hook too
+                    if let Some(in_flight) = &in_flight
+                        && excluded_by.is_none()
+                    {
+                        self.run_completion_hook(in_flight);
+                    }
+                    self.status_bar.set_uncached(excluded_by);
+                    self.check_glyph_coverage(ctx);
+                    self.suggest_pivot();
+                    // Nothing is started for a tab that isn't shown
+                    if !self.running_queue
+                        && self.background.is_none()
+                        && let Some(in_flight) = &in_flight
+                        && in_flight.request.code_language.is_none()
+                        && self
+                            .config
+                            .check_confidence_for(&in_flight.request.target_language)
+                    {
+                        self.start_confidence_check();
                     }
+                    // Already being spoken sentence by sentence
+                    if !self.running_queue
+                        && self.background.is_none()
+                        && self.live_speech.is_none()
+                        && let Some(in_flight) = &in_flight
+                        && self
+                            .config
+                            .auto_speak_for(&in_flight.request.target_language)
+                    {
+                        self.auto_play_translation = true;
+                        self.speak_translation();
+                    }
+                    self.advance_queue(true);
+                }
+                UiMessage::TranslationTruncated => {
+                    tracing::warn!("Translation stopped at the output limit");
+                    self.finish_redacted_stream();
+                    self.interrupt_live_speech();
+                    self.in_flight = None;
+                    self.display.set_translating(false);
+                    self.display.set_truncated(true);
+                    self.advance_queue(true);
+                    ctx.request_repaint();
+                }
+                UiMessage::TranslationLooped(repeated_chars) => {
+                    tracing::warn!("Translation stopped for repeating itself");
+                    self.interrupt_live_speech();
+                    self.in_flight = None;
+                    self.display.set_translating(false);
+                    self.display.stop_repetition(repeated_chars);
+                    self.advance_queue(true);
+                    ctx.request_repaint();
                 }
                 UiMessage::TranslationCancelled => {
                     tracing::info!("Translation cancelled");
-                    self.is_translating = false;
+                    self.finish_redacted_stream();
+                    if self.live_speech.is_some() {
+                        self.stop_audio();
+                    }
+                    self.in_flight = None;
                     self.display.set_translating(false);
+                    self.running_queue = false;
+                    ctx.request_repaint();
+                }
+                UiMessage::UpdateExplanation(chunk) => {
+                    self.display.update_explanation(chunk);
+                    ctx.request_repaint();
+                }
+                UiMessage::ExplanationComplete => {
+                    tracing::info!("Explanation completed successfully");
+                    self.is_explaining = false;
+                    self.display.set_explaining(false);
+                }
+                UiMessage::ExplanationFailed(err) => {
+                    self.is_explaining = false;
+                    self.display.set_explaining(false);
+                    self.display.set_explanation_error(err);
+                    ctx.request_repaint();
+                }
+                UiMessage::ExplanationCancelled => {
+                    self.is_explaining = false;
+                    self.display.set_explaining(false);
+                    ctx.request_repaint();
+                }
+                UiMessage::Romanized {
+                    source,
+                    scheme,
+                    result,
+                } => {
+                    self.romanization_task = None;
+                    let state = match result {
+                        Ok(romanized) => RomanizationState::Ready(romanized),
+                        Err(e) => {
+                            tracing::warn!("Romanization failed: {}", e);
+                            RomanizationState::Failed(e)
+                        }
+                    };
+                    self.sidebar.set_romanization(source, scheme, state);
+                }
+                UiMessage::HookFailed(e) => {
+                    self.toasts.error(format!("Completion hook failed: {}", e));
+                }
+                UiMessage::ConfidenceChecked { pair, result } => {
+                    self.confidence_session = None;
+                    // A check of an earlier translation is dropped
+                    let translation = self.conceal(self.display.translation().as_str());
+                    let current = self
+                        .current_request
+                        .as_ref()
+                        .map(|request| alignment::pair_hash(&request.source_text, &translation));
+                    if current != Some(pair) {
+                        continue;
+                    }
+                    match result {
+                        Ok(response) => {
+                            // Located in the translation as it is shown
+                            let shown = self.display.translation().as_str().to_string();
+                            let spans =
+                                translator::parse_confidence_check(&self.reveal(&response), &shown);
+                            tracing::info!(spans = spans.len(), "Confidence check completed");
+                            self.display.set_low_confidence(shown, spans);
+                        }
+                        Err(reason) => {
+                            tracing::warn!("Confidence check failed: {}", reason);
+                            self.display.confidence_check_failed();
+                        }
+                    }
+                    ctx.request_repaint();
+                }
+                UiMessage::Aligned { pair, word, result } => {
+                    self.alignment_task = None;
+                    // Answers for an earlier translation are only kept
+                    let translation = self.conceal(self.display.translation().as_str());
+                    let current = self
+                        .current_request
+                        .as_ref()
+                        .map(|request| alignment::pair_hash(&request.source_text, &translation));
+                    match result {
+                        Ok(answer) => {
+                            self.aligner.store(pair, word.clone(), answer.clone());
+                            if current == Some(pair) {
+                                self.show_alignment(word, &answer);
+                            }
+                        }
+                        Err(reason) => {
+                            tracing::warn!("Alignment failed: {}", reason);
+                            if current == Some(pair) {
+                                self.display
+                                    .set_alignment(Some(AlignmentView::Failed { word, reason }));
+                            }
+                        }
+                    }
+                    ctx.request_repaint();
+                }
+                UiMessage::PdfExtracted(mut text) | UiMessage::PageExtracted(mut text) => {
+                    if self.config.sanitize_source_text {
+                        let (cleaned, report) = sanitize::clean_source_text(&text);
+                        text = cleaned;
+                        self.notify_cleaned(report);
+                    }
+                    self.pdf_preview.set_text(text);
+                    ctx.request_repaint();
+                }
+                UiMessage::TmxExported {
+                    path,
+                    units,
+                    skipped,
+                } => {
+                    tracing::info!(units, skipped, "Exported the history to {:?}", path);
+                    let mut message = format!("Exported {} entries to {}", units, path.display());
+                    if skipped > 0 {
+                        message.push_str(&format!(", skipped {} of unknown language", skipped));
+                    }
+                    self.toasts.info(message);
+                    ctx.request_repaint();
+                }
+                UiMessage::LogPurged(Ok(purged)) => {
+                    tracing::info!(purged, "Purged the texts from the translation log");
+                    self.toasts.info(if purged == 0 {
+                        "The translation log holds no texts".to_string()
+                    } else {
+                        format!(
+                            "Removed the texts of {} entr{} from the translation log",
+                            purged,
+                            if purged == 1 { "y" } else { "ies" }
+                        )
+                    });
+                    self.forget_deleted_views();
+                    ctx.request_repaint();
+                }
+                UiMessage::LogPurged(Err(err)) => {
+                    tracing::warn!("Purging the translation log failed: {}", err);
+                    self.toasts
+                        .error(format!("Could not purge the translation log: {}", err));
+                    ctx.request_repaint();
+                }
+                UiMessage::TmxExportFailed(err) => {
+                    tracing::warn!("TMX export failed: {}", err);
+                    self.toasts
+                        .error(format!("Could not export the history: {}", err));
+                    ctx.request_repaint();
+                }
+                UiMessage::PdfFailed(err) => {
+                    tracing::warn!("PDF extraction failed: {}", err);
+                    self.pdf_preview.close();
+                    self.toasts.error(err);
+                    ctx.request_repaint();
+                }
+                UiMessage::PageProgress(received) => {
+                    self.pdf_preview
+                        .set_progress(format!("Downloading… {} KB", received / 1024));
+                    ctx.request_repaint();
+                }
+                UiMessage::PageFailed(err) => {
+                    tracing::warn!("Fetching the linked page failed: {}", err);
+                    self.pdf_preview.close();
+                    self.toasts.error(err);
+                    ctx.request_repaint();
+                }
+                UiMessage::ValuesTranslated(results) => {
+                    let flagged = results.iter().filter(|(_, result)| result.is_err()).count();
+                    self.structured.set_results(results);
+                    if flagged > 0 {
+                        self.toasts.warning(format!(
+                            "{} value{} kept the original, see the flagged rows",
+                            flagged,
+                            if flagged == 1 { "" } else { "s" }
+                        ));
+                    }
+                    ctx.request_repaint();
+                }
+                UiMessage::StorageCleaned { report, manual } => {
+                    if manual {
+                        let message = if report == Report::default() {
+                            "Nothing to clean up".to_string()
+                        } else {
+                            format!(
+                                "Deleted {} and compressed {} old file{}, freeing {}",
+                                report.deleted,
+                                report.compressed,
+                                if report.deleted + report.compressed == 1 {
+                                    ""
+                                } else {
+                                    "s"
+                                },
+                                retention::format_size(report.freed_bytes)
+                            )
+                        };
+                        self.toasts.info(message);
+                    }
+                }
+                UiMessage::StorageUsage(usage) => {
+                    self.storage_usage = usage;
+                    ctx.request_repaint();
+                }
+                UiMessage::ValuesFailed(err) => {
+                    tracing::warn!("Value translation failed: {}", err);
+                    self.structured.translation_failed();
+                    self.toasts.error(err);
+                    ctx.request_repaint();
+                }
+                UiMessage::Throughput(chars_per_sec) => {
+                    self.status_bar.set_throughput(chars_per_sec);
+                }
+                UiMessage::Retrying(attempt) => {
+                    self.status_bar
+                        .set_retrying(attempt, self.config.request_attempts);
+                }
+                UiMessage::TranslationMetrics(mut metrics) => {
+                    if metrics.kind == RequestKind::Translation {
+                        metrics.warnings = self.display.warnings().to_vec();
+                        if let Some(logger) = &self.logger {
+                            logger.log_metrics(&metrics);
+                        }
+                        self.learn_cost(&metrics);
+                    }
+                    // Compare with and without pre-connect in the log
+                    if !self.translated_since_launch && metrics.kind == RequestKind::Translation {
+                        self.translated_since_launch = true;
+                        tracing::info!(
+                            first_content_ms = ?metrics.first_content_ms,
+                            preconnected = self.status_bar.connection_warm(),
+                            "First translation since launch"
+                        );
+                    }
+                    self.status_bar.set_metrics(metrics);
+                }
+                UiMessage::Warning { text, kind } => {
+                    self.warn(kind, text);
                     ctx.request_repaint();
                 }
                 UiMessage::RequestSourceTts(text) => {
@@ -500,44 +3362,257 @@ impl TranslateApp {
                     tracing::info!("Stop playback requested");
                     self.stop_audio();
                 }
-                UiMessage::SourceTtsStarted => {
-                    tracing::info!("Source TTS started");
+                UiMessage::Tts(target, update) => {
+                    self.on_tts_update(target, update);
                     ctx.request_repaint();
                 }
-                UiMessage::TranslationTtsStarted => {
-                    tracing::info!("Translation TTS started");
+                UiMessage::PlaybackStateChanged(state) => {
+                    self.display.set_playback_state(state);
                     ctx.request_repaint();
                 }
-                UiMessage::SourceTtsCompleted(path) => {
-                    tracing::info!("Source TTS completed: {}", path);
-                    self.display.set_source_tts_converting(false);
-                    self.display.set_source_audio_path(Some(path));
-                    ctx.request_repaint();
+            }
+            if let Some(id) = self.background.take() {
+                self.swap_document_state(id);
+            }
+        }
+    }
+
+    /// Registers what buttons, shortcuts and the command palette can run
+    fn action_registry() -> ActionRegistry<Self> {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+
+        let mut registry = ActionRegistry::default();
+        registry.register(
+            Action::new("palette", "Command palette", |app: &mut Self, _| {
+                app.palette.toggle()
+            })
+            .with_shortcut(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::P,
+            )),
+        );
+        registry.register(
+            Action::new("translate", "Translate", |app: &mut Self, _| {
+                let api_key = app.sidebar.get_api_key();
+                app.start_translation(api_key);
+            })
+            .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::Enter))
+            .with_enabled(|app| {
+                if app.is_translating {
+                    Err("A translation is running")
+                } else if app.sidebar.get_api_key().is_empty() {
+                    Err("Set an API key first")
+                } else if app.sidebar.get_source_text().trim().is_empty() {
+                    Err("There is no text to translate")
+                } else {
+                    Ok(())
                 }
-                UiMessage::TranslationTtsCompleted(path) => {
-                    tracing::info!("Translation TTS completed: {}", path);
-                    self.display.set_translation_tts_converting(false);
-                    self.display.set_translation_audio_path(Some(path));
-                    ctx.request_repaint();
+            }),
+        );
+        registry.register(
+            Action::new(
+                "translate.cancel",
+                "Cancel translation",
+                |app: &mut Self, _| app.cancel_translation(),
+            )
+            .with_enabled(|app| match app.is_translating {
+                true => Ok(()),
+                false => Err("No translation is running"),
+            }),
+        );
+        registry.register(
+            Action::new("tab.new", "New tab", |app: &mut Self, _| app.new_document())
+                .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::T)),
+        );
+        registry.register(
+            Action::new("tab.close", "Close tab", |app: &mut Self, _| {
+                app.request_close_document(app.documents.active_id())
+            })
+            .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::W))
+            .with_enabled(|app| match app.documents.len() {
+                1 => Err("The last tab stays open"),
+                _ => Ok(()),
+            }),
+        );
+        registry.register(
+            Action::new("tab.next", "Next tab", |app: &mut Self, _| {
+                app.select_document(app.documents.next_id())
+            })
+            .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::Tab))
+            .with_enabled(|app| match app.documents.len() {
+                1 => Err("There is no other tab"),
+                _ => Ok(()),
+            }),
+        );
+        registry.register(
+            Action::new(
+                "translate.explain",
+                "Explain translation",
+                |app: &mut Self, _| app.start_explanation(),
+            )
+            .with_enabled(|app| {
+                if app.is_translating || app.display.translation().is_empty() {
+                    Err("There is no finished translation")
+                } else if app.is_explaining {
+                    Err("An explanation is running")
+                } else {
+                    Ok(())
                 }
-                UiMessage::TtsFailed(err) => {
-                    tracing::error!("TTS failed: {}", err);
-                    self.display.set_source_tts_converting(false);
-                    self.display.set_translation_tts_converting(false);
-                    ctx.request_repaint();
+            }),
+        );
+        registry.register(
+            Action::new(
+                "copy.translation",
+                "Copy translation",
+                |app: &mut Self, ctx| ctx.copy_text(app.display.translation().as_str().to_owned()),
+            )
+            .with_enabled(|app| match app.display.translation().is_empty() {
+                true => Err("There is no translation"),
+                false => Ok(()),
+            }),
+        );
+        registry.register(Action::new(
+            "speak.source",
+            "Speak source text",
+            |app: &mut Self, _| app.speak_source(),
+        ));
+        registry.register(
+            Action::new(
+                "speak.translation",
+                "Speak translation",
+                |app: &mut Self, _| app.speak_translation(),
+            )
+            .with_enabled(|app| {
+                if app.is_translating || app.display.translation().is_empty() {
+                    Err("There is no finished translation")
+                } else {
+                    Ok(())
+                }
+            }),
+        );
+        registry.register(
+            Action::new("audio.stop", "Stop audio", |app: &mut Self, _| {
+                app.stop_audio()
+            })
+            .with_enabled(|app| match app.audio_player.get_state().is_active() {
+                true => Ok(()),
+                false => Err("Nothing is playing"),
+            }),
+        );
+        registry.register(Action::new(
+            "export.tmx",
+            "Export history as TMX…",
+            |app: &mut Self, _| {
+                if let Some(path) = Dialog::new(DialogCategory::Tmx)
+                    .add_filter("TMX", &["tmx"])
+                    .set_file_name("history.tmx")
+                    .save_file()
+                {
+                    app.export_tmx(path, false);
                 }
-                UiMessage::PlaybackStateChanged(state) => {
-                    self.display.set_playback_state(state);
-                    ctx.request_repaint();
+            },
+        ));
+        registry.register(Action::new(
+            "theme.toggle",
+            "Toggle dark theme",
+            |app: &mut Self, ctx| {
+                let mut config = app.config.clone();
+                config.dark_theme = !config.dark_theme;
+                app.apply_config(ctx, config);
+            },
+        ));
+        registry.register(
+            Action::new("view.settings", "Settings", |app: &mut Self, _| {
+                app.settings.toggle_panel();
+                app.refresh_storage_usage();
+            })
+            .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::Comma)),
+        );
+        registry.register(
+            Action::new("view.history", "History", |app: &mut Self, _| {
+                app.history.toggle()
+            })
+            .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::H)),
+        );
+        registry.register(Action::new(
+            "view.glossary",
+            "Glossary",
+            |app: &mut Self, _| {
+                app.glossary_window
+                    .toggle(&app.sidebar.get_target_language())
+            },
+        ));
+        registry.register(Action::new("view.about", "About", |app: &mut Self, _| {
+            app.about.toggle()
+        }));
+        registry.register(Action::new(
+            "config.reload",
+            "Reload settings from file",
+            |app: &mut Self, ctx| app.reload_config_from_file(ctx),
+        ));
+        registry.register(Action::new(
+            "cache.clear_translations",
+            "Clear translation cache",
+            |app: &mut Self, _| app.clear_translation_cache(),
+        ));
+        registry.register(Action::new(
+            "cache.clear_audio",
+            "Clear audio cache",
+            |app: &mut Self, _| app.clear_audio_cache(),
+        ));
+        registry.register(
+            Action::new("updates.check", "Check for updates", |app: &mut Self, _| {
+                app.check_for_updates()
+            })
+            .with_enabled(|app| {
+                if !app.config.check_for_updates {
+                    Err("Update checks are turned off in the settings")
+                } else if app.checking_for_updates {
+                    Err("Already checking")
+                } else {
+                    Ok(())
                 }
-            }
+            }),
+        );
+        registry
+    }
+
+    /// Runs the registered action `id`, telling the user if it can't run now
+    fn run_action(&mut self, ctx: &egui::Context, id: &str) {
+        let actions = self.actions.clone();
+        if let Err(reason) = actions.run(id, self, ctx) {
+            let label = actions.get(id).map_or(id, |action| action.label);
+            self.toasts.info(format!("{}: {}", label, reason));
+        }
+    }
+
+    /// Button running the registered action `id`, disabled with the reason
+    /// while the action can't run
+    fn action_button(&self, ui: &mut egui::Ui, id: &str, text: &str) -> bool {
+        let enabled = self.actions.check(id, self);
+        let mut response = ui.add_enabled(enabled.is_ok(), egui::Button::new(text));
+        if let Some(shortcut) = self.actions.get(id).and_then(|action| action.shortcut) {
+            response = response.on_hover_text(ui.ctx().format_shortcut(&shortcut));
         }
+        if let Err(reason) = enabled {
+            response = response.on_disabled_hover_text(reason);
+        }
+        response.clicked()
     }
 }
 
 impl eframe::App for TranslateApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if !self.fonts_installed {
+            self.install_fonts(ctx);
+            return;
+        }
         self.process_messages(ctx);
+        if let Some(recent_dirs) = dialogs::take_changed() {
+            self.config.recent_dirs = recent_dirs;
+        }
+        self.apply_edited_config(ctx);
+        self.open_forwarded(ctx);
         self.theme.set_visuals(ctx);
 
         // Check if audio playback has finished
@@ -546,42 +3621,279 @@ impl eframe::App for TranslateApp {
             self.display
                 .set_playback_state(crate::services::audio::PlaybackState::Idle);
         }
+        // Follow the position only while it moves
+        if self.audio_player.is_playing()
+            && let Some(progress) = self.audio_player.progress()
+        {
+            self.display.set_playback_progress(Some(progress));
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+        self.play_live_speech(ctx);
+
+        // Before any text box sees the keys of this frame
+        let actions = self.actions.clone();
+        if let Some(id) = ctx.input_mut(|i| actions.pressed(i)) {
+            self.run_action(ctx, id);
+        }
+
+        let layout =
+            LayoutPlan::for_size(ctx.content_rect().size()).pinned(self.config.pinned_layout);
+        if self.layout != Some(layout) {
+            tracing::debug!(?layout, "Window layout changed");
+            ctx.style_mut(|style| style.spacing = layout.spacing());
+            self.layout = Some(layout);
+        }
+        let tabs = layout.arrangement == Arrangement::Tabs;
 
+        let mut clicked_action = None;
         egui::TopBottomPanel::top("top_bar")
-            .exact_height(40.0)
+            .exact_height(layout.top_bar_height())
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.button("⚙ Settings").clicked() {
-                            self.settings.toggle_panel();
+                        for (id, text) in [
+                            ("view.settings", "⚙ Settings"),
+                            ("view.history", "🕘 History"),
+                            ("view.glossary", "📖 Glossary"),
+                            ("view.about", "ℹ About"),
+                            ("palette", "🔎"),
+                        ] {
+                            if self.action_button(ui, id, text) {
+                                clicked_action = Some(id);
+                            }
+                        }
+                        if let Some(id) = tasks_ui(ui, &self.tasks) {
+                            self.tasks.cancel(id);
                         }
                     });
                 });
             });
+        if let Some(id) = clicked_action {
+            self.run_action(ctx, id);
+        }
+
+        self.probe_connectivity_if_due();
+        self.queue_banner_ui(ctx);
+        if layout.show_status_bar {
+            self.status_bar.ui(ctx, self.is_translating);
+        }
+        self.documents_ui(ctx);
+        self.close_confirmation_ui(ctx);
+        self.legacy_config_ui(ctx);
+        if tabs {
+            egui::TopBottomPanel::top("layout_tabs").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Input, "✏ Input");
+                    ui.selectable_value(&mut self.tab, Tab::Result, "📄 Result");
+                });
+            });
+        }
 
-        let (translate_requested, cancel_requested, api_key_to_save) =
-            self.sidebar.ui(ctx, self.is_translating);
+        // Before any text box sees this frame's paste
+        self.clean_source_pastes(ctx);
 
-        if let Some(api_key) = api_key_to_save {
+        self.sidebar
+            .set_term_matcher(self.glossary.matcher(&self.sidebar.get_target_language()));
+        self.update_cost_estimate();
+        let sidebar_actions = match (tabs, self.tab) {
+            (false, _) => self.sidebar.ui(ctx, self.is_translating),
+            (true, Tab::Input) => self.sidebar.tab_ui(ctx, self.is_translating),
+            (true, Tab::Result) => SidebarActions::default(),
+        };
+        if let Some(selection) = sidebar_actions.add_term {
+            self.glossary_window
+                .add_term(&selection, &self.sidebar.get_target_language());
+        }
+
+        if let Some(api_key) = sidebar_actions.api_key {
             self.config.api_key = api_key;
         }
-        self.config.target_language = self.sidebar.get_target_language();
+        if let Some(base_url) = sidebar_actions.base_url
+            && base_url != self.config.api_base_url
+        {
+            tracing::info!(base_url, "API base URL changed");
+            self.config.api_base_url = base_url;
+        }
+        let target_language = self.sidebar.get_target_language();
+        if target_language != self.config.target_language {
+            self.config.target_language = target_language;
+            self.apply_language_profile();
+        }
+        self.config.sidebar_collapsed = self.sidebar.is_collapsed();
+        self.config.sidebar_width = self.sidebar.expanded_width();
+        let context = self.sidebar.prompt_context();
+        self.config.prompt_domain = context.domain;
+        self.config.prompt_audience = context.audience;
+        self.config.romanize_source = self.sidebar.romanize_source();
+        if sidebar_actions.romanize {
+            self.start_romanization();
+        }
 
-        if translate_requested {
+        if sidebar_actions.translate {
             let api_key = self.sidebar.get_api_key();
             if !api_key.is_empty() {
                 self.start_translation(api_key);
             }
         }
 
-        if cancel_requested {
+        // PDFs and localization files picked in the sidebar or dropped on the window
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        let dropped_pdf = dropped.iter().find(|path| pdf::is_pdf(path)).cloned();
+        if let Some(path) = sidebar_actions.open_pdf.or(dropped_pdf) {
+            self.open_pdf(path);
+        }
+        let dropped_document = dropped.into_iter().find(|path| {
+            path.extension()
+                .and_then(|extension| Format::from_extension(&extension.to_string_lossy()))
+                .is_some()
+        });
+        if let Some(path) = sidebar_actions.open_document.or(dropped_document) {
+            self.open_document(path);
+        }
+        if let Some(url) = sidebar_actions.fetch_url {
+            self.open_url(url);
+        }
+        let api_key = self.sidebar.get_api_key();
+        let can_translate = !self.is_translating && !api_key.is_empty();
+        if let Some(action) = self.pdf_preview.ui(ctx, can_translate) {
+            match action {
+                PdfPreviewAction::UseAsSource(text) => self.sidebar.set_source_text(text),
+                PdfPreviewAction::Translate(text) => {
+                    self.sidebar.set_source_text(text);
+                    self.start_translation(api_key);
+                }
+            }
+        }
+        if let Some(decision) = self.redaction_preview.ui(ctx) {
+            match decision {
+                RedactionDecision::Send {
+                    request,
+                    redaction,
+                    dont_ask_again,
+                } => {
+                    if dont_ask_again {
+                        self.config.redaction_preview = false;
+                        self.settings.redaction_preview = false;
+                    }
+                    if can_translate {
+                        self.send_redacted(self.sidebar.get_api_key(), *request, redaction);
+                    }
+                }
+                RedactionDecision::Cancel => tracing::info!("Redacted translation cancelled"),
+            }
+        }
+        if let Some(action) = self.structured.ui(ctx, can_translate) {
+            match action {
+                StructuredAction::Translate(batch) => self.translate_values(batch),
+                StructuredAction::TranslateAsText(text) => {
+                    self.sidebar.set_source_text(text);
+                    self.translate_source(self.sidebar.get_api_key());
+                }
+                StructuredAction::Copy(text) => {
+                    ctx.copy_text(text);
+                    self.toasts.info("Document copied");
+                }
+                StructuredAction::Save(path, text) => match std::fs::write(&path, text) {
+                    Ok(()) => self.toasts.info(format!("Saved {}", path.display())),
+                    Err(e) => {
+                        tracing::warn!("Failed to save {}: {}", path.display(), e);
+                        self.toasts
+                            .error(format!("Could not save {}: {}", path.display(), e));
+                    }
+                },
+            }
+        }
+
+        let log_path = self
+            .logger
+            .as_ref()
+            .map(|logger| logger.path().to_path_buf());
+        match self
+            .history
+            .ui(ctx, log_path.as_deref(), !self.is_translating)
+        {
+            Some(HistoryAction::Load(entry)) => self.load_history_entry(*entry),
+            Some(HistoryAction::ExportTmx {
+                path,
+                skip_unknown_source,
+            }) => self.export_tmx(path, skip_unknown_source),
+            None => {}
+        }
+
+        let languages = AppConfig::get_supported_languages();
+        let glossary_action =
+            self.glossary_window
+                .ui(ctx, &self.glossary, &languages, log_path.as_deref());
+        if let Some(action) = glossary_action {
+            let result = match action {
+                GlossaryAction::Add(term) => self.glossary.add(term),
+                GlossaryAction::Remove(term) => {
+                    let removed = self.glossary.remove(&term);
+                    if removed.is_ok() {
+                        self.soft_delete(Deletion::GlossaryTerm(term), "Glossary term removed");
+                    }
+                    removed
+                }
+                GlossaryAction::Update { original, edited } => {
+                    // Refused edits are shown in their row
+                    let result = self.glossary.update(&original, edited);
+                    self.glossary_window.finish_edit(result);
+                    Ok(())
+                }
+                GlossaryAction::Reload => {
+                    self.glossary.reload();
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                self.toasts.error(e.to_string());
+            }
+        }
+
+        let config_path = AppConfig::config_path();
+        let about_paths = AboutPaths {
+            config: &config_path,
+            translation_cache: self.cache.path(),
+            audio_cache: self.audio_cache.dir(),
+            log: log_path.as_deref(),
+            log_detail: self.config.log_detail,
+        };
+        if self.about.ui(
+            ctx,
+            &about_paths,
+            self.config.check_for_updates,
+            self.checking_for_updates,
+            self.latest_release.as_ref(),
+        ) {
+            self.check_for_updates();
+        }
+
+        if sidebar_actions.cancel {
             self.cancel_translation();
             ctx.request_repaint(); // Force immediate UI update to show cancel
         }
 
-        let (_show_settings, settings_changes) =
-            self.settings
-                .ui(ctx, Some(self.cache.clone()), self.audio_cache.len());
+        if sidebar_actions.speak_source {
+            self.speak_source();
+        }
+
+        if sidebar_actions.toggle_settings {
+            self.settings.toggle_panel();
+            self.refresh_storage_usage();
+        }
+
+        let (_show_settings, settings_changes) = self.settings.ui(
+            ctx,
+            Some(self.cache.clone()),
+            self.audio_cache.len(),
+            &self.storage_usage,
+        );
 
         if let Some(change) = settings_changes {
             match change {
@@ -603,40 +3915,35 @@ impl eframe::App for TranslateApp {
                 }
                 SettingsChange::TtsVoice(voice) => {
                     self.config.tts_voice = voice.clone();
-                    let tts_config = TtsConfig::new(
-                        AppConfig::parse_voice(&voice),
-                        self.config.tts_speed,
-                        self.config.tts_volume,
-                        self.config.coding_plan,
-                        self.config.think_enable,
-                    );
-                    self.tts_service.update_config(tts_config);
+                    self.tts_service.update_config(self.config.tts_config());
                     tracing::info!("TTS voice changed to: {}", voice);
                 }
                 SettingsChange::TtsSpeed(speed) => {
                     self.config.tts_speed = speed;
-                    let tts_config = TtsConfig::new(
-                        AppConfig::parse_voice(&self.config.tts_voice),
-                        speed,
-                        self.config.tts_volume,
-                        self.config.coding_plan,
-                        self.config.think_enable,
-                    );
-                    self.tts_service.update_config(tts_config);
+                    self.tts_service.update_config(self.config.tts_config());
                     tracing::info!("TTS speed changed to: {}", speed);
                 }
                 SettingsChange::TtsVolume(volume) => {
                     self.config.tts_volume = volume;
-                    let tts_config = TtsConfig::new(
-                        AppConfig::parse_voice(&self.config.tts_voice),
-                        self.config.tts_speed,
-                        volume,
-                        self.config.coding_plan,
-                        self.config.think_enable,
-                    );
-                    self.tts_service.update_config(tts_config);
+                    self.tts_service.update_config(self.config.tts_config());
                     tracing::info!("TTS volume changed to: {}", volume);
                 }
+                SettingsChange::TtsTimeouts {
+                    total_secs,
+                    segment_secs,
+                } => {
+                    self.config.tts_timeout_secs = total_secs;
+                    self.config.tts_segment_timeout_secs = segment_secs;
+                    self.tts_service.update_config(self.config.tts_config());
+                    tracing::info!(total_secs, segment_secs, "TTS timeouts changed");
+                }
+                SettingsChange::SpeakWhileTranslating(enabled) => {
+                    self.config.speak_while_translating = enabled;
+                    tracing::info!(
+                        "Speaking while translating {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
                 SettingsChange::KeywordAnalysis(enabled) => {
                     self.config.enable_keyword_analysis = enabled;
                     tracing::info!(
@@ -644,16 +3951,97 @@ impl eframe::App for TranslateApp {
                         if enabled { "enabled" } else { "disabled" }
                     );
                 }
+                SettingsChange::ShowAlternatives(enabled) => {
+                    self.config.show_alternatives = enabled;
+                    tracing::info!(
+                        "Alternatives {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::AutoFontSize {
+                    source,
+                    translation,
+                } => {
+                    self.config.auto_font_source = source;
+                    self.config.auto_font_translation = translation;
+                    self.display.set_auto_font(
+                        source,
+                        translation,
+                        self.config.script_font_scales.clone(),
+                    );
+                    tracing::info!(
+                        "Auto font size: source {}, translation {}",
+                        source,
+                        translation
+                    );
+                }
+                SettingsChange::ScriptFontScales(scales) => {
+                    self.config.script_font_scales = scales.clone();
+                    self.display.set_auto_font(
+                        self.config.auto_font_source,
+                        self.config.auto_font_translation,
+                        scales,
+                    );
+                    tracing::info!("Script font multipliers changed");
+                }
+                SettingsChange::SmoothTyping(chars_per_sec) => {
+                    self.config.smooth_typing = chars_per_sec;
+                    self.display.set_smooth_typing(chars_per_sec);
+                    tracing::info!("Smooth typing: {:?} chars/s", chars_per_sec);
+                }
+                SettingsChange::LanguageProfile { language, profile } => {
+                    tracing::info!(
+                        "Language profile for {} {}",
+                        language,
+                        if profile.is_some() {
+                            "saved"
+                        } else {
+                            "removed"
+                        }
+                    );
+                    match profile {
+                        Some(profile) => self.config.language_profiles.insert(language, profile),
+                        None => self.config.language_profiles.remove(&language),
+                    };
+                    self.sidebar.set_proficiencies(self.config.proficiencies());
+                    self.apply_language_profile();
+                }
+                SettingsChange::RetryRefusals(enabled) => {
+                    self.config.retry_refusals = enabled;
+                    tracing::info!(
+                        "Retrying declined translations {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::PivotEnabled(enabled) => {
+                    self.config.pivot_enabled = enabled;
+                    tracing::info!(
+                        "Pivot translation {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::PivotLanguage(language) => {
+                    tracing::info!("Pivot language changed to: {}", language);
+                    self.config.pivot_language = language;
+                }
+                SettingsChange::Spellcheck(enabled) => {
+                    self.config.spellcheck_enabled = enabled;
+                    self.sidebar
+                        .set_spellcheck(enabled, &self.config.spellcheck_language);
+                    tracing::info!(
+                        "Spell check {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::SpellcheckLanguage(language) => {
+                    self.sidebar
+                        .set_spellcheck(self.config.spellcheck_enabled, &language);
+                    tracing::info!("Spell check language changed to: {}", language);
+                    self.config.spellcheck_language = language;
+                }
                 SettingsChange::ThinkEnable(enabled) => {
                     self.config.think_enable = enabled;
-                    let tts_config = TtsConfig::new(
-                        AppConfig::parse_voice(&self.config.tts_voice),
-                        self.config.tts_speed,
-                        self.config.tts_volume,
-                        self.config.coding_plan,
-                        self.config.think_enable,
-                    );
-                    self.tts_service.update_config(tts_config);
+                    self.tts_service.update_config(self.config.tts_config());
                     tracing::info!(
                         "Thinking mode {}",
                         if enabled { "enabled" } else { "disabled" }
@@ -661,77 +4049,425 @@ impl eframe::App for TranslateApp {
                 }
                 SettingsChange::CodingPlan(enabled) => {
                     self.config.coding_plan = enabled;
-                    let tts_config = TtsConfig::new(
-                        AppConfig::parse_voice(&self.config.tts_voice),
-                        self.config.tts_speed,
-                        self.config.tts_volume,
-                        self.config.coding_plan,
-                        self.config.think_enable,
-                    );
-                    self.tts_service.update_config(tts_config);
+                    self.tts_service.update_config(self.config.tts_config());
                     tracing::info!(
                         "Coding plan mode {}",
                         if enabled { "enabled" } else { "disabled" }
                     );
                 }
+                SettingsChange::ChatThinking(mode) => {
+                    self.config.chat_thinking = mode;
+                    tracing::info!(
+                        "Model thinking set to: {}",
+                        mode.map_or("provider default", |m| m.as_str())
+                    );
+                }
+                SettingsChange::DiscardReasoning(discard) => {
+                    self.config.discard_reasoning = discard;
+                    tracing::info!(
+                        "Model reasoning {}",
+                        if discard { "discarded" } else { "kept" }
+                    );
+                }
+                SettingsChange::WatchConfigFile(enabled) => {
+                    self.config.watch_config_file = enabled;
+                    self.set_config_watch(ctx, enabled);
+                    tracing::info!(
+                        "Watching the config file {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::CheckForUpdates(enabled) => {
+                    self.config.check_for_updates = enabled;
+                    tracing::info!(
+                        "Update checks {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::SharedCache(shared) => {
+                    self.config.shared_cache = shared;
+                    tracing::info!(
+                        "Translation cache {}",
+                        if shared {
+                            "shared between models"
+                        } else {
+                            "kept per model"
+                        }
+                    );
+                }
+                SettingsChange::PreconnectOnStartup(enabled) => {
+                    self.config.preconnect_on_startup = enabled;
+                    tracing::info!(
+                        "Pre-connect on startup {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::Timeouts {
+                    connect_secs,
+                    request_secs,
+                } => {
+                    self.config.connect_timeout_secs = connect_secs;
+                    self.config.request_timeout_secs = request_secs;
+                    transport::set_connect_timeout(Duration::from_secs(connect_secs));
+                    tracing::info!(connect_secs, request_secs, "Timeouts changed");
+                }
+                SettingsChange::RequestAttempts(attempts) => {
+                    self.config.request_attempts = attempts;
+                    tracing::info!(attempts, "Request attempts changed");
+                }
+                SettingsChange::UnescapeContent(enabled) => {
+                    self.config.unescape_content = enabled;
+                    tracing::info!(
+                        "Unescaping of response content {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::MaxTokens(max_tokens) => {
+                    self.config.max_tokens = max_tokens;
+                    tracing::info!(
+                        "Max output tokens set to: {}",
+                        max_tokens.map_or("provider default".to_string(), |n| n.to_string())
+                    );
+                }
+                SettingsChange::RepetitionLimit(max_repeats) => {
+                    self.config.repetition_limit = max_repeats;
+                    tracing::info!(
+                        "Repetition limit set to: {}",
+                        max_repeats.map_or("off".to_string(), |n| n.to_string())
+                    );
+                }
+                SettingsChange::Model(model) => {
+                    tracing::info!("Model set to: {}", model);
+                    self.config.model = model;
+                    // Prices are per model
+                    self.cost_estimated_for = None;
+                }
+                SettingsChange::FastPath { max_chars, model } => {
+                    tracing::info!(
+                        model = %model,
+                        "Fast path limit set to: {}",
+                        max_chars.map_or("off".to_string(), |n| n.to_string())
+                    );
+                    self.config.fast_path_chars = max_chars;
+                    self.config.fast_path_model = model;
+                }
+                SettingsChange::Pricing {
+                    model_pricing,
+                    output_ratio,
+                } => {
+                    tracing::info!(
+                        models = model_pricing.len(),
+                        output_ratio,
+                        "Pricing changed"
+                    );
+                    self.config.model_pricing = model_pricing;
+                    self.config.output_ratio = output_ratio;
+                    self.cost_estimated_for = None;
+                }
+                SettingsChange::SourcePanelLayout(layout) => {
+                    self.config.source_panel_layout = layout;
+                    tracing::info!("Source panel layout changed to: {:?}", layout);
+                }
+                SettingsChange::PinnedLayout(arrangement) => {
+                    self.config.pinned_layout = arrangement;
+                    tracing::info!("Window layout pinned to: {:?}", arrangement);
+                }
+                SettingsChange::SidebarAutoCollapse(enabled) => {
+                    self.config.sidebar_auto_collapse = enabled;
+                    self.sidebar.set_auto_collapse(enabled);
+                    tracing::info!(
+                        "Sidebar auto-collapse {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::SanitizeSourceText(enabled) => {
+                    self.config.sanitize_source_text = enabled;
+                    tracing::info!(
+                        "Pasted text cleanup {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::Redaction {
+                    enabled,
+                    preview,
+                    patterns,
+                } => {
+                    self.config.redact_sensitive = enabled;
+                    self.config.redaction_preview = preview;
+                    self.config.redaction_patterns = patterns;
+                    self.update_speech_redaction();
+                    tracing::info!(
+                        "Redaction of sensitive data {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::CompletionHook {
+                    enabled,
+                    command,
+                    timeout_secs,
+                } => {
+                    tracing::info!(enabled, timeout_secs, "Completion hook changed");
+                    self.config.completion_hook_enabled = enabled;
+                    self.config.completion_hook = command;
+                    self.config.completion_hook_timeout_secs = timeout_secs;
+                }
+                SettingsChange::CacheExclusions(rules) => {
+                    tracing::info!(rules = rules.len(), "Cache exclusion rules changed");
+                    self.config.cache_exclusions = rules;
+                }
+                SettingsChange::Typography { enabled, quotes } => {
+                    self.config.normalize_typography = enabled;
+                    self.config.quote_style = quotes;
+                    tracing::info!(
+                        quotes = quotes.label(),
+                        "Punctuation normalization {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
+                SettingsChange::CustomFont(path) => {
+                    tracing::info!("Custom font changed to: {:?}", path);
+                    self.set_custom_font(ctx, path);
+                }
+                SettingsChange::PracticeMode(enabled) => {
+                    self.config.practice_mode = enabled;
+                    self.display.set_practice_mode(enabled);
+                    tracing::info!(
+                        "Practice mode {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                }
                 SettingsChange::ClearTranslationCache => {
                     self.clear_translation_cache();
                 }
                 SettingsChange::ClearAudioCache => {
                     self.clear_audio_cache();
                 }
+                SettingsChange::ReloadConfigFromFile => {
+                    self.reload_config_from_file(ctx);
+                }
+                SettingsChange::RestoreConfig => {
+                    self.restore_config(ctx);
+                }
+                SettingsChange::CreateDiagnosticBundle { strip_text } => {
+                    self.create_diagnostic_bundle(strip_text);
+                }
+                SettingsChange::Retention {
+                    retention_days,
+                    storage_budget_mb,
+                    log_rotate_mb,
+                } => {
+                    self.config.retention_days = retention_days;
+                    self.config.storage_budget_mb = storage_budget_mb;
+                    self.config.log_rotate_mb = log_rotate_mb;
+                    tracing::info!(
+                        ?retention_days,
+                        ?storage_budget_mb,
+                        ?log_rotate_mb,
+                        "Storage limits changed"
+                    );
+                }
+                SettingsChange::OpenFolder(dir) => {
+                    if let Err(e) = paths::open_folder(&dir) {
+                        tracing::warn!("Failed to open {:?}: {}", dir, e);
+                        self.toasts
+                            .error(format!("Could not open {}: {}", dir.display(), e));
+                    }
+                }
+                SettingsChange::CleanStorage => self.clean_storage(true),
+                SettingsChange::LogDetail(detail) => {
+                    self.config.log_detail = detail;
+                    if let Some(logger) = &self.logger {
+                        logger.set_detail(detail);
+                    }
+                    tracing::info!(?detail, "Translation log detail changed");
+                }
+                SettingsChange::PurgeLogContent => self.purge_log_content(),
             }
         }
 
-        let (
-            play_source_clicked,
-            source_audio_to_play,
-            play_translation_clicked,
-            translation_audio_to_play,
-            start_source_tts,
-            start_translation_tts,
-            cancel_source_tts,
-            cancel_translation_tts,
-        ) = self.display.ui(ctx, self.theme.font_size);
+        let actions = if tabs && self.tab == Tab::Input {
+            DisplayActions::default()
+        } else {
+            self.display.ui(
+                ctx,
+                self.theme.font_size,
+                self.config.source_panel_layout,
+                self.sidebar.shared_source_mut(),
+            )
+        };
+        if let Some((_, view)) = &mut self.viewed_entry {
+            *view = self.display.view_state(ctx);
+        }
 
         // Handle source TTS start
-        if start_source_tts {
-            let source_text = self.display.input_text().to_string();
-            if !source_text.trim().is_empty() {
-                self.start_source_tts(source_text);
-            }
+        if actions.start_source_tts {
+            self.speak_source();
         }
 
         // Handle translation TTS start
-        if start_translation_tts {
-            let translation_text = self.display.translation.clone();
-            if !translation_text.trim().is_empty() {
-                self.start_translation_tts(translation_text);
-            }
+        if actions.start_translation_tts {
+            self.speak_translation();
         }
 
         // Handle source audio button click
-        if play_source_clicked && let Some(audio_path) = source_audio_to_play {
+        if actions.play_source_clicked
+            && let Some(audio_path) = actions.source_audio_to_play
+        {
             self.play_audio(audio_path);
         }
 
         // Handle translation audio button click
-        if play_translation_clicked && let Some(audio_path) = translation_audio_to_play {
+        if actions.play_translation_clicked
+            && let Some(audio_path) = actions.translation_audio_to_play
+        {
             self.play_audio(audio_path);
         }
 
         // Handle source TTS cancel
-        if cancel_source_tts {
+        if actions.cancel_source_tts {
             self.cancel_source_tts();
             ctx.request_repaint(); // Force UI repaint to show cancel immediately
         }
 
         // Handle translation TTS cancel
-        if cancel_translation_tts {
+        if actions.cancel_translation_tts {
             self.cancel_translation_tts();
             ctx.request_repaint(); // Force UI repaint to show cancel immediately
         }
 
+        // Handle seeking and pausing the audio being played
+        if let Some(target) = actions.seek {
+            match self.audio_player.seek(target) {
+                Ok(_) => self
+                    .display
+                    .set_playback_progress(self.audio_player.progress()),
+                Err(e) => tracing::warn!("Failed to seek: {}", e),
+            }
+        }
+        if actions.toggle_pause {
+            self.audio_player.toggle_pause();
+            self.display
+                .set_playback_state(self.audio_player.get_state());
+        }
+
+        // Handle listening volume changes
+        if let Some(volume) = actions.volume_changed {
+            self.config.playback_volume = volume.level;
+            self.config.playback_muted = volume.muted;
+            self.audio_player.set_volume(volume);
+        }
+
+        // Handle a chosen alternative
+        if let Some(index) = actions.promote_alternative {
+            self.promote_alternative(index);
+        }
+
+        // Handle retrying a list item that could not be mapped back
+        if let Some(segment) = actions.retry_list_item {
+            self.retry_list_item(segment);
+        }
+
+        // Handle continuing a truncated translation
+        if actions.continue_translation {
+            self.continue_translation();
+        }
+
+        // Handle retrying a translation stopped for repeating itself
+        if actions.retry_lower_temperature {
+            self.retry_lower_temperature();
+        }
+
+        // Handle loading a font for characters that can't be displayed
+        if actions.load_font
+            && let Some(path) = theme::pick_font_file()
+        {
+            self.set_custom_font(ctx, Some(path));
+        }
+
+        // Handle a text already in the target language
+        let switch_target = actions.switch_target.is_some();
+        if let Some(language) = actions.switch_target {
+            self.sidebar.set_target_language(language);
+        }
+        if (actions.translate_anyway || switch_target) && !self.is_translating {
+            self.translate_source(self.sidebar.get_api_key());
+        }
+
+        // Handle sharing the translation as an image
+        if actions.share_image {
+            self.share_image(ctx);
+        }
+
+        // Handle self-grading in practice mode
+        if let Some(grade) = actions.practice_grade {
+            self.record_practice_grade(grade);
+        }
+
+        if let Some(word) = actions.align_word {
+            self.align_word(word);
+        }
+
+        // Handle explaining the translation, independently of the translation itself
+        if actions.explain {
+            self.start_explanation();
+        }
+        if actions.cancel_explanation {
+            self.cancel_explanation();
+            ctx.request_repaint();
+        }
+
+        // Handle popping out the translation, remembering where the window goes
+        if let Some(open) = actions.reasoning_open {
+            self.config.reasoning_collapsed = !open;
+        }
+        if actions.pop_out {
+            self.display.pop_out(self.config.popout_window);
+        }
+        if let Some(geometry) = self.display.popout_ui(ctx, self.theme.font_size) {
+            self.config.popout_window = Some(geometry);
+        }
+
+        // Handle compare-audio playback
+        match actions.compare {
+            Some(CompareAction::Play(path)) => self.play_local_file(path),
+            Some(CompareAction::Stop) => self.stop_audio(),
+            None => {}
+        }
+
+        // Deletions become permanent once their undo window has passed
+        for deletion in self.undo.expire(Instant::now()) {
+            deletion.finalize();
+        }
+
+        if self.palette.is_open() {
+            let matches = self.actions.search(self.palette.query(), self);
+            if let Some(id) = self.palette.ui(ctx, &matches) {
+                self.run_action(ctx, id);
+            }
+        }
+
+        self.announcer.ui(ctx);
+        match self.toasts.ui(ctx) {
+            Some(ToastAction::RetrySourceTts) => self.speak_source(),
+            Some(ToastAction::RetryTranslationTts) => self.speak_translation(),
+            Some(ToastAction::Undo(id)) => self.undo_deletion(id),
+            Some(ToastAction::OpenReleasePage) => self.about.open(),
+            Some(ToastAction::QueueForLater) => {
+                if let Some(request) = self.offline_request.take() {
+                    if self.offline_queue.push(request) {
+                        self.toasts.info(format!(
+                            "Queued for later ({} waiting)",
+                            self.offline_queue.len()
+                        ));
+                    } else {
+                        self.toasts.warning("The offline queue is full");
+                    }
+                }
+            }
+            None => {}
+        }
+        self.finish_startup();
+
         // Note: TTS is now manually triggered by user buttons
         // Removed auto-start TTS logic to give users more control
 
@@ -741,6 +4477,51 @@ impl eframe::App for TranslateApp {
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if self.scratch_dir.is_some() {
+            return;
+        }
+        self.stored_documents().save(storage);
+        // Both copies are stamped and written together, so on the next start
+        // a file that is newer than the stamp must have been edited by hand
+        if self
+            .persisted
+            .as_ref()
+            .is_some_and(|persisted| persisted.same_settings(&self.config))
+        {
+            return;
+        }
+        self.config.saved_at = Some(chrono::Utc::now().timestamp_millis());
         self.config.save_to_storage(storage);
+        if let Err(e) = self.config.save() {
+            tracing::warn!("Failed to save config file: {}", e);
+        } else if let Some(watcher) = &mut self.config_watcher {
+            // Not an edit to apply
+            watcher.remember_file();
+        }
+        self.persisted = Some(self.config.clone());
+    }
+}
+
+impl Drop for TranslateApp {
+    fn drop(&mut self) {
+        // Deletions still pending at exit become permanent
+        for deletion in self.undo.drain() {
+            deletion.finalize();
+        }
+        self.leave_history_entry();
+        for document in self.documents.iter() {
+            if let Some((entry, view)) = &document.parked.viewed_entry {
+                lock_mutex!(self.view_states).set(entry, view.clone());
+            }
+        }
+
+        if let Some(logger) = &self.logger {
+            logger.flush();
+        }
+        if let Some(dir) = &self.scratch_dir
+            && let Err(e) = std::fs::remove_dir_all(dir)
+        {
+            tracing::warn!("Failed to remove {:?}: {}", dir, e);
+        }
     }
 }
@@ -0,0 +1,208 @@
+//! Pronunciation comparison widget.
+//!
+//! Lets language learners open their own recording and play it against the
+//! TTS output (A/B), optionally looping between the two with a pause.
+
+use crate::ui::display;
+use crate::utils::dialogs::{Dialog, DialogCategory};
+use egui::*;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Which side of the comparison is being played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareSide {
+    /// The synthesized TTS audio
+    Tts,
+    /// The user's own recording
+    Recording,
+}
+
+impl CompareSide {
+    fn other(self) -> Self {
+        match self {
+            CompareSide::Tts => CompareSide::Recording,
+            CompareSide::Recording => CompareSide::Tts,
+        }
+    }
+}
+
+/// Playback action requested by the compare widget.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompareAction {
+    /// Play the given local audio file
+    Play(String),
+    /// Stop playback
+    Stop,
+}
+
+/// State of the "Compare audio" widget.
+pub struct ComparePanel {
+    recording_path: Option<PathBuf>,
+    loop_enabled: bool,
+    loop_gap_secs: f32,
+    /// Side played last while looping, `None` when the loop is not running
+    looping_side: Option<CompareSide>,
+    /// When the next side should start, set once the current one finished
+    next_switch_at: Option<Instant>,
+}
+
+impl Default for ComparePanel {
+    fn default() -> Self {
+        ComparePanel {
+            recording_path: None,
+            loop_enabled: false,
+            loop_gap_secs: 1.0,
+            looping_side: None,
+            next_switch_at: None,
+        }
+    }
+}
+
+impl ComparePanel {
+    /// Stops the A/B loop, e.g. after a playback failure.
+    pub fn stop_loop(&mut self) {
+        self.looping_side = None;
+        self.next_switch_at = None;
+    }
+
+    fn path_for(&self, side: CompareSide, tts_audio: Option<&str>) -> Option<String> {
+        match side {
+            CompareSide::Tts => tts_audio.map(str::to_string),
+            CompareSide::Recording => self
+                .recording_path
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+        }
+    }
+
+    /// Starts playing one side, (re)starting the loop from it when enabled.
+    fn play(&mut self, side: CompareSide, tts_audio: Option<&str>) -> Option<CompareAction> {
+        self.next_switch_at = None;
+        self.looping_side = self.loop_enabled.then_some(side);
+        self.path_for(side, tts_audio).map(CompareAction::Play)
+    }
+
+    /// Advances the A/B loop once the current side has finished playing.
+    fn tick(&mut self, tts_audio: Option<&str>, is_playing: bool) -> Option<CompareAction> {
+        let side = self.looping_side?;
+        if !self.loop_enabled || tts_audio.is_none() || self.recording_path.is_none() {
+            self.stop_loop();
+            return None;
+        }
+
+        if is_playing {
+            self.next_switch_at = None;
+            return None;
+        }
+
+        let now = Instant::now();
+        match self.next_switch_at {
+            None => {
+                self.next_switch_at = Some(now + Duration::from_secs_f32(self.loop_gap_secs));
+                None
+            }
+            Some(at) if now >= at => {
+                let next = side.other();
+                self.looping_side = Some(next);
+                self.next_switch_at = None;
+                self.path_for(next, tts_audio).map(CompareAction::Play)
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Renders the widget.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The egui UI to render into
+    /// * `tts_audio` - Path of the TTS audio to compare against, if any
+    /// * `is_playing` - Whether the audio player is currently playing
+    ///
+    /// # Returns
+    ///
+    /// The playback action requested this frame, if any
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        tts_audio: Option<&str>,
+        is_playing: bool,
+    ) -> Option<CompareAction> {
+        let mut action = self.tick(tts_audio, is_playing);
+
+        display::sync_section(ui, "compare_audio", false);
+        CollapsingHeader::new(RichText::new("🎧Compare audio").size(13.0))
+            .id_salt("compare_audio")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(RichText::new("📂Open my recording…").size(12.0))
+                        .clicked()
+                        && let Some(path) = Dialog::new(DialogCategory::Recording)
+                            .add_filter("Audio", &["wav", "mp3", "ogg"])
+                            .pick_file()
+                    {
+                        tracing::info!("Loaded recording for comparison: {:?}", path);
+                        self.recording_path = Some(path);
+                        self.stop_loop();
+                    }
+
+                    let name = self
+                        .recording_path
+                        .as_ref()
+                        .and_then(|p| p.file_name())
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "No recording loaded".to_string());
+                    ui.label(RichText::new(name).size(12.0).weak());
+                });
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            tts_audio.is_some(),
+                            Button::new(RichText::new("A ▶ TTS").size(12.0)),
+                        )
+                        .on_disabled_hover_text("Convert the text to audio first")
+                        .clicked()
+                    {
+                        action = self.play(CompareSide::Tts, tts_audio);
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.recording_path.is_some(),
+                            Button::new(RichText::new("B ▶ Mine").size(12.0)),
+                        )
+                        .clicked()
+                    {
+                        action = self.play(CompareSide::Recording, tts_audio);
+                    }
+
+                    if ui
+                        .add_enabled(is_playing, Button::new(RichText::new("⏹").size(12.0)))
+                        .on_hover_text("Stop playback")
+                        .clicked()
+                    {
+                        self.stop_loop();
+                        action = Some(CompareAction::Stop);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.checkbox(&mut self.loop_enabled, "🔁Loop A/B").changed()
+                        && !self.loop_enabled
+                    {
+                        self.stop_loop();
+                    }
+                    ui.add(
+                        Slider::new(&mut self.loop_gap_secs, 0.0..=5.0)
+                            .step_by(0.5)
+                            .suffix(" s gap"),
+                    );
+                });
+            });
+
+        action
+    }
+}
@@ -3,16 +3,204 @@
 //! This module provides the central UI component that displays
 //! the input text and streaming translation results.
 
-use crate::services::audio::PlaybackState;
+use crate::api::translator::{Alternative, LowConfidence};
+use crate::channel::channel::Warning;
+use crate::services::audio::{PlaybackProgress, PlaybackState, PlaybackVolume, SKIP_STEP};
+use crate::ui::compare::{CompareAction, ComparePanel};
+use crate::ui::sidebar;
+use crate::utils::alignment;
+use crate::utils::announce;
+use crate::utils::bidi::{self, Direction};
+use crate::utils::config::{SourcePanelLayout, WindowGeometry};
+use crate::utils::links::{self, Link};
+use crate::utils::list::ListTranslation;
+use crate::utils::paragraphs::StreamingText;
+use crate::utils::practice::{self, Grade, PracticeCard};
+use crate::utils::script::{AdaptiveFont, Script};
+use crate::utils::share;
+use crate::utils::shared_text::{Replica, SharedText};
+use crate::utils::typewriter::Typewriter;
+use crate::utils::typography::{self, QuoteStyle};
+use crate::utils::view_state::ViewState;
+use egui::collapsing_header::CollapsingState;
 use egui::*;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Widget ID of the editable source text in the central panel.
+pub const SOURCE_EDIT_ID: &str = "display_source_edit";
+
+/// Accessibility node holding the whole translation.
+const TRANSLATION_TEXT_ID: &str = "display_translation_text";
+
+/// Id source of the pop-out translation viewport.
+const POPOUT_VIEWPORT: &str = "translation_popout";
+
+/// Collapsible sections whose open state is kept with history entries.
+const VIEW_SECTIONS: [&str; 3] = ["pivot", "explanation", "compare_audio"];
+
+/// Temp data of the sections: whether each is open, and the state to restore.
+const SECTION_OPEN_ID: &str = "view_section_open";
+const SECTION_RESTORE_ID: &str = "view_section_restore";
+
+/// Restores the open state of the section with `id_salt` if one is
+/// pending, and notes its current state.
+///
+/// Called right before the section's `CollapsingHeader`.
+pub(crate) fn sync_section(ui: &Ui, id_salt: &str, default_open: bool) {
+    let restore = ui.data_mut(|d| d.remove_temp::<bool>(Id::new(SECTION_RESTORE_ID).with(id_salt)));
+    let id = ui.make_persistent_id(id_salt);
+    let mut state = CollapsingState::load_with_default_open(ui.ctx(), id, default_open);
+    if let Some(open) = restore {
+        state.set_open(open);
+        state.store(ui.ctx());
+    }
+    let open = state.is_open();
+    ui.data_mut(|d| d.insert_temp(Id::new(SECTION_OPEN_ID).with(id_salt), open));
+}
+
+/// User actions collected while rendering the display panel.
+#[derive(Debug, Default)]
+pub struct DisplayActions {
+    pub play_source_clicked: bool,
+    pub source_audio_to_play: Option<String>,
+    pub play_translation_clicked: bool,
+    pub translation_audio_to_play: Option<String>,
+    pub start_source_tts: bool,
+    pub start_translation_tts: bool,
+    pub cancel_source_tts: bool,
+    pub cancel_translation_tts: bool,
+    /// Playback requested by the compare-audio widget
+    pub compare: Option<CompareAction>,
+    /// Index of the alternative chosen as the translation
+    pub promote_alternative: Option<usize>,
+    /// Segment of the list translation to translate again
+    pub retry_list_item: Option<usize>,
+    /// New listening level from the volume popover
+    pub volume_changed: Option<PlaybackVolume>,
+    /// "Continue" was clicked on a truncated translation
+    pub continue_translation: bool,
+    /// "Retry" was clicked on a translation stopped for repeating itself
+    pub retry_lower_temperature: bool,
+    /// "Pop out" was clicked on the translation
+    pub pop_out: bool,
+    /// "Explain" was clicked on the translation
+    pub explain: bool,
+    /// The running explanation should be stopped
+    pub cancel_explanation: bool,
+    /// The reasoning section was opened (`true`) or collapsed
+    pub reasoning_open: Option<bool>,
+    /// Self-grade of a revealed practice item
+    pub practice_grade: Option<Grade>,
+    /// "Load font" was clicked on the missing glyphs banner
+    pub load_font: bool,
+    /// "Translate anyway" was clicked on a text already in the target language
+    pub translate_anyway: bool,
+    /// Target language to switch to before translating such a text
+    pub switch_target: Option<String>,
+    /// "Copy as image" was chosen in the share menu
+    pub share_image: bool,
+    /// Word of the translation double-clicked to find its source phrase
+    pub align_word: Option<String>,
+    /// Position to move the playing audio to
+    pub seek: Option<Duration>,
+    /// Pause or resume was clicked on the playing audio
+    pub toggle_pause: bool,
+}
+
+/// Source phrase looked up for a word of the translation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlignmentView {
+    /// Waiting for the answer
+    Pending { word: String },
+    /// The phrase, highlighted in the source text
+    Found {
+        word: String,
+        /// Byte range in the source text
+        range: Range<usize>,
+        phrase: String,
+        /// Found as answered, not only something similar
+        exact: bool,
+    },
+    /// Nothing usable came back, with the reason
+    Failed { word: String, reason: String },
+}
+
+/// Temporary data key of a word double-clicked in the translation.
+const ALIGN_WORD_ID: &str = "display_align_word";
+
+/// Layouts of a section header row and of its buttons, mirrored for
+/// right-to-left text so the title sits on the right.
+fn header_layouts(direction: Direction) -> (Layout, Layout) {
+    if direction.is_rtl() {
+        (
+            Layout::right_to_left(Align::Center),
+            Layout::left_to_right(Align::Center),
+        )
+    } else {
+        (
+            Layout::left_to_right(Align::Center),
+            Layout::right_to_left(Align::Center),
+        )
+    }
+}
 
 /// Display panel showing source text and translation results.
 #[derive(Default)]
 pub struct DisplayPanel {
     input_text: String,
-    pub translation: String,
+    translation: StreamingText,
     is_translating: bool,
     error_message: Option<String>,
+    alternatives: Vec<Alternative>,
+    /// Per-item result when the translation was made in list mode
+    list: Option<ListTranslation>,
+    /// Segments of the list being translated again
+    list_retrying: Vec<usize>,
+    /// Pivot language and intermediate text when the translation went through one
+    pivot: Option<(String, String)>,
+    /// The translation stopped at the output limit
+    truncated: bool,
+    /// The provider declined to translate the text, the translation is
+    /// whatever arrived before that
+    refused: bool,
+    /// The translation was stopped because the model kept repeating itself
+    repetition_stopped: bool,
+    /// The translation comes from the legacy cache, possibly made by another model
+    legacy_cache: bool,
+    /// Characters of the translation adjusted by punctuation normalization
+    typography_adjusted: usize,
+    /// Source phrase of the word last double-clicked in the translation
+    alignment: Option<AlignmentView>,
+    /// Stretches the model is unsure of, with the translation they were
+    /// found in
+    low_confidence: Option<(String, Vec<LowConfidence>)>,
+    /// A confidence check of the translation is running
+    checking_confidence: bool,
+    /// Language of the current translation
+    target_language: String,
+    /// Characters of the translation the fonts can't display
+    font_warning: Option<String>,
+    /// Warnings raised while making the current translation
+    warnings: Vec<Warning>,
+    /// Language the source text already seems to be in, and the target
+    /// language to offer instead, until the user decides
+    same_language: Option<(String, String)>,
+    /// The model's reasoning behind the current translation, never part of it
+    reasoning: String,
+    reasoning_open: bool,
+    /// Explanation of the current translation
+    explanation: String,
+    is_explaining: bool,
+    explanation_error: Option<String>,
+    /// Hide the finished translation until it is revealed
+    practice_mode: bool,
+    /// Reveal state of the finished translation in practice mode
+    practice: Option<PracticeCard>,
+    /// Comprehension rate shown below the practice controls
+    practice_summary: Option<String>,
 
     // TTS and playback state
     source_tts_converting: bool,
@@ -20,9 +208,77 @@ pub struct DisplayPanel {
     translation_tts_converting: bool,
     translation_audio_path: Option<String>,
     playback_state: PlaybackState,
+    /// Position in the audio being played, when it can be seeked
+    playback_progress: Option<PlaybackProgress>,
+    playback_volume: PlaybackVolume,
+    /// Whether the player can change the level, otherwise only muting is offered
+    volume_adjustable: bool,
+    compare: ComparePanel,
+    /// This panel's copy of the sidebar's source text in the editable layout
+    source_editor: Replica,
+    /// The target language's profile turns off the pronunciation comparison
+    hide_pronunciation: bool,
+    /// Pop-out translation window, `Some` while it is open
+    popout: Option<ViewportBuilder>,
+    /// Size the source text for its dominant script
+    auto_font_source: bool,
+    /// Size the translation for its dominant script
+    auto_font_translation: bool,
+    /// Font size multipliers per script
+    font_scales: BTreeMap<Script, f32>,
+    source_font: AdaptiveFont,
+    translation_font: AdaptiveFont,
+    /// Finished translation the links were found in, and its links
+    links: (String, Vec<Link>),
+    /// Reveals a streaming translation at a steady rate, `None` shows
+    /// chunks as they arrive
+    typewriter: Option<Typewriter>,
+    /// Show the running translation whole instead of typing it out
+    instant: bool,
+    /// View of a history entry to restore on the next frame
+    pending_view: Option<ViewState>,
 }
 
 impl DisplayPanel {
+    /// Restores the scroll position and open sections of a history entry.
+    pub fn restore_view(&mut self, view: ViewState) {
+        self.pending_view = Some(view);
+    }
+
+    /// The current scroll position and open sections.
+    pub fn view_state(&self, ctx: &Context) -> ViewState {
+        ctx.data(|d| ViewState {
+            scroll_fraction: d
+                .get_temp(Id::new("translation_scroll").with("fraction"))
+                .unwrap_or(0.0),
+            sections: VIEW_SECTIONS
+                .iter()
+                .filter_map(|name| {
+                    d.get_temp(Id::new(SECTION_OPEN_ID).with(*name))
+                        .map(|open| (name.to_string(), open))
+                })
+                .collect(),
+        })
+    }
+
+    /// Hands a pending view to the widgets it belongs to.
+    fn apply_pending_view(&mut self, ctx: &Context) {
+        let Some(view) = self.pending_view.take() else {
+            return;
+        };
+        ctx.data_mut(|d| {
+            for name in VIEW_SECTIONS {
+                d.remove_temp::<bool>(Id::new(SECTION_RESTORE_ID).with(name));
+            }
+            for (name, open) in view.sections {
+                d.insert_temp(Id::new(SECTION_RESTORE_ID).with(name), open);
+            }
+            let scroll = Id::new("translation_scroll");
+            d.remove_temp::<f32>(scroll.with("measured"));
+            d.insert_temp(scroll.with("restore"), view.scroll_fraction);
+        });
+    }
+
     /// Sets the input text to display.
     pub fn set_input(&mut self, text: String) {
         self.input_text = text;
@@ -34,23 +290,284 @@ impl DisplayPanel {
         &self.input_text
     }
 
+    /// Gets the translation, split into completed paragraphs and the
+    /// paragraph still streaming
+    pub fn translation(&self) -> &StreamingText {
+        &self.translation
+    }
+
     /// Appends a chunk of translation text (for streaming).
     pub fn update_translation(&mut self, chunk: String) {
-        self.translation.push_str(&chunk);
+        self.translation.push(&chunk);
+        self.practice = None;
+    }
+
+    /// Language the translation is in.
+    pub fn target_language(&self) -> &str {
+        &self.target_language
+    }
+
+    /// Sets the language being translated into, which decides the text
+    /// direction until the translation has enough text to tell.
+    pub fn set_target_language(&mut self, language: &str) {
+        self.target_language = language.to_string();
+    }
+
+    /// Direction the translation is shown in.
+    fn translation_direction(&self) -> Direction {
+        bidi::text_direction(self.translation.as_str(), &self.target_language)
+    }
+
+    /// Marks the translation as cut off at the output limit.
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.truncated = truncated;
+    }
+
+    /// Marks the translation as stopped for repeating itself, dropping the
+    /// last `repeated_chars` characters, which are the repeats.
+    pub fn stop_repetition(&mut self, repeated_chars: usize) {
+        let text = self.translation.as_str();
+        let kept = text.chars().count().saturating_sub(repeated_chars);
+        let end = text.char_indices().nth(kept).map_or(text.len(), |(i, _)| i);
+        let clean = text[..end].to_string();
+        self.translation.set(&clean);
+        self.repetition_stopped = true;
+    }
+
+    /// Sets the alternatives offered for a short input.
+    pub fn set_alternatives(&mut self, alternatives: Vec<Alternative>) {
+        self.alternatives = alternatives;
+    }
+
+    /// Gets the alternatives offered for the current translation
+    pub fn alternatives(&self) -> &[Alternative] {
+        &self.alternatives
+    }
+
+    /// Makes the alternative at `index` the translation.
+    pub fn promote_alternative(&mut self, index: usize) {
+        if let Some(alternative) = self.alternatives.get(index) {
+            self.translation.set(&alternative.text);
+            // The synthesized audio belongs to the previous translation
+            self.translation_audio_path = None;
+        }
+    }
+
+    /// Sets the intermediate text of a translation through `language`.
+    pub fn set_pivot(&mut self, language: String, text: String) {
+        self.pivot = Some((language, text));
+    }
+
+    /// Marks the translation as coming from the legacy cache.
+    pub fn set_legacy_cache(&mut self, legacy: bool) {
+        self.legacy_cache = legacy;
+    }
+
+    /// Gives the finished translation the punctuation of `language`.
+    ///
+    /// Alternatives and list translations are left as they are, since their
+    /// parts are shown and copied separately.
+    pub fn normalize_typography(&mut self, language: &str, quotes: QuoteStyle) {
+        if self.alternatives.len() > 1 || self.list.is_some() {
+            return;
+        }
+        let normalized = typography::normalize(self.translation.as_str(), language, quotes);
+        if normalized.adjusted == 0 {
+            return;
+        }
+        self.translation.set(&normalized.text);
+        if let Some(typewriter) = &mut self.typewriter {
+            typewriter.finish(&normalized.text);
+        }
+        self.typography_adjusted = normalized.adjusted;
+    }
+
+    /// Shows the source phrase looked up for a word, `None` removes it.
+    pub fn set_alignment(&mut self, alignment: Option<AlignmentView>) {
+        self.alignment = alignment;
+    }
+
+    /// Marks the translation as being checked for stretches the model is
+    /// unsure of.
+    pub fn start_confidence_check(&mut self) {
+        self.low_confidence = None;
+        self.checking_confidence = true;
+    }
+
+    /// Underlines `spans` of `translation`, while it is the one shown.
+    pub fn set_low_confidence(&mut self, translation: String, spans: Vec<LowConfidence>) {
+        self.low_confidence = Some((translation, spans));
+        self.checking_confidence = false;
+    }
+
+    /// Ends the confidence check without underlining anything.
+    pub fn confidence_check_failed(&mut self) {
+        self.checking_confidence = false;
+    }
+
+    /// Sets the per-item result of a list translation.
+    pub fn set_list(&mut self, list: ListTranslation) {
+        self.list = Some(list);
+        self.list_retrying.clear();
+    }
+
+    /// Source text of a segment of the list translation.
+    pub fn list_source(&self, segment: usize) -> Option<&str> {
+        self.list.as_ref()?.source(segment)
+    }
+
+    /// Marks a segment of the list as being translated again.
+    pub fn start_list_retry(&mut self, segment: usize) {
+        self.list_retrying.push(segment);
+    }
+
+    /// Puts a retried list item into the translation.
+    pub fn set_list_item(&mut self, segment: usize, translation: String) {
+        self.list_retrying.retain(|&s| s != segment);
+        if let Some(list) = &mut self.list {
+            list.set(segment, translation);
+            self.translation.set(&list.text());
+            self.practice = None;
+            // The synthesized audio belongs to the previous translation
+            self.translation_audio_path = None;
+        }
+    }
+
+    /// Stops showing a list item as being retried.
+    pub fn list_retry_failed(&mut self, segment: usize) {
+        self.list_retrying.retain(|&s| s != segment);
+    }
+
+    /// Appends a chunk of the model's reasoning.
+    pub fn append_reasoning(&mut self, chunk: &str) {
+        self.reasoning.push_str(chunk);
+    }
+
+    /// The model's reasoning behind the current translation.
+    pub fn reasoning(&self) -> &str {
+        &self.reasoning
+    }
+
+    /// Sets whether the reasoning section is open.
+    pub fn set_reasoning_open(&mut self, open: bool) {
+        self.reasoning_open = open;
+    }
+
+    /// Clears the previous explanation before a new one streams in.
+    pub fn start_explanation(&mut self) {
+        self.explanation.clear();
+        self.explanation_error = None;
+        self.is_explaining = true;
+    }
+
+    /// Appends a chunk of the explanation.
+    pub fn update_explanation(&mut self, chunk: String) {
+        self.explanation.push_str(&chunk);
+    }
+
+    /// Sets whether an explanation is in progress.
+    pub fn set_explaining(&mut self, explaining: bool) {
+        self.is_explaining = explaining;
+    }
+
+    /// Sets an error message in place of the explanation.
+    pub fn set_explanation_error(&mut self, error: String) {
+        self.explanation_error = Some(error);
     }
 
     /// Clears the translation text.
     pub fn clear_translation(&mut self) {
         self.translation.clear();
+        if let Some(typewriter) = &mut self.typewriter {
+            typewriter.reset();
+        }
+        self.practice = None;
+        self.reasoning.clear();
+        self.explanation.clear();
+        self.explanation_error = None;
+        self.alternatives.clear();
+        self.list = None;
+        self.list_retrying.clear();
+        self.pivot = None;
+        self.truncated = false;
+        self.refused = false;
+        self.repetition_stopped = false;
+        self.legacy_cache = false;
+        self.typography_adjusted = 0;
+        self.alignment = None;
+        self.low_confidence = None;
+        self.checking_confidence = false;
+        self.font_warning = None;
+        self.warnings.clear();
+        self.same_language = None;
         self.error_message = None;
         // Clear audio paths when starting new translation
         self.source_audio_path = None;
         self.translation_audio_path = None;
     }
 
+    /// Turns practice mode on or off.
+    pub fn set_practice_mode(&mut self, enabled: bool) {
+        self.practice_mode = enabled;
+        self.practice = None;
+    }
+
+    /// Sets the comprehension rate shown with the practice controls.
+    pub fn set_practice_summary(&mut self, summary: Option<String>) {
+        self.practice_summary = summary;
+    }
+
+    /// Whether part of the translation is hidden for practice.
+    fn is_hidden(&self) -> bool {
+        self.practice_mode
+            && !self.translation.is_empty()
+            && self
+                .practice
+                .as_ref()
+                .is_none_or(|card| !card.is_revealed())
+    }
+
+    /// The translation as shown, with hidden practice sentences obscured.
+    fn visible_translation(&self) -> Cow<'_, str> {
+        if !self.practice_mode {
+            return Cow::Borrowed(self.translation.as_str());
+        }
+        match &self.practice {
+            Some(card) => Cow::Owned(card.masked(self.translation.as_str())),
+            // Still streaming, nothing is revealed yet
+            None => Cow::Owned(practice::obscure(self.translation.as_str())),
+        }
+    }
+
+    /// Warns that part of the translation can't be displayed, `None` hides the warning.
+    pub fn set_font_warning(&mut self, warning: Option<String>) {
+        self.font_warning = warning;
+    }
+
+    /// Adds a warning about the current translation.
+    pub fn add_warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Warnings raised while making the current translation.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Asks whether to translate a text that already seems to be in
+    /// `language`, offering to switch the target to `alternative`.
+    pub fn confirm_same_language(&mut self, language: String, alternative: String) {
+        self.same_language = Some((language, alternative));
+    }
+
     /// Sets whether a translation is in progress.
     pub fn set_translating(&mut self, translating: bool) {
         self.is_translating = translating;
+        // A finished translation is shown whole, and continuing it types
+        // only what is added
+        if !translating && let Some(typewriter) = &mut self.typewriter {
+            typewriter.finish(self.translation.as_str());
+        }
     }
 
     /// Sets an error message to display.
@@ -86,9 +603,89 @@ impl DisplayPanel {
 
     /// Updates the playback state
     pub fn set_playback_state(&mut self, state: PlaybackState) {
+        if !state.is_active() {
+            self.playback_progress = None;
+        }
         self.playback_state = state;
     }
 
+    /// Updates the position shown in the playback controls
+    pub fn set_playback_progress(&mut self, progress: Option<PlaybackProgress>) {
+        self.playback_progress = progress;
+    }
+
+    /// Sets the listening level shown in the volume popover
+    pub fn set_playback_volume(&mut self, volume: PlaybackVolume, adjustable: bool) {
+        self.playback_volume = volume;
+        self.volume_adjustable = adjustable;
+    }
+
+    /// Shows or hides the pronunciation comparison.
+    pub fn set_show_pronunciation(&mut self, show: bool) {
+        self.hide_pronunciation = !show;
+        if !show {
+            self.compare.stop_loop();
+        }
+    }
+
+    /// Sets which panels size their text for its dominant script, and the
+    /// multiplier of each script.
+    pub fn set_auto_font(
+        &mut self,
+        source: bool,
+        translation: bool,
+        scales: BTreeMap<Script, f32>,
+    ) {
+        self.auto_font_source = source;
+        self.auto_font_translation = translation;
+        self.font_scales = scales;
+    }
+
+    /// Reveals streaming translations at `chars_per_sec` characters a
+    /// second, or as they arrive with `None`.
+    pub fn set_smooth_typing(&mut self, chars_per_sec: Option<u32>) {
+        self.typewriter = chars_per_sec.map(Typewriter::new);
+    }
+
+    /// Shows the running translation whole as it arrives, without smooth
+    /// typing.
+    pub fn set_instant(&mut self, instant: bool) {
+        self.instant = instant;
+    }
+
+    /// Moves the smooth reveal of a streaming translation forward, asking
+    /// for another frame while it is behind.
+    fn advance_typing(&mut self, ctx: &Context) {
+        let Some(typewriter) = &mut self.typewriter else {
+            return;
+        };
+        if !self.is_translating {
+            return;
+        }
+        let text = self.translation.as_str();
+        if self.instant {
+            typewriter.finish(text);
+            return;
+        }
+        if typewriter.advance(text, Instant::now()) < text.len() {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Font size of the translation text for the base size `font_size`.
+    fn translation_font_size(&mut self, font_size: f32) -> f32 {
+        if !self.auto_font_translation {
+            return font_size;
+        }
+        self.translation_font
+            .font_size(self.translation.as_str(), font_size, &self.font_scales)
+    }
+
+    /// Stops the compare-audio A/B loop
+    pub fn stop_compare_loop(&mut self) {
+        self.compare.stop_loop();
+    }
+
     /// Gets whether source audio is converting
     pub fn is_source_converting(&self) -> bool {
         self.source_tts_converting
@@ -111,7 +708,8 @@ impl DisplayPanel {
         let button_text = if converting {
             "⏳ Converting".to_string()
         } else if let Some(path) = audio_path {
-            if matches!(self.playback_state, PlaybackState::Playing(ref p) if p == path) {
+            if matches!(self.playback_state, PlaybackState::Playing(ref p) | PlaybackState::Paused(ref p) if p == path)
+            {
                 "⏸ Stop".to_string()
             } else {
                 "▶ Play".to_string()
@@ -125,6 +723,103 @@ impl DisplayPanel {
         ui.add_enabled(enabled && !converting && audio_path.is_some(), button)
     }
 
+    /// Renders the mute toggle and volume slider popover, returning the new level if changed.
+    fn volume_ui(&mut self, ui: &mut Ui) -> Option<PlaybackVolume> {
+        let old = self.playback_volume;
+        let icon = if old.muted || old.level == 0.0 {
+            "🔇"
+        } else {
+            "🔉"
+        };
+
+        ui.menu_button(RichText::new(icon).size(12.0), |ui| {
+            ui.checkbox(&mut self.playback_volume.muted, "Mute");
+            if self.volume_adjustable {
+                let mut percent = self.playback_volume.level * 100.0;
+                if ui
+                    .add_enabled(
+                        !self.playback_volume.muted,
+                        Slider::new(&mut percent, 0.0..=100.0)
+                            .step_by(5.0)
+                            .suffix(" %"),
+                    )
+                    .changed()
+                {
+                    self.playback_volume.level = percent / 100.0;
+                }
+            }
+        })
+        .response
+        .on_hover_text("Listening volume");
+
+        (self.playback_volume != old).then_some(self.playback_volume)
+    }
+
+    /// Renders the playback controls of the audio being played.
+    ///
+    /// # Returns
+    ///
+    /// The position to seek to if one was chosen, and whether pause or
+    /// resume was clicked
+    fn transport_ui(&self, ui: &mut Ui, progress: PlaybackProgress) -> (Option<Duration>, bool) {
+        let mut seek = None;
+        let mut toggle_pause = false;
+        let paused = matches!(self.playback_state, PlaybackState::Paused(_));
+        ui.horizontal(|ui| {
+            if ui
+                .small_button("⏪")
+                .on_hover_text("Back 5 seconds")
+                .clicked()
+            {
+                seek = Some(progress.position.saturating_sub(SKIP_STEP));
+            }
+            let (icon, hint) = if paused {
+                ("▶", "Resume")
+            } else {
+                ("⏸", "Pause")
+            };
+            if ui.small_button(icon).on_hover_text(hint).clicked() {
+                toggle_pause = true;
+            }
+            if ui
+                .small_button("⏩")
+                .on_hover_text("Forward 5 seconds")
+                .clicked()
+            {
+                seek = Some(progress.position + SKIP_STEP);
+            }
+            ui.label(
+                RichText::new(format!(
+                    "{} / {}",
+                    format_time(progress.position),
+                    format_time(progress.duration)
+                ))
+                .size(12.0)
+                .monospace(),
+            );
+
+            ui.spacing_mut().slider_width = (ui.available_width() - 60.0).max(80.0);
+            let mut seconds = progress.position.as_secs_f32();
+            if ui
+                .add(
+                    Slider::new(&mut seconds, 0.0..=progress.duration.as_secs_f32())
+                        .show_value(false),
+                )
+                .on_hover_text("Drag to seek")
+                .changed()
+            {
+                seek = Some(Duration::from_secs_f32(seconds));
+            }
+            ui.label(
+                RichText::new(format!("-{}", format_time(progress.remaining())))
+                    .size(12.0)
+                    .monospace()
+                    .weak(),
+            );
+        });
+        (seek, toggle_pause)
+    }
+
     /// Creates a styled frame for text display.
     fn create_text_frame(&self, ui: &Ui) -> Frame {
         Frame::NONE
@@ -142,132 +837,1240 @@ impl DisplayPanel {
             .corner_radius(8.0)
     }
 
+    /// Marks the translation as declined by the provider.
+    pub fn set_refused(&mut self, refused: bool) {
+        self.refused = refused;
+    }
+
+    /// Says that the provider declined the text and what is shown instead.
+    fn refused_banner_ui(&self, ui: &mut Ui) {
+        Frame::NONE
+            .fill(ui.visuals().error_fg_color.gamma_multiply(0.15))
+            .corner_radius(6.0)
+            .inner_margin(Margin::symmetric(12, 8))
+            .show(ui, |ui| {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    "⛔ The provider declined to translate this content.",
+                );
+                if !self.translation.is_empty() {
+                    ui.label(
+                        RichText::new(
+                            "The text below is what arrived before it stopped, not a translation.",
+                        )
+                        .size(12.0)
+                        .weak(),
+                    );
+                }
+            });
+    }
+
+    /// Warns that the translation is incomplete, returning whether "Continue" was clicked.
+    fn truncated_banner_ui(&self, ui: &mut Ui) -> bool {
+        let mut clicked = false;
+        Frame::NONE
+            .fill(ui.visuals().warn_fg_color.gamma_multiply(0.15))
+            .corner_radius(6.0)
+            .inner_margin(Margin::symmetric(12, 8))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "⚠ The translation hit the output limit and is incomplete.",
+                    );
+                    if ui
+                        .button("▶ Continue")
+                        .on_hover_text("Request the rest of the translation")
+                        .clicked()
+                    {
+                        clicked = true;
+                    }
+                });
+            });
+        clicked
+    }
+
+    /// Says that the translation was stopped early, returning whether
+    /// "Retry" was clicked.
+    fn repetition_banner_ui(&self, ui: &mut Ui) -> bool {
+        let mut clicked = false;
+        Frame::NONE
+            .fill(ui.visuals().warn_fg_color.gamma_multiply(0.15))
+            .corner_radius(6.0)
+            .inner_margin(Margin::symmetric(12, 8))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "🔂 Output repetition detected — stopped early",
+                    );
+                    if ui
+                        .button("↻ Retry")
+                        .on_hover_text("Translate again at a lower temperature")
+                        .clicked()
+                    {
+                        clicked = true;
+                    }
+                });
+            });
+        clicked
+    }
+
+    /// Warns about list items that could not be mapped back, returning the
+    /// item whose "Retry" was clicked.
+    fn list_warning_ui(&self, ui: &mut Ui) -> Option<usize> {
+        let list = self.list.as_ref().filter(|list| list.is_mismatched())?;
+        let mut retry = None;
+        Frame::NONE
+            .fill(ui.visuals().warn_fg_color.gamma_multiply(0.15))
+            .corner_radius(6.0)
+            .inner_margin(Margin::symmetric(12, 8))
+            .show(ui, |ui| {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    "⚠ Part of the response could not be mapped back onto the list.",
+                );
+                if list.unexpected() > 0 {
+                    ui.label(
+                        RichText::new(format!(
+                            "{} numbered parts of the response were left out because they don't match the source.",
+                            list.unexpected()
+                        ))
+                        .weak(),
+                    );
+                }
+                for segment in list.missing() {
+                    ui.horizontal(|ui| {
+                        let source = list.source(segment).unwrap_or_default();
+                        let preview: String = source.chars().take(60).collect();
+                        ui.label(format!("{} {} (kept untranslated)", list.label(segment), preview));
+                        if self.list_retrying.contains(&segment) {
+                            ui.spinner();
+                        } else if ui
+                            .small_button("🔄Retry")
+                            .on_hover_text("Translate this item on its own")
+                            .clicked()
+                        {
+                            retry = Some(segment);
+                        }
+                    });
+                }
+            });
+        retry
+    }
+
+    /// Renders the collapsible list of warnings about the translation.
+    fn warnings_ui(&self, ui: &mut Ui, font_size: f32) {
+        let count = self.warnings.len();
+        CollapsingHeader::new(
+            RichText::new(format!(
+                "⚠ {} warning{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            ))
+            .color(ui.visuals().warn_fg_color)
+            .size(font_size * 0.8),
+        )
+        .id_salt("warnings")
+        .default_open(false)
+        .show(ui, |ui| {
+            for warning in &self.warnings {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(
+                        RichText::new(format!("{}:", warning.kind.label()))
+                            .size(font_size * 0.8)
+                            .strong(),
+                    );
+                    ui.label(RichText::new(&warning.text).size(font_size * 0.8));
+                });
+            }
+        });
+    }
+
+    /// Offers a font for characters that can't be displayed, returning
+    /// whether "Load font" was clicked.
+    fn font_banner_ui(&mut self, ui: &mut Ui) -> bool {
+        let Some(warning) = &self.font_warning else {
+            return false;
+        };
+        let mut load = false;
+        let mut dismiss = false;
+        Frame::NONE
+            .fill(ui.visuals().warn_fg_color.gamma_multiply(0.15))
+            .corner_radius(6.0)
+            .inner_margin(Margin::symmetric(12, 8))
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("🔡 {}", warning));
+                    if ui
+                        .button("📂Load font…")
+                        .on_hover_text("Choose a .ttf or .otf file that covers these characters")
+                        .clicked()
+                    {
+                        load = true;
+                    }
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        dismiss = true;
+                    }
+                });
+            });
+        if dismiss {
+            self.font_warning = None;
+        }
+        load
+    }
+
+    /// Asks what to do with a text already in the target language.
+    ///
+    /// # Returns
+    ///
+    /// Whether "Translate anyway" was clicked, and the target language to
+    /// switch to if that was clicked instead
+    fn same_language_banner_ui(&mut self, ui: &mut Ui) -> (bool, Option<String>) {
+        let Some((language, alternative)) = &self.same_language else {
+            return (false, None);
+        };
+        let mut translate = false;
+        let mut switch = None;
+        let mut dismiss = false;
+        Frame::NONE
+            .fill(ui.visuals().warn_fg_color.gamma_multiply(0.15))
+            .corner_radius(6.0)
+            .inner_margin(Margin::symmetric(12, 8))
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("🔤 The text already looks like {}.", language),
+                    );
+                    if ui.button("Translate anyway").clicked() {
+                        translate = true;
+                    }
+                    if ui
+                        .button(format!("Switch target to {}", alternative))
+                        .on_hover_text("Change the target language and translate")
+                        .clicked()
+                    {
+                        switch = Some(alternative.clone());
+                    }
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        dismiss = true;
+                    }
+                });
+            });
+        if translate || switch.is_some() || dismiss {
+            self.same_language = None;
+        }
+        (translate, switch)
+    }
+
+    /// Renders the alternatives as selectable rows, returning the clicked one.
+    fn alternatives_ui(&self, ui: &mut Ui, font_size: f32) -> Option<usize> {
+        let mut clicked = None;
+        ui.label(
+            RichText::new("🔀Alternatives")
+                .strong()
+                .size(font_size * 0.9),
+        );
+        for (i, alternative) in self.alternatives.iter().enumerate() {
+            let mut text =
+                RichText::new(format!("{}. {}", i + 1, alternative.text)).size(font_size);
+            let selected = alternative.text == self.translation.as_str();
+            if selected {
+                text = text.strong();
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .selectable_label(selected, text)
+                    .on_hover_text("Use this translation")
+                    .clicked()
+                    && !selected
+                {
+                    clicked = Some(i);
+                }
+                if !alternative.gloss.is_empty() {
+                    ui.label(
+                        RichText::new(&alternative.gloss)
+                            .size(font_size * 0.8)
+                            .weak(),
+                    );
+                }
+            });
+        }
+        clicked
+    }
+
+    /// Renders the collapsible intermediate text of a pivot translation.
+    fn pivot_ui(&self, ui: &mut Ui, font_size: f32, language: &str, mut text: &str) {
+        sync_section(ui, "pivot", false);
+        CollapsingHeader::new(
+            RichText::new(format!("🔁Via {}", language))
+                .strong()
+                .size(font_size * 0.9),
+        )
+        .id_salt("pivot")
+        .default_open(false)
+        .show(ui, |ui| {
+            self.create_text_frame(ui).show(ui, |ui| {
+                ScrollArea::vertical()
+                    .max_height(240.0)
+                    .id_salt("pivot_scroll")
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        TextEdit::multiline(&mut text)
+                            .font(FontId::new(font_size * 0.9, FontFamily::Proportional))
+                            .desired_width(f32::INFINITY)
+                            .frame(false)
+                            .show(ui);
+                    });
+            });
+        });
+    }
+
+    /// Renders the collapsible reasoning, returning its new open state
+    /// when the header was clicked.
+    fn reasoning_ui(&self, ui: &mut Ui, font_size: f32) -> Option<bool> {
+        let response = CollapsingHeader::new(
+            RichText::new(format!(
+                "🧠Reasoning · {} chars",
+                self.reasoning.chars().count()
+            ))
+            .strong()
+            .size(font_size * 0.9),
+        )
+        .id_salt("reasoning")
+        .open(Some(self.reasoning_open))
+        .show(ui, |ui| {
+            if ui
+                .small_button("📋Copy")
+                .on_hover_text("Copy the reasoning")
+                .clicked()
+            {
+                ui.ctx().copy_text(self.reasoning.clone());
+            }
+            self.create_text_frame(ui).show(ui, |ui| {
+                ScrollArea::vertical()
+                    .max_height(240.0)
+                    .id_salt("reasoning_scroll")
+                    .auto_shrink([false, true])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        TextEdit::multiline(&mut self.reasoning.as_str())
+                            .font(FontId::new(font_size * 0.85, FontFamily::Proportional))
+                            .desired_width(f32::INFINITY)
+                            .frame(false)
+                            .show(ui);
+                    });
+            });
+        });
+        response
+            .header_response
+            .clicked()
+            .then_some(!self.reasoning_open)
+    }
+
+    /// Renders the collapsible explanation, returning whether "Stop" was clicked.
+    fn explanation_ui(&self, ui: &mut Ui, font_size: f32) -> bool {
+        let mut cancel = false;
+        sync_section(ui, "explanation", true);
+        CollapsingHeader::new(
+            RichText::new("💡Explanation")
+                .strong()
+                .size(font_size * 0.9),
+        )
+        .id_salt("explanation")
+        .default_open(true)
+        .show(ui, |ui| {
+            if self.is_explaining
+                && ui
+                    .small_button("⏹Stop")
+                    .on_hover_text("Stop the explanation, the translation is kept")
+                    .clicked()
+            {
+                cancel = true;
+            }
+
+            if let Some(error) = &self.explanation_error {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    RichText::new(format!("❌ Error: {}", error)).size(font_size * 0.9),
+                );
+            } else if self.explanation.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(
+                        RichText::new("Explaining...")
+                            .size(font_size * 0.9)
+                            .color(ui.visuals().weak_text_color()),
+                    );
+                });
+            } else {
+                self.create_text_frame(ui).show(ui, |ui| {
+                    ScrollArea::vertical()
+                        .max_height(240.0)
+                        .id_salt("explanation_scroll")
+                        .auto_shrink([false, true])
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            TextEdit::multiline(&mut self.explanation.as_str())
+                                .font(FontId::new(font_size * 0.9, FontFamily::Proportional))
+                                .desired_width(f32::INFINITY)
+                                .frame(false)
+                                .show(ui);
+                        });
+                });
+            }
+        });
+        cancel
+    }
+
+    /// Renders the reveal and self-grading controls of practice mode,
+    /// returning the grade given to the last revealed item.
+    fn practice_ui(&mut self, ui: &mut Ui, font_size: f32) -> Option<Grade> {
+        let card = self.practice.as_mut()?;
+        let mut grade = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🎓Practice").strong().size(font_size * 0.9));
+            if !card.is_revealed() {
+                if ui
+                    .button("👁Reveal")
+                    .on_hover_text("Show the whole translation")
+                    .clicked()
+                {
+                    card.reveal_all();
+                }
+                if card.sentence_count() > 1
+                    && ui
+                        .button("➡Next sentence")
+                        .on_hover_text("Show one more sentence")
+                        .clicked()
+                {
+                    card.reveal_next();
+                }
+            }
+
+            if let Some(item) = card.pending_item() {
+                let label = if item.sentences.len() == card.sentence_count() {
+                    "Did you understand it?".to_string()
+                } else if item.sentences.len() == 1 {
+                    format!(
+                        "Sentence {} of {}:",
+                        item.sentences.start + 1,
+                        card.sentence_count()
+                    )
+                } else {
+                    format!(
+                        "Sentences {}–{}:",
+                        item.sentences.start + 1,
+                        item.sentences.end
+                    )
+                };
+                ui.label(RichText::new(label).size(font_size * 0.8));
+                if ui.button("✔Got it").clicked() {
+                    grade = Some(Grade::GotIt);
+                }
+                if ui.button("✖Missed it").clicked() {
+                    grade = Some(Grade::MissedIt);
+                }
+            }
+        });
+        if let Some(summary) = &self.practice_summary {
+            ui.label(
+                RichText::new(format!("Comprehension: {}", summary))
+                    .size(12.0)
+                    .weak(),
+            );
+        }
+
+        grade.filter(|grade| card.grade(*grade))
+    }
+
+    /// Finds the links of a finished translation that changed.
+    fn refresh_links(&mut self) {
+        if self.is_translating || self.links.0 == self.translation.as_str() {
+            return;
+        }
+        let text = self.translation.as_str().to_owned();
+        let found = links::find_links(&text);
+        self.links = (text, found);
+    }
+
+    /// Row of clickable links found in the finished translation.
+    fn links_ui(&self, ui: &mut Ui, font_size: f32) {
+        let text = &self.links.0;
+        ui.add_space(4.0);
+        ui.horizontal_wrapped(|ui| {
+            ui.label(
+                RichText::new("🔗")
+                    .size(font_size * 0.8)
+                    .color(ui.visuals().weak_text_color()),
+            );
+            let mut shown: Vec<&str> = Vec::new();
+            for link in &self.links.1 {
+                if shown.contains(&link.target.as_str()) {
+                    continue;
+                }
+                shown.push(&link.target);
+                ui.add(Hyperlink::from_label_and_url(
+                    RichText::new(&text[link.range.clone()]).size(font_size * 0.8),
+                    &link.target,
+                ))
+                .on_hover_text(&link.target);
+            }
+        });
+    }
+
+    /// Renders the translation text, or its loading, error or empty state.
+    ///
+    /// # Returns
+    ///
+    /// Where the paragraph still streaming was drawn, while there is one
+    fn translation_text_ui(&self, ui: &mut Ui, font_size: f32) -> Option<Rect> {
+        // Whole, even while it streams in as several labels or is typed out
+        if !self.translation.is_empty() && self.error_message.is_none() {
+            announce::expose_text(
+                ui.ctx(),
+                Id::new(TRANSLATION_TEXT_ID),
+                "Translation",
+                &self.visible_translation(),
+                self.is_translating,
+            );
+        }
+        let align = if self.translation_direction().is_rtl() {
+            Align::RIGHT
+        } else {
+            Align::LEFT
+        };
+
+        if let Some(error) = &self.error_message {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                RichText::new(format!("❌ Error: {}", error)).size(font_size),
+            );
+        } else if self.is_translating && self.translation.is_empty() {
+            // Show loading indicator while translating
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(
+                    RichText::new("Translating...")
+                        .size(font_size)
+                        .color(ui.visuals().weak_text_color()),
+                );
+            });
+        } else if self.translation.is_empty() {
+            // Show placeholder when empty
+            let display_text = "Translation will appear here...";
+            ui.colored_label(
+                ui.visuals().weak_text_color(),
+                RichText::new(display_text).size(font_size * 0.9).italics(),
+            );
+        } else if self.is_translating && !self.practice_mode {
+            return Some(self.streaming_text_ui(ui, font_size, align));
+        } else {
+            // Show the partial or completed translation
+            let mut display_text = self.visible_translation().into_owned();
+            let output = TextEdit::multiline(&mut display_text)
+                .font(FontId::new(font_size, FontFamily::Proportional))
+                .horizontal_align(align)
+                .desired_width(f32::INFINITY)
+                .desired_rows(5)
+                .frame(false)
+                .lock_focus(true)
+                .show(ui);
+            if !self.is_translating && !self.practice_mode {
+                self.alignment_click(ui, &output);
+                self.low_confidence_ui(ui, &output);
+            }
+            if !self.is_translating
+                && !self.is_hidden()
+                && !self.links.1.is_empty()
+                && self.links.0 == self.translation.as_str()
+            {
+                self.links_ui(ui, font_size);
+            }
+        }
+        None
+    }
+
+    /// Picks up a double-click on a word of the finished translation, and
+    /// says what became of the last one in the text's tooltip.
+    fn alignment_click(&self, ui: &Ui, output: &text_edit::TextEditOutput) {
+        if output.response.double_clicked()
+            && let Some(pos) = output.response.interact_pointer_pos()
+        {
+            let text = self.translation.as_str();
+            let cursor = output.galley.cursor_from_pos(pos - output.galley_pos);
+            let offset = text
+                .char_indices()
+                .nth(cursor.index)
+                .map_or(text.len(), |(i, _)| i);
+            if let Some(word) = alignment::word_at(text, offset) {
+                let word = text[word].to_string();
+                ui.data_mut(|d| d.insert_temp(Id::new(ALIGN_WORD_ID), word));
+            }
+        }
+
+        let note = match &self.alignment {
+            Some(AlignmentView::Pending { word }) => {
+                format!("Looking for the source of \"{}\"…", word)
+            }
+            Some(AlignmentView::Failed { word, reason }) => {
+                format!("Couldn't find the source of \"{}\": {}", word, reason)
+            }
+            _ => return,
+        };
+        output.response.clone().on_hover_text(note);
+    }
+
+    /// The stretches of the shown translation the model is unsure of.
+    fn shown_low_confidence(&self) -> &[LowConfidence] {
+        match &self.low_confidence {
+            Some((text, spans)) if text == self.translation.as_str() => spans,
+            _ => &[],
+        }
+    }
+
+    /// Underlines the stretches the model is unsure of with amber dots, and
+    /// gives the reason of the one under the pointer.
+    fn low_confidence_ui(&self, ui: &Ui, output: &text_edit::TextEditOutput) {
+        let spans = self.shown_low_confidence();
+        if spans.is_empty() {
+            return;
+        }
+        let text = self.translation.as_str();
+        let char_index = |byte: usize| text[..byte].chars().count();
+        let hovered = output
+            .response
+            .hover_pos()
+            .map(|pos| output.galley.cursor_from_pos(pos - output.galley_pos).index);
+        let color = ui.visuals().warn_fg_color;
+        let offset = output.galley_pos.to_vec2();
+        for span in spans {
+            let chars = char_index(span.range.start)..char_index(span.range.end);
+            // One dotted line per row the span is laid out on
+            let mut rows: Vec<Rect> = Vec::new();
+            for index in chars.start..=chars.end {
+                let at = output.galley.pos_from_cursor(text::CCursor::new(index));
+                match rows.last_mut() {
+                    Some(row) if (row.max.y - at.max.y).abs() < 0.5 => *row = row.union(at),
+                    _ => rows.push(at),
+                }
+            }
+            for row in rows.iter().filter(|row| row.width() > 0.0) {
+                let row = row.translate(offset);
+                ui.painter().extend(Shape::dotted_line(
+                    &[pos2(row.min.x, row.max.y), pos2(row.max.x, row.max.y)],
+                    color,
+                    3.0,
+                    0.8,
+                ));
+            }
+            if hovered.is_some_and(|index| chars.contains(&index)) {
+                let reason = if span.reason.is_empty() {
+                    "The model is unsure of this part"
+                } else {
+                    span.reason.as_str()
+                };
+                output.response.clone().on_hover_text_at_pointer(reason);
+            }
+        }
+    }
+
+    /// Lays out the source text with the phrase found for a translated word
+    /// highlighted, if it is still there.
+    fn source_layout_job(&self, ui: &Ui, text: &str, font_size: f32) -> Option<text::LayoutJob> {
+        let Some(AlignmentView::Found {
+            range,
+            phrase,
+            exact,
+            ..
+        }) = &self.alignment
+        else {
+            return None;
+        };
+        if text.get(range.clone()) != Some(phrase.as_str()) {
+            return None;
+        }
+        let color = ui
+            .visuals()
+            .override_text_color
+            .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+        let plain = TextFormat::simple(FontId::new(font_size, FontFamily::Proportional), color);
+        let highlight_color = if *exact {
+            ui.visuals().selection.bg_fill
+        } else {
+            ui.visuals().warn_fg_color.gamma_multiply(0.35)
+        };
+        let highlighted = TextFormat {
+            background: highlight_color,
+            ..plain.clone()
+        };
+        let mut job = text::LayoutJob::default();
+        job.append(&text[..range.start], 0.0, plain.clone());
+        job.append(&text[range.clone()], 0.0, highlighted);
+        job.append(&text[range.end..], 0.0, plain);
+        Some(job)
+    }
+
+    /// Header note naming the highlighted source phrase, returning whether
+    /// it was dismissed.
+    fn alignment_badge_ui(&self, ui: &mut Ui) -> bool {
+        let Some(AlignmentView::Found { word, exact, .. }) = &self.alignment else {
+            return false;
+        };
+        ui.label(RichText::new(format!("🔗 \"{}\"", word)).size(12.0).weak())
+            .on_hover_text("The highlighted phrase was translated as this word");
+        if !exact {
+            ui.label(
+                RichText::new("≈Best guess")
+                    .size(12.0)
+                    .color(ui.visuals().warn_fg_color),
+            )
+            .on_hover_text("The answer isn't in the source as written; this is the closest phrase");
+        }
+        ui.small_button("✖")
+            .on_hover_text("Remove the highlight")
+            .clicked()
+    }
+
+    /// Renders a translation that is still streaming, returning where its
+    /// last paragraph was drawn.
+    ///
+    /// The completed paragraphs don't change any more, so their layout is
+    /// reused from the previous frame and only the tail is laid out again.
+    /// With smooth typing only the revealed part is drawn, the paragraphs
+    /// still being typed as one.
+    fn streaming_text_ui(&self, ui: &mut Ui, font_size: f32, align: Align) -> Rect {
+        let text = self.translation.as_str();
+        let head = self.translation.head();
+        let revealed = self.typewriter.as_ref().map_or(text.len(), |typewriter| {
+            typewriter.revealed().min(text.len())
+        });
+        ui.with_layout(Layout::top_down(align), |ui| {
+            ui.spacing_mut().item_spacing.y = 0.0;
+            if revealed < head.len() {
+                return ui
+                    .add(Label::new(RichText::new(&text[..revealed]).size(font_size)).wrap())
+                    .rect;
+            }
+            // The tail starts on the line after the last blank one
+            if let Some(completed) = head.strip_suffix('\n') {
+                ui.add(Label::new(RichText::new(completed).size(font_size)).wrap());
+            }
+            let tail = &text[head.len()..revealed];
+            ui.add(Label::new(RichText::new(tail).size(font_size)).wrap())
+                .rect
+        })
+        .inner
+    }
+
+    /// Shows the translation in a scroll area that follows the stream.
+    ///
+    /// A finished translation sticks to the bottom. While streaming, the view
+    /// only moves when the last paragraph runs past the bottom: it then jumps
+    /// to the start of that paragraph, leaving room for it to grow, and
+    /// follows its end once it is taller than the view. Scrolling away from
+    /// the end stops the following until the user returns.
+    fn translation_scroll_ui(
+        &self,
+        ui: &mut Ui,
+        id_salt: &str,
+        max_height: f32,
+        add_contents: impl FnOnce(&mut Ui) -> Option<Rect>,
+    ) {
+        let following_id = Id::new(id_salt).with("following");
+        let following = ui.data(|d| d.get_temp(following_id)).unwrap_or(true);
+        // A restored position is applied once the new content was measured
+        let restore_id = Id::new(id_salt).with("restore");
+        let measured_id = Id::new(id_salt).with("measured");
+        let restore: Option<f32> = ui.data(|d| d.get_temp(restore_id));
+        let measured: Option<f32> = ui.data(|d| d.get_temp(measured_id));
+
+        let mut area = ScrollArea::vertical()
+            .max_height(max_height)
+            .id_salt(id_salt)
+            .auto_shrink([false, false])
+            .stick_to_bottom(!self.is_translating && restore.is_none());
+        if let (Some(fraction), Some(max_offset)) = (restore, measured) {
+            area = area.vertical_scroll_offset(fraction * max_offset);
+            ui.data_mut(|d| {
+                d.remove_temp::<f32>(restore_id);
+                d.remove_temp::<f32>(measured_id);
+            });
+        }
+        let output = area.show(ui, |ui| {
+            let Some(tail) = add_contents(ui) else {
+                return;
+            };
+            if !self.is_translating || !following {
+                return;
+            }
+            let view = ui.clip_rect();
+            if ui.min_rect().height() > view.height() {
+                // Room under the tail, so its start can be scrolled to the top
+                ui.add_space((view.height() - tail.height()).max(0.0));
+            }
+            if tail.bottom() > view.bottom() {
+                if tail.height() <= view.height() {
+                    ui.scroll_to_rect(tail, Some(Align::TOP));
+                } else {
+                    let end = Rect::from_min_max(pos2(tail.left(), tail.bottom()), tail.max);
+                    ui.scroll_to_rect(end, Some(Align::BOTTOM));
+                }
+            }
+        });
+
+        let max_offset = output.content_size.y - output.inner_rect.height();
+        let at_end = output.state.offset.y >= max_offset - 1.0;
+        let fraction = if max_offset > 0.0 {
+            (output.state.offset.y / max_offset).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        ui.data_mut(|d| {
+            d.insert_temp(following_id, at_end);
+            d.insert_temp(Id::new(id_salt).with("fraction"), fraction);
+            if restore.is_some() && measured.is_none() {
+                d.insert_temp(measured_id, max_offset.max(0.0));
+            }
+        });
+    }
+
+    /// Copy button for the translation. While streaming it copies the
+    /// completed paragraphs only.
+    fn copy_button_ui(&self, ui: &mut Ui) {
+        let (text, hover) = if self.is_translating {
+            (
+                self.translation.completed(),
+                "Copy the completed paragraphs, the one still streaming is left out",
+            )
+        } else {
+            (self.translation.as_str(), "Copy the translation")
+        };
+        let btn = Button::new(RichText::new("📋Copy").size(12.0)).corner_radius(6.0);
+        if ui
+            .add_enabled(!text.is_empty() && !self.is_hidden(), btn)
+            .on_hover_text(hover)
+            .clicked()
+        {
+            ui.ctx().copy_text(text.to_owned());
+        }
+    }
+
+    /// Share menu for the source and translation pair, enabled once the
+    /// translation is complete.
+    fn share_menu_ui(&self, ui: &mut Ui, actions: &mut DisplayActions) {
+        let enabled = !self.is_translating && !self.translation.is_empty() && !self.is_hidden();
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.menu_button(RichText::new("📤Share").size(12.0), |ui| {
+                let source = self.input_text.as_str();
+                let translation = self.translation.as_str();
+                if ui
+                    .button("Copy as Markdown quote")
+                    .on_hover_text("Source and translation as labeled quote blocks")
+                    .clicked()
+                {
+                    let quote = share::markdown_quote(source, translation, &self.target_language);
+                    ui.ctx().copy_text(quote);
+                    ui.close();
+                }
+                let line = share::arrow_line(source, translation);
+                if ui
+                    .add_enabled(line.is_some(), Button::new("Copy as one line"))
+                    .on_hover_text("\"source → translation\"")
+                    .on_disabled_hover_text("Only for short single-line texts")
+                    .clicked()
+                    && let Some(line) = line
+                {
+                    ui.ctx().copy_text(line);
+                    ui.close();
+                }
+                if ui
+                    .button("Copy as image")
+                    .on_hover_text(
+                        "A picture of the pair, saved as a file where it can't be copied",
+                    )
+                    .clicked()
+                {
+                    actions.share_image = true;
+                    ui.close();
+                }
+            });
+        });
+    }
+
+    /// Renders the contents of the pop-out translation window.
+    ///
+    /// `text_size` is the font size of the translation text itself.
+    fn popout_contents_ui(&self, ui: &mut Ui, font_size: f32, text_size: f32) {
+        let (row_layout, buttons_layout) = header_layouts(self.translation_direction());
+        let row_size = vec2(ui.available_width(), ui.spacing().interact_size.y);
+        ui.allocate_ui_with_layout(row_size, row_layout, |ui| {
+            ui.label(
+                RichText::new("🌐Translation")
+                    .strong()
+                    .size(font_size * 1.1),
+            );
+            ui.with_layout(buttons_layout, |ui| self.copy_button_ui(ui));
+        });
+        ui.add_space(8.0);
+
+        self.create_text_frame(ui).show(ui, |ui| {
+            self.translation_scroll_ui(ui, "popout_translation_scroll", f32::INFINITY, |ui| {
+                self.translation_text_ui(ui, text_size)
+            });
+        });
+    }
+
+    /// Opens the translation in a separate window, at `geometry` if known.
+    pub fn pop_out(&mut self, geometry: Option<WindowGeometry>) {
+        let mut builder = ViewportBuilder::default()
+            .with_title("Translation")
+            .with_inner_size([640.0, 480.0])
+            .with_min_inner_size([240.0, 160.0]);
+        if let Some(geometry) = geometry {
+            builder = builder
+                .with_position([geometry.x, geometry.y])
+                .with_inner_size([geometry.width, geometry.height]);
+        }
+        self.popout = Some(builder);
+    }
+
+    /// Shows the pop-out translation window while it is open.
+    ///
+    /// The window renders the same state as the main view, so streamed chunks
+    /// reach both without being processed twice. Closing it returns the
+    /// translation to the main window.
+    ///
+    /// # Returns
+    ///
+    /// The window's current geometry, if it is open and the platform reports it
+    pub fn popout_ui(&mut self, ctx: &Context, font_size: f32) -> Option<WindowGeometry> {
+        let builder = self.popout.clone()?;
+        let mut close = false;
+        let text_size = self.translation_font_size(font_size);
+        self.refresh_links();
+
+        let geometry = ctx.show_viewport_immediate(
+            ViewportId::from_hash_of(POPOUT_VIEWPORT),
+            builder,
+            |ctx, class| {
+                if class == ViewportClass::Embedded {
+                    // Without multi-window support, float it inside the main window
+                    let mut open = true;
+                    Window::new("🌐Translation")
+                        .open(&mut open)
+                        .default_size([480.0, 360.0])
+                        .show(ctx, |ui| self.popout_contents_ui(ui, font_size, text_size));
+                    close = !open;
+                    return None;
+                }
+
+                CentralPanel::default()
+                    .show(ctx, |ui| self.popout_contents_ui(ui, font_size, text_size));
+                ctx.input(|i| {
+                    let viewport = i.viewport();
+                    close = viewport.close_requested();
+                    viewport
+                        .outer_rect
+                        .zip(viewport.inner_rect)
+                        .map(|(outer, inner)| WindowGeometry {
+                            x: outer.min.x,
+                            y: outer.min.y,
+                            width: inner.width(),
+                            height: inner.height(),
+                        })
+                })
+            },
+        );
+
+        if close {
+            self.popout = None;
+        }
+        geometry
+    }
+
     /// Renders the display panel UI.
     ///
     /// # Arguments
     ///
     /// * `ctx` - The egui context
     /// * `font_size` - Font size for text display
+    /// * `layout` - How the source text panel is shown
+    /// * `source_text` - The sidebar's source text, also edited here in `Editable` layout
     ///
     /// # Returns
     ///
-    /// (play_source_clicked, source_audio_to_play, play_translation_clicked, translation_audio_to_play,
-    ///  start_source_tts, start_translation_tts, cancel_source_tts, cancel_translation_tts)
+    /// The user actions triggered this frame
     pub fn ui(
         &mut self,
         ctx: &Context,
         font_size: f32,
-    ) -> (
-        bool,
-        Option<String>,
-        bool,
-        Option<String>,
-        bool,
-        bool,
-        bool,
-        bool,
-    ) {
-        let mut play_source_clicked = false;
-        let mut source_audio_to_play = None;
-        let mut play_translation_clicked = false;
-        let mut translation_audio_to_play = None;
-        let mut start_source_tts = false;
-        let mut start_translation_tts = false;
-        let mut cancel_source_tts = false;
-        let mut cancel_translation_tts = false;
+        layout: SourcePanelLayout,
+        source_text: &mut SharedText,
+    ) -> DisplayActions {
+        let mut actions = DisplayActions::default();
+        let mut return_popout = false;
+        let mut clear_alignment = false;
+        let direction = self.translation_direction();
+        let translation_font_size = self.translation_font_size(font_size);
+        self.refresh_links();
+        self.advance_typing(ctx);
+        self.apply_pending_view(ctx);
+        let source_font_size = if self.auto_font_source {
+            let text = match layout {
+                SourcePanelLayout::Editable => source_text.as_str(),
+                _ => self.input_text.as_str(),
+            };
+            self.source_font
+                .font_size(text, font_size, &self.font_scales)
+        } else {
+            font_size
+        };
 
         CentralPanel::default().show(ctx, |ui| {
             ui.add_space(16.0);
 
             // Calculate responsive heights based on available space
             let available_height = ui.available_height() - 20.0;
-            let panel_height = (available_height / 2.0).max(150.0) - 16.0; // Ensure minimum height
+            let panel_height = if layout == SourcePanelLayout::Hidden {
+                // Translation takes the full height
+                (available_height - 40.0).max(150.0)
+            } else {
+                (available_height / 2.0).max(150.0) - 16.0 // Ensure minimum height
+            };
 
-            ui.vertical(|ui| {
-                // Source Text section with audio controls
-                ui.horizontal(|ui| {
-                    ui.label(
-                        RichText::new("📄Source Text")
-                            .strong()
-                            .size(font_size * 1.1),
-                    );
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.add_space(8.0);
+            // In the editable layout the panel shows the live sidebar text
+            let source_has_text = match layout {
+                SourcePanelLayout::Editable => !source_text.as_str().trim().is_empty(),
+                _ => !self.input_text.trim().is_empty(),
+            };
 
-                        // TTS Convert button (always enabled)
-                        if !self.source_tts_converting && !self.input_text.trim().is_empty() {
-                            let btn = egui::Button::new(RichText::new("🔊Convert").size(12.0))
-                                .corner_radius(6.0);
-                            if ui.add(btn).on_hover_text("Convert text to audio").clicked() {
-                                start_source_tts = true;
+            ui.vertical(|ui| {
+                if layout != SourcePanelLayout::Hidden {
+                    // Source Text section with audio controls
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new("📄Source Text")
+                                .strong()
+                                .size(font_size * 1.1),
+                        );
+                        clear_alignment = self.alignment_badge_ui(ui);
+                        if layout == SourcePanelLayout::Mirror {
+                            ui.label(RichText::new("🔒Read-only").size(12.0).weak());
+                            if ui
+                                .small_button("✏Edit in sidebar")
+                                .on_hover_text("Move the cursor to the sidebar's source text")
+                                .clicked()
+                            {
+                                ui.ctx().memory_mut(|mem| {
+                                    mem.request_focus(Id::new(sidebar::SOURCE_TEXT_ID))
+                                });
                             }
                         }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.add_space(8.0);
 
-                        ui.add_space(8.0);
+                            // TTS Convert button (always enabled)
+                            if !self.source_tts_converting && source_has_text {
+                                let btn = egui::Button::new(RichText::new("🔊Convert").size(12.0))
+                                    .corner_radius(6.0);
+                                if ui.add(btn).on_hover_text("Convert text to audio").clicked() {
+                                    actions.start_source_tts = true;
+                                }
+                            }
 
-                        // Cancel TTS button (only shown during conversion)
-                        if self.source_tts_converting {
-                            let btn = egui::Button::new(RichText::new("❌Cancel").size(12.0))
-                                .corner_radius(6.0);
-                            if ui.add(btn).on_hover_text("Cancel TTS conversion").clicked() {
-                                cancel_source_tts = true;
+                            ui.add_space(8.0);
+
+                            // Cancel TTS button (only shown during conversion)
+                            if self.source_tts_converting {
+                                let btn = egui::Button::new(RichText::new("❌Cancel").size(12.0))
+                                    .corner_radius(6.0);
+                                if ui.add(btn).on_hover_text("Cancel TTS conversion").clicked() {
+                                    actions.cancel_source_tts = true;
+                                }
                             }
-                        }
 
-                        ui.add_space(8.0);
+                            ui.add_space(8.0);
 
-                        // Play/Stop audio button
-                        if self
-                            .create_audio_button(
-                                ui,
-                                self.source_tts_converting,
-                                self.source_audio_path.as_deref(),
-                                true,
-                                true,
-                            )
-                            .clicked()
-                        {
-                            play_source_clicked = true;
-                            if let Some(path) = self.source_audio_path.clone() {
-                                source_audio_to_play = Some(path);
+                            // Play/Stop audio button
+                            if self
+                                .create_audio_button(
+                                    ui,
+                                    self.source_tts_converting,
+                                    self.source_audio_path.as_deref(),
+                                    true,
+                                    true,
+                                )
+                                .clicked()
+                            {
+                                actions.play_source_clicked = true;
+                                if let Some(path) = self.source_audio_path.clone() {
+                                    actions.source_audio_to_play = Some(path);
+                                }
                             }
-                        }
+                        });
                     });
-                });
-                ui.add_space(10.0);
+                    ui.add_space(10.0);
 
-                self.create_text_frame(ui).show(ui, |ui| {
-                    ScrollArea::vertical()
-                        .max_height(panel_height)
-                        .id_salt("source_scroll")
-                        .auto_shrink([false, false])
-                        .show(ui, |ui| {
-                            let mut source_edit = self.input_text.clone();
-                            TextEdit::multiline(&mut source_edit)
-                                .font(FontId::new(font_size, FontFamily::Proportional))
-                                .desired_width(f32::INFINITY)
-                                .desired_rows(5)
-                                .frame(false)
-                                .lock_focus(true)
-                                .show(ui);
-                        });
-                });
+                    self.create_text_frame(ui).show(ui, |ui| {
+                        ScrollArea::vertical()
+                            .max_height(panel_height)
+                            .id_salt("source_scroll")
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                // Taken out while the layouter borrows the panel
+                                let mut editor = std::mem::take(&mut self.source_editor);
+                                // Highlights the source phrase of a double-clicked word
+                                let mut layouter =
+                                    |ui: &Ui, buf: &dyn TextBuffer, wrap_width: f32| {
+                                        let mut job = self
+                                            .source_layout_job(ui, buf.as_str(), source_font_size)
+                                            .unwrap_or_else(|| {
+                                                text::LayoutJob::simple(
+                                                    buf.as_str().to_owned(),
+                                                    FontId::new(
+                                                        source_font_size,
+                                                        FontFamily::Proportional,
+                                                    ),
+                                                    ui.visuals()
+                                                        .override_text_color
+                                                        .unwrap_or_else(|| {
+                                                            ui.visuals()
+                                                                .widgets
+                                                                .inactive
+                                                                .text_color()
+                                                        }),
+                                                    wrap_width,
+                                                )
+                                            });
+                                        job.wrap.max_width = wrap_width;
+                                        ui.fonts_mut(|f| f.layout_job(job))
+                                    };
+                                if layout == SourcePanelLayout::Editable {
+                                    let id = Id::new(SOURCE_EDIT_ID);
+                                    let focused = ui.memory(|m| m.has_focus(id));
+                                    ui.input(|i| editor.observe(focused, &i.events));
+                                    TextEdit::multiline(editor.sync(source_text))
+                                        .id(id)
+                                        .font(FontId::new(
+                                            source_font_size,
+                                            FontFamily::Proportional,
+                                        ))
+                                        .desired_width(f32::INFINITY)
+                                        .desired_rows(5)
+                                        .frame(false)
+                                        .lock_focus(true)
+                                        .layouter(&mut layouter)
+                                        .show(ui);
+                                    if editor.commit(source_text) {
+                                        ui.ctx().request_repaint();
+                                    }
+                                } else {
+                                    // Read-only, but still selectable for copying
+                                    TextEdit::multiline(&mut self.input_text.as_str())
+                                        .font(FontId::new(
+                                            source_font_size,
+                                            FontFamily::Proportional,
+                                        ))
+                                        .desired_width(f32::INFINITY)
+                                        .desired_rows(5)
+                                        .frame(false)
+                                        .layouter(&mut layouter)
+                                        .show(ui);
+                                }
+                                self.source_editor = editor;
+                            });
+                    });
 
-                ui.add_space(16.0);
+                    ui.add_space(16.0);
+                }
 
-                // Translation section with audio controls
-                ui.horizontal(|ui| {
+                // Translation section with audio controls, mirrored for right-to-left text
+                let (row_layout, buttons_layout) = header_layouts(direction);
+                let row_size = vec2(ui.available_width(), ui.spacing().interact_size.y);
+                ui.allocate_ui_with_layout(row_size, row_layout, |ui| {
                     ui.label(
                         RichText::new("🌐Translation")
                             .strong()
                             .size(font_size * 1.1),
                     );
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if self.legacy_cache {
+                        ui.label(
+                            RichText::new("🕘Legacy cache")
+                                .size(12.0)
+                                .color(ui.visuals().warn_fg_color),
+                        )
+                        .on_hover_text(
+                            "Cached before translations were kept per model, so another model may have made it",
+                        );
+                    }
+                    if self.checking_confidence {
+                        ui.spinner();
+                        ui.label(RichText::new("Checking confidence…").size(12.0).weak());
+                    } else if !self.shown_low_confidence().is_empty() {
+                        ui.label(
+                            RichText::new(format!(
+                                "🎯{} unsure",
+                                self.shown_low_confidence().len()
+                            ))
+                            .size(12.0)
+                            .color(ui.visuals().warn_fg_color),
+                        )
+                        .on_hover_text(
+                            "The model is least sure of the underlined parts; point at one for why",
+                        );
+                    }
+                    if self.typography_adjusted > 0 {
+                        ui.label(
+                            RichText::new(format!("✎{} adjusted", self.typography_adjusted))
+                                .size(12.0)
+                                .weak(),
+                        )
+                        .on_hover_text(
+                            "Characters of punctuation, quotes and spacing changed to the conventions of the target language",
+                        );
+                    }
+                    ui.with_layout(buttons_layout, |ui| {
+                        ui.add_space(8.0);
+
+                        self.copy_button_ui(ui);
+                        ui.add_space(8.0);
+
+                        self.share_menu_ui(ui, &mut actions);
                         ui.add_space(8.0);
 
+                        if self.popout.is_none() {
+                            let btn = egui::Button::new(RichText::new("⧉Pop out").size(12.0))
+                                .corner_radius(6.0);
+                            if ui
+                                .add(btn)
+                                .on_hover_text("Show the translation in a separate window")
+                                .clicked()
+                            {
+                                actions.pop_out = true;
+                            }
+                            ui.add_space(8.0);
+                        }
+
                         // TTS Convert button (only enabled after translation completes)
                         let translation_tts_enabled =
                             !self.is_translating && !self.translation.is_empty();
+
+                        if translation_tts_enabled && !self.is_explaining && !self.is_hidden() {
+                            let btn = egui::Button::new(RichText::new("💡Explain").size(12.0))
+                                .corner_radius(6.0);
+                            if ui
+                                .add(btn)
+                                .on_hover_text("Explain the word choices, grammar and idioms")
+                                .clicked()
+                            {
+                                actions.explain = true;
+                            }
+                            ui.add_space(8.0);
+                        }
+
                         if !self.translation_tts_converting && translation_tts_enabled {
                             let btn = egui::Button::new(RichText::new("🔊Convert").size(12.0))
                                 .corner_radius(6.0);
@@ -276,7 +2079,7 @@ impl DisplayPanel {
                                 .on_hover_text("Convert translation to audio")
                                 .clicked()
                             {
-                                start_translation_tts = true;
+                                actions.start_translation_tts = true;
                             }
                         }
 
@@ -287,7 +2090,7 @@ impl DisplayPanel {
                             let btn = egui::Button::new(RichText::new("❌Cancel").size(12.0))
                                 .corner_radius(6.0);
                             if ui.add(btn).on_hover_text("Cancel TTS conversion").clicked() {
-                                cancel_translation_tts = true;
+                                actions.cancel_translation_tts = true;
                             }
                         }
 
@@ -304,82 +2107,160 @@ impl DisplayPanel {
                             )
                             .clicked()
                         {
-                            play_translation_clicked = true;
+                            actions.play_translation_clicked = true;
                             if let Some(path) = self.translation_audio_path.clone() {
-                                translation_audio_to_play = Some(path);
+                                actions.translation_audio_to_play = Some(path);
                             }
                         }
+
+                        actions.volume_changed = self.volume_ui(ui);
                     });
                 });
                 ui.add_space(8.0);
 
+                if self.refused && !self.is_translating {
+                    self.refused_banner_ui(ui);
+                    ui.add_space(8.0);
+                }
+
+                if self.truncated && !self.is_translating {
+                    actions.continue_translation = self.truncated_banner_ui(ui);
+                    ui.add_space(8.0);
+                }
+
+                if self.repetition_stopped && !self.is_translating {
+                    actions.retry_lower_temperature = self.repetition_banner_ui(ui);
+                    ui.add_space(8.0);
+                }
+
+                if self.font_warning.is_some() {
+                    actions.load_font = self.font_banner_ui(ui);
+                    ui.add_space(8.0);
+                }
+
+                if self.same_language.is_some() {
+                    (actions.translate_anyway, actions.switch_target) =
+                        self.same_language_banner_ui(ui);
+                    ui.add_space(8.0);
+                }
+
                 self.create_text_frame(ui).show(ui, |ui| {
-                    ScrollArea::vertical()
-                        .max_height(panel_height)
-                        .id_salt("translation_scroll")
-                        .auto_shrink([false, false])
-                        .stick_to_bottom(true) // Auto-scroll to bottom as new content arrives
-                        .show(ui, |ui| {
-                            // Show error message if present
-                            if let Some(error) = &self.error_message {
-                                ui.colored_label(
-                                    ui.visuals().error_fg_color,
-                                    RichText::new(format!("❌ Error: {}", error)).size(font_size),
+                    self.translation_scroll_ui(ui, "translation_scroll", panel_height, |ui| {
+                        if self.popout.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    RichText::new("⧉ Shown in a separate window")
+                                        .size(font_size * 0.9)
+                                        .italics()
+                                        .weak(),
                                 );
-                            } else if self.is_translating {
-                                // Show loading indicator while translating
-                                if self.translation.is_empty() {
-                                    ui.horizontal(|ui| {
-                                        ui.spinner();
-                                        ui.label(
-                                            RichText::new("Translating...")
-                                                .size(font_size)
-                                                .color(ui.visuals().weak_text_color()),
-                                        );
-                                    });
-                                } else {
-                                    // Show partial translation
-                                    let mut display_text = self.translation.clone();
-                                    TextEdit::multiline(&mut display_text)
-                                        .font(FontId::new(font_size, FontFamily::Proportional))
-                                        .desired_width(f32::INFINITY)
-                                        .desired_rows(5)
-                                        .frame(false)
-                                        .lock_focus(true)
-                                        .show(ui);
+                                if ui
+                                    .small_button("⤵Return here")
+                                    .on_hover_text("Close the separate window")
+                                    .clicked()
+                                {
+                                    return_popout = true;
                                 }
-                            } else if self.translation.is_empty() {
-                                // Show placeholder when empty
-                                let display_text = "Translation will appear here...";
-                                ui.colored_label(
-                                    ui.visuals().weak_text_color(),
-                                    RichText::new(display_text).size(font_size * 0.9).italics(),
-                                );
-                            } else {
-                                // Show completed translation
-                                let mut display_text = self.translation.clone();
-                                TextEdit::multiline(&mut display_text)
-                                    .font(FontId::new(font_size, FontFamily::Proportional))
-                                    .desired_width(f32::INFINITY)
-                                    .desired_rows(5)
-                                    .frame(false)
-                                    .lock_focus(true)
-                                    .show(ui);
-                            }
-                        });
+                            });
+                            None
+                        } else {
+                            self.translation_text_ui(ui, translation_font_size)
+                        }
+                    });
                 });
+                if return_popout {
+                    self.popout = None;
+                }
+
+                ui.add_space(8.0);
+
+                if let Some(progress) = self.playback_progress {
+                    (actions.seek, actions.toggle_pause) = self.transport_ui(ui, progress);
+                    ui.add_space(8.0);
+                }
+
+                if !self.warnings.is_empty() {
+                    self.warnings_ui(ui, font_size);
+                    ui.add_space(8.0);
+                }
+
+                // Reveal the finished translation in practice mode
+                if self.practice_mode
+                    && self.practice.is_none()
+                    && !self.is_translating
+                    && !self.translation.is_empty()
+                {
+                    self.practice = Some(PracticeCard::new(
+                        self.translation.as_str(),
+                        &self.target_language,
+                    ));
+                }
+                if self.practice.is_some() && self.error_message.is_none() {
+                    actions.practice_grade = self.practice_ui(ui, font_size);
+                    ui.add_space(8.0);
+                }
+
+                if self.alternatives.len() > 1 && !self.is_translating && !self.is_hidden() {
+                    actions.promote_alternative = self.alternatives_ui(ui, font_size);
+                    ui.add_space(8.0);
+                }
+
+                if self
+                    .list
+                    .as_ref()
+                    .is_some_and(ListTranslation::is_mismatched)
+                    && !self.is_translating
+                {
+                    actions.retry_list_item = self.list_warning_ui(ui);
+                    ui.add_space(8.0);
+                }
+
+                if let Some((language, text)) = &self.pivot
+                    && !self.is_hidden()
+                {
+                    self.pivot_ui(ui, font_size, language, text);
+                    ui.add_space(8.0);
+                }
+
+                // Hidden in practice along with the translation it gives away
+                if !self.reasoning.is_empty() && !self.is_hidden() {
+                    actions.reasoning_open = self.reasoning_ui(ui, font_size);
+                    if let Some(open) = actions.reasoning_open {
+                        self.reasoning_open = open;
+                    }
+                    ui.add_space(8.0);
+                }
+
+                if self.is_explaining
+                    || !self.explanation.is_empty()
+                    || self.explanation_error.is_some()
+                {
+                    actions.cancel_explanation = self.explanation_ui(ui, font_size);
+                    ui.add_space(8.0);
+                }
+
+                // Compare the TTS output with the user's own recording
+                if !self.hide_pronunciation {
+                    let tts_audio = self
+                        .translation_audio_path
+                        .as_deref()
+                        .or(self.source_audio_path.as_deref());
+                    let is_playing = matches!(self.playback_state, PlaybackState::Playing(_));
+                    actions.compare = self.compare.ui(ui, tts_audio, is_playing);
+                }
             });
         });
 
-        (
-            play_source_clicked,
-            source_audio_to_play,
-            play_translation_clicked,
-            translation_audio_to_play,
-            start_source_tts,
-            start_translation_tts,
-            cancel_source_tts,
-            cancel_translation_tts,
-        )
+        if clear_alignment {
+            self.alignment = None;
+        }
+        actions.align_word = ctx.data_mut(|d| d.remove_temp::<String>(Id::new(ALIGN_WORD_ID)));
+        actions
     }
 }
+
+/// Formats `time` as minutes and seconds, e.g. "1:05".
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
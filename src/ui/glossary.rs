@@ -0,0 +1,607 @@
+//! Glossary window, and the sidebar's list of the terms in the source text.
+
+use crate::utils::glossary::{Glossary, GlossaryError, Term, TermMatch, TermMatcher};
+use crate::utils::term_mining::{MiningOptions, MiningState, Suggestion, TermMining};
+use egui::*;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long typing must pause before the source text is scanned again.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Change to the glossary made in the window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlossaryAction {
+    Add(Term),
+    Remove(Term),
+    /// A term was edited in its row
+    Update {
+        original: Term,
+        edited: Term,
+    },
+    /// Read the glossary file again, after another window changed it
+    Reload,
+}
+
+/// A term being edited in its row.
+struct RowEdit {
+    original: Term,
+    source: String,
+    target: String,
+    /// Why the last save was refused
+    error: Option<String>,
+}
+
+impl RowEdit {
+    fn new(term: &Term) -> Self {
+        RowEdit {
+            original: term.clone(),
+            source: term.source.clone(),
+            target: term.target.clone(),
+            error: None,
+        }
+    }
+
+    fn edited(&self) -> Term {
+        Term {
+            source: self.source.clone(),
+            target: self.target.clone(),
+            language: self.original.language.clone(),
+        }
+    }
+
+    fn is_modified(&self) -> bool {
+        self.source != self.original.source || self.target != self.original.target
+    }
+}
+
+/// Terms suggested from the translation history.
+#[derive(Default)]
+struct Suggestions {
+    mining: TermMining,
+    generation: u64,
+    /// Target language the suggestions are for
+    language: String,
+    /// Share of the history analysed, while the analysis runs
+    progress: Option<f32>,
+    /// Suggestions not yet accepted or rejected, `None` before an analysis
+    found: Option<Vec<Suggestion>>,
+    error: Option<String>,
+}
+
+impl Suggestions {
+    fn start(&mut self, log_path: &Path, language: &str) {
+        self.generation = self.mining.start(
+            log_path.to_path_buf(),
+            language.to_string(),
+            MiningOptions::default(),
+        );
+        self.language = language.to_string();
+        self.progress = Some(0.0);
+        self.found = None;
+        self.error = None;
+    }
+
+    fn cancel(&mut self) {
+        if self.progress.take().is_some() {
+            self.mining.cancel();
+        }
+    }
+
+    /// Takes the news of the analysis.
+    fn poll(&mut self) {
+        for update in self.mining.poll() {
+            // News of a cancelled or superseded analysis
+            if update.generation != self.generation || self.progress.is_none() {
+                continue;
+            }
+            match update.state {
+                MiningState::Progress(done) => self.progress = Some(done),
+                MiningState::Done(found) => {
+                    self.progress = None;
+                    self.found = Some(found);
+                }
+                MiningState::Failed(e) => {
+                    self.progress = None;
+                    self.error = Some(format!("Could not read the history: {}", e));
+                }
+            }
+        }
+    }
+
+    /// Renders the analysis for `language` and its results, returning the
+    /// term accepted this frame.
+    ///
+    /// Suggestions already in the glossary are left out.
+    fn ui(
+        &mut self,
+        ui: &mut Ui,
+        glossary: &Glossary,
+        language: &str,
+        log_path: Option<&Path>,
+    ) -> Option<Term> {
+        let Some(log_path) = log_path else {
+            ui.label(RichText::new("There is no history to suggest terms from.").weak());
+            return None;
+        };
+        ui.horizontal(|ui| match self.progress {
+            Some(done) => {
+                ui.add(
+                    ProgressBar::new(done)
+                        .desired_width(200.0)
+                        .text("Reading the history…"),
+                );
+                if ui.small_button("Cancel").clicked() {
+                    self.cancel();
+                }
+            }
+            None => {
+                if ui
+                    .button("Suggest glossary terms")
+                    .on_hover_text(
+                        "Look for phrases the history consistently translated the same way",
+                    )
+                    .clicked()
+                {
+                    self.start(log_path, language);
+                }
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(ui.visuals().warn_fg_color));
+        }
+
+        let found = self.found.as_mut().filter(|_| self.language == language)?;
+        let known = glossary.terms_for(language);
+        found.retain(|suggestion| {
+            !known
+                .iter()
+                .any(|term| term.source.eq_ignore_ascii_case(&suggestion.source))
+        });
+        if found.is_empty() {
+            ui.label(RichText::new("No consistently translated terms found.").weak());
+            return None;
+        }
+
+        let mut accepted = None;
+        let mut decided = None;
+        ScrollArea::vertical()
+            .id_salt("glossary_suggestions")
+            .max_height(160.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                Grid::new("glossary_suggestions_grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, suggestion) in found.iter().enumerate() {
+                            ui.label(&suggestion.source);
+                            ui.label(&suggestion.target);
+                            ui.label(
+                                RichText::new(format!("{} entries", suggestion.entries))
+                                    .size(12.0)
+                                    .weak(),
+                            )
+                            .on_hover_text(format!(
+                                "Translated this way in {:.0}% of them",
+                                suggestion.agreement * 100.0
+                            ));
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✔").on_hover_text("Accept").clicked() {
+                                    accepted = Some(Term {
+                                        source: suggestion.source.clone(),
+                                        target: suggestion.target.clone(),
+                                        language: language.to_string(),
+                                    });
+                                    decided = Some(i);
+                                }
+                                if ui.small_button("✖").on_hover_text("Reject").clicked() {
+                                    decided = Some(i);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(i) = decided {
+            found.remove(i);
+        }
+        accepted
+    }
+}
+
+/// State of the glossary window.
+#[derive(Default)]
+pub struct GlossaryWindow {
+    open: bool,
+    /// Target language whose terms are shown
+    language: String,
+    /// The term being added
+    source: String,
+    target: String,
+    /// Move the focus to the translation field next frame
+    focus_target: bool,
+    /// The term being edited in place
+    editing: Option<RowEdit>,
+    suggestions: Suggestions,
+}
+
+impl GlossaryWindow {
+    /// Opens or closes the window, showing the terms for `language`.
+    pub fn toggle(&mut self, language: &str) {
+        self.open = !self.open;
+        if self.open {
+            self.language = language.to_string();
+        } else {
+            self.suggestions.cancel();
+        }
+    }
+
+    /// Opens the window with a new term for `language` started from `source`.
+    pub fn add_term(&mut self, source: &str, language: &str) {
+        self.open = true;
+        self.language = language.to_string();
+        self.source = source.trim().to_string();
+        self.target.clear();
+        self.focus_target = true;
+    }
+
+    /// Ends the edit saved with `result`, or keeps it open with the error.
+    ///
+    /// A refused edit leaves the glossary as it was, so the row shows the
+    /// saved term again once the edit is cancelled.
+    pub fn finish_edit(&mut self, result: Result<(), GlossaryError>) {
+        match result {
+            Ok(()) => self.editing = None,
+            Err(e) => {
+                if let Some(edit) = &mut self.editing {
+                    edit.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Renders the fields of a new term, returning it once it is added.
+    fn new_term_ui(&mut self, ui: &mut Ui) -> Option<Term> {
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.source)
+                    .hint_text("Source term")
+                    .desired_width(140.0),
+            );
+            ui.label("→");
+            let target = ui.add(
+                TextEdit::singleline(&mut self.target)
+                    .hint_text("Translation")
+                    .desired_width(140.0),
+            );
+            if self.focus_target {
+                target.request_focus();
+                self.focus_target = false;
+            }
+            let complete = !self.source.trim().is_empty() && !self.target.trim().is_empty();
+            let submitted = target.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            let clicked = ui.add_enabled(complete, Button::new("Add")).clicked();
+            if !complete || !(clicked || submitted) {
+                return None;
+            }
+            Some(Term {
+                source: std::mem::take(&mut self.source),
+                target: std::mem::take(&mut self.target),
+                language: self.language.clone(),
+            })
+        })
+        .inner
+    }
+
+    /// Renders the window while it is open.
+    ///
+    /// `log_path` is `None` when the translation log is unavailable, so no
+    /// terms can be suggested from the history.
+    pub fn ui(
+        &mut self,
+        ctx: &Context,
+        glossary: &Glossary,
+        languages: &[&'static str],
+        log_path: Option<&Path>,
+    ) -> Option<GlossaryAction> {
+        if !self.open {
+            return None;
+        }
+        self.suggestions.poll();
+        if self.suggestions.progress.is_some() {
+            ctx.request_repaint();
+        }
+
+        let mut action = None;
+        let mut open = true;
+        Window::new("📖Glossary")
+            .id(Id::new("glossary"))
+            .open(&mut open)
+            .default_size([420.0, 360.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Target Language:");
+                    ComboBox::from_id_salt("glossary_language")
+                        .selected_text(&self.language)
+                        .show_ui(ui, |ui| {
+                            for language in languages {
+                                ui.selectable_value(
+                                    &mut self.language,
+                                    language.to_string(),
+                                    *language,
+                                );
+                            }
+                        });
+                });
+                ui.add_space(6.0);
+
+                if glossary.changed_on_disk() {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new("⚠ The glossary was changed by another window.")
+                                .color(ui.visuals().warn_fg_color),
+                        );
+                        if ui
+                            .button("Reload")
+                            .on_hover_text("Read it again; an edit in progress is kept")
+                            .clicked()
+                        {
+                            action = Some(GlossaryAction::Reload);
+                        }
+                    });
+                }
+
+                if let Some(term) = self.new_term_ui(ui) {
+                    action = Some(GlossaryAction::Add(term));
+                }
+                CollapsingHeader::new("💡Suggestions from history")
+                    .id_salt("glossary_suggestions_header")
+                    .show(ui, |ui| {
+                        let accepted = self.suggestions.ui(ui, glossary, &self.language, log_path);
+                        if let Some(term) = accepted {
+                            action = Some(GlossaryAction::Add(term));
+                        }
+                    });
+                ui.separator();
+
+                let terms = glossary.terms_for(&self.language);
+                if terms.is_empty() {
+                    let note = format!("No terms for {} yet.", self.language);
+                    ui.label(RichText::new(note).weak());
+                    return;
+                }
+                ScrollArea::vertical()
+                    .id_salt("glossary_terms")
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        Grid::new("glossary_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for term in terms {
+                                    let row_action = match &mut self.editing {
+                                        Some(edit) if edit.original == *term => {
+                                            Self::edit_row_ui(ui, edit, glossary)
+                                        }
+                                        _ => Self::term_row_ui(ui, term, &mut self.editing),
+                                    };
+                                    match row_action {
+                                        Some(RowAction::Save(edited)) => {
+                                            action = Some(GlossaryAction::Update {
+                                                original: term.clone(),
+                                                edited,
+                                            });
+                                        }
+                                        Some(RowAction::Cancel) => self.editing = None,
+                                        Some(RowAction::Remove) => {
+                                            action = Some(GlossaryAction::Remove(term.clone()));
+                                        }
+                                        None => {}
+                                    }
+                                }
+                            });
+                    });
+            });
+
+        if !open {
+            self.open = false;
+            self.suggestions.cancel();
+        }
+        action
+    }
+
+    /// Renders a saved term, with buttons to edit and remove it.
+    fn term_row_ui(ui: &mut Ui, term: &Term, editing: &mut Option<RowEdit>) -> Option<RowAction> {
+        let source = ui.add(Label::new(&term.source).sense(Sense::click()));
+        let target = ui.add(Label::new(&term.target).sense(Sense::click()));
+        let mut row_action = None;
+        ui.horizontal(|ui| {
+            let edit = ui.small_button("✏").on_hover_text("Edit");
+            if edit.clicked() || source.double_clicked() || target.double_clicked() {
+                *editing = Some(RowEdit::new(term));
+            }
+            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                row_action = Some(RowAction::Remove);
+            }
+        });
+        ui.end_row();
+        row_action
+    }
+
+    /// Renders the fields of a term being edited, with Save and Cancel.
+    ///
+    /// The edit is checked as it is typed; a conflicting source names the
+    /// term it clashes with, and Save stays disabled until it is resolved.
+    fn edit_row_ui(ui: &mut Ui, edit: &mut RowEdit, glossary: &Glossary) -> Option<RowAction> {
+        let source = ui.add(TextEdit::singleline(&mut edit.source).desired_width(120.0));
+        let target = ui.add(TextEdit::singleline(&mut edit.target).desired_width(120.0));
+        if source.changed() || target.changed() {
+            edit.error = None;
+        }
+        let check = glossary.check_edit(&edit.original, &edit.edited());
+        let savable = edit.is_modified() && check.is_ok();
+        let (submitted, escaped) =
+            ui.input(|i| (i.key_pressed(Key::Enter), i.key_pressed(Key::Escape)));
+        let in_fields =
+            source.lost_focus() || target.lost_focus() || source.has_focus() || target.has_focus();
+
+        let mut row_action = None;
+        ui.horizontal(|ui| {
+            if edit.is_modified() {
+                ui.label(RichText::new("●").color(ui.visuals().warn_fg_color))
+                    .on_hover_text("Unsaved changes");
+            }
+            let save = ui.add_enabled(savable, Button::new("Save").small());
+            if save.clicked() || (savable && submitted && in_fields) {
+                row_action = Some(RowAction::Save(edit.edited()));
+            }
+            if ui.small_button("Cancel").clicked() || (escaped && in_fields) {
+                row_action = Some(RowAction::Cancel);
+            }
+        });
+        ui.end_row();
+
+        let problem = edit.error.clone().or_else(|| {
+            check.err().filter(|_| edit.is_modified()).map(|e| match e {
+                GlossaryError::Conflict(term) => {
+                    format!("Conflicts with \"{}\" → \"{}\"", term.source, term.target)
+                }
+                e => e.to_string(),
+            })
+        });
+        if let Some(problem) = problem {
+            ui.label(
+                RichText::new(problem)
+                    .size(12.0)
+                    .color(ui.visuals().warn_fg_color),
+            );
+            ui.end_row();
+        }
+        row_action
+    }
+}
+
+/// What was done in a row of the term list.
+enum RowAction {
+    Save(Term),
+    Cancel,
+    Remove,
+}
+
+/// Glossary terms found in the source text, listed in the sidebar.
+#[derive(Default)]
+pub struct TermsInText {
+    matcher: Arc<TermMatcher>,
+    /// Text as of the last edit
+    text: String,
+    /// When the text last changed, `None` once it has been scanned
+    edited_at: Option<Instant>,
+    /// Text the matches were found in
+    scanned: String,
+    matches: Vec<TermMatch>,
+    /// Source of the term whose occurrences are highlighted
+    highlighted: Option<String>,
+}
+
+impl TermsInText {
+    /// Uses the terms of another target language, or of a changed glossary.
+    pub fn set_matcher(&mut self, matcher: Arc<TermMatcher>) {
+        if !Arc::ptr_eq(&self.matcher, &matcher) {
+            self.matcher = matcher;
+            self.edited_at = Some(Instant::now() - DEBOUNCE);
+        }
+    }
+
+    /// Notes the current text and scans it once typing has paused.
+    pub fn update(&mut self, ctx: &Context, text: &str) {
+        if self.text != text {
+            self.text = text.to_string();
+            self.edited_at = Some(Instant::now());
+        }
+        let Some(edited_at) = self.edited_at else {
+            return;
+        };
+        let elapsed = edited_at.elapsed();
+        if elapsed < DEBOUNCE {
+            ctx.request_repaint_after(DEBOUNCE - elapsed);
+            return;
+        }
+        self.matches = self.matcher.find(&self.text);
+        self.scanned = self.text.clone();
+        self.edited_at = None;
+        if let Some(source) = &self.highlighted
+            && !self.matches.iter().any(|m| &m.term.source == source)
+        {
+            self.highlighted = None;
+        }
+    }
+
+    /// Occurrences of the highlighted term, if `text` wasn't edited since.
+    pub fn highlights(&self, text: &str) -> &[Range<usize>] {
+        let Some(source) = &self.highlighted else {
+            return &[];
+        };
+        if self.scanned != text {
+            return &[];
+        }
+        self.matches
+            .iter()
+            .find(|m| &m.term.source == source)
+            .map_or(&[], |m| m.ranges.as_slice())
+    }
+
+    /// Renders the section.
+    ///
+    /// # Returns
+    ///
+    /// Whether "Add term…" was clicked
+    pub fn ui(&mut self, ui: &mut Ui, selection: &str) -> bool {
+        let mut add_term = false;
+        let title = if self.matches.is_empty() {
+            "Terms in this text".to_string()
+        } else {
+            format!("Terms in this text ({})", self.matches.len())
+        };
+        CollapsingHeader::new(title)
+            .id_salt("sidebar_terms")
+            .show(ui, |ui| {
+                if self.matches.is_empty() {
+                    let note = if self.matcher.is_empty() {
+                        "No glossary terms for this language"
+                    } else {
+                        "No glossary terms in the text"
+                    };
+                    ui.label(RichText::new(note).weak().size(12.0));
+                }
+                for found in &self.matches {
+                    let selected = self.highlighted.as_deref() == Some(found.term.source.as_str());
+                    let label = format!(
+                        "{} → {}  ×{}",
+                        found.term.source,
+                        found.term.target,
+                        found.ranges.len()
+                    );
+                    if ui
+                        .selectable_label(selected, label)
+                        .on_hover_text("Highlight in the source text")
+                        .clicked()
+                    {
+                        self.highlighted = (!selected).then(|| found.term.source.clone());
+                    }
+                }
+                let hint = if selection.trim().is_empty() {
+                    "Add a term to the glossary"
+                } else {
+                    "Add the selected text to the glossary"
+                };
+                if ui.small_button("➕Add term…").on_hover_text(hint).clicked() {
+                    add_term = true;
+                }
+            });
+        add_term
+    }
+}
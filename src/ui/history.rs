@@ -0,0 +1,263 @@
+//! Searchable translation history window.
+//!
+//! The history is read from the translation log. Results arrive in batches
+//! from a background search while the list is already shown.
+
+use crate::utils::dialogs::{Dialog, DialogCategory};
+use crate::utils::history::{self, HistoryEntry, HistoryHit, HistorySearch};
+use crate::utils::query::Query;
+use egui::text::LayoutJob;
+use egui::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Characters shown of the source text and translation of a result.
+const PREVIEW_CHARS: usize = 90;
+
+/// What to do after the history window was used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryAction {
+    /// Load the entry into the panels
+    Load(Box<HistoryEntry>),
+    /// Export the whole history as a TMX file at the path
+    ExportTmx {
+        path: PathBuf,
+        /// Leave out entries whose source language is unknown
+        skip_unknown_source: bool,
+    },
+}
+
+/// State of the history window.
+#[derive(Default)]
+pub struct HistoryPanel {
+    open: bool,
+    query: String,
+    /// Query the current results belong to, `None` to search again
+    searched: Option<String>,
+    search: HistorySearch,
+    generation: u64,
+    hits: Vec<HistoryHit>,
+    searching: bool,
+    /// Leave out entries of unknown source language from a TMX export
+    skip_unknown_source: bool,
+}
+
+impl HistoryPanel {
+    /// Opens or closes the window.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        // The log has grown since the last search
+        self.searched = None;
+    }
+
+    /// Takes the results that arrived from the background search.
+    fn poll(&mut self) {
+        for batch in self.search.poll() {
+            // Results of a superseded query are outdated
+            if batch.generation != self.generation {
+                continue;
+            }
+            self.hits.extend(batch.hits);
+            if batch.done {
+                self.searching = false;
+            }
+        }
+    }
+
+    /// Starts a new search if the query changed.
+    fn search_if_changed(&mut self, log_path: &Path) {
+        if self.searched.as_deref() == Some(self.query.as_str()) {
+            return;
+        }
+        self.generation = self
+            .search
+            .search(log_path.to_path_buf(), Query::parse(&self.query));
+        self.searched = Some(self.query.clone());
+        self.hits.clear();
+        self.searching = true;
+    }
+
+    /// Renders the history window while it is open.
+    ///
+    /// `log_path` is `None` when the translation log is unavailable, and
+    /// results can only be loaded while `can_load`.
+    ///
+    /// # Returns
+    ///
+    /// The entry that was clicked, or the export that was asked for
+    pub fn ui(
+        &mut self,
+        ctx: &Context,
+        log_path: Option<&Path>,
+        can_load: bool,
+    ) -> Option<HistoryAction> {
+        if !self.open {
+            return None;
+        }
+        self.poll();
+        if let Some(log_path) = log_path {
+            self.search_if_changed(log_path);
+        }
+        if self.searching {
+            ctx.request_repaint();
+        }
+
+        let mut action = None;
+        let mut open = true;
+        Window::new("🕘History")
+            .id(Id::new("history"))
+            .open(&mut open)
+            .default_size([560.0, 480.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if log_path.is_none() {
+                    ui.label(
+                        RichText::new(
+                            "The translation log could not be opened, so there is no history.",
+                        )
+                        .weak(),
+                    );
+                    return;
+                }
+
+                ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .id_salt("history_query")
+                        .hint_text("Search…  lang:中文  before:2024-06-01  model:deepseek")
+                        .desired_width(f32::INFINITY),
+                );
+                ui.horizontal(|ui| {
+                    let matches: usize = self.hits.iter().map(HistoryHit::match_count).sum();
+                    let summary = if Query::parse(&self.query).terms.is_empty() {
+                        format!("{} entries", self.hits.len())
+                    } else {
+                        format!("{} entries, {} matches", self.hits.len(), matches)
+                    };
+                    ui.label(RichText::new(summary).size(12.0).weak());
+                    if self.searching {
+                        ui.spinner();
+                    }
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui
+                            .button("Export TMX…")
+                            .on_hover_text("Save the whole history as a translation memory")
+                            .clicked()
+                            && let Some(path) = Dialog::new(DialogCategory::Tmx)
+                                .add_filter("TMX", &["tmx"])
+                                .set_file_name("history.tmx")
+                                .save_file()
+                        {
+                            action = Some(HistoryAction::ExportTmx {
+                                path,
+                                skip_unknown_source: self.skip_unknown_source,
+                            });
+                        }
+                        ui.checkbox(&mut self.skip_unknown_source, "Skip unknown source")
+                            .on_hover_text(
+                                "Leave out entries whose source language can't be told, \
+                                 instead of marking them as undetermined",
+                            );
+                    });
+                });
+                ui.add_space(4.0);
+
+                let row_height = ui.text_style_height(&TextStyle::Body) * 3.0
+                    + ui.spacing().button_padding.y * 2.0;
+                ScrollArea::vertical()
+                    .id_salt("history_results")
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, self.hits.len(), |ui, rows| {
+                        for hit in &self.hits[rows] {
+                            let job = result_job(ui, hit);
+                            let mut response = ui
+                                .add_enabled(
+                                    can_load,
+                                    Button::selectable(false, job)
+                                        .min_size(vec2(ui.available_width(), row_height)),
+                                )
+                                .on_disabled_hover_text("Wait for the running translation");
+                            if let Some(provenance) = &hit.entry.provenance {
+                                response = response.on_hover_text(provenance.describe());
+                            }
+                            if response.clicked() {
+                                action = Some(HistoryAction::Load(Box::new(hit.entry.clone())));
+                            }
+                        }
+                    });
+            });
+
+        if !open {
+            self.open = false;
+        }
+        action
+    }
+}
+
+/// Lays out a result: date and language, then the source text and the
+/// translation around their first match, with the matches highlighted.
+fn result_job(ui: &Ui, hit: &HistoryHit) -> LayoutJob {
+    let font_id = TextStyle::Body.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let plain = TextFormat::simple(font_id.clone(), color);
+    let header = TextFormat::simple(
+        FontId::proportional(font_id.size * 0.85),
+        ui.visuals().weak_text_color(),
+    );
+    let highlighted = TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        color: ui.visuals().strong_text_color(),
+        ..plain.clone()
+    };
+
+    let mut job = LayoutJob::default();
+    let model = hit
+        .entry
+        .provenance
+        .as_ref()
+        .map(|provenance| format!(" · {}", provenance.model))
+        .unwrap_or_default();
+    job.append(
+        &format!(
+            "{} · {}{}\n",
+            hit.entry.timestamp.format("%Y-%m-%d %H:%M"),
+            hit.entry.target_language,
+            model
+        ),
+        0.0,
+        header,
+    );
+    let (source, source_matches) =
+        history::preview(&hit.entry.source_text, &hit.source_matches, PREVIEW_CHARS);
+    append_highlighted(&mut job, &source, &source_matches, &plain, &highlighted);
+    job.append("\n", 0.0, plain.clone());
+    let (translation, translation_matches) = history::preview(
+        &hit.entry.translation,
+        &hit.translation_matches,
+        PREVIEW_CHARS,
+    );
+    append_highlighted(
+        &mut job,
+        &translation,
+        &translation_matches,
+        &plain,
+        &highlighted,
+    );
+    job
+}
+
+/// Appends `text` with the `matches` byte ranges in the highlighted format.
+fn append_highlighted(
+    job: &mut LayoutJob,
+    text: &str,
+    matches: &[Range<usize>],
+    plain: &TextFormat,
+    highlighted: &TextFormat,
+) {
+    let mut offset = 0;
+    for range in matches {
+        job.append(&text[offset..range.start], 0.0, plain.clone());
+        job.append(&text[range.clone()], 0.0, highlighted.clone());
+        offset = range.end;
+    }
+    job.append(&text[offset..], 0.0, plain.clone());
+}
@@ -1,7 +1,19 @@
+pub mod about;
 pub mod app;
+pub mod compare;
 pub mod display;
+pub mod glossary;
+pub mod history;
+pub mod palette;
+pub mod pdf_preview;
+pub mod redaction;
 pub mod settings;
 pub mod sidebar;
+pub mod spelling;
+pub mod status_bar;
+pub mod structured;
+pub mod tasks;
 pub mod theme;
+pub mod toast;
 
 pub use app::TranslateApp;
@@ -0,0 +1,184 @@
+//! Command palette: find and run any registered action by typing part of
+//! its name.
+
+use crate::utils::actions::ActionMatch;
+use egui::text::LayoutJob;
+use egui::*;
+
+/// State of the command palette.
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    /// Index of the highlighted entry in the matches
+    selected: usize,
+    /// Focus the query field on the next frame
+    focus: bool,
+}
+
+impl CommandPalette {
+    /// Opens the palette with an empty query, or closes it.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+        self.focus = self.open;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The text typed so far.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Shows the palette listing `matches` of the current query, returning
+    /// the id of the action chosen to run.
+    ///
+    /// Arrow keys move the highlight, Enter runs the highlighted action and
+    /// Escape closes the palette. Disabled actions are listed with the
+    /// reason but can't be chosen.
+    pub fn ui(&mut self, ctx: &Context, matches: &[ActionMatch]) -> Option<&'static str> {
+        if !self.open {
+            return None;
+        }
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if escape {
+            self.open = false;
+            return None;
+        }
+        if down && self.selected + 1 < matches.len() {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = None;
+        if enter
+            && let Some(found) = matches.get(self.selected)
+            && found.disabled.is_none()
+        {
+            chosen = Some(found.id);
+        }
+
+        Window::new("Command palette")
+            .id(Id::new("command_palette"))
+            .title_bar(false)
+            .anchor(Align2::CENTER_TOP, vec2(0.0, 60.0))
+            .fixed_size(vec2(480.0, 0.0))
+            .show(ctx, |ui| {
+                let field = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command…")
+                        .desired_width(f32::INFINITY),
+                );
+                if self.focus {
+                    field.request_focus();
+                    self.focus = false;
+                }
+                if field.changed() {
+                    self.selected = 0;
+                    ctx.request_repaint();
+                }
+                ui.separator();
+
+                if matches.is_empty() {
+                    ui.label(RichText::new("No matching commands").weak());
+                    return;
+                }
+                ScrollArea::vertical()
+                    .max_height(320.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for (index, found) in matches.iter().enumerate() {
+                            if let Some(id) = self.entry_ui(ui, index, found) {
+                                chosen = Some(id);
+                            }
+                        }
+                    });
+            });
+
+        if chosen.is_some() {
+            self.open = false;
+        }
+        chosen
+    }
+
+    /// Renders one entry, returning its id if it was clicked.
+    fn entry_ui(&self, ui: &mut Ui, index: usize, found: &ActionMatch) -> Option<&'static str> {
+        let selected = index == self.selected;
+        let enabled = found.disabled.is_none();
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            let label = highlighted(ui, found, enabled);
+            let response = ui.add_enabled(enabled, Button::selectable(selected, label));
+            if selected {
+                response.scroll_to_me(None);
+            }
+            let response = match found.disabled {
+                Some(reason) => response.on_disabled_hover_text(reason),
+                None => response,
+            };
+            if response.clicked() {
+                clicked = Some(found.id);
+            }
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if let Some(shortcut) = &found.shortcut {
+                    ui.label(
+                        RichText::new(ui.ctx().format_shortcut(shortcut))
+                            .size(12.0)
+                            .monospace()
+                            .weak(),
+                    );
+                }
+                if let Some(reason) = found.disabled {
+                    ui.label(RichText::new(reason).size(12.0).weak().italics());
+                }
+            });
+        });
+        clicked
+    }
+}
+
+/// The label of `found` with the characters matched by the query underlined.
+fn highlighted(ui: &Ui, found: &ActionMatch, enabled: bool) -> LayoutJob {
+    let font = TextStyle::Body.resolve(ui.style());
+    let color = if enabled {
+        ui.visuals().text_color()
+    } else {
+        ui.visuals().weak_text_color()
+    };
+    let strong = if enabled {
+        ui.visuals().strong_text_color()
+    } else {
+        color
+    };
+    let mut job = LayoutJob::default();
+    for (offset, c) in found.label.char_indices() {
+        let matched = found.matched.contains(&offset);
+        let format = TextFormat {
+            font_id: font.clone(),
+            color: if matched { strong } else { color },
+            underline: if matched {
+                Stroke::new(1.0, strong)
+            } else {
+                Stroke::NONE
+            },
+            ..TextFormat::default()
+        };
+        job.append(c.encode_utf8(&mut [0; 4]), 0.0, format);
+    }
+    job
+}
@@ -0,0 +1,129 @@
+//! Preview of the text extracted from a PDF or a linked web page.
+//!
+//! Extraction is never perfect, so the text is shown for correction before
+//! it becomes the source text.
+
+use egui::*;
+
+/// What to do with the corrected text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PdfPreviewAction {
+    /// Put the text into the source field
+    UseAsSource(String),
+    /// Put the text into the source field and translate it
+    Translate(String),
+}
+
+/// State of the PDF preview window.
+#[derive(Default)]
+pub struct PdfPreview {
+    /// File name and extracted text, `Some` while the preview is open
+    document: Option<(String, String)>,
+    /// Whether a PDF is being extracted
+    extracting: bool,
+    /// How far the extraction got, when that is known
+    progress: Option<String>,
+}
+
+impl PdfPreview {
+    /// Shows that a PDF is being extracted.
+    pub fn start_extraction(&mut self, file_name: String) {
+        self.document = Some((file_name, String::new()));
+        self.extracting = true;
+        self.progress = None;
+    }
+
+    /// Shows how far the extraction got, e.g. how much of a page arrived.
+    pub fn set_progress(&mut self, progress: String) {
+        self.progress = Some(progress);
+    }
+
+    /// Shows the extracted text for correction.
+    pub fn set_text(&mut self, text: String) {
+        if let Some((_, document_text)) = &mut self.document {
+            *document_text = text;
+        }
+        self.extracting = false;
+    }
+
+    /// Closes the preview, e.g. when extraction failed.
+    pub fn close(&mut self) {
+        self.document = None;
+        self.extracting = false;
+    }
+
+    /// Renders the preview window while it is open.
+    ///
+    /// # Returns
+    ///
+    /// The chosen action, after which the preview is closed
+    pub fn ui(&mut self, ctx: &Context, can_translate: bool) -> Option<PdfPreviewAction> {
+        let extracting = self.extracting;
+        let progress = self.progress.as_deref().unwrap_or("Extracting text…");
+        let (file_name, text) = self.document.as_mut()?;
+        let mut action = None;
+        let mut open = true;
+
+        Window::new(format!("📄{}", file_name))
+            .id(Id::new("pdf_preview"))
+            .open(&mut open)
+            .default_size([560.0, 480.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if extracting {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(progress);
+                    });
+                    return;
+                }
+
+                ui.label(
+                    RichText::new("Check the extracted text and correct it before translating.")
+                        .weak(),
+                );
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(can_translate, Button::new("🌐Translate"))
+                        .on_disabled_hover_text(
+                            "A translation is running or the API key is missing",
+                        )
+                        .clicked()
+                    {
+                        action = Some(PdfPreviewAction::Translate(text.clone()));
+                    }
+                    if ui
+                        .button("📝Use as source")
+                        .on_hover_text("Put the text into the source field without translating")
+                        .clicked()
+                    {
+                        action = Some(PdfPreviewAction::UseAsSource(text.clone()));
+                    }
+                    ui.label(
+                        RichText::new(format!("{} characters", text.chars().count()))
+                            .size(12.0)
+                            .weak(),
+                    );
+                });
+                ui.add_space(4.0);
+
+                ScrollArea::vertical()
+                    .id_salt("pdf_preview_scroll")
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.add(
+                            TextEdit::multiline(text)
+                                .id_salt("pdf_preview_text")
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(16),
+                        );
+                    });
+            });
+
+        if !open || action.is_some() {
+            self.close();
+        }
+        action
+    }
+}
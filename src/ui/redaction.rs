@@ -0,0 +1,104 @@
+//! Preview of the values redacted from the source text before it is sent.
+
+use crate::api::request::TranslationRequest;
+use crate::utils::redaction::Redaction;
+use egui::*;
+
+/// What to do with the redacted request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedactionDecision {
+    /// Send the request
+    Send {
+        request: Box<TranslationRequest>,
+        redaction: Redaction,
+        /// Don't show the preview again
+        dont_ask_again: bool,
+    },
+    /// Don't send anything
+    Cancel,
+}
+
+/// State of the redaction preview window.
+#[derive(Default)]
+pub struct RedactionPreview {
+    /// Request waiting for approval, with its source text not yet redacted
+    pending: Option<(TranslationRequest, Redaction)>,
+    dont_ask_again: bool,
+}
+
+impl RedactionPreview {
+    /// Shows what will be redacted from `request` and waits for approval.
+    pub fn open(&mut self, request: TranslationRequest, redaction: Redaction) {
+        self.pending = Some((request, redaction));
+        self.dont_ask_again = false;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Renders the preview window while it is open.
+    ///
+    /// # Returns
+    ///
+    /// The decision, after which the preview is closed
+    pub fn ui(&mut self, ctx: &Context) -> Option<RedactionDecision> {
+        let (_, redaction) = self.pending.as_ref()?;
+        let mut send = false;
+        let mut cancel = false;
+        let mut open = true;
+
+        Window::new("🔒Redacted Before Sending")
+            .id(Id::new("redaction_preview"))
+            .open(&mut open)
+            .default_size([440.0, 320.0])
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(
+                        "These values are replaced before the text is sent and put back into \
+                         the translation on this computer.",
+                    )
+                    .weak(),
+                );
+                ui.add_space(4.0);
+                ScrollArea::vertical()
+                    .id_salt("redaction_preview_values")
+                    .max_height(220.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        Grid::new("redaction_preview_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for redacted in &redaction.values {
+                                    ui.label(&redacted.value);
+                                    ui.label(RichText::new(&redacted.placeholder).monospace());
+                                    ui.label(format!("×{}", redacted.count));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(6.0);
+                ui.checkbox(&mut self.dont_ask_again, "Don't ask again");
+                ui.horizontal(|ui| {
+                    send = ui.button("🌐Send redacted").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if send {
+            let (request, redaction) = self.pending.take()?;
+            return Some(RedactionDecision::Send {
+                request: Box::new(request),
+                redaction,
+                dont_ask_again: self.dont_ask_again,
+            });
+        }
+        if cancel || !open {
+            self.pending = None;
+            return Some(RedactionDecision::Cancel);
+        }
+        None
+    }
+}
@@ -1,8 +1,27 @@
+use crate::api::client::{DEFAULT_MODEL, KNOWN_MODELS, ThinkingMode};
+use crate::ui::theme;
 use crate::utils::cache::TranslationCache;
-use crate::utils::config::AppConfig;
+use crate::utils::cache_rules::{self, CacheRule};
+use crate::utils::config::{self, AppConfig, LanguageProfile, Proficiency, SourcePanelLayout};
+use crate::utils::hooks;
+use crate::utils::layout::Arrangement;
+use crate::utils::logger::LogDetail;
+use crate::utils::pricing::Pricing;
+use crate::utils::redaction;
+use crate::utils::repetition;
+use crate::utils::retention::{self, Usage};
+use crate::utils::script::Script;
+use crate::utils::spellcheck;
+use crate::utils::typography::QuoteStyle;
 use egui::{self, *};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+/// Output token limit suggested when the user first enables one.
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ThemePreference {
     Light,
@@ -18,9 +37,127 @@ pub struct SettingsConfig {
     pub tts_voice: String,
     pub tts_speed: f32,
     pub tts_volume: f32,
+    pub tts_timeout_secs: u64,
+    pub tts_segment_timeout_secs: u64,
+    pub speak_while_translating: bool,
     pub enable_keyword_analysis: bool,
+    pub show_alternatives: bool,
+    pub retry_refusals: bool,
+    pub pivot_enabled: bool,
+    pub pivot_language: String,
+    pub preconnect_on_startup: bool,
+    pub connect_timeout_secs: u64,
+    pub request_timeout_secs: u64,
+    pub request_attempts: u32,
+    pub unescape_content: bool,
+    pub shared_cache: bool,
+    pub check_for_updates: bool,
+    pub watch_config_file: bool,
     pub think_enable: bool,
     pub coding_plan: bool,
+    pub chat_thinking: Option<ThinkingMode>,
+    pub discard_reasoning: bool,
+    pub model: String,
+    pub max_tokens: Option<u32>,
+    pub repetition_limit: Option<usize>,
+    pub fast_path_chars: Option<u32>,
+    pub fast_path_model: String,
+    pub model_pricing: BTreeMap<String, Pricing>,
+    pub output_ratio: f64,
+    pub source_panel_layout: SourcePanelLayout,
+    pub pinned_layout: Option<Arrangement>,
+    pub spellcheck_enabled: bool,
+    pub spellcheck_language: String,
+    pub sidebar_auto_collapse: bool,
+    pub sanitize_source_text: bool,
+    pub redact_sensitive: bool,
+    pub redaction_preview: bool,
+    pub redaction_patterns: Vec<String>,
+    pub cache_exclusions: Vec<CacheRule>,
+    pub completion_hook_enabled: bool,
+    pub completion_hook: String,
+    pub completion_hook_timeout_secs: u64,
+    pub normalize_typography: bool,
+    pub quote_style: QuoteStyle,
+    pub practice_mode: bool,
+    pub custom_font_path: Option<PathBuf>,
+    pub auto_font_source: bool,
+    pub auto_font_translation: bool,
+    pub smooth_typing: Option<u32>,
+    pub script_font_scales: BTreeMap<Script, f32>,
+    pub language_profiles: BTreeMap<String, LanguageProfile>,
+    pub retention_days: Option<u32>,
+    pub storage_budget_mb: Option<u32>,
+    pub log_rotate_mb: Option<u32>,
+    pub log_detail: LogDetail,
+    /// Language whose profile is shown first
+    pub target_language: String,
+}
+
+impl From<&AppConfig> for SettingsConfig {
+    fn from(config: &AppConfig) -> Self {
+        SettingsConfig {
+            font_size: config.font_size,
+            dark_theme: config.dark_theme,
+            tts_voice: config.tts_voice.clone(),
+            tts_speed: config.tts_speed,
+            tts_volume: config.tts_volume,
+            tts_timeout_secs: config.tts_timeout_secs,
+            tts_segment_timeout_secs: config.tts_segment_timeout_secs,
+            speak_while_translating: config.speak_while_translating,
+            enable_keyword_analysis: config.enable_keyword_analysis,
+            show_alternatives: config.show_alternatives,
+            retry_refusals: config.retry_refusals,
+            pivot_enabled: config.pivot_enabled,
+            pivot_language: config.pivot_language.clone(),
+            preconnect_on_startup: config.preconnect_on_startup,
+            connect_timeout_secs: config.connect_timeout_secs,
+            request_timeout_secs: config.request_timeout_secs,
+            request_attempts: config.request_attempts,
+            unescape_content: config.unescape_content,
+            shared_cache: config.shared_cache,
+            check_for_updates: config.check_for_updates,
+            watch_config_file: config.watch_config_file,
+            think_enable: config.think_enable,
+            coding_plan: config.coding_plan,
+            chat_thinking: config.chat_thinking,
+            discard_reasoning: config.discard_reasoning,
+            max_tokens: config.max_tokens,
+            repetition_limit: config.repetition_limit,
+            fast_path_chars: config.fast_path_chars,
+            model: config.model.clone(),
+            fast_path_model: config.fast_path_model.clone(),
+            model_pricing: config.model_pricing.clone(),
+            output_ratio: config.output_ratio,
+            source_panel_layout: config.source_panel_layout,
+            pinned_layout: config.pinned_layout,
+            spellcheck_enabled: config.spellcheck_enabled,
+            spellcheck_language: config.spellcheck_language.clone(),
+            sidebar_auto_collapse: config.sidebar_auto_collapse,
+            sanitize_source_text: config.sanitize_source_text,
+            redact_sensitive: config.redact_sensitive,
+            redaction_preview: config.redaction_preview,
+            redaction_patterns: config.redaction_patterns.clone(),
+            cache_exclusions: config.cache_exclusions.clone(),
+            completion_hook_enabled: config.completion_hook_enabled,
+            completion_hook: config.completion_hook.clone(),
+            completion_hook_timeout_secs: config.completion_hook_timeout_secs,
+            normalize_typography: config.normalize_typography,
+            quote_style: config.quote_style,
+            practice_mode: config.practice_mode,
+            custom_font_path: config.custom_font_path.clone(),
+            auto_font_source: config.auto_font_source,
+            auto_font_translation: config.auto_font_translation,
+            smooth_typing: config.smooth_typing,
+            script_font_scales: config.script_font_scales.clone(),
+            language_profiles: config.language_profiles.clone(),
+            retention_days: config.retention_days,
+            storage_budget_mb: config.storage_budget_mb,
+            log_rotate_mb: config.log_rotate_mb,
+            log_detail: config.log_detail,
+            target_language: config.target_language.clone(),
+        }
+    }
 }
 
 pub struct SettingsPanel {
@@ -29,9 +166,80 @@ pub struct SettingsPanel {
     pub tts_voice: String,
     pub tts_speed: f32,
     pub tts_volume: f32,
+    pub tts_timeout_secs: u64,
+    pub tts_segment_timeout_secs: u64,
+    pub speak_while_translating: bool,
     pub enable_keyword_analysis: bool,
+    pub show_alternatives: bool,
+    pub retry_refusals: bool,
+    pub pivot_enabled: bool,
+    pub pivot_language: String,
+    pub preconnect_on_startup: bool,
+    pub connect_timeout_secs: u64,
+    pub request_timeout_secs: u64,
+    pub request_attempts: u32,
+    pub unescape_content: bool,
+    pub shared_cache: bool,
+    pub check_for_updates: bool,
+    pub watch_config_file: bool,
     pub think_enable: bool,
     pub coding_plan: bool,
+    pub chat_thinking: Option<ThinkingMode>,
+    pub discard_reasoning: bool,
+    /// Model of regular requests, empty for the default one
+    pub model: String,
+    pub max_tokens: Option<u32>,
+    pub repetition_limit: Option<usize>,
+    /// Longest text sent on the fast path, in characters
+    pub fast_path_chars: Option<u32>,
+    /// Model of fast path requests, empty for the regular one
+    pub fast_path_model: String,
+    /// Prices of the models, for cost estimates
+    pub model_pricing: BTreeMap<String, Pricing>,
+    /// Output tokens expected per input token until one is learned
+    pub output_ratio: f64,
+    pub source_panel_layout: SourcePanelLayout,
+    pub pinned_layout: Option<Arrangement>,
+    pub spellcheck_enabled: bool,
+    pub spellcheck_language: String,
+    pub sidebar_auto_collapse: bool,
+    pub sanitize_source_text: bool,
+    pub redact_sensitive: bool,
+    pub redaction_preview: bool,
+    /// Extra patterns to redact, one per line
+    pub redaction_patterns: String,
+    /// Rules for translations that are never cached nor logged
+    cache_exclusions: Vec<CacheRuleDraft>,
+    pub completion_hook_enabled: bool,
+    /// The security warning is shown before the hook is enabled
+    confirming_completion_hook: bool,
+    pub completion_hook: String,
+    pub completion_hook_timeout_secs: u64,
+    pub normalize_typography: bool,
+    pub quote_style: QuoteStyle,
+    pub practice_mode: bool,
+    /// Extra font for scripts the bundled fonts lack
+    pub custom_font_path: Option<PathBuf>,
+    pub auto_font_source: bool,
+    pub auto_font_translation: bool,
+    pub smooth_typing: Option<u32>,
+    /// Font size multipliers per script for automatic sizing
+    pub script_font_scales: BTreeMap<Script, f32>,
+    pub language_profiles: BTreeMap<String, LanguageProfile>,
+    /// Days after which diagnostics and log archives are deleted
+    pub retention_days: Option<u32>,
+    /// Size budget per folder, in MB
+    pub storage_budget_mb: Option<u32>,
+    /// Log size that triggers archiving, in MB
+    pub log_rotate_mb: Option<u32>,
+    /// How much of a translation the translation log keeps
+    pub log_detail: LogDetail,
+    /// Language whose profile is being edited
+    profile_language: String,
+    /// Languages with an installed dictionary
+    spellcheck_languages: Vec<String>,
+    /// Leave translation text out of diagnostic bundles
+    bundle_strip_text: bool,
     show_panel: bool,
     #[allow(dead_code)]
     clear_translation_cache: bool,
@@ -47,9 +255,63 @@ impl Default for SettingsPanel {
             tts_voice: "Tongtong".to_string(),
             tts_speed: 1.0,
             tts_volume: 1.0,
+            tts_timeout_secs: 120,
+            tts_segment_timeout_secs: 30,
+            speak_while_translating: false,
             enable_keyword_analysis: false,
+            show_alternatives: false,
+            retry_refusals: false,
+            pivot_enabled: false,
+            pivot_language: "English".to_string(),
+            preconnect_on_startup: false,
+            connect_timeout_secs: 10,
+            request_timeout_secs: 60,
+            request_attempts: 3,
+            unescape_content: false,
+            shared_cache: false,
+            check_for_updates: false,
+            watch_config_file: false,
             think_enable: true,
             coding_plan: true,
+            chat_thinking: None,
+            discard_reasoning: false,
+            max_tokens: None,
+            repetition_limit: Some(repetition::DEFAULT_MAX_REPEATS),
+            fast_path_chars: Some(200),
+            model: DEFAULT_MODEL.to_string(),
+            fast_path_model: String::new(),
+            model_pricing: BTreeMap::new(),
+            output_ratio: 1.5,
+            source_panel_layout: SourcePanelLayout::default(),
+            pinned_layout: None,
+            spellcheck_enabled: true,
+            spellcheck_language: "en_US".to_string(),
+            sidebar_auto_collapse: true,
+            sanitize_source_text: true,
+            redact_sensitive: false,
+            redaction_preview: true,
+            redaction_patterns: String::new(),
+            cache_exclusions: Vec::new(),
+            completion_hook_enabled: false,
+            confirming_completion_hook: false,
+            completion_hook: String::new(),
+            completion_hook_timeout_secs: 30,
+            normalize_typography: false,
+            quote_style: QuoteStyle::default(),
+            practice_mode: false,
+            custom_font_path: None,
+            auto_font_source: false,
+            auto_font_translation: false,
+            smooth_typing: None,
+            script_font_scales: crate::utils::script::default_font_scales(),
+            language_profiles: BTreeMap::new(),
+            retention_days: Some(30),
+            storage_budget_mb: Some(200),
+            log_rotate_mb: Some(10),
+            log_detail: LogDetail::default(),
+            profile_language: "English".to_string(),
+            spellcheck_languages: Vec::new(),
+            bundle_strip_text: true,
             show_panel: false,
             clear_translation_cache: false,
             clear_audio_cache: false,
@@ -69,20 +331,86 @@ impl SettingsPanel {
             tts_voice: config.tts_voice,
             tts_speed: config.tts_speed,
             tts_volume: config.tts_volume,
+            tts_timeout_secs: config.tts_timeout_secs,
+            tts_segment_timeout_secs: config.tts_segment_timeout_secs,
+            speak_while_translating: config.speak_while_translating,
             enable_keyword_analysis: config.enable_keyword_analysis,
+            show_alternatives: config.show_alternatives,
+            retry_refusals: config.retry_refusals,
+            pivot_enabled: config.pivot_enabled,
+            pivot_language: config.pivot_language,
+            preconnect_on_startup: config.preconnect_on_startup,
+            connect_timeout_secs: config.connect_timeout_secs,
+            request_timeout_secs: config.request_timeout_secs,
+            request_attempts: config.request_attempts,
+            unescape_content: config.unescape_content,
+            shared_cache: config.shared_cache,
+            check_for_updates: config.check_for_updates,
+            watch_config_file: config.watch_config_file,
             think_enable: config.think_enable,
             coding_plan: config.coding_plan,
+            chat_thinking: config.chat_thinking,
+            discard_reasoning: config.discard_reasoning,
+            max_tokens: config.max_tokens,
+            repetition_limit: config.repetition_limit,
+            fast_path_chars: config.fast_path_chars,
+            model: config.model,
+            fast_path_model: config.fast_path_model,
+            model_pricing: config.model_pricing,
+            output_ratio: config.output_ratio,
+            source_panel_layout: config.source_panel_layout,
+            pinned_layout: config.pinned_layout,
+            spellcheck_enabled: config.spellcheck_enabled,
+            spellcheck_language: config.spellcheck_language,
+            sidebar_auto_collapse: config.sidebar_auto_collapse,
+            sanitize_source_text: config.sanitize_source_text,
+            redact_sensitive: config.redact_sensitive,
+            redaction_preview: config.redaction_preview,
+            redaction_patterns: config.redaction_patterns.join("\n"),
+            cache_exclusions: config
+                .cache_exclusions
+                .iter()
+                .map(CacheRuleDraft::from_rule)
+                .collect(),
+            completion_hook_enabled: config.completion_hook_enabled,
+            confirming_completion_hook: false,
+            completion_hook: config.completion_hook,
+            completion_hook_timeout_secs: config.completion_hook_timeout_secs,
+            normalize_typography: config.normalize_typography,
+            quote_style: config.quote_style,
+            practice_mode: config.practice_mode,
+            custom_font_path: config.custom_font_path,
+            auto_font_source: config.auto_font_source,
+            auto_font_translation: config.auto_font_translation,
+            smooth_typing: config.smooth_typing,
+            script_font_scales: config.script_font_scales,
+            language_profiles: config.language_profiles,
+            retention_days: config.retention_days,
+            storage_budget_mb: config.storage_budget_mb,
+            log_rotate_mb: config.log_rotate_mb,
+            log_detail: config.log_detail,
+            profile_language: config.target_language,
+            spellcheck_languages: spellcheck::available_languages(),
+            bundle_strip_text: true,
             show_panel: false,
             clear_translation_cache: false,
             clear_audio_cache: false,
         }
     }
 
+    /// Replaces all values, e.g. after a config restore, keeping the panel open.
+    pub fn reload(&mut self, config: SettingsConfig) {
+        let show_panel = self.show_panel;
+        *self = Self::new(config);
+        self.show_panel = show_panel;
+    }
+
     pub fn ui(
         &mut self,
         ctx: &egui::Context,
         translation_cache: Option<Arc<TranslationCache>>,
         audio_cache_len: usize,
+        storage: &[Usage],
     ) -> (bool, Option<SettingsChange>) {
         let mut settings_changed = None;
 
@@ -92,9 +420,59 @@ impl SettingsPanel {
         let old_tts_voice = self.tts_voice.clone();
         let old_tts_speed = self.tts_speed;
         let old_tts_volume = self.tts_volume;
+        let old_tts_timeouts = (self.tts_timeout_secs, self.tts_segment_timeout_secs);
+        let old_speak_while_translating = self.speak_while_translating;
         let old_enable_keyword_analysis = self.enable_keyword_analysis;
+        let old_show_alternatives = self.show_alternatives;
+        let old_retry_refusals = self.retry_refusals;
+        let old_pivot_enabled = self.pivot_enabled;
+        let old_pivot_language = self.pivot_language.clone();
+        let old_spellcheck_enabled = self.spellcheck_enabled;
+        let old_spellcheck_language = self.spellcheck_language.clone();
         let old_think_enable = self.think_enable;
+        let old_model = self.model.clone();
+        let old_max_tokens = self.max_tokens;
+        let old_repetition_limit = self.repetition_limit;
+        let old_fast_path = (self.fast_path_chars, self.fast_path_model.clone());
+        let old_pricing = (self.model_pricing.clone(), self.output_ratio);
+        let old_preconnect_on_startup = self.preconnect_on_startup;
+        let old_timeouts = (self.connect_timeout_secs, self.request_timeout_secs);
+        let old_request_attempts = self.request_attempts;
+        let old_unescape_content = self.unescape_content;
+        let old_shared_cache = self.shared_cache;
+        let old_check_for_updates = self.check_for_updates;
+        let old_watch_config_file = self.watch_config_file;
         let old_coding_plan = self.coding_plan;
+        let old_chat_thinking = self.chat_thinking;
+        let old_discard_reasoning = self.discard_reasoning;
+        let old_source_panel_layout = self.source_panel_layout;
+        let old_pinned_layout = self.pinned_layout;
+        let old_sidebar_auto_collapse = self.sidebar_auto_collapse;
+        let old_sanitize_source_text = self.sanitize_source_text;
+        let old_redaction = (
+            self.redact_sensitive,
+            self.redaction_preview,
+            self.redaction_patterns.clone(),
+        );
+        let old_cache_exclusions = self.cache_exclusion_rules();
+        let old_completion_hook = (
+            self.completion_hook_enabled,
+            self.completion_hook.clone(),
+            self.completion_hook_timeout_secs,
+        );
+        let old_typography = (self.normalize_typography, self.quote_style);
+        let old_practice_mode = self.practice_mode;
+        let old_retention = (
+            self.retention_days,
+            self.storage_budget_mb,
+            self.log_rotate_mb,
+        );
+        let old_log_detail = self.log_detail;
+        let old_auto_font = (self.auto_font_source, self.auto_font_translation);
+        let old_smooth_typing = self.smooth_typing;
+        let old_script_font_scales = self.script_font_scales.clone();
+        let old_profile_language = self.profile_language.clone();
+        let old_language_profile = self.language_profiles.get(&self.profile_language).cloned();
 
         Window::new("Settings")
             .collapsible(true)
@@ -126,6 +504,58 @@ impl SettingsPanel {
                         });
                         ui.add_space(15.0);
 
+                        // Size text for its script
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔠Auto Font Size:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.auto_font_source, "Source");
+                            ui.checkbox(&mut self.auto_font_translation, "Translation");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Scales the font size above by the multiplier of the script most of the text is written in.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        CollapsingHeader::new(RichText::new("Script multipliers").size(13.0))
+                            .id_salt("script_font_scales")
+                            .show(ui, |ui| {
+                                for script in Script::ALL {
+                                    let scale = self.script_font_scales.entry(script).or_insert(1.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label(RichText::new(script.label()).size(13.0));
+                                        ui.add_space(10.0);
+                                        ui.add(
+                                            Slider::new(scale, 0.8..=1.5)
+                                                .step_by(0.05)
+                                                .suffix("×")
+                                                .show_value(true),
+                                        );
+                                    });
+                                }
+                            });
+                        ui.add_space(15.0);
+
+                        // Reveal streamed text at a steady rate
+                        Self::limit_ui(
+                            ui,
+                            "⌨Smooth Typing:",
+                            &mut self.smooth_typing,
+                            (120, 20..=2000),
+                            " chars/s",
+                        );
+                        ui.label(
+                            RichText::new(
+                                "Types out a streaming translation at a steady rate instead of in bursts. Falls behind by at most two seconds.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(15.0);
+
                         // Theme
                         ui.horizontal(|ui| {
                             ui.label(RichText::new("🌗Theme:").size(14.0));
@@ -136,42 +566,64 @@ impl SettingsPanel {
                             ui.add_space(8.0);
                             ui.radio_value(&mut self.theme_preference, ThemePreference::System, "💻 System");
                         });
+                        ui.add_space(15.0);
 
-                        ui.add_space(20.0);
-                        ui.separator();
-                        ui.add_space(12.0);
-
-                        // Translation Features Section
+                        // Source panel layout
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("📝Translation Features").strong().size(18.0));
+                            ui.label(RichText::new("📐Source Panel:").size(14.0));
+                            ui.add_space(10.0);
+                            egui::ComboBox::from_id_salt("source_panel_layout")
+                                .selected_text(self.source_panel_layout.label())
+                                .show_ui(ui, |ui| {
+                                    for layout in SourcePanelLayout::ALL {
+                                        ui.selectable_value(
+                                            &mut self.source_panel_layout,
+                                            layout,
+                                            layout.label(),
+                                        );
+                                    }
+                                });
                         });
-                        ui.add_space(12.0);
+                        ui.add_space(15.0);
 
-                        // Keyword Analysis Toggle
+                        // Side panel or tabs, by the window's width unless pinned
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("🔍Keyword Analysis:").size(14.0));
+                            ui.label(RichText::new("🗔 Window Layout:").size(14.0));
                             ui.add_space(10.0);
-                            ui.checkbox(&mut self.enable_keyword_analysis, "");
+                            egui::ComboBox::from_id_salt("pinned_layout")
+                                .selected_text(
+                                    self.pinned_layout.map_or("Automatic", |a| a.label()),
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.pinned_layout, None, "Automatic");
+                                    for arrangement in Arrangement::ALL {
+                                        ui.selectable_value(
+                                            &mut self.pinned_layout,
+                                            Some(arrangement),
+                                            arrangement.label(),
+                                        );
+                                    }
+                                });
                         });
                         ui.label(
                             RichText::new(
-                                "When enabled, analyze professional terms and provide explanations during translation. Each term on a separate line.",
+                                "Automatic shows the input and the result as tabs in narrow windows.",
                             )
                             .size(12.0)
                             .weak()
                             .color(Color32::GRAY),
                         );
-                        ui.add_space(12.0);
+                        ui.add_space(15.0);
 
-                        // Think Enable Toggle
+                        // Sidebar collapse in narrow windows
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("🌟Thinking Mode:").size(14.0));
+                            ui.label(RichText::new("📚Auto-collapse Sidebar:").size(14.0));
                             ui.add_space(10.0);
-                            ui.checkbox(&mut self.think_enable, "");
+                            ui.checkbox(&mut self.sidebar_auto_collapse, "");
                         });
                         ui.label(
                             RichText::new(
-                                "When enabled, TTS will use thinking mode for better speech generation quality.",
+                                "Shrink the sidebar to an icon rail when the window is narrower than 720 px.",
                             )
                             .size(12.0)
                             .weak()
@@ -179,16 +631,40 @@ impl SettingsPanel {
                         );
                         ui.add_space(12.0);
 
-                        // Coding Plan Toggle
+                        // Extra font for scripts the bundled fonts lack
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("💻Coding Plan Mode:").size(14.0));
+                            ui.label(RichText::new("🔡Extra Font:").size(14.0));
                             ui.add_space(10.0);
-                            ui.checkbox(&mut self.coding_plan, "");
+                            if ui
+                                .button("📂Load custom font…")
+                                .on_hover_text("Choose a .ttf or .otf file")
+                                .clicked()
+                                && let Some(path) = theme::pick_font_file()
+                            {
+                                settings_changed = Some(SettingsChange::CustomFont(Some(path)));
+                            }
+                            if self.custom_font_path.is_some()
+                                && ui
+                                    .small_button("✖")
+                                    .on_hover_text("Stop using the custom font")
+                                    .clicked()
+                            {
+                                settings_changed = Some(SettingsChange::CustomFont(None));
+                            }
                         });
+                        let font_name = self
+                            .custom_font_path
+                            .as_ref()
+                            .and_then(|path| path.file_name())
+                            .map(|name| name.to_string_lossy().to_string());
                         ui.label(
-                            RichText::new(
-                                "When enabled, TTS will use coding plan mode for optimized speech generation.",
-                            )
+                            RichText::new(match font_name {
+                                Some(name) => format!(
+                                    "Using {} for characters the bundled fonts can't show.",
+                                    name
+                                ),
+                                None => "Load a font for scripts such as Thai or Hindi that show up as boxes.".to_string(),
+                            })
                             .size(12.0)
                             .weak()
                             .color(Color32::GRAY),
@@ -198,118 +674,956 @@ impl SettingsPanel {
                         ui.separator();
                         ui.add_space(12.0);
 
-                        // TTS Section
+                        // Translation Features Section
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("🔊TTS Settings").strong().size(18.0));
+                            ui.label(RichText::new("📝Translation Features").strong().size(18.0));
                         });
                         ui.add_space(12.0);
 
-                        // Voice Selection
+                        // Model of regular requests
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("🎤Voice:").size(14.0));
+                            ui.label(RichText::new("🤖Model:").size(14.0));
                             ui.add_space(10.0);
-
-                            let voices = AppConfig::get_supported_voices();
-                            egui::ComboBox::from_id_salt("voice_selector")
-                                .selected_text(RichText::new(&self.tts_voice).size(14.0))
-                                .width(150.0)
+                            egui::ComboBox::from_id_salt("chat_model")
+                                .selected_text(&self.model)
                                 .show_ui(ui, |ui| {
-                                    for voice in voices {
+                                    for model in KNOWN_MODELS {
                                         ui.selectable_value(
-                                            &mut self.tts_voice,
-                                            voice.to_string(),
-                                            voice,
+                                            &mut self.model,
+                                            model.to_string(),
+                                            *model,
                                         );
                                     }
                                 });
+                            ui.add(
+                                TextEdit::singleline(&mut self.model)
+                                    .hint_text(DEFAULT_MODEL)
+                                    .desired_width(140.0),
+                            );
                         });
-                        ui.add_space(15.0);
+                        ui.label(
+                            RichText::new(
+                                "Pick a model or type the name of another. Each model keeps its own cached translations unless the cache is shared.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        // Speed Slider
+                        // Keyword Analysis Toggle
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("⚡Speed:").size(14.0));
+                            ui.label(RichText::new("🔍Keyword Analysis:").size(14.0));
                             ui.add_space(10.0);
-                            ui.add(
-                                Slider::new(&mut self.tts_speed, 0.5..=2.0)
-                                    .step_by(0.1)
-                                    .suffix("x")
-                                    .show_value(true),
-                            );
+                            ui.checkbox(&mut self.enable_keyword_analysis, "");
                         });
-                        ui.add_space(15.0);
+                        ui.label(
+                            RichText::new(
+                                "When enabled, analyze professional terms and provide explanations during translation. Each term on a separate line.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        // Volume Slider
+                        // Alternatives for short inputs
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("🔈Volume:").size(14.0));
+                            ui.label(RichText::new("🔀Show Alternatives:").size(14.0));
                             ui.add_space(10.0);
-                            ui.add(
-                                Slider::new(&mut self.tts_volume, 0.0..=10.0)
-                                    .step_by(0.5)
-                                    .show_value(true),
-                            );
+                            ui.checkbox(&mut self.show_alternatives, "");
                         });
+                        ui.label(
+                            RichText::new(
+                                "For inputs under six words, offer up to three translations with a short explanation each. Click one to use it.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        ui.add_space(20.0);
-                        ui.separator();
+                        // Second attempt after the provider declines
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🛡Retry Declined Translations:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.retry_refusals, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "When the provider declines to translate a text, try once more with a note that the text is only being translated. Helps with borderline content.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
                         ui.add_space(12.0);
 
-                        // Cache Management Section
+                        // Two-stage translation through a pivot language
                         ui.horizontal(|ui| {
-                            ui.label(RichText::new("💾Cache Management").strong().size(18.0));
+                            ui.label(RichText::new("🔁Pivot via:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.pivot_enabled, "");
+                            if self.pivot_enabled {
+                                egui::ComboBox::from_id_salt("pivot_language")
+                                    .selected_text(&self.pivot_language)
+                                    .show_ui(ui, |ui| {
+                                        for language in AppConfig::get_supported_languages() {
+                                            ui.selectable_value(
+                                                &mut self.pivot_language,
+                                                language.to_string(),
+                                                language,
+                                            );
+                                        }
+                                    });
+                            }
                         });
+                        ui.label(
+                            RichText::new(
+                                "Translate into this language first, then into the target language. Helps pairs the model handles poorly directly, at the cost of a second request. The intermediate text is shown below the translation.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
                         ui.add_space(12.0);
 
-                        // Translation Cache
+                        // Spell check of the source text
                         ui.horizontal(|ui| {
-                            ui.label(
-                                RichText::new(format!(
-                                    "Translation cache: {} entries",
-                                    translation_cache.as_ref().map_or(0, |c| c.len())
-                                ))
-                                .size(14.0),
+                            ui.label(RichText::new("🔤Spell Check:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.add_enabled(
+                                !self.spellcheck_languages.is_empty(),
+                                Checkbox::without_text(&mut self.spellcheck_enabled),
                             );
+                            if self.spellcheck_enabled && !self.spellcheck_languages.is_empty() {
+                                egui::ComboBox::from_id_salt("spellcheck_language")
+                                    .selected_text(&self.spellcheck_language)
+                                    .show_ui(ui, |ui| {
+                                        for language in &self.spellcheck_languages {
+                                            ui.selectable_value(
+                                                &mut self.spellcheck_language,
+                                                language.clone(),
+                                                language,
+                                            );
+                                        }
+                                    });
+                            }
                         });
-                        ui.add_space(8.0);
+                        ui.label(
+                            RichText::new(if self.spellcheck_languages.is_empty() {
+                                "No spelling dictionaries found. Install Hunspell dictionaries (.aff and .dic) to enable spell checking."
+                            } else {
+                                "Underline misspelled words in the source text. Right-click a word for suggestions."
+                            })
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        if ui
-                            .add(
-                                egui::Button::new(
-                                    RichText::new("Clear Translation Cache").size(13.0),
-                                )
-                                .corner_radius(6.0),
+                        // Invisible character cleanup of pasted text
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🧹Clean Pasted Text:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.sanitize_source_text, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Remove zero-width characters, soft hyphens, bidi marks and BOMs, and replace non-breaking spaces, when text is pasted or opened. Typed text is left alone.",
                             )
-                            .clicked()
-                        {
-                            settings_changed = Some(SettingsChange::ClearTranslationCache);
-                        }
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        ui.add_space(15.0);
+                        Self::redaction_ui(
+                            ui,
+                            &mut self.redact_sensitive,
+                            &mut self.redaction_preview,
+                            &mut self.redaction_patterns,
+                        );
+                        ui.add_space(12.0);
 
-                        // Audio Cache
+                        Self::cache_exclusions_ui(ui, &mut self.cache_exclusions);
+                        ui.add_space(12.0);
+
+                        Self::completion_hook_ui(
+                            ui,
+                            &mut self.completion_hook_enabled,
+                            &mut self.confirming_completion_hook,
+                            &mut self.completion_hook,
+                            &mut self.completion_hook_timeout_secs,
+                        );
+                        ui.add_space(12.0);
+
+                        Self::typography_ui(
+                            ui,
+                            &mut self.normalize_typography,
+                            &mut self.quote_style,
+                        );
+                        ui.add_space(12.0);
+
+                        // Listening practice
                         ui.horizontal(|ui| {
-                            ui.label(
-                                RichText::new(format!("Audio cache: {} files", audio_cache_len))
-                                    .size(14.0),
-                            );
+                            ui.label(RichText::new("🎓Practice Mode:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.practice_mode, "");
                         });
-                        ui.add_space(8.0);
+                        ui.label(
+                            RichText::new(
+                                "Hide finished translations until you reveal them, so you can listen first and then check your understanding.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        if ui
-                            .add(
-                                egui::Button::new(RichText::new("Clear Audio Cache").size(13.0))
-                                    .corner_radius(6.0),
+                        // Model thinking field for translation requests
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🧠Model Thinking:").size(14.0));
+                            ui.add_space(10.0);
+                            egui::ComboBox::from_id_salt("chat_thinking_selector")
+                                .selected_text(
+                                    self.chat_thinking.map_or("Provider default", |m| m.label()),
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.chat_thinking,
+                                        None,
+                                        "Provider default",
+                                    );
+                                    for mode in ThinkingMode::ALL {
+                                        ui.selectable_value(
+                                            &mut self.chat_thinking,
+                                            Some(mode),
+                                            mode.label(),
+                                        );
+                                    }
+                                });
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Controls the thinking field sent with translation requests. Disabling it reduces latency; \"Omit field\" is safest for OpenAI-compatible servers.",
                             )
-                            .clicked()
-                        {
-                            settings_changed = Some(SettingsChange::ClearAudioCache);
-                        }
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
 
-                        ui.add_space(25.0);
-                        ui.separator();
-                        ui.add_space(15.0);
-                    });
-                });
-            });
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🗑Discard Reasoning:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.discard_reasoning, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Drop the model's reasoning as it arrives instead of showing it and keeping it in the translation log.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Output length limit
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("📏Max Output Tokens:").size(14.0));
+                            ui.add_space(10.0);
+                            let mut limited = self.max_tokens.is_some();
+                            if ui.checkbox(&mut limited, "Limit").changed() {
+                                self.max_tokens = limited.then_some(DEFAULT_MAX_TOKENS);
+                            }
+                            if let Some(max_tokens) = &mut self.max_tokens {
+                                ui.add(
+                                    DragValue::new(max_tokens)
+                                        .range(256..=131_072)
+                                        .speed(64.0),
+                                );
+                            }
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Caps the length of each response. Raise it if long translations stop mid-sentence; unset uses the provider default.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Stopping responses stuck in a loop
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔂Stop Repeating Output:").size(14.0));
+                            ui.add_space(10.0);
+                            let mut limited = self.repetition_limit.is_some();
+                            if ui.checkbox(&mut limited, "After").changed() {
+                                self.repetition_limit =
+                                    limited.then_some(repetition::DEFAULT_MAX_REPEATS);
+                            }
+                            if let Some(max_repeats) = &mut self.repetition_limit {
+                                ui.add(DragValue::new(max_repeats).range(3..=50));
+                                ui.label("repeats");
+                            }
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Stops a response once the model repeats the same text this many times in a row, keeping what came before. Raise it for lyrics or chants.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Short texts in one round trip
+                        Self::limit_ui(
+                            ui,
+                            "⚡Fast Path Up To:",
+                            &mut self.fast_path_chars,
+                            (200, 20..=2000),
+                            " chars",
+                        );
+                        if self.fast_path_chars.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.label("Fast model:");
+                                ui.add(
+                                    TextEdit::singleline(&mut self.fast_path_model)
+                                        .hint_text("Same as regular")
+                                        .desired_width(160.0),
+                                );
+                            });
+                        }
+                        ui.label(
+                            RichText::new(
+                                "Short texts are translated without thinking and shown at once when the whole response arrives, optionally by a smaller, faster model.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Prices for cost estimates
+                        Self::pricing_ui(
+                            ui,
+                            &self.model,
+                            self.fast_path_chars.map(|_| self.fast_path_model.trim()),
+                            &mut self.model_pricing,
+                            &mut self.output_ratio,
+                        );
+                        ui.label(
+                            RichText::new(
+                                "Prices per 1000 tokens. Texts of 2000 characters and more get a cost estimate under the Translate button, and the actual cost once translated. The output is expected at the ratio until translations of the language pair were seen.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Warm up the connection at startup
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔌Pre-connect on Startup:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.preconnect_on_startup, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Connects to the provider in the background when the app starts, so the first translation arrives sooner. Takes effect on the next start; no tokens are spent.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Giving up on an unresponsive provider
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("⏱Timeouts:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.label(RichText::new("Connect").size(12.0));
+                            ui.add(
+                                DragValue::new(&mut self.connect_timeout_secs)
+                                    .range(config::CONNECT_TIMEOUT_RANGE)
+                                    .suffix(" s"),
+                            );
+                            ui.label(RichText::new("Response").size(12.0));
+                            ui.add(
+                                DragValue::new(&mut self.request_timeout_secs)
+                                    .range(config::REQUEST_TIMEOUT_RANGE)
+                                    .suffix(" s"),
+                            );
+                        });
+                        ui.label(
+                            RichText::new(
+                                "A translation fails when no connection is made within the connect time, or when the provider doesn't start to answer or stops sending for the response time.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Sending a request again while the provider is busy
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔁Attempts:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.add(DragValue::new(&mut self.request_attempts).range(1..=6));
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Times a request is sent when the provider is busy (429), has a server error (5xx) or can't be reached, waiting longer before each retry. A rejected API key fails at once.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Providers sending escaped content
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("⤵Unescape Content:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.unescape_content, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Turns literal \\n, \\t, \\\", \\\\, \\* and \\u{…} sequences in responses into the characters they stand for. Only needed for providers that send their text escaped.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Manual update checks from the About window
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔄Update Checks:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.check_for_updates, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Adds \"Check for updates\" to the About window, which asks GitHub for the latest release. Nothing is checked or downloaded on its own.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Think Enable Toggle
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🌟Thinking Mode:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.think_enable, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "When enabled, TTS will use thinking mode for better speech generation quality.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        // Coding Plan Toggle
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("💻Coding Plan Mode:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.coding_plan, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "When enabled, TTS will use coding plan mode for optimized speech generation.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.add_space(12.0);
+
+                        // TTS Section
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔊TTS Settings").strong().size(18.0));
+                        });
+                        ui.add_space(12.0);
+
+                        // Voice Selection
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🎤Voice:").size(14.0));
+                            ui.add_space(10.0);
+
+                            let voices = AppConfig::get_supported_voices();
+                            egui::ComboBox::from_id_salt("voice_selector")
+                                .selected_text(RichText::new(&self.tts_voice).size(14.0))
+                                .width(150.0)
+                                .show_ui(ui, |ui| {
+                                    for voice in voices {
+                                        ui.selectable_value(
+                                            &mut self.tts_voice,
+                                            voice.to_string(),
+                                            voice,
+                                        );
+                                    }
+                                });
+                        });
+                        ui.add_space(15.0);
+
+                        // Speed Slider
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("⚡Speed:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.add(
+                                Slider::new(&mut self.tts_speed, 0.5..=2.0)
+                                    .step_by(0.1)
+                                    .suffix("x")
+                                    .show_value(true),
+                            );
+                        });
+                        ui.add_space(15.0);
+
+                        // Volume Slider
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔈Synthesis Volume:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.add(
+                                Slider::new(&mut self.tts_volume, 0.0..=10.0)
+                                    .step_by(0.5)
+                                    .show_value(true),
+                            );
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Loudness baked into the generated audio, e.g. for export. Changing it re-synthesizes. Use the speaker button next to Play to change the listening volume.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(15.0);
+
+                        // Giving up on a stuck conversion
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("⏱Synthesis Timeouts:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.label(RichText::new("Per round").size(12.0));
+                            ui.add(
+                                DragValue::new(&mut self.tts_segment_timeout_secs)
+                                    .range(5..=300)
+                                    .suffix(" s"),
+                            );
+                            ui.label(RichText::new("In all").size(12.0));
+                            ui.add(
+                                DragValue::new(&mut self.tts_timeout_secs)
+                                    .range(10..=1800)
+                                    .suffix(" s"),
+                            );
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Long texts are converted a few segments at a time. Each round of segments gets the per-round time, and a conversion fails once it takes longer than the time in all.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(15.0);
+
+                        // Overlapping speech with streaming
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🗨Speak While Translating:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.speak_while_translating, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "For languages that speak automatically, start reading each sentence as soon as it is translated instead of waiting for the whole translation.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.add_space(12.0);
+
+                        // Per-language preferences
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🌐Language Profiles").strong().size(18.0));
+                        });
+                        ui.add_space(8.0);
+                        ui.label(
+                            RichText::new(
+                                "Applied whenever the language is selected as the target. Languages without a profile use the settings above.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(12.0);
+
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🌍Language:").size(14.0));
+                            ui.add_space(10.0);
+                            egui::ComboBox::from_id_salt("profile_language_selector")
+                                .selected_text(RichText::new(&self.profile_language).size(14.0))
+                                .width(150.0)
+                                .show_ui(ui, |ui| {
+                                    for lang in AppConfig::get_supported_languages() {
+                                        ui.horizontal(|ui| {
+                                            ui.selectable_value(
+                                                &mut self.profile_language,
+                                                lang.to_string(),
+                                                lang,
+                                            );
+                                            if let Some(profile) = self.language_profiles.get(lang)
+                                            {
+                                                theme::proficiency_tag_ui(ui, profile.proficiency);
+                                            }
+                                        });
+                                    }
+                                });
+                        });
+                        ui.add_space(12.0);
+
+                        let language = self.profile_language.clone();
+                        match self.language_profiles.get_mut(&language) {
+                            Some(profile) => {
+                                if Self::language_profile_ui(ui, profile) {
+                                    self.language_profiles.remove(&language);
+                                }
+                            }
+                            None => {
+                                if ui
+                                    .add(
+                                        egui::Button::new(
+                                            RichText::new(format!("Create Profile for {}", language))
+                                                .size(13.0),
+                                        )
+                                        .corner_radius(6.0),
+                                    )
+                                    .clicked()
+                                {
+                                    self.language_profiles.insert(
+                                        language,
+                                        LanguageProfile::new(&self.tts_voice, self.tts_speed),
+                                    );
+                                }
+                            }
+                        }
+
+                        ui.add_space(20.0);
+                        ui.separator();
+                        ui.add_space(12.0);
+
+                        // Cache Management Section
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("💾Cache Management").strong().size(18.0));
+                        });
+                        ui.add_space(12.0);
+
+                        // Translation Cache
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "Translation cache: {} entries ({} legacy)",
+                                    translation_cache.as_ref().map_or(0, |c| c.len()),
+                                    translation_cache.as_ref().map_or(0, |c| c.legacy_len())
+                                ))
+                                .size(14.0),
+                            );
+                        });
+                        ui.add_space(8.0);
+
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🔗Share Between Models:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.shared_cache, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Each model keeps its own cached translations, falling back to the legacy ones from before they were split. Sharing uses one cache for every model, as before.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(8.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    RichText::new("Clear Translation Cache").size(13.0),
+                                )
+                                .corner_radius(6.0),
+                            )
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::ClearTranslationCache);
+                        }
+
+                        ui.add_space(15.0);
+
+                        // Audio Cache
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!("Audio cache: {} files", audio_cache_len))
+                                    .size(14.0),
+                            );
+                        });
+                        ui.add_space(8.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(RichText::new("Clear Audio Cache").size(13.0))
+                                    .corner_radius(6.0),
+                            )
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::ClearAudioCache);
+                        }
+
+                        ui.add_space(25.0);
+                        ui.separator();
+                        ui.add_space(15.0);
+
+                        // Config backup
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🗂Configuration").strong().size(18.0));
+                        });
+                        ui.add_space(12.0);
+
+                        if ui
+                            .add_enabled(
+                                AppConfig::has_backup(),
+                                egui::Button::new(
+                                    RichText::new("Restore Previous Config").size(13.0),
+                                )
+                                .corner_radius(6.0),
+                            )
+                            .on_hover_text("Replace the settings with the previously saved version")
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::RestoreConfig);
+                        }
+                        ui.add_space(8.0);
+
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    RichText::new("Reload Config from File").size(13.0),
+                                )
+                                .corner_radius(6.0),
+                            )
+                            .on_hover_text(format!(
+                                "Apply changes made to {} while the app is running",
+                                AppConfig::config_path().display()
+                            ))
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::ReloadConfigFromFile);
+                        }
+                        ui.add_space(12.0);
+
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("👁Watch Config File:").size(14.0));
+                            ui.add_space(10.0);
+                            ui.checkbox(&mut self.watch_config_file, "");
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Applies changes saved to the config file in another editor right away, without reloading by hand. An invalid file is reported and the current settings are kept.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+
+                        ui.add_space(25.0);
+                        ui.separator();
+                        ui.add_space(15.0);
+
+                        // Diagnostics for bug reports
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🩺Diagnostics").strong().size(18.0));
+                        });
+                        ui.add_space(12.0);
+
+                        ui.checkbox(
+                            &mut self.bundle_strip_text,
+                            "Leave out source and translation text",
+                        );
+                        ui.add_space(8.0);
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    RichText::new("Create Diagnostic Bundle").size(13.0),
+                                )
+                                .corner_radius(6.0),
+                            )
+                            .on_hover_text(
+                                "Save system details, the redacted config, recent logs and cache statistics to attach to a bug report",
+                            )
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::CreateDiagnosticBundle {
+                                strip_text: self.bundle_strip_text,
+                            });
+                        }
+                        ui.label(
+                            RichText::new("The API key is never included.")
+                                .size(12.0)
+                                .weak()
+                                .color(Color32::GRAY),
+                        );
+                        if !self.cache_exclusions.is_empty() {
+                            ui.label(
+                                RichText::new(
+                                    "⚠ The config in the bundle includes the Never Cache rules and the names or patterns in them.",
+                                )
+                                .size(12.0)
+                                .color(ui.visuals().warn_fg_color),
+                            );
+                        }
+
+                        ui.add_space(25.0);
+                        ui.separator();
+                        ui.add_space(15.0);
+
+                        // Clean-up of diagnostics and old logs
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("🧹Storage").strong().size(18.0));
+                        });
+                        ui.add_space(12.0);
+
+                        Self::limit_ui(
+                            ui,
+                            "🗓Delete After:",
+                            &mut self.retention_days,
+                            (30, 1..=365),
+                            " days",
+                        );
+                        Self::limit_ui(
+                            ui,
+                            "📦Size Budget:",
+                            &mut self.storage_budget_mb,
+                            (200, 10..=10_000),
+                            " MB per folder",
+                        );
+                        Self::limit_ui(
+                            ui,
+                            "🗜Archive Log Over:",
+                            &mut self.log_rotate_mb,
+                            (10, 1..=1000),
+                            " MB",
+                        );
+                        ui.label(
+                            RichText::new(
+                                "Diagnostic bundles and archived logs past their age or over the budget are deleted at startup, oldest first. An archived log is compressed and leaves the history. Files you save elsewhere are never touched.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(8.0);
+
+                        Grid::new("storage_usage")
+                            .num_columns(3)
+                            .spacing([12.0, 4.0])
+                            .show(ui, |ui| {
+                                for usage in storage {
+                                    ui.label(RichText::new(usage.category.label()).size(13.0));
+                                    ui.label(
+                                        RichText::new(format!(
+                                            "{} · {} item{}",
+                                            retention::format_size(usage.bytes),
+                                            usage.entries,
+                                            if usage.entries == 1 { "" } else { "s" }
+                                        ))
+                                        .size(13.0),
+                                    );
+                                    if ui
+                                        .small_button("📂")
+                                        .on_hover_text(format!("Open {}", usage.dir.display()))
+                                        .clicked()
+                                    {
+                                        settings_changed =
+                                            Some(SettingsChange::OpenFolder(usage.dir.clone()));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        ui.add_space(8.0);
+                        if ui
+                            .add(
+                                egui::Button::new(RichText::new("Clean Now").size(13.0))
+                                    .corner_radius(6.0),
+                            )
+                            .on_hover_text(
+                                "Compress archived logs and delete what the limits above let go",
+                            )
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::CleanStorage);
+                        }
+                        ui.add_space(12.0);
+
+                        // What the translation log keeps
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("📝Log Detail:").size(14.0));
+                            ui.add_space(10.0);
+                            egui::ComboBox::from_id_salt("log_detail")
+                                .selected_text(self.log_detail.label())
+                                .show_ui(ui, |ui| {
+                                    for detail in LogDetail::ALL {
+                                        ui.selectable_value(
+                                            &mut self.log_detail,
+                                            detail,
+                                            detail.label(),
+                                        );
+                                    }
+                                });
+                        });
+                        ui.label(
+                            RichText::new(
+                                "Metadata only keeps the languages, lengths, model and timings of a translation but none of its text, so it stays out of the history. Off writes neither the log nor the request metrics.",
+                            )
+                            .size(12.0)
+                            .weak()
+                            .color(Color32::GRAY),
+                        );
+                        ui.add_space(8.0);
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    RichText::new("Purge Content from Existing Log").size(13.0),
+                                )
+                                .corner_radius(6.0),
+                            )
+                            .on_hover_text(
+                                "Remove the source texts, translations and reasoning from the translation log, keeping the metadata of every entry. This can't be undone.",
+                            )
+                            .clicked()
+                        {
+                            settings_changed = Some(SettingsChange::PurgeLogContent);
+                        }
+
+                        ui.add_space(25.0);
+                        ui.separator();
+                        ui.add_space(15.0);
+                    });
+                });
+            });
 
         // Detect changes and apply immediately
         if self.font_size != old_font_size {
@@ -322,24 +1636,607 @@ impl SettingsPanel {
             settings_changed = Some(SettingsChange::TtsSpeed(self.tts_speed));
         } else if self.tts_volume != old_tts_volume {
             settings_changed = Some(SettingsChange::TtsVolume(self.tts_volume));
+        } else if (self.tts_timeout_secs, self.tts_segment_timeout_secs) != old_tts_timeouts {
+            settings_changed = Some(SettingsChange::TtsTimeouts {
+                total_secs: self.tts_timeout_secs,
+                segment_secs: self.tts_segment_timeout_secs,
+            });
+        } else if self.speak_while_translating != old_speak_while_translating {
+            settings_changed = Some(SettingsChange::SpeakWhileTranslating(
+                self.speak_while_translating,
+            ));
         } else if self.enable_keyword_analysis != old_enable_keyword_analysis {
             settings_changed = Some(SettingsChange::KeywordAnalysis(
                 self.enable_keyword_analysis,
             ));
+        } else if self.show_alternatives != old_show_alternatives {
+            settings_changed = Some(SettingsChange::ShowAlternatives(self.show_alternatives));
+        } else if self.retry_refusals != old_retry_refusals {
+            settings_changed = Some(SettingsChange::RetryRefusals(self.retry_refusals));
+        } else if self.pivot_enabled != old_pivot_enabled {
+            settings_changed = Some(SettingsChange::PivotEnabled(self.pivot_enabled));
+        } else if self.pivot_language != old_pivot_language {
+            settings_changed = Some(SettingsChange::PivotLanguage(self.pivot_language.clone()));
+        } else if self.spellcheck_enabled != old_spellcheck_enabled {
+            settings_changed = Some(SettingsChange::Spellcheck(self.spellcheck_enabled));
+        } else if self.spellcheck_language != old_spellcheck_language {
+            settings_changed = Some(SettingsChange::SpellcheckLanguage(
+                self.spellcheck_language.clone(),
+            ));
         } else if self.think_enable != old_think_enable {
             settings_changed = Some(SettingsChange::ThinkEnable(self.think_enable));
         } else if self.coding_plan != old_coding_plan {
             settings_changed = Some(SettingsChange::CodingPlan(self.coding_plan));
+        } else if self.chat_thinking != old_chat_thinking {
+            settings_changed = Some(SettingsChange::ChatThinking(self.chat_thinking));
+        } else if self.discard_reasoning != old_discard_reasoning {
+            settings_changed = Some(SettingsChange::DiscardReasoning(self.discard_reasoning));
+        } else if self.model != old_model {
+            settings_changed = Some(SettingsChange::Model(self.model.trim().to_string()));
+        } else if self.max_tokens != old_max_tokens {
+            settings_changed = Some(SettingsChange::MaxTokens(self.max_tokens));
+        } else if self.repetition_limit != old_repetition_limit {
+            settings_changed = Some(SettingsChange::RepetitionLimit(self.repetition_limit));
+        } else if (self.fast_path_chars, self.fast_path_model.clone()) != old_fast_path {
+            settings_changed = Some(SettingsChange::FastPath {
+                max_chars: self.fast_path_chars,
+                model: self.fast_path_model.trim().to_string(),
+            });
+        } else if (self.model_pricing.clone(), self.output_ratio) != old_pricing {
+            settings_changed = Some(SettingsChange::Pricing {
+                model_pricing: self.model_pricing.clone(),
+                output_ratio: self.output_ratio,
+            });
+        } else if self.shared_cache != old_shared_cache {
+            settings_changed = Some(SettingsChange::SharedCache(self.shared_cache));
+        } else if self.preconnect_on_startup != old_preconnect_on_startup {
+            settings_changed = Some(SettingsChange::PreconnectOnStartup(
+                self.preconnect_on_startup,
+            ));
+        } else if (self.connect_timeout_secs, self.request_timeout_secs) != old_timeouts {
+            settings_changed = Some(SettingsChange::Timeouts {
+                connect_secs: self.connect_timeout_secs,
+                request_secs: self.request_timeout_secs,
+            });
+        } else if self.request_attempts != old_request_attempts {
+            settings_changed = Some(SettingsChange::RequestAttempts(self.request_attempts));
+        } else if self.unescape_content != old_unescape_content {
+            settings_changed = Some(SettingsChange::UnescapeContent(self.unescape_content));
+        } else if (
+            self.retention_days,
+            self.storage_budget_mb,
+            self.log_rotate_mb,
+        ) != old_retention
+        {
+            settings_changed = Some(SettingsChange::Retention {
+                retention_days: self.retention_days,
+                storage_budget_mb: self.storage_budget_mb,
+                log_rotate_mb: self.log_rotate_mb,
+            });
+        } else if self.log_detail != old_log_detail {
+            settings_changed = Some(SettingsChange::LogDetail(self.log_detail));
+        } else if self.check_for_updates != old_check_for_updates {
+            settings_changed = Some(SettingsChange::CheckForUpdates(self.check_for_updates));
+        } else if self.watch_config_file != old_watch_config_file {
+            settings_changed = Some(SettingsChange::WatchConfigFile(self.watch_config_file));
+        } else if self.source_panel_layout != old_source_panel_layout {
+            settings_changed = Some(SettingsChange::SourcePanelLayout(self.source_panel_layout));
+        } else if self.pinned_layout != old_pinned_layout {
+            settings_changed = Some(SettingsChange::PinnedLayout(self.pinned_layout));
+        } else if self.sidebar_auto_collapse != old_sidebar_auto_collapse {
+            settings_changed = Some(SettingsChange::SidebarAutoCollapse(
+                self.sidebar_auto_collapse,
+            ));
+        } else if self.sanitize_source_text != old_sanitize_source_text {
+            settings_changed = Some(SettingsChange::SanitizeSourceText(
+                self.sanitize_source_text,
+            ));
+        } else if (
+            self.redact_sensitive,
+            self.redaction_preview,
+            self.redaction_patterns.clone(),
+        ) != old_redaction
+        {
+            settings_changed = Some(SettingsChange::Redaction {
+                enabled: self.redact_sensitive,
+                preview: self.redaction_preview,
+                patterns: pattern_lines(&self.redaction_patterns),
+            });
+        } else if self.cache_exclusion_rules() != old_cache_exclusions
+            && self
+                .cache_exclusion_rules()
+                .iter()
+                .all(|rule| cache_rules::check(rule).is_ok())
+        {
+            // Invalid rules are shown as errors and only saved once fixed
+            settings_changed = Some(SettingsChange::CacheExclusions(
+                self.cache_exclusion_rules(),
+            ));
+        } else if (
+            self.completion_hook_enabled,
+            self.completion_hook.clone(),
+            self.completion_hook_timeout_secs,
+        ) != old_completion_hook
+        {
+            settings_changed = Some(SettingsChange::CompletionHook {
+                enabled: self.completion_hook_enabled,
+                command: self.completion_hook.clone(),
+                timeout_secs: self.completion_hook_timeout_secs,
+            });
+        } else if (self.normalize_typography, self.quote_style) != old_typography {
+            settings_changed = Some(SettingsChange::Typography {
+                enabled: self.normalize_typography,
+                quotes: self.quote_style,
+            });
+        } else if self.practice_mode != old_practice_mode {
+            settings_changed = Some(SettingsChange::PracticeMode(self.practice_mode));
+        } else if (self.auto_font_source, self.auto_font_translation) != old_auto_font {
+            settings_changed = Some(SettingsChange::AutoFontSize {
+                source: self.auto_font_source,
+                translation: self.auto_font_translation,
+            });
+        } else if self.smooth_typing != old_smooth_typing {
+            settings_changed = Some(SettingsChange::SmoothTyping(self.smooth_typing));
+        } else if self.script_font_scales != old_script_font_scales {
+            settings_changed = Some(SettingsChange::ScriptFontScales(
+                self.script_font_scales.clone(),
+            ));
+        } else if self.profile_language == old_profile_language
+            && self.language_profiles.get(&self.profile_language) != old_language_profile.as_ref()
+        {
+            settings_changed = Some(SettingsChange::LanguageProfile {
+                language: self.profile_language.clone(),
+                profile: self.language_profiles.get(&self.profile_language).cloned(),
+            });
         }
 
         (self.show_panel, settings_changed)
     }
 
+    /// Renders the redaction of sensitive values before sending.
+    fn redaction_ui(ui: &mut Ui, enabled: &mut bool, preview: &mut bool, patterns: &mut String) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🔒Redact Sensitive Data:").size(14.0));
+            ui.add_space(10.0);
+            ui.checkbox(enabled, "");
+        });
+        ui.label(
+            RichText::new(
+                "Replace email addresses, phone numbers and card-like numbers with placeholders before the text is sent, and put them back into the translation on this computer.",
+            )
+            .size(12.0)
+            .weak()
+            .color(Color32::GRAY),
+        );
+        if !*enabled {
+            return;
+        }
+        ui.checkbox(preview, "Show what will be redacted before sending");
+        ui.label(RichText::new("Extra patterns (regular expressions, one per line):").size(12.0));
+        ui.add(
+            TextEdit::multiline(patterns)
+                .hint_text(r"\bEMP-\d{6}\b")
+                .desired_rows(3)
+                .desired_width(f32::INFINITY)
+                .font(TextStyle::Monospace),
+        );
+        for pattern in pattern_lines(patterns) {
+            if let Err(e) = redaction::check_pattern(&pattern) {
+                let error = format!("{} is skipped: {}", pattern, e.lines().last().unwrap_or(""));
+                ui.label(
+                    RichText::new(error)
+                        .size(12.0)
+                        .color(ui.visuals().warn_fg_color),
+                );
+            }
+        }
+    }
+
+    /// Renders the rules for translations that are never cached nor logged.
+    fn cache_exclusions_ui(ui: &mut Ui, drafts: &mut Vec<CacheRuleDraft>) {
+        ui.label(RichText::new("🚫Never Cache:").size(14.0));
+        ui.label(
+            RichText::new(
+                "Translations of source texts matching a pattern (a regular expression, ^ and $ match at each line), into one of the listed target languages, are not kept in the cache or the history. Leave the languages empty for all.",
+            )
+            .size(12.0)
+            .weak()
+            .color(Color32::GRAY),
+        );
+        let mut removed = None;
+        for (index, draft) in drafts.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut draft.name)
+                        .hint_text("Name")
+                        .desired_width(110.0),
+                );
+                ui.add(
+                    TextEdit::singleline(&mut draft.pattern)
+                        .hint_text(r"(?i)\bacme\b")
+                        .desired_width(170.0)
+                        .font(TextStyle::Monospace),
+                );
+                ui.add(
+                    TextEdit::singleline(&mut draft.languages)
+                        .hint_text("All languages")
+                        .desired_width(110.0),
+                )
+                .on_hover_text("Target languages, separated by commas");
+                if ui.small_button("✖").on_hover_text("Remove rule").clicked() {
+                    removed = Some(index);
+                }
+            });
+            if let Err(e) = cache_rules::check(&draft.to_rule()) {
+                let error = format!("Not saved: {}", e.lines().last().unwrap_or(""));
+                ui.label(
+                    RichText::new(error)
+                        .size(12.0)
+                        .color(ui.visuals().warn_fg_color),
+                );
+            }
+        }
+        if let Some(index) = removed {
+            drafts.remove(index);
+        }
+        if ui.button("➕ Add Rule").clicked() {
+            drafts.push(CacheRuleDraft::default());
+        }
+    }
+
+    /// Renders the command run after each successful translation.
+    ///
+    /// Turning it on shows what the command is trusted with first, and
+    /// only enables it once that is confirmed.
+    fn completion_hook_ui(
+        ui: &mut Ui,
+        enabled: &mut bool,
+        confirming: &mut bool,
+        command: &mut String,
+        timeout_secs: &mut u64,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🪝Completion Hook:").size(14.0));
+            ui.add_space(10.0);
+            if ui.checkbox(enabled, "").changed() && *enabled {
+                *enabled = false;
+                *confirming = true;
+            }
+        });
+        ui.label(
+            RichText::new(format!(
+                "Run a command after each successful translation. The source text and the translation are passed in temporary files; use {}, {} and {} in the command. It is started directly, not through a shell. Translations matching a Never Cache rule never run it.",
+                hooks::SOURCE_FILE,
+                hooks::TRANSLATION_FILE,
+                hooks::TARGET_LANG
+            ))
+            .size(12.0)
+            .weak()
+            .color(Color32::GRAY),
+        );
+
+        if *confirming {
+            Frame::group(ui.style()).show(ui, |ui| {
+                ui.label(
+                    RichText::new(
+                        "⚠ The command runs with your permissions every time a translation finishes and can read the full source text and translation. Only enable it for a command you trust.",
+                    )
+                    .size(12.0)
+                    .color(ui.visuals().warn_fg_color),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("I understand, enable").clicked() {
+                        *enabled = true;
+                        *confirming = false;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        *confirming = false;
+                    }
+                });
+            });
+        }
+
+        if !*enabled {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(command)
+                    .hint_text("sh ~/bin/notify.sh {translation_file} {target_lang}")
+                    .desired_width(320.0)
+                    .font(TextStyle::Monospace),
+            );
+            ui.label(RichText::new("Timeout:").size(12.0));
+            ui.add(DragValue::new(timeout_secs).range(1..=600).suffix(" s"));
+        });
+        if let Err(e) = hooks::split_command(command) {
+            ui.label(
+                RichText::new(e.to_string())
+                    .size(12.0)
+                    .color(ui.visuals().warn_fg_color),
+            );
+        }
+    }
+
+    /// The cache exclusion rules as edited.
+    fn cache_exclusion_rules(&self) -> Vec<CacheRule> {
+        self.cache_exclusions
+            .iter()
+            .map(CacheRuleDraft::to_rule)
+            .collect()
+    }
+
+    /// Renders the punctuation normalization of finished translations.
+    fn typography_ui(ui: &mut Ui, enabled: &mut bool, quotes: &mut QuoteStyle) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("✎Normalize Punctuation:").size(14.0));
+            ui.add_space(10.0);
+            ui.checkbox(enabled, "");
+        });
+        ui.label(
+            RichText::new(
+                "Once a translation is complete, use the punctuation of the target language: full-width marks in Chinese and Japanese, no-break spaces before French ?!:; and its quote marks.",
+            )
+            .size(12.0)
+            .weak()
+            .color(Color32::GRAY),
+        );
+        if !*enabled {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Quotes:");
+            egui::ComboBox::from_id_salt("quote_style")
+                .selected_text(quotes.label())
+                .show_ui(ui, |ui| {
+                    for style in QuoteStyle::ALL {
+                        ui.selectable_value(quotes, style, style.label());
+                    }
+                });
+        });
+    }
+
+    /// Renders an optional limit as a checkbox and, when set, its value.
+    ///
+    /// Checking the box starts from the first value of `(default, range)`.
+    fn limit_ui(
+        ui: &mut Ui,
+        label: &str,
+        value: &mut Option<u32>,
+        (default, range): (u32, RangeInclusive<u32>),
+        suffix: &str,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(label).size(14.0));
+            ui.add_space(10.0);
+            let mut limited = value.is_some();
+            if ui.checkbox(&mut limited, "").changed() {
+                *value = limited.then_some(default);
+            }
+            if let Some(value) = value {
+                ui.add(DragValue::new(value).range(range).suffix(suffix));
+            }
+        });
+    }
+
+    /// Renders the prices of the regular `model` and, when one is used,
+    /// the fast path model, each behind a checkbox, and the expected
+    /// output ratio.
+    fn pricing_ui(
+        ui: &mut Ui,
+        model: &str,
+        fast_path_model: Option<&str>,
+        model_pricing: &mut BTreeMap<String, Pricing>,
+        output_ratio: &mut f64,
+    ) {
+        ui.label(RichText::new("💰Pricing:").size(14.0));
+        let model = match model.trim() {
+            "" => DEFAULT_MODEL,
+            model => model,
+        };
+        let mut models = vec![model];
+        if let Some(fast_model) = fast_path_model
+            && !fast_model.is_empty()
+            && fast_model != model
+        {
+            models.push(fast_model);
+        }
+        for model in models {
+            ui.horizontal(|ui| {
+                let mut priced = model_pricing.contains_key(model);
+                if ui.checkbox(&mut priced, model).changed() {
+                    if priced {
+                        model_pricing.insert(model.to_string(), Pricing::default());
+                    } else {
+                        model_pricing.remove(model);
+                    }
+                }
+                if let Some(pricing) = model_pricing.get_mut(model) {
+                    for (prefix, price) in [
+                        ("in ", &mut pricing.input_per_1k),
+                        ("out ", &mut pricing.output_per_1k),
+                    ] {
+                        ui.add(
+                            DragValue::new(price)
+                                .range(0.0..=100.0)
+                                .speed(0.0001)
+                                .max_decimals(5)
+                                .prefix(prefix),
+                        );
+                    }
+                    ui.add(TextEdit::singleline(&mut pricing.currency).desired_width(40.0));
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Output tokens per input token:");
+            ui.add(
+                DragValue::new(output_ratio)
+                    .range(0.1..=20.0)
+                    .speed(0.05)
+                    .max_decimals(2),
+            );
+        });
+    }
+
+    /// Renders the fields of a language profile, returning whether
+    /// "Remove Profile" was clicked.
+    fn language_profile_ui(ui: &mut Ui, profile: &mut LanguageProfile) -> bool {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🏷Proficiency:").size(14.0));
+            ui.add_space(10.0);
+            egui::ComboBox::from_id_salt("profile_proficiency")
+                .selected_text(
+                    RichText::new(profile.proficiency.label())
+                        .size(14.0)
+                        .color(theme::proficiency_color(profile.proficiency)),
+                )
+                .show_ui(ui, |ui| {
+                    for proficiency in Proficiency::ALL {
+                        ui.selectable_value(
+                            &mut profile.proficiency,
+                            proficiency,
+                            RichText::new(proficiency.label())
+                                .color(theme::proficiency_color(proficiency)),
+                        );
+                    }
+                });
+        });
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("📚Domain:").size(14.0));
+            ui.add_space(10.0);
+            ui.add(
+                TextEdit::singleline(&mut profile.prompt_domain)
+                    .hint_text("optional")
+                    .desired_width(150.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("👥Audience:").size(14.0));
+            ui.add_space(10.0);
+            ui.add(
+                TextEdit::singleline(&mut profile.prompt_audience)
+                    .hint_text("optional")
+                    .desired_width(150.0),
+            );
+        });
+        ui.label(
+            RichText::new("Used when the sidebar leaves the hint empty.")
+                .size(12.0)
+                .weak()
+                .color(Color32::GRAY),
+        );
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🎤Voice:").size(14.0));
+            ui.add_space(10.0);
+            egui::ComboBox::from_id_salt("profile_voice")
+                .selected_text(RichText::new(&profile.tts_voice).size(14.0))
+                .width(150.0)
+                .show_ui(ui, |ui| {
+                    for voice in AppConfig::get_supported_voices() {
+                        ui.selectable_value(&mut profile.tts_voice, voice.to_string(), voice);
+                    }
+                });
+        });
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("⚡Speed:").size(14.0));
+            ui.add_space(10.0);
+            ui.add(
+                Slider::new(&mut profile.tts_speed, 0.5..=2.0)
+                    .step_by(0.1)
+                    .suffix("x")
+                    .show_value(true),
+            );
+        });
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🗣Show Pronunciation:").size(14.0));
+            ui.add_space(10.0);
+            ui.checkbox(&mut profile.show_pronunciation, "");
+        });
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🔈Speak Automatically:").size(14.0));
+            ui.add_space(10.0);
+            ui.checkbox(&mut profile.auto_speak, "");
+        });
+        ui.label(
+            RichText::new("Reads each finished translation aloud.")
+                .size(12.0)
+                .weak()
+                .color(Color32::GRAY),
+        );
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🎯Check Confidence:").size(14.0));
+            ui.add_space(10.0);
+            ui.checkbox(&mut profile.check_confidence, "");
+        });
+        ui.label(
+            RichText::new(
+                "After each translation, asks the model which parts it is least sure of and underlines them. Costs an extra request.",
+            )
+            .size(12.0)
+            .weak()
+            .color(Color32::GRAY),
+        );
+        ui.add_space(10.0);
+
+        ui.add(egui::Button::new(RichText::new("Remove Profile").size(13.0)).corner_radius(6.0))
+            .clicked()
+    }
+
     pub fn toggle_panel(&mut self) {
         self.show_panel = !self.show_panel;
     }
 }
 
+/// A cache exclusion rule being edited, its languages on one line.
+#[derive(Debug, Clone, Default)]
+struct CacheRuleDraft {
+    name: String,
+    pattern: String,
+    languages: String,
+}
+
+impl CacheRuleDraft {
+    fn from_rule(rule: &CacheRule) -> Self {
+        CacheRuleDraft {
+            name: rule.name.clone(),
+            pattern: rule.pattern.clone(),
+            languages: rule.languages.join(", "),
+        }
+    }
+
+    fn to_rule(&self) -> CacheRule {
+        CacheRule {
+            name: self.name.trim().to_string(),
+            pattern: self.pattern.clone(),
+            languages: self
+                .languages
+                .split([',', '，'])
+                .map(str::trim)
+                .filter(|language| !language.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// The non-blank lines of the redaction patterns field.
+fn pattern_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub enum SettingsChange {
     FontSize(f32),
@@ -347,9 +2244,116 @@ pub enum SettingsChange {
     TtsVoice(String),
     TtsSpeed(f32),
     TtsVolume(f32),
+    /// Seconds a conversion may take in all, and per round of segments,
+    /// changed
+    TtsTimeouts {
+        total_secs: u64,
+        segment_secs: u64,
+    },
+    /// Speaking sentence by sentence while translating was turned on or off
+    SpeakWhileTranslating(bool),
     KeywordAnalysis(bool),
+    ShowAlternatives(bool),
+    RetryRefusals(bool),
+    PivotEnabled(bool),
+    PivotLanguage(String),
+    Spellcheck(bool),
+    SpellcheckLanguage(String),
     ThinkEnable(bool),
     CodingPlan(bool),
+    ChatThinking(Option<ThinkingMode>),
+    /// The model's reasoning is dropped as it arrives (`true`) or shown
+    DiscardReasoning(bool),
+    /// Model of regular requests, empty for the default one
+    Model(String),
+    MaxTokens(Option<u32>),
+    RepetitionLimit(Option<usize>),
+    /// Short text fast path limit and model changed
+    FastPath {
+        max_chars: Option<u32>,
+        model: String,
+    },
+    /// Prices of the models or the expected output ratio changed
+    Pricing {
+        model_pricing: BTreeMap<String, Pricing>,
+        output_ratio: f64,
+    },
+    PreconnectOnStartup(bool),
+    /// Seconds given to connecting, and to waiting for the provider to
+    /// respond or to go on, changed
+    Timeouts {
+        connect_secs: u64,
+        request_secs: u64,
+    },
+    /// Times a request is sent before a transient failure is shown changed
+    RequestAttempts(u32),
+    /// Decoding of escape sequences in responses was turned on or off
+    UnescapeContent(bool),
+    /// Whether cached translations are shared between models
+    SharedCache(bool),
+    CheckForUpdates(bool),
+    /// Following the configuration file for changes was turned on or off
+    WatchConfigFile(bool),
+    SourcePanelLayout(SourcePanelLayout),
+    /// Arrangement of the window pinned by the user, `None` to follow its width
+    PinnedLayout(Option<Arrangement>),
+    SidebarAutoCollapse(bool),
+    SanitizeSourceText(bool),
+    /// Redaction of sensitive values before sending was changed
+    Redaction {
+        enabled: bool,
+        preview: bool,
+        patterns: Vec<String>,
+    },
+    /// Rules for translations that are never cached nor logged were
+    /// changed, sent only while all of them are valid
+    CacheExclusions(Vec<CacheRule>),
+    /// The command run after each successful translation was changed
+    CompletionHook {
+        enabled: bool,
+        command: String,
+        timeout_secs: u64,
+    },
+    /// Punctuation normalization of finished translations was changed
+    Typography {
+        enabled: bool,
+        quotes: QuoteStyle,
+    },
+    PracticeMode(bool),
+    AutoFontSize {
+        source: bool,
+        translation: bool,
+    },
+    ScriptFontScales(BTreeMap<Script, f32>),
+    /// Characters per second streaming translations are revealed at,
+    /// `None` shows them as they arrive
+    SmoothTyping(Option<u32>),
+    /// A language profile was edited, created or removed (`None`)
+    LanguageProfile {
+        language: String,
+        profile: Option<LanguageProfile>,
+    },
+    /// Load a different extra font, `None` removes it
+    CustomFont(Option<PathBuf>),
     ClearTranslationCache,
     ClearAudioCache,
+    RestoreConfig,
+    /// Replace the settings with the configuration file
+    ReloadConfigFromFile,
+    CreateDiagnosticBundle {
+        strip_text: bool,
+    },
+    /// Limits for diagnostic bundles and log archives changed
+    Retention {
+        retention_days: Option<u32>,
+        storage_budget_mb: Option<u32>,
+        log_rotate_mb: Option<u32>,
+    },
+    /// Open one of the app's folders in the file manager
+    OpenFolder(PathBuf),
+    /// Compress and delete old files now
+    CleanStorage,
+    LogDetail(LogDetail),
+    /// Strip the texts from the translation log written so far
+    PurgeLogContent,
 }
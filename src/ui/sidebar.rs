@@ -1,11 +1,133 @@
-use crate::utils::config::AppConfig;
+use crate::api::client::{self, DEFAULT_BASE_URL, ThinkingMode};
+use crate::api::prompt::{AUDIENCE_PRESETS, DOMAIN_PRESETS, PromptContext};
+use crate::ui::glossary::TermsInText;
+use crate::ui::spelling::SpellHighlighter;
+use crate::ui::theme;
+use crate::utils::code::CodeLanguage;
+use crate::utils::config::{AppConfig, Proficiency};
+use crate::utils::dialogs::{Dialog, DialogCategory};
+use crate::utils::glossary::TermMatcher;
+use crate::utils::pricing::{self, Estimate};
+use crate::utils::romanize::Scheme;
+use crate::utils::shared_text::{Replica, SharedText};
+use crate::utils::webpage;
 use egui::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Widget ID of the sidebar's source text box, used to move focus to it.
+pub const SOURCE_TEXT_ID: &str = "sidebar_source_text";
+
+/// Width of the collapsed icon rail.
+const RAIL_WIDTH: f32 = 44.0;
+
+/// Windows narrower than this collapse the sidebar automatically.
+const AUTO_COLLAPSE_WIDTH: f32 = 720.0;
+
+/// How much wider than [`AUTO_COLLAPSE_WIDTH`] a narrow window must get to
+/// count as wide again, so resizing around the threshold doesn't flicker.
+const AUTO_EXPAND_MARGIN: f32 = 40.0;
+
+/// Height kept free under the source text for its romanization.
+const ROMANIZATION_HEIGHT: f32 = 110.0;
+
+/// Romanization of the source text, as far as it got.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RomanizationState {
+    Pending,
+    Ready(String),
+    Failed(String),
+}
+
+/// Cost of translating the source text, shown under the Translate button.
+#[derive(Debug, Clone)]
+struct SourceCost {
+    estimate: Estimate,
+    /// What the last translation of the text cost
+    actual: Option<f64>,
+}
+
+/// Romanization shown under the source text it was made for.
+#[derive(Debug, Clone)]
+struct SourceRomanization {
+    source: String,
+    scheme: Scheme,
+    state: RomanizationState,
+}
+
+/// User actions collected while rendering the sidebar.
+#[derive(Debug, Default)]
+pub struct SidebarActions {
+    pub translate: bool,
+    pub cancel: bool,
+    /// API key edited in the key field
+    pub api_key: Option<String>,
+    /// Valid base URL entered in the endpoint field, empty for the default
+    pub base_url: Option<String>,
+    /// "Speak" was clicked on the rail
+    pub speak_source: bool,
+    /// The rail's settings button was clicked
+    pub toggle_settings: bool,
+    /// PDF picked with "Open PDF…"
+    pub open_pdf: Option<PathBuf>,
+    /// JSON or YAML file picked with "Open JSON/YAML…"
+    pub open_document: Option<PathBuf>,
+    /// "Fetch page" was clicked for a source text that is only a link
+    pub fetch_url: Option<String>,
+    /// "Add term…" was clicked, with the selected source text
+    pub add_term: Option<String>,
+    /// "Romanize source" was turned on, or a failed romanization retried
+    pub romanize: bool,
+}
 
 pub struct Sidebar {
     api_key: String,
+    /// API base URL as entered, empty for the default
+    base_url: String,
+    /// Why the entered base URL can't be used
+    base_url_error: Option<String>,
     target_language: String,
-    source_text: String,
+    /// Also edited in the central panel in the editable layout
+    source: SharedText,
+    source_editor: Replica,
     languages: Vec<&'static str>,
+    /// Per-request override of the thinking setting, not persisted
+    thinking_override: Option<ThinkingMode>,
+    /// Translate only comments and strings of source code
+    code_mode: bool,
+    code_language: CodeLanguage,
+    /// Translate numbered lists item by item
+    list_mode: bool,
+    /// Recently used target languages, most recent first
+    recent_languages: Vec<String>,
+    /// Proficiency tags of the languages with a profile
+    proficiencies: BTreeMap<String, Proficiency>,
+    /// Domain hint for the prompt
+    domain: String,
+    /// Audience hint for the prompt
+    audience: String,
+    spelling: SpellHighlighter,
+    /// Glossary terms in the source text
+    terms: TermsInText,
+    /// Text selected in the source box when it last had the focus
+    selection: String,
+    /// Shown as an icon rail instead of the full panel
+    collapsed: bool,
+    /// Width of the full panel, kept while collapsed
+    expanded_width: f32,
+    /// Collapse automatically when the window gets narrow
+    auto_collapse: bool,
+    /// Collapsed by the automation rather than the user
+    auto_collapsed: bool,
+    /// Whether the window was narrow last frame
+    was_narrow: bool,
+    /// Source text popup opened from the rail
+    source_popup_open: bool,
+    /// Show Chinese and Japanese source text romanized under it
+    romanize_source: bool,
+    romanization: Option<SourceRomanization>,
+    cost: Option<SourceCost>,
 }
 
 impl Default for Sidebar {
@@ -13,9 +135,32 @@ impl Default for Sidebar {
         let config = AppConfig::default();
         Sidebar {
             api_key: config.api_key,
+            base_url: config.api_base_url,
+            base_url_error: None,
             target_language: config.target_language,
-            source_text: String::new(),
+            source: SharedText::default(),
+            source_editor: Replica::default(),
             languages: AppConfig::get_supported_languages(),
+            thinking_override: None,
+            code_mode: false,
+            code_language: CodeLanguage::Auto,
+            list_mode: false,
+            recent_languages: Vec::new(),
+            proficiencies: BTreeMap::new(),
+            domain: String::new(),
+            audience: String::new(),
+            spelling: SpellHighlighter::default(),
+            terms: TermsInText::default(),
+            selection: String::new(),
+            collapsed: config.sidebar_collapsed,
+            expanded_width: config.sidebar_width,
+            auto_collapse: config.sidebar_auto_collapse,
+            auto_collapsed: false,
+            was_narrow: false,
+            source_popup_open: false,
+            romanize_source: config.romanize_source,
+            romanization: None,
+            cost: None,
         }
     }
 }
@@ -38,101 +183,636 @@ impl Sidebar {
             .corner_radius(8.0)
     }
 
-    pub fn ui(&mut self, ctx: &Context, is_translating: bool) -> (bool, bool, Option<String>) {
-        let mut translate_requested = false;
-        let mut cancel_requested = false;
-        let mut api_key_to_save = None;
+    /// Renders the target languages of a language selector, tagging those
+    /// with a profile.
+    fn language_options_ui(
+        ui: &mut Ui,
+        target_language: &mut String,
+        languages: &[&'static str],
+        proficiencies: &BTreeMap<String, Proficiency>,
+    ) {
+        for lang in languages {
+            ui.horizontal(|ui| {
+                ui.selectable_value(target_language, lang.to_string(), *lang);
+                if let Some(proficiency) = proficiencies.get(*lang) {
+                    theme::proficiency_tag_ui(ui, *proficiency);
+                }
+            });
+        }
+    }
 
-        SidePanel::right("sidebar")
-            .default_width(300.0)
-            .resizable(true)
-            .show(ctx, |ui| {
-                ui.vertical_centered(|ui| {
-                    ui.heading("Settings");
+    /// Renders a free-text hint with preset suggestions and a clear button.
+    fn hint_field(ui: &mut Ui, label: &str, id: &str, value: &mut String, presets: &[&str]) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(
+                TextEdit::singleline(value)
+                    .hint_text("optional")
+                    .desired_width(110.0),
+            );
+            egui::ComboBox::from_id_salt(id)
+                .selected_text("")
+                .width(24.0)
+                .show_ui(ui, |ui| {
+                    for preset in presets {
+                        if ui
+                            .selectable_label(value.as_str() == *preset, *preset)
+                            .clicked()
+                        {
+                            *value = preset.to_string();
+                        }
+                    }
                 });
+            if ui
+                .add_enabled(!value.is_empty(), Button::new("✕").small())
+                .on_hover_text("Clear")
+                .clicked()
+            {
+                value.clear();
+            }
+        });
+    }
 
-                ui.add_space(10.0);
-                ui.separator();
-                ui.add_space(10.0);
+    /// Collapses the sidebar when the window becomes narrow, and expands it
+    /// again when the window widens if it was collapsed that way.
+    ///
+    /// Only changes in width act, so the user can still expand the sidebar
+    /// in a narrow window.
+    fn auto_collapse_for(&mut self, window_width: f32) {
+        let threshold = if self.was_narrow {
+            AUTO_COLLAPSE_WIDTH + AUTO_EXPAND_MARGIN
+        } else {
+            AUTO_COLLAPSE_WIDTH
+        };
+        let narrow = window_width < threshold;
+        if self.auto_collapse && narrow != self.was_narrow {
+            if narrow && !self.collapsed {
+                self.collapsed = true;
+                self.auto_collapsed = true;
+            } else if !narrow && self.auto_collapsed {
+                self.collapsed = false;
+                self.auto_collapsed = false;
+            }
+        }
+        self.was_narrow = narrow;
+    }
 
-                ui.label("API Key:");
-                ui.add_space(5.0);
+    /// Collapses or expands the sidebar on the user's request.
+    fn set_collapsed(&mut self, collapsed: bool) {
+        self.collapsed = collapsed;
+        self.auto_collapsed = false;
+        self.source_popup_open = false;
+    }
 
-                let key_response = ui.add(
-                    TextEdit::singleline(&mut self.api_key)
-                        .hint_text("Enter your Z.AI API key")
-                        .password(true),
-                );
+    fn can_translate(&self) -> bool {
+        !self.source.as_str().is_empty() && !self.api_key.is_empty()
+    }
 
-                if key_response.lost_focus() || key_response.has_focus() {
-                    api_key_to_save = Some(self.api_key.clone());
-                }
+    /// Renders the source text box with spell check underlines and the
+    /// occurrences of the glossary term picked in the sidebar.
+    fn source_text_ui(&mut self, ui: &mut Ui, max_height: f32) {
+        self.create_text_frame(ui).show(ui, |ui| {
+            ScrollArea::vertical()
+                .max_height(max_height)
+                .id_salt("source_text_scroll")
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    let id = Id::new(SOURCE_TEXT_ID);
+                    let focused = ui.memory(|m| m.has_focus(id));
+                    ui.input(|i| self.source_editor.observe(focused, &i.events));
+                    let text = self.source_editor.sync(&self.source);
+                    self.spelling.update(ui.ctx(), text);
+                    self.terms.update(ui.ctx(), text);
+                    let spelling = &self.spelling;
+                    let terms = &self.terms;
+                    let mut layouter = |ui: &Ui, buf: &dyn TextBuffer, wrap_width: f32| {
+                        let text = buf.as_str();
+                        let job = spelling.layout_job(ui, text, wrap_width, terms.highlights(text));
+                        ui.fonts_mut(|f| f.layout_job(job))
+                    };
+                    let output = TextEdit::multiline(text)
+                        .id(id)
+                        .hint_text("Enter text to translate...")
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(10)
+                        .frame(false)
+                        .layouter(&mut layouter)
+                        .show(ui);
+                    if output.response.has_focus() {
+                        self.selection = output
+                            .cursor_range
+                            .map(|range| range.slice_str(text).to_string())
+                            .unwrap_or_default();
+                    }
+                    self.spelling.context_menu(&output, text);
+                    if self.source_editor.commit(&mut self.source) {
+                        ui.ctx().request_repaint();
+                    }
+                });
+        });
+    }
 
-                ui.add_space(15.0);
+    /// The romanization to show under the source text, if it was made for
+    /// the text as it is now.
+    fn shown_romanization(&self) -> Option<&SourceRomanization> {
+        self.romanization
+            .as_ref()
+            .filter(|shown| self.romanize_source && shown.source == self.source.as_str())
+    }
 
-                ui.label("Target Language:");
-                ui.add_space(5.0);
+    /// Renders the estimated cost of translating the source text, with the
+    /// actual cost once it was translated.
+    fn cost_ui(&self, ui: &mut Ui) {
+        let Some(cost) = &self.cost else {
+            return;
+        };
+        let estimate = &cost.estimate;
+        let text = match cost.actual {
+            Some(actual) => format!(
+                "{} · actual {} {}",
+                estimate.summary(),
+                pricing::format_amount(actual),
+                estimate.currency
+            ),
+            None => estimate.summary(),
+        };
+        ui.label(RichText::new(text).size(12.0).weak())
+            .on_hover_text(format!("Estimated cost: {}", estimate.details()));
+    }
 
-                egui::ComboBox::from_id_salt("language_selector")
-                    .selected_text(&self.target_language)
-                    .show_ui(ui, |ui| {
-                        for lang in &self.languages {
-                            ui.selectable_value(&mut self.target_language, lang.to_string(), *lang);
+    /// Renders the romanization of the source text in a dimmer block with
+    /// its own copy button.
+    fn romanization_ui(&self, ui: &mut Ui, actions: &mut SidebarActions) {
+        let Some(shown) = self.shown_romanization() else {
+            return;
+        };
+        ui.add_space(6.0);
+        Frame::NONE
+            .fill(ui.visuals().faint_bg_color)
+            .inner_margin(Margin::symmetric(12, 8))
+            .corner_radius(8.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(shown.scheme.label()).size(12.0).weak());
+                    if let RomanizationState::Ready(text) = &shown.state {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui
+                                .small_button("📋Copy")
+                                .on_hover_text("Copy the romanization")
+                                .clicked()
+                            {
+                                ui.ctx().copy_text(text.clone());
+                            }
+                        });
+                    }
+                });
+                match &shown.state {
+                    RomanizationState::Pending => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(RichText::new("Romanizing…").weak());
+                        });
+                    }
+                    RomanizationState::Ready(text) => {
+                        ScrollArea::vertical()
+                            .id_salt("romanization_scroll")
+                            .max_height(ROMANIZATION_HEIGHT - 40.0)
+                            .show(ui, |ui| {
+                                ui.add(
+                                    Label::new(
+                                        RichText::new(text).color(ui.visuals().weak_text_color()),
+                                    )
+                                    .selectable(true),
+                                );
+                            });
+                    }
+                    RomanizationState::Failed(reason) => {
+                        ui.label(
+                            RichText::new(format!("Couldn't romanize: {}", reason))
+                                .weak()
+                                .italics(),
+                        );
+                        if ui.small_button("Retry").clicked() {
+                            actions.romanize = true;
                         }
-                    });
-
-                ui.add_space(15.0);
+                    }
+                }
+            });
+    }
 
-                ui.label("Source Text:");
-                ui.add_space(5.0);
+    /// Renders the collapsed sidebar as a narrow column of icon buttons.
+    fn rail_ui(&mut self, ctx: &Context, is_translating: bool, actions: &mut SidebarActions) {
+        let rail_button = |icon: &str| {
+            Button::new(RichText::new(icon).size(18.0))
+                .min_size(vec2(32.0, 32.0))
+                .corner_radius(6.0)
+        };
 
-                // Translate/Cancel control (moved before input box)
+        SidePanel::right("sidebar_rail")
+            .exact_width(RAIL_WIDTH)
+            .resizable(false)
+            .show(ctx, |ui| {
                 ui.vertical_centered(|ui| {
+                    ui.add_space(8.0);
+                    if ui
+                        .add(rail_button("«"))
+                        .on_hover_text("Expand the sidebar")
+                        .clicked()
+                    {
+                        self.set_collapsed(false);
+                        return;
+                    }
+                    ui.add_space(12.0);
+
                     if is_translating {
-                        // Show cancel button during translation
-                        if ui.button("Cancel").clicked() {
-                            cancel_requested = true;
+                        if ui.add(rail_button("⏹")).on_hover_text("Cancel").clicked() {
+                            actions.cancel = true;
                         }
-                    } else {
-                        // Show translate button when not translating
-                        let translate_btn = ui.add_enabled(
-                            !self.source_text.is_empty() && !self.api_key.is_empty(),
-                            Button::new("Translate"),
-                        );
+                    } else if ui
+                        .add_enabled(self.can_translate(), rail_button("🌐"))
+                        .on_hover_text(format!("Translate to {}", self.target_language))
+                        .on_disabled_hover_text("Enter source text and an API key first")
+                        .clicked()
+                    {
+                        actions.translate = true;
+                    }
+                    ui.add_space(4.0);
 
-                        if translate_btn.clicked() {
-                            translate_requested = true;
-                        }
+                    if ui
+                        .add(rail_button("📝").selected(self.source_popup_open))
+                        .on_hover_text("Source text")
+                        .clicked()
+                    {
+                        self.source_popup_open = !self.source_popup_open;
+                    }
+                    ui.add_space(4.0);
+
+                    if ui
+                        .add_enabled(!self.source.as_str().trim().is_empty(), rail_button("🔊"))
+                        .on_hover_text("Speak the source text")
+                        .clicked()
+                    {
+                        actions.speak_source = true;
+                    }
+                    ui.add_space(4.0);
+
+                    if ui.add(rail_button("⚙")).on_hover_text("Settings").clicked() {
+                        actions.toggle_settings = true;
                     }
                 });
+            });
+    }
 
-                ui.add_space(10.0);
-
-                // Calculate responsive height for text input
-                let available_height = ui.available_height() - 20.0; // Reserve space for margins
-                let text_input_height = available_height.max(150.0);
-
-                self.create_text_frame(ui).show(ui, |ui| {
-                    ScrollArea::vertical()
-                        .max_height(text_input_height)
-                        .id_salt("source_text_scroll")
-                        .auto_shrink([false, false])
-                        .show(ui, |ui| {
-                            TextEdit::multiline(&mut self.source_text)
-                                .hint_text("Enter text to translate...")
-                                .desired_width(f32::INFINITY)
-                                .desired_rows(10)
-                                .frame(false)
-                                .show(ui);
+    /// Renders the source text entry as a popup next to the rail.
+    fn source_popup_ui(&mut self, ctx: &Context) {
+        let mut open = self.source_popup_open;
+        Window::new("📝Source Text")
+            .id(Id::new("sidebar_source_popup"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_size([360.0, 280.0])
+            .anchor(Align2::RIGHT_TOP, [-(RAIL_WIDTH + 12.0), 48.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Target Language:");
+                    egui::ComboBox::from_id_salt("popup_language_selector")
+                        .selected_text(&self.target_language)
+                        .show_ui(ui, |ui| {
+                            Self::language_options_ui(
+                                ui,
+                                &mut self.target_language,
+                                &self.languages,
+                                &self.proficiencies,
+                            );
                         });
                 });
+                ui.add_space(5.0);
+                self.source_text_ui(ui, 240.0);
+            });
+        self.source_popup_open = open;
+    }
+
+    pub fn ui(&mut self, ctx: &Context, is_translating: bool) -> SidebarActions {
+        let mut actions = SidebarActions::default();
+
+        self.auto_collapse_for(ctx.content_rect().width());
+        if self.collapsed {
+            self.rail_ui(ctx, is_translating, &mut actions);
+            if self.source_popup_open {
+                self.source_popup_ui(ctx);
+            }
+            return actions;
+        }
+
+        let panel = SidePanel::right("sidebar")
+            .default_width(self.expanded_width)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .small_button("»")
+                        .on_hover_text("Collapse the sidebar to icons")
+                        .clicked()
+                    {
+                        self.set_collapsed(true);
+                    }
+                    ui.vertical_centered(|ui| {
+                        ui.heading("Settings");
+                    });
+                });
+
+                self.contents_ui(ui, is_translating, &mut actions);
             });
 
-        (translate_requested, cancel_requested, api_key_to_save)
+        // Remember the width the user dragged it to
+        if !self.collapsed {
+            self.expanded_width = panel.response.rect.width();
+        }
+
+        actions
+    }
+
+    /// Renders the sidebar's content filling the window, as the "Input"
+    /// tab of narrow windows.
+    pub fn tab_ui(&mut self, ctx: &Context, is_translating: bool) -> SidebarActions {
+        let mut actions = SidebarActions::default();
+        CentralPanel::default().show(ctx, |ui| {
+            ScrollArea::vertical()
+                .id_salt("sidebar_tab_scroll")
+                .auto_shrink([false, false])
+                .show(ui, |ui| self.contents_ui(ui, is_translating, &mut actions));
+        });
+        actions
+    }
+
+    /// Renders what the sidebar holds, below its header.
+    fn contents_ui(&mut self, ui: &mut Ui, is_translating: bool, actions: &mut SidebarActions) {
+        ui.add_space(10.0);
+        ui.separator();
+        ui.add_space(10.0);
+
+        ui.label("API Key:");
+        ui.add_space(5.0);
+
+        let key_response = ui.add(
+            TextEdit::singleline(&mut self.api_key)
+                .hint_text("Enter your Z.AI API key")
+                .password(true),
+        );
+
+        if key_response.lost_focus() || key_response.has_focus() {
+            actions.api_key = Some(self.api_key.clone());
+        }
+
+        ui.add_space(10.0);
+
+        ui.label("API Endpoint:");
+        ui.add_space(5.0);
+
+        let url_response =
+            ui.add(TextEdit::singleline(&mut self.base_url).hint_text(DEFAULT_BASE_URL));
+        if url_response.changed() {
+            self.base_url_error = None;
+        }
+        if url_response.lost_focus() {
+            match client::normalize_base_url(&self.base_url) {
+                Ok(url) => {
+                    if !self.base_url.trim().is_empty() {
+                        self.base_url = url;
+                    }
+                    actions.base_url = Some(self.base_url.trim().to_string());
+                }
+                Err(e) => self.base_url_error = Some(e.to_string()),
+            }
+        }
+        if let Some(error) = &self.base_url_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.add_space(15.0);
+
+        ui.label("Target Language:");
+        ui.add_space(5.0);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("language_selector")
+                .selected_text(&self.target_language)
+                .show_ui(ui, |ui| {
+                    Self::language_options_ui(
+                        ui,
+                        &mut self.target_language,
+                        &self.languages,
+                        &self.proficiencies,
+                    );
+                });
+            if let Some(proficiency) = self.proficiencies.get(&self.target_language) {
+                theme::proficiency_tag_ui(ui, *proficiency);
+            }
+        });
+
+        ui.add_space(10.0);
+
+        CollapsingHeader::new("Advanced")
+            .id_salt("sidebar_advanced")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Thinking:");
+                    egui::ComboBox::from_id_salt("thinking_override")
+                        .selected_text(
+                            self.thinking_override
+                                .map_or("Settings default", |m| m.label()),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.thinking_override,
+                                None,
+                                "Settings default",
+                            );
+                            for mode in ThinkingMode::ALL {
+                                ui.selectable_value(
+                                    &mut self.thinking_override,
+                                    Some(mode),
+                                    mode.label(),
+                                );
+                            }
+                        });
+                });
+
+                Self::hint_field(
+                    ui,
+                    "Domain:",
+                    "prompt_domain",
+                    &mut self.domain,
+                    &DOMAIN_PRESETS,
+                );
+                Self::hint_field(
+                    ui,
+                    "Audience:",
+                    "prompt_audience",
+                    &mut self.audience,
+                    &AUDIENCE_PRESETS,
+                );
+
+                ui.checkbox(&mut self.code_mode, "Code mode")
+                    .on_hover_text("Translate only comments and string literals");
+                ui.add_enabled_ui(self.code_mode, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Language:");
+                        egui::ComboBox::from_id_salt("code_language")
+                            .selected_text(self.code_language.label())
+                            .show_ui(ui, |ui| {
+                                for language in CodeLanguage::ALL {
+                                    ui.selectable_value(
+                                        &mut self.code_language,
+                                        language,
+                                        language.label(),
+                                    );
+                                }
+                            });
+                    });
+                });
+
+                ui.add_enabled(
+                    !self.code_mode,
+                    egui::Checkbox::new(&mut self.list_mode, "List mode"),
+                )
+                .on_hover_text(
+                    "Translate numbered lists item by item, keeping their numbering",
+                );
+
+                if ui
+                    .checkbox(&mut self.romanize_source, "Romanize source")
+                    .on_hover_text(
+                        "Show Chinese source text in Pinyin and Japanese in romaji under the source text",
+                    )
+                    .changed()
+                    && self.romanize_source
+                {
+                    actions.romanize = true;
+                }
+            });
+
+        if self.terms.ui(ui, &self.selection) {
+            actions.add_term = Some(self.selection.clone());
+        }
+
+        ui.add_space(15.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Source Text:");
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                #[cfg(feature = "pdf")]
+                if ui
+                    .small_button("📄Open PDF…")
+                    .on_hover_text(
+                        "Translate the text of a PDF (or drop one on the window)",
+                    )
+                    .clicked()
+                {
+                    actions.open_pdf = Dialog::new(DialogCategory::Pdf)
+                        .add_filter("PDF", &["pdf"])
+                        .pick_file();
+                }
+                if ui
+                    .small_button("🗂Open JSON/YAML…")
+                    .on_hover_text(
+                        "Translate the string values of a localization file (or drop one on the window)",
+                    )
+                    .clicked()
+                {
+                    actions.open_document = Dialog::new(DialogCategory::Document)
+                        .add_filter("JSON or YAML", &["json", "yaml", "yml"])
+                        .pick_file();
+                }
+                if let Some(url) = webpage::single_url(self.source.as_str())
+                    && ui
+                        .small_button("🔗Fetch page")
+                        .on_hover_text(
+                            "Translate the text of the linked page instead of the link",
+                        )
+                        .clicked()
+                {
+                    actions.fetch_url = Some(url);
+                }
+            });
+        });
+        ui.add_space(5.0);
+
+        // Recent target languages; Ctrl/Cmd+click also starts the translation
+        let chips: Vec<&String> = self
+            .recent_languages
+            .iter()
+            .filter(|l| **l != self.target_language)
+            .collect();
+        if !chips.is_empty() {
+            let mut picked = None;
+            ui.horizontal_wrapped(|ui| {
+                for language in chips {
+                    if ui
+                        .selectable_label(false, RichText::new(language).size(12.0))
+                        .on_hover_text("Ctrl+click to translate right away")
+                        .clicked()
+                    {
+                        picked = Some(language.clone());
+                    }
+                }
+            });
+
+            if let Some(language) = picked {
+                self.target_language = language;
+                let modifier_held = ui.input(|i| i.modifiers.command);
+                if modifier_held && !is_translating && self.can_translate() {
+                    actions.translate = true;
+                }
+            }
+            ui.add_space(5.0);
+        }
+
+        // Translate/Cancel control (moved before input box)
+        ui.vertical_centered(|ui| {
+            if is_translating {
+                // Show cancel button during translation
+                if ui.button("Cancel").clicked() {
+                    actions.cancel = true;
+                }
+            } else {
+                // Show translate button when not translating
+                let translate_btn = ui.add_enabled(self.can_translate(), Button::new("Translate"));
+
+                if translate_btn.clicked() {
+                    actions.translate = true;
+                }
+            }
+            self.cost_ui(ui);
+        });
+
+        ui.add_space(10.0);
+
+        // Calculate responsive height for text input
+        let mut available_height = ui.available_height() - 20.0; // Reserve space for margins
+        if self.shown_romanization().is_some() {
+            available_height -= ROMANIZATION_HEIGHT;
+        }
+        let text_input_height = available_height.max(150.0);
+
+        self.source_text_ui(ui, text_input_height);
+        self.romanization_ui(ui, actions);
     }
 
     pub fn get_source_text(&self) -> String {
-        self.source_text.clone()
+        self.source.as_str().to_string()
+    }
+
+    /// Replaces the source text, as when it is loaded from elsewhere.
+    pub fn set_source_text(&mut self, text: String) {
+        self.source.set(text);
+    }
+
+    /// The shared source text, for editing it from the central panel too
+    pub fn shared_source_mut(&mut self) -> &mut SharedText {
+        &mut self.source
     }
 
     pub fn get_api_key(&self) -> String {
@@ -143,11 +823,198 @@ impl Sidebar {
         self.target_language.clone()
     }
 
+    pub fn thinking_override(&self) -> Option<ThinkingMode> {
+        self.thinking_override
+    }
+
+    /// The code language when code mode is on, `None` for plain text
+    pub fn code_mode(&self) -> Option<CodeLanguage> {
+        self.code_mode.then_some(self.code_language)
+    }
+
+    /// Whether numbered lists are translated item by item
+    pub fn list_mode(&self) -> bool {
+        self.list_mode
+    }
+
+    pub fn romanize_source(&self) -> bool {
+        self.romanize_source
+    }
+
+    pub fn set_romanize_source(&mut self, enabled: bool) {
+        self.romanize_source = enabled;
+    }
+
+    /// Shows `state` as the romanization of `source` by `scheme`.
+    pub fn set_romanization(&mut self, source: String, scheme: Scheme, state: RomanizationState) {
+        self.romanization = Some(SourceRomanization {
+            source,
+            scheme,
+            state,
+        });
+    }
+
+    /// Whether `source` is romanized, or being romanized.
+    pub fn has_romanization(&self, source: &str) -> bool {
+        self.romanization.as_ref().is_some_and(|shown| {
+            shown.source == source && !matches!(shown.state, RomanizationState::Failed(_))
+        })
+    }
+
+    pub fn clear_romanization(&mut self) {
+        self.romanization = None;
+    }
+
+    /// Counts the changes of the source text.
+    pub fn source_generation(&self) -> u64 {
+        self.source.generation()
+    }
+
+    /// Shows `estimate` as the cost of translating the source text, `None`
+    /// for no estimate.
+    pub fn set_cost_estimate(&mut self, estimate: Option<Estimate>) {
+        self.cost = estimate.map(|estimate| SourceCost {
+            estimate,
+            actual: None,
+        });
+    }
+
+    /// Shows what translating the source text actually cost, next to its
+    /// estimate.
+    pub fn set_actual_cost(&mut self, actual: f64) {
+        if let Some(cost) = &mut self.cost {
+            cost.actual = Some(actual);
+        }
+    }
+
     pub fn set_api_key(&mut self, api_key: String) {
         self.api_key = api_key;
     }
 
+    pub fn set_base_url(&mut self, base_url: String) {
+        self.base_url = base_url;
+        self.base_url_error = None;
+    }
+
     pub fn set_target_language(&mut self, language: String) {
         self.target_language = language;
     }
+
+    /// Domain and audience hints for the next translation
+    pub fn prompt_context(&self) -> PromptContext {
+        PromptContext::new(&self.domain, &self.audience)
+    }
+
+    pub fn set_prompt_hints(&mut self, domain: String, audience: String) {
+        self.domain = domain;
+        self.audience = audience;
+    }
+
+    pub fn set_recent_languages(&mut self, languages: Vec<String>) {
+        self.recent_languages = languages;
+    }
+
+    /// Sets the proficiency tags shown next to languages with a profile.
+    pub fn set_proficiencies(&mut self, proficiencies: BTreeMap<String, Proficiency>) {
+        self.proficiencies = proficiencies;
+    }
+
+    /// Restores the collapsed state, expanded width and auto-collapse setting.
+    pub fn set_layout(&mut self, collapsed: bool, expanded_width: f32, auto_collapse: bool) {
+        self.collapsed = collapsed;
+        self.expanded_width = expanded_width;
+        self.set_auto_collapse(auto_collapse);
+    }
+
+    /// Turns collapsing in narrow windows on or off.
+    pub fn set_auto_collapse(&mut self, auto_collapse: bool) {
+        self.auto_collapse = auto_collapse;
+        self.auto_collapsed = false;
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.collapsed
+    }
+
+    /// Width of the full panel, also while collapsed
+    pub fn expanded_width(&self) -> f32 {
+        self.expanded_width
+    }
+
+    /// Sets the glossary terms of the target language to look for.
+    pub fn set_term_matcher(&mut self, matcher: Arc<TermMatcher>) {
+        self.terms.set_matcher(matcher);
+    }
+
+    /// Turns spell checking of the source text on or off.
+    pub fn set_spellcheck(&mut self, enabled: bool, language: &str) {
+        self.spelling.configure(enabled, language);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded_sidebar() -> Sidebar {
+        let mut sidebar = Sidebar::default();
+        sidebar.set_layout(false, 300.0, true);
+        sidebar.auto_collapse_for(1200.0);
+        sidebar
+    }
+
+    #[test]
+    fn test_narrow_window_collapses_and_wide_expands() {
+        let mut sidebar = expanded_sidebar();
+        sidebar.auto_collapse_for(AUTO_COLLAPSE_WIDTH);
+        assert!(!sidebar.is_collapsed());
+
+        sidebar.auto_collapse_for(AUTO_COLLAPSE_WIDTH - 1.0);
+        assert!(sidebar.is_collapsed());
+
+        sidebar.auto_collapse_for(1200.0);
+        assert!(!sidebar.is_collapsed());
+    }
+
+    #[test]
+    fn test_resizing_around_the_threshold_does_not_flicker() {
+        let mut sidebar = expanded_sidebar();
+        sidebar.auto_collapse_for(AUTO_COLLAPSE_WIDTH - 1.0);
+        assert!(sidebar.is_collapsed());
+
+        for width in [AUTO_COLLAPSE_WIDTH + 1.0, AUTO_COLLAPSE_WIDTH - 1.0] {
+            sidebar.auto_collapse_for(width);
+            assert!(sidebar.is_collapsed(), "expanded at {}", width);
+        }
+        sidebar.auto_collapse_for(AUTO_COLLAPSE_WIDTH + AUTO_EXPAND_MARGIN - 1.0);
+        assert!(sidebar.is_collapsed());
+
+        sidebar.auto_collapse_for(AUTO_COLLAPSE_WIDTH + AUTO_EXPAND_MARGIN);
+        assert!(!sidebar.is_collapsed());
+    }
+
+    #[test]
+    fn test_user_choice_is_kept_until_the_width_changes_side() {
+        let mut sidebar = expanded_sidebar();
+        sidebar.auto_collapse_for(600.0);
+        sidebar.set_collapsed(false);
+        sidebar.auto_collapse_for(500.0);
+        assert!(
+            !sidebar.is_collapsed(),
+            "expanded by the user in a narrow window"
+        );
+
+        // Collapsed by the user, so widening leaves it collapsed
+        sidebar.set_collapsed(true);
+        sidebar.auto_collapse_for(1200.0);
+        assert!(sidebar.is_collapsed());
+    }
+
+    #[test]
+    fn test_disabled_automation_keeps_the_sidebar() {
+        let mut sidebar = expanded_sidebar();
+        sidebar.set_auto_collapse(false);
+        sidebar.auto_collapse_for(500.0);
+        assert!(!sidebar.is_collapsed());
+    }
 }
@@ -0,0 +1,216 @@
+//! Spell check highlighting for the source text box.
+
+use crate::utils::spellcheck::{self, Misspelling, SpellChecker};
+use egui::text::{CCursor, LayoutJob};
+use egui::*;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// How long typing must pause before the text is checked.
+const DEBOUNCE: Duration = Duration::from_millis(400);
+
+/// Word under the pointer when the context menu was opened.
+struct MenuTarget {
+    misspelling: Misspelling,
+    suggestions: Vec<String>,
+}
+
+/// Checks the source text in the background and underlines misspellings.
+pub struct SpellHighlighter {
+    enabled: bool,
+    language: String,
+    checker: SpellChecker,
+    /// Generation of the latest check
+    generation: u64,
+    misspellings: Vec<Misspelling>,
+    /// Text as of the last edit
+    text: String,
+    /// When the text last changed, `None` once it has been checked
+    edited_at: Option<Instant>,
+    menu_target: Option<MenuTarget>,
+}
+
+impl Default for SpellHighlighter {
+    fn default() -> Self {
+        SpellHighlighter {
+            enabled: false,
+            language: String::new(),
+            checker: SpellChecker::new(None),
+            generation: 0,
+            misspellings: Vec::new(),
+            text: String::new(),
+            edited_at: None,
+            menu_target: None,
+        }
+    }
+}
+
+impl SpellHighlighter {
+    /// Turns checking on or off and selects the dictionary language.
+    ///
+    /// Checking stays silently disabled when no dictionary is available.
+    pub fn configure(&mut self, enabled: bool, language: &str) {
+        if self.enabled == enabled && self.language == language {
+            return;
+        }
+
+        self.enabled = enabled;
+        self.language = language.to_string();
+        let dictionary = enabled
+            .then(|| spellcheck::load_dictionary(language))
+            .flatten();
+        if enabled && dictionary.is_none() {
+            tracing::debug!("No {} dictionary, spell check disabled", language);
+        }
+        self.checker = SpellChecker::new(dictionary);
+        self.misspellings.clear();
+        self.menu_target = None;
+        // Recheck the current text with the new dictionary
+        self.edited_at = Some(Instant::now() - DEBOUNCE);
+    }
+
+    fn is_active(&self) -> bool {
+        self.enabled && self.checker.is_available()
+    }
+
+    /// Notes the current text and collects finished check results.
+    ///
+    /// Starts a check once typing has paused; requests a repaint while a
+    /// check is pending.
+    pub fn update(&mut self, ctx: &Context, text: &str) {
+        if !self.is_active() {
+            return;
+        }
+
+        if self.text != text {
+            self.text = text.to_string();
+            self.edited_at = Some(Instant::now());
+        }
+
+        if let Some(edited_at) = self.edited_at {
+            let elapsed = edited_at.elapsed();
+            if elapsed >= DEBOUNCE {
+                self.generation = self.checker.check(self.text.clone());
+                self.edited_at = None;
+            } else {
+                ctx.request_repaint_after(DEBOUNCE - elapsed);
+            }
+        }
+
+        let mut received = false;
+        for result in self.checker.poll() {
+            // Results for text that has changed since are outdated
+            if result.generation == self.generation {
+                spellcheck::merge(&mut self.misspellings, result.range, result.misspellings);
+                received = true;
+            }
+        }
+        if received {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Lays out `text` with the misspelled words underlined and the
+    /// `highlights` byte ranges marked.
+    pub fn layout_job(
+        &self,
+        ui: &Ui,
+        text: &str,
+        wrap_width: f32,
+        highlights: &[Range<usize>],
+    ) -> LayoutJob {
+        let font_id = FontSelection::default().resolve(ui.style());
+        let color = ui
+            .visuals()
+            .override_text_color
+            .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+        let plain = TextFormat::simple(font_id, color);
+
+        // Skip words edited since they were checked
+        let misspelled: Vec<Range<usize>> = if self.is_active() {
+            self.misspellings
+                .iter()
+                .filter(|m| m.matches(text))
+                .map(|m| m.range.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut bounds: Vec<usize> = misspelled
+            .iter()
+            .chain(highlights)
+            .flat_map(|range| [range.start, range.end])
+            .filter(|&at| at < text.len() && text.is_char_boundary(at))
+            .chain([0, text.len()])
+            .collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut job = LayoutJob::default();
+        job.wrap.max_width = wrap_width;
+        for span in bounds.windows(2) {
+            let inside = |ranges: &[Range<usize>]| ranges.iter().any(|r| r.contains(&span[0]));
+            let mut format = plain.clone();
+            if inside(&misspelled) {
+                format.underline = Stroke::new(1.5, Color32::RED);
+            }
+            if inside(highlights) {
+                format.background = ui.visuals().selection.bg_fill.gamma_multiply(0.6);
+            }
+            job.append(&text[span[0]..span[1]], 0.0, format);
+        }
+        if text.is_empty() {
+            job.append("", 0.0, plain);
+        }
+        job
+    }
+
+    /// Shows suggestions when a misspelled word is right-clicked, and applies
+    /// the chosen one to `text`.
+    pub fn context_menu(&mut self, output: &text_edit::TextEditOutput, text: &mut String) {
+        if !self.is_active() {
+            return;
+        }
+
+        if output.response.secondary_clicked()
+            && let Some(pos) = output.response.interact_pointer_pos()
+        {
+            let cursor: CCursor = output.galley.cursor_from_pos(pos - output.galley_pos);
+            let offset = text
+                .char_indices()
+                .nth(cursor.index)
+                .map_or(text.len(), |(i, _)| i);
+            self.menu_target = self
+                .misspellings
+                .iter()
+                .find(|m| m.range.contains(&offset) && m.matches(text))
+                .map(|m| MenuTarget {
+                    suggestions: self.checker.suggest(&m.word),
+                    misspelling: m.clone(),
+                });
+        }
+
+        let Some(target) = &self.menu_target else {
+            return;
+        };
+        let mut replacement = None;
+        output.response.context_menu(|ui| {
+            if target.suggestions.is_empty() {
+                ui.label(RichText::new("No suggestions").italics());
+            }
+            for suggestion in &target.suggestions {
+                if ui.button(suggestion).clicked() {
+                    replacement = Some(suggestion.clone());
+                    ui.close();
+                }
+            }
+        });
+
+        if let Some(replacement) = replacement {
+            if target.misspelling.matches(text) {
+                text.replace_range(target.misspelling.range.clone(), &replacement);
+            }
+            self.menu_target = None;
+        }
+    }
+}
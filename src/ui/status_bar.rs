@@ -0,0 +1,140 @@
+//! Status bar at the bottom of the window.
+//!
+//! Shows live stream throughput while translating and a summary of the last
+//! finished request afterwards.
+
+use crate::utils::metrics::{RequestKind, RequestMetrics, RequestOutcome};
+use egui::*;
+
+/// State of the bottom status bar.
+#[derive(Default)]
+pub struct StatusBar {
+    /// Rolling characters per second of the running translation
+    throughput: Option<f64>,
+    /// Attempt and attempts in all of a request sent again before it
+    /// started to respond
+    retrying: Option<(u32, u32)>,
+    /// Metrics of the last finished translation
+    last_metrics: Option<RequestMetrics>,
+    /// A connection to the provider was opened at startup
+    connection_warm: bool,
+    /// Rule that kept the last translation out of the cache
+    uncached_by: Option<String>,
+}
+
+impl StatusBar {
+    /// Resets the live figures when a new translation starts.
+    pub fn start_request(&mut self) {
+        self.throughput = None;
+        self.retrying = None;
+        self.uncached_by = None;
+    }
+
+    /// Notes the rule that kept the finished translation out of the cache.
+    pub fn set_uncached(&mut self, rule: Option<String>) {
+        self.uncached_by = rule;
+    }
+
+    /// Updates the live throughput.
+    pub fn set_throughput(&mut self, chars_per_sec: f64) {
+        self.throughput = Some(chars_per_sec);
+        self.retrying = None;
+    }
+
+    /// Shows that the request is sent again, as `attempt` of
+    /// `max_attempts`.
+    pub fn set_retrying(&mut self, attempt: u32, max_attempts: u32) {
+        self.retrying = Some((attempt, max_attempts));
+    }
+
+    /// Stores the summary of a finished translation.
+    pub fn set_metrics(&mut self, metrics: RequestMetrics) {
+        self.throughput = None;
+        self.last_metrics = Some(metrics);
+    }
+
+    /// Shows that a connection to the provider is ready.
+    pub fn set_connection_warm(&mut self) {
+        self.connection_warm = true;
+    }
+
+    /// Whether a connection to the provider was opened at startup.
+    pub fn connection_warm(&self) -> bool {
+        self.connection_warm
+    }
+
+    /// Renders the status bar.
+    pub fn ui(&self, ctx: &Context, is_translating: bool) {
+        TopBottomPanel::bottom("status_bar")
+            .exact_height(24.0)
+            .show(ctx, |ui| {
+                ui.horizontal_centered(|ui| {
+                    let text = if is_translating {
+                        match (self.throughput, self.retrying) {
+                            (Some(cps), _) => format!("⏱ {:.1} chars/s", cps),
+                            (None, Some((attempt, max_attempts))) => {
+                                format!("⟳ Retrying ({}/{})…", attempt, max_attempts)
+                            }
+                            (None, None) => "⏱ Waiting for the first content…".to_string(),
+                        }
+                    } else if let Some(metrics) = &self.last_metrics {
+                        Self::summary(metrics)
+                    } else {
+                        "Ready".to_string()
+                    };
+                    ui.label(RichText::new(text).size(12.0).weak());
+                    if !is_translating && let Some(rule) = &self.uncached_by {
+                        ui.label(
+                            RichText::new(format!("· not cached (rule: {})", rule))
+                                .size(12.0)
+                                .weak(),
+                        )
+                        .on_hover_text("Not kept in the cache or the history");
+                    }
+                    if self.connection_warm {
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            ui.label(
+                                RichText::new("●")
+                                    .size(12.0)
+                                    .color(Color32::from_rgb(80, 200, 120)),
+                            )
+                            .on_hover_text("Connected to the provider");
+                        });
+                    }
+                });
+            });
+    }
+
+    fn summary(metrics: &RequestMetrics) -> String {
+        let outcome = match (metrics.kind, metrics.outcome) {
+            (RequestKind::Translation, RequestOutcome::Completed) => "Last request",
+            (RequestKind::Translation, RequestOutcome::Cancelled) => "Cancelled",
+            (RequestKind::Translation, RequestOutcome::Failed) => "Failed",
+            (RequestKind::Explanation, RequestOutcome::Completed) => "Last explanation",
+            (RequestKind::Explanation, RequestOutcome::Cancelled) => "Explanation cancelled",
+            (RequestKind::Explanation, RequestOutcome::Failed) => "Explanation failed",
+            (RequestKind::Confidence, RequestOutcome::Completed) => "Last confidence check",
+            (RequestKind::Confidence, RequestOutcome::Cancelled) => "Confidence check cancelled",
+            (RequestKind::Confidence, RequestOutcome::Failed) => "Confidence check failed",
+        };
+        let mut text = format!(
+            "{}: {} chars in {:.1} s",
+            outcome,
+            metrics.total_chars,
+            metrics.duration_ms as f64 / 1000.0
+        );
+        if let Some(avg) = metrics.avg_chars_per_sec {
+            text.push_str(&format!(" · avg {:.1} chars/s", avg));
+        }
+        if let Some(min) = metrics.min_chars_per_sec {
+            text.push_str(&format!(" · min {:.1} chars/s", min));
+        }
+        if let Some(tokens) = metrics.tokens {
+            text.push_str(&format!(" · {} tokens", tokens.prompt + tokens.completion));
+            if let Some(reasoning) = tokens.reasoning.filter(|&r| r > 0) {
+                text.push_str(&format!(" ({} reasoning)", reasoning));
+            }
+        }
+        text
+    }
+}
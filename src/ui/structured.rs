@@ -0,0 +1,242 @@
+//! Window for translating the string values of a JSON or YAML document.
+//!
+//! Each value gets a row with a checkbox, its path, the original and the
+//! translation. Values whose translation lost a placeholder are flagged and
+//! keep their original text in the output.
+
+use crate::utils::dialogs::{Dialog, DialogCategory};
+use crate::utils::structured::{Format, StructuredDocument, ValueBatch, ValueError};
+use egui::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What to do with the document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructuredAction {
+    /// Translate the selected values
+    Translate(ValueBatch),
+    /// Translate the whole text as ordinary text instead
+    TranslateAsText(String),
+    /// Copy the translated document
+    Copy(String),
+    /// Write the translated document to a file
+    Save(PathBuf, String),
+}
+
+/// A document open in the window.
+struct OpenDocument {
+    /// File name, or what the document was taken from
+    name: String,
+    document: StructuredDocument,
+    selected: Vec<bool>,
+    translations: HashMap<usize, String>,
+    /// Values whose translation could not be used
+    flags: HashMap<usize, ValueError>,
+    translating: bool,
+}
+
+/// State of the document window.
+#[derive(Default)]
+pub struct StructuredWindow {
+    /// `Some` while the window is open
+    open: Option<OpenDocument>,
+}
+
+impl StructuredWindow {
+    /// Opens `document` with all its values selected.
+    pub fn open(&mut self, name: String, document: StructuredDocument) {
+        let selected = vec![true; document.values().len()];
+        self.open = Some(OpenDocument {
+            name,
+            document,
+            selected,
+            translations: HashMap::new(),
+            flags: HashMap::new(),
+            translating: false,
+        });
+    }
+
+    /// Applies the per-value results of a translation.
+    pub fn set_results(&mut self, results: Vec<(usize, Result<String, ValueError>)>) {
+        let Some(open) = &mut self.open else {
+            return;
+        };
+        for (index, result) in results {
+            match result {
+                Ok(translation) => {
+                    open.flags.remove(&index);
+                    open.translations.insert(index, translation);
+                }
+                Err(e) => {
+                    open.translations.remove(&index);
+                    open.flags.insert(index, e);
+                }
+            }
+        }
+        open.translating = false;
+    }
+
+    /// Ends a translation that failed as a whole.
+    pub fn translation_failed(&mut self) {
+        if let Some(open) = &mut self.open {
+            open.translating = false;
+        }
+    }
+
+    /// Renders the window while it is open.
+    ///
+    /// # Returns
+    ///
+    /// The chosen action; translating as text closes the window
+    pub fn ui(&mut self, ctx: &Context, can_translate: bool) -> Option<StructuredAction> {
+        let open_document = self.open.as_mut()?;
+        let mut action = None;
+        let mut open = true;
+
+        Window::new(format!(
+            "🗂{} ({})",
+            open_document.name,
+            open_document.document.format.label()
+        ))
+        .id(Id::new("structured_document"))
+        .open(&mut open)
+        .default_size([720.0, 520.0])
+        .collapsible(false)
+        .show(ctx, |ui| {
+            let OpenDocument {
+                name,
+                document,
+                selected,
+                translations,
+                flags,
+                translating,
+            } = open_document;
+            let values = document.values();
+            let selected_count = selected.iter().filter(|s| **s).count();
+
+            ui.horizontal(|ui| {
+                let translate = ui
+                    .add_enabled(
+                        can_translate && !*translating && selected_count > 0,
+                        Button::new(format!("🌐Translate {} selected", selected_count)),
+                    )
+                    .on_disabled_hover_text(
+                        "Nothing is selected, a translation is running or the API key is missing",
+                    );
+                if translate.clicked() {
+                    let indices: Vec<usize> = (0..values.len()).filter(|&i| selected[i]).collect();
+                    action = Some(StructuredAction::Translate(ValueBatch::new(
+                        document, &indices,
+                    )));
+                    *translating = true;
+                }
+                if *translating {
+                    ui.spinner();
+                }
+                if ui.small_button("All").clicked() {
+                    selected.fill(true);
+                }
+                if ui.small_button("None").clicked() {
+                    selected.fill(false);
+                }
+                if ui
+                    .small_button("Untranslated")
+                    .on_hover_text("Select the values without a translation")
+                    .clicked()
+                {
+                    for (i, selected) in selected.iter_mut().enumerate() {
+                        *selected = !translations.contains_key(&i);
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("📋Copy document").clicked() {
+                    action = Some(StructuredAction::Copy(document.render(translations)));
+                }
+                if ui.button("💾Save as…").clicked()
+                    && let Some(path) = Dialog::new(DialogCategory::DocumentExport)
+                        .set_file_name(&save_name(name, document.format))
+                        .save_file()
+                {
+                    action = Some(StructuredAction::Save(path, document.render(translations)));
+                }
+                if ui
+                    .add_enabled(can_translate, Button::new("📝Translate as text"))
+                    .on_hover_text("Translate the whole document as ordinary text")
+                    .clicked()
+                {
+                    action = Some(StructuredAction::TranslateAsText(
+                        document.render(&HashMap::new()),
+                    ));
+                }
+                if !flags.is_empty() {
+                    ui.label(
+                        RichText::new(format!("⚠ {} flagged", flags.len()))
+                            .color(ui.visuals().warn_fg_color),
+                    );
+                }
+            });
+            ui.add_space(4.0);
+
+            ScrollArea::vertical()
+                .id_salt("structured_values")
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    Grid::new("structured_values_grid")
+                        .num_columns(4)
+                        .striped(true)
+                        .spacing([8.0, 4.0])
+                        .show(ui, |ui| {
+                            ui.label("");
+                            ui.label(RichText::new("Key").strong());
+                            ui.label(RichText::new("Original").strong());
+                            ui.label(RichText::new("Translation").strong());
+                            ui.end_row();
+
+                            for (i, value) in values.iter().enumerate() {
+                                ui.checkbox(&mut selected[i], "");
+                                ui.label(RichText::new(&value.path).monospace().size(12.0));
+                                ui.add(Label::new(&value.text).wrap());
+                                match (translations.get(&i), flags.get(&i)) {
+                                    (Some(translation), _) => {
+                                        ui.add(Label::new(translation).wrap());
+                                    }
+                                    (None, Some(flag)) => {
+                                        ui.label(
+                                            RichText::new(format!("⚠ {}", flag))
+                                                .color(ui.visuals().warn_fg_color),
+                                        )
+                                        .on_hover_text(
+                                            "The original is kept; select the value to retry it",
+                                        );
+                                    }
+                                    (None, None) => {
+                                        ui.label("");
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+
+        if !open || matches!(action, Some(StructuredAction::TranslateAsText(_))) {
+            self.open = None;
+        }
+        action
+    }
+}
+
+/// File name suggested when saving a document: the name of the opened
+/// file, or one with the extension of its format.
+fn save_name(name: &str, format: Format) -> String {
+    let from_file = std::path::Path::new(name)
+        .extension()
+        .and_then(|extension| Format::from_extension(&extension.to_string_lossy()))
+        == Some(format);
+    if from_file {
+        name.to_string()
+    } else {
+        format!("translated.{}", format.label().to_ascii_lowercase())
+    }
+}
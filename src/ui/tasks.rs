@@ -0,0 +1,84 @@
+//! Top bar indicator of the background tasks, with a popover to stop them.
+
+use crate::utils::tasks::{TaskId, TaskInfo, TaskRegistry};
+use egui::*;
+use std::time::Duration;
+
+/// How often the elapsed times are refreshed while tasks run.
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// Renders the indicator and, when clicked, the list of running tasks.
+///
+/// # Returns
+///
+/// The task whose cancel button was clicked
+pub fn tasks_ui(ui: &mut Ui, registry: &TaskRegistry) -> Option<TaskId> {
+    let tasks = registry.tasks();
+    if tasks.is_empty() {
+        return None;
+    }
+    ui.ctx().request_repaint_after(REFRESH);
+
+    let mut cancel = None;
+    let title = RichText::new(tasks.len().to_string()).size(12.0);
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 4.0;
+        ui.add(Spinner::new().size(12.0));
+        let response = ui.menu_button(title, |ui| {
+            ui.set_min_width(320.0);
+            ui.label(RichText::new("Background Tasks").strong());
+            ui.separator();
+            Grid::new("tasks_grid")
+                .num_columns(4)
+                .spacing([8.0, 6.0])
+                .show(ui, |ui| {
+                    for task in &tasks {
+                        if task_row_ui(ui, task) {
+                            cancel = Some(task.id);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+        response.response.on_hover_text(format!(
+            "{} background task{} running",
+            tasks.len(),
+            if tasks.len() == 1 { "" } else { "s" }
+        ));
+    });
+    cancel
+}
+
+/// Renders one task, returning whether its cancel button was clicked.
+fn task_row_ui(ui: &mut Ui, task: &TaskInfo) -> bool {
+    ui.label(RichText::new(task.kind.label()).size(12.0).weak());
+    ui.label(&task.label);
+    ui.horizontal(|ui| {
+        ui.label(
+            RichText::new(format_elapsed(task.elapsed()))
+                .size(12.0)
+                .monospace(),
+        );
+        if let Some(progress) = task.progress {
+            ui.add(
+                ProgressBar::new(progress)
+                    .desired_width(60.0)
+                    .show_percentage(),
+            );
+        }
+    });
+    if task.cancelling {
+        ui.label(RichText::new("Stopping…").size(12.0).weak());
+        return false;
+    }
+    ui.add_enabled(task.cancellable, Button::new("✖").small())
+        .on_hover_text("Cancel")
+        .on_disabled_hover_text("This task can't be cancelled")
+        .clicked()
+}
+
+/// `m:ss` since the task started.
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
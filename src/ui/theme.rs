@@ -1,8 +1,51 @@
+use crate::utils::config::Proficiency;
+use crate::utils::dialogs::{Dialog, DialogCategory};
+use crate::utils::share::ImageStyle;
 use egui::{FontDefinitions, FontFamily, TextStyle, *};
+use std::path::{Path, PathBuf};
+
+/// Name the user-supplied font is registered under.
+const CUSTOM_FONT: &str = "custom_font";
 
 pub struct Theme {
     pub dark: bool,
     pub font_size: f32,
+    /// Font file loaded at runtime, the last fallback for missing glyphs
+    pub custom_font: Option<PathBuf>,
+}
+
+/// Reads a font file, checking that it is a font egui can use.
+pub fn load_font_file(path: &Path) -> Result<Vec<u8>, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    ab_glyph::FontRef::try_from_slice(&bytes)
+        .map_err(|_| format!("{} is not a TrueType or OpenType font", path.display()))?;
+    Ok(bytes)
+}
+
+/// Asks the user for a .ttf or .otf file.
+pub fn pick_font_file() -> Option<PathBuf> {
+    Dialog::new(DialogCategory::Font)
+        .add_filter("Fonts", &["ttf", "otf"])
+        .pick_file()
+}
+
+/// Color of the tag shown next to a language with a profile.
+pub fn proficiency_color(proficiency: Proficiency) -> Color32 {
+    match proficiency {
+        Proficiency::Native => Color32::from_rgb(76, 175, 80),
+        Proficiency::Fluent => Color32::from_rgb(66, 133, 244),
+        Proficiency::Learning => Color32::from_rgb(255, 152, 0),
+    }
+}
+
+/// Small colored tag with the proficiency label.
+pub fn proficiency_tag_ui(ui: &mut Ui, proficiency: Proficiency) {
+    ui.label(
+        RichText::new(proficiency.label())
+            .size(10.0)
+            .color(proficiency_color(proficiency)),
+    );
 }
 
 impl Default for Theme {
@@ -10,12 +53,17 @@ impl Default for Theme {
         Theme {
             dark: true,
             font_size: 16.0,
+            custom_font: None,
         }
     }
 }
 
 impl Theme {
-    pub fn setup_fonts(&self, ctx: &Context) {
+    /// Installs the bundled fonts and the custom font, if any.
+    ///
+    /// A custom font that can't be loaded is left out and its error is
+    /// returned; the bundled fonts are installed either way.
+    pub fn setup_fonts(&self, ctx: &Context) -> Result<(), String> {
         let mut fonts = FontDefinitions::default();
 
         // Load STSong for Chinese and other CJK languages
@@ -56,7 +104,84 @@ impl Theme {
             .or_default()
             .push("stsong".to_owned());
 
+        // DejaVu Sans for Arabic and Hebrew
+        #[cfg(feature = "rtl-font")]
+        {
+            fonts.font_data.insert(
+                "dejavu_sans".to_owned(),
+                std::sync::Arc::new(egui::FontData::from_static(include_bytes!(
+                    "../../fonts/DejaVuSans.ttf"
+                ))),
+            );
+            for family in [FontFamily::Proportional, FontFamily::Monospace] {
+                fonts
+                    .families
+                    .entry(family)
+                    .or_default()
+                    .push("dejavu_sans".to_owned());
+            }
+        }
+
+        let mut result = Ok(());
+        if let Some(path) = &self.custom_font {
+            match load_font_file(path) {
+                Ok(bytes) => {
+                    tracing::info!("Loaded custom font {:?}", path);
+                    fonts.font_data.insert(
+                        CUSTOM_FONT.to_owned(),
+                        std::sync::Arc::new(egui::FontData::from_owned(bytes)),
+                    );
+                    for family in [FontFamily::Proportional, FontFamily::Monospace] {
+                        fonts
+                            .families
+                            .entry(family)
+                            .or_default()
+                            .push(CUSTOM_FONT.to_owned());
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Custom font not loaded: {}", e);
+                    result = Err(e);
+                }
+            }
+        }
+
         ctx.set_fonts(fonts);
+        result
+    }
+
+    /// The bundled fonts and the custom font in fallback order, for text
+    /// rendered outside egui.
+    pub fn share_fonts(&self) -> Vec<ab_glyph::FontArc> {
+        let mut fonts: Vec<&'static [u8]> = vec![
+            include_bytes!("../../fonts/NotoSerifKR-VariableFont_wght.ttf"),
+            include_bytes!("../../fonts/STSong.ttf"),
+        ];
+        #[cfg(feature = "rtl-font")]
+        fonts.push(include_bytes!("../../fonts/DejaVuSans.ttf"));
+
+        let mut fonts: Vec<_> = fonts
+            .into_iter()
+            .filter_map(|bytes| ab_glyph::FontArc::try_from_slice(bytes).ok())
+            .collect();
+        if let Some(path) = &self.custom_font
+            && let Ok(bytes) = load_font_file(path)
+            && let Ok(font) = ab_glyph::FontArc::try_from_vec(bytes)
+        {
+            fonts.push(font);
+        }
+        fonts
+    }
+
+    /// Style of a shared image in the colors of `visuals`.
+    pub fn share_style(&self, visuals: &Visuals) -> ImageStyle {
+        ImageStyle {
+            background: visuals.panel_fill.to_array(),
+            text: visuals.text_color().to_array(),
+            label: visuals.weak_text_color().to_array(),
+            font_size: (self.font_size * 1.4).round(),
+            ..ImageStyle::default()
+        }
     }
 
     pub fn apply_style(&self, ctx: &Context) {
@@ -0,0 +1,166 @@
+//! Lightweight toast notifications.
+//!
+//! Toasts are short-lived messages stacked in the bottom-right corner of the
+//! window, used for non-blocking feedback such as playback failures.
+
+use crate::utils::undo::UndoId;
+use egui::*;
+use std::time::{Duration, Instant};
+
+/// Severity of a toast, controls its icon and color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    /// Informational message
+    Info,
+    /// Something worth attention that did not fail
+    Warning,
+    /// An operation failed
+    Error,
+}
+
+/// Follow-up action offered by a toast button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastAction {
+    /// Retry the source text TTS conversion
+    RetrySourceTts,
+    /// Retry the translation TTS conversion
+    RetryTranslationTts,
+    /// Queue the failed offline translation for later
+    QueueForLater,
+    /// Undo a soft-deleted item
+    Undo(UndoId),
+    /// Show the notes of the newer release found by an update check
+    OpenReleasePage,
+}
+
+/// A single toast notification.
+struct Toast {
+    kind: ToastKind,
+    text: String,
+    /// Button label and the action it triggers
+    action: Option<(&'static str, ToastAction)>,
+    expires_at: Instant,
+}
+
+/// Stack of active toast notifications.
+#[derive(Default)]
+pub struct Toasts {
+    items: Vec<Toast>,
+}
+
+impl Toasts {
+    /// How long a toast stays visible unless dismissed.
+    const DURATION: Duration = Duration::from_secs(5);
+
+    /// Shows an informational toast.
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(ToastKind::Info, text.into(), None, Self::DURATION);
+    }
+
+    /// Shows an informational toast with an action button for `duration`, e.g. "Undo".
+    pub fn info_with_action(
+        &mut self,
+        text: impl Into<String>,
+        label: &'static str,
+        action: ToastAction,
+        duration: Duration,
+    ) {
+        self.push(
+            ToastKind::Info,
+            text.into(),
+            Some((label, action)),
+            duration,
+        );
+    }
+
+    /// Shows a warning toast.
+    pub fn warning(&mut self, text: impl Into<String>) {
+        self.push(ToastKind::Warning, text.into(), None, Self::DURATION);
+    }
+
+    /// Shows an error toast.
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(ToastKind::Error, text.into(), None, Self::DURATION);
+    }
+
+    /// Shows an error toast with an action button, e.g. "Retry".
+    pub fn error_with_action(
+        &mut self,
+        text: impl Into<String>,
+        label: &'static str,
+        action: ToastAction,
+    ) {
+        self.push(
+            ToastKind::Error,
+            text.into(),
+            Some((label, action)),
+            Self::DURATION,
+        );
+    }
+
+    fn push(
+        &mut self,
+        kind: ToastKind,
+        text: String,
+        action: Option<(&'static str, ToastAction)>,
+        duration: Duration,
+    ) {
+        self.items.push(Toast {
+            kind,
+            text,
+            action,
+            expires_at: Instant::now() + duration,
+        });
+    }
+
+    /// Renders the active toasts and drops expired ones.
+    ///
+    /// Returns the action of a clicked toast button, which also dismisses it.
+    pub fn ui(&mut self, ctx: &Context) -> Option<ToastAction> {
+        let now = Instant::now();
+        self.items.retain(|toast| toast.expires_at > now);
+        if self.items.is_empty() {
+            return None;
+        }
+
+        let mut clicked = None;
+
+        Area::new(Id::new("toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, vec2(-12.0, -12.0))
+            .order(Order::Foreground)
+            .show(ctx, |ui| {
+                let mut dismissed = None;
+
+                for (index, toast) in self.items.iter().enumerate() {
+                    let (icon, color) = match toast.kind {
+                        ToastKind::Info => ("ℹ", ui.visuals().text_color()),
+                        ToastKind::Warning => ("⚠", ui.visuals().warn_fg_color),
+                        ToastKind::Error => ("❌", ui.visuals().error_fg_color),
+                    };
+
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(color, format!("{} {}", icon, toast.text));
+                            if let Some((label, action)) = toast.action
+                                && ui.small_button(label).clicked()
+                            {
+                                clicked = Some(action);
+                                dismissed = Some(index);
+                            }
+                            if ui.small_button("✕").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                    ui.add_space(6.0);
+                }
+
+                if let Some(index) = dismissed {
+                    self.items.remove(index);
+                }
+            });
+
+        clicked
+    }
+}
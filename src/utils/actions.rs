@@ -0,0 +1,335 @@
+//! Registry of the app's actions.
+//!
+//! Every action the user can trigger is registered once with its label,
+//! keyboard shortcut and the condition under which it can run. Buttons,
+//! shortcuts and the command palette all run actions through the
+//! [`ActionRegistry`], so they agree on what an action is called and when
+//! it is available.
+
+use egui::{Context, InputState, KeyboardShortcut};
+
+/// Runs an action on the app `T`.
+pub type Handler<T> = fn(&mut T, &Context);
+
+/// Tells whether an action can run on the app `T`, or why not.
+pub type Enabled<T> = fn(&T) -> Result<(), &'static str>;
+
+/// Something the user can do, from a button, a shortcut or the palette.
+pub struct Action<T> {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub shortcut: Option<KeyboardShortcut>,
+    enabled: Enabled<T>,
+    run: Handler<T>,
+}
+
+impl<T> Action<T> {
+    /// An action that is always available.
+    pub fn new(id: &'static str, label: &'static str, run: Handler<T>) -> Self {
+        Action {
+            id,
+            label,
+            shortcut: None,
+            enabled: |_| Ok(()),
+            run,
+        }
+    }
+
+    /// Binds the action to `shortcut`.
+    pub fn with_shortcut(mut self, shortcut: KeyboardShortcut) -> Self {
+        self.shortcut = Some(shortcut);
+        self
+    }
+
+    /// Makes the action available only when `enabled` says so.
+    pub fn with_enabled(mut self, enabled: Enabled<T>) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+/// An action matching a palette query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionMatch {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub shortcut: Option<KeyboardShortcut>,
+    /// Why the action can't run right now, `None` if it can
+    pub disabled: Option<&'static str>,
+    /// Byte offsets of the label's characters matched by the query
+    pub matched: Vec<usize>,
+}
+
+/// All actions of the app `T`, in the order they were registered.
+pub struct ActionRegistry<T> {
+    actions: Vec<Action<T>>,
+}
+
+impl<T> Default for ActionRegistry<T> {
+    fn default() -> Self {
+        ActionRegistry {
+            actions: Vec::new(),
+        }
+    }
+}
+
+impl<T> ActionRegistry<T> {
+    /// Adds `action`, replacing one registered with the same id.
+    pub fn register(&mut self, action: Action<T>) {
+        match self.actions.iter_mut().find(|known| known.id == action.id) {
+            Some(known) => *known = action,
+            None => self.actions.push(action),
+        }
+    }
+
+    /// The action registered as `id`.
+    pub fn get(&self, id: &str) -> Option<&Action<T>> {
+        self.actions.iter().find(|action| action.id == id)
+    }
+
+    /// Whether the action `id` can run on `app`, or why not.
+    pub fn check(&self, id: &str, app: &T) -> Result<(), &'static str> {
+        let action = self.get(id).ok_or("Unknown action")?;
+        (action.enabled)(app)
+    }
+
+    /// Runs the action `id` on `app` if it can run, or says why not.
+    pub fn run(&self, id: &str, app: &mut T, ctx: &Context) -> Result<(), &'static str> {
+        let action = self.get(id).ok_or("Unknown action")?;
+        (action.enabled)(app)?;
+        tracing::debug!(action = id, "Running action");
+        (action.run)(app, ctx);
+        Ok(())
+    }
+
+    /// The action whose shortcut was pressed this frame, consuming the key.
+    ///
+    /// Shortcuts with more modifiers are tried first, as a shortcut matches
+    /// while extra modifiers are held.
+    pub fn pressed(&self, input: &mut InputState) -> Option<&'static str> {
+        let mut bound: Vec<(&'static str, KeyboardShortcut)> = self
+            .actions
+            .iter()
+            .filter_map(|action| action.shortcut.map(|shortcut| (action.id, shortcut)))
+            .collect();
+        bound.sort_by_key(|(_, shortcut)| std::cmp::Reverse(modifier_count(shortcut)));
+        bound
+            .into_iter()
+            .find(|(_, shortcut)| input.consume_shortcut(shortcut))
+            .map(|(id, _)| id)
+    }
+
+    /// Actions whose label matches `query`, best first, with whether they
+    /// can run on `app`.
+    ///
+    /// An empty query lists every action in registration order.
+    pub fn search(&self, query: &str, app: &T) -> Vec<ActionMatch> {
+        let mut matches: Vec<(i32, ActionMatch)> = self
+            .actions
+            .iter()
+            .filter_map(|action| {
+                let (score, matched) = fuzzy_match(query, action.label)?;
+                Some((
+                    score,
+                    ActionMatch {
+                        id: action.id,
+                        label: action.label,
+                        shortcut: action.shortcut,
+                        disabled: (action.enabled)(app).err(),
+                        matched,
+                    },
+                ))
+            })
+            .collect();
+        // Stable, so equal scores keep the registration order
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, found)| found).collect()
+    }
+}
+
+fn modifier_count(shortcut: &KeyboardShortcut) -> usize {
+    let modifiers = shortcut.modifiers;
+    [
+        modifiers.alt,
+        modifiers.shift,
+        modifiers.ctrl || modifiers.command || modifiers.mac_cmd,
+    ]
+    .iter()
+    .filter(|held| **held)
+    .count()
+}
+
+/// Scores how well `query` matches `label`, `None` if it doesn't.
+///
+/// The characters of the query must appear in the label in order, ignoring
+/// case. Characters in a row and at the start of words score higher, so
+/// "cc" prefers "Clear cache" to "Check for updates". Returns the byte
+/// offsets of the matched characters too.
+pub fn fuzzy_match(query: &str, label: &str) -> Option<(i32, Vec<usize>)> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let mut matched = Vec::with_capacity(query.len());
+    let mut score = 0;
+    let mut wanted = query.iter().peekable();
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for (offset, c) in label.char_indices() {
+        let Some(&&next) = wanted.peek() else {
+            break;
+        };
+        let word_start = previous.is_none_or(|p| !p.is_alphanumeric());
+        if c.to_lowercase().eq(std::iter::once(next)) {
+            wanted.next();
+            matched.push(offset);
+            score += 1;
+            if previous_matched {
+                score += 4;
+            }
+            if word_start {
+                score += 6;
+            }
+            previous_matched = true;
+        } else {
+            // Gaps cost a little, so tighter matches win
+            if !matched.is_empty() {
+                score -= 1;
+            }
+            previous_matched = false;
+        }
+        previous = Some(c);
+    }
+    wanted.peek().is_none().then_some((score, matched))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, Key, Modifiers, RawInput};
+
+    #[derive(Default)]
+    struct App {
+        translating: bool,
+        translated: usize,
+        palette_open: bool,
+    }
+
+    fn registry() -> ActionRegistry<App> {
+        let mut registry = ActionRegistry::default();
+        registry.register(
+            Action::new("translate", "Translate", |app: &mut App, _| {
+                app.translated += 1
+            })
+            .with_shortcut(KeyboardShortcut::new(Modifiers::COMMAND, Key::P))
+            .with_enabled(|app| match app.translating {
+                true => Err("Already translating"),
+                false => Ok(()),
+            }),
+        );
+        registry.register(Action::new("cache.clear", "Clear cache", |_, _| {}));
+        registry.register(Action::new("updates.check", "Check for updates", |_, _| {}));
+        registry.register(
+            Action::new("palette", "Command palette", |app: &mut App, _| {
+                app.palette_open = true
+            })
+            .with_shortcut(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::P,
+            )),
+        );
+        registry
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("", "Translate").is_some());
+        assert!(fuzzy_match("TRANS", "Translate").is_some());
+        assert!(fuzzy_match("tx", "Translate").is_none());
+        assert!(fuzzy_match("clear cache", "Clear cache").is_some());
+
+        let (_, matched) = fuzzy_match("cc", "Clear cache").unwrap();
+        assert_eq!(matched, vec![0, 6]);
+        let (word_starts, _) = fuzzy_match("cc", "Clear cache").unwrap();
+        let (inside_word, _) = fuzzy_match("cc", "Check for updates").unwrap();
+        assert!(word_starts > inside_word);
+    }
+
+    #[test]
+    fn test_search_ranks_and_reports_disabled() {
+        let registry = registry();
+        let app = App {
+            translating: true,
+            ..App::default()
+        };
+        let all = registry.search("", &app);
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].id, "translate");
+        assert_eq!(all[0].disabled, Some("Already translating"));
+        assert_eq!(all[1].disabled, None);
+
+        let found = registry.search("clc", &app);
+        assert_eq!(found[0].id, "cache.clear");
+        assert!(found.iter().all(|found| found.id != "translate"));
+        assert!(registry.search("zzz", &app).is_empty());
+    }
+
+    #[test]
+    fn test_run_respects_enabled() {
+        let registry = registry();
+        let ctx = Context::default();
+        let mut app = App::default();
+        assert_eq!(registry.run("translate", &mut app, &ctx), Ok(()));
+        assert_eq!(app.translated, 1);
+
+        app.translating = true;
+        assert_eq!(
+            registry.run("translate", &mut app, &ctx),
+            Err("Already translating")
+        );
+        assert_eq!(app.translated, 1);
+        assert!(registry.run("missing", &mut app, &ctx).is_err());
+
+        // Other actions don't depend on it
+        assert_eq!(registry.run("palette", &mut app, &ctx), Ok(()));
+        assert!(app.palette_open);
+    }
+
+    #[test]
+    fn test_register_replaces_same_id() {
+        let mut registry = registry();
+        registry.register(Action::new("cache.clear", "Empty cache", |_, _| {}));
+        assert_eq!(registry.get("cache.clear").unwrap().label, "Empty cache");
+        assert_eq!(registry.search("", &App::default()).len(), 4);
+    }
+
+    #[test]
+    fn test_pressed_prefers_more_modifiers() {
+        let registry = registry();
+        let press = |modifiers: Modifiers| {
+            let ctx = Context::default();
+            let input = RawInput {
+                modifiers,
+                events: vec![Event::Key {
+                    key: Key::P,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers,
+                }],
+                ..RawInput::default()
+            };
+            ctx.begin_pass(input);
+            let pressed = ctx.input_mut(|i| registry.pressed(i));
+            let _ = ctx.end_pass();
+            pressed
+        };
+        assert_eq!(
+            press(Modifiers::COMMAND | Modifiers::SHIFT),
+            Some("palette")
+        );
+        assert_eq!(press(Modifiers::COMMAND), Some("translate"));
+        assert_eq!(press(Modifiers::NONE), None);
+    }
+}
@@ -0,0 +1,329 @@
+//! Finding the source phrase a translated word came from.
+//!
+//! The model is asked which source phrase corresponds to a word of the
+//! translation and answers with a substring of the source. [`locate`] finds
+//! that answer in the source: verbatim if it can, otherwise the most similar
+//! stretch, which is only a best guess. Answers are kept per translation pair
+//! and word by [`Aligner`], which also spaces the requests out.
+
+use crate::utils::query;
+use crate::utils::script::{Script, script_of};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Shortest time between two alignment requests.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(800);
+
+/// Lowest similarity a stretch of the source needs to count as a best guess.
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// Longest source searched for a best guess, in characters.
+const MAX_FUZZY_CHARS: usize = 20_000;
+
+/// Where the answer was found in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alignment {
+    /// Byte range in the source
+    pub range: Range<usize>,
+    /// The answer was found as it is, not only something similar to it
+    pub exact: bool,
+}
+
+/// Byte range of the word at byte `offset` of `text`.
+///
+/// A word is a run of letters and digits, with apostrophes and hyphens
+/// between them. Scripts written without spaces have no visible word
+/// boundaries, so there the word is the single character.
+pub fn word_at(text: &str, offset: usize) -> Option<Range<usize>> {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    // The character at the offset, or the one before it at the end of a word
+    let (start, c) = text[offset..]
+        .chars()
+        .next()
+        .filter(|&c| is_word_char(c))
+        .map(|c| (offset, c))
+        .or_else(|| {
+            text[..offset]
+                .char_indices()
+                .next_back()
+                .filter(|&(_, c)| is_word_char(c))
+        })?;
+    if is_spaceless(c) {
+        return Some(start..start + c.len_utf8());
+    }
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let i = chars.partition_point(|&(at, _)| at < start);
+    let letter = |j: usize| {
+        chars
+            .get(j)
+            .is_some_and(|&(_, c)| is_word_char(c) && !is_spaceless(c))
+    };
+    let joiner = |j: usize| chars.get(j).is_some_and(|&(_, c)| is_joiner(c));
+    let mut first = i;
+    loop {
+        if first >= 1 && letter(first - 1) {
+            first -= 1;
+        } else if first >= 2 && joiner(first - 1) && letter(first - 2) {
+            first -= 2;
+        } else {
+            break;
+        }
+    }
+    let mut last = i;
+    loop {
+        if letter(last + 1) {
+            last += 1;
+        } else if joiner(last + 1) && letter(last + 2) {
+            last += 2;
+        } else {
+            break;
+        }
+    }
+    let (at, c) = chars[last];
+    Some(chars[first].0..at + c.len_utf8())
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+fn is_joiner(c: char) -> bool {
+    matches!(c, '\'' | '’' | '-')
+}
+
+/// Whether `c` belongs to a script written without spaces between words.
+pub fn is_spaceless(c: char) -> bool {
+    // Korean is written with spaces
+    let hangul = matches!(c as u32, 0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF);
+    matches!(script_of(c), Some(Script::Cjk | Script::Thai)) && !hangul
+}
+
+/// The answer without the quotes, labels and full stop models add around it.
+pub fn clean_answer(answer: &str) -> &str {
+    let mut answer = answer.trim();
+    if let Some((label, rest)) = answer.split_once(':')
+        && label.len() < 20
+        && label.to_lowercase().contains("source")
+    {
+        answer = rest.trim();
+    }
+    answer
+        .trim_end_matches(['.', '。'])
+        .trim_matches(|c: char| {
+            matches!(
+                c,
+                '"' | '\'' | '`' | '“' | '”' | '‘' | '’' | '「' | '」' | '«' | '»'
+            )
+        })
+        .trim()
+}
+
+/// Finds `answer` in `source`.
+///
+/// The first verbatim occurrence wins, then the first one ignoring case.
+/// Otherwise the stretch of the source most similar to the answer is a best
+/// guess, if it is similar enough.
+pub fn locate(source: &str, answer: &str) -> Option<Alignment> {
+    let answer = clean_answer(answer);
+    if answer.is_empty() {
+        return None;
+    }
+    if let Some(start) = source.find(answer) {
+        return Some(Alignment {
+            range: start..start + answer.len(),
+            exact: true,
+        });
+    }
+    if let Some(range) = query::find_all(source, answer).into_iter().next() {
+        return Some(Alignment { range, exact: true });
+    }
+    best_guess(source, answer).map(|range| Alignment {
+        range,
+        exact: false,
+    })
+}
+
+/// The stretch of `source` as long as `answer` with the most character
+/// pairs in common with it, widened to whole words.
+fn best_guess(source: &str, answer: &str) -> Option<Range<usize>> {
+    let chars: Vec<(usize, char)> = source
+        .char_indices()
+        .map(|(i, c)| (i, c.to_lowercase().next().unwrap_or(c)))
+        .collect();
+    let wanted: Vec<char> = answer.chars().flat_map(char::to_lowercase).collect();
+    let width = wanted.len();
+    if width < 2 || chars.len() < width || chars.len() > MAX_FUZZY_CHARS {
+        return None;
+    }
+    let wanted = bigrams(&wanted);
+
+    let mut best: Option<(f64, usize)> = None;
+    for start in 0..=chars.len() - width {
+        let window: Vec<char> = chars[start..start + width]
+            .iter()
+            .map(|(_, c)| *c)
+            .collect();
+        let score = dice(&wanted, &bigrams(&window));
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, start));
+        }
+    }
+    let (score, start) = best?;
+    if score < MIN_SIMILARITY {
+        return None;
+    }
+
+    let begin = chars[start].0;
+    let end = chars.get(start + width).map_or(source.len(), |(i, _)| *i);
+    let first = word_at(source, begin).map_or(begin, |word| word.start.min(begin));
+    let last = word_at(source, end.saturating_sub(1))
+        .filter(|_| end > begin)
+        .map_or(end, |word| word.end.max(end));
+    Some(first..last)
+}
+
+fn bigrams(chars: &[char]) -> HashMap<(char, char), usize> {
+    let mut counts = HashMap::new();
+    for pair in chars.windows(2) {
+        *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Dice coefficient of two bigram multisets, 1.0 for identical ones.
+fn dice(a: &HashMap<(char, char), usize>, b: &HashMap<(char, char), usize>) -> f64 {
+    let total: usize = a.values().sum::<usize>() + b.values().sum::<usize>();
+    if total == 0 {
+        return 0.0;
+    }
+    let common: usize = a
+        .iter()
+        .map(|(pair, count)| (*count).min(b.get(pair).copied().unwrap_or(0)))
+        .sum();
+    2.0 * common as f64 / total as f64
+}
+
+/// Identifies a source text and its translation.
+pub fn pair_hash(source: &str, translation: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    translation.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Answers so far and the pace of new requests.
+#[derive(Debug, Default)]
+pub struct Aligner {
+    /// Answer per translation pair and word
+    answers: HashMap<(u64, String), String>,
+    /// When the last request was allowed to start
+    last_slot: Option<Instant>,
+}
+
+impl Aligner {
+    /// Most answers kept before they are all dropped.
+    const MAX_ANSWERS: usize = 500;
+
+    /// The model's answer for `word` in a pair, if it was asked before.
+    pub fn cached(&self, pair: u64, word: &str) -> Option<&str> {
+        self.answers
+            .get(&(pair, word.to_string()))
+            .map(String::as_str)
+    }
+
+    /// Keeps the model's answer for `word` in a pair.
+    pub fn store(&mut self, pair: u64, word: String, answer: String) {
+        if self.answers.len() >= Self::MAX_ANSWERS {
+            self.answers.clear();
+        }
+        self.answers.insert((pair, word), answer);
+    }
+
+    /// Reserves the next request slot, returning how long to wait for it.
+    pub fn next_slot(&mut self, now: Instant) -> Duration {
+        let slot = self
+            .last_slot
+            .map_or(now, |last| (last + MIN_INTERVAL).max(now));
+        self.last_slot = Some(slot);
+        slot - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, offset: usize) -> Option<&str> {
+        word_at(text, offset).map(|range| &text[range])
+    }
+
+    #[test]
+    fn test_word_at() {
+        let text = "It's a well-known fact, isn't it?";
+        assert_eq!(word(text, 1), Some("It's"));
+        assert_eq!(word(text, 4), Some("It's"));
+        assert_eq!(word(text, 10), Some("well-known"));
+        assert_eq!(word(text, 22), Some("fact"));
+        assert_eq!(word(text, 5), Some("a"));
+        assert_eq!(word(text, 23), None);
+        assert_eq!(word(text, text.len()), None);
+        assert_eq!(word("-dash-", 1), Some("dash"));
+        // One character of a script written without spaces
+        assert_eq!(word("我喜欢猫。", 3), Some("喜"));
+        assert_eq!(word("안녕하세요 세계", 3), Some("안녕하세요"));
+    }
+
+    #[test]
+    fn test_clean_answer() {
+        assert_eq!(clean_answer("  \"the cat\".\n"), "the cat");
+        assert_eq!(clean_answer("Source phrase: «le chat»"), "le chat");
+        assert_eq!(clean_answer("「猫」。"), "猫");
+    }
+
+    #[test]
+    fn test_locate_prefers_the_first_exact_match() {
+        let source = "The cat sat. The Cat ran.";
+        let found = locate(source, "'Cat'").unwrap();
+        assert_eq!(&source[found.range.clone()], "Cat");
+        assert!(found.exact);
+
+        let found = locate(source, "the cat").unwrap();
+        assert_eq!(found.range, 0..7);
+        assert!(found.exact);
+    }
+
+    #[test]
+    fn test_locate_falls_back_to_a_best_guess() {
+        let source = "Wir haben das Angebot sorgfältig geprüft und angenommen.";
+        // Slightly misquoted by the model
+        let found = locate(source, "sorgfaltig gepruft").unwrap();
+        assert!(!found.exact);
+        assert_eq!(&source[found.range], "sorgfältig geprüft");
+
+        assert_eq!(locate(source, "completely unrelated"), None);
+        assert_eq!(locate(source, "  "), None);
+    }
+
+    #[test]
+    fn test_aligner_caches_and_spaces_requests() {
+        let mut aligner = Aligner::default();
+        let pair = pair_hash("Hello", "Hallo");
+        assert_ne!(pair, pair_hash("Hello", "Servus"));
+        assert_eq!(aligner.cached(pair, "Hallo"), None);
+        aligner.store(pair, "Hallo".to_string(), "Hello".to_string());
+        assert_eq!(aligner.cached(pair, "Hallo"), Some("Hello"));
+        assert_eq!(aligner.cached(pair_hash("Hello", "Servus"), "Hallo"), None);
+
+        let now = Instant::now();
+        assert_eq!(aligner.next_slot(now), Duration::ZERO);
+        assert_eq!(aligner.next_slot(now), MIN_INTERVAL);
+        assert_eq!(aligner.next_slot(now + MIN_INTERVAL * 5), Duration::ZERO);
+    }
+}
@@ -0,0 +1,246 @@
+//! Spoken progress of translations, for screen readers.
+//!
+//! Announcements are written to an AccessKit live region, which screen
+//! readers read out whenever its text changes. They follow the same
+//! [`UiMessage`]s that update the translation panel: the start of a
+//! translation, how much of it has arrived (at most once per
+//! [`PROGRESS_INTERVAL`]), its completion and whatever ended it early.
+//!
+//! Nothing is written while no assistive technology is connected, as egui
+//! only builds the accessibility tree then.
+
+use crate::channel::channel::UiMessage;
+use egui::accesskit::{Live, Role};
+use egui::{Context, Id};
+use std::time::{Duration, Instant};
+
+/// Shortest time between two progress announcements.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Id of the live region node.
+const LIVE_REGION_ID: &str = "announcements";
+
+/// Something to be read out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announcement {
+    pub text: String,
+    /// Interrupts what is being read, for errors
+    pub urgent: bool,
+}
+
+/// Announces the progress of translations.
+#[derive(Debug, Default)]
+pub struct Announcer {
+    current: Option<Announcement>,
+    /// Characters received of the translation streaming in
+    received: usize,
+    /// When progress was last announced, while a translation streams
+    progress_at: Option<Instant>,
+}
+
+impl Announcer {
+    /// The last announcement, read out when it was made.
+    pub fn current(&self) -> Option<&Announcement> {
+        self.current.as_ref()
+    }
+
+    fn say(&mut self, text: impl Into<String>, urgent: bool) {
+        let text = text.into();
+        tracing::debug!(text, "Announcing");
+        self.current = Some(Announcement { text, urgent });
+    }
+
+    /// Announces a translation that was just requested.
+    pub fn started(&mut self, now: Instant) {
+        self.received = 0;
+        self.progress_at = Some(now);
+        self.say("Translation started", false);
+    }
+
+    /// Follows a message about the translation.
+    ///
+    /// `translated_chars` counts the characters of the finished
+    /// translation, as shown; it is only called on completion.
+    pub fn message(
+        &mut self,
+        msg: &UiMessage,
+        translated_chars: impl FnOnce() -> usize,
+        now: Instant,
+    ) {
+        match msg {
+            UiMessage::UpdateTranslation(chunk) => {
+                self.received += chunk.chars().count();
+                if let Some(at) = self.progress_at
+                    && now.duration_since(at) >= PROGRESS_INTERVAL
+                {
+                    self.progress_at = Some(now);
+                    self.say(format!("Received {} characters", self.received), false);
+                }
+            }
+            UiMessage::TranslationComplete => {
+                self.progress_at = None;
+                let chars = translated_chars();
+                self.say(format!("Translation complete, {} characters", chars), false);
+            }
+            UiMessage::Error(e) | UiMessage::Offline(e) => {
+                self.progress_at = None;
+                self.say(format!("Translation failed: {}", e), true);
+            }
+            UiMessage::TranslationRefused(_) => {
+                self.progress_at = None;
+                self.say("The provider declined the translation", true);
+            }
+            UiMessage::TranslationTruncated => {
+                self.progress_at = None;
+                self.say("Translation stopped at the output limit", true);
+            }
+            UiMessage::TranslationLooped(_) => {
+                self.progress_at = None;
+                self.say("Translation stopped for repeating itself", true);
+            }
+            UiMessage::TranslationCancelled => {
+                self.progress_at = None;
+                self.say("Translation cancelled", false);
+            }
+            _ => {}
+        }
+    }
+
+    /// Writes the current announcement to the live region.
+    pub fn ui(&self, ctx: &Context) {
+        let Some(announcement) = &self.current else {
+            return;
+        };
+        ctx.accesskit_node_builder(Id::new(LIVE_REGION_ID), |node| {
+            node.set_role(Role::Status);
+            node.set_live(if announcement.urgent {
+                Live::Assertive
+            } else {
+                Live::Polite
+            });
+            node.set_label(announcement.text.clone());
+        });
+    }
+}
+
+/// Gives the accessibility node `id` the whole of `text` as its value.
+///
+/// Text drawn in pieces, such as a translation streaming in as several
+/// labels, is then still read as one; `busy` tells that more is coming.
+pub fn expose_text(ctx: &Context, id: Id, label: &str, text: &str, busy: bool) {
+    ctx.accesskit_node_builder(id, |node| {
+        node.set_role(Role::Document);
+        node.set_label(label);
+        node.set_value(text);
+        node.set_read_only();
+        if busy {
+            node.set_busy();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::RawInput;
+    use egui::accesskit::{Node, NodeId};
+
+    /// One frame showing `announcer` and the translation `text`, returning
+    /// the accessibility nodes of both.
+    fn frame(ctx: &Context, announcer: &Announcer, text: &str, busy: bool) -> (Node, Node) {
+        let output = ctx.run(RawInput::default(), |ctx| {
+            announcer.ui(ctx);
+            expose_text(ctx, Id::new("translation"), "Translation", text, busy);
+        });
+        let update = output.platform_output.accesskit_update.unwrap();
+        let node = |id: Id| {
+            let id = NodeId::from(id.value());
+            let (_, node) = update
+                .nodes
+                .iter()
+                .find(|(node_id, _)| *node_id == id)
+                .unwrap();
+            node.clone()
+        };
+        (node(Id::new(LIVE_REGION_ID)), node(Id::new("translation")))
+    }
+
+    #[test]
+    fn test_scripted_translation_is_announced_in_order() {
+        let ctx = Context::default();
+        ctx.enable_accesskit();
+        let start = Instant::now();
+        let at = |secs: f32| start + Duration::from_secs_f32(secs);
+
+        let mut announcer = Announcer::default();
+        let mut text = String::new();
+        let mut frames = Vec::new();
+        announcer.started(at(0.0));
+        frames.push(frame(&ctx, &announcer, &text, true));
+
+        let chunks = [
+            (0.5, "Hallo "),
+            (1.0, "Welt, "),
+            (4.5, "wie geht's"),
+            (5.0, "?"),
+        ];
+        for (secs, chunk) in chunks {
+            let msg = UiMessage::UpdateTranslation(chunk.to_string());
+            announcer.message(&msg, || unreachable!(), at(secs));
+            text.push_str(chunk);
+            frames.push(frame(&ctx, &announcer, &text, true));
+        }
+        let chars = text.chars().count();
+        announcer.message(&UiMessage::TranslationComplete, || chars, at(5.5));
+        frames.push(frame(&ctx, &announcer, &text, false));
+
+        let spoken: Vec<&str> = frames
+            .iter()
+            .map(|(region, _)| region.label().unwrap())
+            .collect();
+        assert_eq!(
+            spoken,
+            vec![
+                "Translation started",
+                "Translation started",
+                "Translation started",
+                "Received 22 characters",
+                // Throttled
+                "Received 22 characters",
+                "Translation complete, 23 characters",
+            ]
+        );
+        let values: Vec<&str> = frames
+            .iter()
+            .map(|(_, translation)| translation.value().unwrap())
+            .collect();
+        assert_eq!(values[2], "Hallo Welt, ");
+        assert_eq!(values[5], "Hallo Welt, wie geht's?");
+        assert!(frames[4].1.is_busy());
+        assert!(!frames[5].1.is_busy());
+        assert_eq!(frames[5].0.role(), Role::Status);
+        assert_eq!(frames[5].0.live(), Some(Live::Polite));
+    }
+
+    #[test]
+    fn test_errors_interrupt() {
+        let mut announcer = Announcer::default();
+        assert_eq!(announcer.current(), None);
+        announcer.started(Instant::now());
+        let msg = UiMessage::Error("Invalid API key".to_string());
+        announcer.message(&msg, || 0, Instant::now());
+        assert_eq!(
+            announcer.current(),
+            Some(&Announcement {
+                text: "Translation failed: Invalid API key".to_string(),
+                urgent: true,
+            })
+        );
+
+        // No progress once the translation ended
+        let later = Instant::now() + PROGRESS_INTERVAL * 2;
+        let msg = UiMessage::UpdateTranslation("late".to_string());
+        announcer.message(&msg, || 0, later);
+        assert!(announcer.current().unwrap().urgent);
+    }
+}
@@ -0,0 +1,135 @@
+//! Text direction detection for right-to-left languages.
+//!
+//! The direction of a translation comes from its text when it has enough
+//! strongly directional characters, judged by their Unicode bidi class, and
+//! otherwise from the target language.
+
+use unicode_bidi::{BidiClass, bidi_class};
+
+/// How many strongly directional characters are looked at.
+const SCAN_LIMIT: usize = 64;
+
+/// Fewer strong characters than this are not enough to judge a text.
+const MIN_STRONG: usize = 3;
+
+/// Paragraph direction of a text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Direction {
+    /// Whether this is right-to-left.
+    pub fn is_rtl(self) -> bool {
+        self == Direction::RightToLeft
+    }
+}
+
+/// Returns the direction a language is written in.
+///
+/// Accepts the names in the language list as well as English names and
+/// language codes (`"ar"`, `"he-IL"`).
+pub fn language_direction(language: &str) -> Direction {
+    let language = language.trim().to_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    match primary {
+        "العربية" | "arabic" | "ar" | "עברית" | "hebrew" | "he" | "iw" | "فارسی" | "persian"
+        | "farsi" | "fa" | "اردو" | "urdu" | "ur" | "ייִדיש" | "yiddish" | "yi" => {
+            Direction::RightToLeft
+        }
+        _ => Direction::LeftToRight,
+    }
+}
+
+/// Detects the direction of `text` from the majority of its first strongly
+/// directional characters.
+///
+/// Returns `None` when the text has too few of them to tell, e.g. when it is
+/// empty or only digits and punctuation.
+pub fn detect_direction(text: &str) -> Option<Direction> {
+    let mut rtl = 0;
+    let mut ltr = 0;
+
+    for c in text.chars() {
+        match bidi_class(c) {
+            BidiClass::R | BidiClass::AL => rtl += 1,
+            BidiClass::L => ltr += 1,
+            _ => continue,
+        }
+        if rtl + ltr >= SCAN_LIMIT {
+            break;
+        }
+    }
+
+    if rtl + ltr < MIN_STRONG {
+        None
+    } else if rtl > ltr {
+        Some(Direction::RightToLeft)
+    } else {
+        Some(Direction::LeftToRight)
+    }
+}
+
+/// Returns the direction to show a translation into `target_language` in.
+pub fn text_direction(text: &str, target_language: &str) -> Direction {
+    detect_direction(text).unwrap_or_else(|| language_direction(target_language))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_direction() {
+        assert_eq!(language_direction("العربية"), Direction::RightToLeft);
+        assert_eq!(language_direction("עברית"), Direction::RightToLeft);
+        assert_eq!(language_direction("Arabic"), Direction::RightToLeft);
+        assert_eq!(language_direction("he-IL"), Direction::RightToLeft);
+        assert_eq!(language_direction("English"), Direction::LeftToRight);
+        assert_eq!(language_direction("中文"), Direction::LeftToRight);
+        assert_eq!(language_direction(""), Direction::LeftToRight);
+    }
+
+    #[test]
+    fn test_detect_direction() {
+        assert_eq!(
+            detect_direction("مرحبا بالعالم"),
+            Some(Direction::RightToLeft)
+        );
+        assert_eq!(detect_direction("שלום עולם"), Some(Direction::RightToLeft));
+        assert_eq!(
+            detect_direction("Hello world"),
+            Some(Direction::LeftToRight)
+        );
+        assert_eq!(detect_direction("你好世界"), Some(Direction::LeftToRight));
+        // Digits and punctuation are not strongly directional
+        assert_eq!(detect_direction("123, 456!"), None);
+        assert_eq!(detect_direction(""), None);
+    }
+
+    #[test]
+    fn test_detect_direction_uses_majority() {
+        // An embedded Latin name doesn't flip an Arabic sentence
+        assert_eq!(
+            detect_direction("استخدمت برنامج Rust اليوم في العمل"),
+            Some(Direction::RightToLeft)
+        );
+        assert_eq!(
+            detect_direction("The word سلام means peace"),
+            Some(Direction::LeftToRight)
+        );
+    }
+
+    #[test]
+    fn test_text_direction_falls_back_to_language() {
+        assert_eq!(text_direction("", "العربية"), Direction::RightToLeft);
+        assert_eq!(text_direction("42", "עברית"), Direction::RightToLeft);
+        assert_eq!(
+            text_direction("Hello there", "العربية"),
+            Direction::LeftToRight
+        );
+        assert_eq!(text_direction("", "English"), Direction::LeftToRight);
+    }
+}
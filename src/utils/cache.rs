@@ -2,13 +2,52 @@
 //!
 //! This module provides in-memory and persistent caching of translations
 //! to avoid redundant API calls for previously translated text.
+//!
+//! On disk the cache is a JSON snapshot plus an append-only journal next to
+//! it (`translation_cache.journal`). A new entry appends one JSON line to the
+//! journal instead of rewriting the snapshot; loading replays the journal
+//! over the snapshot. Once the journal grows past a size limit, or entries
+//! are evicted, both are compacted into a fresh snapshot. A line torn by a
+//! crash mid-append ends the replay, and the entries before it are kept.
+//!
+//! Translations of different models differ, so each provider profile reads
+//! and writes its own [`Namespace`]. Entries written before the cache was
+//! split keep their keys and make up the legacy namespace, which profiles
+//! still fall back to; the shared cache option uses it for everything.
+//!
+//! Entries record the [`Provenance`] of their translation. It isn't part of
+//! the key, so entries written before it was recorded still match.
+//!
+//! At startup the cache is loaded in the background: it reads as empty until
+//! loaded, and writes wait for the load.
+//!
+//! The entries are split into [`SHARDS`] maps by the hash of their key, each
+//! behind its own lock, so a burst of writes only holds up lookups of the
+//! keys in the shard being written. Async callers use [`TranslationCache::get_async`]
+//! and [`TranslationCache::set_async`], which never wait for a lock or the
+//! disk on the calling task.
 
 use crate::lock_mutex;
+use crate::utils::loading::{self, LoadGate};
+use crate::utils::paths;
+use crate::utils::provenance::Provenance;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+/// Journal size above which it is compacted into the snapshot.
+const JOURNAL_LIMIT_BYTES: u64 = 512 * 1024;
+
+/// Number of separately locked maps the entries are split into.
+pub const SHARDS: usize = 16;
+
+type Entries = HashMap<String, CacheEntry>;
 
 /// A cache entry containing translated text and optional keyword analysis the translated text
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,12 +56,141 @@ struct CacheEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     keyword_analysis: Option<String>,
     timestamp: i64,
+    /// Order the entries were written in, telling apart entries written
+    /// within the same second
+    #[serde(default)]
+    sequence: u64,
+    /// What the translation was made with, unknown for older entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provenance: Option<Provenance>,
+}
+
+/// One line of the journal: an entry added or replaced.
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalRecord {
+    key: String,
+    entry: CacheEntry,
+}
+
+/// What replaying the journal found.
+#[derive(Debug, Default)]
+struct JournalReplay {
+    /// Size of the journal file
+    bytes: u64,
+    /// Records applied
+    records: usize,
+    /// Replay stopped at a line that could not be read
+    torn: bool,
+}
+
+/// Part of the cache a handle reads and writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Namespace {
+    /// Entries not tied to a profile: those written before the cache was
+    /// split, and every entry while the cache is shared
+    Legacy,
+    /// Entries of one provider profile, by its [`fingerprint`]
+    Profile(String),
+}
+
+/// Identifies the provider, model and sampling settings a translation was
+/// made with.
+pub fn fingerprint(base_url: &str, model: &str, temperature: Option<f32>) -> String {
+    match temperature {
+        Some(temperature) => format!("{}@{}|t={}", model, base_url, temperature),
+        None => format!("{}@{}", model, base_url),
+    }
+}
+
+/// The cache was busy with a write to the shard of the key looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheBusy;
+
+/// The entries, split into maps by the hash of their key.
+struct Shards(Vec<Mutex<Entries>>);
+
+impl Shards {
+    fn new(count: usize) -> Self {
+        Shards((0..count.max(1)).map(|_| Mutex::default()).collect())
+    }
+
+    /// Index of the map holding `key`.
+    fn index(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish() as usize % self.0.len()
+    }
+
+    /// The map holding `key`.
+    fn of(&self, key: &str) -> &Mutex<Entries> {
+        &self.0[self.index(key)]
+    }
+
+    /// Locks every map, always in the same order so two callers can't wait
+    /// on each other.
+    fn lock_all(&self) -> Vec<MutexGuard<'_, Entries>> {
+        self.0.iter().map(|shard| lock_mutex!(shard)).collect()
+    }
+
+    /// Replaces the entries with `entries`.
+    fn replace(&self, entries: Entries) {
+        let mut shards = self.lock_all();
+        for shard in shards.iter_mut() {
+            shard.clear();
+        }
+        for (key, entry) in entries {
+            shards[self.index(&key)].insert(key, entry);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|shard| lock_mutex!(shard).len()).sum()
+    }
+}
+
+/// Every entry of the locked `shards` in one map, for writing to disk.
+fn merged(shards: &[MutexGuard<'_, Entries>]) -> Entries {
+    shards
+        .iter()
+        .flat_map(|shard| shard.iter())
+        .map(|(key, entry)| (key.clone(), entry.clone()))
+        .collect()
+}
+
+/// A translation found in the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHit {
+    pub translation: String,
+    pub keyword_analysis: Option<String>,
+    /// Found in the legacy namespace by a profile, so it may have been made
+    /// by another model
+    pub legacy: bool,
+    pub provenance: Option<Provenance>,
 }
 
 /// Translation cache for storing translations in memory and on disk
+///
+/// Handles made with [`Self::scoped`] share the entries and files, each
+/// reading and writing its own namespace.
 pub struct TranslationCache {
-    cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
+    cache: Arc<Shards>,
     cache_file: PathBuf,
+    /// Entries added since the snapshot was written, one JSON line each
+    journal_file: PathBuf,
+    /// Held while appending to the journal, as writes to different shards
+    /// run at the same time
+    journal: Arc<Mutex<()>>,
+    /// Journal size above which it is compacted
+    journal_limit: u64,
+    /// Sequence number of the next entry written
+    next_sequence: Arc<AtomicU64>,
+    namespace: Namespace,
+    /// Whether [`Self::set`] stores anything
+    writable: bool,
+    /// Opens once the entries on disk are loaded
+    loaded: LoadGate,
+    /// Recorded with every entry [`Self::set`] stores
+    provenance: Option<Provenance>,
 }
 
 impl TranslationCache {
@@ -32,20 +200,108 @@ impl TranslationCache {
     ///
     /// * `cache_file` - Path to the cache file for persistence
     pub fn new(cache_file: PathBuf) -> Self {
+        let translation_cache = Self::unloaded(cache_file);
+        translation_cache.load();
+        translation_cache
+    }
+
+    /// Creates a cache whose entries are loaded on a background thread.
+    ///
+    /// Until they are, lookups miss and writes wait for them.
+    pub fn load_in_background(cache_file: PathBuf) -> Self {
+        let translation_cache = Self::unloaded(cache_file);
+        let loader = translation_cache.scoped(Namespace::Legacy);
+        loading::spawn("translation-cache", move || loader.load());
+        translation_cache
+    }
+
+    /// A cache without entries whose load hasn't started.
+    fn unloaded(cache_file: PathBuf) -> Self {
+        Self::unloaded_with_shards(cache_file, SHARDS)
+    }
+
+    /// A cache without entries split into `shards` maps.
+    fn unloaded_with_shards(cache_file: PathBuf, shards: usize) -> Self {
         tracing::info!("Initializing translation cache at: {:?}", cache_file);
+        let journal_file = cache_file.with_extension("journal");
+        TranslationCache {
+            cache: Arc::new(Shards::new(shards)),
+            cache_file,
+            journal_file,
+            journal: Arc::default(),
+            journal_limit: JOURNAL_LIMIT_BYTES,
+            next_sequence: Arc::new(AtomicU64::new(0)),
+            namespace: Namespace::Legacy,
+            writable: true,
+            loaded: LoadGate::closed(),
+            provenance: None,
+        }
+    }
 
-        let cache = if cache_file.exists() {
-            Self::load_from_file(&cache_file).unwrap_or_default()
+    /// Reads the snapshot and replays the journal over it.
+    fn load(&self) {
+        let _span = tracing::info_span!("load_translation_cache").entered();
+        let mut cache = if self.cache_file.exists() {
+            Self::load_from_file(&self.cache_file).unwrap_or_default()
         } else {
             HashMap::new()
         };
+        let replay = Self::replay_journal(&self.journal_file, &mut cache);
+
+        let next_sequence = cache.values().map(|entry| entry.sequence + 1).max();
+        self.next_sequence
+            .store(next_sequence.unwrap_or(0), Ordering::Relaxed);
+        // Nothing was written meanwhile, writes wait for the gate
+        self.cache.replace(cache);
+        self.loaded.open();
+
+        // A torn line would hide every line appended after it
+        if (replay.torn || replay.bytes > self.journal_limit)
+            && let Err(e) = self.compact()
+        {
+            tracing::warn!("Failed to compact the cache journal: {}", e);
+        }
+    }
 
+    /// A handle to the same entries that reads and writes `namespace`.
+    pub fn scoped(&self, namespace: Namespace) -> TranslationCache {
         TranslationCache {
-            cache: Arc::new(Mutex::new(cache)),
-            cache_file,
+            cache: self.cache.clone(),
+            cache_file: self.cache_file.clone(),
+            journal_file: self.journal_file.clone(),
+            journal: self.journal.clone(),
+            journal_limit: self.journal_limit,
+            next_sequence: self.next_sequence.clone(),
+            namespace,
+            writable: self.writable,
+            loaded: self.loaded.clone(),
+            provenance: self.provenance.clone(),
+        }
+    }
+
+    /// A handle to the same entries that reads them but never stores any,
+    /// for translations that must not be persisted.
+    pub fn without_writes(&self) -> TranslationCache {
+        TranslationCache {
+            writable: false,
+            ..self.scoped(self.namespace.clone())
+        }
+    }
+
+    /// A handle to the same entries that records `provenance` with the
+    /// entries it stores.
+    pub fn with_provenance(&self, provenance: Provenance) -> TranslationCache {
+        TranslationCache {
+            provenance: Some(provenance),
+            ..self.scoped(self.namespace.clone())
         }
     }
 
+    /// Namespace this handle reads and writes.
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
     /// Generates a cache key from source text, target language, and keyword analysis setting
     fn generate_key(
         source_text: &str,
@@ -58,6 +314,20 @@ impl TranslationCache {
         )
     }
 
+    /// Key of an entry in this handle's namespace; legacy keys have no prefix.
+    pub fn key(
+        &self,
+        source_text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+    ) -> String {
+        let key = Self::generate_key(source_text, target_language, enable_keyword_analysis);
+        match &self.namespace {
+            Namespace::Legacy => key,
+            Namespace::Profile(fingerprint) => format!("@{}::{}", fingerprint, key),
+        }
+    }
+
     /// Retrieves a translation from the cache
     ///
     /// # Arguments
@@ -75,22 +345,117 @@ impl TranslationCache {
         target_language: &str,
         enable_keyword_analysis: bool,
     ) -> Option<(String, Option<String>)> {
-        let key = Self::generate_key(source_text, target_language, enable_keyword_analysis);
-        let cache = lock_mutex!(self.cache);
+        self.lookup(source_text, target_language, enable_keyword_analysis)
+            .map(|hit| (hit.translation, hit.keyword_analysis))
+    }
+
+    /// Retrieves a translation from this handle's namespace, falling back
+    /// to the legacy namespace for a profile.
+    pub fn lookup(
+        &self,
+        source_text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+    ) -> Option<CacheHit> {
+        self.lookup_with(source_text, target_language, enable_keyword_analysis, true)
+            .unwrap_or(None)
+    }
 
-        if let Some(entry) = cache.get(&key) {
+    /// Like [`Self::lookup`], but returns [`CacheBusy`] instead of waiting
+    /// while a write holds the shard of the key.
+    pub fn try_lookup(
+        &self,
+        source_text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+    ) -> Result<Option<CacheHit>, CacheBusy> {
+        self.lookup_with(source_text, target_language, enable_keyword_analysis, false)
+    }
+
+    /// Retrieves a translation without blocking the calling task.
+    ///
+    /// Answers right away unless a write holds the shard of the key, in
+    /// which case the lookup moves to a blocking thread.
+    pub async fn get_async(
+        &self,
+        source_text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+    ) -> Option<(String, Option<String>)> {
+        let hit = match self.try_lookup(source_text, target_language, enable_keyword_analysis) {
+            Ok(hit) => hit,
+            Err(CacheBusy) => {
+                tracing::debug!("Cache shard busy, looking up on a blocking thread");
+                let cache = self.scoped(self.namespace.clone());
+                let (source_text, target_language) =
+                    (source_text.to_string(), target_language.to_string());
+                tokio::task::spawn_blocking(move || {
+                    cache.lookup(&source_text, &target_language, enable_keyword_analysis)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Cache lookup failed: {}", e);
+                    None
+                })
+            }
+        };
+        hit.map(|hit| (hit.translation, hit.keyword_analysis))
+    }
+
+    /// Looks an entry up, waiting for busy shards only if `wait` is set.
+    fn lookup_with(
+        &self,
+        source_text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+        wait: bool,
+    ) -> Result<Option<CacheHit>, CacheBusy> {
+        let key = self.key(source_text, target_language, enable_keyword_analysis);
+        let mut found = self.find(&key, wait)?.map(|entry| (entry, false));
+        if found.is_none() && self.namespace != Namespace::Legacy {
+            let legacy_key =
+                Self::generate_key(source_text, target_language, enable_keyword_analysis);
+            found = self.find(&legacy_key, wait)?.map(|entry| (entry, true));
+        }
+        Ok(if let Some((entry, legacy)) = found {
             tracing::info!(
-                "Cache hit for key: {}",
+                "Cache hit{} for key: {}",
+                if legacy {
+                    " in the legacy namespace"
+                } else {
+                    ""
+                },
                 key.chars().take(50).collect::<String>()
             );
-            Some((entry.translation.clone(), entry.keyword_analysis.clone()))
+            Some(CacheHit {
+                translation: entry.translation,
+                keyword_analysis: entry.keyword_analysis,
+                legacy,
+                provenance: entry.provenance,
+            })
         } else {
             tracing::debug!(
                 "Cache miss for key: {}",
                 key.chars().take(50).collect::<String>()
             );
             None
-        }
+        })
+    }
+
+    /// The entry stored as `key`, or [`CacheBusy`] if its shard is locked
+    /// and `wait` isn't set.
+    fn find(&self, key: &str, wait: bool) -> Result<Option<CacheEntry>, CacheBusy> {
+        let shard = self.cache.of(key);
+        let shard = if wait {
+            lock_mutex!(shard)
+        } else {
+            match shard.try_lock() {
+                Ok(shard) => shard,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => return Err(CacheBusy),
+            }
+        };
+        Ok(shard.get(key).cloned())
     }
 
     /// Stores a translation in the cache
@@ -113,54 +478,112 @@ impl TranslationCache {
         const MAX_CACHE_SIZE: usize = 1000;
         const CLEANUP_SIZE: usize = 100;
 
-        let key = Self::generate_key(source_text, target_language, enable_keyword_analysis);
+        if !self.writable {
+            tracing::debug!("Translation excluded from the cache");
+            return;
+        }
+        self.loaded.wait();
+
+        let key = self.key(source_text, target_language, enable_keyword_analysis);
         let entry = CacheEntry {
             translation,
             keyword_analysis,
             timestamp: chrono::Utc::now().timestamp(),
+            sequence: self.next_sequence.fetch_add(1, Ordering::Relaxed),
+            provenance: self.provenance.clone(),
         };
 
         {
-            let mut cache = lock_mutex!(self.cache);
-            cache.insert(key.clone(), entry);
+            // Appended under the shard's lock, so the journal has the writes
+            // of a key in the order they were made
+            let mut shard = lock_mutex!(self.cache.of(&key));
+            if let Err(e) = self.append_to_journal(&key, &entry) {
+                tracing::warn!("Failed to append to the cache journal: {}", e);
+            }
+            shard.insert(key.clone(), entry);
             tracing::info!(
                 "Cached translation for key: {}",
                 key.chars().take(50).collect::<String>()
             );
+        }
 
-            // Check if cache size exceeds limit
-            if cache.len() > MAX_CACHE_SIZE {
+        // Check if cache size exceeds limit
+        let evicted = self.cache.len() > MAX_CACHE_SIZE && {
+            let mut shards = self.cache.lock_all();
+            let len: usize = shards.iter().map(|shard| shard.len()).sum();
+            // Another write may have evicted meanwhile
+            len > MAX_CACHE_SIZE && {
                 tracing::info!(
                     "Cache size {} exceeds limit {}, removing oldest {} entries",
-                    cache.len(),
+                    len,
                     MAX_CACHE_SIZE,
                     CLEANUP_SIZE
                 );
 
-                // Collect all entries with their keys and timestamps
-                let mut entries: Vec<(String, i64)> = cache
+                // Collect all entries with their shard, key and write order
+                let mut entries: Vec<(usize, String, (i64, u64))> = shards
                     .iter()
-                    .map(|(k, v)| (k.clone(), v.timestamp))
+                    .enumerate()
+                    .flat_map(|(index, shard)| {
+                        shard
+                            .iter()
+                            .map(move |(k, v)| (index, k.clone(), (v.timestamp, v.sequence)))
+                    })
                     .collect();
 
-                // Sort by timestamp (oldest first)
-                entries.sort_by(|a, b| a.1.cmp(&b.1));
+                // Sort by age (oldest first)
+                entries.sort_by_key(|(_, _, order)| *order);
 
                 // Remove oldest CLEANUP_SIZE entries
-                for (key_to_remove, _) in entries.iter().take(CLEANUP_SIZE) {
-                    cache.remove(key_to_remove);
+                for (index, key_to_remove, _) in entries.iter().take(CLEANUP_SIZE) {
+                    shards[*index].remove(key_to_remove);
                 }
 
-                tracing::info!("Cache cleanup completed, new size: {}", cache.len());
+                tracing::info!(
+                    "Cache cleanup completed, new size: {}",
+                    len - CLEANUP_SIZE.min(len)
+                );
+                true
             }
-        }
+        };
 
-        // Save to disk asynchronously (best effort)
-        if let Err(e) = self.save_to_file() {
+        // Evicted entries are only gone from disk once the snapshot is rewritten
+        if (evicted || self.journal_len() > self.journal_limit)
+            && let Err(e) = self.compact()
+        {
             tracing::warn!("Failed to save cache to disk: {}", e);
         }
     }
 
+    /// Stores a translation without blocking the calling task.
+    ///
+    /// [`Self::set`] may wait for the cache to load and write to disk, so it
+    /// runs on a blocking thread; this returns once it is stored.
+    pub async fn set_async(
+        &self,
+        source_text: &str,
+        target_language: &str,
+        enable_keyword_analysis: bool,
+        translation: String,
+        keyword_analysis: Option<String>,
+    ) {
+        let cache = self.scoped(self.namespace.clone());
+        let (source_text, target_language) = (source_text.to_string(), target_language.to_string());
+        let stored = tokio::task::spawn_blocking(move || {
+            cache.set(
+                &source_text,
+                &target_language,
+                enable_keyword_analysis,
+                translation,
+                keyword_analysis,
+            )
+        })
+        .await;
+        if let Err(e) = stored {
+            tracing::warn!("Failed to store translation in the cache: {}", e);
+        }
+    }
+
     /// Loads cache from file
     fn load_from_file(
         path: &std::path::Path,
@@ -171,46 +594,182 @@ impl TranslationCache {
         Ok(cache)
     }
 
+    /// Applies the journal's records to `cache`, stopping at the first line
+    /// that can't be read.
+    fn replay_journal(path: &Path, cache: &mut HashMap<String, CacheEntry>) -> JournalReplay {
+        let Ok(content) = fs::read(path) else {
+            return JournalReplay::default();
+        };
+        let mut replay = JournalReplay {
+            bytes: content.len() as u64,
+            ..JournalReplay::default()
+        };
+        for line in content.split_inclusive(|&b| b == b'\n') {
+            // A line without its newline was cut off mid-append
+            let record = line
+                .strip_suffix(b"\n")
+                .and_then(|line| serde_json::from_slice::<JournalRecord>(line).ok());
+            let Some(record) = record else {
+                tracing::warn!(
+                    "Cache journal is damaged after {} records, ignoring the rest",
+                    replay.records
+                );
+                replay.torn = true;
+                break;
+            };
+            cache.insert(record.key, record.entry);
+            replay.records += 1;
+        }
+        if replay.records > 0 {
+            tracing::info!("Replayed {} entries from the cache journal", replay.records);
+        }
+        replay
+    }
+
+    /// Appends an added or replaced entry to the journal as one line.
+    fn append_to_journal(&self, key: &str, entry: &CacheEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(&JournalRecord {
+            key: key.to_string(),
+            entry: entry.clone(),
+        })?;
+        line.push(b'\n');
+        let _journal = lock_mutex!(self.journal);
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal_file)?;
+        // One write, so a crash leaves at most the last line torn
+        journal.write_all(&line)
+    }
+
+    /// Current size of the journal in bytes.
+    fn journal_len(&self) -> u64 {
+        fs::metadata(&self.journal_file).map_or(0, |meta| meta.len())
+    }
+
+    /// Writes every entry into a fresh snapshot and empties the journal.
+    ///
+    /// Every shard stays locked throughout, so no entry is appended to the
+    /// journal between the snapshot and its removal.
+    fn compact(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let shards = self.cache.lock_all();
+        let _journal = lock_mutex!(self.journal);
+        self.save_to_file(&merged(&shards))?;
+        if let Err(e) = fs::remove_file(&self.journal_file)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
     /// Saves cache to file
-    fn save_to_file(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let cache = self.cache.lock().expect("Cache mutex poisoned");
-        let content = serde_json::to_string(&*cache)?;
-        fs::write(&self.cache_file, content)?;
+    ///
+    /// The snapshot is written to a temporary file first and renamed over
+    /// the old one, so a crash never leaves half a snapshot.
+    fn save_to_file(&self, cache: &Entries) -> Result<(), Box<dyn std::error::Error>> {
+        let content = serde_json::to_string(cache)?;
+        let temp_file = self.cache_file.with_extension("json.tmp");
+        fs::write(&temp_file, content)?;
+        fs::rename(&temp_file, &self.cache_file)?;
         tracing::debug!("Saved {} entries to cache file", cache.len());
         Ok(())
     }
 
     /// Clears all entries from the cache
-    #[allow(dead_code)]
     pub fn clear(&self) {
-        let mut cache = self.cache.lock().expect("Cache mutex poisoned");
-        cache.clear();
+        self.loaded.wait();
+        let mut shards = self.cache.lock_all();
+        for shard in shards.iter_mut() {
+            shard.clear();
+        }
         tracing::info!("Cache cleared");
 
-        // Remove cache file
-        if self.cache_file.exists() {
-            let _ = fs::remove_file(&self.cache_file);
+        // Remove cache file and journal
+        for file in [&self.cache_file, &self.journal_file] {
+            if file.exists() {
+                let _ = fs::remove_file(file);
+            }
+        }
+    }
+
+    /// Clears all entries after writing them to a timestamped backup file.
+    ///
+    /// Nothing is cleared if the backup cannot be written.
+    ///
+    /// # Returns
+    ///
+    /// The path of the backup file, used to undo the clear
+    pub fn clear_with_backup(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.loaded.wait();
+        let backup_file = self.cache_file.with_extension(format!(
+            "{}.bak.json",
+            chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")
+        ));
+        {
+            let shards = self.cache.lock_all();
+            fs::write(&backup_file, serde_json::to_string(&merged(&shards))?)?;
+        }
+        self.clear();
+        Ok(backup_file)
+    }
+
+    /// Restores the entries of a backup written by [`Self::clear_with_backup`].
+    ///
+    /// Entries added since the clear are kept. The backup file is removed.
+    pub fn restore_backup(&self, backup_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let entries = Self::load_from_file(backup_file)?;
+        self.loaded.wait();
+        for (key, entry) in entries {
+            lock_mutex!(self.cache.of(&key)).entry(key).or_insert(entry);
         }
+        self.compact()?;
+        let _ = fs::remove_file(backup_file);
+        tracing::info!("Restored translation cache from {:?}", backup_file);
+        Ok(())
+    }
+
+    /// File the cache is saved to.
+    pub fn path(&self) -> &Path {
+        &self.cache_file
     }
 
     /// Returns the number of entries in the cache
     pub fn len(&self) -> usize {
-        lock_mutex!(self.cache).len()
+        self.cache.len()
+    }
+
+    /// Whether the cache has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of entries in the legacy namespace
+    pub fn legacy_len(&self) -> usize {
+        self.cache
+            .lock_all()
+            .iter()
+            .flat_map(|shard| shard.keys())
+            .filter(|key| !key.starts_with('@'))
+            .count()
     }
 }
 
-impl Default for TranslationCache {
-    fn default() -> Self {
-        let cache_file = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("ai-translate")
-            .join("translation_cache.json");
+impl TranslationCache {
+    /// Where the cache is kept, creating its folder if needed.
+    pub fn default_path() -> PathBuf {
+        let cache_file = paths::config_dir().join("translation_cache.json");
 
         if let Some(parent) = cache_file.parent() {
             let _ = fs::create_dir_all(parent);
         }
+        cache_file
+    }
+}
 
-        Self::new(cache_file)
+impl Default for TranslationCache {
+    fn default() -> Self {
+        Self::new(Self::default_path())
     }
 }
 
@@ -218,6 +777,7 @@ impl Default for TranslationCache {
 mod tests {
     use super::*;
     use std::env;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_cache_key_generation() {
@@ -248,6 +808,7 @@ mod tests {
         assert_eq!(result, None);
 
         // Cleanup
+        let _ = fs::remove_file(cache_file.with_extension("journal"));
         let _ = fs::remove_file(cache_file);
     }
 
@@ -277,6 +838,7 @@ mod tests {
         }
 
         // Cleanup
+        let _ = fs::remove_file(cache_file.with_extension("journal"));
         let _ = fs::remove_file(cache_file);
     }
 
@@ -293,9 +855,417 @@ mod tests {
         assert!(cache.get("test", "Chinese", false).is_none());
 
         // Cleanup
+        let _ = fs::remove_file(cache_file.with_extension("journal"));
+        let _ = fs::remove_file(cache_file);
+    }
+
+    #[test]
+    fn test_cache_clear_with_backup_and_restore() {
+        let cache_file = env::temp_dir().join("test_cache_backup.json");
+        let cache = TranslationCache::new(cache_file.clone());
+
+        cache.set("old", "Chinese", false, "旧".to_string(), None);
+        let backup_file = cache.clear_with_backup().unwrap();
+        assert!(backup_file.exists());
+        assert_eq!(cache.len(), 0);
+
+        cache.set("new", "Chinese", false, "新".to_string(), None);
+        cache.restore_backup(&backup_file).unwrap();
+        assert!(cache.get("old", "Chinese", false).is_some());
+        assert!(cache.get("new", "Chinese", false).is_some());
+        assert!(!backup_file.exists());
+
+        // Cleanup
+        let _ = fs::remove_file(cache_file.with_extension("journal"));
         let _ = fs::remove_file(cache_file);
     }
 
+    /// A cache at a fresh path in the temp directory.
+    fn fresh_cache(name: &str) -> (TranslationCache, PathBuf) {
+        let dir = env::temp_dir().join(format!("test_cache_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let cache_file = dir.join("translation_cache.json");
+        (TranslationCache::new(cache_file.clone()), dir)
+    }
+
+    #[test]
+    fn test_new_entries_go_to_the_journal() {
+        let (cache, dir) = fresh_cache("journal_replay");
+        let cache_file = cache.cache_file.clone();
+        assert_eq!(cache.journal_file, dir.join("translation_cache.journal"));
+
+        cache.set("one", "Deutsch", false, "eins".to_string(), None);
+        cache.set("two", "Deutsch", false, "zwei".to_string(), None);
+        cache.set("one", "Deutsch", false, "Eins".to_string(), None);
+
+        // Only the journal was written, one line per entry
+        assert!(!cache_file.exists());
+        let journal = fs::read_to_string(&cache.journal_file).unwrap();
+        assert_eq!(journal.lines().count(), 3);
+
+        // Replayed in order over the snapshot, the last write wins
+        let reloaded = TranslationCache::new(cache_file);
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(
+            reloaded.get("one", "Deutsch", false),
+            Some(("Eins".to_string(), None))
+        );
+        assert_eq!(
+            reloaded.get("two", "Deutsch", false),
+            Some(("zwei".to_string(), None))
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_large_journal_is_compacted() {
+        let (mut cache, dir) = fresh_cache("journal_compact");
+        cache.journal_limit = 1024;
+
+        for i in 0..20 {
+            cache.set(
+                &format!("text {}", i),
+                "Deutsch",
+                false,
+                "x".repeat(100),
+                None,
+            );
+        }
+        // Compacted at least once, and never far past the limit
+        assert!(cache.cache_file.exists());
+        assert!(cache.journal_len() <= 1024 + 200);
+
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 20);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_torn_journal_line_ends_replay() {
+        let (cache, dir) = fresh_cache("journal_torn");
+        cache.set("one", "Deutsch", false, "eins".to_string(), None);
+        cache.set("two", "Deutsch", false, "zwei".to_string(), None);
+
+        // A crash cut the last append short
+        let journal = fs::read_to_string(&cache.journal_file).unwrap();
+        let torn = &journal[..journal.len() - 10];
+        fs::write(&cache.journal_file, torn).unwrap();
+
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded.get("one", "Deutsch", false).is_some());
+        // The torn tail is compacted away, so later appends are replayed
+        assert!(!reloaded.journal_file.exists());
+        reloaded.set("three", "Deutsch", false, "drei".to_string(), None);
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded.get("three", "Deutsch", false).is_some());
+
+        // Garbage in the middle stops the replay there as well
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(&reloaded.journal_file)
+            .unwrap();
+        journal
+            .write_all(
+                b"not json\n{\"key\":\"k\",\"entry\":{\"translation\":\"t\",\"timestamp\":0}}\n",
+            )
+            .unwrap();
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded.get("three", "Deutsch", false).is_some());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_profiles_are_isolated() {
+        let (cache, dir) = fresh_cache("profiles");
+        let a = cache.scoped(Namespace::Profile(fingerprint("https://a", "m1", None)));
+        let b = cache.scoped(Namespace::Profile(fingerprint("https://a", "m2", None)));
+        let cooler = cache.scoped(Namespace::Profile(fingerprint(
+            "https://a",
+            "m1",
+            Some(0.3),
+        )));
+
+        a.set("hello", "Deutsch", false, "Hallo".to_string(), None);
+        assert_eq!(
+            a.get("hello", "Deutsch", false),
+            Some(("Hallo".to_string(), None))
+        );
+        assert_eq!(b.get("hello", "Deutsch", false), None);
+        assert_eq!(cooler.get("hello", "Deutsch", false), None);
+        assert_eq!(cache.get("hello", "Deutsch", false), None);
+
+        b.set("hello", "Deutsch", false, "Guten Tag".to_string(), None);
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        let a = reloaded.scoped(a.namespace().clone());
+        assert_eq!(
+            a.get("hello", "Deutsch", false),
+            Some(("Hallo".to_string(), None))
+        );
+        assert_eq!(reloaded.legacy_len(), 0);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_profiles_fall_back_to_legacy_entries() {
+        let (cache, dir) = fresh_cache("legacy");
+        // A snapshot from before the cache was split by profile
+        let old = serde_json::json!({
+            "Deutsch::false::hello": {"translation": "Hallo", "timestamp": 1}
+        });
+        fs::write(&cache.cache_file, old.to_string()).unwrap();
+        let cache = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(cache.legacy_len(), 1);
+
+        let profile = cache.scoped(Namespace::Profile(fingerprint("https://a", "m1", None)));
+        let hit = profile.lookup("hello", "Deutsch", false).unwrap();
+        assert_eq!(hit.translation, "Hallo");
+        assert!(hit.legacy);
+        // The shared cache reads the legacy entries as its own
+        assert!(!cache.lookup("hello", "Deutsch", false).unwrap().legacy);
+
+        // The profile's own entry takes precedence
+        profile.set("hello", "Deutsch", false, "Servus".to_string(), None);
+        let hit = profile.lookup("hello", "Deutsch", false).unwrap();
+        assert_eq!(hit.translation, "Servus");
+        assert!(!hit.legacy);
+        assert_eq!(cache.get("hello", "Deutsch", false).unwrap().0, "Hallo");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_handles_without_writes() {
+        let (cache, dir) = fresh_cache("without_writes");
+        cache.set("hello", "Deutsch", false, "Hallo".to_string(), None);
+        let excluded = cache
+            .scoped(Namespace::Profile(fingerprint("https://a", "m1", None)))
+            .without_writes();
+        excluded.set("Acme", "Deutsch", false, "Acme".to_string(), None);
+        assert_eq!(excluded.get("Acme", "Deutsch", false), None);
+        // Earlier entries are still read
+        assert_eq!(
+            excluded
+                .lookup("hello", "Deutsch", false)
+                .unwrap()
+                .translation,
+            "Hallo"
+        );
+
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_entries_record_provenance() {
+        let (cache, dir) = fresh_cache("provenance");
+        cache.set("hello", "Deutsch", false, "Hallo".to_string(), None);
+        let provenance = Provenance {
+            model: "m1".to_string(),
+            template: "Standard".to_string(),
+            ..Provenance::default()
+        };
+        cache.with_provenance(provenance.clone()).set(
+            "bye",
+            "Deutsch",
+            false,
+            "Tschüss".to_string(),
+            None,
+        );
+
+        // Written before provenance was recorded
+        let old = cache.lookup("hello", "Deutsch", false).unwrap();
+        assert_eq!(old.provenance, None);
+        // Not part of the key: any handle finds the entry
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        let hit = reloaded.lookup("bye", "Deutsch", false).unwrap();
+        assert_eq!(hit.translation, "Tschüss");
+        assert_eq!(hit.provenance, Some(provenance));
+
+        // Older snapshots without the field still load
+        let entry: CacheEntry =
+            serde_json::from_str(r#"{"translation":"Hallo","timestamp":0}"#).unwrap();
+        assert_eq!(entry.provenance, None);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_loading_in_background() {
+        let (cache, dir) = fresh_cache("background");
+        // About 5 MB on disk
+        let long_text = "Lorem ipsum dolor sit amet. ".repeat(180);
+        for i in 0..999 {
+            cache.set(
+                &format!("{} {}", long_text, i),
+                "English",
+                false,
+                long_text.clone(),
+                None,
+            );
+        }
+        cache.compact().unwrap();
+        assert!(fs::metadata(&cache.cache_file).unwrap().len() > 5_000_000);
+
+        let started = Instant::now();
+        let loading = TranslationCache::load_in_background(cache.cache_file.clone());
+        assert!(started.elapsed() < Duration::from_millis(100));
+        // A write waits for the entries on disk instead of replacing them
+        loading.set("hello", "Deutsch", false, "Hallo".to_string(), None);
+        assert_eq!(loading.len(), 1000);
+        assert!(
+            loading
+                .get(&format!("{} 7", long_text), "English", false)
+                .is_some()
+        );
+
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 1000);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_entries_are_spread_over_shards() {
+        let (cache, dir) = fresh_cache("shards");
+        for i in 0..200 {
+            cache.set(
+                &format!("text {}", i),
+                "Deutsch",
+                false,
+                i.to_string(),
+                None,
+            );
+        }
+        let sizes: Vec<usize> = cache.cache.lock_all().iter().map(|s| s.len()).collect();
+        assert_eq!(sizes.len(), SHARDS);
+        assert!(sizes.iter().all(|&size| size > 0), "{:?}", sizes);
+        assert_eq!(cache.len(), 200);
+
+        // Compacted from every shard and loaded back into them
+        cache.compact().unwrap();
+        let reloaded = TranslationCache::new(cache.cache_file.clone());
+        assert_eq!(reloaded.len(), 200);
+        assert_eq!(
+            reloaded.get("text 42", "Deutsch", false),
+            Some(("42".to_string(), None))
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async_access_never_waits_for_a_busy_shard() {
+        let (cache, dir) = fresh_cache("async");
+        cache
+            .set_async("hello", "Deutsch", false, "Hallo".to_string(), None)
+            .await;
+        assert_eq!(
+            cache
+                .try_lookup("hello", "Deutsch", false)
+                .unwrap()
+                .unwrap()
+                .translation,
+            "Hallo"
+        );
+
+        // A write holding the shard of the key for a while
+        let key = TranslationCache::generate_key("hello", "Deutsch", false);
+        let (locked, is_locked) = std::sync::mpsc::channel();
+        let writer = cache.scoped(Namespace::Legacy);
+        let write = std::thread::spawn(move || {
+            let _shard = lock_mutex!(writer.cache.of(&key));
+            locked.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+        });
+        is_locked.recv().unwrap();
+        assert_eq!(cache.try_lookup("hello", "Deutsch", false), Err(CacheBusy));
+
+        // Answered once the write is done, without holding up the runtime
+        let started = Instant::now();
+        let lookup = cache.get_async("hello", "Deutsch", false);
+        let ticked = tokio::time::sleep(Duration::from_millis(20));
+        tokio::pin!(lookup, ticked);
+        tokio::select! {
+            _ = &mut ticked => {}
+            _ = &mut lookup => panic!("lookup should wait for the write"),
+        }
+        assert_eq!(lookup.await, Some(("Hallo".to_string(), None)));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        write.join().unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    /// Compares lookup latency while other threads write in bulk, with
+    /// every entry behind one lock and split into shards. Only prints the
+    /// numbers, as they depend on the machine; run with
+    /// `cargo test --lib -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_lookups_during_bulk_inserts() {
+        let measure = |shards: usize| {
+            let dir = env::temp_dir().join(format!("test_cache_bench_{}", shards));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let mut cache =
+                TranslationCache::unloaded_with_shards(dir.join("translation_cache.json"), shards);
+            // Compaction locks every shard either way
+            cache.journal_limit = u64::MAX;
+            cache.load();
+            for i in 0..100 {
+                cache.set(&format!("read {}", i), "Deutsch", false, "x".into(), None);
+            }
+
+            let text = "Lorem ipsum dolor sit amet. ".repeat(40);
+            let writers: Vec<_> = (0..4)
+                .map(|writer| {
+                    let cache = cache.scoped(Namespace::Legacy);
+                    let text = text.clone();
+                    std::thread::spawn(move || {
+                        for i in 0..200 {
+                            let source = format!("write {} {}", writer, i);
+                            cache.set(&source, "Deutsch", false, text.clone(), None);
+                        }
+                    })
+                })
+                .collect();
+            let mut latencies = Vec::new();
+            while !writers.iter().all(|writing| writing.is_finished()) {
+                for i in 0..100 {
+                    let started = Instant::now();
+                    assert!(
+                        cache
+                            .get(&format!("read {}", i), "Deutsch", false)
+                            .is_some()
+                    );
+                    latencies.push(started.elapsed());
+                }
+            }
+            for writing in writers {
+                writing.join().unwrap();
+            }
+            let _ = fs::remove_dir_all(dir);
+
+            latencies.sort();
+            let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+            let stalls = latencies
+                .iter()
+                .filter(|latency| **latency > Duration::from_micros(100))
+                .count();
+            println!(
+                "{:>2} shard(s): {} lookups, mean {:?}, p99.9 {:?}, max {:?}, {} over 100µs",
+                shards,
+                latencies.len(),
+                mean,
+                latencies[latencies.len() * 999 / 1000],
+                latencies.last().unwrap(),
+                stalls
+            );
+        };
+        measure(1);
+        measure(SHARDS);
+    }
+
     #[test]
     fn test_cache_limit() {
         let temp_dir = env::temp_dir();
@@ -318,10 +1288,7 @@ mod tests {
         }
 
         // Verify cache size is within limit
-        let cache_size = {
-            let cache_inner = cache.cache.lock().expect("Cache mutex poisoned");
-            cache_inner.len()
-        };
+        let cache_size = cache.len();
         assert!(
             cache_size <= 1000,
             "Cache size {} should be <= 1000",
@@ -333,6 +1300,7 @@ mod tests {
         assert!(cache.get("test_1400", "English", true).is_some());
 
         // Cleanup
+        let _ = fs::remove_file(cache_file.with_extension("journal"));
         let _ = fs::remove_file(cache_file);
     }
 }
@@ -0,0 +1,187 @@
+//! Rules keeping some translations out of the cache and the translation log.
+//!
+//! A rule matches a source text by a regular expression, a target language
+//! by name, or both. A translation matching any rule is still shown, but it
+//! is never written to disk: not to the cache, not to the history. Patterns
+//! are matched line by line, so `^` and `$` anchor at every line of a
+//! multi-line source.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// A rule excluding translations from the cache, as configured.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheRule {
+    /// Shown when the rule keeps a translation out of the cache
+    pub name: String,
+    /// Regular expression matched against the source text, empty to match
+    /// any text
+    #[serde(default)]
+    pub pattern: String,
+    /// Target languages the rule applies to, empty for all
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+impl CacheRule {
+    /// The name shown for the rule, its pattern if it has no name.
+    pub fn label(&self) -> &str {
+        if self.name.trim().is_empty() {
+            &self.pattern
+        } else {
+            self.name.trim()
+        }
+    }
+}
+
+/// Compiles a rule pattern, with `^` and `$` matching at line boundaries.
+fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).multi_line(true).build()
+}
+
+/// Checks that a rule can be used, returning why it can't.
+pub fn check(rule: &CacheRule) -> Result<(), String> {
+    if rule.pattern.trim().is_empty() && rule.languages.is_empty() {
+        return Err("Give a pattern, target languages or both".to_string());
+    }
+    if !rule.pattern.trim().is_empty() {
+        compile(&rule.pattern).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// A usable rule.
+struct CompiledRule {
+    label: String,
+    pattern: Option<Regex>,
+    languages: Vec<String>,
+}
+
+/// The configured rules, ready for matching.
+#[derive(Default)]
+pub struct CacheRules {
+    rules: Vec<CompiledRule>,
+}
+
+impl CacheRules {
+    /// Compiles `rules`.
+    ///
+    /// Settings only accept valid rules, so one that fails [`check`] here
+    /// was edited into the config file by hand; it is skipped with a warning.
+    pub fn new(rules: &[CacheRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| {
+                if let Err(e) = check(rule) {
+                    tracing::warn!(rule = rule.label(), "Skipping invalid cache rule: {}", e);
+                    return None;
+                }
+                let pattern = rule.pattern.trim();
+                Some(CompiledRule {
+                    label: rule.label().to_string(),
+                    pattern: (!pattern.is_empty())
+                        .then(|| compile(&rule.pattern).ok())
+                        .flatten(),
+                    languages: rule.languages.clone(),
+                })
+            })
+            .collect();
+        CacheRules { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first rule excluding the translation of `source_text` into
+    /// `target_language`, by its label.
+    pub fn matching(&self, source_text: &str, target_language: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                let language_matches = rule.languages.is_empty()
+                    || rule
+                        .languages
+                        .iter()
+                        .any(|language| language == target_language);
+                let text_matches = rule
+                    .pattern
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.is_match(source_text));
+                language_matches && text_matches
+            })
+            .map(|rule| rule.label.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, pattern: &str, languages: &[&str]) -> CacheRule {
+        CacheRule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            languages: languages.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_pattern_and_language_rules() {
+        let rules = CacheRules::new(&[
+            rule("Client names", r"(?i)\b(acme|globex)\b", &[]),
+            rule("Legal German", "", &["Deutsch"]),
+            rule("Contracts", "Vertrag", &["English", "中文"]),
+        ]);
+        assert_eq!(
+            rules.matching("Meeting with ACME tomorrow", "日本語"),
+            Some("Client names")
+        );
+        assert_eq!(rules.matching("Hello", "Deutsch"), Some("Legal German"));
+        assert_eq!(rules.matching("Der Vertrag", "English"), Some("Contracts"));
+        // Both the pattern and the language must match
+        assert_eq!(rules.matching("Der Vertrag", "Français"), None);
+        assert_eq!(rules.matching("Acmeville", "English"), None);
+        assert!(
+            CacheRules::default()
+                .matching("anything", "English")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_anchors_match_every_line() {
+        let rules = CacheRules::new(&[
+            rule("Confidential header", r"^CONFIDENTIAL:", &[]),
+            rule("Signed", r"Globex Corp\.$", &[]),
+        ]);
+        let source = "Dear team,\nCONFIDENTIAL: see below\nRegards";
+        assert_eq!(
+            rules.matching(source, "English"),
+            Some("Confidential header")
+        );
+        let source = "Regards,\nGlobex Corp.\nSent from my phone";
+        assert_eq!(rules.matching(source, "English"), Some("Signed"));
+        // Anchors still have to hold
+        assert_eq!(rules.matching("Not CONFIDENTIAL: here", "English"), None);
+        assert_eq!(rules.matching("Globex Corp. and others", "English"), None);
+        // Multi-line patterns can span lines
+        let rules = CacheRules::new(&[rule("Block", r"(?s)BEGIN.*END", &[])]);
+        assert_eq!(
+            rules.matching("BEGIN\nsecret\nEND", "English"),
+            Some("Block")
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules_are_reported_and_skipped() {
+        assert!(check(&rule("Broken", "(unclosed", &[])).is_err());
+        assert!(check(&rule("Empty", "  ", &[])).is_err());
+        assert!(check(&rule("Ok", "", &["English"])).is_ok());
+        assert!(check(&rule("Ok", "acme", &[])).is_ok());
+
+        let rules = CacheRules::new(&[rule("Broken", "(unclosed", &[]), rule("", "acme", &[])]);
+        // An unnamed rule is shown by its pattern
+        assert_eq!(rules.matching("(unclosed acme", "English"), Some("acme"));
+    }
+}
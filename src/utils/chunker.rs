@@ -0,0 +1,274 @@
+//! Splitting long texts into chunks that fit a request.
+//!
+//! Paragraphs are packed whole while they fit. A paragraph that is longer
+//! than a chunk on its own is split into sentences with the shared
+//! [`segmenter`] and those are packed instead, which matters for Chinese
+//! and Japanese text that often comes as one paragraph without blank lines.
+//! Only a single sentence longer than a chunk is cut inside, after a
+//! clause mark such as `，、；` where there is one; the chunk after such a
+//! cut gets the end of the one before as context, to read but not to
+//! translate again.
+//!
+//! The chunks are byte ranges that cover the text exactly once, so joining
+//! their texts gives back the original without any repeated overlap.
+
+use crate::utils::segmenter;
+use std::ops::Range;
+
+/// Marks after which an overlong sentence may be cut.
+const CLAUSE_MARKS: &[char] = &['，', '、', '；', '：', ',', ';', ':'];
+
+/// A piece of a text, as byte ranges into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub range: Range<usize>,
+    /// End of the previous chunk, when this one starts inside a sentence
+    pub context: Option<Range<usize>>,
+}
+
+/// Splits texts into chunks of up to a number of characters.
+#[derive(Debug, Clone)]
+pub struct TextChunker {
+    max_chars: usize,
+    /// Characters before a cut inside a sentence given as context
+    overlap_chars: usize,
+    lang_hint: Option<String>,
+}
+
+impl TextChunker {
+    /// A chunker for chunks of up to `max_chars` characters.
+    pub fn new(max_chars: usize) -> Self {
+        TextChunker {
+            max_chars: max_chars.max(1),
+            overlap_chars: 0,
+            lang_hint: None,
+        }
+    }
+
+    /// Gives up to `chars` characters before a cut inside a sentence as
+    /// context of the chunk after it.
+    pub fn with_overlap(mut self, chars: usize) -> Self {
+        self.overlap_chars = chars;
+        self
+    }
+
+    /// Splits sentences by the rules of `lang_hint`, see
+    /// [`segmenter::split_sentences`].
+    pub fn with_language(mut self, lang_hint: &str) -> Self {
+        self.lang_hint = Some(lang_hint.to_string());
+        self
+    }
+
+    /// The chunks of `text`, in order.
+    pub fn chunks(&self, text: &str) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        let mut packed: Option<Range<usize>> = None;
+        let mut packed_chars = 0;
+        for paragraph in paragraphs(text) {
+            let chars = text[paragraph.clone()].chars().count();
+            if chars > self.max_chars {
+                chunks.extend(packed.take().map(whole));
+                packed_chars = 0;
+                self.split_paragraph(text, paragraph, &mut chunks);
+                continue;
+            }
+            if packed_chars + chars > self.max_chars {
+                chunks.extend(packed.take().map(whole));
+                packed_chars = 0;
+            }
+            packed = Some(packed.map_or(paragraph.clone(), |range| range.start..paragraph.end));
+            packed_chars += chars;
+        }
+        chunks.extend(packed.map(whole));
+        chunks
+    }
+
+    /// Packs the sentences of an overlong `paragraph` of `text`.
+    fn split_paragraph(&self, text: &str, paragraph: Range<usize>, chunks: &mut Vec<Chunk>) {
+        let offset = paragraph.start;
+        let sentences =
+            segmenter::chunk_sentences(&text[paragraph], self.lang_hint.as_deref(), self.max_chars);
+        for sentences in sentences {
+            let range = offset + sentences.start..offset + sentences.end;
+            if text[range.clone()].chars().count() > self.max_chars {
+                self.cut_sentence(text, range, chunks);
+            } else {
+                chunks.push(whole(range));
+            }
+        }
+    }
+
+    /// Cuts an overlong sentence, after the last clause mark that fits in
+    /// a chunk or, failing that, after `max_chars` characters.
+    fn cut_sentence(&self, text: &str, sentence: Range<usize>, chunks: &mut Vec<Chunk>) {
+        let mut start = sentence.start;
+        let mut context = None;
+        while start < sentence.end {
+            let rest = &text[start..sentence.end];
+            let limit = rest
+                .char_indices()
+                .nth(self.max_chars)
+                .map_or(rest.len(), |(index, _)| index);
+            let end = if limit == rest.len() {
+                rest.len()
+            } else {
+                rest[..limit]
+                    .char_indices()
+                    .filter(|(_, c)| CLAUSE_MARKS.contains(c))
+                    .map(|(index, c)| index + c.len_utf8())
+                    .next_back()
+                    .unwrap_or(limit)
+            };
+            let range = start..start + end;
+            chunks.push(Chunk {
+                range: range.clone(),
+                context: context.take(),
+            });
+            if self.overlap_chars > 0 {
+                let piece = &text[range.clone()];
+                let overlap = piece
+                    .char_indices()
+                    .rev()
+                    .nth(self.overlap_chars - 1)
+                    .map_or(0, |(index, _)| index);
+                context = Some(range.start + overlap..range.end);
+            }
+            start = range.end;
+        }
+    }
+}
+
+fn whole(range: Range<usize>) -> Chunk {
+    Chunk {
+        range,
+        context: None,
+    }
+}
+
+/// Paragraphs of `text`, each with the blank lines after it, covering all
+/// of it.
+fn paragraphs(text: &str) -> Vec<Range<usize>> {
+    let mut paragraphs = Vec::new();
+    let mut start = 0;
+    let mut newlines = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '\n' => newlines += 1,
+            '\r' | ' ' | '\t' => {}
+            _ => {
+                if newlines >= 2 {
+                    paragraphs.push(start..index);
+                    start = index;
+                }
+                newlines = 0;
+            }
+        }
+    }
+    if start < text.len() {
+        paragraphs.push(start..text.len());
+    }
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// About 30,000 characters of Chinese in a single paragraph.
+    fn long_paragraph() -> String {
+        let sentences = [
+            "机器翻译在过去十年里取得了很大的进步。",
+            "神经网络模型能够学习两种语言之间的对应关系，并生成流畅的译文！",
+            "但是长文本的翻译仍然是一个难题，因为模型一次只能处理有限的内容。",
+            "你知道为什么句子的边界如此重要吗？",
+            "如果在句子中间切开，译文的衔接处就会出现明显的错误。",
+        ];
+        let mut text = String::new();
+        let mut i = 0;
+        while text.chars().count() < 30_000 {
+            text.push_str(sentences[i % sentences.len()]);
+            i += 1;
+        }
+        text
+    }
+
+    fn joined(text: &str, chunks: &[Chunk]) -> String {
+        chunks
+            .iter()
+            .map(|chunk| &text[chunk.range.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn test_single_paragraph_is_packed_by_sentence() {
+        let text = long_paragraph();
+        let chunks = TextChunker::new(1000).with_overlap(50).chunks(&text);
+        assert!(chunks.len() >= 30);
+        assert_eq!(joined(&text, &chunks), text);
+        for chunk in &chunks {
+            let piece = &text[chunk.range.clone()];
+            assert!(piece.chars().count() <= 1000);
+            assert!(
+                piece.ends_with(['。', '！', '？']),
+                "ends mid-sentence: {}",
+                piece
+            );
+            assert_eq!(chunk.context, None);
+            // Starts where a sentence starts
+            let before = &text[..chunk.range.start];
+            assert!(before.is_empty() || before.ends_with(['。', '！', '？']));
+        }
+    }
+
+    #[test]
+    fn test_overlong_sentence_is_cut_at_a_clause() {
+        let clause = "在这种情况下我们只能在逗号后面切开，";
+        let mut text = "前面的句子。".to_string();
+        text.push_str(&clause.repeat(20));
+        text.push_str("最后结束。\n\n下一段。");
+        let chunks = TextChunker::new(100).with_overlap(5).chunks(&text);
+        assert_eq!(joined(&text, &chunks), text);
+
+        let cut: Vec<&Chunk> = chunks.iter().filter(|c| c.context.is_some()).collect();
+        assert!(!cut.is_empty());
+        for (previous, chunk) in chunks.iter().zip(&chunks[1..]) {
+            assert!(text[chunk.range.clone()].chars().count() <= 100);
+            let Some(context) = &chunk.context else {
+                continue;
+            };
+            // The context is the end of the previous chunk, not repeated in this one
+            assert_eq!(context.end, chunk.range.start);
+            assert!(context.start >= previous.range.start);
+            assert_eq!(text[context.clone()].chars().count(), 5);
+            assert!(text[previous.range.clone()].ends_with('，'));
+        }
+        assert_eq!(&text[chunks.last().unwrap().range.clone()], "下一段。");
+    }
+
+    #[test]
+    fn test_paragraphs_are_packed_whole() {
+        let text = "First paragraph.\n\nSecond one.\n\n\nThird, a bit longer than the others.";
+        let chunks = TextChunker::new(40).chunks(text);
+        let pieces: Vec<&str> = chunks.iter().map(|c| &text[c.range.clone()]).collect();
+        assert_eq!(
+            pieces,
+            vec![
+                "First paragraph.\n\nSecond one.\n\n\n",
+                "Third, a bit longer than the others."
+            ]
+        );
+        assert!(TextChunker::new(30).chunks("").is_empty());
+    }
+
+    #[test]
+    fn test_text_without_marks_is_cut_at_the_limit() {
+        let text = "字".repeat(250);
+        let chunks = TextChunker::new(100).chunks(&text);
+        let lengths: Vec<usize> = chunks
+            .iter()
+            .map(|c| text[c.range.clone()].chars().count())
+            .collect();
+        assert_eq!(lengths, vec![100, 100, 50]);
+        assert!(chunks[1].context.is_none());
+    }
+}
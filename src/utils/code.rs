@@ -0,0 +1,753 @@
+//! Lightweight lexer for translating comments and strings inside source code.
+//!
+//! Code mode only translates human-readable spans (comments and prose-like
+//! string literals) and leaves every other byte of the snippet untouched.
+//! The spans are batched into a single request using numbered markers and
+//! spliced back into the original code once the translation arrives.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Programming language of a code snippet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CodeLanguage {
+    /// Detect from the fence info string or the code itself
+    #[default]
+    Auto,
+    Rust,
+    Python,
+    /// JavaScript and TypeScript
+    JavaScript,
+    /// C, C++, Java, Go and other C-like languages
+    CLike,
+    Shell,
+    /// Conservative `//`, `#`, `/* */` and quote heuristic
+    Generic,
+}
+
+impl CodeLanguage {
+    /// All languages, in display order.
+    pub const ALL: [CodeLanguage; 7] = [
+        CodeLanguage::Auto,
+        CodeLanguage::Rust,
+        CodeLanguage::Python,
+        CodeLanguage::JavaScript,
+        CodeLanguage::CLike,
+        CodeLanguage::Shell,
+        CodeLanguage::Generic,
+    ];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            CodeLanguage::Auto => "Auto-detect",
+            CodeLanguage::Rust => "Rust",
+            CodeLanguage::Python => "Python",
+            CodeLanguage::JavaScript => "JavaScript / TypeScript",
+            CodeLanguage::CLike => "C / C++ / Java / Go",
+            CodeLanguage::Shell => "Shell",
+            CodeLanguage::Generic => "Other",
+        }
+    }
+
+    /// Maps a markdown fence info string (e.g. "rust", "py") to a language.
+    fn from_fence_info(info: &str) -> Option<Self> {
+        let info = info.trim().to_ascii_lowercase();
+        let name = info.split([' ', ',', '{']).next().unwrap_or_default();
+        match name {
+            "rust" | "rs" => Some(CodeLanguage::Rust),
+            "python" | "py" | "python3" => Some(CodeLanguage::Python),
+            "js" | "javascript" | "ts" | "typescript" | "jsx" | "tsx" => {
+                Some(CodeLanguage::JavaScript)
+            }
+            "c" | "h" | "cpp" | "c++" | "cc" | "hpp" | "java" | "go" | "kotlin" | "kt"
+            | "swift" | "cs" | "csharp" => Some(CodeLanguage::CLike),
+            "sh" | "bash" | "shell" | "zsh" => Some(CodeLanguage::Shell),
+            _ => None,
+        }
+    }
+}
+
+/// Detects the language of a snippet from its fence info string or content.
+///
+/// Falls back to [`CodeLanguage::Generic`] when nothing matches.
+pub fn detect_language(code: &str) -> CodeLanguage {
+    if let Some(first) = code.lines().find(|l| !l.trim().is_empty())
+        && let Some(info) = first.trim().strip_prefix("```")
+        && let Some(language) = CodeLanguage::from_fence_info(info)
+    {
+        return language;
+    }
+
+    if code.starts_with("#!") {
+        return if code.lines().next().is_some_and(|l| l.contains("python")) {
+            CodeLanguage::Python
+        } else {
+            CodeLanguage::Shell
+        };
+    }
+
+    let has = |pattern: &str| code.contains(pattern);
+    if has("fn ") && (has("let ") || has("::") || has("-> ") || has("pub ")) {
+        CodeLanguage::Rust
+    } else if has("#include") || has("public class") || has("package ") || has("int main") {
+        CodeLanguage::CLike
+    } else if has("function ") || has("=>") || has("console.") || has("const ") {
+        CodeLanguage::JavaScript
+    } else if (has("def ") || has("elif ") || has("import ")) && !has(";") && !has("{") {
+        CodeLanguage::Python
+    } else if has("echo ") || has("then\n") || has("fi\n") {
+        CodeLanguage::Shell
+    } else {
+        CodeLanguage::Generic
+    }
+}
+
+/// Kind of a translatable span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Comment,
+    String,
+}
+
+/// A translatable span inside the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub kind: SpanKind,
+    /// Byte range of the text to replace, delimiters and surrounding whitespace excluded
+    pub range: Range<usize>,
+    /// Delimiter that must not appear in a replacement (it would end the span early)
+    terminator: &'static str,
+}
+
+/// Where a `#` may start a comment.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HashRule {
+    /// Anywhere outside strings (Python)
+    Anywhere,
+    /// At line start or after whitespace (shell)
+    AfterWhitespace,
+    /// After whitespace and followed by whitespace, so `#include` / `#[attr]` are code
+    Spaced,
+}
+
+/// Comment and string syntax of a language.
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [u8],
+    hash_rule: HashRule,
+    triple_quotes: bool,
+    rust_prefixes: bool,
+    python_prefixes: bool,
+}
+
+impl Syntax {
+    fn for_language(language: CodeLanguage) -> Self {
+        let c_like = Syntax {
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: b"\"",
+            hash_rule: HashRule::Anywhere,
+            triple_quotes: false,
+            rust_prefixes: false,
+            python_prefixes: false,
+        };
+
+        match language {
+            CodeLanguage::Rust => Syntax {
+                rust_prefixes: true,
+                ..c_like
+            },
+            CodeLanguage::CLike => c_like,
+            CodeLanguage::JavaScript => Syntax {
+                quotes: b"\"'`",
+                ..c_like
+            },
+            CodeLanguage::Python => Syntax {
+                line_comments: &["#"],
+                block_comment: None,
+                quotes: b"\"'",
+                triple_quotes: true,
+                python_prefixes: true,
+                ..c_like
+            },
+            CodeLanguage::Shell => Syntax {
+                line_comments: &["#"],
+                block_comment: None,
+                quotes: b"\"'",
+                hash_rule: HashRule::AfterWhitespace,
+                ..c_like
+            },
+            CodeLanguage::Auto | CodeLanguage::Generic => Syntax {
+                line_comments: &["//", "#"],
+                quotes: b"\"'",
+                hash_rule: HashRule::Spaced,
+                ..c_like
+            },
+        }
+    }
+
+    /// Whether a line comment marker at `index` really starts a comment.
+    fn comment_allowed_at(&self, code: &str, index: usize, marker: &str) -> bool {
+        let bytes = code.as_bytes();
+        let prev = index.checked_sub(1).map(|i| bytes[i]);
+        let next = bytes.get(index + marker.len()).copied();
+
+        if marker == "#" {
+            // Shebang lines are code
+            if index == 0 && next == Some(b'!') {
+                return false;
+            }
+            let after_space = prev.is_none_or(|b| b.is_ascii_whitespace());
+            return match self.hash_rule {
+                HashRule::Anywhere => true,
+                HashRule::AfterWhitespace => after_space,
+                HashRule::Spaced => after_space && next.is_none_or(|b| b.is_ascii_whitespace()),
+            };
+        }
+
+        // In the generic heuristic `://` is far more likely a URL than a comment
+        !(self.hash_rule == HashRule::Spaced && prev == Some(b':'))
+    }
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Whether a string literal's content reads like prose rather than an identifier.
+fn is_prose(text: &str) -> bool {
+    text.chars().any(char::is_alphabetic) && (text.contains(' ') || !text.is_ascii())
+}
+
+/// Pushes the whitespace-trimmed `range` as a segment if it is worth translating.
+fn push_trimmed(
+    code: &str,
+    range: Range<usize>,
+    kind: SpanKind,
+    terminator: &'static str,
+    segments: &mut Vec<Segment>,
+) {
+    let text = &code[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    let end = range.start + text.trim_end().len();
+    if start >= end {
+        return;
+    }
+
+    let core = &code[start..end];
+    let translatable = match kind {
+        SpanKind::Comment => core.chars().any(char::is_alphabetic),
+        SpanKind::String => is_prose(core),
+    };
+    if translatable {
+        segments.push(Segment {
+            kind,
+            range: start..end,
+            terminator,
+        });
+    }
+}
+
+/// Pushes each line of a multi-line span separately, so line prefixes such as
+/// the ` * ` of block comments and the indentation stay untouched.
+fn push_lines(
+    code: &str,
+    range: Range<usize>,
+    kind: SpanKind,
+    terminator: &'static str,
+    segments: &mut Vec<Segment>,
+) {
+    let mut line_start = range.start;
+    for line in code[range.clone()].split('\n') {
+        let line_end = line_start + line.len();
+        let prefix = match kind {
+            SpanKind::Comment => {
+                let trimmed = line.trim_start();
+                let stars = trimmed.len() - trimmed.trim_start_matches(['*', '!']).len();
+                line.len() - trimmed.len() + stars
+            }
+            SpanKind::String => 0,
+        };
+        push_trimmed(
+            code,
+            line_start + prefix..line_end,
+            kind,
+            terminator,
+            segments,
+        );
+        line_start = line_end + 1;
+    }
+}
+
+/// Finds the closing `quote` of a string starting at `start`, skipping escapes.
+fn find_string_end(code: &str, start: usize, quote: u8, multiline: bool) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let mut index = start;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\n' if !multiline => return None,
+            byte if byte == quote => return Some(index),
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+/// Index just past a Rust char literal starting at `index`, such as `'x'`,
+/// `'\''` or `'"'`; `None` for anything else, lifetimes included.
+fn char_literal_end(code: &str, index: usize) -> Option<usize> {
+    let rest = code[index..].strip_prefix('\'')?;
+    let body = if let Some(escaped) = rest.strip_prefix('\\') {
+        // The escaped character, then at most the rest of `\u{10FFFF}`
+        let first = escaped.chars().next()?;
+        let tail = &escaped[first.len_utf8()..];
+        1 + first.len_utf8() + tail.find('\'').filter(|&p| p <= 8)?
+    } else {
+        let c = rest.chars().next().filter(|c| !matches!(c, '\'' | '\n'))?;
+        c.len_utf8()
+    };
+    rest[body..]
+        .starts_with('\'')
+        .then_some(index + 1 + body + 1)
+}
+
+/// Lexes a string literal starting at `index`.
+///
+/// Returns the index just past the literal, or `None` if no string starts here.
+fn lex_string(
+    code: &str,
+    index: usize,
+    syntax: &Syntax,
+    segments: &mut Vec<Segment>,
+) -> Option<usize> {
+    let bytes = code.as_bytes();
+    let prev_is_ident = index > 0 && is_ident_byte(bytes[index - 1]);
+    let mut quote_at = index;
+    let mut untouched = false;
+
+    if syntax.rust_prefixes
+        && let Some(end) = char_literal_end(code, index)
+    {
+        // A quote in a char literal doesn't start a string
+        return Some(end);
+    }
+
+    if syntax.rust_prefixes && !prev_is_ident {
+        let mut k = index;
+        let is_bytes = bytes[k] == b'b';
+        if is_bytes {
+            k += 1;
+        }
+        if bytes.get(k) == Some(&b'r') {
+            // Raw strings are never translated
+            k += 1;
+            let hashes = bytes[k..].iter().take_while(|b| **b == b'#').count();
+            k += hashes;
+            if bytes.get(k) == Some(&b'"') {
+                let closing = format!("\"{}", "#".repeat(hashes));
+                return Some(
+                    code[k + 1..]
+                        .find(&closing)
+                        .map_or(code.len(), |p| k + 1 + p + closing.len()),
+                );
+            }
+            return None;
+        }
+        if is_bytes {
+            if bytes.get(k) != Some(&b'"') {
+                return None;
+            }
+            quote_at = k;
+            untouched = true;
+        }
+    } else if syntax.python_prefixes && !prev_is_ident {
+        let prefix_len = bytes[index..]
+            .iter()
+            .take(2)
+            .take_while(|b| b"rRbBuUfF".contains(b))
+            .count();
+        if prefix_len > 0 {
+            let prefix = &code[index..index + prefix_len];
+            // Raw, byte and format strings are left as-is
+            untouched = prefix.contains(['r', 'R', 'b', 'B', 'f', 'F']);
+            quote_at = index + prefix_len;
+        }
+    }
+
+    let quote = *bytes.get(quote_at)?;
+    if !syntax.quotes.contains(&quote) {
+        return None;
+    }
+
+    if syntax.triple_quotes {
+        let triple = if quote == b'"' { "\"\"\"" } else { "'''" };
+        if code[quote_at..].starts_with(triple) {
+            let content_start = quote_at + 3;
+            let content_end = code[content_start..]
+                .find(triple)
+                .map_or(code.len(), |p| content_start + p);
+            if !untouched && !code[content_start..content_end].contains('\\') {
+                push_lines(
+                    code,
+                    content_start..content_end,
+                    SpanKind::String,
+                    triple,
+                    segments,
+                );
+            }
+            return Some((content_end + 3).min(code.len()));
+        }
+    }
+
+    let multiline = quote == b'`' || syntax.rust_prefixes;
+    let Some(end) = find_string_end(code, quote_at + 1, quote, multiline) else {
+        // Unterminated on this line: treat the quote as a plain character
+        return Some(quote_at + 1);
+    };
+
+    let content = &code[quote_at + 1..end];
+    let has_escape_or_interpolation = content.contains('\\')
+        || content.contains('\n')
+        || (quote == b'`' && content.contains("${"));
+    if !untouched && !has_escape_or_interpolation {
+        let terminator = match quote {
+            b'"' => "\"",
+            b'\'' => "'",
+            _ => "`",
+        };
+        push_trimmed(
+            code,
+            quote_at + 1..end,
+            SpanKind::String,
+            terminator,
+            segments,
+        );
+    }
+    Some(end + 1)
+}
+
+/// Extracts the translatable comment and string spans of a snippet.
+///
+/// Spans are returned in order and never overlap. Raw strings, byte strings,
+/// strings with escape sequences or interpolation, and identifier-like
+/// strings are skipped so they round-trip untouched.
+pub fn extract_segments(code: &str, language: CodeLanguage) -> Vec<Segment> {
+    let language = match language {
+        CodeLanguage::Auto => detect_language(code),
+        other => other,
+    };
+    let syntax = Syntax::for_language(language);
+    let mut segments = Vec::new();
+    let mut index = 0;
+
+    while index < code.len() {
+        let rest = &code[index..];
+
+        if let Some((open, close)) = syntax.block_comment
+            && rest.starts_with(open)
+        {
+            let body_start = index + open.len();
+            let body_end = code[body_start..]
+                .find(close)
+                .map_or(code.len(), |p| body_start + p);
+            push_lines(
+                code,
+                body_start..body_end,
+                SpanKind::Comment,
+                close,
+                &mut segments,
+            );
+            index = (body_end + close.len()).min(code.len());
+            continue;
+        }
+
+        if let Some(marker) = syntax
+            .line_comments
+            .iter()
+            .find(|marker| rest.starts_with(**marker))
+            && syntax.comment_allowed_at(code, index, marker)
+        {
+            let mut body_start = index + marker.len();
+            let repeat = marker.as_bytes()[marker.len() - 1];
+            // Skip doc-comment markers such as `///`, `//!` and `##`
+            body_start += code.as_bytes()[body_start..]
+                .iter()
+                .take_while(|b| **b == repeat || **b == b'!')
+                .count();
+            let end = code[body_start..]
+                .find('\n')
+                .map_or(code.len(), |p| body_start + p);
+            push_trimmed(
+                code,
+                body_start..end,
+                SpanKind::Comment,
+                "\n",
+                &mut segments,
+            );
+            index = end;
+            continue;
+        }
+
+        if let Some(next) = lex_string(code, index, &syntax, &mut segments) {
+            index = next;
+            continue;
+        }
+
+        index += rest.chars().next().map_or(1, char::len_utf8);
+    }
+
+    segments
+}
+
+/// Builds the batched request body: one `⟦n⟧ text` line per segment.
+pub fn build_batch(code: &str, segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| format!("⟦{}⟧ {}", i + 1, &code[segment.range.clone()]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses `⟦n⟧ text` lines from the model's response, ignoring anything else.
+pub fn parse_batch(response: &str) -> HashMap<usize, String> {
+    let mut translations = HashMap::new();
+    for line in response.lines() {
+        let Some(rest) = line.trim().strip_prefix('⟦') else {
+            continue;
+        };
+        let Some((number, text)) = rest.split_once('⟧') else {
+            continue;
+        };
+        let Ok(number) = number.trim().parse::<usize>() else {
+            continue;
+        };
+        let text = text.trim();
+        if !text.is_empty() {
+            translations.insert(number, text.to_string());
+        }
+    }
+    translations
+}
+
+/// Splices translations (keyed by 1-based segment number) back into the code.
+///
+/// Segments without a usable translation keep their original text, so the
+/// code outside the segments is reproduced byte-for-byte.
+pub fn reassemble(
+    code: &str,
+    segments: &[Segment],
+    translations: &HashMap<usize, String>,
+) -> String {
+    let mut output = String::with_capacity(code.len());
+    let mut last = 0;
+
+    for (i, segment) in segments.iter().enumerate() {
+        output.push_str(&code[last..segment.range.start]);
+        let original = &code[segment.range.clone()];
+        let replacement = translations
+            .get(&(i + 1))
+            .map(String::as_str)
+            .filter(|text| {
+                // A replacement must not be able to end the span early
+                !text.contains('\n')
+                    && !text.contains(segment.terminator)
+                    && (segment.kind == SpanKind::Comment || !text.contains('\\'))
+            })
+            .unwrap_or(original);
+        output.push_str(replacement);
+        last = segment.range.end;
+    }
+
+    output.push_str(&code[last..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(code: &str, language: CodeLanguage) -> Vec<&str> {
+        extract_segments(code, language)
+            .into_iter()
+            .map(|s| &code[s.range])
+            .collect()
+    }
+
+    /// The code with every segment removed, i.e. what must never change.
+    fn skeleton(code: &str, language: CodeLanguage) -> String {
+        let mut out = String::new();
+        let mut last = 0;
+        for segment in extract_segments(code, language) {
+            out.push_str(&code[last..segment.range.start]);
+            out.push('\u{0}');
+            last = segment.range.end;
+        }
+        out.push_str(&code[last..]);
+        out
+    }
+
+    const SAMPLES: &[(&str, CodeLanguage)] = &[
+        (
+            "/// 计算两个数的和\nfn add(a: i32, b: i32) -> i32 {\n    // 返回结果\n    let msg = \"你好 世界\";\n    let raw = r#\"原始 \"字符串\"\"#;\n    let esc = \"换行\\n符\";\n    a + b /* 行内注释 */\n}\n",
+            CodeLanguage::Rust,
+        ),
+        (
+            "def greet(name):\n    \"\"\"向用户问好。\n\n    多行文档。\n    \"\"\"\n    # 打印问候\n    print(f\"你好 {name}\")\n    return '再见 朋友'  # 结束\n",
+            CodeLanguage::Python,
+        ),
+        (
+            "// 初始化\nconst a = `模板 ${x}`;\nconst b = '普通 字符串';\n/*\n * 多行\n * 注释\n */\n",
+            CodeLanguage::JavaScript,
+        ),
+        (
+            "#!/bin/bash\n# 安装依赖\necho \"开始 安装\" # 行尾\nURL=http://example.com/a#b\n",
+            CodeLanguage::Shell,
+        ),
+        (
+            "#include <stdio.h>\nvoid f() { // 注释 here\n  x = \"http://a.b\"; // 链接\n}\n",
+            CodeLanguage::Generic,
+        ),
+    ];
+
+    #[test]
+    fn test_rust_segments() {
+        let code = SAMPLES[0].0;
+        assert_eq!(
+            texts(code, CodeLanguage::Rust),
+            vec!["计算两个数的和", "返回结果", "你好 世界", "行内注释"]
+        );
+    }
+
+    #[test]
+    fn test_rust_char_literals_are_code() {
+        let code = "if c == '\"' { /* 引号 */ }\nlet q = '\\'';\nfn f<'a>(s: &'a str) {}\nlet s = \"你好 世界\"; // 结束\n";
+        assert_eq!(
+            texts(code, CodeLanguage::Rust),
+            vec!["引号", "你好 世界", "结束"]
+        );
+        assert_eq!(char_literal_end("'\\u{1F600}'", 0), Some(11));
+        assert_eq!(char_literal_end("'语'", 0), Some(5));
+        assert_eq!(char_literal_end("'a>", 0), None);
+    }
+
+    #[test]
+    fn test_python_segments() {
+        let code = SAMPLES[1].0;
+        assert_eq!(
+            texts(code, CodeLanguage::Python),
+            vec![
+                "向用户问好。",
+                "多行文档。",
+                "打印问候",
+                "再见 朋友",
+                "结束"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_javascript_segments_skip_interpolation() {
+        let code = SAMPLES[2].0;
+        assert_eq!(
+            texts(code, CodeLanguage::JavaScript),
+            vec!["初始化", "普通 字符串", "多行", "注释"]
+        );
+    }
+
+    #[test]
+    fn test_shell_segments_skip_shebang() {
+        let code = SAMPLES[3].0;
+        assert_eq!(
+            texts(code, CodeLanguage::Shell),
+            vec!["安装依赖", "开始 安装", "行尾"]
+        );
+    }
+
+    #[test]
+    fn test_generic_fallback_is_conservative() {
+        let code = SAMPLES[4].0;
+        assert_eq!(
+            texts(code, CodeLanguage::Generic),
+            vec!["注释 here", "链接"]
+        );
+    }
+
+    #[test]
+    fn test_identifier_strings_are_skipped() {
+        let code = "let k = \"Content-Type\";\nlet e = \"\";\n";
+        assert!(texts(code, CodeLanguage::Rust).is_empty());
+    }
+
+    #[test]
+    fn test_identity_round_trip() {
+        for (code, language) in SAMPLES {
+            let segments = extract_segments(code, *language);
+            assert_eq!(reassemble(code, &segments, &HashMap::new()), *code);
+
+            let identity = parse_batch(&build_batch(code, &segments));
+            assert_eq!(reassemble(code, &segments, &identity), *code);
+        }
+    }
+
+    #[test]
+    fn test_translated_code_keeps_structure() {
+        for (code, language) in SAMPLES {
+            let segments = extract_segments(code, *language);
+            let translations = (1..=segments.len())
+                .map(|n| (n, format!("译文 {}", n)))
+                .collect();
+            let translated = reassemble(code, &segments, &translations);
+
+            assert_ne!(translated, *code);
+            assert_eq!(skeleton(&translated, *language), skeleton(code, *language));
+        }
+    }
+
+    #[test]
+    fn test_unsafe_replacements_are_rejected() {
+        let code = "let s = \"你好 世界\"; // 注释\n";
+        let segments = extract_segments(code, CodeLanguage::Rust);
+        let translations = HashMap::from([
+            (1, "say \"hi\"".to_string()),
+            (2, "line\nbreak".to_string()),
+        ]);
+        assert_eq!(reassemble(code, &segments, &translations), code);
+    }
+
+    #[test]
+    fn test_parse_batch_tolerates_noise() {
+        let response = "```\n⟦1⟧ Hello\nSure, here you go:\n ⟦2⟧  World \n⟦x⟧ bad\n⟦3⟧\n```";
+        let parsed = parse_batch(response);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[&1], "Hello");
+        assert_eq!(parsed[&2], "World");
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language("```rust\nfn main() {}\n```"),
+            CodeLanguage::Rust
+        );
+        assert_eq!(detect_language("```py\nx = 1\n```"), CodeLanguage::Python);
+        assert_eq!(
+            detect_language("fn main() {\n    let x = 1;\n}"),
+            CodeLanguage::Rust
+        );
+        assert_eq!(
+            detect_language("def f(x):\n    return x\n"),
+            CodeLanguage::Python
+        );
+        assert_eq!(
+            detect_language("#include <stdio.h>\nint main() {}"),
+            CodeLanguage::CLike
+        );
+        assert_eq!(detect_language("#!/bin/sh\necho hi\n"), CodeLanguage::Shell);
+        assert_eq!(detect_language("just some text"), CodeLanguage::Generic);
+    }
+}
@@ -3,17 +3,153 @@
 //! This module handles loading, saving, and managing application configuration
 //! including API keys, language preferences, and UI settings.
 
-use egui::Id;
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
+use crate::api::client::{self, ThinkingMode};
+use crate::api::prompt::PromptContext;
+use crate::api::transport;
+use crate::error::Result;
+use crate::lock_mutex;
+use crate::services::audio::PlaybackVolume;
+use crate::services::tts::TtsConfig;
+use crate::utils::cache_rules::CacheRule;
+use crate::utils::dialogs::RecentDirs;
+use crate::utils::layout::Arrangement;
+use crate::utils::logger::LogDetail;
+use crate::utils::paths::AppPaths;
+use crate::utils::pricing::Pricing;
+use crate::utils::repetition;
+use crate::utils::retention::RetentionPolicy;
+use crate::utils::script::{self, Script};
+use crate::utils::typography::QuoteStyle;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
 use text2audio::Voice;
 
+/// How the central source text panel is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SourcePanelLayout {
+    /// Read-only copy of the translated source text
+    #[default]
+    Mirror,
+    /// Editable, kept in sync with the sidebar's source text
+    Editable,
+    /// Not shown, the translation takes the full height
+    Hidden,
+}
+
+impl SourcePanelLayout {
+    /// All layouts, in display order.
+    pub const ALL: [SourcePanelLayout; 3] = [
+        SourcePanelLayout::Mirror,
+        SourcePanelLayout::Editable,
+        SourcePanelLayout::Hidden,
+    ];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            SourcePanelLayout::Mirror => "Mirror (read-only)",
+            SourcePanelLayout::Editable => "Editable here",
+            SourcePanelLayout::Hidden => "Hidden",
+        }
+    }
+}
+
+/// How well the user knows a target language, shown as a colored tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Proficiency {
+    Native,
+    Fluent,
+    #[default]
+    Learning,
+}
+
+impl Proficiency {
+    /// All levels, in display order.
+    pub const ALL: [Proficiency; 3] = [
+        Proficiency::Native,
+        Proficiency::Fluent,
+        Proficiency::Learning,
+    ];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            Proficiency::Native => "Native",
+            Proficiency::Fluent => "Fluent",
+            Proficiency::Learning => "Learning",
+        }
+    }
+}
+
+/// Preferences applied whenever a target language is selected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageProfile {
+    #[serde(default)]
+    pub proficiency: Proficiency,
+    /// Domain hint used when the sidebar leaves it empty
+    #[serde(default)]
+    pub prompt_domain: String,
+    /// Audience hint used when the sidebar leaves it empty
+    #[serde(default)]
+    pub prompt_audience: String,
+    /// Show the pronunciation comparison under the translation
+    #[serde(default = "default_show_pronunciation")]
+    pub show_pronunciation: bool,
+    #[serde(default = "default_voice")]
+    pub tts_voice: String,
+    #[serde(default = "default_speed")]
+    pub tts_speed: f32,
+    /// Speak each finished translation
+    #[serde(default)]
+    pub auto_speak: bool,
+    /// Ask which parts of each finished translation the model is unsure of
+    #[serde(default)]
+    pub check_confidence: bool,
+}
+
+impl LanguageProfile {
+    /// A new profile speaking with the given voice and speed and no
+    /// prompt hints of its own.
+    pub fn new(tts_voice: &str, tts_speed: f32) -> Self {
+        LanguageProfile {
+            proficiency: Proficiency::default(),
+            prompt_domain: String::new(),
+            prompt_audience: String::new(),
+            show_pronunciation: default_show_pronunciation(),
+            tts_voice: tts_voice.to_string(),
+            tts_speed,
+            auto_speak: false,
+            check_confidence: false,
+        }
+    }
+}
+
+/// Position and size of a secondary window, in points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
 /// Application configuration structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     /// Z.AI API key for authentication
     pub api_key: String,
+    /// Base URL of the OpenAI-compatible API, empty for the Z.AI endpoint
+    #[serde(default)]
+    pub api_base_url: String,
+    /// Model of translation requests, empty for the default model
+    #[serde(default = "default_model")]
+    pub model: String,
     /// Target language for translation
     pub target_language: String,
     /// UI font size in pixels
@@ -29,15 +165,234 @@ pub struct AppConfig {
     /// TTS volume level
     #[serde(default = "default_volume")]
     pub tts_volume: f32,
+    /// Listening volume applied at playback time, 0.0 to 1.0
+    #[serde(default = "default_playback_volume")]
+    pub playback_volume: f32,
+    /// Mute read-aloud playback
+    #[serde(default)]
+    pub playback_muted: bool,
+    /// Speak translations that are read aloud automatically sentence by
+    /// sentence while they stream, instead of once they are complete
+    #[serde(default)]
+    pub speak_while_translating: bool,
     /// Enable keyword analysis during translation
     #[serde(default = "default_keyword_analysis")]
     pub enable_keyword_analysis: bool,
+    /// Offer up to three alternatives for short inputs
+    #[serde(default)]
+    pub show_alternatives: bool,
+    /// Retry once with a translation-only framing when the provider declines
+    #[serde(default)]
+    pub retry_refusals: bool,
+    /// Translate through `pivot_language` instead of directly
+    #[serde(default)]
+    pub pivot_enabled: bool,
+    /// Language translations go through when pivoting
+    #[serde(default = "default_pivot_language")]
+    pub pivot_language: String,
+    /// Open a connection to the provider at startup, so the first
+    /// translation doesn't wait for DNS and the TLS handshake
+    #[serde(default)]
+    pub preconnect_on_startup: bool,
+    /// Seconds given to opening a connection to the provider
+    #[serde(
+        default = "default_connect_timeout",
+        deserialize_with = "connect_timeout_in_range"
+    )]
+    pub connect_timeout_secs: u64,
+    /// Seconds waited for the provider to start responding, or to send
+    /// more of a response, before the translation fails
+    #[serde(
+        default = "default_request_timeout",
+        deserialize_with = "request_timeout_in_range"
+    )]
+    pub request_timeout_secs: u64,
+    /// Times a request is sent, the first one included, when the provider
+    /// is busy, fails or can't be reached before it starts to respond
+    #[serde(default = "default_request_attempts")]
+    pub request_attempts: u32,
+    /// Decode escape sequences such as a literal `\n` that the provider
+    /// leaves in the content of its responses
+    #[serde(default)]
+    pub unescape_content: bool,
+    /// Show Chinese and Japanese source text romanized under it
+    #[serde(default)]
+    pub romanize_source: bool,
+    /// Offer looking up the latest release in the About window
+    #[serde(default)]
+    pub check_for_updates: bool,
+    /// Apply changes made to the configuration file while the app runs
+    #[serde(default)]
+    pub watch_config_file: bool,
+    /// Underline misspelled words in the source text
+    #[serde(default = "default_spellcheck_enabled")]
+    pub spellcheck_enabled: bool,
+    /// Hunspell dictionary used for spell checking, e.g. `en_US`
+    #[serde(default = "default_spellcheck_language")]
+    pub spellcheck_language: String,
     /// Enable thinking mode in AI model
     #[serde(default = "default_think_enable")]
     pub think_enable: bool,
     /// Enable coding plan mode in TTS
     #[serde(default = "default_coding_plan")]
     pub coding_plan: bool,
+    /// Thinking field for translation requests, `None` uses the provider default
+    #[serde(default)]
+    pub chat_thinking: Option<ThinkingMode>,
+    /// Output token limit for translations, `None` uses the provider default
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Consecutive repeats of the same text after which a response is
+    /// stopped, `None` to never stop one
+    #[serde(default = "default_repetition_limit")]
+    pub repetition_limit: Option<usize>,
+    /// Layout of the central source text panel
+    #[serde(default)]
+    pub source_panel_layout: SourcePanelLayout,
+    /// Arrangement of the window the user pinned, `None` to pick it by the
+    /// window's width
+    #[serde(default)]
+    pub pinned_layout: Option<Arrangement>,
+    /// Overall TTS conversion timeout in seconds
+    #[serde(default = "default_tts_timeout")]
+    pub tts_timeout_secs: u64,
+    /// Per-segment TTS conversion timeout in seconds
+    #[serde(default = "default_tts_segment_timeout")]
+    pub tts_segment_timeout_secs: u64,
+    /// Recently used target languages, most recent first
+    #[serde(default)]
+    pub recent_languages: Vec<String>,
+    /// Folder each kind of file dialog was last used in
+    #[serde(default)]
+    pub recent_dirs: RecentDirs,
+    /// How many recent target languages to remember
+    #[serde(default = "default_recent_language_limit")]
+    pub recent_language_limit: usize,
+    /// Stream throughput (chars/s) below which a slow-stream warning is shown
+    #[serde(default = "default_slow_stream_floor")]
+    pub slow_stream_floor_cps: f64,
+    /// How long the throughput must stay below the floor before warning, in seconds
+    #[serde(default = "default_slow_stream_grace")]
+    pub slow_stream_grace_secs: u64,
+    /// Domain hint injected into the prompt, empty for none
+    #[serde(default)]
+    pub prompt_domain: String,
+    /// Audience hint injected into the prompt, empty for none
+    #[serde(default)]
+    pub prompt_audience: String,
+    /// Maximum number of translations queued while offline
+    #[serde(default = "default_offline_queue_limit")]
+    pub offline_queue_limit: usize,
+    /// Last geometry of the popped-out translation window
+    #[serde(default)]
+    pub popout_window: Option<WindowGeometry>,
+    /// Size the source text for its dominant script
+    #[serde(default)]
+    pub auto_font_source: bool,
+    /// Size the translation for its dominant script
+    #[serde(default)]
+    pub auto_font_translation: bool,
+    /// Font size multipliers per script for automatic sizing
+    #[serde(default = "script::default_font_scales")]
+    pub script_font_scales: BTreeMap<Script, f32>,
+    /// Whether the sidebar is collapsed to an icon rail
+    #[serde(default)]
+    pub sidebar_collapsed: bool,
+    /// Whether the model's reasoning is collapsed under its header
+    #[serde(default = "default_reasoning_collapsed")]
+    pub reasoning_collapsed: bool,
+    /// Drop the model's reasoning as it arrives instead of showing and
+    /// logging it
+    #[serde(default)]
+    pub discard_reasoning: bool,
+    /// Width of the expanded sidebar in points
+    #[serde(default = "default_sidebar_width")]
+    pub sidebar_width: f32,
+    /// Collapse the sidebar automatically in narrow windows
+    #[serde(default = "default_sidebar_auto_collapse")]
+    pub sidebar_auto_collapse: bool,
+    /// Strip invisible characters from text pasted into the source box
+    #[serde(default = "default_sanitize_source_text")]
+    pub sanitize_source_text: bool,
+    /// Hide finished translations until they are revealed, for listening practice
+    #[serde(default = "default_practice_mode")]
+    pub practice_mode: bool,
+    /// Extra font loaded at startup for scripts the bundled fonts lack
+    #[serde(default)]
+    pub custom_font_path: Option<PathBuf>,
+    /// Preferences per target language, languages without one use the
+    /// global settings
+    #[serde(default)]
+    pub language_profiles: BTreeMap<String, LanguageProfile>,
+    /// Days after which diagnostic bundles and log archives are deleted,
+    /// `None` to keep them
+    #[serde(default = "default_retention_days")]
+    pub retention_days: Option<u32>,
+    /// Size in MB each of the app's folders is kept under, `None` for no limit
+    #[serde(default = "default_storage_budget_mb")]
+    pub storage_budget_mb: Option<u32>,
+    /// Size in MB above which the translation log is archived at startup,
+    /// `None` to never archive it
+    #[serde(default = "default_log_rotate_mb")]
+    pub log_rotate_mb: Option<u32>,
+    /// How much of a translation the translation log keeps
+    #[serde(default)]
+    pub log_detail: LogDetail,
+    /// Characters per second a streaming translation is revealed at,
+    /// `None` to show chunks as they arrive
+    #[serde(default)]
+    pub smooth_typing: Option<u32>,
+    /// Share cached translations between models instead of keeping them
+    /// per provider profile
+    #[serde(default)]
+    pub shared_cache: bool,
+    /// Replace sensitive values in the source text with placeholders before
+    /// sending it, restoring them in the translation
+    #[serde(default)]
+    pub redact_sensitive: bool,
+    /// Regular expressions of further values to redact
+    #[serde(default)]
+    pub redaction_patterns: Vec<String>,
+    /// Show what will be redacted and ask before sending
+    #[serde(default = "default_redaction_preview")]
+    pub redaction_preview: bool,
+    /// Rules for translations that are never cached nor logged
+    #[serde(default)]
+    pub cache_exclusions: Vec<CacheRule>,
+    /// Run `completion_hook` after each successful translation
+    #[serde(default)]
+    pub completion_hook_enabled: bool,
+    /// Command run after each successful translation, with the
+    /// `{source_file}`, `{translation_file}` and `{target_lang}` placeholders
+    #[serde(default)]
+    pub completion_hook: String,
+    /// Seconds the completion hook may run before it is stopped
+    #[serde(default = "default_completion_hook_timeout")]
+    pub completion_hook_timeout_secs: u64,
+    /// Longest source text, in characters, sent on the fast path without
+    /// thinking or streaming, `None` to always take the regular path
+    #[serde(default = "default_fast_path_chars")]
+    pub fast_path_chars: Option<u32>,
+    /// Model for fast path requests, empty for the regular model
+    #[serde(default)]
+    pub fast_path_model: String,
+    /// Rewrite the punctuation of finished translations by the conventions
+    /// of the target language
+    #[serde(default)]
+    pub normalize_typography: bool,
+    /// Quote marks used when normalizing typography
+    #[serde(default)]
+    pub quote_style: QuoteStyle,
+    /// Prices per model, models without one get no cost estimate
+    #[serde(default)]
+    pub model_pricing: BTreeMap<String, Pricing>,
+    /// Output tokens expected per input token for language pairs no
+    /// translation was learned from yet
+    #[serde(default = "default_output_ratio")]
+    pub output_ratio: f64,
+    /// When these settings were last saved, in milliseconds since the epoch
+    #[serde(default)]
+    pub saved_at: Option<i64>,
 }
 
 /// Default think_enable setting
@@ -55,6 +410,25 @@ fn default_keyword_analysis() -> bool {
     false
 }
 
+/// Default spell check setting
+fn default_spellcheck_enabled() -> bool {
+    true
+}
+
+/// Default spell check dictionary
+fn default_spellcheck_language() -> String {
+    "en_US".to_string()
+}
+
+/// Default show_pronunciation setting of a language profile
+fn default_show_pronunciation() -> bool {
+    true
+}
+
+fn default_pivot_language() -> String {
+    "English".to_string()
+}
+
 /// Default voice name for TTS
 fn default_voice() -> String {
     "Tongtong".to_string()
@@ -70,39 +444,456 @@ fn default_volume() -> f32 {
     1.0
 }
 
+/// Default listening volume
+fn default_playback_volume() -> f32 {
+    1.0
+}
+
+/// Default overall TTS timeout
+fn default_tts_timeout() -> u64 {
+    120
+}
+
+/// Default per-segment TTS timeout
+fn default_tts_segment_timeout() -> u64 {
+    30
+}
+
+/// Default number of recent target languages
+fn default_recent_language_limit() -> usize {
+    3
+}
+
+/// Default offline queue capacity
+fn default_offline_queue_limit() -> usize {
+    20
+}
+
+/// Default expanded sidebar width
+fn default_sidebar_width() -> f32 {
+    300.0
+}
+
+/// Default sidebar_auto_collapse setting
+fn default_sidebar_auto_collapse() -> bool {
+    true
+}
+
+/// Default sanitize_source_text setting
+fn default_sanitize_source_text() -> bool {
+    true
+}
+
+/// Default reasoning_collapsed setting
+fn default_reasoning_collapsed() -> bool {
+    true
+}
+
+/// Default practice_mode setting
+fn default_practice_mode() -> bool {
+    false
+}
+
+/// Default fast_path_chars setting
+fn default_fast_path_chars() -> Option<u32> {
+    Some(200)
+}
+
+/// Default model setting
+fn default_model() -> String {
+    client::DEFAULT_MODEL.to_string()
+}
+
+/// Default connect_timeout_secs
+fn default_connect_timeout() -> u64 {
+    transport::DEFAULT_CONNECT_TIMEOUT.as_secs()
+}
+
+/// Default request_timeout_secs
+fn default_request_timeout() -> u64 {
+    client::DEFAULT_REQUEST_TIMEOUT.as_secs()
+}
+
+/// Seconds `connect_timeout_secs` may be set to
+pub const CONNECT_TIMEOUT_RANGE: RangeInclusive<u64> = 1..=120;
+
+/// Seconds `request_timeout_secs` may be set to
+pub const REQUEST_TIMEOUT_RANGE: RangeInclusive<u64> = 5..=600;
+
+/// Reads connect_timeout_secs, brought into range if the file was edited
+fn connect_timeout_in_range<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<u64, D::Error> {
+    let secs = u64::deserialize(deserializer)?;
+    Ok(secs.clamp(*CONNECT_TIMEOUT_RANGE.start(), *CONNECT_TIMEOUT_RANGE.end()))
+}
+
+/// Reads request_timeout_secs, brought into range if the file was edited
+fn request_timeout_in_range<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<u64, D::Error> {
+    let secs = u64::deserialize(deserializer)?;
+    Ok(secs.clamp(*REQUEST_TIMEOUT_RANGE.start(), *REQUEST_TIMEOUT_RANGE.end()))
+}
+
+/// Default request_attempts
+fn default_request_attempts() -> u32 {
+    client::DEFAULT_REQUEST_ATTEMPTS
+}
+
+/// Default output_ratio, allowing for some reasoning
+fn default_output_ratio() -> f64 {
+    1.5
+}
+
+/// Default completion hook timeout
+fn default_completion_hook_timeout() -> u64 {
+    30
+}
+
+/// Default redaction_preview setting
+fn default_redaction_preview() -> bool {
+    true
+}
+
+/// Default repetition_limit setting
+fn default_repetition_limit() -> Option<usize> {
+    Some(repetition::DEFAULT_MAX_REPEATS)
+}
+
+/// Default retention_days setting
+fn default_retention_days() -> Option<u32> {
+    Some(30)
+}
+
+/// Default storage_budget_mb setting
+fn default_storage_budget_mb() -> Option<u32> {
+    Some(200)
+}
+
+/// Default log_rotate_mb setting
+fn default_log_rotate_mb() -> Option<u32> {
+    Some(10)
+}
+
+/// Default slow-stream throughput floor
+fn default_slow_stream_floor() -> f64 {
+    5.0
+}
+
+/// Default slow-stream grace period
+fn default_slow_stream_grace() -> u64 {
+    15
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         AppConfig {
             api_key: String::new(),
+            api_base_url: String::new(),
+            model: default_model(),
             target_language: "English".to_string(),
             font_size: 16.0,
             dark_theme: true,
             tts_voice: default_voice(),
             tts_speed: default_speed(),
             tts_volume: default_volume(),
+            playback_volume: default_playback_volume(),
+            playback_muted: false,
+            speak_while_translating: false,
             enable_keyword_analysis: default_keyword_analysis(),
+            show_alternatives: false,
+            retry_refusals: false,
+            pivot_enabled: false,
+            pivot_language: default_pivot_language(),
+            preconnect_on_startup: false,
+            connect_timeout_secs: default_connect_timeout(),
+            request_timeout_secs: default_request_timeout(),
+            request_attempts: default_request_attempts(),
+            check_for_updates: false,
+            watch_config_file: false,
+            spellcheck_enabled: default_spellcheck_enabled(),
+            spellcheck_language: default_spellcheck_language(),
             think_enable: default_think_enable(),
             coding_plan: default_coding_plan(),
+            chat_thinking: None,
+            max_tokens: None,
+            repetition_limit: default_repetition_limit(),
+            source_panel_layout: SourcePanelLayout::default(),
+            pinned_layout: None,
+            tts_timeout_secs: default_tts_timeout(),
+            tts_segment_timeout_secs: default_tts_segment_timeout(),
+            recent_languages: Vec::new(),
+            recent_dirs: RecentDirs::default(),
+            recent_language_limit: default_recent_language_limit(),
+            slow_stream_floor_cps: default_slow_stream_floor(),
+            slow_stream_grace_secs: default_slow_stream_grace(),
+            prompt_domain: String::new(),
+            prompt_audience: String::new(),
+            offline_queue_limit: default_offline_queue_limit(),
+            popout_window: None,
+            auto_font_source: false,
+            auto_font_translation: false,
+            script_font_scales: script::default_font_scales(),
+            sidebar_collapsed: false,
+            reasoning_collapsed: default_reasoning_collapsed(),
+            discard_reasoning: false,
+            sidebar_width: default_sidebar_width(),
+            sidebar_auto_collapse: default_sidebar_auto_collapse(),
+            sanitize_source_text: default_sanitize_source_text(),
+            practice_mode: default_practice_mode(),
+            custom_font_path: None,
+            language_profiles: BTreeMap::new(),
+            retention_days: default_retention_days(),
+            storage_budget_mb: default_storage_budget_mb(),
+            log_rotate_mb: default_log_rotate_mb(),
+            log_detail: LogDetail::default(),
+            smooth_typing: None,
+            shared_cache: false,
+            redact_sensitive: false,
+            redaction_patterns: Vec::new(),
+            redaction_preview: default_redaction_preview(),
+            cache_exclusions: Vec::new(),
+            completion_hook_enabled: false,
+            completion_hook: String::new(),
+            completion_hook_timeout_secs: default_completion_hook_timeout(),
+            fast_path_chars: default_fast_path_chars(),
+            fast_path_model: String::new(),
+            normalize_typography: false,
+            quote_style: QuoteStyle::default(),
+            model_pricing: BTreeMap::new(),
+            output_ratio: default_output_ratio(),
+            unescape_content: false,
+            romanize_source: false,
+            saved_at: None,
         }
     }
 }
 
+/// Saves this close together count as the same save of both copies.
+const SYNC_TOLERANCE_MS: i64 = 2000;
+
+/// Whether the configuration file has a backup, looked up once and refreshed
+/// whenever the file is written, so the settings panel need not stat it every frame.
+static BACKUP_EXISTS: Mutex<Option<bool>> = Mutex::new(None);
+
+/// Which copy of the configuration the app starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Neither copy exists
+    Default,
+    /// The eframe storage snapshot
+    Storage,
+    /// The configuration file
+    File,
+}
+
+/// Outcome of comparing the storage snapshot with the configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncDecision {
+    pub source: ConfigSource,
+    /// Whether the other copy is outdated and should be rewritten
+    pub resync: bool,
+}
+
+/// Decides which copy of the configuration is newer.
+///
+/// `storage_saved_at` is the `saved_at` stamp of the storage snapshot (`0`
+/// for snapshots from versions without it) and `file_modified` the
+/// modification time of the file, both in milliseconds since the epoch and
+/// `None` for a missing copy. A file edited after the snapshot was taken
+/// wins, so changes made by hand are never discarded.
+pub fn decide_sync(storage_saved_at: Option<i64>, file_modified: Option<i64>) -> SyncDecision {
+    let (source, resync) = match (storage_saved_at, file_modified) {
+        (None, None) => (ConfigSource::Default, false),
+        (Some(_), None) => (ConfigSource::Storage, true),
+        (None, Some(_)) => (ConfigSource::File, true),
+        (Some(saved), Some(modified)) if modified > saved + SYNC_TOLERANCE_MS => {
+            (ConfigSource::File, true)
+        }
+        (Some(saved), Some(modified)) => {
+            (ConfigSource::Storage, modified < saved - SYNC_TOLERANCE_MS)
+        }
+    };
+    SyncDecision { source, resync }
+}
+
+/// Modification time of `path` in milliseconds since the epoch.
+fn modified_millis(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    i64::try_from(since_epoch.as_millis()).ok()
+}
+
 impl AppConfig {
     /// Returns the path to the configuration file.
     pub fn config_path() -> PathBuf {
-        PathBuf::from(".ai-translate-config.json")
+        AppPaths::get().config_file.clone()
     }
 
-    /// Loads configuration from file, or returns default if file doesn't exist.
-    pub fn load() -> Self {
-        let path = Self::config_path();
-        if path.exists()
-            && let Ok(content) = fs::read_to_string(&path)
-            && let Ok(config) = serde_json::from_str::<AppConfig>(&content)
-        {
-            return config;
+    /// Returns where the settings file of an older version is kept once it
+    /// was imported.
+    fn imported_path(legacy_path: &Path) -> PathBuf {
+        let mut name = legacy_path.as_os_str().to_owned();
+        name.push(".imported");
+        PathBuf::from(name)
+    }
+
+    /// Returns the backup of the previous version of the configuration file.
+    fn backup_path(path: &Path) -> PathBuf {
+        path.with_extension("json.bak")
+    }
+
+    /// Loads the newer of the eframe storage snapshot and the configuration file.
+    ///
+    /// # Returns
+    ///
+    /// The configuration and whether the two copies need to be synced again
+    pub fn load_synced(storage: Option<&dyn eframe::Storage>) -> (Self, bool) {
+        let stored = storage.and_then(Self::read_storage);
+        Self::resolve(stored, AppPaths::get())
+    }
+
+    /// Loads from the configuration file, or while there is none from the
+    /// settings file of an older version, until they are imported.
+    fn resolve(stored: Option<Self>, paths: &AppPaths) -> (Self, bool) {
+        let path = match &paths.legacy_config_file {
+            Some(legacy_path) if !paths.config_file.exists() => legacy_path,
+            _ => &paths.config_file,
+        };
+        let decision = decide_sync(
+            stored.as_ref().map(|config| config.saved_at.unwrap_or(0)),
+            modified_millis(path),
+        );
+        tracing::info!(
+            source = ?decision.source,
+            resync = decision.resync,
+            path = %path.display(),
+            "Resolved configuration"
+        );
+
+        let config = match decision.source {
+            ConfigSource::File => Self::read(path).or(stored),
+            ConfigSource::Storage => stored,
+            ConfigSource::Default => None,
+        };
+        (config.unwrap_or_default(), decision.resync)
+    }
+
+    /// Reads the configuration file, failing if it is missing or invalid.
+    pub fn load_file() -> Result<Self> {
+        let content = fs::read_to_string(Self::config_path())?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Whether `other` holds the same settings, ignoring when they were saved.
+    pub fn same_settings(&self, other: &Self) -> bool {
+        let stamped = AppConfig {
+            saved_at: other.saved_at,
+            ..self.clone()
+        };
+        serde_json::to_value(&stamped).ok() == serde_json::to_value(other).ok()
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content)
+            .inspect_err(|e| tracing::warn!("Ignoring invalid config {}: {}", path.display(), e))
+            .ok()
+    }
+
+    /// Imports the settings file of an older version into the configuration
+    /// file, replacing it.
+    ///
+    /// The imported file is renamed to `.imported`, so it is offered once.
+    pub fn import_legacy() -> Result<Self> {
+        let paths = AppPaths::get();
+        match &paths.legacy_config_file {
+            Some(legacy_path) => {
+                let imported = Self::import_from(legacy_path, &paths.config_file);
+                Self::refresh_backup_state();
+                imported
+            }
+            None => Err(std::io::Error::from(std::io::ErrorKind::NotFound).into()),
         }
-        AppConfig::default()
+    }
+
+    fn import_from(legacy_path: &Path, path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(legacy_path)?;
+        let config: AppConfig = serde_json::from_str(&content)?;
+        config.save_to(path)?;
+        fs::rename(legacy_path, Self::imported_path(legacy_path))?;
+        tracing::info!(
+            "Imported config from {} to {}",
+            legacy_path.display(),
+            path.display()
+        );
+        Ok(config)
+    }
+
+    /// Saves the configuration to the configuration file.
+    pub fn save(&self) -> Result<()> {
+        let saved = self.save_to(&Self::config_path());
+        Self::refresh_backup_state();
+        saved
+    }
+
+    /// Writes the configuration atomically, keeping the previous version as a backup.
+    ///
+    /// The new content is written to a temporary file which then replaces the
+    /// configuration file, so a failed write never truncates it. Unchanged
+    /// content is not rewritten, so the backup keeps the last different version.
+    fn save_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        if fs::read_to_string(path).is_ok_and(|current| current == json) {
+            return Ok(());
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        let written = File::create(&temp_path).and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+
+        if path.exists() {
+            fs::copy(path, Self::backup_path(path))?;
+        }
+        fs::rename(&temp_path, path)?;
+        tracing::debug!("Saved config to {}", path.display());
+        Ok(())
+    }
+
+    /// Whether a previous version of the configuration file is available.
+    pub fn has_backup() -> bool {
+        *lock_mutex!(BACKUP_EXISTS)
+            .get_or_insert_with(|| Self::backup_path(&Self::config_path()).exists())
+    }
+
+    fn refresh_backup_state() {
+        *lock_mutex!(BACKUP_EXISTS) = Some(Self::backup_path(&Self::config_path()).exists());
+    }
+
+    /// Restores the previous version of the configuration file.
+    ///
+    /// The replaced version becomes the new backup, so a restore can be undone.
+    pub fn restore_backup() -> Result<Self> {
+        let restored = Self::restore_from(&Self::config_path());
+        Self::refresh_backup_state();
+        restored
+    }
+
+    fn restore_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(Self::backup_path(path))?;
+        let config: AppConfig = serde_json::from_str(&content)?;
+        config.save_to(path)?;
+        Ok(config)
     }
 
     /// Returns a list of supported target languages.
@@ -118,6 +909,8 @@ impl AppConfig {
             "Português",
             "Русский",
             "Italiano",
+            "العربية",
+            "עברית",
         ]
     }
 
@@ -142,41 +935,119 @@ impl AppConfig {
         }
     }
 
-    /// Returns the egui memory ID for this configuration.
-    pub fn config_id() -> Id {
-        Id::new("app_config")
+    /// Moves `language` to the front of the recent target languages.
+    pub fn record_recent_language(&mut self, language: &str) {
+        self.recent_languages.retain(|l| l != language);
+        self.recent_languages.insert(0, language.to_string());
+        self.recent_languages.truncate(self.recent_language_limit);
     }
 
-    /// Saves the configuration to egui's memory.
-    pub fn save_to_memory(&self, ctx: &egui::Context) {
-        ctx.memory_mut(|mem| {
-            mem.data.insert_persisted(Self::config_id(), self.clone());
-        });
+    /// Base URL requests are sent to, the default endpoint if none or an
+    /// invalid one is set.
+    pub fn base_url(&self) -> String {
+        client::normalize_base_url(&self.api_base_url).unwrap_or_else(|e| {
+            tracing::warn!("Using the default API base URL: {}", e);
+            client::DEFAULT_BASE_URL.to_string()
+        })
     }
 
-    /// Loads the configuration from egui's memory.
-    pub fn load_from_memory(ctx: &egui::Context) -> Option<Self> {
-        ctx.memory_mut(|mem| mem.data.get_persisted::<Self>(Self::config_id()))
+    /// Model of regular requests, the default one if none is set.
+    pub fn chat_model(&self) -> &str {
+        match self.model.trim() {
+            "" => client::DEFAULT_MODEL,
+            model => model,
+        }
     }
 
-    /// Loads the configuration from memory or file, or returns default.
-    pub fn load_or_default(ctx: &egui::Context) -> Self {
-        Self::load_from_memory(ctx).unwrap_or_else(|| {
-            let config = Self::load();
-            config.save_to_memory(ctx);
-            config
-        })
+    /// The profile of `language`, if the user made one.
+    pub fn language_profile(&self, language: &str) -> Option<&LanguageProfile> {
+        self.language_profiles.get(language)
     }
 
-    /// Loads the configuration from eframe storage.
-    pub fn from_storage(storage: &dyn eframe::Storage) -> Self {
-        if let Some(json) = storage.get_string("app_config") {
-            serde_json::from_str(&json).unwrap_or_default()
-        } else {
-            Self::default()
+    /// When the app deletes its diagnostic bundles and log archives.
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_days: self.retention_days,
+            max_bytes: self.storage_budget_mb.map(|mb| u64::from(mb) * 1024 * 1024),
         }
     }
 
+    /// Proficiency tags of the languages with a profile.
+    pub fn proficiencies(&self) -> BTreeMap<String, Proficiency> {
+        self.language_profiles
+            .iter()
+            .map(|(language, profile)| (language.clone(), profile.proficiency))
+            .collect()
+    }
+
+    /// Fills the hints the sidebar leaves empty from the profile of
+    /// `language`; hints given for the request win.
+    pub fn prompt_context_for(&self, language: &str, mut context: PromptContext) -> PromptContext {
+        if let Some(profile) = self.language_profile(language) {
+            if context.domain.trim().is_empty() {
+                context.domain = profile.prompt_domain.clone();
+            }
+            if context.audience.trim().is_empty() {
+                context.audience = profile.prompt_audience.clone();
+            }
+        }
+        context
+    }
+
+    /// Whether the pronunciation comparison is shown for `language`.
+    pub fn show_pronunciation_for(&self, language: &str) -> bool {
+        self.language_profile(language)
+            .map_or(default_show_pronunciation(), |p| p.show_pronunciation)
+    }
+
+    /// Whether finished translations into `language` are spoken.
+    pub fn auto_speak_for(&self, language: &str) -> bool {
+        self.language_profile(language)
+            .is_some_and(|p| p.auto_speak)
+    }
+
+    /// Whether finished translations into `language` get a confidence check.
+    pub fn check_confidence_for(&self, language: &str) -> bool {
+        self.language_profile(language)
+            .is_some_and(|p| p.check_confidence)
+    }
+
+    /// Builds the TTS service configuration from these settings, with the
+    /// voice and speed of the current target language's profile.
+    pub fn tts_config(&self) -> TtsConfig {
+        let (voice, speed) = match self.language_profile(&self.target_language) {
+            Some(profile) => (profile.tts_voice.as_str(), profile.tts_speed),
+            None => (self.tts_voice.as_str(), self.tts_speed),
+        };
+        TtsConfig::new(
+            Self::parse_voice(voice),
+            speed,
+            self.tts_volume,
+            self.coding_plan,
+            self.think_enable,
+        )
+        .with_timeouts(
+            Duration::from_secs(self.tts_timeout_secs),
+            Duration::from_secs(self.tts_segment_timeout_secs),
+        )
+    }
+
+    /// The listening level for the audio player.
+    pub fn playback_volume(&self) -> PlaybackVolume {
+        PlaybackVolume {
+            level: self.playback_volume,
+            muted: self.playback_muted,
+        }
+    }
+
+    /// Reads the configuration snapshot from eframe storage, if there is one.
+    fn read_storage(storage: &dyn eframe::Storage) -> Option<Self> {
+        let json = storage.get_string("app_config")?;
+        serde_json::from_str(&json)
+            .inspect_err(|e| tracing::warn!("Ignoring invalid stored config: {}", e))
+            .ok()
+    }
+
     /// Saves the configuration to eframe storage.
     pub fn save_to_storage(&self, storage: &mut dyn eframe::Storage) {
         if let Ok(json) = serde_json::to_string(self) {
@@ -188,6 +1059,7 @@ impl AppConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::paths;
 
     #[test]
     fn test_default_config() {
@@ -197,6 +1069,13 @@ mod tests {
         assert_eq!(config.font_size, 16.0);
         assert!(config.dark_theme);
         assert!(!config.enable_keyword_analysis);
+        assert_eq!(config.base_url(), client::DEFAULT_BASE_URL);
+        assert_eq!(config.chat_model(), client::DEFAULT_MODEL);
+        let unset = AppConfig {
+            model: " ".to_string(),
+            ..AppConfig::default()
+        };
+        assert_eq!(unset.chat_model(), client::DEFAULT_MODEL);
     }
 
     #[test]
@@ -206,38 +1085,553 @@ mod tests {
         assert!(languages.contains(&"English"));
         assert!(languages.contains(&"中文"));
         assert!(languages.contains(&"日本語"));
+        assert!(languages.contains(&"العربية"));
+        assert!(languages.contains(&"עברית"));
     }
 
     #[test]
     fn test_serialization() {
         let config = AppConfig {
             api_key: "test_key".to_string(),
+            api_base_url: "https://api.example.com/v1".to_string(),
+            model: "glm-4-flash".to_string(),
             target_language: "中文".to_string(),
             font_size: 18.0,
             dark_theme: false,
             tts_voice: "Tongtong".to_string(),
             tts_speed: 1.0,
             tts_volume: 1.0,
+            playback_volume: 0.6,
+            playback_muted: true,
+            speak_while_translating: true,
             enable_keyword_analysis: true,
+            show_alternatives: true,
+            retry_refusals: true,
+            pivot_enabled: true,
+            pivot_language: "Français".to_string(),
+            preconnect_on_startup: true,
+            connect_timeout_secs: 5,
+            request_timeout_secs: 90,
+            request_attempts: 5,
+            check_for_updates: true,
+            watch_config_file: true,
+            spellcheck_enabled: false,
+            spellcheck_language: "de_DE".to_string(),
             think_enable: true,
             coding_plan: true,
+            chat_thinking: Some(ThinkingMode::Omit),
+            max_tokens: Some(16384),
+            repetition_limit: None,
+            source_panel_layout: SourcePanelLayout::Hidden,
+            pinned_layout: Some(Arrangement::Tabs),
+            tts_timeout_secs: 60,
+            tts_segment_timeout_secs: 15,
+            recent_languages: vec!["日本語".to_string(), "English".to_string()],
+            recent_dirs: RecentDirs::default(),
+            recent_language_limit: 5,
+            slow_stream_floor_cps: 2.5,
+            slow_stream_grace_secs: 30,
+            prompt_domain: "legal".to_string(),
+            prompt_audience: "children".to_string(),
+            offline_queue_limit: 5,
+            popout_window: Some(WindowGeometry {
+                x: 1920.0,
+                y: 40.0,
+                width: 640.0,
+                height: 480.0,
+            }),
+            auto_font_source: true,
+            auto_font_translation: true,
+            script_font_scales: BTreeMap::from([(Script::Cjk, 1.3), (Script::Latin, 0.9)]),
+            sidebar_collapsed: true,
+            reasoning_collapsed: false,
+            discard_reasoning: true,
+            sidebar_width: 360.0,
+            sidebar_auto_collapse: false,
+            sanitize_source_text: false,
+            practice_mode: true,
+            custom_font_path: Some(PathBuf::from("/usr/share/fonts/NotoSansThai.ttf")),
+            language_profiles: BTreeMap::from([(
+                "日本語".to_string(),
+                LanguageProfile {
+                    proficiency: Proficiency::Fluent,
+                    prompt_domain: "anime".to_string(),
+                    prompt_audience: String::new(),
+                    show_pronunciation: false,
+                    tts_voice: "Jam".to_string(),
+                    tts_speed: 0.8,
+                    auto_speak: true,
+                    check_confidence: true,
+                },
+            )]),
+            retention_days: None,
+            storage_budget_mb: Some(50),
+            log_rotate_mb: Some(1),
+            log_detail: LogDetail::Metadata,
+            smooth_typing: Some(90),
+            shared_cache: true,
+            redact_sensitive: true,
+            redaction_patterns: vec![r"\bEMP-\d{6}\b".to_string()],
+            redaction_preview: false,
+            cache_exclusions: vec![CacheRule {
+                name: "Client names".to_string(),
+                pattern: r"(?i)^acme\b".to_string(),
+                languages: vec!["Deutsch".to_string()],
+            }],
+            completion_hook_enabled: true,
+            completion_hook: "sh notify.sh {translation_file} {target_lang}".to_string(),
+            completion_hook_timeout_secs: 5,
+            fast_path_chars: None,
+            fast_path_model: "glm-4.5-air".to_string(),
+            normalize_typography: true,
+            quote_style: QuoteStyle::Corner,
+            model_pricing: BTreeMap::from([(
+                "glm-4.7".to_string(),
+                Pricing {
+                    currency: "EUR".to_string(),
+                    input_per_1k: 0.0006,
+                    output_per_1k: 0.0022,
+                },
+            )]),
+            output_ratio: 2.5,
+            unescape_content: true,
+            romanize_source: true,
+            saved_at: Some(1_717_200_000_000),
         };
 
         let json = serde_json::to_string(&config).unwrap();
         let deserialized: AppConfig = serde_json::from_str(&json).unwrap();
 
         assert_eq!(config.api_key, deserialized.api_key);
+        assert_eq!(config.api_base_url, deserialized.api_base_url);
+        assert_eq!(config.model, deserialized.model);
+        assert_eq!(deserialized.chat_model(), "glm-4-flash");
         assert_eq!(config.target_language, deserialized.target_language);
         assert_eq!(config.font_size, deserialized.font_size);
         assert_eq!(config.dark_theme, deserialized.dark_theme);
         assert_eq!(config.tts_voice, deserialized.tts_voice);
         assert_eq!(config.tts_speed, deserialized.tts_speed);
         assert_eq!(config.tts_volume, deserialized.tts_volume);
+        assert_eq!(config.playback_volume, deserialized.playback_volume);
+        assert_eq!(config.playback_muted, deserialized.playback_muted);
+        assert_eq!(
+            config.speak_while_translating,
+            deserialized.speak_while_translating
+        );
         assert_eq!(
             config.enable_keyword_analysis,
             deserialized.enable_keyword_analysis
         );
+        assert_eq!(config.show_alternatives, deserialized.show_alternatives);
+        assert_eq!(config.retry_refusals, deserialized.retry_refusals);
+        assert_eq!(config.pivot_enabled, deserialized.pivot_enabled);
+        assert_eq!(config.pivot_language, deserialized.pivot_language);
+        assert_eq!(
+            config.preconnect_on_startup,
+            deserialized.preconnect_on_startup
+        );
+        assert_eq!(
+            config.connect_timeout_secs,
+            deserialized.connect_timeout_secs
+        );
+        assert_eq!(
+            config.request_timeout_secs,
+            deserialized.request_timeout_secs
+        );
+        assert_eq!(config.request_attempts, deserialized.request_attempts);
+        assert_eq!(config.check_for_updates, deserialized.check_for_updates);
+        assert_eq!(config.watch_config_file, deserialized.watch_config_file);
+        assert_eq!(config.spellcheck_enabled, deserialized.spellcheck_enabled);
+        assert_eq!(config.spellcheck_language, deserialized.spellcheck_language);
         assert_eq!(config.think_enable, deserialized.think_enable);
         assert_eq!(config.coding_plan, deserialized.coding_plan);
+        assert_eq!(config.chat_thinking, deserialized.chat_thinking);
+        assert_eq!(config.max_tokens, deserialized.max_tokens);
+        assert_eq!(config.repetition_limit, deserialized.repetition_limit);
+        assert_eq!(config.source_panel_layout, deserialized.source_panel_layout);
+        assert_eq!(config.pinned_layout, deserialized.pinned_layout);
+        assert_eq!(config.tts_timeout_secs, deserialized.tts_timeout_secs);
+        assert_eq!(
+            config.tts_segment_timeout_secs,
+            deserialized.tts_segment_timeout_secs
+        );
+        assert_eq!(config.recent_languages, deserialized.recent_languages);
+        assert_eq!(config.recent_dirs, deserialized.recent_dirs);
+        assert_eq!(
+            config.recent_language_limit,
+            deserialized.recent_language_limit
+        );
+        assert_eq!(
+            config.slow_stream_floor_cps,
+            deserialized.slow_stream_floor_cps
+        );
+        assert_eq!(
+            config.slow_stream_grace_secs,
+            deserialized.slow_stream_grace_secs
+        );
+        assert_eq!(config.prompt_domain, deserialized.prompt_domain);
+        assert_eq!(config.prompt_audience, deserialized.prompt_audience);
+        assert_eq!(config.offline_queue_limit, deserialized.offline_queue_limit);
+        assert_eq!(config.popout_window, deserialized.popout_window);
+        assert_eq!(config.auto_font_source, deserialized.auto_font_source);
+        assert_eq!(
+            config.auto_font_translation,
+            deserialized.auto_font_translation
+        );
+        assert_eq!(config.script_font_scales, deserialized.script_font_scales);
+        assert_eq!(config.language_profiles, deserialized.language_profiles);
+        assert_eq!(config.sidebar_collapsed, deserialized.sidebar_collapsed);
+        assert_eq!(config.reasoning_collapsed, deserialized.reasoning_collapsed);
+        assert_eq!(config.discard_reasoning, deserialized.discard_reasoning);
+        assert_eq!(config.sidebar_width, deserialized.sidebar_width);
+        assert_eq!(
+            config.sidebar_auto_collapse,
+            deserialized.sidebar_auto_collapse
+        );
+        assert_eq!(
+            config.sanitize_source_text,
+            deserialized.sanitize_source_text
+        );
+        assert_eq!(config.practice_mode, deserialized.practice_mode);
+        assert_eq!(config.custom_font_path, deserialized.custom_font_path);
+        assert_eq!(config.retention_days, deserialized.retention_days);
+        assert_eq!(config.storage_budget_mb, deserialized.storage_budget_mb);
+        assert_eq!(config.log_rotate_mb, deserialized.log_rotate_mb);
+        assert_eq!(config.log_detail, deserialized.log_detail);
+        assert_eq!(config.smooth_typing, deserialized.smooth_typing);
+        assert_eq!(config.shared_cache, deserialized.shared_cache);
+        assert_eq!(config.redact_sensitive, deserialized.redact_sensitive);
+        assert_eq!(config.redaction_patterns, deserialized.redaction_patterns);
+        assert_eq!(config.redaction_preview, deserialized.redaction_preview);
+        assert_eq!(config.cache_exclusions, deserialized.cache_exclusions);
+        assert_eq!(
+            config.completion_hook_enabled,
+            deserialized.completion_hook_enabled
+        );
+        assert_eq!(config.completion_hook, deserialized.completion_hook);
+        assert_eq!(
+            config.completion_hook_timeout_secs,
+            deserialized.completion_hook_timeout_secs
+        );
+        assert_eq!(config.fast_path_chars, deserialized.fast_path_chars);
+        assert_eq!(config.fast_path_model, deserialized.fast_path_model);
+        assert_eq!(
+            config.normalize_typography,
+            deserialized.normalize_typography
+        );
+        assert_eq!(config.quote_style, deserialized.quote_style);
+        assert_eq!(config.model_pricing, deserialized.model_pricing);
+        assert_eq!(config.output_ratio, deserialized.output_ratio);
+        assert_eq!(config.unescape_content, deserialized.unescape_content);
+        assert_eq!(config.romanize_source, deserialized.romanize_source);
+        assert_eq!(config.saved_at, deserialized.saved_at);
+    }
+
+    #[test]
+    fn test_record_recent_language() {
+        let mut config = AppConfig::default();
+        for language in ["English", "中文", "日本語", "中文", "Français"] {
+            config.record_recent_language(language);
+        }
+        assert_eq!(config.recent_languages, vec!["Français", "中文", "日本語"]);
+    }
+
+    #[test]
+    fn test_language_profiles() {
+        let mut config = AppConfig::default();
+        let mut profile = LanguageProfile::new(&config.tts_voice, config.tts_speed);
+        profile.prompt_domain = "anime".to_string();
+        profile.auto_speak = true;
+        profile.check_confidence = true;
+        profile.show_pronunciation = false;
+        profile.tts_speed = 0.8;
+        config
+            .language_profiles
+            .insert("日本語".to_string(), profile);
+
+        // Hints given for the request win over the profile
+        let context = config.prompt_context_for("日本語", PromptContext::new("", "kids"));
+        assert_eq!(context, PromptContext::new("anime", "kids"));
+        let context = config.prompt_context_for("日本語", PromptContext::new("games", ""));
+        assert_eq!(context.domain, "games");
+        assert!(config.auto_speak_for("日本語"));
+        assert!(config.check_confidence_for("日本語"));
+        assert!(!config.show_pronunciation_for("日本語"));
+        assert_eq!(config.tts_config().speed, 1.0);
+        config.target_language = "日本語".to_string();
+        assert_eq!(config.tts_config().speed, 0.8);
+
+        // Languages without a profile use the global settings and get none
+        let context = config.prompt_context_for("Français", PromptContext::default());
+        assert_eq!(context, PromptContext::default());
+        assert!(!config.auto_speak_for("Français"));
+        assert!(!config.check_confidence_for("Français"));
+        assert!(config.show_pronunciation_for("Français"));
+        assert_eq!(config.language_profiles.len(), 1);
+    }
+
+    #[test]
+    fn test_chat_thinking_defaults_to_provider() {
+        let json =
+            r#"{"api_key":"","target_language":"English","font_size":16.0,"dark_theme":true}"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.chat_thinking, None);
+        assert_eq!(config.source_panel_layout, SourcePanelLayout::Mirror);
+        assert_eq!(config.tts_timeout_secs, 120);
+        assert_eq!(config.tts_segment_timeout_secs, 30);
+        assert_eq!(config.connect_timeout_secs, 10);
+        assert_eq!(config.request_timeout_secs, 60);
+        assert_eq!(config.request_attempts, 3);
+        assert_eq!(config.script_font_scales.get(&Script::Cjk), Some(&1.15));
+    }
+
+    #[test]
+    fn test_edited_timeouts_are_brought_into_range() {
+        let json = r#"{"api_key":"","target_language":"English","font_size":16.0,"dark_theme":true,"connect_timeout_secs":1000,"request_timeout_secs":0}"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.connect_timeout_secs, 120);
+        assert_eq!(config.request_timeout_secs, 5);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_save_keeps_backup_and_restores() {
+        let dir = temp_dir("test_config_backup");
+        let path = dir.join("config.json");
+
+        let mut config = AppConfig::default();
+        config.save_to(&path).unwrap();
+        assert!(!AppConfig::backup_path(&path).exists());
+
+        config.font_size = 20.0;
+        config.save_to(&path).unwrap();
+        // Saving unchanged content keeps the backup of the previous version
+        config.save_to(&path).unwrap();
+        assert_eq!(AppConfig::read(&path).unwrap().font_size, 20.0);
+
+        let restored = AppConfig::restore_from(&path).unwrap();
+        assert_eq!(restored.font_size, 16.0);
+        assert_eq!(AppConfig::read(&path).unwrap().font_size, 16.0);
+        // The restore itself can be undone
+        let backup = AppConfig::read(&AppConfig::backup_path(&path)).unwrap();
+        assert_eq!(backup.font_size, 20.0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original_intact() {
+        let dir = temp_dir("test_config_failed_write");
+        let path = dir.join("config.json");
+        AppConfig::default().save_to(&path).unwrap();
+        let original = fs::read_to_string(&path).unwrap();
+
+        let changed = AppConfig {
+            api_key: "new".to_string(),
+            ..Default::default()
+        };
+
+        // The temporary file cannot be created
+        let temp_path = path.with_extension("json.tmp");
+        fs::create_dir(&temp_path).unwrap();
+        assert!(changed.save_to(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        fs::remove_dir(&temp_path).unwrap();
+
+        // Read-only directory (has no effect when running as root)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+            if File::create(dir.join("probe")).is_err() {
+                assert!(changed.save_to(&path).is_err());
+                assert_eq!(fs::read_to_string(&path).unwrap(), original);
+                assert!(!temp_path.exists());
+            }
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_sync_decision() {
+        let decision = |source, resync| SyncDecision { source, resync };
+        let saved = 1_717_200_000_000;
+
+        assert_eq!(
+            decide_sync(None, None),
+            decision(ConfigSource::Default, false)
+        );
+        assert_eq!(
+            decide_sync(Some(saved), None),
+            decision(ConfigSource::Storage, true)
+        );
+        assert_eq!(
+            decide_sync(None, Some(saved)),
+            decision(ConfigSource::File, true)
+        );
+        // Both copies written by the same save
+        assert_eq!(
+            decide_sync(Some(saved), Some(saved + 150)),
+            decision(ConfigSource::Storage, false)
+        );
+        // The file was edited by hand after the last save
+        assert_eq!(
+            decide_sync(Some(saved), Some(saved + 60_000)),
+            decision(ConfigSource::File, true)
+        );
+        // Writing the file failed, or an older file was put back
+        assert_eq!(
+            decide_sync(Some(saved), Some(saved - 60_000)),
+            decision(ConfigSource::Storage, true)
+        );
+        // Snapshots from before `saved_at` lose against any file
+        assert_eq!(
+            decide_sync(Some(0), Some(saved)),
+            decision(ConfigSource::File, true)
+        );
+    }
+
+    /// Stand-in for eframe storage with a config snapshot saved at `saved_at`.
+    fn snapshot(api_key: &str, saved_at: Option<i64>) -> AppConfig {
+        AppConfig {
+            api_key: api_key.to_string(),
+            saved_at,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_prefers_edited_file() {
+        let dir = temp_dir("test_config_resolve_file");
+        let paths = AppPaths::resolve(&dir, &dir);
+        let path = &paths.config_file;
+        snapshot("edited", None).save_to(path).unwrap();
+
+        // The snapshot is a minute older than the edit
+        let saved_at = modified_millis(path).unwrap() - 60_000;
+        let (config, resync) = AppConfig::resolve(Some(snapshot("stored", Some(saved_at))), &paths);
+        assert_eq!(config.api_key, "edited");
+        assert!(resync);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_resolve_keeps_newer_storage() {
+        let dir = temp_dir("test_config_resolve_storage");
+        let paths = AppPaths::resolve(&dir, &dir);
+        let path = &paths.config_file;
+        snapshot("file", None).save_to(path).unwrap();
+        let modified = modified_millis(path).unwrap();
+
+        let (config, resync) = AppConfig::resolve(Some(snapshot("stored", Some(modified))), &paths);
+        assert_eq!(config.api_key, "stored");
+        assert!(!resync);
+
+        let (config, resync) =
+            AppConfig::resolve(Some(snapshot("stored", Some(modified + 60_000))), &paths);
+        assert_eq!(config.api_key, "stored");
+        assert!(resync);
+
+        // An unreadable file never replaces the snapshot
+        fs::write(path, "{ not json").unwrap();
+        let (config, _) = AppConfig::resolve(Some(snapshot("stored", Some(0))), &paths);
+        assert_eq!(config.api_key, "stored");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_same_settings_ignores_save_time() {
+        let config = snapshot("key", Some(1));
+        assert!(config.same_settings(&snapshot("key", Some(2))));
+        assert!(config.same_settings(&snapshot("key", None)));
+        assert!(!config.same_settings(&snapshot("other", Some(1))));
+    }
+
+    /// Settings files of a run started in `dir`, with a configuration file
+    /// and a file of an older version holding the given API keys.
+    fn settings_files(dir: &Path, canonical: Option<&str>, legacy: Option<&str>) -> AppPaths {
+        let config_dir = dir.join("config");
+        if let Some(api_key) = canonical {
+            snapshot(api_key, None)
+                .save_to(&config_dir.join("config.json"))
+                .unwrap();
+        }
+        if let Some(api_key) = legacy {
+            let json = serde_json::to_string(&snapshot(api_key, None)).unwrap();
+            fs::write(dir.join(paths::LEGACY_CONFIG_FILE), json).unwrap();
+        }
+        AppPaths::resolve(&config_dir, dir)
+    }
+
+    #[test]
+    fn test_resolution_order_of_settings_files() {
+        // (configuration file, older version's file, settings loaded)
+        let cases = [
+            (None, None, ""),
+            (Some("canonical"), None, "canonical"),
+            (None, Some("legacy"), "legacy"),
+            (Some("canonical"), Some("legacy"), "canonical"),
+        ];
+        for (i, (canonical, legacy, expected)) in cases.into_iter().enumerate() {
+            let dir = temp_dir(&format!("test_config_resolution_{}", i));
+            let paths = settings_files(&dir, canonical, legacy);
+            assert!(paths.config_file.is_absolute());
+            assert_eq!(paths.legacy_config_file.is_some(), legacy.is_some());
+
+            let (config, resync) = AppConfig::resolve(None, &paths);
+            assert_eq!(config.api_key, expected, "case {}", i);
+            assert_eq!(resync, canonical.is_some() || legacy.is_some());
+            // Nothing is imported without asking
+            assert_eq!(paths.config_file.exists(), canonical.is_some());
+
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn test_legacy_config_imported_once() {
+        for (i, canonical) in [None, Some("canonical")].into_iter().enumerate() {
+            let dir = temp_dir(&format!("test_config_import_{}", i));
+            let paths = settings_files(&dir, canonical, Some("legacy"));
+            let legacy_path = paths.legacy_config_file.clone().unwrap();
+
+            let config = AppConfig::import_from(&legacy_path, &paths.config_file).unwrap();
+            assert_eq!(config.api_key, "legacy");
+            assert_eq!(AppConfig::resolve(None, &paths).0.api_key, "legacy");
+            assert!(!legacy_path.exists());
+            assert!(AppConfig::imported_path(&legacy_path).exists());
+
+            // Not offered again
+            let paths = AppPaths::resolve(&dir.join("config"), &dir);
+            assert_eq!(paths.legacy_config_file, None);
+            assert!(AppConfig::import_from(&legacy_path, &paths.config_file).is_err());
+
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    #[test]
+    fn test_invalid_legacy_config_is_not_imported() {
+        let dir = temp_dir("test_config_import_invalid");
+        let legacy_path = dir.join(paths::LEGACY_CONFIG_FILE);
+        fs::write(&legacy_path, "{ not json").unwrap();
+        let paths = AppPaths::resolve(&dir.join("config"), &dir);
+
+        assert_eq!(AppConfig::resolve(None, &paths).0.api_key, "");
+        assert!(AppConfig::import_from(&legacy_path, &paths.config_file).is_err());
+        assert!(legacy_path.exists());
+        assert!(!paths.config_file.exists());
+
+        let _ = fs::remove_dir_all(dir);
     }
 }
@@ -0,0 +1,289 @@
+//! Following the configuration file while it is edited by hand.
+//!
+//! With the watch option on, a [`ConfigWatcher`] follows the directory of
+//! the configuration file, as editors often save by replacing the file
+//! instead of writing to it. The burst of events of one save is debounced
+//! into a single reload, and the UI is woken for it, so an edit applies
+//! while the app is idle in the background. Content that was already
+//! applied, such as the
+//! app's own saves, is recognized by its hash and not reloaded again, so
+//! saving the settings never triggers a reload of them.
+
+use crate::utils::config::AppConfig;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last event before the file is read.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Settings named in the summary of a reload before the rest is counted.
+const SUMMARY_NAMES: usize = 4;
+
+/// Tells which changes of the file are worth reloading.
+#[derive(Debug, Default)]
+struct Changes {
+    /// When the file last changed, while a reload is pending
+    last_event: Option<Instant>,
+    /// Hash of the content last applied or written by the app
+    known: Option<u64>,
+}
+
+impl Changes {
+    fn touched(&mut self, now: Instant) {
+        self.last_event = Some(now);
+    }
+
+    /// Whether the file has been quiet long enough since it changed.
+    fn is_due(&self, now: Instant) -> bool {
+        self.wait(now).is_some_and(|wait| wait.is_zero())
+    }
+
+    /// How long until a pending reload is due, `None` without one.
+    fn wait(&self, now: Instant) -> Option<Duration> {
+        self.last_event
+            .map(|last_event| DEBOUNCE.saturating_sub(now.duration_since(last_event)))
+    }
+
+    /// Ends the pending reload with `content`, returning whether it is new.
+    fn settle(&mut self, content: &str) -> bool {
+        self.last_event = None;
+        let hash = hash(content);
+        if self.known == Some(hash) {
+            return false;
+        }
+        self.known = Some(hash);
+        true
+    }
+
+    /// Marks `content` as known, so finding it in the file reloads nothing.
+    fn remember(&mut self, content: &str) {
+        self.known = Some(hash(content));
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Watches the configuration file for changes made outside the app.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    events: Receiver<()>,
+    changes: Changes,
+    /// Repainted when a pending reload is due
+    ctx: egui::Context,
+    /// Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    /// Starts watching the file at `path`, taking its current content as
+    /// applied, and repainting `ctx` when it changes.
+    pub fn start(path: PathBuf, ctx: egui::Context) -> notify::Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let name = path.file_name().map(|name| name.to_os_string());
+        let waker = ctx.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                // Reads of the file and writes to its siblings don't matter
+                if !matches!(event.kind, EventKind::Access(_))
+                    && event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == name.as_deref())
+                {
+                    let _ = tx.send(());
+                    waker.request_repaint();
+                }
+            })?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        tracing::info!("Watching {} for changes", path.display());
+
+        let mut config_watcher = ConfigWatcher {
+            path,
+            events,
+            changes: Changes::default(),
+            ctx,
+            _watcher: watcher,
+        };
+        config_watcher.remember_file();
+        Ok(config_watcher)
+    }
+
+    /// Takes the file as it is now as applied, e.g. after the app saved it.
+    pub fn remember_file(&mut self) {
+        if let Ok(content) = fs::read_to_string(&self.path) {
+            self.changes.remember(&content);
+        }
+    }
+
+    /// The configuration file once it changed and settled, or why it
+    /// couldn't be read.
+    ///
+    /// While a change is settling, a repaint is scheduled for when it is due.
+    pub fn poll(&mut self) -> Option<Result<AppConfig, String>> {
+        let now = Instant::now();
+        while self.events.try_recv().is_ok() {
+            self.changes.touched(now);
+        }
+        if !self.changes.is_due(now) {
+            if let Some(wait) = self.changes.wait(now) {
+                self.ctx.request_repaint_after(wait);
+            }
+            return None;
+        }
+        // Gone between the remove and the rename of a replacing save, the
+        // rename itself is the next event
+        let content = fs::read_to_string(&self.path).ok()?;
+        if !self.changes.settle(&content) {
+            tracing::debug!("Config file changed to content already applied");
+            return None;
+        }
+        Some(serde_json::from_str(&content).map_err(|e| e.to_string()))
+    }
+}
+
+/// Names of the settings that differ between `old` and `new`, ignoring
+/// when they were saved.
+pub fn changed_settings(old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter(|(name, value)| {
+            name.as_str() != "saved_at" && old.get(name.as_str()) != Some(value)
+        })
+        .map(|(name, _)| name.replace('_', " "))
+        .collect()
+}
+
+/// A short list of the changed settings, for a toast.
+pub fn summary(changed: &[String]) -> String {
+    if changed.len() <= SUMMARY_NAMES {
+        return changed.join(", ");
+    }
+    format!(
+        "{} and {} more",
+        changed[..SUMMARY_NAMES].join(", "),
+        changed.len() - SUMMARY_NAMES
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bursts_are_debounced() {
+        let mut changes = Changes::default();
+        let start = Instant::now();
+        assert!(!changes.is_due(start));
+
+        // An editor writing the file several times in a row
+        changes.touched(start);
+        changes.touched(start + Duration::from_millis(100));
+        assert!(!changes.is_due(start + Duration::from_millis(250)));
+        assert_eq!(
+            changes.wait(start + Duration::from_millis(250)),
+            Some(Duration::from_millis(150))
+        );
+        assert!(changes.is_due(start + Duration::from_millis(400)));
+
+        assert!(changes.settle("{\"font_size\": 16}"));
+        assert!(!changes.is_due(start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_known_content_is_not_reloaded() {
+        let mut changes = Changes::default();
+        changes.remember("saved by the app");
+        assert!(!changes.settle("saved by the app"));
+        assert!(changes.settle("edited by hand"));
+        // Saving the same edit again changes nothing
+        assert!(!changes.settle("edited by hand"));
+    }
+
+    #[test]
+    fn test_changed_settings() {
+        let old = AppConfig::default();
+        let new = AppConfig {
+            font_size: old.font_size + 2.0,
+            dark_theme: !old.dark_theme,
+            saved_at: Some(1),
+            ..old.clone()
+        };
+        let changed = changed_settings(&old, &new);
+        assert_eq!(changed.len(), 2);
+        assert!(changed.contains(&"font size".to_string()));
+        assert!(changed.contains(&"dark theme".to_string()));
+        assert!(changed_settings(&old, &old).is_empty());
+
+        let names: Vec<String> = (1..=6).map(|i| format!("setting {}", i)).collect();
+        assert_eq!(summary(&names[..2]), "setting 1, setting 2");
+        assert_eq!(
+            summary(&names),
+            "setting 1, setting 2, setting 3, setting 4 and 2 more"
+        );
+    }
+
+    #[test]
+    fn test_watches_external_edits() {
+        let dir = std::env::temp_dir().join("test_config_watch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        let config = AppConfig::default();
+        fs::write(&path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
+        let ctx = egui::Context::default();
+        let repaints = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        ctx.set_request_repaint_callback({
+            let repaints = repaints.clone();
+            move |_| {
+                repaints.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        });
+        let mut watcher = ConfigWatcher::start(path.clone(), ctx).unwrap();
+
+        let edited = AppConfig {
+            font_size: config.font_size + 4.0,
+            ..config.clone()
+        };
+        fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+        let poll_until = |watcher: &mut ConfigWatcher| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if let Some(reloaded) = watcher.poll() {
+                    return Some(reloaded);
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            None
+        };
+        let reloaded = poll_until(&mut watcher).expect("edit was noticed");
+        assert_eq!(reloaded.unwrap().font_size, edited.font_size);
+        // The idle UI was woken to apply it
+        assert!(repaints.load(std::sync::atomic::Ordering::SeqCst) > 0);
+
+        // A broken edit is reported, and the app's own save is ignored
+        fs::write(&path, "{ not json").unwrap();
+        assert!(poll_until(&mut watcher).expect("edit was noticed").is_err());
+        fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+        watcher.remember_file();
+        std::thread::sleep(DEBOUNCE * 2);
+        assert!(watcher.poll().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
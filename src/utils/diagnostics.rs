@@ -0,0 +1,395 @@
+//! Diagnostic bundles for bug reports.
+//!
+//! A bundle is a folder with the app and system details, the redacted
+//! configuration, the tail of the translation logs, recent tracing output,
+//! and cache statistics. The API key is removed from every file in it.
+
+use crate::utils::config::AppConfig;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Number of log entries included from each log file.
+const LOG_ENTRIES: usize = 50;
+
+/// Replacement for redacted values.
+const REDACTED: &str = "[REDACTED]";
+
+/// Separator line between entries of the translation log.
+const LOG_SEPARATOR_LEN: usize = 80;
+
+/// Keeps the most recent formatted tracing lines in memory.
+///
+/// Used as the writer of a `fmt` layer, so the lines can be included in a
+/// diagnostic bundle without writing a log file.
+#[derive(Clone)]
+pub struct TraceBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl TraceBuffer {
+    /// Lines kept by default.
+    pub const CAPACITY: usize = 500;
+
+    pub fn new(capacity: usize) -> Self {
+        TraceBuffer {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    fn push(&self, line: String) {
+        // Never log from here, this runs inside the subscriber
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The buffered lines, oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        let lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        lines.iter().cloned().collect()
+    }
+}
+
+impl Default for TraceBuffer {
+    fn default() -> Self {
+        Self::new(Self::CAPACITY)
+    }
+}
+
+/// Writer for one tracing event, committed to the buffer when dropped.
+pub struct TraceBufferWriter {
+    buffer: TraceBuffer,
+    pending: Vec<u8>,
+}
+
+impl Write for TraceBufferWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for TraceBufferWriter {
+    fn drop(&mut self) {
+        let text = String::from_utf8_lossy(&self.pending);
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            self.buffer.push(line.to_string());
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for TraceBuffer {
+    type Writer = TraceBufferWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TraceBufferWriter {
+            buffer: self.clone(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+/// Whether a config field holds a credential.
+fn is_secret_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with("key")
+        || name.contains("secret")
+        || name.contains("password")
+        || name.contains("authorization")
+        || name.contains("header")
+}
+
+/// Replaces every occurrence of `secret` in `text`.
+fn redact_text(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        text.to_string()
+    } else {
+        text.replace(secret, REDACTED)
+    }
+}
+
+/// Redacts credential fields, and the API key wherever else it appears.
+fn redact_value(value: &mut Value, api_key: &str) {
+    match value {
+        Value::Object(map) => {
+            for (name, field) in map.iter_mut() {
+                if is_secret_field(name) && !field.is_null() {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(field, api_key);
+                }
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| redact_value(item, api_key)),
+        Value::String(text) => *text = redact_text(text, api_key),
+        _ => {}
+    }
+}
+
+/// The configuration as pretty JSON with all credentials removed.
+pub fn redacted_config(config: &AppConfig) -> String {
+    let mut value = serde_json::to_value(config).unwrap_or(Value::Null);
+    redact_value(&mut value, &config.api_key);
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+/// The last `count` lines of a JSONL file.
+fn tail_lines(content: &str, count: usize) -> String {
+    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    let start = lines.len().saturating_sub(count);
+    lines[start..].join("\n")
+}
+
+/// The last `count` entries of the translation log, optionally without the
+/// source and translated text.
+fn tail_translation_log(content: &str, count: usize, strip_text: bool) -> String {
+    let separator = "-".repeat(LOG_SEPARATOR_LEN);
+    let entries: Vec<&str> = content
+        .split(&separator)
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    let start = entries.len().saturating_sub(count);
+
+    entries[start..]
+        .iter()
+        .map(|entry| {
+            if strip_text {
+                strip_entry_text(entry)
+            } else {
+                entry.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&format!("\n{}\n", separator))
+}
+
+/// Replaces the source and translated text of a log entry with their lengths.
+fn strip_entry_text(entry: &str) -> String {
+    let Some(source_at) = entry.find("Source Text: ") else {
+        return entry.to_string();
+    };
+    let header = &entry[..source_at];
+    let body = &entry[source_at + "Source Text: ".len()..];
+    let (source, translation) = match body.find("\nTranslation: ") {
+        Some(at) => (&body[..at], &body[at + "\nTranslation: ".len()..]),
+        None => (body, ""),
+    };
+    format!(
+        "{}Source Text: [stripped, {} chars]\nTranslation: [stripped, {} chars]",
+        header,
+        source.chars().count(),
+        translation.chars().count()
+    )
+}
+
+/// Everything collected into a bundle.
+pub struct BundleInputs<'a> {
+    pub config: &'a AppConfig,
+    /// Translation log and its metrics JSONL file
+    pub log_files: Option<(PathBuf, PathBuf)>,
+    pub trace_lines: Vec<String>,
+    pub translation_cache_entries: usize,
+    pub audio_cache_files: usize,
+    pub offline_queue_len: usize,
+    /// Leave source and translated text out of the log excerpt
+    pub strip_text: bool,
+}
+
+/// App, build and system details.
+fn system_info() -> String {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string());
+    format!(
+        "App: {} {}\nBuild: {}\nOS: {} ({})\nArch: {}\nLocale: {}\nCreated: {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        std::env::consts::OS,
+        std::env::consts::FAMILY,
+        std::env::consts::ARCH,
+        locale,
+        chrono::Local::now().to_rfc3339(),
+    )
+}
+
+/// Writes a diagnostic bundle into a new folder under `dir`.
+///
+/// # Returns
+///
+/// The path of the bundle folder
+pub fn write_bundle(dir: &Path, inputs: &BundleInputs) -> io::Result<PathBuf> {
+    let bundle_dir = dir.join(format!(
+        "diagnostics-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::create_dir_all(&bundle_dir)?;
+
+    let api_key = &inputs.config.api_key;
+    let write =
+        |name: &str, content: &str| fs::write(bundle_dir.join(name), redact_text(content, api_key));
+
+    write("system.txt", &system_info())?;
+    write("config.json", &redacted_config(inputs.config))?;
+
+    if let Some((log_file, metrics_file)) = &inputs.log_files {
+        let log = fs::read_to_string(log_file).unwrap_or_default();
+        write(
+            "translations.log",
+            &tail_translation_log(&log, LOG_ENTRIES, inputs.strip_text),
+        )?;
+        let metrics = fs::read_to_string(metrics_file).unwrap_or_default();
+        write("metrics.jsonl", &tail_lines(&metrics, LOG_ENTRIES))?;
+    }
+
+    write("trace.log", &inputs.trace_lines.join("\n"))?;
+    write(
+        "cache.txt",
+        &format!(
+            "Translation cache entries: {}\nAudio cache files: {}\nOffline queue: {}\n",
+            inputs.translation_cache_entries, inputs.audio_cache_files, inputs.offline_queue_len
+        ),
+    )?;
+
+    tracing::info!("Wrote diagnostic bundle to {:?}", bundle_dir);
+    Ok(bundle_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const KEY: &str = "sk-test-0123456789abcdef";
+
+    fn config_with_key_everywhere() -> AppConfig {
+        AppConfig {
+            api_key: KEY.to_string(),
+            prompt_domain: format!("pasted {} by mistake", KEY),
+            prompt_audience: KEY.to_string(),
+            recent_languages: vec!["English".to_string(), KEY.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config_redaction() {
+        let json = redacted_config(&config_with_key_everywhere());
+        assert!(!json.contains(KEY));
+        assert!(!json.contains("0123456789abcdef"));
+
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["api_key"], REDACTED);
+        assert_eq!(value["prompt_domain"], "pasted [REDACTED] by mistake");
+        assert_eq!(value["recent_languages"][0], "English");
+        // Other settings survive
+        assert_eq!(
+            value["target_language"],
+            AppConfig::default().target_language
+        );
+    }
+
+    #[test]
+    fn test_secret_fields_redacted_without_key() {
+        let mut value = serde_json::json!({
+            "api_key": "",
+            "custom_headers": {"Authorization": "Bearer abc"},
+            "max_tokens": 4096
+        });
+        redact_value(&mut value, "");
+        assert_eq!(value["api_key"], REDACTED);
+        assert_eq!(value["custom_headers"], REDACTED);
+        assert_eq!(value["max_tokens"], 4096);
+    }
+
+    #[test]
+    fn test_translation_log_tail_and_strip() {
+        let separator = "-".repeat(LOG_SEPARATOR_LEN);
+        let log: String = (0..60)
+            .map(|i| {
+                format!(
+                    "[2025-01-01 00:00:{:02}]\nSource Language: Auto\nTarget Language: English\nThinking: enabled\nSource Text: 源文{}\nTranslation: text {}\n{}\n",
+                    i % 60,
+                    i,
+                    i,
+                    separator
+                )
+            })
+            .collect();
+
+        let kept = tail_translation_log(&log, 50, false);
+        assert_eq!(kept.matches("Source Language").count(), 50);
+        assert!(kept.contains("源文59"));
+        assert!(!kept.contains("源文9\n"));
+
+        let stripped = tail_translation_log(&log, 2, true);
+        assert!(!stripped.contains("源文"));
+        assert!(!stripped.contains("text 59"));
+        assert!(stripped.contains("Source Text: [stripped, 4 chars]"));
+        assert!(stripped.contains("Target Language: English"));
+    }
+
+    #[test]
+    fn test_trace_buffer_keeps_recent_lines() {
+        let buffer = TraceBuffer::new(3);
+        for i in 0..5 {
+            let mut writer = buffer.make_writer();
+            writeln!(writer, "line {}", i).unwrap();
+        }
+        assert_eq!(buffer.lines(), vec!["line 2", "line 3", "line 4"]);
+    }
+
+    #[test]
+    fn test_bundle_never_contains_key() {
+        let dir = env::temp_dir().join("test_diagnostic_bundle");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let log_file = dir.join("translations.log");
+        let metrics_file = dir.join("translations.metrics.jsonl");
+        fs::write(&log_file, format!("Source Text: {}\nTranslation: x\n", KEY)).unwrap();
+        fs::write(&metrics_file, "{\"total_chars\":1}\n").unwrap();
+
+        let config = config_with_key_everywhere();
+        let inputs = BundleInputs {
+            config: &config,
+            log_files: Some((log_file, metrics_file)),
+            trace_lines: vec![format!("INFO key={}", KEY)],
+            translation_cache_entries: 3,
+            audio_cache_files: 1,
+            offline_queue_len: 0,
+            strip_text: false,
+        };
+        let bundle = write_bundle(&dir, &inputs).unwrap();
+
+        let files: Vec<_> = fs::read_dir(&bundle).unwrap().flatten().collect();
+        assert_eq!(files.len(), 6);
+        for file in files {
+            let content = fs::read_to_string(file.path()).unwrap();
+            assert!(!content.contains(KEY), "{:?} leaks the key", file.path());
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,186 @@
+//! File dialogs that open where the user left off.
+//!
+//! Every file dialog of the app is built with [`Dialog`], which starts in
+//! the folder of the last file picked for the same [`DialogCategory`] and
+//! remembers the folder of the file picked now. The folders are stored in
+//! the settings as [`RecentDirs`]; the app hands them over with
+//! [`set_recent_dirs`] and takes them back with [`take_changed`], so the
+//! panels opening dialogs need no access to the settings.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// What a file dialog is for; each remembers its own folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogCategory {
+    /// PDF files to translate
+    Pdf,
+    /// JSON and YAML files to translate
+    Document,
+    /// Translated JSON and YAML files
+    DocumentExport,
+    /// Translation memories exported from the history
+    Tmx,
+    /// Recordings compared with the spoken translation
+    Recording,
+    /// Extra fonts
+    Font,
+}
+
+/// Folder of the last file picked per category.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecentDirs(BTreeMap<DialogCategory, PathBuf>);
+
+impl RecentDirs {
+    /// Folder a dialog of `category` starts in: the folder used last if it
+    /// still exists, else the documents folder.
+    pub fn start_dir(&self, category: DialogCategory) -> Option<PathBuf> {
+        self.0
+            .get(&category)
+            .filter(|dir| dir.is_dir())
+            .cloned()
+            .or_else(fallback_dir)
+    }
+
+    /// Remembers the folder of `picked`, returning whether it changed.
+    pub fn record(&mut self, category: DialogCategory, picked: &Path) -> bool {
+        let dir = if picked.is_dir() {
+            picked
+        } else {
+            match picked.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => return false,
+            }
+        };
+        if self.0.get(&category).map(PathBuf::as_path) == Some(dir) {
+            return false;
+        }
+        self.0.insert(category, dir.to_path_buf());
+        true
+    }
+}
+
+/// The documents folder, or the home folder where there is none.
+fn fallback_dir() -> Option<PathBuf> {
+    dirs::document_dir().or_else(dirs::home_dir)
+}
+
+/// The folders of this run, and whether a dialog changed them.
+static RECENT: LazyLock<Mutex<(RecentDirs, bool)>> = LazyLock::new(Mutex::default);
+
+/// Starts the dialogs in `dirs` from now on, as loaded from the settings.
+pub fn set_recent_dirs(dirs: RecentDirs) {
+    *crate::lock_mutex!(RECENT) = (dirs, false);
+}
+
+/// The folders, if a dialog remembered a new one since the last call.
+pub fn take_changed() -> Option<RecentDirs> {
+    let mut recent = crate::lock_mutex!(RECENT);
+    std::mem::take(&mut recent.1).then(|| recent.0.clone())
+}
+
+/// A native file dialog of a category.
+pub struct Dialog {
+    category: DialogCategory,
+    dialog: rfd::FileDialog,
+}
+
+impl Dialog {
+    /// A dialog starting in the folder used last for `category`.
+    pub fn new(category: DialogCategory) -> Self {
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = crate::lock_mutex!(RECENT).0.start_dir(category) {
+            dialog = dialog.set_directory(dir);
+        }
+        Dialog { category, dialog }
+    }
+
+    pub fn add_filter(mut self, name: &str, extensions: &[&str]) -> Self {
+        self.dialog = self.dialog.add_filter(name, extensions);
+        self
+    }
+
+    /// Proposes `file_name` for saving.
+    pub fn set_file_name(mut self, file_name: &str) -> Self {
+        self.dialog = self.dialog.set_file_name(file_name);
+        self
+    }
+
+    /// Asks for a file to open.
+    pub fn pick_file(self) -> Option<PathBuf> {
+        let category = self.category;
+        self.dialog
+            .pick_file()
+            .inspect(|path| remember(category, path))
+    }
+
+    /// Asks where to save a file.
+    pub fn save_file(self) -> Option<PathBuf> {
+        let category = self.category;
+        self.dialog
+            .save_file()
+            .inspect(|path| remember(category, path))
+    }
+}
+
+fn remember(category: DialogCategory, picked: &Path) {
+    let mut recent = crate::lock_mutex!(RECENT);
+    if recent.0.record(category, picked) {
+        tracing::debug!(?category, ?picked, "Remembered the folder of a dialog");
+        recent.1 = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_folders_fall_back_to_documents() {
+        let dir = std::env::temp_dir().join("test_dialogs_fallback");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut recent = RecentDirs::default();
+        assert_eq!(recent.start_dir(DialogCategory::Tmx), fallback_dir());
+
+        assert!(recent.record(DialogCategory::Tmx, &dir.join("history.tmx")));
+        assert_eq!(recent.start_dir(DialogCategory::Tmx), Some(dir.clone()));
+        // The same folder again is no change
+        assert!(!recent.record(DialogCategory::Tmx, &dir.join("other.tmx")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(recent.start_dir(DialogCategory::Tmx), fallback_dir());
+        // A bare file name has no folder to remember
+        assert!(!recent.record(DialogCategory::Tmx, Path::new("history.tmx")));
+    }
+
+    #[test]
+    fn test_categories_keep_their_own_folder() {
+        let temp = std::env::temp_dir();
+        let fonts = temp.join("test_dialogs_fonts");
+        let exports = temp.join("test_dialogs_exports");
+        std::fs::create_dir_all(&fonts).unwrap();
+        std::fs::create_dir_all(&exports).unwrap();
+
+        let mut recent = RecentDirs::default();
+        recent.record(DialogCategory::Font, &fonts.join("Noto.ttf"));
+        recent.record(DialogCategory::DocumentExport, &exports.join("de.json"));
+        assert_eq!(recent.start_dir(DialogCategory::Font), Some(fonts.clone()));
+        assert_eq!(
+            recent.start_dir(DialogCategory::DocumentExport),
+            Some(exports.clone())
+        );
+        assert_eq!(recent.start_dir(DialogCategory::Document), fallback_dir());
+
+        // Stored in the settings by category name
+        let json = serde_json::to_string(&recent).unwrap();
+        assert!(json.contains("\"document_export\":"), "{}", json);
+        assert_eq!(serde_json::from_str::<RecentDirs>(&json).unwrap(), recent);
+
+        let _ = std::fs::remove_dir_all(&fonts);
+        let _ = std::fs::remove_dir_all(&exports);
+    }
+}
@@ -0,0 +1,289 @@
+//! Translations open side by side in tabs.
+//!
+//! Every tab is a document of its own: a source text, a target language
+//! and what the display shows for them. Only the active document is in the
+//! widgets; the others keep their state parked in their [`Document`] until
+//! they are selected again.
+//!
+//! The documents are stored across restarts with their texts, each capped
+//! at [`MAX_STORED_CHARS`].
+
+use crate::utils::tasks;
+use serde::{Deserialize, Serialize};
+
+/// Longest text stored per source text or translation, in characters.
+pub const MAX_STORED_CHARS: usize = 20_000;
+
+/// Key of the documents in the eframe storage.
+const STORAGE_KEY: &str = "documents";
+
+/// Identifies a document while the app runs.
+pub type DocumentId = u64;
+
+/// One tab.
+#[derive(Debug)]
+pub struct Document<P> {
+    pub id: DocumentId,
+    /// Source text, while another tab is active
+    pub source_text: String,
+    /// Target language, while another tab is active
+    pub target_language: String,
+    /// What the tab shows, while another tab is active
+    pub parked: P,
+}
+
+/// Label of a tab with `source_text`: the start of the text.
+pub fn title(source_text: &str) -> String {
+    match tasks::label_text(source_text) {
+        label if label.is_empty() => "New tab".to_string(),
+        label => label,
+    }
+}
+
+/// The open tabs, one of them active.
+#[derive(Debug)]
+pub struct Documents<P> {
+    documents: Vec<Document<P>>,
+    active: usize,
+    next_id: DocumentId,
+}
+
+impl<P: Default> Documents<P> {
+    /// A single tab.
+    pub fn new(source_text: String, target_language: String, parked: P) -> Self {
+        let mut documents = Documents {
+            documents: Vec::new(),
+            active: 0,
+            next_id: 1,
+        };
+        documents.push(source_text, target_language, parked);
+        documents
+    }
+
+    /// Adds a tab at the end, returning its id; it isn't selected.
+    pub fn push(&mut self, source_text: String, target_language: String, parked: P) -> DocumentId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.documents.push(Document {
+            id,
+            source_text,
+            target_language,
+            parked,
+        });
+        id
+    }
+
+    /// Opens an empty tab right after the active one, returning its id;
+    /// it isn't selected.
+    pub fn open(&mut self, target_language: String) -> DocumentId {
+        let id = self.push(String::new(), target_language, P::default());
+        let document = self.documents.pop().expect("just pushed");
+        self.documents.insert(self.active + 1, document);
+        id
+    }
+
+    /// Closes the tab `id`, unless it is the last one.
+    ///
+    /// Closing the active tab activates the one after it, or the one before
+    /// if it was the last.
+    pub fn close(&mut self, id: DocumentId) -> Option<Document<P>> {
+        if self.documents.len() == 1 {
+            return None;
+        }
+        let index = self.index(id)?;
+        let document = self.documents.remove(index);
+        if index < self.active || self.active == self.documents.len() {
+            self.active -= 1;
+        }
+        Some(document)
+    }
+
+    /// Makes `id` the active tab, returning whether there is such a tab.
+    pub fn select(&mut self, id: DocumentId) -> bool {
+        match self.index(id) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The tab after the active one, the first after the last.
+    pub fn next_id(&self) -> DocumentId {
+        self.documents[(self.active + 1) % self.documents.len()].id
+    }
+
+    pub fn active_id(&self) -> DocumentId {
+        self.documents[self.active].id
+    }
+
+    pub fn active_mut(&mut self) -> &mut Document<P> {
+        &mut self.documents[self.active]
+    }
+
+    pub fn get_mut(&mut self, id: DocumentId) -> Option<&mut Document<P>> {
+        self.documents.iter_mut().find(|document| document.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Document<P>> {
+        self.documents.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    fn index(&self, id: DocumentId) -> Option<usize> {
+        self.documents.iter().position(|document| document.id == id)
+    }
+}
+
+/// A tab as stored across restarts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoredDocument {
+    pub source_text: String,
+    pub target_language: String,
+    pub translation: String,
+}
+
+/// The tabs as stored across restarts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoredDocuments {
+    pub documents: Vec<StoredDocument>,
+    /// Index of the active tab
+    pub active: usize,
+}
+
+impl StoredDocuments {
+    /// The tabs saved last, `None` if there are none.
+    pub fn load(storage: &dyn eframe::Storage) -> Option<Self> {
+        let json = storage.get_string(STORAGE_KEY)?;
+        serde_json::from_str::<Self>(&json)
+            .inspect_err(|e| tracing::warn!("Ignoring invalid stored tabs: {}", e))
+            .ok()
+            .filter(|stored| !stored.documents.is_empty())
+    }
+
+    /// Saves the tabs, their texts capped at [`MAX_STORED_CHARS`].
+    pub fn save(self, storage: &mut dyn eframe::Storage) {
+        if let Ok(json) = serde_json::to_string(&self.capped()) {
+            storage.set_string(STORAGE_KEY, json);
+        }
+    }
+
+    /// The tabs with their texts cut at [`MAX_STORED_CHARS`].
+    fn capped(mut self) -> Self {
+        for document in &mut self.documents {
+            for text in [&mut document.source_text, &mut document.translation] {
+                if let Some((end, _)) = text.char_indices().nth(MAX_STORED_CHARS) {
+                    text.truncate(end);
+                }
+            }
+        }
+        self.active = self.active.min(self.documents.len().saturating_sub(1));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn titles(documents: &Documents<()>) -> Vec<String> {
+        documents
+            .iter()
+            .map(|document| title(&document.source_text))
+            .collect()
+    }
+
+    #[test]
+    fn test_open_select_and_close() {
+        let mut documents = Documents::new("First".to_string(), "English".to_string(), ());
+        let first = documents.active_id();
+        let third = documents.open("Deutsch".to_string());
+        // Opened right after the active tab
+        let second = documents.open("Deutsch".to_string());
+        assert_eq!(documents.active_id(), first);
+        assert_eq!(documents.next_id(), second);
+        documents.get_mut(third).unwrap().source_text = "Third".to_string();
+        assert_eq!(titles(&documents), ["First", "New tab", "Third"]);
+
+        assert!(documents.select(third));
+        assert_eq!(documents.next_id(), first);
+        assert!(!documents.select(99));
+
+        // The active tab closed, the one before it takes over at the end
+        assert_eq!(documents.close(third).unwrap().source_text, "Third");
+        assert_eq!(documents.active_id(), second);
+        // Closing a tab before the active one keeps it active
+        documents.close(first);
+        assert_eq!(documents.active_id(), second);
+        // The last tab stays
+        assert!(documents.close(second).is_none());
+        assert_eq!(documents.len(), 1);
+    }
+
+    /// Storage of the tests, in memory.
+    #[derive(Default)]
+    struct Memory(HashMap<String, String>);
+
+    impl eframe::Storage for Memory {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn test_stored_texts_are_capped() {
+        let mut storage = Memory::default();
+        assert_eq!(StoredDocuments::load(&storage), None);
+
+        let long = "ä".repeat(MAX_STORED_CHARS + 10);
+        StoredDocuments {
+            documents: vec![
+                StoredDocument {
+                    source_text: long.clone(),
+                    target_language: "English".to_string(),
+                    translation: "Short".to_string(),
+                },
+                StoredDocument {
+                    source_text: "Hallo".to_string(),
+                    target_language: "English".to_string(),
+                    translation: long,
+                },
+            ],
+            active: 5,
+        }
+        .save(&mut storage);
+
+        let stored = StoredDocuments::load(&storage).unwrap();
+        assert_eq!(stored.active, 1);
+        assert_eq!(
+            stored.documents[0].source_text.chars().count(),
+            MAX_STORED_CHARS
+        );
+        assert_eq!(stored.documents[0].translation, "Short");
+        assert_eq!(
+            stored.documents[1].translation.chars().count(),
+            MAX_STORED_CHARS
+        );
+
+        // Nothing to restore from an empty list
+        StoredDocuments::default().save(&mut storage);
+        assert_eq!(StoredDocuments::load(&storage), None);
+    }
+}
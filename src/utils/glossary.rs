@@ -0,0 +1,501 @@
+//! Mandated translations of terms, per target language.
+//!
+//! The glossary is a small JSON file in the config directory. Each target
+//! language gets a [`TermMatcher`] compiled into one Aho-Corasick automaton
+//! whenever the glossary changes, so finding the terms of a text is a
+//! single pass over it. The same matcher decides which terms the prompt
+//! enforces and which ones the sidebar lists, so the two always agree.
+//!
+//! Other instances of the app may save the same file. Each change checks
+//! that the file is still as it was last read or written, and refuses to
+//! overwrite it otherwise, so the user can reload before editing again.
+
+use crate::utils::paths;
+use crate::utils::script::{Script, script_of};
+use aho_corasick::{AhoCorasick, MatchKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// A source term and the translation it must get.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Term {
+    pub source: String,
+    pub target: String,
+    /// Target language the translation is for
+    pub language: String,
+}
+
+impl Term {
+    /// The term with surrounding whitespace removed from its texts.
+    fn trimmed(self) -> Term {
+        Term {
+            source: self.source.trim().to_string(),
+            target: self.target.trim().to_string(),
+            language: self.language,
+        }
+    }
+
+    /// Whether `other` is a term for the same source text and language.
+    fn same_source(&self, other: &Term) -> bool {
+        self.language == other.language && self.source.eq_ignore_ascii_case(&other.source)
+    }
+}
+
+/// Why the glossary was not changed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum GlossaryError {
+    /// The term lacks a source or a translation
+    #[error("A term needs both a source and a translation")]
+    Incomplete,
+
+    /// Another term of the language has the same source
+    #[error("\"{}\" is already in the glossary as \"{}\"", .0.source, .0.target)]
+    Conflict(Term),
+
+    /// The edited term is no longer in the glossary
+    #[error("The term is no longer in the glossary")]
+    Missing,
+
+    /// The file was saved elsewhere since it was read
+    #[error("The glossary was changed by another window, reload it first")]
+    ChangedOnDisk,
+
+    /// The file could not be written
+    #[error("Could not save the glossary: {0}")]
+    Save(String),
+}
+
+/// What identifies a version of the glossary file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    /// Stamp of `file`, `None` if there is no file.
+    fn of(file: &Path) -> Option<FileStamp> {
+        let metadata = fs::metadata(file).ok()?;
+        Some(FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// A term found in a text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermMatch {
+    pub term: Term,
+    /// Byte ranges of the occurrences, in order
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// Finds the terms of one target language in a text.
+#[derive(Debug, Default)]
+pub struct TermMatcher {
+    automaton: Option<AhoCorasick>,
+    terms: Vec<Term>,
+}
+
+impl TermMatcher {
+    /// Compiles the automaton for `terms`.
+    pub fn new(terms: Vec<Term>) -> Self {
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(terms.iter().map(|term| term.source.as_str()))
+            .inspect_err(|e| tracing::warn!("Failed to compile glossary terms: {}", e))
+            .ok()
+            .filter(|_| !terms.is_empty());
+        TermMatcher { automaton, terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The terms occurring in `text`, in order of their first occurrence.
+    ///
+    /// Only whole words count, so "cat" is not found in "concatenate".
+    /// Letters match regardless of ASCII case.
+    pub fn find(&self, text: &str) -> Vec<TermMatch> {
+        let Some(automaton) = &self.automaton else {
+            return Vec::new();
+        };
+        let mut matches: Vec<TermMatch> = Vec::new();
+        let mut slots: HashMap<usize, usize> = HashMap::new();
+        for found in automaton.find_iter(text) {
+            let range = found.range();
+            if !is_whole_word(text, &range) {
+                continue;
+            }
+            let slot = *slots.entry(found.pattern().as_usize()).or_insert_with(|| {
+                matches.push(TermMatch {
+                    term: self.terms[found.pattern().as_usize()].clone(),
+                    ranges: Vec::new(),
+                });
+                matches.len() - 1
+            });
+            matches[slot].ranges.push(range);
+        }
+        matches
+    }
+
+    /// Source and target of each term in `text`, for the prompt.
+    pub fn prompt_terms(&self, text: &str) -> Vec<(String, String)> {
+        self.find(text)
+            .into_iter()
+            .map(|found| (found.term.source, found.term.target))
+            .collect()
+    }
+}
+
+/// Whether `range` of `text` doesn't start or end inside a word.
+///
+/// Scripts written without spaces have no visible word boundaries, so
+/// their characters never count as continuing a word.
+pub fn is_whole_word(text: &str, range: &Range<usize>) -> bool {
+    let joins = |a: Option<char>, b: Option<char>| match (a, b) {
+        (Some(a), Some(b)) => is_word_char(a) && is_word_char(b),
+        _ => false,
+    };
+    let before = text[..range.start].chars().next_back();
+    let first = text[range.clone()].chars().next();
+    let last = text[range.clone()].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !joins(before, first) && !joins(last, after)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() && !matches!(script_of(c), Some(Script::Cjk | Script::Thai))
+}
+
+/// The terms of all languages, stored in a file.
+pub struct Glossary {
+    terms: Vec<Term>,
+    file: PathBuf,
+    /// The file as last read or written
+    stamp: Option<FileStamp>,
+    /// Compiled matcher per target language
+    matchers: HashMap<String, Arc<TermMatcher>>,
+    empty: Arc<TermMatcher>,
+}
+
+impl Glossary {
+    /// Loads the glossary from `file`, starting empty if it is missing or
+    /// unreadable.
+    pub fn new(file: PathBuf) -> Self {
+        let mut glossary = Glossary {
+            terms: Vec::new(),
+            file,
+            stamp: None,
+            matchers: HashMap::new(),
+            empty: Arc::default(),
+        };
+        glossary.reload();
+        glossary
+    }
+
+    /// Reads the file again, dropping the terms as known so far.
+    pub fn reload(&mut self) {
+        self.stamp = FileStamp::of(&self.file);
+        self.terms = fs::read_to_string(&self.file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        self.compile();
+    }
+
+    /// Whether the file was changed by someone else since it was last
+    /// read or written.
+    pub fn changed_on_disk(&self) -> bool {
+        FileStamp::of(&self.file) != self.stamp
+    }
+
+    /// Returns the default glossary file in the config directory.
+    pub fn default_path() -> PathBuf {
+        paths::config_dir().join("glossary.json")
+    }
+
+    /// The terms for `language`, sorted by source.
+    pub fn terms_for(&self, language: &str) -> Vec<&Term> {
+        let mut terms: Vec<&Term> = self
+            .terms
+            .iter()
+            .filter(|term| term.language == language)
+            .collect();
+        terms.sort_by_key(|term| term.source.to_lowercase());
+        terms
+    }
+
+    /// The matcher for `language`, shared until the glossary changes.
+    pub fn matcher(&self, language: &str) -> Arc<TermMatcher> {
+        self.matchers
+            .get(language)
+            .cloned()
+            .unwrap_or_else(|| self.empty.clone())
+    }
+
+    /// Adds a term, replacing the translation of the same source term.
+    pub fn add(&mut self, term: Term) -> Result<(), GlossaryError> {
+        let term = term.trimmed();
+        if term.source.is_empty() || term.target.is_empty() {
+            return Err(GlossaryError::Incomplete);
+        }
+        let mut terms = self.terms.clone();
+        terms.retain(|existing| !existing.same_source(&term));
+        terms.push(term);
+        self.commit(terms)
+    }
+
+    /// Removes a term.
+    pub fn remove(&mut self, term: &Term) -> Result<(), GlossaryError> {
+        let mut terms = self.terms.clone();
+        terms.retain(|existing| existing != term);
+        self.commit(terms)
+    }
+
+    /// Checks that `original` can be replaced by `edited`, without
+    /// changing anything.
+    ///
+    /// The edited source may not be that of another term of the language,
+    /// which the error names.
+    pub fn check_edit(&self, original: &Term, edited: &Term) -> Result<(), GlossaryError> {
+        let edited = edited.clone().trimmed();
+        if edited.source.is_empty() || edited.target.is_empty() {
+            return Err(GlossaryError::Incomplete);
+        }
+        if !self.terms.contains(original) {
+            return Err(GlossaryError::Missing);
+        }
+        match self
+            .terms
+            .iter()
+            .find(|existing| *existing != original && existing.same_source(&edited))
+        {
+            Some(conflict) => Err(GlossaryError::Conflict(conflict.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces `original` by `edited`, keeping its place.
+    pub fn update(&mut self, original: &Term, edited: Term) -> Result<(), GlossaryError> {
+        self.check_edit(original, &edited)?;
+        let mut terms = self.terms.clone();
+        if let Some(term) = terms.iter_mut().find(|term| *term == original) {
+            *term = edited.trimmed();
+        }
+        self.commit(terms)
+    }
+
+    /// Saves `terms` and makes them the glossary's, unless the file was
+    /// changed elsewhere or can't be written.
+    fn commit(&mut self, terms: Vec<Term>) -> Result<(), GlossaryError> {
+        if self.changed_on_disk() {
+            tracing::warn!("Glossary file changed on disk, not overwriting it");
+            return Err(GlossaryError::ChangedOnDisk);
+        }
+        self.save(&terms)?;
+        self.stamp = FileStamp::of(&self.file);
+        self.terms = terms;
+        self.compile();
+        Ok(())
+    }
+
+    fn compile(&mut self) {
+        let mut by_language: HashMap<String, Vec<Term>> = HashMap::new();
+        for term in &self.terms {
+            by_language
+                .entry(term.language.clone())
+                .or_default()
+                .push(term.clone());
+        }
+        self.matchers = by_language
+            .into_iter()
+            .map(|(language, terms)| (language, Arc::new(TermMatcher::new(terms))))
+            .collect();
+    }
+
+    /// Writes `terms` to disk, through a temporary file.
+    fn save(&self, terms: &[Term]) -> Result<(), GlossaryError> {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = self.file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temp_file = self.file.with_extension("json.tmp");
+            fs::write(&temp_file, serde_json::to_string_pretty(terms)?)?;
+            fs::rename(&temp_file, &self.file)
+        };
+        write().map_err(|e| {
+            tracing::warn!("Failed to save glossary: {}", e);
+            GlossaryError::Save(e.to_string())
+        })
+    }
+}
+
+impl Default for Glossary {
+    fn default() -> Self {
+        Self::new(Self::default_path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(source: &str, target: &str) -> Term {
+        Term {
+            source: source.to_string(),
+            target: target.to_string(),
+            language: "German".to_string(),
+        }
+    }
+
+    fn temp_glossary(name: &str) -> Glossary {
+        let file = std::env::temp_dir().join(format!("test_glossary_{}.json", name));
+        let _ = fs::remove_file(&file);
+        Glossary::new(file)
+    }
+
+    #[test]
+    fn test_find_whole_words_ignoring_case() {
+        let matcher = TermMatcher::new(vec![
+            term("cat", "Katze"),
+            term("load balancer", "Lastverteiler"),
+            term("load", "Last"),
+        ]);
+        let text = "A Load Balancer, a cat and concatenated cats. The cat's load.";
+        let found = matcher.find(text);
+        let sources: Vec<&str> = found.iter().map(|m| m.term.source.as_str()).collect();
+        assert_eq!(sources, ["load balancer", "cat", "load"]);
+        assert_eq!(&text[found[0].ranges[0].clone()], "Load Balancer");
+        // "concatenated" and "cats" are other words
+        assert_eq!(found[1].ranges.len(), 2);
+        assert_eq!(found[2].ranges.len(), 1);
+
+        assert_eq!(
+            matcher.prompt_terms("cat"),
+            vec![("cat".to_string(), "Katze".to_string())]
+        );
+        assert!(TermMatcher::default().find(text).is_empty());
+    }
+
+    #[test]
+    fn test_find_in_text_without_spaces() {
+        let matcher = TermMatcher::new(vec![term("机器学习", "maschinelles Lernen")]);
+        assert_eq!(matcher.find("我研究机器学习。")[0].ranges, vec![9..21]);
+    }
+
+    #[test]
+    fn test_glossary_is_saved_and_compiled_per_language() {
+        let mut glossary = temp_glossary("saved");
+        glossary.add(term(" API ", "Schnittstelle")).unwrap();
+        glossary
+            .add(Term {
+                language: "French".to_string(),
+                ..term("API", "interface")
+            })
+            .unwrap();
+        // Replaces the translation of the same source term
+        glossary.add(term("api", "API")).unwrap();
+        assert!(glossary.add(term("empty", " ")).is_err());
+
+        let german = glossary.matcher("German");
+        assert_eq!(german.prompt_terms("The API")[0].1, "API");
+        assert!(glossary.matcher("Japanese").is_empty());
+        // Compiled once per change, not per lookup
+        assert!(Arc::ptr_eq(&german, &glossary.matcher("German")));
+
+        let reloaded = Glossary::new(glossary.file.clone());
+        assert_eq!(reloaded.terms_for("German"), vec![&term("api", "API")]);
+        assert_eq!(reloaded.terms_for("French").len(), 1);
+
+        glossary.remove(&term("api", "API")).unwrap();
+        assert!(glossary.matcher("German").is_empty());
+        let _ = fs::remove_file(&glossary.file);
+    }
+
+    #[test]
+    fn test_edit_is_validated_and_saved_in_place() {
+        let mut glossary = temp_glossary("edit");
+        glossary.add(term("API", "Schnittstelle")).unwrap();
+        glossary.add(term("cat", "Katze")).unwrap();
+        let original = term("cat", "Katze");
+
+        assert_eq!(
+            glossary.update(&original, term("cat", " ")),
+            Err(GlossaryError::Incomplete)
+        );
+        // Names the term it would clash with
+        let clash = glossary.update(&original, term("api", "Katze"));
+        assert_eq!(
+            clash,
+            Err(GlossaryError::Conflict(term("API", "Schnittstelle")))
+        );
+        assert!(clash.unwrap_err().to_string().contains("Schnittstelle"));
+        assert_eq!(glossary.terms_for("German")[1], &original);
+        // The same source term in another language doesn't clash
+        let french = Term {
+            language: "French".to_string(),
+            ..term("API", "interface")
+        };
+        glossary.add(french).unwrap();
+
+        // Changing only the case of its own source is fine
+        glossary
+            .update(&original, term("Cat ", "Hauskatze"))
+            .unwrap();
+        assert_eq!(
+            glossary.update(&original, term("cat", "Mieze")),
+            Err(GlossaryError::Missing)
+        );
+        assert_eq!(
+            glossary.matcher("German").prompt_terms("cat")[0].1,
+            "Hauskatze"
+        );
+        let reloaded = Glossary::new(glossary.file.clone());
+        assert_eq!(
+            reloaded.terms_for("German"),
+            vec![&term("API", "Schnittstelle"), &term("Cat", "Hauskatze")]
+        );
+        let _ = fs::remove_file(&glossary.file);
+    }
+
+    #[test]
+    fn test_changes_from_another_instance_are_not_overwritten() {
+        let mut glossary = temp_glossary("concurrent");
+        glossary.add(term("cat", "Katze")).unwrap();
+        assert!(!glossary.changed_on_disk());
+
+        let mut other = Glossary::new(glossary.file.clone());
+        other.add(term("load balancer", "Lastverteiler")).unwrap();
+        assert!(glossary.changed_on_disk());
+
+        let original = term("cat", "Katze");
+        assert_eq!(
+            glossary.update(&original, term("cat", "Hauskatze")),
+            Err(GlossaryError::ChangedOnDisk)
+        );
+        assert_eq!(
+            glossary.add(term("dog", "Hund")),
+            Err(GlossaryError::ChangedOnDisk)
+        );
+        // Nothing changed in memory either
+        assert_eq!(glossary.terms_for("German"), vec![&original]);
+
+        glossary.reload();
+        assert_eq!(glossary.terms_for("German").len(), 2);
+        glossary
+            .update(&original, term("cat", "Hauskatze"))
+            .unwrap();
+        other.reload();
+        assert_eq!(other.terms_for("German")[0].target, "Hauskatze");
+        let _ = fs::remove_file(&glossary.file);
+    }
+}
@@ -0,0 +1,177 @@
+//! Glyph coverage of translated text.
+//!
+//! Scripts without a glyph in the loaded fonts render as empty boxes. The
+//! coverage check counts such characters so the UI can offer to load a font
+//! that has them.
+
+use egui::{Context, FontId};
+
+/// Share of missing characters above which coverage counts as poor.
+const POOR_COVERAGE_RATIO: f64 = 0.02;
+
+/// Characters of a text that the fonts can't display.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlyphCoverage {
+    /// Number of visible characters checked
+    pub checked: usize,
+    /// Number of those characters without a glyph
+    pub missing: usize,
+    /// Scripts of the missing characters, in order of appearance
+    pub scripts: Vec<&'static str>,
+}
+
+impl GlyphCoverage {
+    /// Whether enough characters are missing to make the text hard to read.
+    pub fn is_poor(&self) -> bool {
+        self.checked > 0 && self.missing as f64 / self.checked as f64 > POOR_COVERAGE_RATIO
+    }
+
+    /// Banner text describing what can't be displayed.
+    pub fn summary(&self) -> String {
+        let what = if self.scripts.is_empty() {
+            "Some characters".to_string()
+        } else {
+            format!("{} characters", self.scripts.join(", "))
+        };
+        format!(
+            "{} can't be displayed with the installed fonts ({} of {} missing).",
+            what, self.missing, self.checked
+        )
+    }
+}
+
+/// Name of the script `c` belongs to, for the scripts that are most often
+/// missing from the bundled fonts.
+fn script_name(c: char) -> Option<&'static str> {
+    let name = match c as u32 {
+        0x0530..=0x058F => "Armenian",
+        0x0590..=0x05FF => "Hebrew",
+        0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => "Arabic",
+        0x0700..=0x074F => "Syriac",
+        0x0780..=0x07BF => "Thaana",
+        0x0900..=0x097F => "Devanagari",
+        0x0980..=0x09FF => "Bengali",
+        0x0A00..=0x0A7F => "Gurmukhi",
+        0x0A80..=0x0AFF => "Gujarati",
+        0x0B00..=0x0B7F => "Odia",
+        0x0B80..=0x0BFF => "Tamil",
+        0x0C00..=0x0C7F => "Telugu",
+        0x0C80..=0x0CFF => "Kannada",
+        0x0D00..=0x0D7F => "Malayalam",
+        0x0D80..=0x0DFF => "Sinhala",
+        0x0E00..=0x0E7F => "Thai",
+        0x0E80..=0x0EFF => "Lao",
+        0x0F00..=0x0FFF => "Tibetan",
+        0x1000..=0x109F => "Myanmar",
+        0x10A0..=0x10FF => "Georgian",
+        0x1200..=0x139F => "Ethiopic",
+        0x1780..=0x17FF => "Khmer",
+        0x1800..=0x18AF => "Mongolian",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Checks which characters of `text` have no glyph.
+///
+/// Whitespace and control characters are not checked.
+pub fn check_coverage(text: &str, mut has_glyph: impl FnMut(char) -> bool) -> GlyphCoverage {
+    let mut coverage = GlyphCoverage::default();
+    for c in text.chars() {
+        if c.is_whitespace() || c.is_control() {
+            continue;
+        }
+        coverage.checked += 1;
+        if has_glyph(c) {
+            continue;
+        }
+        coverage.missing += 1;
+        if let Some(script) = script_name(c)
+            && !coverage.scripts.contains(&script)
+        {
+            coverage.scripts.push(script);
+        }
+    }
+    coverage
+}
+
+/// Checks `text` against the fonts loaded in `ctx` for `font_id`.
+///
+/// The fonts only exist once the first frame has started.
+pub fn font_coverage(ctx: &Context, font_id: &FontId, text: &str) -> GlyphCoverage {
+    ctx.fonts_mut(|fonts| check_coverage(text, |c| fonts.has_glyph(font_id, c)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{FontDefinitions, FontFamily, RawInput};
+
+    /// Pretends only Latin and CJK have glyphs.
+    fn latin_and_cjk(c: char) -> bool {
+        c.is_ascii() || ('\u{4E00}'..='\u{9FFF}').contains(&c) || "。，".contains(c)
+    }
+
+    #[test]
+    fn test_full_coverage() {
+        let coverage = check_coverage("Hello, 你好。", latin_and_cjk);
+        assert_eq!(coverage.checked, 9);
+        assert_eq!(coverage.missing, 0);
+        assert!(!coverage.is_poor());
+    }
+
+    #[test]
+    fn test_missing_script_is_poor() {
+        let coverage = check_coverage("สวัสดีครับ means hello", latin_and_cjk);
+        assert_eq!(coverage.missing, 10);
+        assert_eq!(coverage.scripts, vec!["Thai"]);
+        assert!(coverage.is_poor());
+        assert_eq!(
+            coverage.summary(),
+            "Thai characters can't be displayed with the installed fonts (10 of 20 missing)."
+        );
+    }
+
+    #[test]
+    fn test_scripts_are_listed_once_in_order() {
+        let coverage = check_coverage("नमस्ते ሰላም नमस्ते", latin_and_cjk);
+        assert_eq!(coverage.scripts, vec!["Devanagari", "Ethiopic"]);
+    }
+
+    #[test]
+    fn test_whitespace_is_not_checked() {
+        let coverage = check_coverage(" \t\n\u{00A0}", |_| false);
+        assert_eq!(coverage.checked, 0);
+        assert!(!coverage.is_poor());
+    }
+
+    #[test]
+    fn test_font_coverage_of_a_context() {
+        // Only the monospace font left, for both families
+        let mut fonts = FontDefinitions::default();
+        let monospace = fonts.families[&FontFamily::Monospace][..1].to_vec();
+        fonts.families.insert(FontFamily::Proportional, monospace);
+        let ctx = Context::default();
+        ctx.set_fonts(fonts);
+        let _ = ctx.run(RawInput::default(), |_| {});
+
+        let font_id = FontId::proportional(14.0);
+        let latin = font_coverage(&ctx, &font_id, "Settings → Translate");
+        assert_eq!(latin.missing, 0);
+        let thai = font_coverage(&ctx, &font_id, "การตั้งค่า");
+        assert!(thai.is_poor());
+        assert_eq!(thai.scripts, vec!["Thai"]);
+        assert_eq!(thai.missing, thai.checked);
+    }
+
+    #[test]
+    fn test_a_stray_symbol_is_not_poor() {
+        let mut text = "a".repeat(99);
+        text.push('\u{1F9FF}');
+        let coverage = check_coverage(&text, latin_and_cjk);
+        assert_eq!(coverage.missing, 1);
+        assert!(coverage.scripts.is_empty());
+        assert!(!coverage.is_poor());
+        assert!(coverage.summary().starts_with("Some characters"));
+    }
+}
@@ -0,0 +1,559 @@
+//! Translation history, read back from the translation log.
+//!
+//! Searching runs on a background thread, newest entries first. Matches are
+//! sent back in batches as they are found, so the first results show up
+//! right away even in a history of thousands of entries. Every search gets a
+//! generation number; a search superseded by a newer query stops, and its
+//! remaining batches are discarded.
+
+use crate::utils::provenance::Provenance;
+use crate::utils::query::{self, Query};
+use chrono::NaiveDateTime;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Entries searched between checks for a newer query.
+const BATCH_SIZE: usize = 256;
+
+/// Line that ends every entry in the log.
+fn separator() -> String {
+    format!("{}\n", "-".repeat(80))
+}
+
+/// One translation from the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub timestamp: NaiveDateTime,
+    /// As logged, "Auto-detected" unless the user picked one
+    pub source_language: String,
+    pub target_language: String,
+    pub source_text: String,
+    pub translation: String,
+    /// What the translation was made with, unknown for older entries
+    pub provenance: Option<Provenance>,
+}
+
+/// Parses one entry as written by [`Logger::log`](crate::utils::logger::Logger::log).
+///
+/// The source text ends at the first line starting with "Translation: ", so
+/// a source text containing such a line is cut short.
+fn parse_entry(entry: &str) -> Option<HistoryEntry> {
+    let (first_line, rest) = entry.split_once('\n')?;
+    let timestamp = first_line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S").ok())?;
+
+    // Header lines up to the source text, which may span several lines
+    let mut source_language = String::new();
+    let mut target_language = String::new();
+    let mut provenance = Provenance::default();
+    let mut has_provenance = false;
+    let mut rest = rest;
+    loop {
+        if let Some(text) = rest.strip_prefix("Source Text: ") {
+            rest = text;
+            break;
+        }
+        let (line, next) = rest.split_once('\n')?;
+        if let Some(language) = line.strip_prefix("Source Language: ") {
+            source_language = language.to_string();
+        } else if let Some(language) = line.strip_prefix("Target Language: ") {
+            target_language = language.to_string();
+        } else if provenance.read_header(line) {
+            has_provenance = true;
+        }
+        rest = next;
+    }
+
+    let (source_text, translation) = rest.split_once("\nTranslation: ")?;
+    Some(HistoryEntry {
+        timestamp,
+        source_language,
+        target_language,
+        source_text: source_text.to_string(),
+        translation: translation
+            .strip_suffix('\n')
+            .unwrap_or(translation)
+            .to_string(),
+        provenance: has_provenance.then_some(provenance),
+    })
+}
+
+/// Parses the contents of a translation log, oldest entry first.
+///
+/// Entries that can't be parsed are skipped.
+pub fn parse_log(content: &str) -> Vec<HistoryEntry> {
+    content
+        .split(&separator())
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(parse_entry)
+        .collect()
+}
+
+/// Reads the history from the translation log at `path`.
+pub fn load(path: &Path) -> std::io::Result<Vec<HistoryEntry>> {
+    Ok(parse_log(&std::fs::read_to_string(path)?))
+}
+
+/// An entry matching a query, with the matched fragments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryHit {
+    pub entry: HistoryEntry,
+    /// Byte ranges of the matches in the source text
+    pub source_matches: Vec<Range<usize>>,
+    /// Byte ranges of the matches in the translation
+    pub translation_matches: Vec<Range<usize>>,
+}
+
+impl HistoryHit {
+    /// Number of matched fragments.
+    pub fn match_count(&self) -> usize {
+        self.source_matches.len() + self.translation_matches.len()
+    }
+}
+
+/// Checks `entry` against `query`.
+///
+/// Every term has to appear in the source text or in the translation.
+pub fn match_entry(entry: &HistoryEntry, query: &Query) -> Option<HistoryHit> {
+    if !query.accepts(entry.timestamp.date(), &entry.target_language)
+        || !query.accepts_provenance(entry.provenance.as_ref())
+    {
+        return None;
+    }
+
+    let mut source_matches = Vec::new();
+    let mut translation_matches = Vec::new();
+    for term in &query.terms {
+        let in_source = query::find_all(&entry.source_text, term);
+        let in_translation = query::find_all(&entry.translation, term);
+        if in_source.is_empty() && in_translation.is_empty() {
+            return None;
+        }
+        source_matches.extend(in_source);
+        translation_matches.extend(in_translation);
+    }
+
+    Some(HistoryHit {
+        entry: entry.clone(),
+        source_matches: merge_ranges(source_matches),
+        translation_matches: merge_ranges(translation_matches),
+    })
+}
+
+/// Sorts `ranges` and joins the overlapping ones.
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Searches `entries` newest first, handing over the hits of every
+/// `batch_size` entries to `emit`.
+///
+/// Stops early once `emit` returns `false`.
+pub fn search_entries(
+    entries: &[HistoryEntry],
+    query: &Query,
+    batch_size: usize,
+    mut emit: impl FnMut(Vec<HistoryHit>) -> bool,
+) {
+    let newest_first: Vec<&HistoryEntry> = entries.iter().rev().collect();
+    for batch in newest_first.chunks(batch_size) {
+        let hits: Vec<HistoryHit> = batch
+            .iter()
+            .filter_map(|entry| match_entry(entry, query))
+            .collect();
+        if !emit(hits) {
+            return;
+        }
+    }
+}
+
+/// A single line of `text` around its first match, for a result list.
+///
+/// Line breaks become spaces and cut off ends are marked with "…".
+///
+/// # Returns
+///
+/// The preview and the byte ranges of the matches within it
+pub fn preview(
+    text: &str,
+    matches: &[Range<usize>],
+    max_chars: usize,
+) -> (String, Vec<Range<usize>>) {
+    const CONTEXT_CHARS: usize = 20;
+
+    let first_match = matches.first().map_or(0, |range| range.start);
+    let start = text[..first_match]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS.saturating_sub(1))
+        .map_or(0, |(i, _)| i);
+    let end = text[start..]
+        .char_indices()
+        .nth(max_chars)
+        .map_or(text.len(), |(i, _)| start + i);
+
+    let mut line = String::new();
+    if start > 0 {
+        line.push('…');
+    }
+    let shift = line.len();
+    line.extend(
+        text[start..end]
+            .chars()
+            .map(|c| if c == '\n' || c == '\r' { ' ' } else { c }),
+    );
+    if end < text.len() {
+        line.push('…');
+    }
+
+    let ranges = matches
+        .iter()
+        .filter(|range| range.start < end && range.end > start)
+        .map(|range| range.start.max(start) - start + shift..range.end.min(end) - start + shift)
+        .collect();
+    (line, ranges)
+}
+
+/// Hits found by a search since the last poll.
+#[derive(Debug)]
+pub struct SearchBatch {
+    pub generation: u64,
+    pub hits: Vec<HistoryHit>,
+    /// Whether the whole history has been searched
+    pub done: bool,
+}
+
+/// Background search over the translation log.
+pub struct HistorySearch {
+    generation: Arc<AtomicU64>,
+    tx: Sender<SearchBatch>,
+    rx: Receiver<SearchBatch>,
+}
+
+impl Default for HistorySearch {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        HistorySearch {
+            generation: Arc::new(AtomicU64::new(0)),
+            tx,
+            rx,
+        }
+    }
+}
+
+impl HistorySearch {
+    /// Starts searching the log at `log_path` for `query`, superseding
+    /// earlier searches.
+    ///
+    /// The log is read again for every search, so new translations show up.
+    /// Returns the generation of the new search.
+    pub fn search(&self, log_path: PathBuf, query: Query) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let tx = self.tx.clone();
+        let spawned = std::thread::Builder::new()
+            .name("history-search".to_string())
+            .spawn(move || {
+                let entries = load(&log_path).unwrap_or_else(|e| {
+                    tracing::warn!("Failed to read the translation history: {}", e);
+                    Vec::new()
+                });
+                let mut sent = true;
+                search_entries(&entries, &query, BATCH_SIZE, |hits| {
+                    // A newer query arrived, stop working on this one
+                    if current.load(Ordering::SeqCst) != generation {
+                        sent = false;
+                        return false;
+                    }
+                    if hits.is_empty() {
+                        return true;
+                    }
+                    sent = tx
+                        .send(SearchBatch {
+                            generation,
+                            hits,
+                            done: false,
+                        })
+                        .is_ok();
+                    sent
+                });
+                if sent {
+                    let _ = tx.send(SearchBatch {
+                        generation,
+                        hits: Vec::new(),
+                        done: true,
+                    });
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("Failed to spawn history search thread: {}", e);
+        }
+
+        generation
+    }
+
+    /// Returns the batches that arrived since the last call.
+    pub fn poll(&self) -> Vec<SearchBatch> {
+        self.rx.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::prompt::PromptContext;
+    use crate::utils::logger::Logger;
+
+    fn entry(day: u32, language: &str, source: &str, translation: &str) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: chrono::NaiveDate::from_ymd_opt(2024, 6, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            source_language: "Auto-detected".to_string(),
+            target_language: language.to_string(),
+            source_text: source.to_string(),
+            translation: translation.to_string(),
+            provenance: None,
+        }
+    }
+
+    fn history() -> Vec<HistoryEntry> {
+        vec![
+            HistoryEntry {
+                provenance: Some(Provenance {
+                    model: "deepseek-chat".to_string(),
+                    template: "Standard".to_string(),
+                    app_version: "1.4.0".to_string(),
+                    ..Provenance::default()
+                }),
+                ..entry(
+                    1,
+                    "Deutsch",
+                    "The bank is closed",
+                    "Die Bank ist geschlossen",
+                )
+            },
+            entry(2, "中文", "I went to the bank", "我去了银行"),
+            entry(3, "中文", "River bank", "河岸"),
+            entry(4, "English", "Guten Morgen", "Good morning"),
+        ]
+    }
+
+    #[test]
+    fn test_parse_logged_entries() {
+        let dir = std::env::temp_dir().join("test_history_parse");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("translations.log");
+
+        let logger = Logger::new(path.to_str().unwrap()).unwrap();
+        let context = PromptContext {
+            domain: "Legal".to_string(),
+            ..PromptContext::default()
+        };
+        let provenance = Provenance {
+            model: "deepseek-chat".to_string(),
+            template: "Standard".to_string(),
+            glossary_hash: "0123456789abcdef".to_string(),
+            app_version: "1.4.0".to_string(),
+            options: "thinking=disabled, domain=Legal".to_string(),
+        };
+        logger.log(
+            "Auto-detected",
+            "Deutsch",
+            "Hello",
+            "Hallo",
+            "disabled",
+            &context,
+            Some(&provenance),
+            None,
+        );
+        logger.log(
+            "Auto-detected",
+            "中文",
+            "First line\nSecond line",
+            "第一行\n第二行",
+            "enabled",
+            &PromptContext::default(),
+            None,
+            Some("Is \"Source Text: \" a header?\nTranslation: no"),
+        );
+        logger.flush();
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].source_language, "Auto-detected");
+        assert_eq!(entries[0].target_language, "Deutsch");
+        assert_eq!(entries[0].source_text, "Hello");
+        assert_eq!(entries[0].translation, "Hallo");
+        assert_eq!(entries[0].provenance, Some(provenance));
+        assert_eq!(entries[1].source_text, "First line\nSecond line");
+        assert_eq!(entries[1].translation, "第一行\n第二行");
+        assert_eq!(entries[1].provenance, None);
+        // The reasoning is logged on a line of its own, apart from the translation
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            log.contains("Reasoning: \"Is \\\"Source Text: \\\" a header?\\nTranslation: no\"\n")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_skips_broken_entries() {
+        let sep = separator();
+        let log = format!(
+            "garbage\n{sep}[2024-06-01 10:00:00]\nTarget Language: Deutsch\nSource Text: a\nTranslation: b\n{sep}"
+        );
+        let entries = parse_log(&log);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].target_language, "Deutsch");
+        assert_eq!(entries[0].source_text, "a");
+        assert_eq!(entries[0].translation, "b");
+    }
+
+    #[test]
+    fn test_match_source_and_translation() {
+        let history = history();
+        let hit = match_entry(&history[0], &Query::parse("BANK")).unwrap();
+        assert_eq!(hit.source_matches, vec![4..8]);
+        assert_eq!(hit.translation_matches, vec![4..8]);
+        assert_eq!(hit.match_count(), 2);
+
+        // Terms may be found in different texts, but all must be found
+        assert!(match_entry(&history[1], &Query::parse("went 银行")).is_some());
+        assert!(match_entry(&history[1], &Query::parse("went river")).is_none());
+    }
+
+    #[test]
+    fn test_match_qualifiers() {
+        let history = history();
+        let query = Query::parse("lang:中文 bank before:2024-06-03");
+        let matching: Vec<usize> = (0..history.len())
+            .filter(|&i| match_entry(&history[i], &query).is_some())
+            .collect();
+        assert_eq!(matching, vec![1]);
+
+        // Qualifiers alone match without highlights
+        let hit = match_entry(&history[3], &Query::parse("after:2024-06-04")).unwrap();
+        assert_eq!(hit.match_count(), 0);
+
+        // Provenance qualifiers skip entries that don't record any
+        let matching: Vec<usize> = (0..history.len())
+            .filter(|&i| match_entry(&history[i], &Query::parse("model:deepseek bank")).is_some())
+            .collect();
+        assert_eq!(matching, vec![0]);
+        assert!(match_entry(&history[0], &Query::parse("version:1.3")).is_none());
+    }
+
+    #[test]
+    fn test_overlapping_terms_are_merged() {
+        let history = history();
+        let hit = match_entry(&history[0], &Query::parse("bank \"bank is\" closed")).unwrap();
+        assert_eq!(hit.source_matches, vec![4..11, 12..18]);
+    }
+
+    #[test]
+    fn test_search_in_batches_newest_first() {
+        let history = history();
+        let mut batches = Vec::new();
+        search_entries(&history, &Query::parse("bank"), 2, |hits| {
+            batches.push(
+                hits.iter()
+                    .map(|hit| hit.entry.timestamp.format("%d").to_string())
+                    .collect::<Vec<_>>(),
+            );
+            true
+        });
+        assert_eq!(batches, vec![vec!["03"], vec!["02", "01"]]);
+
+        // Stopping after the first batch
+        let mut calls = 0;
+        search_entries(&history, &Query::default(), 1, |_| {
+            calls += 1;
+            false
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_preview_around_first_match() {
+        let text = format!("{}needle\nand more text after it", "x".repeat(100));
+        let matches = query::find_all(&text, "needle");
+        let (line, ranges) = preview(&text, &matches, 40);
+        assert!(line.starts_with('…'));
+        assert!(line.ends_with('…'));
+        assert!(!line.contains('\n'));
+        assert_eq!(&line[ranges[0].clone()], "needle");
+    }
+
+    #[test]
+    fn test_preview_of_short_text() {
+        let text = "银行 bank";
+        let (line, ranges) = preview(text, &query::find_all(text, "bank"), 40);
+        assert_eq!(line, text);
+        assert_eq!(ranges, vec![7..11]);
+
+        let (line, ranges) = preview("", &[], 40);
+        assert_eq!(line, "");
+        assert!(ranges.is_empty());
+    }
+
+    #[test]
+    fn test_background_search() {
+        let dir = std::env::temp_dir().join("test_history_search");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("translations.log");
+        let logger = Logger::new(path.to_str().unwrap()).unwrap();
+        for i in 0..250 {
+            let source = if i % 50 == 0 { "bank" } else { "other" };
+            logger.log(
+                "Auto-detected",
+                "Deutsch",
+                source,
+                "x",
+                "disabled",
+                &PromptContext::default(),
+                None,
+                None,
+            );
+        }
+        logger.flush();
+
+        let search = HistorySearch::default();
+        // Superseded right away, its results are ignored
+        search.search(path.clone(), Query::parse("other"));
+        let generation = search.search(path.clone(), Query::parse("bank"));
+
+        let mut hits = Vec::new();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        'poll: while std::time::Instant::now() < deadline {
+            for batch in search.poll() {
+                if batch.generation != generation {
+                    continue;
+                }
+                hits.extend(batch.hits);
+                if batch.done {
+                    break 'poll;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(hits.len(), 5);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
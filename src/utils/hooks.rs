@@ -0,0 +1,435 @@
+//! External command run after each successful translation.
+//!
+//! The command is a template such as
+//! `sh ~/bin/append.sh {source_file} {translation_file} {target_lang}`. The
+//! source text and the translation are written to temporary files, the
+//! placeholders are replaced by their paths and the target language, and
+//! the command is started directly, not through a shell, so nothing in the
+//! texts or the language name is ever interpreted as shell syntax. Its
+//! standard error is captured for the log, and it is killed once it runs
+//! longer than the timeout. The temporary files are removed afterwards.
+
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Placeholder for the path of the file holding the source text.
+pub const SOURCE_FILE: &str = "{source_file}";
+/// Placeholder for the path of the file holding the translation.
+pub const TRANSLATION_FILE: &str = "{translation_file}";
+/// Placeholder for the target language.
+pub const TARGET_LANG: &str = "{target_lang}";
+
+/// Bytes of standard error kept, from the end.
+const MAX_STDERR_BYTES: usize = 16 * 1024;
+
+/// How often a running command is checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Numbers the temporary folders of the runs of this process.
+static RUNS: AtomicU64 = AtomicU64::new(0);
+
+/// Why a hook run failed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HookError {
+    /// The command template can't be split into a program and arguments
+    #[error("The hook command is invalid: {0}")]
+    InvalidCommand(String),
+
+    /// The texts could not be written for the command
+    #[error("Could not write the texts for the hook: {0}")]
+    Files(String),
+
+    /// The program could not be started
+    #[error("Could not start {program}: {reason}")]
+    Start { program: String, reason: String },
+
+    /// The command ran longer than the timeout and was killed
+    #[error("The hook did not finish within {} s and was stopped", .0.as_secs_f32())]
+    TimedOut(Duration),
+
+    /// The command exited with an error
+    #[error("The hook exited with {status}{}", last_line(stderr))]
+    Failed { status: String, stderr: String },
+}
+
+/// `: ` and the last line of `stderr`, for a short error message.
+fn last_line(stderr: &str) -> String {
+    stderr
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| format!(": {}", line.trim()))
+        .unwrap_or_default()
+}
+
+/// Splits a command template into words at whitespace outside quotes.
+///
+/// Single and double quotes group words and are removed; there are no
+/// escapes, so a path with spaces is written as `"C:\My Scripts\hook.cmd"`.
+pub fn split_command(template: &str) -> Result<Vec<String>, HookError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    for c in template.chars() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => word.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            None => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if let Some(open) = quote {
+        return Err(HookError::InvalidCommand(format!(
+            "{} is never closed",
+            open
+        )));
+    }
+    if in_word {
+        words.push(word);
+    }
+    if words.is_empty() {
+        return Err(HookError::InvalidCommand("no program given".to_string()));
+    }
+    Ok(words)
+}
+
+/// A command run after each successful translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionHook {
+    /// Command template with placeholders
+    pub command: String,
+    /// How long the command may run before it is killed
+    pub timeout: Duration,
+}
+
+/// Temporary folder with the texts of one run, removed when dropped.
+struct RunFiles {
+    dir: PathBuf,
+    source: PathBuf,
+    translation: PathBuf,
+}
+
+impl RunFiles {
+    fn write(source: &str, translation: &str) -> std::io::Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "ai-translate-hook-{}-{}",
+            std::process::id(),
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let files = RunFiles {
+            source: dir.join("source.txt"),
+            translation: dir.join("translation.txt"),
+            dir,
+        };
+        fs::write(&files.source, source)?;
+        fs::write(&files.translation, translation)?;
+        Ok(files)
+    }
+}
+
+impl Drop for RunFiles {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            tracing::warn!("Failed to remove {:?}: {}", self.dir, e);
+        }
+    }
+}
+
+impl CompletionHook {
+    /// The program and arguments run for a translation into `target_lang`,
+    /// with the texts in `source_file` and `translation_file`.
+    fn argv(
+        &self,
+        source_file: &str,
+        translation_file: &str,
+        target_lang: &str,
+    ) -> Result<Vec<String>, HookError> {
+        Ok(split_command(&self.command)?
+            .into_iter()
+            .map(|word| {
+                word.replace(SOURCE_FILE, source_file)
+                    .replace(TRANSLATION_FILE, translation_file)
+                    .replace(TARGET_LANG, target_lang)
+            })
+            .collect())
+    }
+
+    /// Runs the command for a finished translation and waits for it.
+    ///
+    /// Returns what the command wrote to standard error, for the log.
+    pub fn run(
+        &self,
+        source: &str,
+        translation: &str,
+        target_lang: &str,
+    ) -> Result<String, HookError> {
+        let files =
+            RunFiles::write(source, translation).map_err(|e| HookError::Files(e.to_string()))?;
+        let argv = self.argv(
+            &files.source.to_string_lossy(),
+            &files.translation.to_string_lossy(),
+            target_lang,
+        )?;
+        let (program, args) = argv.split_first().expect("split_command returns a word");
+
+        let started = Instant::now();
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| HookError::Start {
+                program: program.clone(),
+                reason: e.to_string(),
+            })?;
+        // Read on a thread of its own, so a chatty command can't fill the
+        // pipe and block while it is waited for
+        let stderr = child.stderr.take().map(|mut pipe| {
+            std::thread::spawn(move || {
+                let mut output = Vec::new();
+                let _ = pipe.read_to_end(&mut output);
+                let start = output.len().saturating_sub(MAX_STDERR_BYTES);
+                String::from_utf8_lossy(&output[start..]).into_owned()
+            })
+        });
+
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if started.elapsed() >= self.timeout => {
+                    tracing::warn!(program, "Completion hook timed out, stopping it");
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(HookError::TimedOut(self.timeout));
+                }
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    return Err(HookError::Start {
+                        program: program.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        };
+        let stderr = stderr
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        tracing::info!(
+            program,
+            status = %status,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            stderr = stderr.trim_end(),
+            "Completion hook finished"
+        );
+        if status.success() {
+            Ok(stderr)
+        } else {
+            Err(HookError::Failed {
+                status: status.to_string(),
+                stderr,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command("sh  'my hook.sh' {source_file} --lang=\"{target_lang}\"").unwrap(),
+            vec!["sh", "my hook.sh", "{source_file}", "--lang={target_lang}"]
+        );
+        assert_eq!(split_command("run \"\"").unwrap(), vec!["run", ""]);
+        assert!(matches!(
+            split_command("sh 'unclosed"),
+            Err(HookError::InvalidCommand(_))
+        ));
+        assert!(matches!(
+            split_command("   "),
+            Err(HookError::InvalidCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_placeholders_stay_single_arguments() {
+        let hook = CompletionHook {
+            command: "notify {target_lang} {source_file}:{translation_file}".to_string(),
+            timeout: Duration::from_secs(1),
+        };
+        assert_eq!(
+            hook.argv("/tmp/s.txt", "/tmp/t.txt", "Chinese; rm -rf ~")
+                .unwrap(),
+            vec!["notify", "Chinese; rm -rf ~", "/tmp/s.txt:/tmp/t.txt"]
+        );
+    }
+
+    /// A fresh folder for the files of one test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("test_hooks_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn hook(command: String, timeout: Duration) -> CompletionHook {
+        CompletionHook { command, timeout }
+    }
+
+    #[cfg(unix)]
+    mod unix {
+        use super::*;
+
+        /// Writes a shell script, run with `sh` so it needn't be executable.
+        fn script(dir: &Path, body: &str) -> PathBuf {
+            let path = dir.join("hook.sh");
+            fs::write(&path, body).unwrap();
+            path
+        }
+
+        #[test]
+        fn test_hook_gets_the_texts() {
+            let dir = test_dir("echo");
+            let out = dir.join("out.txt");
+            let script = script(
+                &dir,
+                "{ cat \"$1\"; echo; cat \"$2\"; echo; echo \"$3\"; echo \"$1\"; } > \"$4\"\necho progress >&2\n",
+            );
+            let hook = hook(
+                format!(
+                    "sh \"{}\" {{source_file}} {{translation_file}} {{target_lang}} \"{}\"",
+                    script.display(),
+                    out.display()
+                ),
+                Duration::from_secs(10),
+            );
+            let stderr = hook.run("Hello", "你好", "Simplified Chinese").unwrap();
+            assert_eq!(stderr, "progress\n");
+
+            let written = fs::read_to_string(&out).unwrap();
+            let lines: Vec<&str> = written.lines().collect();
+            assert_eq!(lines[..3], ["Hello", "你好", "Simplified Chinese"]);
+            // The texts are cleaned up after the run
+            assert!(!Path::new(lines[3]).exists());
+            let _ = fs::remove_dir_all(dir);
+        }
+
+        #[test]
+        fn test_failure_reports_status_and_stderr() {
+            let dir = test_dir("fail");
+            let script = script(
+                &dir,
+                "echo 'warming up' >&2\necho 'no such org file' >&2\nexit 3\n",
+            );
+            let hook = hook(
+                format!("sh \"{}\"", script.display()),
+                Duration::from_secs(10),
+            );
+            let error = hook.run("a", "b", "Deutsch").unwrap_err();
+            let HookError::Failed { status, stderr } = &error else {
+                panic!("unexpected error {:?}", error);
+            };
+            assert!(status.contains('3'), "{}", status);
+            assert_eq!(stderr, "warming up\nno such org file\n");
+            assert!(error.to_string().ends_with(": no such org file"));
+
+            let missing = super::hook("no-such-hook-program".to_string(), Duration::from_secs(1));
+            assert!(matches!(
+                missing.run("a", "b", "Deutsch"),
+                Err(HookError::Start { .. })
+            ));
+            let _ = fs::remove_dir_all(dir);
+        }
+
+        #[test]
+        fn test_slow_hook_is_stopped() {
+            let dir = test_dir("timeout");
+            let script = script(&dir, "sleep 5\n");
+            let hook = hook(
+                format!("sh \"{}\"", script.display()),
+                Duration::from_millis(200),
+            );
+            let started = Instant::now();
+            assert_eq!(
+                hook.run("a", "b", "Deutsch"),
+                Err(HookError::TimedOut(Duration::from_millis(200)))
+            );
+            assert!(started.elapsed() < Duration::from_secs(3));
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    #[cfg(windows)]
+    mod windows {
+        use super::*;
+
+        /// Writes a batch file stub.
+        fn stub(dir: &Path, body: &str) -> PathBuf {
+            let path = dir.join("hook.cmd");
+            fs::write(&path, body).unwrap();
+            path
+        }
+
+        #[test]
+        fn test_hook_gets_the_texts() {
+            let dir = test_dir("echo");
+            let out = dir.join("out.txt");
+            let stub = stub(
+                &dir,
+                "@echo off\r\ntype %1 > %4\r\necho.>> %4\r\ntype %2 >> %4\r\necho.>> %4\r\necho %~3>> %4\r\n",
+            );
+            let hook = hook(
+                format!(
+                    "\"{}\" {{source_file}} {{translation_file}} {{target_lang}} \"{}\"",
+                    stub.display(),
+                    out.display()
+                ),
+                Duration::from_secs(10),
+            );
+            hook.run("Hello", "Hallo", "Deutsch").unwrap();
+            let written = fs::read_to_string(&out).unwrap();
+            let lines: Vec<&str> = written.lines().collect();
+            assert_eq!(lines[..3], ["Hello", "Hallo", "Deutsch"]);
+            let _ = fs::remove_dir_all(dir);
+        }
+
+        #[test]
+        fn test_failure_reports_status_and_stderr() {
+            let dir = test_dir("fail");
+            let stub = stub(
+                &dir,
+                "@echo off\r\necho no such org file 1>&2\r\nexit /b 3\r\n",
+            );
+            let hook = hook(format!("\"{}\"", stub.display()), Duration::from_secs(10));
+            let error = hook.run("a", "b", "Deutsch").unwrap_err();
+            let HookError::Failed { status, stderr } = &error else {
+                panic!("unexpected error {:?}", error);
+            };
+            assert!(status.contains('3'), "{}", status);
+            assert!(stderr.contains("no such org file"));
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! One running instance at a time.
+//!
+//! Two instances would overwrite each other's settings and cache, so the
+//! first one holds an exclusive lock on a file in the data folder for as
+//! long as it runs, and names the loopback port it listens on in a file
+//! next to it. Windows doesn't let others read a locked file, hence two.
+//! A later launch that can't take the lock sends its command line to that
+//! port, one JSON line with a token from the address file so no other
+//! local program can pose as it, and exits.
+//!
+//! The operating system drops the lock when its holder exits, crashed or
+//! not, so a lock file left behind is simply taken over. `--new-instance`
+//! skips all of this and runs without saving anything.
+
+use crate::utils::paths;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, mpsc};
+use std::thread;
+use std::time::Duration;
+
+/// Flag that starts another instance anyway, without persistence.
+pub const NEW_INSTANCE_FLAG: &str = "--new-instance";
+
+/// How long a connection to the running instance may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest message accepted, in bytes.
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+/// How often and how long to wait for a starting instance to write its
+/// address.
+const ANNOUNCE_ATTEMPTS: u32 = 20;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The lock file.
+pub fn lock_path() -> PathBuf {
+    paths::data_dir().join("instance.lock")
+}
+
+/// Folder a secondary instance keeps its files in, removed when it exits.
+pub fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("ai-translate-{}", std::process::id()))
+}
+
+/// File next to the lock naming where its holder listens.
+fn address_path(lock_path: &Path) -> PathBuf {
+    lock_path.with_extension("addr")
+}
+
+/// What the command line asks for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandLine {
+    /// Run beside the running instance instead of handing over to it
+    pub new_instance: bool,
+    /// Files to open, made absolute so another instance finds them too
+    pub files: Vec<PathBuf>,
+}
+
+impl CommandLine {
+    /// Reads the arguments after the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut command_line = CommandLine::default();
+        for arg in args {
+            if arg == NEW_INSTANCE_FLAG {
+                command_line.new_instance = true;
+            } else if arg.starts_with("--") {
+                tracing::warn!("Ignoring unknown option {}", arg);
+            } else {
+                let path = PathBuf::from(&arg);
+                command_line
+                    .files
+                    .push(std::path::absolute(&path).unwrap_or(path));
+            }
+        }
+        command_line
+    }
+
+    /// The files as sent to the running instance.
+    pub fn forwarded_args(&self) -> Vec<String> {
+        self.files
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect()
+    }
+}
+
+/// How this process was started, handed to the app.
+#[derive(Debug)]
+pub struct Launch {
+    /// Files named on the command line
+    pub files: Vec<PathBuf>,
+    /// Command lines of later launches, for the running instance
+    pub forwarded: Option<Forwarded>,
+    /// Whether settings and data are saved, which a second instance doesn't
+    pub persist: bool,
+}
+
+impl Default for Launch {
+    fn default() -> Self {
+        Launch {
+            files: Vec::new(),
+            forwarded: None,
+            persist: true,
+        }
+    }
+}
+
+/// Where the running instance listens, as written to the address file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub pid: u32,
+    pub port: u16,
+    pub token: String,
+}
+
+/// A command line sent to the running instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub token: String,
+    pub args: Vec<String>,
+}
+
+impl Message {
+    /// The message as one line of JSON, newline included.
+    pub fn encode(&self) -> String {
+        let mut line = serde_json::to_string(self).expect("message serializes");
+        line.push('\n');
+        line
+    }
+
+    /// Reads a message from one line.
+    pub fn decode(line: &str) -> io::Result<Self> {
+        serde_json::from_str(line.trim_end())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Outcome of trying to become the running instance.
+#[derive(Debug)]
+pub enum Acquired {
+    /// No other instance runs; this one holds the lock now
+    Primary(InstanceLock),
+    /// Another instance runs and can be reached here
+    Secondary(Endpoint),
+}
+
+/// The held lock, released when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+    address_file: PathBuf,
+}
+
+/// Takes the lock at `path`, or finds out who holds it.
+///
+/// A file left behind by an instance that crashed is no longer locked and
+/// is taken over.
+pub fn acquire(path: &Path) -> io::Result<Acquired> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    let address_file = address_path(path);
+    match file.try_lock() {
+        Ok(()) => {
+            // A later launch waits for the new address instead of trying
+            // one left behind
+            match fs::remove_file(&address_file) {
+                Ok(()) => tracing::info!("Took over the lock of an instance that exited"),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            Ok(Acquired::Primary(InstanceLock {
+                _file: file,
+                address_file,
+            }))
+        }
+        Err(TryLockError::WouldBlock) => read_endpoint(&address_file).map(Acquired::Secondary),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Reads the holder's address, waiting briefly for one that is starting up.
+fn read_endpoint(path: &Path) -> io::Result<Endpoint> {
+    for attempt in 0..ANNOUNCE_ATTEMPTS {
+        if attempt > 0 {
+            thread::sleep(ANNOUNCE_INTERVAL);
+        }
+        if let Ok(content) = fs::read_to_string(path)
+            && let Ok(endpoint) = serde_json::from_str(&content)
+        {
+            return Ok(endpoint);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "the running instance did not write its address",
+    ))
+}
+
+/// Command lines handed over by later launches.
+#[derive(Debug)]
+pub struct Forwarded {
+    launches: mpsc::Receiver<Vec<String>>,
+    /// Woken when a launch arrives, once the window is there
+    context: Arc<OnceLock<egui::Context>>,
+}
+
+impl Forwarded {
+    /// Repaints `ctx` whenever a launch arrives, so it is opened while
+    /// the window is idle.
+    pub fn wake(&self, ctx: &egui::Context) {
+        let _ = self.context.set(ctx.clone());
+    }
+
+    /// The arguments of each command line received since the last call.
+    pub fn try_iter(&self) -> mpsc::TryIter<'_, Vec<String>> {
+        self.launches.try_iter()
+    }
+}
+
+impl InstanceLock {
+    /// Listens for command lines of later launches and writes the address
+    /// for them.
+    pub fn listen(&self) -> io::Result<Forwarded> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let endpoint = Endpoint {
+            pid: std::process::id(),
+            port: listener.local_addr()?.port(),
+            token: format!("{:016x}", RandomState::new().hash_one(std::process::id())),
+        };
+
+        // Written whole and renamed, so it is never read half done
+        let temp_file = self.address_file.with_extension("addr.tmp");
+        fs::write(&temp_file, serde_json::to_string(&endpoint)?)?;
+        fs::rename(&temp_file, &self.address_file)?;
+
+        let (tx, rx) = mpsc::channel();
+        let context: Arc<OnceLock<egui::Context>> = Arc::default();
+        let listener_context = context.clone();
+        thread::Builder::new()
+            .name("instance-listener".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            tracing::warn!("Failed to accept a launch: {}", e);
+                            continue;
+                        }
+                    };
+                    match receive(stream, &endpoint.token) {
+                        Ok(args) => {
+                            if tx.send(args).is_err() {
+                                break;
+                            }
+                            if let Some(ctx) = listener_context.get() {
+                                ctx.request_repaint();
+                            }
+                        }
+                        Err(e) => tracing::warn!("Rejected a forwarded launch: {}", e),
+                    }
+                }
+            })?;
+        Ok(Forwarded {
+            launches: rx,
+            context,
+        })
+    }
+}
+
+/// Reads one message, checks its token and acknowledges it.
+fn receive(mut stream: TcpStream, token: &str) -> io::Result<Vec<String>> {
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_MESSAGE_BYTES)).read_line(&mut line)?;
+    let message = Message::decode(&line)?;
+    if message.token != token {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "wrong token",
+        ));
+    }
+    stream.write_all(b"ok\n")?;
+    Ok(message.args)
+}
+
+/// Sends `args` to the running instance and waits for it to accept them.
+pub fn forward(endpoint: &Endpoint, args: &[String]) -> io::Result<()> {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port));
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+    let message = Message {
+        token: endpoint.token.clone(),
+        args: args.to_vec(),
+    };
+    stream.write_all(message.encode().as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream.take(16)).read_line(&mut reply)?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the running instance did not accept the launch",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_lock(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("test_instance_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("instance.lock")
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let message = Message {
+            token: "abc".to_string(),
+            args: vec!["/tmp/a file.pdf".to_string(), "—\n".to_string()],
+        };
+        let line = message.encode();
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(Message::decode(&line).unwrap(), message);
+
+        assert!(Message::decode("not json\n").is_err());
+        assert!(Message::decode(r#"{"args":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_command_line() {
+        let args = ["--new-instance", "notes.json", "/tmp/a.pdf", "--bogus"];
+        let command_line = CommandLine::parse(args.map(String::from));
+        assert!(command_line.new_instance);
+        assert_eq!(command_line.files.len(), 2);
+        assert!(command_line.files.iter().all(|path| path.is_absolute()));
+        assert!(command_line.files[0].ends_with("notes.json"));
+        assert_eq!(command_line.forwarded_args()[1], "/tmp/a.pdf");
+
+        assert_eq!(CommandLine::parse(Vec::new()), CommandLine::default());
+    }
+
+    #[test]
+    fn test_second_launch_reaches_the_first() {
+        let path = temp_lock("reach");
+        let Acquired::Primary(lock) = acquire(&path).unwrap() else {
+            panic!("the first launch should hold the lock");
+        };
+        let launches = lock.listen().unwrap();
+        let ctx = egui::Context::default();
+        let repaints = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        ctx.set_request_repaint_callback({
+            let repaints = repaints.clone();
+            move |_| {
+                repaints.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        });
+        launches.wake(&ctx);
+
+        let Acquired::Secondary(endpoint) = acquire(&path).unwrap() else {
+            panic!("the lock should be taken");
+        };
+        assert_eq!(endpoint.pid, std::process::id());
+        forward(&endpoint, &["a.pdf".to_string()]).unwrap();
+        assert_eq!(
+            launches
+                .launches
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap(),
+            vec!["a.pdf".to_string()]
+        );
+        // The idle window is woken to open it
+        let woken = (0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            repaints.load(std::sync::atomic::Ordering::SeqCst) > 0
+        });
+        assert!(woken);
+
+        // A message without the token is turned away
+        let forged = Endpoint {
+            token: "guess".to_string(),
+            ..endpoint
+        };
+        assert!(forward(&forged, &[]).is_err());
+        assert!(launches.try_iter().next().is_none());
+
+        drop(lock);
+        assert!(matches!(acquire(&path).unwrap(), Acquired::Primary(_)));
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        // Left behind by an instance that crashed, so nobody locks it
+        let path = temp_lock("stale");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let stale = Endpoint {
+            pid: u32::MAX,
+            port: 1,
+            token: "old".to_string(),
+        };
+        fs::write(&path, "").unwrap();
+        fs::write(address_path(&path), serde_json::to_string(&stale).unwrap()).unwrap();
+
+        let Acquired::Primary(lock) = acquire(&path).unwrap() else {
+            panic!("a stale lock should be taken over");
+        };
+        // Its address is gone, so nobody tries to reach the crashed instance
+        assert!(!address_path(&path).exists());
+        lock.listen().unwrap();
+        let Acquired::Secondary(endpoint) = acquire(&path).unwrap() else {
+            panic!("the lock should be taken");
+        };
+        assert_ne!(endpoint, stale);
+        assert_eq!(endpoint.pid, std::process::id());
+    }
+
+    #[test]
+    fn test_unannounced_lock_times_out() {
+        let path = temp_lock("unannounced");
+        let Acquired::Primary(_lock) = acquire(&path).unwrap() else {
+            panic!("the first launch should hold the lock");
+        };
+        let error = acquire(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,126 @@
+//! BCP-47 codes of language names, for files read by other tools.
+//!
+//! The app names languages the way their speakers write them ("Deutsch",
+//! "中文"), and users may type others in English. Translation memories and
+//! similar exchange formats need language tags instead, so names are mapped
+//! through the table below on a best-effort basis.
+
+/// Tag of a language that isn't known.
+pub const UNDETERMINED: &str = "und";
+
+/// Language names, native and English, and their BCP-47 tags.
+const LANGUAGE_CODES: &[(&str, &str)] = &[
+    ("English", "en"),
+    ("中文", "zh"),
+    ("Chinese", "zh"),
+    ("简体中文", "zh-Hans"),
+    ("Simplified Chinese", "zh-Hans"),
+    ("繁體中文", "zh-Hant"),
+    ("Traditional Chinese", "zh-Hant"),
+    ("日本語", "ja"),
+    ("Japanese", "ja"),
+    ("한국어", "ko"),
+    ("Korean", "ko"),
+    ("Français", "fr"),
+    ("French", "fr"),
+    ("Deutsch", "de"),
+    ("German", "de"),
+    ("Español", "es"),
+    ("Spanish", "es"),
+    ("Português", "pt"),
+    ("Portuguese", "pt"),
+    ("Русский", "ru"),
+    ("Russian", "ru"),
+    ("Italiano", "it"),
+    ("Italian", "it"),
+    ("العربية", "ar"),
+    ("Arabic", "ar"),
+    ("עברית", "he"),
+    ("Hebrew", "he"),
+    ("Nederlands", "nl"),
+    ("Dutch", "nl"),
+    ("Polski", "pl"),
+    ("Polish", "pl"),
+    ("Türkçe", "tr"),
+    ("Turkish", "tr"),
+    ("Українська", "uk"),
+    ("Ukrainian", "uk"),
+    ("Ελληνικά", "el"),
+    ("Greek", "el"),
+    ("Svenska", "sv"),
+    ("Swedish", "sv"),
+    ("Tiếng Việt", "vi"),
+    ("Vietnamese", "vi"),
+    ("ไทย", "th"),
+    ("Thai", "th"),
+    ("हिन्दी", "hi"),
+    ("Hindi", "hi"),
+    ("Bahasa Indonesia", "id"),
+    ("Indonesian", "id"),
+];
+
+/// The BCP-47 tag of a language name, ignoring case and surrounding spaces.
+pub fn bcp47(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_lowercase();
+    LANGUAGE_CODES
+        .iter()
+        .find(|(name, _)| name.to_lowercase() == language)
+        .map(|(_, code)| *code)
+}
+
+/// The BCP-47 tag of a language name, [`UNDETERMINED`] if it isn't known.
+pub fn bcp47_or_und(language: &str) -> &'static str {
+    bcp47(language).unwrap_or(UNDETERMINED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::AppConfig;
+
+    #[test]
+    fn test_built_in_languages_have_codes() {
+        for language in AppConfig::get_supported_languages() {
+            assert!(bcp47(language).is_some(), "{}", language);
+        }
+        assert_eq!(bcp47("中文"), Some("zh"));
+        assert_eq!(bcp47("日本語"), Some("ja"));
+        assert_eq!(bcp47("עברית"), Some("he"));
+    }
+
+    #[test]
+    fn test_english_names_and_case() {
+        assert_eq!(bcp47("german"), Some("de"));
+        assert_eq!(bcp47("  Traditional Chinese "), Some("zh-Hant"));
+        assert_eq!(bcp47("ESPAÑOL"), Some("es"));
+        assert_eq!(bcp47("русский"), Some("ru"));
+    }
+
+    #[test]
+    fn test_unknown_languages() {
+        assert_eq!(bcp47("Klingon"), None);
+        assert_eq!(bcp47("Auto-detected"), None);
+        assert_eq!(bcp47(""), None);
+        assert_eq!(bcp47_or_und("Klingon"), "und");
+        assert_eq!(bcp47_or_und("Deutsch"), "de");
+    }
+
+    #[test]
+    fn test_codes_are_well_formed() {
+        for (name, code) in LANGUAGE_CODES {
+            let mut subtags = code.split('-');
+            let primary = subtags.next().unwrap();
+            assert!(
+                (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_lowercase()),
+                "{}: {}",
+                name,
+                code
+            );
+            // Script subtags are four letters, title case
+            for script in subtags {
+                assert_eq!(script.len(), 4, "{}", code);
+                assert!(script.starts_with(|c: char| c.is_ascii_uppercase()));
+            }
+        }
+    }
+}
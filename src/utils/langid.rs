@@ -0,0 +1,356 @@
+//! Rough guess of the language a text is written in.
+//!
+//! Used to catch a source text that is already in the target language
+//! before tokens are spent translating it. The guess is made locally from
+//! the mix of scripts in the text and, for Latin script, from a short list
+//! of stop words per language. Only the beginning of a long text is looked
+//! at, so a guess takes well under a millisecond.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Characters looked at to guess the language.
+const SAMPLE_CHARS: usize = 2000;
+
+/// Confidence from which a guess counts as certain enough to warn about.
+pub const HIGH_CONFIDENCE: f32 = 0.8;
+
+/// Stop words it takes for a Latin-script guess to be fully trusted.
+const FULL_SUPPORT_HITS: usize = 2;
+
+/// Share of the words that are stop words in ordinary running text.
+const FULL_STOP_WORD_DENSITY: f32 = 0.25;
+
+/// Share of Han characters that must be kana for a text to be Japanese.
+const JAPANESE_KANA_SHARE: f32 = 0.1;
+
+/// Common short words of each Latin-script language, by language name.
+const STOP_WORDS: [(&str, &[&str]); 6] = [
+    (
+        "English",
+        &[
+            "the", "and", "is", "are", "of", "to", "you", "that", "it", "with", "for", "this",
+            "was", "have", "be", "not", "what", "how", "on", "my", "we", "they", "will", "can",
+            "do", "from", "your", "at", "would", "there", "an", "or", "please", "thank",
+        ],
+    ),
+    (
+        "Deutsch",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ich", "sie", "es", "ein", "eine", "zu",
+            "mit", "den", "auf", "für", "sich", "dem", "auch", "wir", "wie", "sind", "hat",
+            "haben", "noch", "aber", "oder", "wird", "um", "bitte", "danke", "ihr",
+        ],
+    ),
+    (
+        "Français",
+        &[
+            "le", "la", "les", "et", "est", "un", "une", "des", "du", "je", "vous", "nous", "il",
+            "elle", "que", "qui", "pas", "ne", "pour", "dans", "ce", "cette", "sur", "avec",
+            "sont", "mais", "au", "aux", "c", "j", "qu", "merci", "très",
+        ],
+    ),
+    (
+        "Español",
+        &[
+            "el", "los", "las", "es", "y", "que", "en", "un", "una", "por", "para", "con", "no",
+            "está", "son", "pero", "como", "del", "se", "lo", "muy", "también", "yo", "usted",
+            "gracias", "al", "hay", "qué",
+        ],
+    ),
+    (
+        "Português",
+        &[
+            "o", "os", "as", "e", "é", "não", "um", "uma", "em", "no", "na", "do", "da", "dos",
+            "das", "que", "com", "para", "por", "mas", "você", "eu", "ele", "ela", "muito",
+            "obrigado", "são", "está", "também",
+        ],
+    ),
+    (
+        "Italiano",
+        &[
+            "il", "lo", "gli", "la", "e", "è", "non", "un", "una", "di", "che", "per", "con",
+            "sono", "del", "della", "ma", "ci", "questo", "questa", "io", "lei", "anche", "molto",
+            "grazie", "come", "nel", "al",
+        ],
+    ),
+];
+
+/// Languages listing each stop word, as indices into [`STOP_WORDS`].
+static STOP_WORD_INDEX: LazyLock<HashMap<&'static str, Vec<usize>>> = LazyLock::new(|| {
+    let mut index: HashMap<&'static str, Vec<usize>> = HashMap::new();
+    for (language, (_, words)) in STOP_WORDS.iter().enumerate() {
+        for word in *words {
+            index.entry(*word).or_default().push(language);
+        }
+    }
+    index
+});
+
+/// The language a text is most likely written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guess {
+    /// Name of the language, as in the list of supported languages
+    pub language: &'static str,
+    /// From 0.0 to 1.0
+    pub confidence: f32,
+}
+
+impl Guess {
+    /// Whether the guess is certain enough to act on.
+    pub fn is_confident(&self) -> bool {
+        self.confidence >= HIGH_CONFIDENCE
+    }
+}
+
+/// Letters of the sample counted per group of scripts.
+#[derive(Debug, Default)]
+struct Letters {
+    latin: usize,
+    cyrillic: usize,
+    arabic: usize,
+    hebrew: usize,
+    hangul: usize,
+    kana: usize,
+    han: usize,
+    other: usize,
+}
+
+impl Letters {
+    fn count(text: &str) -> Self {
+        let mut letters = Letters::default();
+        for c in text
+            .chars()
+            .take(SAMPLE_CHARS)
+            .filter(|c| c.is_alphabetic())
+        {
+            let slot = match c as u32 {
+                0x0041..=0x024F | 0x1E00..=0x1EFF => &mut letters.latin,
+                0x0400..=0x052F => &mut letters.cyrillic,
+                0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => {
+                    &mut letters.arabic
+                }
+                0x0590..=0x05FF | 0xFB1D..=0xFB4F => &mut letters.hebrew,
+                0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => &mut letters.hangul,
+                0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => &mut letters.kana,
+                0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => &mut letters.han,
+                _ => &mut letters.other,
+            };
+            *slot += 1;
+        }
+        letters
+    }
+
+    fn total(&self) -> usize {
+        self.latin
+            + self.cyrillic
+            + self.arabic
+            + self.hebrew
+            + self.hangul
+            + self.kana
+            + self.han
+            + self.other
+    }
+}
+
+/// Guesses the language of `text`.
+///
+/// # Returns
+///
+/// `None` if the text has no letters, is mostly in a script of no
+/// supported language, or is in Latin script without telling stop words
+pub fn detect(text: &str) -> Option<Guess> {
+    let letters = Letters::count(text);
+    let total = letters.total();
+    if total == 0 {
+        return None;
+    }
+    let share = |count: usize| count as f32 / total as f32;
+
+    let cjk = letters.hangul + letters.kana + letters.han;
+    let groups = [
+        letters.latin,
+        letters.cyrillic,
+        letters.arabic,
+        letters.hebrew,
+        cjk,
+        letters.other,
+    ];
+    let dominant = groups.iter().copied().max().unwrap_or(0);
+    let guess = |language: &'static str, count: usize| Guess {
+        language,
+        confidence: share(count),
+    };
+
+    if dominant == cjk {
+        if letters.hangul * 2 >= cjk {
+            return Some(guess("한국어", cjk));
+        }
+        let kana_share = letters.kana as f32 / (letters.kana + letters.han) as f32;
+        let language = if kana_share >= JAPANESE_KANA_SHARE {
+            "日本語"
+        } else {
+            "中文"
+        };
+        return Some(guess(language, cjk));
+    }
+    if dominant == letters.cyrillic {
+        return Some(guess("Русский", dominant));
+    }
+    if dominant == letters.arabic {
+        return Some(guess("العربية", dominant));
+    }
+    if dominant == letters.hebrew {
+        return Some(guess("עברית", dominant));
+    }
+    if dominant == letters.latin {
+        return detect_latin(text).map(|guess| Guess {
+            confidence: guess.confidence * share(dominant),
+            ..guess
+        });
+    }
+    None
+}
+
+/// Guesses a Latin-script language from its stop words.
+fn detect_latin(text: &str) -> Option<Guess> {
+    let sample: String = text.chars().take(SAMPLE_CHARS).collect();
+    let mut hits = [0usize; STOP_WORDS.len()];
+    let mut words = 0;
+    for word in sample
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+    {
+        words += 1;
+        if let Some(languages) = STOP_WORD_INDEX.get(word.to_lowercase().as_str()) {
+            for &language in languages {
+                hits[language] += 1;
+            }
+        }
+    }
+
+    let (best, &best_hits) = hits.iter().enumerate().max_by_key(|(_, hits)| **hits)?;
+    if best_hits == 0 {
+        return None;
+    }
+    let runner_up = hits
+        .iter()
+        .enumerate()
+        .filter(|(language, _)| *language != best)
+        .map(|(_, hits)| *hits)
+        .max()
+        .unwrap_or(0);
+    // Words shared with a close runner-up say little about which one it is
+    let purity = 1.0 - (runner_up as f32 / best_hits as f32).powi(2);
+    let support = (best_hits as f32 / FULL_SUPPORT_HITS as f32).min(1.0);
+    let density = (best_hits as f32 / words as f32 / FULL_STOP_WORD_DENSITY).min(1.0);
+    Some(Guess {
+        language: STOP_WORDS[best].0,
+        confidence: purity * support * density,
+    })
+}
+
+/// Whether `text` is confidently already in `language`.
+pub fn is_in_language(text: &str, language: &str) -> bool {
+    detect(text).is_some_and(|guess| guess.language == language && guess.is_confident())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(text: &str) -> Option<&'static str> {
+        detect(text)
+            .filter(Guess::is_confident)
+            .map(|guess| guess.language)
+    }
+
+    #[test]
+    fn test_detect_built_in_languages() {
+        let samples = [
+            (
+                "English",
+                "The weather is nice today and we are going to the park.",
+            ),
+            ("中文", "今天天气很好，我们去公园散步吧。"),
+            ("日本語", "今日はいい天気なので、公園に散歩に行きましょう。"),
+            ("한국어", "오늘 날씨가 좋아서 공원에 산책하러 가요."),
+            (
+                "Français",
+                "Il fait beau aujourd'hui et nous allons au parc.",
+            ),
+            (
+                "Deutsch",
+                "Das Wetter ist heute schön und wir gehen in den Park.",
+            ),
+            (
+                "Español",
+                "Hoy hace buen tiempo y los niños están en el parque.",
+            ),
+            (
+                "Português",
+                "O tempo está bom hoje e nós vamos ao parque com as crianças.",
+            ),
+            ("Русский", "Сегодня хорошая погода, и мы идём в парк."),
+            (
+                "Italiano",
+                "Oggi il tempo è bello e andiamo al parco con gli amici.",
+            ),
+            ("العربية", "الطقس جميل اليوم وسنذهب إلى الحديقة."),
+            ("עברית", "מזג האוויר יפה היום ואנחנו הולכים לפארק."),
+        ];
+        for (expected, text) in samples {
+            assert_eq!(language(text), Some(expected), "{}", text);
+            assert!(is_in_language(text, expected));
+        }
+        // Every sample's language is one the app offers
+        let supported = crate::utils::config::AppConfig::get_supported_languages();
+        assert!(samples.iter().all(|(l, _)| supported.contains(l)));
+    }
+
+    #[test]
+    fn test_short_samples() {
+        assert_eq!(language("How are you?"), Some("English"));
+        assert_eq!(language("Das ist nicht gut."), Some("Deutsch"));
+        assert_eq!(language("Merci, c'est très gentil."), Some("Français"));
+        assert_eq!(language("谢谢"), Some("中文"));
+        assert_eq!(language("ありがとう"), Some("日本語"));
+        assert_eq!(language("감사합니다"), Some("한국어"));
+        assert_eq!(language("Спасибо"), Some("Русский"));
+        // Too little to go on
+        assert_eq!(language("Hello"), None);
+        assert_eq!(language("Danke"), None);
+        assert_eq!(language("Paris"), None);
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("12345 !?"), None);
+    }
+
+    #[test]
+    fn test_mixed_language_samples_are_not_confident() {
+        // Half English, half German
+        assert_eq!(
+            language("The meeting is at noon. Das Treffen ist um zwölf."),
+            None
+        );
+        // Chinese with an English sentence of similar length
+        assert_eq!(
+            language("这是我们公司今年推出的新产品。This is our new product and it is great."),
+            None
+        );
+        // A foreign name doesn't change a clearly English text
+        assert_eq!(
+            language("We met 王小明 at the station and then we went to the office."),
+            Some("English")
+        );
+        // Kanji with a little kana is still Japanese, not Chinese
+        assert_eq!(language("東京都庁の展望室"), Some("日本語"));
+        // Scripts of no supported language
+        assert_eq!(detect("Καλημέρα σας"), None);
+    }
+
+    #[test]
+    fn test_only_the_start_of_a_long_text_is_read() {
+        let text = "The cat is on the mat. ".repeat(1000) + &"Der Hund ist hier. ".repeat(2000);
+        assert_eq!(language(&text), Some("English"));
+    }
+}
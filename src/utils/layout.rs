@@ -0,0 +1,169 @@
+//! Arrangement of the main window for its size.
+//!
+//! Wide windows show the sidebar as a side panel next to the translation,
+//! which the sidebar itself collapses to a rail in narrower ones. Below
+//! [`TABS_BELOW_WIDTH`] even the rail leaves too little room, so the
+//! sidebar's content and the translation become two tabs, "Input" and
+//! "Result". Below [`COMPACT_BELOW_HEIGHT`] the spacing is tightened and
+//! the status bar hidden.
+//!
+//! The user can pin an arrangement in the settings, which then applies at
+//! any width.
+
+use egui::style::Spacing;
+use egui::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
+
+/// Window width below which the sidebar and the translation become tabs.
+pub const TABS_BELOW_WIDTH: f32 = 560.0;
+
+/// Window height below which the spacing is tightened.
+pub const COMPACT_BELOW_HEIGHT: f32 = 420.0;
+
+/// Smallest size the window can be resized to.
+pub const MIN_WINDOW_SIZE: [f32; 2] = [360.0, 280.0];
+
+/// How the sidebar and the translation share the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Arrangement {
+    /// The sidebar next to the translation
+    #[default]
+    SidePanel,
+    /// One of them at a time, switched with tabs
+    Tabs,
+}
+
+impl Arrangement {
+    /// All arrangements, in display order.
+    pub const ALL: [Arrangement; 2] = [Arrangement::SidePanel, Arrangement::Tabs];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            Arrangement::SidePanel => "Side panel",
+            Arrangement::Tabs => "Tabs",
+        }
+    }
+}
+
+/// Tab shown in the [`Arrangement::Tabs`] arrangement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tab {
+    /// The sidebar's content
+    #[default]
+    Input,
+    /// The translation
+    Result,
+}
+
+/// Layout of the main window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutPlan {
+    pub arrangement: Arrangement,
+    /// Tighter spacing for low windows
+    pub compact: bool,
+    pub show_status_bar: bool,
+}
+
+impl LayoutPlan {
+    /// The layout for a window of `size` points.
+    pub fn for_size(size: Vec2) -> Self {
+        let compact = size.y < COMPACT_BELOW_HEIGHT;
+        LayoutPlan {
+            arrangement: if size.x < TABS_BELOW_WIDTH {
+                Arrangement::Tabs
+            } else {
+                Arrangement::SidePanel
+            },
+            compact,
+            show_status_bar: !compact,
+        }
+    }
+
+    /// Uses the arrangement the user pinned, if any, instead of the one for
+    /// the width.
+    pub fn pinned(mut self, arrangement: Option<Arrangement>) -> Self {
+        if let Some(arrangement) = arrangement {
+            self.arrangement = arrangement;
+        }
+        self
+    }
+
+    /// Height of the top bar.
+    pub fn top_bar_height(&self) -> f32 {
+        if self.compact { 30.0 } else { 40.0 }
+    }
+
+    /// Spacing of the widgets.
+    pub fn spacing(&self) -> Spacing {
+        let spacing = Spacing::default();
+        if !self.compact {
+            return spacing;
+        }
+        Spacing {
+            item_spacing: spacing.item_spacing * 0.5,
+            button_padding: vec2(spacing.button_padding.x, 1.0),
+            menu_margin: spacing.menu_margin / 2.0,
+            indent: spacing.indent * 0.75,
+            interact_size: vec2(spacing.interact_size.x, 16.0),
+            ..spacing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoints() {
+        let plan = LayoutPlan::for_size(vec2(900.0, 600.0));
+        assert_eq!(plan.arrangement, Arrangement::SidePanel);
+        assert!(!plan.compact);
+        assert!(plan.show_status_bar);
+
+        let narrow = LayoutPlan::for_size(vec2(480.0, 600.0));
+        assert_eq!(narrow.arrangement, Arrangement::Tabs);
+        assert!(narrow.show_status_bar);
+
+        let low = LayoutPlan::for_size(vec2(900.0, 360.0));
+        assert_eq!(low.arrangement, Arrangement::SidePanel);
+        assert!(low.compact);
+        assert!(!low.show_status_bar);
+
+        // A threshold itself is on the roomy side
+        let edge = LayoutPlan::for_size(vec2(TABS_BELOW_WIDTH, COMPACT_BELOW_HEIGHT));
+        assert_eq!(edge.arrangement, Arrangement::SidePanel);
+        assert!(!edge.compact);
+
+        let smallest = LayoutPlan::for_size(Vec2::from(MIN_WINDOW_SIZE));
+        assert_eq!(smallest.arrangement, Arrangement::Tabs);
+        assert!(smallest.compact);
+    }
+
+    #[test]
+    fn test_pinned_arrangement_wins() {
+        let narrow = LayoutPlan::for_size(vec2(400.0, 300.0));
+        let pinned = narrow.pinned(Some(Arrangement::SidePanel));
+        assert_eq!(pinned.arrangement, Arrangement::SidePanel);
+        // The height still decides the spacing
+        assert!(pinned.compact);
+
+        let wide = LayoutPlan::for_size(vec2(1200.0, 800.0));
+        assert_eq!(
+            wide.pinned(Some(Arrangement::Tabs)).arrangement,
+            Arrangement::Tabs
+        );
+        assert_eq!(wide.pinned(None), wide);
+    }
+
+    #[test]
+    fn test_compact_spacing_is_tighter() {
+        let roomy = LayoutPlan::for_size(vec2(900.0, 600.0));
+        let compact = LayoutPlan::for_size(vec2(900.0, 300.0));
+        assert_eq!(roomy.spacing(), Spacing::default());
+        assert!(compact.spacing().item_spacing.y < roomy.spacing().item_spacing.y);
+        assert!(compact.spacing().interact_size.y < roomy.spacing().interact_size.y);
+        assert!(compact.top_bar_height() < roomy.top_bar_height());
+    }
+}
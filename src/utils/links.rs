@@ -0,0 +1,153 @@
+//! Links and email addresses in text.
+//!
+//! Models tend to translate the words in a URL path or to put spaces into
+//! it, so links are masked before translating (see
+//! [`mask_urls`](crate::api::filter::mask_urls)) and shown as clickable
+//! links in the finished translation.
+
+use regex::Regex;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+/// URLs with a scheme or starting with `www.`, and email addresses.
+///
+/// Whitespace, quotes, angle brackets and CJK punctuation end a URL; the
+/// punctuation a sentence puts after one is trimmed by [`find_links`].
+static LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?ix)
+        \b(?:https?://|ftp://|www\.)[^\s<>"'`，。、；：！？（）「」『』【】]+
+        | \b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b
+        "#,
+    )
+    .expect("valid link pattern")
+});
+
+/// Punctuation that ends a sentence rather than the URL before it.
+const TRAILING_PUNCTUATION: [char; 8] = ['.', ',', ';', ':', '!', '?', '\'', '"'];
+
+/// A link found in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Where the link is written in the text
+    pub range: Range<usize>,
+    /// Address to open, with `https://` or `mailto:` added where the text
+    /// leaves it out
+    pub target: String,
+}
+
+/// Cuts the punctuation after a URL off its end.
+///
+/// A closing parenthesis or bracket only belongs to the URL when the URL
+/// also has the opening one, as in
+/// `https://en.wikipedia.org/wiki/Rust_(programming_language)`.
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let Some(last) = url.chars().last() else {
+            return url;
+        };
+        let unbalanced = |open: char, close: char| {
+            last == close && url.matches(close).count() > url.matches(open).count()
+        };
+        if TRAILING_PUNCTUATION.contains(&last) || unbalanced('(', ')') || unbalanced('[', ']') {
+            url = &url[..url.len() - last.len_utf8()];
+        } else {
+            return url;
+        }
+    }
+}
+
+/// Finds the URLs and email addresses in `text`, in order.
+pub fn find_links(text: &str) -> Vec<Link> {
+    LINK.find_iter(text)
+        .filter_map(|found| {
+            let written = found.as_str();
+            let (written, target) = if written.contains('@') && !written.contains("://") {
+                (written, format!("mailto:{}", written))
+            } else {
+                let url = trim_url(written);
+                let target = if url.len() >= 4 && url[..4].eq_ignore_ascii_case("www.") {
+                    format!("https://{}", url)
+                } else {
+                    url.to_string()
+                };
+                (url, target)
+            };
+            // A bare scheme or "www." is not a link
+            let has_host = written
+                .split_once("://")
+                .map_or(written.len() > 4, |(_, rest)| !rest.is_empty());
+            has_host.then(|| Link {
+                range: found.start()..found.start() + written.len(),
+                target,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The links of `text` as written.
+    fn written(text: &str) -> Vec<&str> {
+        find_links(text)
+            .into_iter()
+            .map(|link| &text[link.range])
+            .collect()
+    }
+
+    #[test]
+    fn test_trailing_punctuation_is_not_part_of_the_url() {
+        assert_eq!(
+            written("See https://example.com/docs. Or https://example.com/a?b=1, then stop!"),
+            vec!["https://example.com/docs", "https://example.com/a?b=1"]
+        );
+        assert_eq!(
+            written("访问 https://example.com/中文。谢谢"),
+            vec!["https://example.com/中文"]
+        );
+        assert_eq!(
+            written("\"https://example.com/x\"?"),
+            vec!["https://example.com/x"]
+        );
+    }
+
+    #[test]
+    fn test_parentheses() {
+        assert_eq!(
+            written(
+                "(see https://example.com/page) and https://en.wikipedia.org/wiki/Rust_(programming_language)."
+            ),
+            vec![
+                "https://example.com/page",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)"
+            ]
+        );
+        assert_eq!(
+            written("Read [the guide](https://example.com/guide)."),
+            vec!["https://example.com/guide"]
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let links = find_links("Mail me@example.org or visit www.example.com/path.");
+        assert_eq!(
+            links,
+            vec![
+                Link {
+                    range: 5..19,
+                    target: "mailto:me@example.org".to_string()
+                },
+                Link {
+                    range: 29..49,
+                    target: "https://www.example.com/path".to_string()
+                },
+            ]
+        );
+        assert!(find_links("no links here, just http:// and www.").is_empty());
+        assert!(find_links("user@localhost is not an address").is_empty());
+    }
+}
@@ -0,0 +1,458 @@
+//! Numbered lists translated item by item.
+//!
+//! List mode parses ordered lists (`1.`, `2)`, `(3)`, `a.`, `一、` and
+//! nested combinations of them) out of the source text. Every item body and
+//! every stretch of other text becomes one segment of a batched request with
+//! numbered markers, like code mode, and the translations are put back
+//! behind the original numbers, so the model can neither renumber, reorder
+//! nor merge the items.
+
+use std::collections::HashMap;
+
+/// Chinese numerals accepted in front of `、`.
+const CHINESE_NUMERALS: &str = "一二三四五六七八九十百";
+
+/// Length in bytes of the number at the start of `rest`, e.g. `12` or `b`,
+/// and the character following it.
+fn number_len(rest: &str) -> Option<(usize, char)> {
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let len = match digits {
+        1..=3 => digits,
+        0 => {
+            let first = rest.chars().next()?;
+            if first.is_ascii_alphabetic() {
+                1
+            } else {
+                rest.chars()
+                    .take_while(|c| CHINESE_NUMERALS.contains(*c))
+                    .take(3)
+                    .map(char::len_utf8)
+                    .sum()
+            }
+        }
+        _ => return None,
+    };
+    if len == 0 {
+        return None;
+    }
+    Some((len, rest[len..].chars().next()?))
+}
+
+/// Length in bytes of the indentation, number and spacing that start a list
+/// item, e.g. `  2) ` or `三、`.
+fn item_marker_len(line: &str) -> Option<usize> {
+    let rest = line.trim_start();
+    let indent = line.len() - rest.len();
+
+    let (open, rest) = match rest.strip_prefix(['(', '（']) {
+        Some(inner) => (rest.len() - inner.len(), inner),
+        None => (0, rest),
+    };
+    let (len, closer) = number_len(rest)?;
+    let chinese = !rest.as_bytes()[0].is_ascii();
+    let valid = match closer {
+        '、' => open == 0,
+        ')' | '）' => !chinese,
+        '.' | '．' => open == 0 && !chinese,
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+
+    let after = &rest[len + closer.len_utf8()..];
+    let body = after.trim_start();
+    // "3.5 kg" and a bare "1." are not items; full-width marks need no space
+    if body.is_empty() || (closer.is_ascii() && body.len() == after.len()) {
+        return None;
+    }
+    Some(indent + open + len + closer.len_utf8() + (after.len() - body.len()))
+}
+
+/// Width of the leading whitespace of `line`.
+fn indent_width(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// An item of a numbered list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem {
+    /// Indentation and number, e.g. `  2) `, kept as it is
+    pub marker: String,
+    /// Text of the item, with its continuation lines
+    pub body: String,
+    /// Indentation of the continuation lines
+    pub continuation_indent: String,
+}
+
+/// A part of a parsed list document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// A list item, whose body is translated
+    Item(ListItem),
+    /// Other lines, translated as a whole
+    Text(String),
+    /// An empty line
+    Blank,
+}
+
+/// Source text split into list items and other text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListDocument {
+    pub blocks: Vec<Block>,
+    trailing_newline: bool,
+}
+
+impl ListDocument {
+    /// Parses `text`, returning `None` unless it has at least two items.
+    ///
+    /// An indented line right after an item continues it; any other line is
+    /// text between the items. Items nested by indentation are items of
+    /// their own.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut blocks: Vec<Block> = Vec::new();
+        for line in text.lines() {
+            if line.trim().is_empty() {
+                blocks.push(Block::Blank);
+                continue;
+            }
+            if let Some(len) = item_marker_len(line) {
+                blocks.push(Block::Item(ListItem {
+                    marker: line[..len].to_string(),
+                    body: line[len..].trim_end().to_string(),
+                    continuation_indent: " ".repeat(line[..len].chars().count()),
+                }));
+                continue;
+            }
+            match blocks.last_mut() {
+                Some(Block::Item(item)) if indent_width(line) > indent_width(&item.marker) => {
+                    if !item.body.contains('\n') {
+                        item.continuation_indent = line[..indent_width(line)].to_string();
+                    }
+                    item.body.push('\n');
+                    item.body.push_str(line.trim());
+                }
+                Some(Block::Text(text)) => {
+                    text.push('\n');
+                    text.push_str(line);
+                }
+                _ => blocks.push(Block::Text(line.to_string())),
+            }
+        }
+
+        let items = blocks
+            .iter()
+            .filter(|block| matches!(block, Block::Item(_)))
+            .count();
+        (items >= 2).then(|| ListDocument {
+            blocks,
+            trailing_newline: text.ends_with('\n'),
+        })
+    }
+
+    /// The translated parts, in order: item bodies and other text.
+    pub fn segments(&self) -> Vec<&str> {
+        self.blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Item(item) => Some(item.body.as_str()),
+                Block::Text(text) => Some(text.as_str()),
+                Block::Blank => None,
+            })
+            .collect()
+    }
+
+    /// Builds the batched request body: `⟦n⟧ text` per segment, where the
+    /// lines after the first belong to the same segment.
+    pub fn build_batch(&self) -> String {
+        self.segments()
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| format!("⟦{}⟧ {}", i + 1, segment))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Puts `translations` (one per segment, `None` to keep the source) back
+    /// behind the original numbers.
+    pub fn reassemble(&self, translations: &[Option<String>]) -> String {
+        let mut lines = Vec::new();
+        let mut segment = 0;
+        for block in &self.blocks {
+            let source = match block {
+                Block::Blank => {
+                    lines.push(String::new());
+                    continue;
+                }
+                Block::Item(item) => &item.body,
+                Block::Text(text) => text,
+            };
+            let text = translations
+                .get(segment)
+                .and_then(Option::as_deref)
+                .unwrap_or(source);
+            segment += 1;
+
+            match block {
+                Block::Item(item) => {
+                    for (i, line) in text.lines().enumerate() {
+                        let prefix = if i == 0 {
+                            &item.marker
+                        } else {
+                            &item.continuation_indent
+                        };
+                        lines.push(format!("{}{}", prefix, line.trim()));
+                    }
+                }
+                _ => lines.extend(text.lines().map(str::to_string)),
+            }
+        }
+
+        let mut output = lines.join("\n");
+        if self.trailing_newline {
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Parses a `⟦n⟧ text` response, where lines without a marker continue the
+/// segment before them.
+///
+/// # Returns
+///
+/// The text of each segment by 1-based number, and the numbers that were
+/// given more than once
+pub fn parse_batch(response: &str) -> (HashMap<usize, String>, Vec<usize>) {
+    let mut segments: HashMap<usize, String> = HashMap::new();
+    let mut repeated = Vec::new();
+    let mut current = None;
+    for line in response.lines() {
+        let marked = line.trim().strip_prefix('⟦').and_then(|rest| {
+            let (number, text) = rest.split_once('⟧')?;
+            Some((number.trim().parse::<usize>().ok()?, text.trim()))
+        });
+        match marked {
+            Some((number, text)) => {
+                if segments.insert(number, text.to_string()).is_some() {
+                    repeated.push(number);
+                }
+                current = Some(number);
+            }
+            None if !line.trim().is_empty() => {
+                if let Some(text) = current.and_then(|number| segments.get_mut(&number)) {
+                    text.push('\n');
+                    text.push_str(line.trim());
+                }
+            }
+            None => {}
+        }
+    }
+    (segments, repeated)
+}
+
+/// A list translated segment by segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListTranslation {
+    document: ListDocument,
+    /// Translation of each segment, `None` where the response had none
+    translations: Vec<Option<String>>,
+    /// Numbers in the response that are not segments of the source or came
+    /// more than once
+    unexpected: usize,
+}
+
+impl ListTranslation {
+    /// Maps a batched response onto the segments of `document`.
+    pub fn new(document: ListDocument, response: &str) -> Self {
+        let count = document.segments().len();
+        let (mut segments, repeated) = parse_batch(response);
+        let translations = (1..=count)
+            .map(|number| segments.remove(&number).filter(|text| !text.is_empty()))
+            .collect();
+        ListTranslation {
+            document,
+            translations,
+            unexpected: segments.len() + repeated.len(),
+        }
+    }
+
+    /// The reassembled list, with the source text of missing segments.
+    pub fn text(&self) -> String {
+        self.document.reassemble(&self.translations)
+    }
+
+    /// Segments (0-based) whose translation is missing or empty.
+    pub fn missing(&self) -> Vec<usize> {
+        self.translations
+            .iter()
+            .enumerate()
+            .filter(|(_, translation)| translation.is_none())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Whether the response could not be mapped back onto the source exactly.
+    pub fn is_mismatched(&self) -> bool {
+        self.unexpected > 0 || self.translations.iter().any(Option::is_none)
+    }
+
+    /// Number of response items that did not belong to any source segment.
+    pub fn unexpected(&self) -> usize {
+        self.unexpected
+    }
+
+    /// Source text of `segment`.
+    pub fn source(&self, segment: usize) -> Option<&str> {
+        self.document.segments().get(segment).copied()
+    }
+
+    /// Short name of `segment` for the UI, e.g. "2)" or "text 3".
+    pub fn label(&self, segment: usize) -> String {
+        let block = self
+            .document
+            .blocks
+            .iter()
+            .filter(|block| !matches!(block, Block::Blank))
+            .nth(segment);
+        match block {
+            Some(Block::Item(item)) => item.marker.trim().to_string(),
+            _ => format!("text {}", segment + 1),
+        }
+    }
+
+    /// Replaces the translation of `segment`, e.g. after a retry.
+    pub fn set(&mut self, segment: usize, translation: String) {
+        let translation = translation.trim();
+        if let Some(slot) = self.translations.get_mut(segment)
+            && !translation.is_empty()
+        {
+            *slot = Some(translation.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(document: &ListDocument) -> Vec<(&str, &str)> {
+        document
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Item(item) => Some((item.marker.as_str(), item.body.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_numbering_styles() {
+        let text = "1. Open the lid\n2) Pour water\n(3) Wait\n4、Drink\na. First\nB) Second\n一、准备\n二、开始\n（3）结束";
+        let document = ListDocument::parse(text).unwrap();
+        assert_eq!(
+            items(&document),
+            vec![
+                ("1. ", "Open the lid"),
+                ("2) ", "Pour water"),
+                ("(3) ", "Wait"),
+                ("4、", "Drink"),
+                ("a. ", "First"),
+                ("B) ", "Second"),
+                ("一、", "准备"),
+                ("二、", "开始"),
+                ("（3）", "结束"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_items() {
+        assert_eq!(ListDocument::parse("1. Only one item"), None);
+        assert_eq!(ListDocument::parse("Just text\nmore text"), None);
+        // Decimals, bare numbers and years are no items
+        let document = ListDocument::parse("3.5 kg\n1.\n2024. A year\n1. a\n2. b").unwrap();
+        assert_eq!(items(&document), vec![("1. ", "a"), ("2. ", "b")]);
+        assert_eq!(
+            document.blocks[0],
+            Block::Text("3.5 kg\n1.\n2024. A year".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_and_continued_items() {
+        let text = "Steps:\n\n1. Prepare\n   a) Wash\n   b) Cut\n      into cubes\n2. Cook\n   slowly\nDone.\n";
+        let document = ListDocument::parse(text).unwrap();
+        assert_eq!(document.blocks[0], Block::Text("Steps:".to_string()));
+        assert_eq!(document.blocks[1], Block::Blank);
+        assert_eq!(
+            items(&document),
+            vec![
+                ("1. ", "Prepare"),
+                ("   a) ", "Wash"),
+                ("   b) ", "Cut\ninto cubes"),
+                ("2. ", "Cook\nslowly"),
+            ]
+        );
+        assert_eq!(
+            document.blocks.last(),
+            Some(&Block::Text("Done.".to_string()))
+        );
+        assert_eq!(
+            document.segments(),
+            vec![
+                "Steps:",
+                "Prepare",
+                "Wash",
+                "Cut\ninto cubes",
+                "Cook\nslowly",
+                "Done."
+            ]
+        );
+        // Reassembling the source reproduces it
+        assert_eq!(document.reassemble(&[]), text);
+    }
+
+    #[test]
+    fn test_batch_round_trip() {
+        let document = ListDocument::parse("1. One\n2. Two\n   more").unwrap();
+        assert_eq!(document.build_batch(), "⟦1⟧ One\n⟦2⟧ Two\nmore");
+
+        let (segments, repeated) = parse_batch("Sure:\n⟦1⟧ Eins\n\n⟦2⟧ Zwei\nmehr\n⟦2⟧ again");
+        assert_eq!(segments[&1], "Eins");
+        assert_eq!(segments[&2], "again");
+        assert_eq!(repeated, vec![2]);
+    }
+
+    #[test]
+    fn test_reassemble_keeps_numbering() {
+        let text = "Schritte:\n10) Öffnen\n11) Gießen\n   langsam\n   a. kalt\n   b. warm";
+        let document = ListDocument::parse(text).unwrap();
+        // The model renumbered the items, the numbers of the source are kept
+        let response = "⟦1⟧ Steps:\n⟦2⟧ Open\n⟦3⟧ Pour\nslowly\n⟦4⟧ cold\n⟦5⟧ warm";
+        let list = ListTranslation::new(document, response);
+        assert!(!list.is_mismatched());
+        assert_eq!(
+            list.text(),
+            "Steps:\n10) Open\n11) Pour\n   slowly\n   a. cold\n   b. warm"
+        );
+    }
+
+    #[test]
+    fn test_missing_and_unexpected_items_are_flagged() {
+        let document = ListDocument::parse("一、准备\n二、开始\n三、结束").unwrap();
+        let mut list = ListTranslation::new(document, "⟦1⟧ Prepare\n⟦2⟧\n⟦4⟧ Extra");
+        assert!(list.is_mismatched());
+        assert_eq!(list.missing(), vec![1, 2]);
+        assert_eq!(list.unexpected(), 1);
+        assert_eq!(list.text(), "一、Prepare\n二、开始\n三、结束");
+        assert_eq!(list.source(2), Some("结束"));
+        assert_eq!(list.label(1), "二、");
+
+        list.set(1, " Start ".to_string());
+        list.set(2, "   ".to_string());
+        assert_eq!(list.missing(), vec![2]);
+        assert_eq!(list.text(), "一、Prepare\n二、Start\n三、结束");
+    }
+}
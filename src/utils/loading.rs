@@ -0,0 +1,99 @@
+//! Data loaded in the background at startup.
+//!
+//! Reading the caches from disk takes a while, so it happens off the UI
+//! thread and the window appears right away. Until the [`LoadGate`] of the
+//! data opens, readers find it empty and writers wait, so what is being
+//! loaded is never overwritten.
+
+use crate::lock_mutex;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Opens once data loading in the background is complete.
+#[derive(Clone)]
+pub struct LoadGate(Arc<(Mutex<bool>, Condvar)>);
+
+impl LoadGate {
+    /// A gate for data that is still loading.
+    pub fn closed() -> Self {
+        LoadGate(Arc::new((Mutex::new(false), Condvar::new())))
+    }
+
+    /// Marks the data as loaded, waking everything waiting for it.
+    pub fn open(&self) {
+        let (loaded, ready) = &*self.0;
+        *lock_mutex!(loaded) = true;
+        ready.notify_all();
+    }
+
+    /// Whether the data is loaded.
+    pub fn is_open(&self) -> bool {
+        *lock_mutex!(self.0.0)
+    }
+
+    /// Blocks until the data is loaded.
+    pub fn wait(&self) {
+        let (loaded, ready) = &*self.0;
+        let mut loaded = lock_mutex!(loaded);
+        while !*loaded {
+            loaded = ready
+                .wait(loaded)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+/// Runs `load` on a thread of its own named `name`, or right here if no
+/// thread can be started.
+pub fn spawn(name: &str, load: impl FnOnce() + Send + 'static) {
+    let load = Arc::new(Mutex::new(Some(load)));
+    let spawned = std::thread::Builder::new().name(name.to_string()).spawn({
+        let load = load.clone();
+        move || {
+            if let Some(load) = lock_mutex!(load).take() {
+                load();
+            }
+        }
+    });
+    if let Err(e) = spawned {
+        tracing::warn!("Failed to start {}, loading in the foreground: {}", name, e);
+        if let Some(load) = lock_mutex!(load).take() {
+            load();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_waiters_are_released_when_loaded() {
+        let gate = LoadGate::closed();
+        assert!(!gate.is_open());
+
+        let started = Instant::now();
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                let gate = gate.clone();
+                std::thread::spawn(move || {
+                    gate.wait();
+                    started.elapsed()
+                })
+            })
+            .collect();
+        spawn("test-loader", {
+            let gate = gate.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(50));
+                gate.open();
+            }
+        });
+        for waiter in waiters {
+            assert!(waiter.join().unwrap() >= Duration::from_millis(50));
+        }
+        assert!(gate.is_open());
+        // An open gate doesn't block
+        gate.wait();
+    }
+}
@@ -2,20 +2,101 @@
 //!
 //! This module provides file-based logging for translation operations,
 //! recording timestamps, languages, and translation content.
+//!
+//! How much is written follows the [`LogDetail`] setting; at
+//! [`LogDetail::Metadata`] entries keep the lengths of the texts instead of
+//! the texts. [`Logger::purge_content`] turns the entries already written
+//! into such entries.
 
+use crate::api::prompt::PromptContext;
+use crate::lock_mutex;
+use crate::utils::metrics::RequestMetrics;
+use crate::utils::provenance::Provenance;
 use chrono::Local;
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Entries waiting for the writer thread before new ones are dropped.
+const QUEUE_CAPACITY: usize = 256;
+
+/// Consecutive failed writes after which the log is reported as broken.
+const PERSISTENT_FAILURES: usize = 3;
+
+/// How long [`Logger::flush`] waits for the writer thread.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How much of a translation is logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogDetail {
+    /// Nothing is written to the log or the metrics file
+    Off,
+    /// Languages, lengths, model and timings, but no text
+    Metadata,
+    /// Source text, translation and reasoning as well
+    #[default]
+    Full,
+}
+
+impl LogDetail {
+    /// All levels, in display order.
+    pub const ALL: [LogDetail; 3] = [LogDetail::Off, LogDetail::Metadata, LogDetail::Full];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            LogDetail::Off => "Off",
+            LogDetail::Metadata => "Metadata only",
+            LogDetail::Full => "Full",
+        }
+    }
+}
+
+/// Work for the writer thread.
+enum Command {
+    /// A complete translation log entry
+    Entry(String),
+    /// One JSON line for the metrics file
+    Metrics(String),
+    /// Acknowledge once everything before it is written
+    Flush(mpsc::Sender<()>),
+    /// Strip the texts from the log, answering with the entries purged
+    Purge(mpsc::Sender<io::Result<usize>>),
+}
+
+/// State shared between the logger and its writer thread.
+#[derive(Default)]
+struct WriterState {
+    /// Entries dropped because the queue was full
+    dropped: AtomicUsize,
+    /// Error of a persistent write failure, until the app picks it up
+    failure: Mutex<Option<String>>,
+    /// Whether a failure has been reported, so it is only reported once
+    failure_reported: AtomicBool,
+}
 
 /// Logger for recording translation history to a file.
+///
+/// Entries are written by a dedicated thread, so logging never blocks the
+/// caller on a slow disk. When the writer falls behind, new entries are
+/// dropped and counted instead.
 pub struct Logger {
-    file: Mutex<std::fs::File>,
+    path: PathBuf,
+    tx: SyncSender<Command>,
+    state: Arc<WriterState>,
+    detail: Mutex<LogDetail>,
 }
 
 impl Logger {
     /// Creates a new logger that writes to the specified file path.
     ///
+    /// Request metrics go to a sibling `.metrics.jsonl` file.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the log file
@@ -25,12 +106,120 @@ impl Logger {
     /// Result containing the logger or an IO error
     pub fn new(path: &str) -> std::io::Result<Self> {
         tracing::info!("Initializing translation logger at: {}", path);
-        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let path = PathBuf::from(path);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metrics_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::metrics_path_for(&path))?;
+        Self::with_files(path, file, metrics_file, QUEUE_CAPACITY)
+    }
+
+    /// Starts the writer thread for already opened files.
+    fn with_files(
+        path: PathBuf,
+        file: File,
+        metrics_file: File,
+        capacity: usize,
+    ) -> std::io::Result<Self> {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let state = Arc::new(WriterState::default());
+        let writer_state = state.clone();
+        let log_path = path.clone();
+        std::thread::Builder::new()
+            .name("translation-logger".to_string())
+            .spawn(move || write_entries(rx, &log_path, file, metrics_file, &writer_state))?;
         Ok(Logger {
-            file: Mutex::new(file),
+            path,
+            tx,
+            state,
+            detail: Mutex::new(LogDetail::default()),
         })
     }
 
+    /// Path of the metrics file that goes with the log at `path`.
+    pub fn metrics_path_for(path: &Path) -> PathBuf {
+        path.with_extension("metrics.jsonl")
+    }
+
+    /// Path of the translation log.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the request metrics JSONL file.
+    pub fn metrics_path(&self) -> PathBuf {
+        Self::metrics_path_for(&self.path)
+    }
+
+    /// How much of a translation is logged.
+    pub fn detail(&self) -> LogDetail {
+        *lock_mutex!(self.detail)
+    }
+
+    /// Logs `detail` of the translations from now on.
+    pub fn set_detail(&self, detail: LogDetail) {
+        *lock_mutex!(self.detail) = detail;
+    }
+
+    /// Number of entries dropped because the writer fell behind.
+    pub fn dropped(&self) -> usize {
+        self.state.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the error once writes have kept failing.
+    ///
+    /// Only the first persistent failure is returned, and only once, so the
+    /// user is warned a single time.
+    pub fn take_write_failure(&self) -> Option<String> {
+        lock_mutex!(self.state.failure).take()
+    }
+
+    /// Waits until every entry queued so far is written, e.g. at shutdown.
+    pub fn flush(&self) {
+        let (ack_tx, ack_rx) = mpsc::channel();
+        if self.tx.send(Command::Flush(ack_tx)).is_err()
+            || ack_rx.recv_timeout(FLUSH_TIMEOUT).is_err()
+        {
+            tracing::warn!("Translation log could not be flushed");
+        }
+        let dropped = self.dropped();
+        if dropped > 0 {
+            tracing::warn!(
+                "{} log entries were dropped because the writer fell behind",
+                dropped
+            );
+        }
+    }
+
+    /// Strips the source texts, translations and reasoning from the entries
+    /// written so far, keeping the rest of them, and returns how many
+    /// entries were purged.
+    ///
+    /// Blocks until the writer thread has rewritten the whole log, so it
+    /// shouldn't be called on the UI thread.
+    pub fn purge_content(&self) -> io::Result<usize> {
+        let (result_tx, result_rx) = mpsc::channel();
+        let stopped = || io::Error::other("the translation log writer has stopped");
+        self.tx
+            .send(Command::Purge(result_tx))
+            .map_err(|_| stopped())?;
+        result_rx.recv().map_err(|_| stopped())?
+    }
+
+    /// Queues a command, dropping it if the writer is behind.
+    fn enqueue(&self, command: Command) {
+        match self.tx.try_send(command) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.state.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::warn!("Translation log writer has stopped");
+            }
+        }
+    }
+
     /// Logs a translation operation with metadata.
     ///
     /// # Arguments
@@ -39,7 +228,23 @@ impl Logger {
     /// * `target_lang` - Target language name
     /// * `source_text` - Original text
     /// * `translated` - Translated text
-    pub fn log(&self, source_lang: &str, target_lang: &str, source_text: &str, translated: &str) {
+    /// * `thinking` - Effective thinking mode used for the request
+    /// * `context` - Domain and audience hints used for the request
+    /// * `provenance` - Model and prompt the translation was made with
+    /// * `reasoning` - The model's reasoning, kept apart from the translation
+    #[allow(clippy::too_many_arguments)]
+    pub fn log(
+        &self,
+        source_lang: &str,
+        target_lang: &str,
+        source_text: &str,
+        translated: &str,
+        thinking: &str,
+        context: &PromptContext,
+        provenance: Option<&Provenance>,
+        reasoning: Option<&str>,
+    ) {
+        let detail = self.detail();
         let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
 
         // Log to tracing as well
@@ -48,23 +253,490 @@ impl Logger {
             target_language = target_lang,
             source_length = source_text.len(),
             translated_length = translated.len(),
+            thinking = thinking,
+            domain = %context.domain,
+            audience = %context.audience,
             "Translation completed"
         );
 
+        if detail == LogDetail::Off {
+            return;
+        }
+
+        let mut hints = String::new();
+        if !context.domain.is_empty() {
+            hints.push_str(&format!("Domain: {}\n", context.domain));
+        }
+        if !context.audience.is_empty() {
+            hints.push_str(&format!("Audience: {}\n", context.audience));
+        }
+        if let Some(provenance) = provenance {
+            hints.push_str(&provenance.header_lines());
+        }
+        if detail == LogDetail::Metadata {
+            let log_entry = format!(
+                "[{}]\nSource Language: {}\nTarget Language: {}\nThinking: {}\n{}{}{}\n",
+                timestamp,
+                source_lang,
+                target_lang,
+                thinking,
+                hints,
+                length_lines(source_text.chars().count(), translated.chars().count()),
+                separator()
+            );
+            self.enqueue(Command::Entry(log_entry));
+            return;
+        }
+        // One JSON string, so it can't be mistaken for the lines after it
+        if let Some(reasoning) = reasoning.filter(|r| !r.is_empty())
+            && let Ok(reasoning) = serde_json::to_string(reasoning)
+        {
+            hints.push_str(&format!("Reasoning: {}\n", reasoning));
+        }
+
         // Log to file
         let log_entry = format!(
-            "[{}]\nSource Language: {}\nTarget Language: {}\nSource Text: {}\nTranslation: {}\n{}\n",
+            "[{}]\nSource Language: {}\nTarget Language: {}\nThinking: {}\n{}Source Text: {}\nTranslation: {}\n{}\n",
             timestamp,
             source_lang,
             target_lang,
+            thinking,
+            hints,
             source_text,
             translated,
-            "-".repeat(80)
+            separator()
+        );
+
+        self.enqueue(Command::Entry(log_entry));
+    }
+
+    /// Appends the metrics of a finished request as one JSON line.
+    pub fn log_metrics(&self, metrics: &RequestMetrics) {
+        tracing::info!(
+            kind = ?metrics.kind,
+            outcome = ?metrics.outcome,
+            total_chars = metrics.total_chars,
+            duration_ms = metrics.duration_ms,
+            avg_chars_per_sec = ?metrics.avg_chars_per_sec,
+            min_chars_per_sec = ?metrics.min_chars_per_sec,
+            "Request metrics"
         );
 
-        if let Ok(mut file) = self.file.lock() {
-            let _ = file.write_all(log_entry.as_bytes());
-            let _ = file.flush();
+        if self.detail() == LogDetail::Off {
+            return;
         }
+        let Ok(line) = serde_json::to_string(metrics) else {
+            return;
+        };
+        self.enqueue(Command::Metrics(format!("{}\n", line)));
+    }
+}
+
+/// Writer thread: writes queued entries until the logger is dropped.
+fn write_entries(
+    rx: Receiver<Command>,
+    path: &Path,
+    mut file: File,
+    mut metrics_file: File,
+    state: &WriterState,
+) {
+    let mut failures = 0;
+
+    for command in rx {
+        let result = match command {
+            Command::Entry(entry) => write_whole(&mut file, &entry),
+            Command::Metrics(line) => write_whole(&mut metrics_file, &line),
+            Command::Flush(ack) => {
+                let _ = ack.send(());
+                continue;
+            }
+            Command::Purge(result_tx) => {
+                // The rewritten log replaces the file, so it is opened anew
+                let result = purge_log(path).and_then(|purged| {
+                    file = OpenOptions::new().append(true).open(path)?;
+                    Ok(purged)
+                });
+                let _ = result_tx.send(result);
+                continue;
+            }
+        };
+
+        match result {
+            Ok(()) => failures = 0,
+            Err(e) => {
+                failures += 1;
+                tracing::error!("Failed to write translation log: {}", e);
+                if failures >= PERSISTENT_FAILURES
+                    && !state.failure_reported.swap(true, Ordering::Relaxed)
+                {
+                    *lock_mutex!(state.failure) = Some(e.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Line that ends every entry, without its line break.
+fn separator() -> String {
+    "-".repeat(80)
+}
+
+/// The lines that stand in for the texts of an entry at
+/// [`LogDetail::Metadata`].
+fn length_lines(source_chars: usize, translation_chars: usize) -> String {
+    format!(
+        "Source Length: {} chars\nTranslation Length: {} chars\n",
+        source_chars, translation_chars
+    )
+}
+
+/// Rewrites the log at `path` without its texts, through a temporary file
+/// that replaces it when complete.
+fn purge_log(path: &Path) -> io::Result<usize> {
+    let temp_path = path.with_extension("purging");
+    let result = File::open(path).and_then(|log| {
+        let mut temp = BufWriter::new(File::create(&temp_path)?);
+        let purged = purge_content(BufReader::new(log), &mut temp)?;
+        temp.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(purged)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Copies the log from `reader` to `writer` line by line, replacing the
+/// source text and translation of every entry by their lengths and leaving
+/// out the reasoning, and returns how many entries had texts.
+///
+/// Entries already without texts are copied as they are.
+fn purge_content(mut reader: impl BufRead, mut writer: impl Write) -> io::Result<usize> {
+    /// Part of an entry a line belongs to.
+    #[derive(PartialEq)]
+    enum Part {
+        Header,
+        Source,
+        Translation,
+    }
+
+    /// Adds a line of text to the length of a text spanning lines.
+    fn add_line(length: &mut Option<usize>, text: &str) {
+        *length = Some(length.map_or(0, |chars| chars + 1) + text.chars().count());
+    }
+
+    let separator = separator();
+    let mut part = Part::Header;
+    let (mut source, mut translation) = (None, None);
+    let mut purged = 0;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let content = line.strip_suffix('\n').unwrap_or(&line);
+        match part {
+            Part::Header => {
+                if let Some(text) = content.strip_prefix("Source Text: ") {
+                    part = Part::Source;
+                    add_line(&mut source, text);
+                } else if !content.starts_with("Reasoning: ") {
+                    writer.write_all(line.as_bytes())?;
+                }
+            }
+            _ if content == separator => {
+                writer.write_all(
+                    length_lines(source.unwrap_or(0), translation.unwrap_or(0)).as_bytes(),
+                )?;
+                writer.write_all(line.as_bytes())?;
+                purged += 1;
+                part = Part::Header;
+                (source, translation) = (None, None);
+            }
+            Part::Source => match content.strip_prefix("Translation: ") {
+                Some(text) => {
+                    part = Part::Translation;
+                    add_line(&mut translation, text);
+                }
+                None => add_line(&mut source, content),
+            },
+            Part::Translation => add_line(&mut translation, content),
+        }
+        line.clear();
+    }
+    // The last entry may have been cut short
+    if part != Part::Header {
+        writer.write_all(length_lines(source.unwrap_or(0), translation.unwrap_or(0)).as_bytes())?;
+        purged += 1;
+    }
+    writer.flush()?;
+    Ok(purged)
+}
+
+/// Writes a complete entry with a single call so entries never interleave.
+fn write_whole(file: &mut File, entry: &str) -> std::io::Result<()> {
+    file.write_all(entry.as_bytes())?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THREADS: usize = 8;
+    const ENTRIES_PER_THREAD: usize = 50;
+
+    fn test_logger(name: &str, capacity: usize) -> (Logger, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("test_logger_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("translations.log");
+        let file = File::create(&path).unwrap();
+        let metrics_file = File::create(Logger::metrics_path_for(&path)).unwrap();
+        let logger = Logger::with_files(path, file, metrics_file, capacity).unwrap();
+        (logger, dir)
+    }
+
+    /// Logs from several threads at once.
+    fn hammer(logger: &Logger) {
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                scope.spawn(move || {
+                    for i in 0..ENTRIES_PER_THREAD {
+                        let text = format!("t{}-{} {}", thread, i, "x".repeat(i * 20));
+                        logger.log(
+                            "English",
+                            "Deutsch",
+                            &text,
+                            &text.to_uppercase(),
+                            "disabled",
+                            &PromptContext::default(),
+                            None,
+                            None,
+                        );
+                    }
+                });
+            }
+        });
+    }
+
+    /// Splits the log into entries, checking that each one is whole.
+    fn parse_entries(log: &str) -> Vec<String> {
+        let separator = format!("{}\n", "-".repeat(80));
+        let mut sources = Vec::new();
+        for entry in log.split(&separator).filter(|e| !e.is_empty()) {
+            let lines: Vec<&str> = entry.lines().collect();
+            assert_eq!(lines.len(), 6, "broken entry: {:?}", entry);
+            assert!(lines[0].starts_with('['));
+            assert_eq!(lines[1], "Source Language: English");
+            let source = lines[4].strip_prefix("Source Text: ").unwrap();
+            let translation = lines[5].strip_prefix("Translation: ").unwrap();
+            assert_eq!(translation, source.to_uppercase());
+            sources.push(source.to_string());
+        }
+        sources
+    }
+
+    #[test]
+    fn test_concurrent_entries_stay_whole() {
+        let (logger, dir) = test_logger("concurrent", THREADS * ENTRIES_PER_THREAD);
+        hammer(&logger);
+        logger.flush();
+
+        let log = std::fs::read_to_string(logger.path()).unwrap();
+        let mut sources = parse_entries(&log);
+        assert_eq!(logger.dropped(), 0);
+        assert_eq!(sources.len(), THREADS * ENTRIES_PER_THREAD);
+        sources.sort();
+        sources.dedup();
+        assert_eq!(sources.len(), THREADS * ENTRIES_PER_THREAD);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_overflow_drops_and_counts() {
+        let (logger, dir) = test_logger("overflow", 1);
+        hammer(&logger);
+        logger.flush();
+
+        let log = std::fs::read_to_string(logger.path()).unwrap();
+        let written = parse_entries(&log).len();
+        assert_eq!(written + logger.dropped(), THREADS * ENTRIES_PER_THREAD);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_concurrent_metrics_lines_are_valid_json() {
+        let (logger, dir) = test_logger("metrics", THREADS * ENTRIES_PER_THREAD);
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    let meter =
+                        crate::utils::metrics::ThroughputMeter::new(std::time::Instant::now());
+                    for _ in 0..ENTRIES_PER_THREAD {
+                        logger.log_metrics(&meter.finish(
+                            std::time::Instant::now(),
+                            "glm-4.7",
+                            "Deutsch",
+                            "disabled",
+                            crate::utils::metrics::RequestOutcome::Completed,
+                        ));
+                    }
+                });
+            }
+        });
+        logger.flush();
+
+        let metrics = std::fs::read_to_string(logger.metrics_path()).unwrap();
+        let lines: Vec<&str> = metrics.lines().collect();
+        assert_eq!(lines.len(), THREADS * ENTRIES_PER_THREAD);
+        for line in lines {
+            let json: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(json["target_language"], "Deutsch");
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_persistent_failure_reported_once() {
+        let dir = std::env::temp_dir().join("test_logger_failure");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Every write to /dev/full fails with "no space left on device"
+        let file = OpenOptions::new().write(true).open("/dev/full").unwrap();
+        let metrics_file = File::create(dir.join("metrics.jsonl")).unwrap();
+        let logger =
+            Logger::with_files(dir.join("full.log"), file, metrics_file, QUEUE_CAPACITY).unwrap();
+
+        let log_one = || {
+            logger.log(
+                "English",
+                "Deutsch",
+                "Hello",
+                "Hallo",
+                "disabled",
+                &PromptContext::default(),
+                None,
+                None,
+            )
+        };
+        log_one();
+        logger.flush();
+        assert_eq!(logger.take_write_failure(), None);
+
+        for _ in 0..PERSISTENT_FAILURES * 2 {
+            log_one();
+        }
+        logger.flush();
+        assert!(logger.take_write_failure().is_some());
+        assert_eq!(logger.take_write_failure(), None);
+
+        // Further failures are not reported again
+        log_one();
+        logger.flush();
+        assert_eq!(logger.take_write_failure(), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// Logs one translation whose texts are easy to look for.
+    fn log_secret(logger: &Logger) {
+        logger.log(
+            "English",
+            "Deutsch",
+            "SECRET source\non two lines",
+            "GEHEIM Übersetzung",
+            "enabled",
+            &PromptContext::default(),
+            None,
+            Some("SECRET reasoning"),
+        );
+        logger.log_metrics(
+            &crate::utils::metrics::ThroughputMeter::new(std::time::Instant::now()).finish(
+                std::time::Instant::now(),
+                "glm-4.7",
+                "Deutsch",
+                "enabled",
+                crate::utils::metrics::RequestOutcome::Completed,
+            ),
+        );
+    }
+
+    #[test]
+    fn test_metadata_level_writes_no_text() {
+        let (logger, dir) = test_logger("metadata", QUEUE_CAPACITY);
+        assert_eq!(logger.detail(), LogDetail::Full);
+
+        logger.set_detail(LogDetail::Off);
+        log_secret(&logger);
+        logger.flush();
+        assert!(std::fs::read(logger.path()).unwrap().is_empty());
+        assert!(std::fs::read(logger.metrics_path()).unwrap().is_empty());
+
+        logger.set_detail(LogDetail::Metadata);
+        log_secret(&logger);
+        logger.flush();
+        let log = std::fs::read(logger.path()).unwrap();
+        let metrics = std::fs::read(logger.metrics_path()).unwrap();
+        for written in [&log, &metrics] {
+            for secret in ["SECRET", "GEHEIM", "Übersetzung"] {
+                let found = written
+                    .windows(secret.len())
+                    .any(|window| window == secret.as_bytes());
+                assert!(!found, "{} was written", secret);
+            }
+        }
+        let log = String::from_utf8(log).unwrap();
+        assert!(log.contains("Target Language: Deutsch\n"), "{}", log);
+        assert!(
+            log.contains("Source Length: 26 chars\nTranslation Length: 18 chars\n"),
+            "{}",
+            log
+        );
+        assert_eq!(String::from_utf8(metrics).unwrap().lines().count(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_purge_keeps_metadata() {
+        let separator = separator();
+        let log = format!(
+            "[2025-01-01 10:00:00]\nSource Language: Auto-detected\nTarget Language: Deutsch\nThinking: enabled\nDomain: legal\nReasoning: \"Think\\nhard\"\nSource Text: Hello\nworld\nTranslation: Hallo Welt\n{sep}\n\
+             [2025-01-01 10:01:00]\nSource Language: English\nTarget Language: 日本語\nThinking: disabled\nSource Length: 3 chars\nTranslation Length: 2 chars\n{sep}\n\
+             [2025-01-01 10:02:00]\nSource Language: English\nTarget Language: 日本語\nThinking: disabled\nSource Text: Cut\nTranslation: 切",
+            sep = separator
+        );
+
+        let mut purged = Vec::new();
+        assert_eq!(purge_content(log.as_bytes(), &mut purged).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(purged).unwrap(),
+            format!(
+                "[2025-01-01 10:00:00]\nSource Language: Auto-detected\nTarget Language: Deutsch\nThinking: enabled\nDomain: legal\nSource Length: 11 chars\nTranslation Length: 10 chars\n{sep}\n\
+                 [2025-01-01 10:01:00]\nSource Language: English\nTarget Language: 日本語\nThinking: disabled\nSource Length: 3 chars\nTranslation Length: 2 chars\n{sep}\n\
+                 [2025-01-01 10:02:00]\nSource Language: English\nTarget Language: 日本語\nThinking: disabled\nSource Length: 3 chars\nTranslation Length: 1 chars\n",
+                sep = separator
+            )
+        );
+    }
+
+    #[test]
+    fn test_purge_rewrites_the_open_log() {
+        let (logger, dir) = test_logger("purge", QUEUE_CAPACITY);
+        log_secret(&logger);
+        log_secret(&logger);
+        assert_eq!(logger.purge_content().unwrap(), 2);
+
+        let log = std::fs::read_to_string(logger.path()).unwrap();
+        assert!(!log.contains("SECRET"), "{}", log);
+        assert_eq!(log.matches("Source Length: 26 chars\n").count(), 2);
+        assert!(crate::utils::history::parse_log(&log).is_empty());
+        assert!(!logger.path().with_extension("purging").exists());
+
+        // Entries logged afterwards go to the rewritten file
+        log_secret(&logger);
+        logger.flush();
+        let log = std::fs::read_to_string(logger.path()).unwrap();
+        assert_eq!(crate::utils::history::parse_log(&log).len(), 1);
+        assert_eq!(logger.purge_content().unwrap(), 1);
+        let _ = std::fs::remove_dir_all(dir);
     }
 }
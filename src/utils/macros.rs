@@ -5,8 +5,9 @@
 
 /// Helper macro to lock a mutex with consistent error handling.
 ///
-/// This macro provides a standardized way to acquire a mutex lock,
-/// panicking with a consistent error message if the mutex is poisoned.
+/// This macro provides a standardized way to acquire a mutex lock. A mutex
+/// poisoned by a panic on another thread is recovered instead of propagating
+/// the panic, so a failed background task cannot take the UI down with it.
 ///
 /// # Example
 ///
@@ -16,6 +17,9 @@
 #[macro_export]
 macro_rules! lock_mutex {
     ($mutex:expr) => {
-        $mutex.lock().expect("Mutex poisoned")
+        $mutex.lock().unwrap_or_else(|poisoned| {
+            tracing::warn!("Recovering poisoned mutex");
+            poisoned.into_inner()
+        })
     };
 }
@@ -0,0 +1,342 @@
+//! Streaming throughput measurement and per-request metrics.
+//!
+//! The throughput meter keeps a rolling window of received characters so a
+//! crawling stream can be told apart from a slow start, and summarizes the
+//! request into [`RequestMetrics`] for the JSONL metrics log.
+
+use crate::channel::channel::Warning;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How a translation request ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestOutcome {
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// What a request was for, so follow-ups are counted apart from translations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestKind {
+    #[default]
+    Translation,
+    /// Explanation of an existing translation
+    Explanation,
+    /// Check of which parts of a translation the model is unsure of
+    Confidence,
+}
+
+/// Tokens a request used, as the provider reported them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TokenUsage {
+    pub prompt: u32,
+    pub completion: u32,
+    /// Part of the completion spent reasoning, when reported apart
+    pub reasoning: Option<u32>,
+}
+
+/// Summary of a single translation request.
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestMetrics {
+    /// Local start time, RFC 3339
+    pub timestamp: String,
+    pub kind: RequestKind,
+    pub model: String,
+    pub target_language: String,
+    pub thinking: String,
+    pub outcome: RequestOutcome,
+    /// Characters received
+    pub total_chars: usize,
+    pub duration_ms: u64,
+    /// Time until the first content arrived
+    pub first_content_ms: Option<u64>,
+    /// Average characters per second after the first content
+    pub avg_chars_per_sec: Option<f64>,
+    /// Lowest rolling characters per second over a full window
+    pub min_chars_per_sec: Option<f64>,
+    /// Sent on the short text fast path, in one round trip without thinking
+    pub fast_path: bool,
+    /// Tokens used, when the provider reported them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<TokenUsage>,
+    /// Warnings shown with the translation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+/// Rolling characters-per-second meter for a streaming response.
+pub struct ThroughputMeter {
+    kind: RequestKind,
+    fast_path: bool,
+    started_at: Instant,
+    /// Local time of `started_at`, as logged
+    started_wall: chrono::DateTime<chrono::Local>,
+    first_content_at: Option<Instant>,
+    samples: VecDeque<(Instant, usize)>,
+    total_chars: usize,
+    min_cps: Option<f64>,
+    below_floor_since: Option<Instant>,
+    slow_reported: bool,
+}
+
+impl ThroughputMeter {
+    /// Length of the rolling window.
+    pub const WINDOW: Duration = Duration::from_secs(5);
+
+    /// Creates a meter for a request started at `now`.
+    pub fn new(now: Instant) -> Self {
+        ThroughputMeter {
+            kind: RequestKind::Translation,
+            fast_path: false,
+            started_at: now,
+            started_wall: chrono::Local::now(),
+            first_content_at: None,
+            samples: VecDeque::new(),
+            total_chars: 0,
+            min_cps: None,
+            below_floor_since: None,
+            slow_reported: false,
+        }
+    }
+
+    /// Attributes the request to `kind` instead of a translation.
+    pub fn with_kind(mut self, kind: RequestKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Tags the request as sent on the short text fast path.
+    pub fn with_fast_path(mut self, fast_path: bool) -> Self {
+        self.fast_path = fast_path;
+        self
+    }
+
+    /// Records a received chunk of `chars` characters.
+    pub fn record(&mut self, chars: usize, now: Instant) {
+        if chars == 0 {
+            return;
+        }
+        self.first_content_at.get_or_insert(now);
+        self.samples.push_back((now, chars));
+        self.total_chars += chars;
+    }
+
+    /// Rolling characters per second over the last [`Self::WINDOW`].
+    ///
+    /// Returns `None` until the first content arrives.
+    pub fn rate(&mut self, now: Instant) -> Option<f64> {
+        let first = self.first_content_at?;
+        while self
+            .samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > Self::WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        let since_first = now.duration_since(first);
+        let span = since_first
+            .min(Self::WINDOW)
+            .max(Duration::from_millis(500));
+        let chars: usize = self.samples.iter().map(|(_, chars)| chars).sum();
+        let rate = chars as f64 / span.as_secs_f64();
+
+        // Only full windows count towards the minimum, the first seconds ramp up
+        if since_first >= Self::WINDOW {
+            self.min_cps = Some(self.min_cps.map_or(rate, |min| min.min(rate)));
+        }
+        Some(rate)
+    }
+
+    /// Returns `true` once when the rate has stayed below `floor` for longer
+    /// than `grace` after the first content.
+    pub fn check_slow(&mut self, now: Instant, floor: f64, grace: Duration) -> bool {
+        let Some(rate) = self.rate(now) else {
+            return false;
+        };
+        if rate >= floor {
+            self.below_floor_since = None;
+            return false;
+        }
+
+        let since = *self.below_floor_since.get_or_insert(now);
+        if !self.slow_reported && now.duration_since(since) > grace {
+            self.slow_reported = true;
+            return true;
+        }
+        false
+    }
+
+    /// Summarizes the request once it has ended.
+    pub fn finish(
+        &self,
+        now: Instant,
+        model: &str,
+        target_language: &str,
+        thinking: &str,
+        outcome: RequestOutcome,
+    ) -> RequestMetrics {
+        let streaming = self
+            .first_content_at
+            .map(|first| now.duration_since(first))
+            // A single cached chunk has no meaningful rate
+            .filter(|elapsed| *elapsed >= Duration::from_millis(100));
+
+        RequestMetrics {
+            timestamp: self.started_wall.to_rfc3339(),
+            kind: self.kind,
+            model: model.to_string(),
+            target_language: target_language.to_string(),
+            thinking: thinking.to_string(),
+            outcome,
+            total_chars: self.total_chars,
+            duration_ms: now.duration_since(self.started_at).as_millis() as u64,
+            first_content_ms: self
+                .first_content_at
+                .map(|first| first.duration_since(self.started_at).as_millis() as u64),
+            avg_chars_per_sec: streaming.map(|d| self.total_chars as f64 / d.as_secs_f64()),
+            min_chars_per_sec: self.min_cps,
+            fast_path: self.fast_path,
+            tokens: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::channel::WarningKind;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn test_rate_uses_rolling_window() {
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::new(start);
+        assert_eq!(meter.rate(start), None);
+
+        // 10 chars per second for 10 seconds
+        for i in 0..10 {
+            meter.record(10, start + secs(i as f64));
+        }
+        let rate = meter.rate(start + secs(9.5)).unwrap();
+        assert!((rate - 10.0).abs() < 0.01, "rate was {}", rate);
+
+        // Nothing new for 10 seconds: the window empties
+        assert_eq!(meter.rate(start + secs(20.0)), Some(0.0));
+    }
+
+    #[test]
+    fn test_slow_stream_reported_once() {
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::new(start);
+        let grace = Duration::from_secs(15);
+        meter.record(3, start);
+
+        let mut reports = 0;
+        for s in 1..40 {
+            if meter.check_slow(start + secs(s as f64), 5.0, grace) {
+                reports += 1;
+                assert!(s > 15, "reported too early at {} s", s);
+            }
+        }
+        assert_eq!(reports, 1);
+    }
+
+    #[test]
+    fn test_fast_stream_not_reported() {
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::new(start);
+        for s in 0..30 {
+            let now = start + secs(s as f64);
+            meter.record(50, now);
+            assert!(!meter.check_slow(now, 5.0, Duration::from_secs(15)));
+        }
+    }
+
+    #[test]
+    fn test_finish_summarizes_request() {
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::new(start);
+        for i in 0..10 {
+            meter.record(20, start + secs(1.0 + i as f64));
+            meter.rate(start + secs(1.0 + i as f64));
+        }
+
+        let metrics = meter.finish(
+            start + secs(11.0),
+            "glm-4.7",
+            "English",
+            "omit",
+            RequestOutcome::Completed,
+        );
+        assert_eq!(metrics.total_chars, 200);
+        assert_eq!(metrics.first_content_ms, Some(1000));
+        assert_eq!(metrics.duration_ms, 11000);
+        assert!((metrics.avg_chars_per_sec.unwrap() - 20.0).abs() < 0.01);
+        assert!(metrics.min_chars_per_sec.is_some());
+
+        let json = serde_json::to_string(&metrics).unwrap();
+        assert!(json.contains("\"outcome\":\"completed\""));
+        assert!(json.contains("\"kind\":\"translation\""));
+        assert!(!json.contains("warnings"));
+    }
+
+    #[test]
+    fn test_warnings_are_logged_with_the_metrics() {
+        let start = Instant::now();
+        let mut metrics = ThroughputMeter::new(start).finish(
+            start + secs(1.0),
+            "glm-4.7",
+            "English",
+            "omit",
+            RequestOutcome::Completed,
+        );
+        metrics.warnings.push(Warning {
+            kind: WarningKind::SlowStream,
+            text: "Stream unusually slow".to_string(),
+        });
+        let json: serde_json::Value = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(json["warnings"][0]["kind"], "slow_stream");
+        assert_eq!(json["warnings"][0]["text"], "Stream unusually slow");
+    }
+
+    #[test]
+    fn test_finish_keeps_request_kind() {
+        let start = Instant::now();
+        let meter = ThroughputMeter::new(start).with_kind(RequestKind::Explanation);
+        let metrics = meter.finish(
+            start + secs(1.0),
+            "glm-4.7",
+            "English",
+            "omit",
+            RequestOutcome::Completed,
+        );
+        assert_eq!(metrics.kind, RequestKind::Explanation);
+        let json = serde_json::to_string(&metrics).unwrap();
+        assert!(json.contains("\"kind\":\"explanation\""));
+    }
+
+    #[test]
+    fn test_finish_without_content() {
+        let start = Instant::now();
+        let meter = ThroughputMeter::new(start);
+        let metrics = meter.finish(
+            start + secs(2.0),
+            "glm-4.7",
+            "English",
+            "omit",
+            RequestOutcome::Cancelled,
+        );
+        assert_eq!(metrics.first_content_ms, None);
+        assert_eq!(metrics.avg_chars_per_sec, None);
+        assert_eq!(metrics.min_chars_per_sec, None);
+    }
+}
@@ -1,5 +1,56 @@
+pub mod actions;
+pub mod alignment;
+pub mod announce;
+pub mod bidi;
 pub mod cache;
+pub mod cache_rules;
+pub mod chunker;
+pub mod code;
 pub mod config;
+pub mod config_watch;
+pub mod diagnostics;
+pub mod dialogs;
+pub mod documents;
+pub mod glossary;
+pub mod glyphs;
+pub mod history;
+pub mod hooks;
+pub mod instance;
+pub mod langcodes;
+pub mod langid;
+pub mod layout;
+pub mod links;
+pub mod list;
+pub mod loading;
 pub mod logger;
+pub mod metrics;
+pub mod offline_queue;
+pub mod paragraphs;
+pub mod paths;
+pub mod pdf;
+pub mod practice;
+pub mod pricing;
+pub mod provenance;
+pub mod query;
+pub mod redaction;
+pub mod repetition;
+pub mod retention;
+pub mod romanize;
+pub mod sanitize;
+pub mod script;
+pub mod segmenter;
+pub mod share;
+pub mod shared_text;
+pub mod spellcheck;
+pub mod structured;
+pub mod tasks;
+pub mod term_mining;
+pub mod tmx;
+pub mod typewriter;
+pub mod typography;
+pub mod undo;
+pub mod version;
+pub mod view_state;
+pub mod webpage;
 #[macro_use]
 pub mod macros;
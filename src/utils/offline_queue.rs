@@ -0,0 +1,277 @@
+//! Durable queue for translations requested while offline.
+//!
+//! Queued requests are persisted to a small JSON file in the data directory
+//! so they survive restarts. Nothing in this module runs a request; the UI
+//! only replays them after an explicit click.
+
+use crate::api::request::TranslationRequest;
+use crate::utils::paths;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+/// A translation request waiting for connectivity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedTranslation {
+    request: TranslationRequest,
+    /// Unix timestamp of when the request was queued
+    queued_at: i64,
+}
+
+/// Persistent FIFO queue of offline translation requests.
+pub struct OfflineQueue {
+    entries: VecDeque<QueuedTranslation>,
+    queue_file: PathBuf,
+    capacity: usize,
+}
+
+impl OfflineQueue {
+    /// Loads the queue from `queue_file`, starting empty if it is missing or unreadable.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue_file` - Path to the queue file for persistence
+    /// * `capacity` - Maximum number of queued requests
+    pub fn new(queue_file: PathBuf, capacity: usize) -> Self {
+        let entries = fs::read_to_string(&queue_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        let queue = OfflineQueue {
+            entries,
+            queue_file,
+            capacity,
+        };
+        if !queue.is_empty() {
+            tracing::info!("Loaded {} queued offline translations", queue.len());
+        }
+        queue
+    }
+
+    /// Returns the default queue file in the data directory.
+    pub fn default_path() -> PathBuf {
+        let dir = paths::data_dir();
+        let _ = fs::create_dir_all(&dir);
+        dir.join("offline_queue.json")
+    }
+
+    /// Appends a request, returning `false` if the queue is full.
+    pub fn push(&mut self, request: TranslationRequest) -> bool {
+        if self.entries.len() >= self.capacity {
+            tracing::warn!("Offline queue full ({} entries)", self.capacity);
+            return false;
+        }
+
+        self.entries.push_back(QueuedTranslation {
+            request,
+            queued_at: chrono::Utc::now().timestamp(),
+        });
+        self.save();
+        true
+    }
+
+    /// The oldest queued request, left in the queue until [`Self::pop_front`].
+    pub fn front(&self) -> Option<&TranslationRequest> {
+        self.entries.front().map(|entry| &entry.request)
+    }
+
+    /// Removes the oldest queued request once it has been processed.
+    pub fn pop_front(&mut self) -> Option<TranslationRequest> {
+        let entry = self.entries.pop_front()?;
+        self.save();
+        Some(entry.request)
+    }
+
+    /// Settles the oldest queued request after running it, returning
+    /// whether a run of the queue goes on with the next one.
+    ///
+    /// A finished request leaves the queue. One that failed stays at the
+    /// front and stops the run, so an error every request would run into,
+    /// such as a rejected API key, doesn't empty the queue.
+    pub fn settle_front(&mut self, finished: bool) -> bool {
+        if finished {
+            self.pop_front();
+        }
+        finished
+    }
+
+    /// Discards all queued requests.
+    #[cfg(test)]
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+
+    /// Removes and returns all queued requests, e.g. to offer an undo.
+    pub fn take_all(&mut self) -> Vec<QueuedTranslation> {
+        let entries = self.entries.drain(..).collect();
+        self.save();
+        entries
+    }
+
+    /// Puts back requests returned by [`Self::take_all`] ahead of newer ones.
+    pub fn restore(&mut self, entries: Vec<QueuedTranslation>) {
+        for entry in entries.into_iter().rev() {
+            self.entries.push_front(entry);
+        }
+        self.save();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the queue to disk (best effort).
+    fn save(&self) {
+        let result = serde_json::to_string(&self.entries)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&self.queue_file, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save offline queue: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::client::ThinkingMode;
+    use std::env;
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest {
+            source_text: text.to_string(),
+            target_language: "English".to_string(),
+            enable_keyword_analysis: false,
+            thinking: ThinkingMode::Omit,
+            code_language: None,
+            list_mode: false,
+            pivot_language: None,
+            context: Default::default(),
+            show_alternatives: false,
+            max_tokens: None,
+            temperature: None,
+            fast_path: false,
+        }
+    }
+
+    #[test]
+    fn test_queue_survives_restart() {
+        let queue_file = env::temp_dir().join("test_offline_queue_restart.json");
+        let _ = fs::remove_file(&queue_file);
+
+        {
+            let mut queue = OfflineQueue::new(queue_file.clone(), 10);
+            assert!(queue.push(request("一")));
+            assert!(queue.push(request("二")));
+        }
+
+        {
+            let mut queue = OfflineQueue::new(queue_file.clone(), 10);
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue.front().unwrap().source_text, "一");
+            assert_eq!(queue.pop_front().unwrap().source_text, "一");
+        }
+
+        let queue = OfflineQueue::new(queue_file.clone(), 10);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.front().unwrap().source_text, "二");
+
+        let _ = fs::remove_file(queue_file);
+    }
+
+    #[test]
+    fn test_queue_capacity() {
+        let queue_file = env::temp_dir().join("test_offline_queue_capacity.json");
+        let _ = fs::remove_file(&queue_file);
+
+        let mut queue = OfflineQueue::new(queue_file.clone(), 2);
+        assert!(queue.push(request("a")));
+        assert!(queue.push(request("b")));
+        assert!(!queue.push(request("c")));
+        assert_eq!(queue.len(), 2);
+
+        queue.clear();
+        assert!(queue.is_empty());
+        assert!(OfflineQueue::new(queue_file.clone(), 2).is_empty());
+
+        let _ = fs::remove_file(queue_file);
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_keeps_the_queue() {
+        use crate::api::client::ApiClient;
+        use crate::api::session::{SessionOptions, StreamEvent, TranslationSession};
+        use crate::api::translator::Translator;
+        use crate::api::transport::ScriptedTransport;
+        use crate::utils::cache::TranslationCache;
+        use futures_util::StreamExt;
+        use std::sync::Arc;
+
+        let queue_file = env::temp_dir().join("test_offline_queue_failed_run.json");
+        let cache_file = env::temp_dir().join("test_offline_queue_failed_run_cache.json");
+        let _ = fs::remove_file(&queue_file);
+        let _ = fs::remove_file(&cache_file);
+        let mut queue = OfflineQueue::new(queue_file.clone(), 10);
+        queue.push(request("a"));
+        queue.push(request("b"));
+
+        // The key is rejected, as it would be for every queued request
+        let transport =
+            Arc::new(ScriptedTransport::with_chunks(&["A"], "stop").rejecting_first(&[401, 401]));
+        let client = ApiClient::new("bad_key".to_string()).with_transport(transport.clone());
+        let session = TranslationSession::new(
+            Translator::with_client(client, Arc::new(TranslationCache::new(cache_file.clone()))),
+            SessionOptions::default(),
+        );
+        while let Some(request) = queue.front().cloned() {
+            let finished = session
+                .translate(request, None)
+                .any(|event| std::future::ready(matches!(event, StreamEvent::Completed)))
+                .await;
+            if !queue.settle_front(finished) {
+                break;
+            }
+        }
+
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(OfflineQueue::new(queue_file.clone(), 10).len(), 2);
+
+        // Once requests go through again, the run empties the queue
+        assert!(queue.settle_front(true));
+        assert_eq!(queue.front().unwrap().source_text, "b");
+
+        let _ = fs::remove_file(queue_file);
+        let _ = fs::remove_file(cache_file);
+    }
+
+    #[test]
+    fn test_take_all_and_restore() {
+        let queue_file = env::temp_dir().join("test_offline_queue_restore.json");
+        let _ = fs::remove_file(&queue_file);
+
+        let mut queue = OfflineQueue::new(queue_file.clone(), 10);
+        queue.push(request("a"));
+        queue.push(request("b"));
+
+        let taken = queue.take_all();
+        assert!(queue.is_empty());
+        assert!(OfflineQueue::new(queue_file.clone(), 10).is_empty());
+
+        queue.push(request("c"));
+        queue.restore(taken);
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop_front())
+            .map(|request| request.source_text)
+            .collect();
+        assert_eq!(order, vec!["a", "b", "c"]);
+
+        let _ = fs::remove_file(queue_file);
+    }
+}
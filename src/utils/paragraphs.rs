@@ -0,0 +1,209 @@
+//! Paragraph-aware buffer for streamed text.
+//!
+//! A streamed translation is a list of completed paragraphs followed by the
+//! live tail that is still growing. A paragraph is complete once a blank
+//! line ends it and the next paragraph has started, so the structure never
+//! changes its mind about a paragraph when more blank lines arrive. Views
+//! lay out the completed paragraphs once and only the tail every frame, and
+//! copy actions can leave out the unfinished tail.
+
+use std::ops::Range;
+
+/// Streamed text split into completed paragraphs and a live tail.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamingText {
+    text: String,
+    /// Byte offsets where the completed paragraphs end, each including the
+    /// blank lines after it
+    paragraph_ends: Vec<usize>,
+}
+
+/// Finds where the paragraph after a blank line starts, looking at the
+/// line breaks from byte `from` on.
+fn next_paragraph_start(text: &str, from: usize) -> Option<usize> {
+    let mut search = from;
+    while let Some(offset) = text[search..].find('\n') {
+        let line_end = search + offset;
+        let rest = &text[line_end + 1..];
+        let blank = &rest[..rest.len() - rest.trim_start().len()];
+        // A blank line, then the next paragraph has begun
+        if let Some(last_break) = blank.rfind('\n')
+            && blank.len() < rest.len()
+            && !text[..line_end].trim().is_empty()
+        {
+            return Some(line_end + 1 + last_break + 1);
+        }
+        search = line_end + 1;
+    }
+    None
+}
+
+impl StreamingText {
+    /// Creates the structure for text that has already arrived.
+    pub fn new(text: &str) -> Self {
+        let mut streaming = StreamingText::default();
+        streaming.push(text);
+        streaming
+    }
+
+    /// Appends a chunk, completing the paragraphs it finishes.
+    pub fn push(&mut self, chunk: &str) {
+        let tail_start = self.tail_range().start;
+        // A separator may begin in the whitespace already at the end
+        let mut from = tail_start + self.tail().trim_end().len();
+        self.text.push_str(chunk);
+        while let Some(start) = next_paragraph_start(&self.text, from) {
+            self.paragraph_ends.push(start);
+            from = start;
+        }
+    }
+
+    /// Replaces the whole text.
+    pub fn set(&mut self, text: &str) {
+        *self = StreamingText::new(text);
+    }
+
+    /// Removes all text.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.paragraph_ends.clear();
+    }
+
+    /// All the text, completed paragraphs and tail.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Byte range of the live tail.
+    fn tail_range(&self) -> Range<usize> {
+        self.paragraph_ends.last().copied().unwrap_or(0)..self.text.len()
+    }
+
+    /// The completed paragraphs, each with the blank lines after it.
+    #[cfg(test)]
+    pub fn paragraphs(&self) -> impl Iterator<Item = &str> {
+        let starts = std::iter::once(0).chain(self.paragraph_ends.iter().copied());
+        starts
+            .zip(self.paragraph_ends.iter().copied())
+            .map(|(start, end)| &self.text[start..end])
+    }
+
+    /// Number of completed paragraphs.
+    #[cfg(test)]
+    pub fn paragraph_count(&self) -> usize {
+        self.paragraph_ends.len()
+    }
+
+    /// The paragraph still being streamed.
+    pub fn tail(&self) -> &str {
+        &self.text[self.tail_range()]
+    }
+
+    /// Everything before the tail, the completed paragraphs with their
+    /// blank lines.
+    pub fn head(&self) -> &str {
+        &self.text[..self.tail_range().start]
+    }
+
+    /// The completed paragraphs, without the blank lines after the last.
+    pub fn completed(&self) -> &str {
+        self.head().trim_end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "First line\nof one.\n\n  Second\n\n\nThird, 中文。\n \nLast";
+
+    fn paragraphs(streaming: &StreamingText) -> Vec<&str> {
+        streaming.paragraphs().collect()
+    }
+
+    #[test]
+    fn test_paragraphs_and_tail() {
+        let streaming = StreamingText::new(TEXT);
+        assert_eq!(
+            paragraphs(&streaming),
+            vec![
+                "First line\nof one.\n\n",
+                "  Second\n\n\n",
+                "Third, 中文。\n \n"
+            ]
+        );
+        assert_eq!(streaming.tail(), "Last");
+        assert_eq!(streaming.head().len() + streaming.tail().len(), TEXT.len());
+        assert_eq!(
+            streaming.completed(),
+            "First line\nof one.\n\n  Second\n\n\nThird, 中文。"
+        );
+        assert_eq!(streaming.as_str(), TEXT);
+    }
+
+    #[test]
+    fn test_blank_lines_wait_for_the_next_paragraph() {
+        let mut streaming = StreamingText::new("One\n\n");
+        assert_eq!(streaming.paragraph_count(), 0);
+        assert_eq!(streaming.completed(), "");
+        streaming.push("\n");
+        assert_eq!(streaming.paragraph_count(), 0);
+        streaming.push("Two");
+        assert_eq!(paragraphs(&streaming), vec!["One\n\n\n"]);
+        assert_eq!(streaming.tail(), "Two");
+
+        // Leading blank lines belong to the first paragraph
+        let streaming = StreamingText::new("\n\nOne\n\nTwo");
+        assert_eq!(paragraphs(&streaming), vec!["\n\nOne\n\n"]);
+    }
+
+    #[test]
+    fn test_every_split_gives_the_same_paragraphs() {
+        let expected = StreamingText::new(TEXT);
+        let boundaries: Vec<usize> = TEXT.char_indices().map(|(i, _)| i).skip(1).collect();
+
+        // Every single split point
+        for &split in &boundaries {
+            let mut streaming = StreamingText::default();
+            streaming.push(&TEXT[..split]);
+            streaming.push(&TEXT[split..]);
+            assert_eq!(streaming, expected, "split at {}", split);
+        }
+
+        // Every pair of split points
+        for (i, &first) in boundaries.iter().enumerate() {
+            for &second in &boundaries[i + 1..] {
+                let mut streaming = StreamingText::default();
+                streaming.push(&TEXT[..first]);
+                streaming.push(&TEXT[first..second]);
+                streaming.push(&TEXT[second..]);
+                assert_eq!(streaming, expected, "split at {} and {}", first, second);
+            }
+        }
+
+        // Character by character, the paragraph count never goes down
+        let mut streaming = StreamingText::default();
+        let mut count = 0;
+        for c in TEXT.chars() {
+            streaming.push(&c.to_string());
+            assert!(streaming.paragraph_count() >= count);
+            count = streaming.paragraph_count();
+        }
+        assert_eq!(streaming, expected);
+    }
+
+    #[test]
+    fn test_set_and_clear() {
+        let mut streaming = StreamingText::new("a\n\nb");
+        streaming.set("c");
+        assert_eq!(streaming.paragraph_count(), 0);
+        assert_eq!(streaming.tail(), "c");
+        streaming.clear();
+        assert!(streaming.is_empty());
+        assert_eq!(streaming.tail(), "");
+    }
+}
@@ -0,0 +1,102 @@
+//! Folders the app keeps its own files in.
+//!
+//! Everything the app writes by itself lives in an `ai-translate` folder
+//! of the platform's config, data or cache directory. Files the user saves
+//! somewhere else are never looked at from here.
+//!
+//! Older versions kept their settings in the working directory, so which
+//! settings were used depended on where the app was started from. Such a
+//! file is only offered for import now, see [`AppPaths`].
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Name of the app's folder in each platform directory.
+const APP_DIR: &str = "ai-translate";
+
+/// Settings file older versions kept in the working directory.
+pub const LEGACY_CONFIG_FILE: &str = ".ai-translate-config.json";
+
+/// Where the settings are, decided once per run.
+///
+/// Loading, saving and watching the settings all use [`AppPaths::get`], so
+/// they never disagree about the file, whatever the working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppPaths {
+    /// The settings file, an absolute path
+    pub config_file: PathBuf,
+    /// Settings of an older version found in the working directory, still
+    /// to be imported
+    pub legacy_config_file: Option<PathBuf>,
+}
+
+impl AppPaths {
+    /// The paths of this run, resolved on first use.
+    pub fn get() -> &'static AppPaths {
+        static PATHS: OnceLock<AppPaths> = OnceLock::new();
+        PATHS.get_or_init(|| {
+            let working_dir = std::env::current_dir().unwrap_or_default();
+            let paths = AppPaths::resolve(&config_dir(), &working_dir);
+            tracing::info!(?paths, "Resolved the settings files");
+            paths
+        })
+    }
+
+    /// The paths for settings kept in `config_dir`, with a file of an older
+    /// version looked for in `working_dir`.
+    pub fn resolve(config_dir: &Path, working_dir: &Path) -> Self {
+        let config_file = config_dir.join("config.json");
+        let legacy_config_file = working_dir.join(LEGACY_CONFIG_FILE);
+        AppPaths {
+            config_file: std::path::absolute(&config_file).unwrap_or(config_file),
+            legacy_config_file: legacy_config_file
+                .is_file()
+                .then(|| std::path::absolute(&legacy_config_file).unwrap_or(legacy_config_file)),
+        }
+    }
+}
+
+fn app_dir(base: Option<PathBuf>) -> PathBuf {
+    base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR)
+}
+
+/// Settings and the translation cache.
+pub fn config_dir() -> PathBuf {
+    app_dir(dirs::config_dir())
+}
+
+/// Offline queue, practice statistics, diagnostics and log archives.
+pub fn data_dir() -> PathBuf {
+    app_dir(dirs::data_dir())
+}
+
+/// Generated audio.
+pub fn audio_cache_dir() -> PathBuf {
+    app_dir(dirs::cache_dir()).join("audio")
+}
+
+/// Diagnostic bundles.
+pub fn diagnostics_dir() -> PathBuf {
+    data_dir().join("diagnostics")
+}
+
+/// Rotated, compressed translation logs.
+pub fn log_archive_dir() -> PathBuf {
+    data_dir().join("logs")
+}
+
+/// Opens `dir` in the system file manager, creating it if needed.
+pub fn open_folder(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener).arg(dir).spawn()?;
+    Ok(())
+}
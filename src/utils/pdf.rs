@@ -0,0 +1,244 @@
+//! Text extraction from PDF documents.
+//!
+//! Text is extracted page by page with `pdf-extract` when the `pdf` feature is
+//! enabled. PDFs hard-wrap their lines, so lines that continue a sentence are
+//! merged back into paragraphs, and the pages are joined with page markers
+//! that the translation keeps in place.
+
+use std::path::Path;
+
+/// Pages with fewer readable characters than this on average count as
+/// scanned images without a text layer.
+const MIN_CHARS_PER_PAGE: usize = 20;
+
+/// Why no text could be taken from a PDF.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PdfError {
+    /// The file could not be parsed
+    #[error("Could not read the PDF: {0}")]
+    Unreadable(String),
+
+    /// The pages have (almost) no text, e.g. a scanned document
+    #[error(
+        "The PDF has no text layer, it looks like scanned images. Run it through OCR first, then translate the recognized text."
+    )]
+    ImageOnly,
+
+    /// Built without the `pdf` feature
+    #[error("This build does not include PDF support")]
+    #[cfg(not(feature = "pdf"))]
+    Unsupported,
+}
+
+/// Marker put between pages, e.g. `— page 3 —`.
+pub fn page_marker(page: usize) -> String {
+    format!("— page {} —", page)
+}
+
+/// Whether `line` is a page marker.
+pub fn is_page_marker(line: &str) -> bool {
+    line.trim()
+        .strip_prefix("— page ")
+        .and_then(|rest| rest.strip_suffix(" —"))
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether a line ends a sentence, so the next line can't continue it.
+fn ends_sentence(line: &str) -> bool {
+    line.ends_with([
+        '.', '!', '?', ':', ';', '…', '"', '”', '»', ')', '。', '！', '？', '：', '；', '」',
+    ])
+}
+
+/// Merges hard-wrapped lines back into paragraphs.
+///
+/// A line is joined with the next when it doesn't end a sentence and the
+/// next line starts with a lowercase letter. Words hyphenated at the line
+/// end are rejoined. Blank lines separate paragraphs, every other line
+/// break is kept.
+pub fn merge_wrapped_lines(text: &str) -> String {
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        let continues = !current.is_empty()
+            && !ends_sentence(&current)
+            && line.chars().next().is_some_and(char::is_lowercase);
+        if !continues {
+            if !current.is_empty() {
+                current.push('\n');
+            }
+        } else if let Some(stem) = current.strip_suffix('-')
+            && stem.chars().last().is_some_and(char::is_alphabetic)
+        {
+            current.truncate(stem.len());
+        } else {
+            current.push(' ');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+
+    paragraphs.join("\n\n")
+}
+
+/// Whether the pages have enough text to be more than scanned images.
+pub fn has_text_layer(pages: &[String]) -> bool {
+    let chars: usize = pages
+        .iter()
+        .map(|page| page.chars().filter(|c| c.is_alphanumeric()).count())
+        .sum();
+    chars >= MIN_CHARS_PER_PAGE * pages.len().max(1)
+}
+
+/// Joins the merged pages into one text, with a marker before every page
+/// of a multi-page document. Empty pages are left out.
+pub fn document_text(pages: &[String]) -> String {
+    let merged: Vec<(usize, String)> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| (i + 1, merge_wrapped_lines(page)))
+        .filter(|(_, text)| !text.is_empty())
+        .collect();
+
+    if pages.len() == 1 {
+        return merged.into_iter().map(|(_, text)| text).collect();
+    }
+    merged
+        .into_iter()
+        .map(|(page, text)| format!("{}\n\n{}", page_marker(page), text))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Extracts the text of the PDF at `path`, ready to be translated.
+///
+/// Runs the whole extraction, so call it off the UI thread.
+#[cfg(feature = "pdf")]
+pub fn extract(path: &Path) -> Result<String, PdfError> {
+    tracing::info!("Extracting PDF text from {:?}", path);
+    // The extractor panics on some malformed fonts instead of failing
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_by_pages(path))
+        .map_err(|_| PdfError::Unreadable("the document is malformed".to_string()))?
+        .map_err(|e| PdfError::Unreadable(e.to_string()))?;
+
+    tracing::debug!(pages = pages.len(), "Extracted PDF pages");
+    if !has_text_layer(&pages) {
+        tracing::warn!("PDF {:?} has no text layer", path);
+        return Err(PdfError::ImageOnly);
+    }
+    Ok(document_text(&pages))
+}
+
+/// Extracts the text of the PDF at `path`, ready to be translated.
+#[cfg(not(feature = "pdf"))]
+pub fn extract(_path: &Path) -> Result<String, PdfError> {
+    Err(PdfError::Unsupported)
+}
+
+/// Whether `path` looks like a PDF file.
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merges_wrapped_lines() {
+        let page = "The quick brown fox jumps\nover the lazy dog. It was\nnot amused.\n";
+        assert_eq!(
+            merge_wrapped_lines(page),
+            "The quick brown fox jumps over the lazy dog. It was not amused."
+        );
+    }
+
+    #[test]
+    fn test_keeps_breaks_after_sentences_and_before_capitals() {
+        // A heading and list items are not continued
+        let page = "Introduction\nThis is the first line.\nnext sentence starts lowercase\n- Item one\n- Item two";
+        assert_eq!(
+            merge_wrapped_lines(page),
+            "Introduction\nThis is the first line.\nnext sentence starts lowercase\n- Item one\n- Item two"
+        );
+    }
+
+    #[test]
+    fn test_rejoins_hyphenated_words() {
+        assert_eq!(
+            merge_wrapped_lines("an extra-\nordinary trans-\nlation"),
+            "an extraordinary translation"
+        );
+        // A dash on its own is not a hyphenation
+        assert_eq!(merge_wrapped_lines("one -\ntwo"), "one - two");
+    }
+
+    #[test]
+    fn test_blank_lines_separate_paragraphs() {
+        let page = "  First paragraph\ncontinues here.\n\n\n  Second paragraph\nends here.  ";
+        assert_eq!(
+            merge_wrapped_lines(page),
+            "First paragraph continues here.\n\nSecond paragraph ends here."
+        );
+    }
+
+    #[test]
+    fn test_document_text_marks_pages() {
+        let pages = vec![
+            "Page one text\nwraps here.".to_string(),
+            "   \n".to_string(),
+            "Page three.".to_string(),
+        ];
+        assert_eq!(
+            document_text(&pages),
+            "— page 1 —\n\nPage one text wraps here.\n\n— page 3 —\n\nPage three."
+        );
+
+        // A single page needs no marker
+        assert_eq!(
+            document_text(&["Just one\npage.".to_string()]),
+            "Just one page."
+        );
+    }
+
+    #[test]
+    fn test_page_markers() {
+        assert!(is_page_marker(&page_marker(3)));
+        assert!(is_page_marker("  — page 12 —"));
+        assert!(!is_page_marker("— page —"));
+        assert!(!is_page_marker("— page three —"));
+        assert!(!is_page_marker("page 3"));
+    }
+
+    #[test]
+    fn test_detects_scanned_documents() {
+        let scanned = vec!["\n\n".to_string(), " 3 ".to_string()];
+        assert!(!has_text_layer(&scanned));
+        assert!(!has_text_layer(&[]));
+
+        let text = vec![
+            "A page with a real text layer on it.".to_string(),
+            "Another page with plenty of words.".to_string(),
+        ];
+        assert!(has_text_layer(&text));
+    }
+
+    #[test]
+    fn test_is_pdf() {
+        assert!(is_pdf(Path::new("paper.pdf")));
+        assert!(is_pdf(Path::new("/tmp/SCAN.PDF")));
+        assert!(!is_pdf(Path::new("notes.txt")));
+        assert!(!is_pdf(Path::new("pdf")));
+    }
+}
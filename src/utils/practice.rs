@@ -0,0 +1,363 @@
+//! Practice mode: the translation stays hidden until it is revealed.
+//!
+//! A [`PracticeCard`] tracks which sentences of a finished translation have
+//! been revealed and how each revealed item was graded. Grades are tallied
+//! per day in [`PracticeStats`], a small JSON file in the data directory, so
+//! the comprehension rate can be followed over time.
+
+use crate::utils::paths;
+use crate::utils::segmenter;
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Placeholder shown instead of every hidden character.
+const HIDDEN_CHAR: char = '•';
+
+/// How the learner rated their understanding of a revealed item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    GotIt,
+    MissedIt,
+}
+
+/// A revealed run of sentences and its grade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevealedItem {
+    /// Indices of the revealed sentences
+    pub sentences: Range<usize>,
+    pub grade: Option<Grade>,
+}
+
+/// Reveal state of one translation.
+#[derive(Debug, Clone)]
+pub struct PracticeCard {
+    /// Byte ranges of the sentences of the translation
+    sentences: Vec<Range<usize>>,
+    items: Vec<RevealedItem>,
+}
+
+impl PracticeCard {
+    /// Creates a card with every sentence of `translation` hidden.
+    ///
+    /// `language` is the translation's language, used as segmenter hint.
+    pub fn new(translation: &str, language: &str) -> Self {
+        PracticeCard {
+            sentences: segmenter::split_sentences(translation, Some(language)),
+            items: Vec::new(),
+        }
+    }
+
+    /// Number of sentences revealed so far.
+    fn revealed(&self) -> usize {
+        self.items.last().map_or(0, |item| item.sentences.end)
+    }
+
+    /// Whether the whole translation is visible.
+    pub fn is_revealed(&self) -> bool {
+        self.revealed() >= self.sentences.len()
+    }
+
+    /// Number of sentences in the translation.
+    pub fn sentence_count(&self) -> usize {
+        self.sentences.len()
+    }
+
+    /// Reveals the next hidden sentence as an item of its own.
+    pub fn reveal_next(&mut self) {
+        let start = self.revealed();
+        if start < self.sentences.len() {
+            self.items.push(RevealedItem {
+                sentences: start..start + 1,
+                grade: None,
+            });
+        }
+    }
+
+    /// Reveals every hidden sentence as one item.
+    pub fn reveal_all(&mut self) {
+        let start = self.revealed();
+        if start < self.sentences.len() {
+            self.items.push(RevealedItem {
+                sentences: start..self.sentences.len(),
+                grade: None,
+            });
+        }
+    }
+
+    /// The most recently revealed item, while it is still ungraded.
+    pub fn pending_item(&self) -> Option<&RevealedItem> {
+        self.items.last().filter(|item| item.grade.is_none())
+    }
+
+    /// Grades the most recently revealed item.
+    ///
+    /// # Returns
+    ///
+    /// Whether the grade was recorded; an item is graded only once
+    pub fn grade(&mut self, grade: Grade) -> bool {
+        match self.items.last_mut() {
+            Some(item) if item.grade.is_none() => {
+                item.grade = Some(grade);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The translation with every hidden sentence obscured.
+    ///
+    /// Hidden characters become dots and whitespace is kept, so the shape of
+    /// the text is visible but not its words. See [`obscure`].
+    pub fn masked(&self, translation: &str) -> String {
+        let Some(first_hidden) = self.sentences.get(self.revealed()) else {
+            return translation.to_string();
+        };
+        let (visible, hidden) = translation.split_at(first_hidden.start);
+        format!("{}{}", visible, obscure(hidden))
+    }
+}
+
+/// Obscures `text`, keeping only its whitespace.
+pub fn obscure(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_whitespace() { c } else { HIDDEN_CHAR })
+        .collect()
+}
+
+/// Grades recorded on one day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayTally {
+    pub got_it: u32,
+    pub missed: u32,
+}
+
+impl DayTally {
+    pub fn total(&self) -> u32 {
+        self.got_it + self.missed
+    }
+
+    /// Share of items understood, `None` if nothing was graded.
+    pub fn rate(&self) -> Option<f64> {
+        (self.total() > 0).then(|| self.got_it as f64 / self.total() as f64)
+    }
+
+    fn add(&mut self, other: DayTally) {
+        self.got_it += other.got_it;
+        self.missed += other.missed;
+    }
+}
+
+/// Persistent per-day tally of practice grades.
+pub struct PracticeStats {
+    /// Tallies keyed by local date, `YYYY-MM-DD`
+    days: BTreeMap<String, DayTally>,
+    stats_file: PathBuf,
+}
+
+impl PracticeStats {
+    /// Loads the statistics from `stats_file`, starting empty if it is missing or unreadable.
+    pub fn new(stats_file: PathBuf) -> Self {
+        let days = fs::read_to_string(&stats_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        PracticeStats { days, stats_file }
+    }
+
+    /// Returns the default statistics file in the data directory.
+    pub fn default_path() -> PathBuf {
+        let dir = paths::data_dir();
+        let _ = fs::create_dir_all(&dir);
+        dir.join("practice_stats.json")
+    }
+
+    /// Counts a grade for `day`.
+    pub fn record(&mut self, grade: Grade, day: NaiveDate) {
+        let tally = self.days.entry(day.to_string()).or_default();
+        match grade {
+            Grade::GotIt => tally.got_it += 1,
+            Grade::MissedIt => tally.missed += 1,
+        }
+        self.save();
+    }
+
+    /// Grades recorded from `since` on, or all of them for `None`.
+    pub fn tally_since(&self, since: Option<NaiveDate>) -> DayTally {
+        let since = since.map(|day| day.to_string()).unwrap_or_default();
+        let mut total = DayTally::default();
+        for tally in self.days.range(since..).map(|(_, tally)| *tally) {
+            total.add(tally);
+        }
+        total
+    }
+
+    /// One-line comprehension summary for today, the last seven days and
+    /// overall, e.g. "Today 3/4 · 7 days 80% · overall 76% of 120".
+    ///
+    /// Returns `None` before anything was graded.
+    pub fn summary(&self, today: NaiveDate) -> Option<String> {
+        let overall = self.tally_since(None);
+        let overall_rate = overall.rate()?;
+        let today_tally = self.tally_since(Some(today));
+        let week = self.tally_since(today.checked_sub_days(Days::new(6)));
+
+        let mut parts = Vec::new();
+        if today_tally.total() > 0 {
+            parts.push(format!(
+                "Today {}/{}",
+                today_tally.got_it,
+                today_tally.total()
+            ));
+        }
+        if let Some(rate) = week.rate() {
+            parts.push(format!("7 days {:.0}%", rate * 100.0));
+        }
+        parts.push(format!(
+            "overall {:.0}% of {}",
+            overall_rate * 100.0,
+            overall.total()
+        ));
+        Some(parts.join(" · "))
+    }
+
+    /// Writes the statistics to disk (best effort).
+    fn save(&self) {
+        let result = serde_json::to_string_pretty(&self.days)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&self.stats_file, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save practice statistics: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSLATION: &str = "Guten Morgen. Wie geht es dir? Mir geht es gut.";
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+    }
+
+    fn stats(name: &str) -> PracticeStats {
+        let path = std::env::temp_dir().join(format!("test_practice_{}.json", name));
+        let _ = fs::remove_file(&path);
+        PracticeStats::new(path)
+    }
+
+    #[test]
+    fn test_everything_starts_hidden() {
+        let card = PracticeCard::new(TRANSLATION, "Deutsch");
+        assert_eq!(card.sentence_count(), 3);
+        assert!(!card.is_revealed());
+        assert_eq!(
+            card.masked(TRANSLATION),
+            "••••• ••••••• ••• •••• •• •••• ••• •••• •• ••••"
+        );
+        assert_eq!(card.pending_item(), None);
+    }
+
+    #[test]
+    fn test_reveal_one_sentence_at_a_time() {
+        let mut card = PracticeCard::new(TRANSLATION, "Deutsch");
+        card.reveal_next();
+        assert_eq!(
+            card.masked(TRANSLATION),
+            "Guten Morgen. ••• •••• •• •••• ••• •••• •• ••••"
+        );
+        assert_eq!(card.pending_item().unwrap().sentences, 0..1);
+
+        card.reveal_next();
+        card.reveal_next();
+        assert!(card.is_revealed());
+        assert_eq!(card.masked(TRANSLATION), TRANSLATION);
+
+        // Nothing left to reveal
+        card.reveal_next();
+        card.reveal_all();
+        assert_eq!(card.pending_item().unwrap().sentences, 2..3);
+    }
+
+    #[test]
+    fn test_reveal_all_is_one_item() {
+        let mut card = PracticeCard::new(TRANSLATION, "Deutsch");
+        card.reveal_next();
+        assert!(card.grade(Grade::GotIt));
+        card.reveal_all();
+        assert!(card.is_revealed());
+        assert_eq!(card.pending_item().unwrap().sentences, 1..3);
+    }
+
+    #[test]
+    fn test_items_are_graded_once() {
+        let mut card = PracticeCard::new(TRANSLATION, "Deutsch");
+        // Nothing revealed, nothing to grade
+        assert!(!card.grade(Grade::GotIt));
+
+        card.reveal_next();
+        assert!(card.grade(Grade::MissedIt));
+        assert!(!card.grade(Grade::GotIt));
+        assert_eq!(card.pending_item(), None);
+    }
+
+    #[test]
+    fn test_multiline_masking_keeps_line_breaks() {
+        let text = "Erste Zeile.\nZweite Zeile.";
+        let mut card = PracticeCard::new(text, "de");
+        card.reveal_next();
+        assert_eq!(card.masked(text), "Erste Zeile.\n•••••• ••••••");
+    }
+
+    #[test]
+    fn test_stats_tally_by_day() {
+        let mut stats = stats("tally");
+        stats.record(Grade::GotIt, day(1));
+        stats.record(Grade::MissedIt, day(1));
+        stats.record(Grade::GotIt, day(8));
+        stats.record(Grade::GotIt, day(10));
+
+        assert_eq!(
+            stats.tally_since(None),
+            DayTally {
+                got_it: 3,
+                missed: 1
+            }
+        );
+        assert_eq!(stats.tally_since(Some(day(8))).total(), 2);
+        assert_eq!(stats.tally_since(Some(day(11))).rate(), None);
+    }
+
+    #[test]
+    fn test_stats_summary() {
+        let mut stats = stats("summary");
+        assert_eq!(stats.summary(day(10)), None);
+
+        stats.record(Grade::MissedIt, day(1));
+        stats.record(Grade::GotIt, day(9));
+        stats.record(Grade::GotIt, day(10));
+        stats.record(Grade::MissedIt, day(10));
+        assert_eq!(
+            stats.summary(day(10)).as_deref(),
+            Some("Today 1/2 · 7 days 67% · overall 50% of 4")
+        );
+        assert_eq!(stats.summary(day(20)).as_deref(), Some("overall 50% of 4"));
+    }
+
+    #[test]
+    fn test_stats_persist() {
+        let path = std::env::temp_dir().join("test_practice_persist.json");
+        let _ = fs::remove_file(&path);
+        let mut stats = PracticeStats::new(path.clone());
+        stats.record(Grade::GotIt, day(5));
+
+        let reloaded = PracticeStats::new(path.clone());
+        assert_eq!(reloaded.tally_since(None).got_it, 1);
+        let _ = fs::remove_file(&path);
+    }
+}
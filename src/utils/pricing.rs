@@ -0,0 +1,339 @@
+//! Cost of a translation, estimated before it is sent.
+//!
+//! Nothing here is exact. Input tokens are estimated from the characters of
+//! the text, more per character for scripts tokenizers split finer than
+//! Latin. Output tokens follow from the ratio of output to input tokens the
+//! language pair came to in earlier translations, learned in
+//! [`OutputRatios`], or from a configured ratio until there are any. The
+//! estimate is a range around that ratio, narrower once it was learned.
+//!
+//! Prices are entered per model; without them no estimate is made.
+
+use crate::utils::langid;
+use crate::utils::metrics::TokenUsage;
+use crate::utils::paths;
+use crate::utils::script::{Script, script_of};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Shortest source text, in characters, whose cost is estimated.
+pub const MIN_ESTIMATED_CHARS: usize = 2_000;
+
+/// Tokens of the instructions sent along with every text.
+const PROMPT_TOKENS: u32 = 200;
+
+/// Factor the output may be off the expected ratio by, either way, once the
+/// ratio was learned.
+const LEARNED_SPREAD: f64 = 1.25;
+
+/// Factor the output may be off the configured ratio by, either way.
+const DEFAULT_SPREAD: f64 = 2.0;
+
+/// Translations after which a new one stops weighing more than the average
+/// of the older ones, so the learned ratio follows changes of the model.
+const LEARNING_WINDOW: u32 = 10;
+
+/// Prices of a model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    /// Shown after the amounts, e.g. `USD`
+    pub currency: String,
+    /// Price per 1000 input tokens
+    pub input_per_1k: f64,
+    /// Price per 1000 output tokens, reasoning included
+    pub output_per_1k: f64,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Pricing {
+            currency: "USD".to_string(),
+            input_per_1k: 0.0,
+            output_per_1k: 0.0,
+        }
+    }
+}
+
+impl Pricing {
+    /// Whether any price was entered.
+    pub fn is_set(&self) -> bool {
+        self.input_per_1k > 0.0 || self.output_per_1k > 0.0
+    }
+
+    /// Cost of a request using these tokens.
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        (input_tokens as f64 * self.input_per_1k + output_tokens as f64 * self.output_per_1k)
+            / 1000.0
+    }
+}
+
+/// Rough number of tokens of `text`.
+///
+/// About four characters a token in Latin script, as well as for digits,
+/// punctuation and spaces, a token per character in Chinese, Japanese and
+/// Korean, and in between for the other scripts.
+pub fn estimate_tokens(text: &str) -> u32 {
+    // In hundredths of a token, so the sum is exact
+    let hundredths: u64 = text
+        .chars()
+        .map(|c| match script_of(c) {
+            None | Some(Script::Latin) => 25,
+            Some(Script::Cyrillic | Script::Greek | Script::Arabic | Script::Hebrew) => 40,
+            Some(Script::Devanagari | Script::Thai) => 50,
+            Some(Script::Cjk) => 100,
+        })
+        .sum();
+    hundredths.div_ceil(100) as u32
+}
+
+/// Expected cost of translating a text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    /// Input tokens, instructions included
+    pub input_tokens: u32,
+    pub min_output_tokens: u32,
+    pub max_output_tokens: u32,
+    pub min_cost: f64,
+    pub max_cost: f64,
+    pub currency: String,
+    /// The output ratio was learned from earlier translations
+    pub learned: bool,
+}
+
+impl Estimate {
+    /// Estimates the cost of translating `text` at `pricing`.
+    ///
+    /// The output is expected at `learned_ratio` output tokens per input
+    /// token when the language pair has one, else at `default_ratio`.
+    pub fn new(
+        text: &str,
+        pricing: &Pricing,
+        learned_ratio: Option<f64>,
+        default_ratio: f64,
+    ) -> Self {
+        let input_tokens = estimate_tokens(text) + PROMPT_TOKENS;
+        let (ratio, spread) = match learned_ratio {
+            Some(ratio) => (ratio, LEARNED_SPREAD),
+            None => (default_ratio, DEFAULT_SPREAD),
+        };
+        let output = |ratio: f64| (input_tokens as f64 * ratio).round() as u32;
+        let min_output_tokens = output(ratio / spread);
+        let max_output_tokens = output(ratio * spread);
+        Estimate {
+            input_tokens,
+            min_output_tokens,
+            max_output_tokens,
+            min_cost: pricing.cost(input_tokens, min_output_tokens),
+            max_cost: pricing.cost(input_tokens, max_output_tokens),
+            currency: pricing.currency.clone(),
+            learned: learned_ratio.is_some(),
+        }
+    }
+
+    /// The estimate in a line, e.g. "≈ 0.02–0.05 USD".
+    pub fn summary(&self) -> String {
+        format!(
+            "≈ {}–{} {}",
+            format_amount(self.min_cost),
+            format_amount(self.max_cost),
+            self.currency
+        )
+    }
+
+    /// The tokens the estimate is made of, e.g. "12.5k tokens in,
+    /// 8.3k–20.8k out".
+    pub fn details(&self) -> String {
+        format!(
+            "{} tokens in, {}–{} out{}",
+            format_tokens(self.input_tokens),
+            format_tokens(self.min_output_tokens),
+            format_tokens(self.max_output_tokens),
+            if self.learned {
+                ", as earlier translations of this language pair"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// An amount of money with two decimals, or more for amounts below a cent.
+pub fn format_amount(amount: f64) -> String {
+    if amount > 0.0 && amount < 0.01 {
+        format!("{:.4}", amount)
+    } else {
+        format!("{:.2}", amount)
+    }
+}
+
+/// A number of tokens, in thousands from 1000 on.
+fn format_tokens(tokens: u32) -> String {
+    match tokens {
+        0..1000 => tokens.to_string(),
+        1000..100_000 => format!("{:.1}k", tokens as f64 / 1000.0),
+        _ => format!("{}k", tokens / 1000),
+    }
+}
+
+/// Key of the language pair of translating `source_text` into
+/// `target_language`, e.g. "Deutsch → English".
+///
+/// Sources whose language can't be told apart share the pair of any
+/// language into the target.
+pub fn language_pair(source_text: &str, target_language: &str) -> String {
+    let source = langid::detect(source_text)
+        .filter(|guess| guess.is_confident())
+        .map_or("*", |guess| guess.language);
+    format!("{} → {}", source, target_language)
+}
+
+/// Output tokens per input token of a language pair.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct LearnedRatio {
+    ratio: f64,
+    /// Translations it was learned from
+    translations: u32,
+}
+
+/// Output ratios learned per language pair, kept in a small JSON file in
+/// the data directory.
+pub struct OutputRatios {
+    /// Ratios keyed by [`language_pair`]
+    pairs: BTreeMap<String, LearnedRatio>,
+    ratios_file: PathBuf,
+}
+
+impl OutputRatios {
+    /// Loads the ratios from `ratios_file`, starting empty if it is missing
+    /// or unreadable.
+    pub fn new(ratios_file: PathBuf) -> Self {
+        let pairs = fs::read_to_string(&ratios_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        OutputRatios { pairs, ratios_file }
+    }
+
+    /// Returns the default ratios file in the data directory.
+    pub fn default_path() -> PathBuf {
+        let dir = paths::data_dir();
+        let _ = fs::create_dir_all(&dir);
+        dir.join("output_ratios.json")
+    }
+
+    /// Learned output tokens per input token of `pair`, if it was
+    /// translated before.
+    pub fn ratio(&self, pair: &str) -> Option<f64> {
+        self.pairs.get(pair).map(|learned| learned.ratio)
+    }
+
+    /// Learns from the tokens a completed translation of `pair` used.
+    pub fn record(&mut self, pair: &str, usage: TokenUsage) {
+        if usage.prompt == 0 {
+            return;
+        }
+        let ratio = usage.completion as f64 / usage.prompt as f64;
+        let learned = self.pairs.entry(pair.to_string()).or_insert(LearnedRatio {
+            ratio,
+            translations: 0,
+        });
+        learned.translations += 1;
+        let weight = 1.0 / learned.translations.min(LEARNING_WINDOW) as f64;
+        learned.ratio += (ratio - learned.ratio) * weight;
+        self.save();
+    }
+
+    /// Writes the ratios to disk (best effort).
+    fn save(&self) {
+        let result = serde_json::to_string_pretty(&self.pairs)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&self.ratios_file, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save output ratios: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pricing() -> Pricing {
+        Pricing {
+            currency: "EUR".to_string(),
+            input_per_1k: 0.5,
+            output_per_1k: 2.0,
+        }
+    }
+
+    fn usage(prompt: u32, completion: u32) -> TokenUsage {
+        TokenUsage {
+            prompt,
+            completion,
+            reasoning: None,
+        }
+    }
+
+    #[test]
+    fn test_tokens_depend_on_the_script() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens(&"word ".repeat(100)), 125);
+        assert_eq!(estimate_tokens(&"слово ".repeat(100)), 225);
+        assert_eq!(estimate_tokens(&"翻译".repeat(100)), 200);
+    }
+
+    #[test]
+    fn test_estimate_range() {
+        let text = "a".repeat(3200);
+        let estimate = Estimate::new(&text, &pricing(), None, 1.5);
+        assert_eq!(estimate.input_tokens, 1000);
+        assert_eq!(estimate.min_output_tokens, 750);
+        assert_eq!(estimate.max_output_tokens, 3000);
+        assert!((estimate.min_cost - 2.0).abs() < 1e-9);
+        assert!((estimate.max_cost - 6.5).abs() < 1e-9);
+        assert_eq!(estimate.summary(), "≈ 2.00–6.50 EUR");
+        assert_eq!(estimate.details(), "1.0k tokens in, 750–3.0k out");
+
+        // A learned ratio narrows the range
+        let learned = Estimate::new(&text, &pricing(), Some(1.0), 1.5);
+        assert_eq!(learned.min_output_tokens, 800);
+        assert_eq!(learned.max_output_tokens, 1250);
+        assert!(learned.details().ends_with("of this language pair"));
+    }
+
+    #[test]
+    fn test_cost_of_tokens() {
+        assert!(!Pricing::default().is_set());
+        assert!((pricing().cost(2000, 500) - 2.0).abs() < 1e-9);
+        assert_eq!(format_amount(0.004), "0.0040");
+        assert_eq!(format_amount(0.0), "0.00");
+        assert_eq!(format_amount(12.345), "12.35");
+        assert_eq!(format_tokens(250_000), "250k");
+    }
+
+    #[test]
+    fn test_ratios_are_learned_per_pair() {
+        let path = std::env::temp_dir().join("test_pricing_ratios.json");
+        let _ = fs::remove_file(&path);
+        let pair = language_pair("Das ist der Text, und er ist nicht lang.", "English");
+        assert_eq!(pair, "Deutsch → English");
+        assert_eq!(language_pair("12345", "English"), "* → English");
+
+        let mut ratios = OutputRatios::new(path.clone());
+        assert_eq!(ratios.ratio(&pair), None);
+        ratios.record(&pair, usage(1000, 2000));
+        assert_eq!(ratios.ratio(&pair), Some(2.0));
+        ratios.record(&pair, usage(1000, 1000));
+        assert_eq!(ratios.ratio(&pair), Some(1.5));
+        // Nothing to learn without input tokens
+        ratios.record(&pair, usage(0, 1000));
+        assert_eq!(ratios.ratio(&pair), Some(1.5));
+        assert_eq!(ratios.ratio("* → English"), None);
+
+        // Kept across runs
+        assert_eq!(OutputRatios::new(path.clone()).ratio(&pair), Some(1.5));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,250 @@
+//! Where a translation came from.
+//!
+//! Cached and logged translations record the model, the kind of prompt,
+//! the glossary terms and the app version they were made with, so a stale
+//! entry can be told apart from a fresh one. Entries written before this
+//! was recorded simply have none.
+//!
+//! In the translation log the fields are header lines of their own, read
+//! back by [`Provenance::read_header`]; in the cache they are a JSON object.
+
+use crate::api::request::TranslationRequest;
+use crate::api::translator::is_short_input;
+use crate::utils::list::ListDocument;
+use crate::utils::version::VERSION;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// What a translation was made with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    #[serde(default)]
+    pub model: String,
+    /// Kind of prompt, such as "Standard" or "Pivot via English"
+    #[serde(default)]
+    pub template: String,
+    /// Hash of the glossary terms the prompt enforced, empty if none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub glossary_hash: String,
+    #[serde(default)]
+    pub app_version: String,
+    /// Other request options, such as "thinking=enabled, domain=Legal"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub options: String,
+}
+
+/// Log header line prefixes, in the order they are written.
+const MODEL: &str = "Model: ";
+const TEMPLATE: &str = "Template: ";
+const GLOSSARY: &str = "Glossary: ";
+const APP_VERSION: &str = "App Version: ";
+const OPTIONS: &str = "Options: ";
+
+impl Provenance {
+    /// The provenance of translating `request` with `model`, by this
+    /// version of the app.
+    pub fn of(request: &TranslationRequest, model: &str) -> Self {
+        Provenance {
+            model: model.to_string(),
+            template: template(request),
+            glossary_hash: glossary_hash(&request.context.terms),
+            app_version: VERSION.to_string(),
+            options: options(request),
+        }
+    }
+
+    /// The fields as log header lines, leaving out empty ones.
+    pub fn header_lines(&self) -> String {
+        [
+            (MODEL, &self.model),
+            (TEMPLATE, &self.template),
+            (GLOSSARY, &self.glossary_hash),
+            (APP_VERSION, &self.app_version),
+            (OPTIONS, &self.options),
+        ]
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(prefix, value)| format!("{}{}\n", prefix, value.replace('\n', " ")))
+        .collect()
+    }
+
+    /// Reads a log header line written by [`Self::header_lines`] into the
+    /// matching field, returning whether it was one.
+    pub fn read_header(&mut self, line: &str) -> bool {
+        let fields = [
+            (MODEL, &mut self.model),
+            (TEMPLATE, &mut self.template),
+            (GLOSSARY, &mut self.glossary_hash),
+            (APP_VERSION, &mut self.app_version),
+            (OPTIONS, &mut self.options),
+        ];
+        for (prefix, field) in fields {
+            if let Some(value) = line.strip_prefix(prefix) {
+                *field = value.to_string();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// One line per field, for a tooltip.
+    pub fn describe(&self) -> String {
+        let glossary = if self.glossary_hash.is_empty() {
+            "none"
+        } else {
+            &self.glossary_hash
+        };
+        let mut lines = vec![
+            format!("Model: {}", self.model),
+            format!("Template: {}", self.template),
+            format!("Glossary: {}", glossary),
+            format!("App version: {}", self.app_version),
+        ];
+        if !self.options.is_empty() {
+            lines.push(format!("Options: {}", self.options));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Name of the prompt `request` is translated with.
+fn template(request: &TranslationRequest) -> String {
+    if let Some(language) = request.code_language {
+        return format!("Code ({})", language.label());
+    }
+    if request.list_mode && ListDocument::parse(&request.source_text).is_some() {
+        return "List".to_string();
+    }
+    if let Some(pivot) = &request.pivot_language {
+        return format!("Pivot via {}", pivot);
+    }
+    if request.show_alternatives && is_short_input(&request.source_text) {
+        return "Alternatives".to_string();
+    }
+    let template = if request.fast_path {
+        "Fast path"
+    } else {
+        "Standard"
+    };
+    if request.context.translation_only {
+        format!("{} (translation only)", template)
+    } else {
+        template.to_string()
+    }
+}
+
+/// Identifies the glossary `terms` a prompt enforced.
+fn glossary_hash(terms: &[(String, String)]) -> String {
+    if terms.is_empty() {
+        return String::new();
+    }
+    let mut hasher = DefaultHasher::new();
+    terms.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// The options of `request` that change its translation.
+fn options(request: &TranslationRequest) -> String {
+    let mut options = vec![format!("thinking={}", request.thinking.as_str())];
+    if let Some(temperature) = request.temperature {
+        options.push(format!("temperature={}", temperature));
+    }
+    if request.enable_keyword_analysis {
+        options.push("keywords".to_string());
+    }
+    if !request.context.domain.is_empty() {
+        options.push(format!("domain={}", request.context.domain));
+    }
+    if !request.context.audience.is_empty() {
+        options.push(format!("audience={}", request.context.audience));
+    }
+    options.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::client::ThinkingMode;
+    use crate::api::prompt::PromptContext;
+
+    fn request(text: &str) -> TranslationRequest {
+        TranslationRequest {
+            source_text: text.to_string(),
+            target_language: "Deutsch".to_string(),
+            enable_keyword_analysis: false,
+            thinking: ThinkingMode::Disabled,
+            code_language: None,
+            list_mode: false,
+            pivot_language: None,
+            context: PromptContext::default(),
+            show_alternatives: false,
+            max_tokens: None,
+            temperature: None,
+            fast_path: false,
+        }
+    }
+
+    #[test]
+    fn test_provenance_of_request() {
+        let plain = Provenance::of(&request("Hello there, how are you today?"), "model-a");
+        assert_eq!(plain.model, "model-a");
+        assert_eq!(plain.template, "Standard");
+        assert_eq!(plain.glossary_hash, "");
+        assert_eq!(plain.app_version, VERSION);
+        assert_eq!(plain.options, "thinking=disabled");
+
+        let mut pivot = request("Hello there, how are you today?");
+        pivot.pivot_language = Some("English".to_string());
+        pivot.temperature = Some(0.5);
+        pivot.context = PromptContext::new("Legal", "");
+        let pivot = Provenance::of(&pivot, "model-a");
+        assert_eq!(pivot.template, "Pivot via English");
+        assert_eq!(
+            pivot.options,
+            "thinking=disabled, temperature=0.5, domain=Legal"
+        );
+
+        let mut alternatives = request("Hello");
+        alternatives.show_alternatives = true;
+        assert_eq!(Provenance::of(&alternatives, "m").template, "Alternatives");
+    }
+
+    #[test]
+    fn test_glossary_hash_follows_terms() {
+        let mut with_terms = request("Open the cache");
+        with_terms.context.terms = vec![("cache".to_string(), "Zwischenspeicher".to_string())];
+        let first = Provenance::of(&with_terms, "m").glossary_hash;
+        assert_eq!(first.len(), 16);
+        assert_eq!(Provenance::of(&with_terms, "m").glossary_hash, first);
+
+        with_terms.context.terms[0].1 = "Cache".to_string();
+        assert_ne!(Provenance::of(&with_terms, "m").glossary_hash, first);
+    }
+
+    #[test]
+    fn test_header_lines_round_trip() {
+        let provenance = Provenance {
+            model: "model-a".to_string(),
+            template: "Standard".to_string(),
+            glossary_hash: String::new(),
+            app_version: "1.2.3".to_string(),
+            options: "thinking=enabled, domain=Two\nlines".to_string(),
+        };
+        let lines = provenance.header_lines();
+        assert_eq!(
+            lines,
+            "Model: model-a\nTemplate: Standard\nApp Version: 1.2.3\n\
+             Options: thinking=enabled, domain=Two lines\n"
+        );
+
+        let mut read = Provenance::default();
+        for line in lines.lines() {
+            assert!(read.read_header(line));
+        }
+        assert!(!read.read_header("Thinking: enabled"));
+        assert_eq!(read.model, "model-a");
+        assert_eq!(read.options, "thinking=enabled, domain=Two lines");
+        assert_eq!(read.glossary_hash, "");
+    }
+}
@@ -0,0 +1,270 @@
+//! Search queries over the translation history.
+//!
+//! A query is a list of words that must all appear in an entry, plus a few
+//! qualifiers:
+//!
+//! - `lang:中文` keeps entries translated into a matching language
+//! - `before:2024-06-01` keeps entries from before that day
+//! - `after:2024-06-01` keeps entries from that day on
+//! - `model:`, `template:` and `version:` keep entries made with a matching
+//!   model, prompt kind or app version; entries logged before these were
+//!   recorded never match them
+//!
+//! Words in double quotes are matched as one phrase. A qualifier with an
+//! invalid value is searched for as a plain word.
+
+use crate::utils::provenance::Provenance;
+use chrono::NaiveDate;
+use std::ops::Range;
+
+/// A parsed search query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Words or phrases that must all appear, lowercased
+    pub terms: Vec<String>,
+    /// Part of the target language name, lowercased
+    pub language: Option<String>,
+    /// Only entries before this day
+    pub before: Option<NaiveDate>,
+    /// Only entries on or after this day
+    pub after: Option<NaiveDate>,
+    /// Part of the model name, lowercased
+    pub model: Option<String>,
+    /// Part of the prompt kind, lowercased
+    pub template: Option<String>,
+    /// Part of the app version, lowercased
+    pub version: Option<String>,
+}
+
+impl Query {
+    /// Parses the text typed into the search box.
+    pub fn parse(input: &str) -> Self {
+        let mut query = Query::default();
+        for token in tokens(input) {
+            if !query.apply_qualifier(&token) {
+                query.terms.push(token.to_lowercase());
+            }
+        }
+        query
+    }
+
+    /// Applies `token` if it is a valid qualifier.
+    fn apply_qualifier(&mut self, token: &str) -> bool {
+        let Some((name, value)) = token.split_once(':') else {
+            return false;
+        };
+        if value.is_empty() {
+            return false;
+        }
+        match name {
+            "lang" => self.language = Some(value.to_lowercase()),
+            "model" => self.model = Some(value.to_lowercase()),
+            "template" => self.template = Some(value.to_lowercase()),
+            "version" => self.version = Some(value.to_lowercase()),
+            "before" | "after" => {
+                let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") else {
+                    return false;
+                };
+                if name == "before" {
+                    self.before = Some(day);
+                } else {
+                    self.after = Some(day);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether an entry from `day`, translated into `language`, passes the
+    /// qualifiers.
+    pub fn accepts(&self, day: NaiveDate, language: &str) -> bool {
+        self.before.is_none_or(|before| day < before)
+            && self.after.is_none_or(|after| day >= after)
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|wanted| language.to_lowercase().contains(wanted.as_str()))
+    }
+
+    /// Whether an entry made as `provenance` describes passes the
+    /// provenance qualifiers.
+    pub fn accepts_provenance(&self, provenance: Option<&Provenance>) -> bool {
+        let wanted = [
+            (&self.model, provenance.map(|p| &p.model)),
+            (&self.template, provenance.map(|p| &p.template)),
+            (&self.version, provenance.map(|p| &p.app_version)),
+        ];
+        wanted.iter().all(|(wanted, value)| match wanted {
+            None => true,
+            Some(wanted) => {
+                value.is_some_and(|value| value.to_lowercase().contains(wanted.as_str()))
+            }
+        })
+    }
+}
+
+/// Splits `input` at whitespace, keeping double-quoted phrases together.
+fn tokens(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Finds every occurrence of `needle` in `haystack`, ignoring case.
+///
+/// Case is folded per character with Unicode lowercasing, so "ÉTÉ" finds
+/// "été" and "ΣΊΣΥΦΟΣ" finds "σίσυφοσ". The ranges are byte ranges of
+/// `haystack` and never overlap.
+pub fn find_all(haystack: &str, needle: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    // Lowercased characters with the byte range of the character they came from
+    let folded: Vec<(char, Range<usize>)> = haystack
+        .char_indices()
+        .flat_map(|(i, c)| {
+            let range = i..i + c.len_utf8();
+            c.to_lowercase().map(move |lower| (lower, range.clone()))
+        })
+        .collect();
+
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= folded.len() {
+        let window = &folded[i..i + needle.len()];
+        if window.iter().map(|(c, _)| c).eq(needle.iter()) {
+            let range = window[0].1.start..window[needle.len() - 1].1.end;
+            // Skip the rest of a character whose lowercase form is longer
+            while i < folded.len() && folded[i].1.start < range.end {
+                i += 1;
+            }
+            matches.push(range);
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_plain_words() {
+        let query = Query::parse("  Hello   World ");
+        assert_eq!(query.terms, vec!["hello", "world"]);
+        assert_eq!(query.language, None);
+        assert_eq!(Query::parse("   "), Query::default());
+    }
+
+    #[test]
+    fn test_parse_qualifiers() {
+        let query = Query::parse("lang:中文 before:2024-06-01 after:2024-01-15 银行");
+        assert_eq!(query.terms, vec!["银行"]);
+        assert_eq!(query.language.as_deref(), Some("中文"));
+        assert_eq!(query.before, Some(date("2024-06-01")));
+        assert_eq!(query.after, Some(date("2024-01-15")));
+    }
+
+    #[test]
+    fn test_invalid_qualifiers_are_words() {
+        let query = Query::parse("before:June lang: note:x http://example.com");
+        assert_eq!(
+            query.terms,
+            vec!["before:june", "lang:", "note:x", "http://example.com"]
+        );
+        assert_eq!(query.before, None);
+        assert_eq!(query.language, None);
+    }
+
+    #[test]
+    fn test_parse_quoted_phrase() {
+        let query = Query::parse(r#""Guten Morgen" lang:Deutsch "#);
+        assert_eq!(query.terms, vec!["guten morgen"]);
+        assert_eq!(query.language.as_deref(), Some("deutsch"));
+
+        // An unterminated quote runs to the end
+        assert_eq!(Query::parse(r#"a "b c"#).terms, vec!["a", "b c"]);
+    }
+
+    #[test]
+    fn test_accepts_qualifiers() {
+        let query = Query::parse("lang:deu after:2024-01-01 before:2024-06-01");
+        assert!(query.accepts(date("2024-01-01"), "Deutsch"));
+        assert!(query.accepts(date("2024-05-31"), "deutsch"));
+        assert!(!query.accepts(date("2024-06-01"), "Deutsch"));
+        assert!(!query.accepts(date("2023-12-31"), "Deutsch"));
+        assert!(!query.accepts(date("2024-03-01"), "English"));
+        assert!(Query::default().accepts(date("1999-01-01"), ""));
+    }
+
+    #[test]
+    fn test_accepts_provenance() {
+        let provenance = Provenance {
+            model: "deepseek-chat".to_string(),
+            template: "Pivot via English".to_string(),
+            app_version: "1.4.0".to_string(),
+            ..Provenance::default()
+        };
+        let query = Query::parse("model:DeepSeek template:pivot version:1.4");
+        assert_eq!(query.model.as_deref(), Some("deepseek"));
+        assert!(query.terms.is_empty());
+        assert!(query.accepts_provenance(Some(&provenance)));
+        assert!(!Query::parse("template:standard").accepts_provenance(Some(&provenance)));
+        // Unrecorded provenance only passes without these qualifiers
+        assert!(!query.accepts_provenance(None));
+        assert!(Query::parse("lang:deu").accepts_provenance(None));
+    }
+
+    #[test]
+    fn test_find_all_ignores_case() {
+        let text = "Bank, bank and BANK";
+        assert_eq!(find_all(text, "bank"), vec![0..4, 6..10, 15..19]);
+        assert!(find_all(text, "river").is_empty());
+        assert!(find_all(text, "").is_empty());
+    }
+
+    #[test]
+    fn test_find_all_unicode() {
+        let text = "Été à ÉTÉ, 中文和中文";
+        assert_eq!(find_all(text, "été"), vec![0..5, 9..14]);
+        assert_eq!(&text[find_all(text, "中文")[1].clone()], "中文");
+        assert_eq!(find_all("ΣΊΣΥΦΟΣ", "σίσυφοσ").len(), 1);
+    }
+
+    #[test]
+    fn test_find_all_expanding_lowercase() {
+        // "İ" lowercases to "i" and a combining dot
+        let text = "İstanbul istanbul";
+        assert_eq!(find_all(text, "i̇stanbul"), vec![0..9]);
+        assert_eq!(find_all(text, "stanbul"), vec![2..9, 11..18]);
+    }
+
+    #[test]
+    fn test_find_all_does_not_overlap() {
+        assert_eq!(find_all("aaaa", "aa"), vec![0..2, 2..4]);
+    }
+}
@@ -0,0 +1,265 @@
+//! Sensitive values kept out of translation requests.
+//!
+//! With redaction on, email addresses, phone numbers, card-like digit runs
+//! and whatever the user's own patterns match are replaced by placeholders
+//! such as `⟦EMAIL_1⟧` before the source text goes into a request. The same
+//! value always gets the same placeholder, and the values are put back into
+//! the translation locally. The request, and so the cache and the log, only
+//! ever contain the placeholders.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+const OPEN: char = '⟦';
+const CLOSE: char = '⟧';
+
+/// Longest placeholder held back while streaming, in bytes.
+const MAX_PLACEHOLDER_LEN: usize = 32;
+
+/// Email addresses.
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b")
+        .expect("valid email pattern")
+});
+
+/// Phone numbers in E.164 form, optionally grouped by spaces, dots or dashes.
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\+[1-9](?:[ .-]?[0-9]){6,14}\b").expect("valid phone pattern"));
+
+/// Runs of 13 to 19 digits, grouped or not, as on payment cards.
+static CARD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[0-9](?:[ -]?[0-9]){12,18}\b").expect("valid card pattern"));
+
+/// Kind of value matched by the user's own patterns.
+const CUSTOM_KIND: &str = "REDACTED";
+
+/// Checks that a user pattern compiles, returning why it doesn't.
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    Regex::new(pattern).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Finds the values to redact.
+pub struct Redactor {
+    /// Kind named in the placeholder, and its pattern, in order of priority
+    rules: Vec<(&'static str, Regex)>,
+}
+
+impl Redactor {
+    /// The built-in rules followed by `patterns`.
+    ///
+    /// Blank patterns are ignored and ones that don't compile are skipped
+    /// with a warning.
+    pub fn new(patterns: &[String]) -> Self {
+        let mut rules = vec![
+            ("EMAIL", EMAIL.clone()),
+            ("PHONE", PHONE.clone()),
+            ("CARD", CARD.clone()),
+        ];
+        for pattern in patterns.iter().filter(|p| !p.trim().is_empty()) {
+            match Regex::new(pattern) {
+                Ok(regex) => rules.push((CUSTOM_KIND, regex)),
+                Err(e) => tracing::warn!("Skipping invalid redaction pattern: {}", e),
+            }
+        }
+        Redactor { rules }
+    }
+
+    /// Replaces the sensitive values of `text` with placeholders.
+    ///
+    /// Where matches overlap the one starting first wins, then the rule
+    /// listed first. Values that would leave nothing but whitespace are
+    /// not redacted.
+    pub fn redact(&self, text: &str) -> Redaction {
+        let mut found: Vec<(usize, usize, usize)> = Vec::new();
+        for (rule, (_, regex)) in self.rules.iter().enumerate() {
+            for m in regex.find_iter(text) {
+                if !m.as_str().trim().is_empty() {
+                    found.push((m.start(), rule, m.end()));
+                }
+            }
+        }
+        found.sort_unstable();
+
+        let mut redaction = Redaction::default();
+        let mut end = 0;
+        for (start, rule, stop) in found {
+            if start < end {
+                continue;
+            }
+            redaction.text.push_str(&text[end..start]);
+            let placeholder = redaction.placeholder_for(self.rules[rule].0, &text[start..stop]);
+            redaction.text.push_str(&placeholder);
+            end = stop;
+        }
+        redaction.text.push_str(&text[end..]);
+        redaction
+    }
+}
+
+/// A value replaced by a placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redacted {
+    pub placeholder: String,
+    pub value: String,
+    /// How often the value occurs in the source
+    pub count: usize,
+}
+
+/// A text with its sensitive values replaced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redaction {
+    /// The text as it is sent
+    pub text: String,
+    /// The values, in order of their first occurrence
+    pub values: Vec<Redacted>,
+    /// Start of a placeholder cut off at the end of the last streamed chunk
+    held: String,
+}
+
+impl Redaction {
+    /// Whether nothing was redacted.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The placeholder of `value`, numbered per kind on first sight.
+    fn placeholder_for(&mut self, kind: &str, value: &str) -> String {
+        if let Some(known) = self.values.iter_mut().find(|r| r.value == value) {
+            known.count += 1;
+            return known.placeholder.clone();
+        }
+        let prefix = format!("{}{}_", OPEN, kind);
+        let n = self
+            .values
+            .iter()
+            .filter(|r| r.placeholder.starts_with(&prefix))
+            .count();
+        let placeholder = format!("{}{}{}", prefix, n + 1, CLOSE);
+        self.values.push(Redacted {
+            placeholder: placeholder.clone(),
+            value: value.to_string(),
+            count: 1,
+        });
+        placeholder
+    }
+
+    /// Puts the values back in place of their placeholders.
+    pub fn restore(&self, text: &str) -> String {
+        if !text.contains(OPEN) {
+            return text.to_string();
+        }
+        self.values.iter().fold(text.to_string(), |text, r| {
+            text.replace(&r.placeholder, &r.value)
+        })
+    }
+
+    /// Replaces the values in `text` with their placeholders again, for
+    /// text to be logged or sent on after restoring.
+    pub fn conceal(&self, text: &str) -> String {
+        let mut values: Vec<&Redacted> = self.values.iter().collect();
+        // A value containing another one goes first
+        values.sort_by_key(|r| std::cmp::Reverse(r.value.len()));
+        values.into_iter().fold(text.to_string(), |text, r| {
+            text.replace(&r.value, &r.placeholder)
+        })
+    }
+
+    /// Restores a chunk of a streamed translation.
+    ///
+    /// A placeholder split across chunks is held back until its closing
+    /// bracket arrives, see [`Redaction::finish_stream`].
+    pub fn restore_chunk(&mut self, chunk: &str) -> String {
+        let mut text = std::mem::take(&mut self.held) + chunk;
+        if let Some(open) = text.rfind(OPEN)
+            && !text[open..].contains(CLOSE)
+            && text.len() - open <= MAX_PLACEHOLDER_LEN
+        {
+            self.held = text.split_off(open);
+        }
+        self.restore(&text)
+    }
+
+    /// Text held back at the end of the stream, as it is.
+    pub fn finish_stream(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+
+    /// Placeholders missing from a translation, whose values can't be
+    /// restored.
+    pub fn missing(&self, translation: &str) -> Vec<&str> {
+        self.values
+            .iter()
+            .filter(|r| !translation.contains(&r.placeholder))
+            .map(|r| r.placeholder.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_restore_round_trip() {
+        let redactor = Redactor::new(&[]);
+        let source = "Mail anna@example.com or call +49 30 1234567. \
+                      Card 4111 1111 1111 1111. Again: anna@example.com, bob@example.org.";
+        let redaction = redactor.redact(source);
+        assert_eq!(
+            redaction.text,
+            "Mail ⟦EMAIL_1⟧ or call ⟦PHONE_1⟧. \
+             Card ⟦CARD_1⟧. Again: ⟦EMAIL_1⟧, ⟦EMAIL_2⟧."
+        );
+        assert_eq!(redaction.values.len(), 4);
+        // Repeated identical values share a placeholder
+        assert_eq!(redaction.values[0].count, 2);
+        assert_eq!(redaction.values[0].value, "anna@example.com");
+
+        let translation = "Schreiben Sie ⟦EMAIL_1⟧ oder ⟦EMAIL_2⟧, rufen Sie ⟦PHONE_1⟧ an \
+                           (⟦EMAIL_1⟧). Karte ⟦CARD_1⟧.";
+        let restored = redaction.restore(translation);
+        assert_eq!(
+            restored,
+            "Schreiben Sie anna@example.com oder bob@example.org, rufen Sie +49 30 1234567 an \
+             (anna@example.com). Karte 4111 1111 1111 1111."
+        );
+        assert_eq!(redaction.conceal(&restored), translation);
+        assert!(redaction.missing(translation).is_empty());
+        assert_eq!(
+            redaction.missing("⟦EMAIL_1⟧"),
+            ["⟦PHONE_1⟧", "⟦CARD_1⟧", "⟦EMAIL_2⟧"]
+        );
+    }
+
+    #[test]
+    fn test_text_without_sensitive_values_is_unchanged() {
+        let redaction = Redactor::new(&[]).redact("Order 12345 shipped on 2024-05-01 at 9:30.");
+        assert!(redaction.is_empty());
+        assert_eq!(redaction.text, "Order 12345 shipped on 2024-05-01 at 9:30.");
+    }
+
+    #[test]
+    fn test_user_patterns() {
+        let patterns = vec![
+            r"\bEMP-\d{6}\b".to_string(),
+            "(".to_string(),
+            " ".to_string(),
+        ];
+        assert!(check_pattern("(").is_err());
+        // The invalid and blank patterns are skipped
+        let redaction = Redactor::new(&patterns).redact("EMP-123456 and EMP-654321, EMP-123456");
+        assert_eq!(
+            redaction.text,
+            "⟦REDACTED_1⟧ and ⟦REDACTED_2⟧, ⟦REDACTED_1⟧"
+        );
+    }
+
+    #[test]
+    fn test_restore_placeholders_split_across_chunks() {
+        let mut redaction = Redactor::new(&[]).redact("Write to a@b.io");
+        let chunks = ["Schreib an ⟦EM", "AIL_1", "⟧ bitte ⟦"];
+        let restored: String = chunks.iter().map(|c| redaction.restore_chunk(c)).collect();
+        assert_eq!(restored, "Schreib an a@b.io bitte ");
+        assert_eq!(redaction.finish_stream(), "⟦");
+    }
+}
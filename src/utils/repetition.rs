@@ -0,0 +1,239 @@
+//! Detection of a model stuck repeating itself.
+//!
+//! Now and then a stream degenerates into the same sentence over and over
+//! until the output limit. [`RepetitionDetector`] follows the text as it
+//! streams and tells when its end has turned into such a loop, so the
+//! request can be stopped and the text before the loop kept.
+//!
+//! Each character costs O(1): a rolling hash of the last
+//! [`MIN_UNIT`] characters finds where that stretch was last seen, which
+//! gives the period of a possible loop, and the loop is then followed by
+//! comparing each new character with the one a period earlier.
+
+use std::collections::HashMap;
+
+/// Shortest unit that counts as repeated, in characters.
+///
+/// A loop of a shorter unit, like "ha", is measured in multiples of it at
+/// least this long.
+pub const MIN_UNIT: usize = 20;
+
+/// Default number of consecutive repeats of a unit that are still accepted.
+pub const DEFAULT_MAX_REPEATS: usize = 8;
+
+/// Multiplier of the rolling hash.
+const BASE: u64 = 0x100_0000_01b3;
+
+/// Finds the loop at the end of streamed text.
+#[derive(Debug)]
+pub struct RepetitionDetector {
+    /// Consecutive repeats above which the text counts as looping
+    max_repeats: usize,
+    chars: Vec<char>,
+    /// Hash of the last [`MIN_UNIT`] characters
+    hash: u64,
+    /// `BASE` to the power of `MIN_UNIT - 1`, to take the oldest character
+    /// out of the hash
+    outgoing: u64,
+    /// Where each stretch of [`MIN_UNIT`] characters was last seen, by
+    /// hash, as the index of its last character
+    seen: HashMap<u64, usize>,
+    /// Period of the loop the text ends in, if it ends in one
+    period: Option<usize>,
+    /// Whether the repeated unit has letters or digits; separator lines
+    /// like `----` repeat without being a loop
+    wordy: bool,
+    /// Characters at the end that equal the one a period before them
+    matched: usize,
+}
+
+impl RepetitionDetector {
+    /// Creates a detector accepting up to `max_repeats` consecutive repeats.
+    pub fn new(max_repeats: usize) -> Self {
+        RepetitionDetector {
+            max_repeats: max_repeats.max(2),
+            chars: Vec::new(),
+            hash: 0,
+            outgoing: (1..MIN_UNIT).fold(1, |power: u64, _| power.wrapping_mul(BASE)),
+            seen: HashMap::new(),
+            period: None,
+            wordy: false,
+            matched: 0,
+        }
+    }
+
+    /// Adds streamed text.
+    ///
+    /// Once the text ends in more than the accepted number of repeats,
+    /// returns how many characters of everything pushed so far are past the
+    /// first occurrence of the repeated unit. Those are what to drop to keep
+    /// the clean prefix.
+    pub fn push(&mut self, text: &str) -> Option<usize> {
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if self.push_char(c) {
+                return Some(self.matched + chars.count());
+            }
+        }
+        None
+    }
+
+    /// Adds one character, returning whether the text now loops.
+    fn push_char(&mut self, c: char) -> bool {
+        self.chars.push(c);
+        let end = self.chars.len() - 1;
+
+        if end >= MIN_UNIT {
+            let oldest = self.chars[end - MIN_UNIT] as u64;
+            self.hash = self.hash.wrapping_sub(oldest.wrapping_mul(self.outgoing));
+        }
+        self.hash = self.hash.wrapping_mul(BASE).wrapping_add(c as u64);
+
+        match self.period {
+            Some(period) if self.chars[end - period] == c => self.matched += 1,
+            _ => {
+                self.period = None;
+                self.matched = 0;
+            }
+        }
+        if end + 1 < MIN_UNIT {
+            return false;
+        }
+
+        if self.period.is_none()
+            && let Some(&last) = self.seen.get(&self.hash)
+        {
+            self.start_loop(end - last);
+        }
+        self.seen.insert(self.hash, end);
+
+        self.period
+            .is_some_and(|period| self.wordy && (self.matched + period) / period > self.max_repeats)
+    }
+
+    /// Starts following a loop if the last [`MIN_UNIT`] characters repeat
+    /// the ones `distance` before them.
+    fn start_loop(&mut self, distance: usize) {
+        let period = distance * MIN_UNIT.div_ceil(distance);
+        let end = self.chars.len();
+        if end < period + MIN_UNIT {
+            return;
+        }
+        let window = end - MIN_UNIT..end;
+        let earlier = window.start - period..window.end - period;
+        // Rules out hash collisions
+        if self.chars[window] != self.chars[earlier] {
+            return;
+        }
+        self.period = Some(period);
+        self.matched = MIN_UNIT;
+        self.wordy = self.chars[end - period..]
+            .iter()
+            .any(|c| c.is_alphanumeric());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Streams `text` in chunks of `chunk_size` characters, returning the
+    /// text kept once a loop is found.
+    fn stream(text: &str, chunk_size: usize) -> Option<String> {
+        let mut detector = RepetitionDetector::new(DEFAULT_MAX_REPEATS);
+        let chars: Vec<char> = text.chars().collect();
+        let mut streamed = String::new();
+        for chunk in chars.chunks(chunk_size) {
+            let chunk: String = chunk.iter().collect();
+            streamed.push_str(&chunk);
+            if let Some(dropped) = detector.push(&chunk) {
+                let kept = streamed.chars().count() - dropped;
+                return Some(streamed.chars().take(kept).collect());
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_repeated_sentence_is_stopped() {
+        let sentence = "The committee will review the proposal next week. ";
+        let text = format!("Here is the summary:\n{}", sentence.repeat(40));
+        for chunk_size in [1, 7, 64, 1000] {
+            assert_eq!(
+                stream(&text, chunk_size).as_deref(),
+                Some(format!("Here is the summary:\n{}", sentence).as_str()),
+                "chunks of {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_stopped_soon_after_the_limit() {
+        let sentence = "Das Wetter ist heute schön und warm. ";
+        let mut detector = RepetitionDetector::new(4);
+        let repeats = (1..=40)
+            .find(|_| detector.push(sentence).is_some())
+            .unwrap();
+        assert_eq!(repeats, 5);
+    }
+
+    #[test]
+    fn test_short_and_cjk_loops() {
+        let laughing = format!("Sure! {}", "ha".repeat(200));
+        let kept = stream(&laughing, 3).unwrap();
+        assert!(kept.starts_with("Sure! haha"));
+        assert!(kept.chars().count() < 40);
+
+        // Under twenty characters, so the unit is the sentence twice
+        let sentence = "我们明天会再讨论这个问题，请耐心等待。";
+        let text = format!("翻译如下：{}", sentence.repeat(30));
+        assert_eq!(
+            stream(&text, 5),
+            Some(format!("翻译如下：{}", sentence.repeat(2)))
+        );
+    }
+
+    #[test]
+    fn test_refrains_are_not_loops() {
+        let chorus =
+            "Let it be, let it be, let it be, let it be\nWhisper words of wisdom, let it be\n";
+        let lyrics = [
+            "When I find myself in times of trouble, Mother Mary comes to me\n",
+            "Speaking words of wisdom, let it be\n\n",
+            chorus,
+            "\nAnd in my hour of darkness she is standing right in front of me\n",
+            "Speaking words of wisdom, let it be\n\n",
+            chorus,
+            chorus,
+            "\nAnd when the broken-hearted people living in the world agree\n",
+            chorus,
+            chorus,
+            chorus,
+        ]
+        .concat();
+        assert_eq!(stream(&lyrics, 9), None);
+
+        let refrain = "Na na na na, na na na na, hey hey hey, goodbye\n".repeat(6);
+        assert_eq!(stream(&refrain, 4), None);
+    }
+
+    #[test]
+    fn test_ordinary_text_is_not_a_loop() {
+        let table = format!(
+            "| Name | Value |\n|{}|{}|\n{}",
+            "-".repeat(40),
+            "=".repeat(40),
+            "| a | 1 |\n| b | 2 |\n| c | 3 |\n"
+        );
+        assert_eq!(stream(&table, 8), None);
+        assert_eq!(stream(&"─".repeat(400), 16), None);
+
+        let legal = "The Licensee shall not sublicense the Software. \
+            The Licensee shall not modify the Software. \
+            The Licensee shall not distribute the Software. \
+            The Licensee shall not rent the Software. "
+            .repeat(2);
+        assert_eq!(stream(&legal, 11), None);
+    }
+}
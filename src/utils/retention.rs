@@ -0,0 +1,573 @@
+//! Clean-up of the files the app piles up over time.
+//!
+//! Diagnostic bundles and rotated translation logs are never read again
+//! after a while. [`run`] compresses rotated logs and deletes entries that
+//! are older than the configured age or, oldest first, that don't fit the
+//! size budget of their folder. Only entries directly inside the app's own
+//! folders and named the way the app names them are ever deleted.
+
+use crate::utils::paths;
+use chrono::{Local, NaiveDateTime};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Format of the timestamps in bundle and archive names.
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Kind of files the app keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Diagnostic bundles, one folder each
+    Diagnostics,
+    /// Rotated translation logs and request metrics
+    LogArchives,
+    /// Generated audio; its cache evicts entries by itself
+    AudioCache,
+}
+
+impl Category {
+    pub const ALL: [Category; 3] = [
+        Category::Diagnostics,
+        Category::LogArchives,
+        Category::AudioCache,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Diagnostics => "Diagnostic bundles",
+            Category::LogArchives => "Log archives",
+            Category::AudioCache => "Audio cache",
+        }
+    }
+
+    /// Whether the retention policy applies.
+    fn is_managed(self) -> bool {
+        !matches!(self, Category::AudioCache)
+    }
+
+    /// Whether an entry of the category's folder belongs to it.
+    fn includes(self, name: &str, is_dir: bool) -> bool {
+        match self {
+            Category::Diagnostics => is_dir && name.starts_with("diagnostics-"),
+            Category::LogArchives => {
+                !is_dir
+                    && [".log", ".jsonl", ".gz"]
+                        .iter()
+                        .any(|ext| name.ends_with(ext))
+            }
+            Category::AudioCache => !is_dir,
+        }
+    }
+}
+
+/// The folders of each category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDirs {
+    pub diagnostics: PathBuf,
+    pub log_archives: PathBuf,
+    pub audio_cache: PathBuf,
+}
+
+impl Default for StorageDirs {
+    fn default() -> Self {
+        StorageDirs {
+            diagnostics: paths::diagnostics_dir(),
+            log_archives: paths::log_archive_dir(),
+            audio_cache: paths::audio_cache_dir(),
+        }
+    }
+}
+
+impl StorageDirs {
+    pub fn dir(&self, category: Category) -> &Path {
+        match category {
+            Category::Diagnostics => &self.diagnostics,
+            Category::LogArchives => &self.log_archives,
+            Category::AudioCache => &self.audio_cache,
+        }
+    }
+}
+
+/// When entries are deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetentionPolicy {
+    /// Entries older than this are deleted, `None` keeps them
+    pub max_age_days: Option<u32>,
+    /// Size each folder is kept under, `None` for no limit
+    pub max_bytes: Option<u64>,
+}
+
+/// A file or bundle folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// Size, of all files inside for a folder
+    pub bytes: u64,
+    /// When it was written
+    pub modified: SystemTime,
+    pub is_dir: bool,
+}
+
+/// The entries of `category` directly inside `dir`.
+///
+/// Symbolic links are skipped, so nothing outside the folder is reached.
+pub fn scan(dir: &Path, category: Category) -> Vec<Entry> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .flatten()
+        .filter_map(|item| {
+            let metadata = item.path().symlink_metadata().ok()?;
+            if metadata.file_type().is_symlink() {
+                return None;
+            }
+            let name = item.file_name().to_string_lossy().to_string();
+            let is_dir = metadata.is_dir();
+            if !category.includes(&name, is_dir) {
+                return None;
+            }
+            let path = item.path();
+            let bytes = if is_dir {
+                dir_size(&path)
+            } else {
+                metadata.len()
+            };
+            let modified = stamp_in_name(&name)
+                .or_else(|| metadata.modified().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            Some(Entry {
+                path,
+                bytes,
+                modified,
+                is_dir,
+            })
+        })
+        .collect()
+}
+
+/// Total size of the files in `dir` and its subfolders.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return 0;
+    };
+    read_dir
+        .flatten()
+        .filter_map(|item| {
+            let metadata = item.path().symlink_metadata().ok()?;
+            Some(if metadata.is_dir() {
+                dir_size(&item.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+/// Time in a name such as `diagnostics-20240131-093000`, in local time.
+///
+/// The folder's own modification time changes whenever something inside
+/// it does, the name records when the bundle was made.
+fn stamp_in_name(name: &str) -> Option<SystemTime> {
+    let stamp = name.get(name.find('-')? + 1..)?.get(..15)?;
+    let time = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).ok()?;
+    Some(time.and_local_timezone(Local).earliest()?.into())
+}
+
+/// The entries to delete under `policy`, oldest first.
+///
+/// Entries older than the maximum age go, then the oldest of the rest
+/// until the total fits the budget.
+pub fn expired<'a>(
+    entries: &'a [Entry],
+    policy: &RetentionPolicy,
+    now: SystemTime,
+) -> Vec<&'a Entry> {
+    let mut entries: Vec<&Entry> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.modified);
+    let max_age = policy.max_age_days.map(|days| DAY * days);
+    let mut total: u64 = entries.iter().map(|entry| entry.bytes).sum();
+
+    entries
+        .into_iter()
+        .filter(|entry| {
+            let too_old = max_age.is_some_and(|max_age| {
+                now.duration_since(entry.modified)
+                    .is_ok_and(|age| age > max_age)
+            });
+            let over_budget = policy.max_bytes.is_some_and(|max_bytes| total > max_bytes);
+            if too_old || over_budget {
+                total -= entry.bytes;
+            }
+            too_old || over_budget
+        })
+        .collect()
+}
+
+/// Moves `log` into `archive_dir` once it is larger than `max_bytes`.
+///
+/// The archived copy is named after the log and the current time, and is
+/// compressed by the next [`run`]. Must be called before the log is opened
+/// for writing.
+pub fn rotate_log(log: &Path, archive_dir: &Path, max_bytes: u64) -> io::Result<Option<PathBuf>> {
+    let Ok(metadata) = fs::metadata(log) else {
+        return Ok(None);
+    };
+    if metadata.len() <= max_bytes {
+        return Ok(None);
+    }
+    let (Some(stem), Some(extension)) = (log.file_stem(), log.extension()) else {
+        return Ok(None);
+    };
+
+    fs::create_dir_all(archive_dir)?;
+    let archived = archive_dir.join(format!(
+        "{}-{}.{}",
+        stem.to_string_lossy(),
+        Local::now().format(STAMP_FORMAT),
+        extension.to_string_lossy()
+    ));
+    // Renaming fails across file systems
+    if fs::rename(log, &archived).is_err() {
+        fs::copy(log, &archived)?;
+        fs::remove_file(log)?;
+    }
+    tracing::info!("Rotated {:?} to {:?}", log, archived);
+    Ok(Some(archived))
+}
+
+/// Replaces `path` with a gzip-compressed `<path>.gz`.
+pub fn compress(path: &Path) -> io::Result<PathBuf> {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".gz");
+    let compressed = PathBuf::from(compressed);
+
+    if let Err(e) = gzip(path, &compressed) {
+        let _ = fs::remove_file(&compressed);
+        return Err(e);
+    }
+    fs::remove_file(path)?;
+    Ok(compressed)
+}
+
+fn gzip(path: &Path, compressed: &Path) -> io::Result<()> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut encoder = GzEncoder::new(
+        BufWriter::new(File::create(compressed)?),
+        Compression::default(),
+    );
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.flush()
+}
+
+/// Space taken by one category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub category: Category,
+    pub dir: PathBuf,
+    pub bytes: u64,
+    /// Files, or bundles for diagnostics
+    pub entries: usize,
+}
+
+/// Space taken by each category.
+pub fn usage(dirs: &StorageDirs) -> Vec<Usage> {
+    Category::ALL
+        .into_iter()
+        .map(|category| {
+            let dir = dirs.dir(category);
+            let entries = scan(dir, category);
+            Usage {
+                category,
+                dir: dir.to_path_buf(),
+                bytes: entries.iter().map(|entry| entry.bytes).sum(),
+                entries: entries.len(),
+            }
+        })
+        .collect()
+}
+
+/// What a clean-up did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub compressed: usize,
+    pub deleted: usize,
+    pub freed_bytes: u64,
+}
+
+/// Compresses rotated logs and deletes what `policy` lets go.
+pub fn run(dirs: &StorageDirs, policy: &RetentionPolicy, now: SystemTime) -> Report {
+    let mut report = Report::default();
+
+    for entry in scan(&dirs.log_archives, Category::LogArchives) {
+        if entry.path.extension().is_some_and(|ext| ext == "gz") {
+            continue;
+        }
+        match compress(&entry.path) {
+            Ok(_) => report.compressed += 1,
+            Err(e) => tracing::warn!("Failed to compress {:?}: {}", entry.path, e),
+        }
+    }
+
+    for category in Category::ALL.into_iter().filter(|c| c.is_managed()) {
+        let entries = scan(dirs.dir(category), category);
+        for entry in expired(&entries, policy, now) {
+            let removed = if entry.is_dir {
+                fs::remove_dir_all(&entry.path)
+            } else {
+                fs::remove_file(&entry.path)
+            };
+            match removed {
+                Ok(()) => {
+                    report.deleted += 1;
+                    report.freed_bytes += entry.bytes;
+                }
+                Err(e) => tracing::warn!("Failed to delete {:?}: {}", entry.path, e),
+            }
+        }
+    }
+
+    if report != Report::default() {
+        tracing::info!(
+            compressed = report.compressed,
+            deleted = report.deleted,
+            freed_bytes = report.freed_bytes,
+            "Cleaned up old files"
+        );
+    }
+    report
+}
+
+/// Size in B, KB, MB or GB, for display.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn entry(name: &str, bytes: u64, days_old: u32, now: SystemTime) -> Entry {
+        Entry {
+            path: PathBuf::from(name),
+            bytes,
+            modified: now - DAY * days_old,
+            is_dir: false,
+        }
+    }
+
+    fn names(entries: Vec<&Entry>) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    /// A fresh folder tree: the app's folders and a user's export folder.
+    fn tree(name: &str) -> (PathBuf, StorageDirs) {
+        let root = std::env::temp_dir().join(format!("test_retention_{}", name));
+        let _ = fs::remove_dir_all(&root);
+        let dirs = StorageDirs {
+            diagnostics: root.join("data").join("diagnostics"),
+            log_archives: root.join("data").join("logs"),
+            audio_cache: root.join("cache").join("audio"),
+        };
+        for dir in [&dirs.diagnostics, &dirs.log_archives, &dirs.audio_cache] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(root.join("exports")).unwrap();
+        (root, dirs)
+    }
+
+    fn write_aged(path: &Path, bytes: usize, days_old: u32) {
+        fs::write(path, vec![b'x'; bytes]).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - DAY * days_old)
+            .unwrap();
+    }
+
+    fn bundle(dirs: &StorageDirs, days_old: u32, bytes: usize) -> PathBuf {
+        let time = Local::now() - chrono::Duration::days(i64::from(days_old));
+        let dir = dirs
+            .diagnostics
+            .join(format!("diagnostics-{}", time.format(STAMP_FORMAT)));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("trace.log"), vec![b'x'; bytes]).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_expired_by_age_then_budget() {
+        let now = SystemTime::now();
+        let entries = vec![
+            entry("new", 40, 1, now),
+            entry("ancient", 10, 90, now),
+            entry("old", 30, 20, now),
+            entry("middle", 30, 5, now),
+        ];
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_bytes: None,
+        };
+        assert_eq!(names(expired(&entries, &policy, now)), vec!["ancient"]);
+
+        // 100 after the ancient one goes, the oldest go until 70 fit
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_bytes: Some(70),
+        };
+        assert_eq!(
+            names(expired(&entries, &policy, now)),
+            vec!["ancient", "old"]
+        );
+
+        let policy = RetentionPolicy {
+            max_age_days: None,
+            max_bytes: Some(0),
+        };
+        assert_eq!(expired(&entries, &policy, now).len(), 4);
+        assert!(expired(&entries, &RetentionPolicy::default(), now).is_empty());
+    }
+
+    #[test]
+    fn test_bundle_age_comes_from_its_name() {
+        let (root, dirs) = tree("bundle_age");
+        let old = bundle(&dirs, 40, 10);
+        // Touched today, still made 40 days ago
+        fs::write(old.join("notes.txt"), "added later").unwrap();
+
+        let entries = scan(&dirs.diagnostics, Category::Diagnostics);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[0].bytes, 10 + "added later".len() as u64);
+        let age = SystemTime::now()
+            .duration_since(entries[0].modified)
+            .unwrap();
+        assert!(age > DAY * 39 && age < DAY * 41);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_run_on_synthetic_tree() {
+        let (root, dirs) = tree("run");
+        let old_bundle = bundle(&dirs, 30, 100);
+        let new_bundle = bundle(&dirs, 2, 100);
+        // Not named like a bundle, never touched
+        let foreign = dirs.diagnostics.join("my-notes");
+        fs::create_dir_all(&foreign).unwrap();
+        write_aged(&foreign.join("notes.txt"), 10, 400);
+
+        write_aged(&dirs.log_archives.join("translations-old.log.gz"), 50, 60);
+        write_aged(&dirs.log_archives.join("translations-new.log.gz"), 50, 3);
+        write_aged(&dirs.log_archives.join("readme.txt"), 50, 400);
+        write_aged(&dirs.audio_cache.join("old.wav"), 50, 400);
+        write_aged(&root.join("exports").join("diagnostics-old.log"), 50, 400);
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(14),
+            max_bytes: None,
+        };
+        let report = run(&dirs, &policy, SystemTime::now());
+
+        assert_eq!(report.deleted, 2);
+        assert_eq!(report.freed_bytes, 150);
+        assert!(!old_bundle.exists());
+        assert!(new_bundle.exists());
+        assert!(foreign.join("notes.txt").exists());
+        assert!(!dirs.log_archives.join("translations-old.log.gz").exists());
+        assert!(dirs.log_archives.join("translations-new.log.gz").exists());
+        assert!(dirs.log_archives.join("readme.txt").exists());
+        // The audio cache manages itself, exports are the user's
+        assert!(dirs.audio_cache.join("old.wav").exists());
+        assert!(root.join("exports").join("diagnostics-old.log").exists());
+
+        let usage = usage(&dirs);
+        assert_eq!(usage[0].category, Category::Diagnostics);
+        assert_eq!((usage[0].entries, usage[0].bytes), (1, 100));
+        assert_eq!((usage[1].entries, usage[1].bytes), (1, 50));
+        assert_eq!((usage[2].entries, usage[2].bytes), (1, 50));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_budget_keeps_newest() {
+        let (root, dirs) = tree("budget");
+        for (name, days_old) in [("a.log.gz", 9), ("b.log.gz", 6), ("c.log.gz", 3)] {
+            write_aged(&dirs.log_archives.join(name), 1000, days_old);
+        }
+        let policy = RetentionPolicy {
+            max_age_days: None,
+            max_bytes: Some(2500),
+        };
+        let report = run(&dirs, &policy, SystemTime::now());
+
+        assert_eq!(report.deleted, 1);
+        assert!(!dirs.log_archives.join("a.log.gz").exists());
+        assert!(dirs.log_archives.join("b.log.gz").exists());
+        assert!(dirs.log_archives.join("c.log.gz").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_rotate_and_compress_log() {
+        let (root, dirs) = tree("rotate");
+        let log = root.join("translations.log");
+        let content = "[2024-01-31] Hello => Hallo\n".repeat(100);
+        fs::write(&log, &content).unwrap();
+
+        assert_eq!(
+            rotate_log(&log, &dirs.log_archives, 1_000_000).unwrap(),
+            None
+        );
+        assert!(log.exists());
+
+        let archived = rotate_log(&log, &dirs.log_archives, 100).unwrap().unwrap();
+        assert!(!log.exists());
+        assert!(archived.starts_with(&dirs.log_archives));
+        assert_eq!(rotate_log(&log, &dirs.log_archives, 100).unwrap(), None);
+
+        let report = run(&dirs, &RetentionPolicy::default(), SystemTime::now());
+        assert_eq!(report.compressed, 1);
+        assert!(!archived.exists());
+
+        let entries = scan(&dirs.log_archives, Category::LogArchives);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].bytes < content.len() as u64);
+        let mut decompressed = String::new();
+        GzDecoder::new(File::open(&entries[0].path).unwrap())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, content);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+}
@@ -0,0 +1,217 @@
+//! Romanized rendering of Chinese and Japanese source text.
+//!
+//! Only the runs of Chinese characters and kana are romanized; everything
+//! between them, such as Latin words, numbers and placeholders, is kept as
+//! it is. The runs are sent to the model as numbered lines and its answers
+//! are put back in their place with [`assemble`].
+
+use serde::{Deserialize, Serialize};
+
+/// How a text is romanized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scheme {
+    /// Hanyu Pinyin with tone marks, for Chinese
+    Pinyin,
+    /// Hepburn romaji, for Japanese
+    Hepburn,
+}
+
+impl Scheme {
+    /// The scheme for `text`, `None` if it has nothing to romanize.
+    ///
+    /// Any kana makes the text Japanese; Chinese characters alone are
+    /// taken as Chinese.
+    pub fn for_text(text: &str) -> Option<Scheme> {
+        let mut han = false;
+        for c in text.chars() {
+            if is_kana(c) {
+                return Some(Scheme::Hepburn);
+            }
+            han |= is_han(c);
+        }
+        han.then_some(Scheme::Pinyin)
+    }
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            Scheme::Pinyin => "Pinyin",
+            Scheme::Hepburn => "Hepburn romaji",
+        }
+    }
+
+    /// Language of the text, for the prompt.
+    pub fn language(self) -> &'static str {
+        match self {
+            Scheme::Pinyin => "Chinese",
+            Scheme::Hepburn => "Japanese",
+        }
+    }
+
+    /// Stable name, part of the cache key.
+    pub fn key(self) -> &'static str {
+        match self {
+            Scheme::Pinyin => "pinyin-tones",
+            Scheme::Hepburn => "hepburn",
+        }
+    }
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c,
+        '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FFFF}'
+        // Iteration and closing marks
+        | '々' | '〆' | '〇'
+    )
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c,
+        '\u{3041}'..='\u{309F}' | '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}'
+    )
+}
+
+/// A stretch of text that is romanized or kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub text: String,
+    /// Chinese characters or kana, to be romanized
+    pub cjk: bool,
+}
+
+/// Splits `text` into runs of Chinese characters and kana and the runs
+/// between them.
+pub fn split_runs(text: &str) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for c in text.chars() {
+        let cjk = is_han(c) || is_kana(c);
+        match runs.last_mut() {
+            Some(run) if run.cjk == cjk => run.text.push(c),
+            _ => runs.push(Run {
+                text: c.to_string(),
+                cjk,
+            }),
+        }
+    }
+    runs
+}
+
+/// The text of the runs to romanize, in order.
+pub fn cjk_runs(runs: &[Run]) -> Vec<&str> {
+    runs.iter()
+        .filter(|run| run.cjk)
+        .map(|run| run.text.as_str())
+        .collect()
+}
+
+/// CJK punctuation and full-width forms as their ASCII counterparts, so the
+/// romanized text reads naturally; other characters are kept.
+fn ascii_punctuation(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '。' | '｡' => out.push_str(". "),
+            '、' | '､' => out.push_str(", "),
+            '「' | '」' | '『' | '』' => out.push('"'),
+            '・' | '･' => out.push(' '),
+            '〜' => out.push('~'),
+            '\u{3000}' => out.push(' '),
+            // Full-width ASCII
+            '\u{FF01}'..='\u{FF5E}' => {
+                out.push(char::from_u32(c as u32 - 0xFEE0).unwrap_or(c));
+                if matches!(c, '，' | '：' | '；' | '！' | '？') {
+                    out.push(' ');
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Puts the romanized runs back between the kept ones.
+///
+/// Returns `None` if the number of romanized runs doesn't match. A space is
+/// added where a romanized run would otherwise run into a word.
+pub fn assemble(runs: &[Run], romanized: &[String]) -> Option<String> {
+    if romanized.len() != runs.iter().filter(|run| run.cjk).count() {
+        return None;
+    }
+    let mut romanized = romanized.iter();
+    let mut out = String::new();
+    for run in runs {
+        let part = if run.cjk {
+            romanized.next()?.trim().to_string()
+        } else {
+            ascii_punctuation(&run.text)
+        };
+        let joins_words = out.chars().next_back().is_some_and(char::is_alphanumeric)
+            && part.chars().next().is_some_and(char::is_alphanumeric);
+        if joins_words {
+            out.push(' ');
+        }
+        out.push_str(&part);
+    }
+    // Punctuation turned into ". " leaves trailing spaces before line ends
+    Some(
+        out.lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme_for_text() {
+        assert_eq!(Scheme::for_text("我们走吧"), Some(Scheme::Pinyin));
+        assert_eq!(Scheme::for_text("東京へ行きます"), Some(Scheme::Hepburn));
+        assert_eq!(Scheme::for_text("カタカナ only"), Some(Scheme::Hepburn));
+        assert_eq!(Scheme::for_text("Hello, world"), None);
+        assert_eq!(Scheme::for_text("안녕하세요"), None);
+        assert_eq!(Scheme::for_text(""), None);
+    }
+
+    #[test]
+    fn test_latin_parts_are_kept() {
+        let runs = split_runs("我用Rust写代码，版本1.85。");
+        assert_eq!(cjk_runs(&runs), vec!["我用", "写代码", "版本"]);
+        let romanized = ["wǒ yòng", "xiě dàimǎ", "bǎnběn"].map(String::from);
+        assert_eq!(
+            assemble(&runs, &romanized).unwrap(),
+            "wǒ yòng Rust xiě dàimǎ, bǎnběn 1.85."
+        );
+
+        // Names with spaces and punctuation of their own stay intact
+        let runs = split_runs("田中さんとJohn O'Neil-Smithが来た");
+        let romanized = ["Tanaka-san to", "ga kita"].map(String::from);
+        assert_eq!(
+            assemble(&runs, &romanized).unwrap(),
+            "Tanaka-san to John O'Neil-Smith ga kita"
+        );
+    }
+
+    #[test]
+    fn test_lines_are_kept() {
+        let runs = split_runs("第一行。\n第二行");
+        assert_eq!(cjk_runs(&runs), vec!["第一行", "第二行"]);
+        let romanized = ["dì yī háng", "dì èr háng"].map(String::from);
+        assert_eq!(
+            assemble(&runs, &romanized).unwrap(),
+            "dì yī háng.\ndì èr háng"
+        );
+    }
+
+    #[test]
+    fn test_mismatched_answer_is_rejected() {
+        let runs = split_runs("你好 world 再见");
+        assert_eq!(assemble(&runs, &["nǐ hǎo".to_string()]), None);
+        assert!(split_runs("").is_empty());
+    }
+}
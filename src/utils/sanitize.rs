@@ -0,0 +1,316 @@
+//! Cleanup of invisible characters in pasted source text.
+//!
+//! Text copied from web pages and word processors carries byte order marks,
+//! zero-width spaces, soft hyphens, bidi control marks and non-breaking
+//! spaces. They are invisible in the source box but confuse the model,
+//! inflate token counts and make otherwise identical texts miss the cache.
+//!
+//! Zero-width joiners and non-joiners are kept: emoji sequences and scripts
+//! such as Persian and the Indic scripts need them.
+
+/// What [`clean_source_text`] removed or replaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanReport {
+    /// Byte order marks (U+FEFF)
+    pub byte_order_marks: usize,
+    /// Zero-width spaces and word joiners
+    pub zero_width: usize,
+    /// Soft hyphens (U+00AD)
+    pub soft_hyphens: usize,
+    /// Directional marks, embeddings, overrides and isolates
+    pub bidi_marks: usize,
+    /// Control characters other than tab and line breaks
+    pub control: usize,
+    /// Non-breaking and fixed-width spaces, replaced with plain spaces
+    pub spaces: usize,
+    /// Unicode line and paragraph separators, replaced with line breaks
+    pub line_separators: usize,
+}
+
+impl CleanReport {
+    /// Whether nothing was changed.
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        *self == CleanReport::default()
+    }
+
+    /// Adds the counts of `other`.
+    pub fn merge(&mut self, other: CleanReport) {
+        self.byte_order_marks += other.byte_order_marks;
+        self.zero_width += other.zero_width;
+        self.soft_hyphens += other.soft_hyphens;
+        self.bidi_marks += other.bidi_marks;
+        self.control += other.control;
+        self.spaces += other.spaces;
+        self.line_separators += other.line_separators;
+    }
+
+    /// One-line description, e.g. "Removed 14 zero-width characters".
+    ///
+    /// Returns `None` if nothing was changed.
+    pub fn summary(&self) -> Option<String> {
+        let removed: Vec<String> = [
+            (self.zero_width, "zero-width character"),
+            (self.soft_hyphens, "soft hyphen"),
+            (self.bidi_marks, "bidi mark"),
+            (self.byte_order_marks, "byte order mark"),
+            (self.control, "control character"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| plural(count, name))
+        .collect();
+
+        let replaced: Vec<String> = [
+            (self.spaces, "non-breaking space"),
+            (self.line_separators, "line separator"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| plural(count, name))
+        .collect();
+
+        let mut parts = Vec::new();
+        if !removed.is_empty() {
+            parts.push(format!("Removed {}", removed.join(", ")));
+        }
+        if !replaced.is_empty() {
+            let verb = if parts.is_empty() {
+                "Replaced"
+            } else {
+                "replaced"
+            };
+            parts.push(format!("{} {}", verb, replaced.join(", ")));
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
+}
+
+fn plural(count: usize, name: &str) -> String {
+    if count == 1 {
+        format!("1 {}", name)
+    } else {
+        format!("{} {}s", count, name)
+    }
+}
+
+/// How a character is cleaned up.
+enum Cleanup {
+    Keep,
+    Remove,
+    Replace(char),
+}
+
+/// Decides what happens to `c`, counting it in `report`.
+fn classify(c: char, report: &mut CleanReport) -> Cleanup {
+    match c {
+        '\u{FEFF}' => {
+            report.byte_order_marks += 1;
+            Cleanup::Remove
+        }
+        // Zero-width space, word joiner, Mongolian vowel separator
+        '\u{200B}' | '\u{2060}' | '\u{180E}' => {
+            report.zero_width += 1;
+            Cleanup::Remove
+        }
+        '\u{00AD}' => {
+            report.soft_hyphens += 1;
+            Cleanup::Remove
+        }
+        // LRM, RLM, ALM, embeddings and overrides, isolates
+        '\u{200E}'
+        | '\u{200F}'
+        | '\u{061C}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2066}'..='\u{2069}' => {
+            report.bidi_marks += 1;
+            Cleanup::Remove
+        }
+        // No-break, narrow no-break and figure spaces
+        '\u{00A0}' | '\u{202F}' | '\u{2007}' => {
+            report.spaces += 1;
+            Cleanup::Replace(' ')
+        }
+        '\u{2028}' | '\u{2029}' => {
+            report.line_separators += 1;
+            Cleanup::Replace('\n')
+        }
+        '\t' | '\n' | '\r' => Cleanup::Keep,
+        c if c.is_control() => {
+            report.control += 1;
+            Cleanup::Remove
+        }
+        _ => Cleanup::Keep,
+    }
+}
+
+/// Strips invisible characters from text entering the source box and
+/// normalizes unusual spaces and line separators.
+///
+/// # Returns
+///
+/// The cleaned text and what was changed
+pub fn clean_source_text(text: &str) -> (String, CleanReport) {
+    let mut report = CleanReport::default();
+    let mut cleaned = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match classify(c, &mut report) {
+            Cleanup::Keep => cleaned.push(c),
+            Cleanup::Remove => {}
+            Cleanup::Replace(replacement) => cleaned.push(replacement),
+        }
+    }
+
+    (cleaned, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(text: &str) -> String {
+        clean_source_text(text).0
+    }
+
+    #[test]
+    fn test_clean_text_is_unchanged() {
+        let corpus = [
+            "",
+            "Hello, world!",
+            "Tabs\tand\nnewlines\r\nstay",
+            "中文文本，没有问题。",
+            "日本語のテキスト",
+            "مرحبا بالعالم",
+            "שלום עולם",
+            "Ünïcödé àccents — and “quotes” … ©",
+            "Emoji 👍🏽 and 🇩🇪",
+        ];
+        for text in corpus {
+            let (cleaned, report) = clean_source_text(text);
+            assert_eq!(cleaned, text);
+            assert!(report.is_empty(), "{:?} reported {:?}", text, report);
+            assert_eq!(report.summary(), None);
+        }
+    }
+
+    #[test]
+    fn test_strips_byte_order_mark() {
+        // Text files saved by Notepad, and copies from some PDFs
+        let (cleaned, report) = clean_source_text("\u{FEFF}Hello\u{FEFF} world");
+        assert_eq!(cleaned, "Hello world");
+        assert_eq!(report.byte_order_marks, 2);
+    }
+
+    #[test]
+    fn test_strips_zero_width_characters() {
+        // Web pages insert zero-width spaces as line break opportunities
+        let (cleaned, report) =
+            clean_source_text("super\u{200B}cali\u{200B}fragilistic\u{2060}expiali\u{180E}docious");
+        assert_eq!(cleaned, "supercalifragilisticexpialidocious");
+        assert_eq!(report.zero_width, 4);
+        assert_eq!(
+            report.summary().as_deref(),
+            Some("Removed 4 zero-width characters")
+        );
+    }
+
+    #[test]
+    fn test_keeps_joiners() {
+        // Family emoji and Persian half-space rely on ZWJ and ZWNJ
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let persian = "می\u{200C}خواهم";
+        assert_eq!(clean(family), family);
+        assert_eq!(clean(persian), persian);
+    }
+
+    #[test]
+    fn test_strips_soft_hyphens() {
+        // Word documents with automatic hyphenation
+        let (cleaned, report) = clean_source_text("Donau\u{00AD}dampf\u{00AD}schiff\u{00AD}fahrt");
+        assert_eq!(cleaned, "Donaudampfschifffahrt");
+        assert_eq!(report.soft_hyphens, 3);
+        assert_eq!(report.summary().as_deref(), Some("Removed 3 soft hyphens"));
+    }
+
+    #[test]
+    fn test_strips_bidi_marks() {
+        let (cleaned, report) = clean_source_text(
+            "\u{200F}مرحبا\u{200E} Rust \u{202B}text\u{202C} \u{2067}iso\u{2069}\u{061C}",
+        );
+        assert_eq!(cleaned, "مرحبا Rust text iso");
+        assert_eq!(report.bidi_marks, 7);
+    }
+
+    #[test]
+    fn test_replaces_non_breaking_spaces() {
+        // French typography and Word's Ctrl+Shift+Space
+        let (cleaned, report) =
+            clean_source_text("Bonjour\u{202F}! Il est 10\u{00A0}h, prix\u{2007}100");
+        assert_eq!(cleaned, "Bonjour ! Il est 10 h, prix 100");
+        assert_eq!(report.spaces, 3);
+        assert_eq!(
+            report.summary().as_deref(),
+            Some("Replaced 3 non-breaking spaces")
+        );
+    }
+
+    #[test]
+    fn test_replaces_line_separators() {
+        let (cleaned, report) = clean_source_text("one\u{2028}two\u{2029}three");
+        assert_eq!(cleaned, "one\ntwo\nthree");
+        assert_eq!(report.line_separators, 2);
+    }
+
+    #[test]
+    fn test_strips_control_characters() {
+        let (cleaned, report) =
+            clean_source_text("bell\u{0007} null\u{0000} esc\u{001B}[0m c1\u{0085}\u{009B}");
+        assert_eq!(cleaned, "bell null esc[0m c1");
+        assert_eq!(report.control, 5);
+    }
+
+    #[test]
+    fn test_mixed_real_world_paste() {
+        // Copied from a web page: BOM, ZWSPs, a soft hyphen, NBSPs and an RLM
+        let pasted = "\u{FEFF}The\u{00A0}quick\u{200B} brown fox jum\u{00AD}ps over\u{00A0}the\u{200F} lazy\u{200B} dog.";
+        let (cleaned, report) = clean_source_text(pasted);
+        assert_eq!(cleaned, "The quick brown fox jumps over the lazy dog.");
+        assert_eq!(
+            report,
+            CleanReport {
+                byte_order_marks: 1,
+                zero_width: 2,
+                soft_hyphens: 1,
+                bidi_marks: 1,
+                control: 0,
+                spaces: 2,
+                line_separators: 0,
+            }
+        );
+        assert_eq!(
+            report.summary().as_deref(),
+            Some(
+                "Removed 2 zero-width characters, 1 soft hyphen, 1 bidi mark, 1 byte order mark; replaced 2 non-breaking spaces"
+            )
+        );
+    }
+
+    #[test]
+    fn test_cleaning_is_idempotent() {
+        let pasted = "\u{FEFF}a\u{200B}b\u{00A0}c\u{2028}d\u{00AD}e";
+        let once = clean(pasted);
+        let (twice, report) = clean_source_text(&once);
+        assert_eq!(once, twice);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_merge_reports() {
+        let mut total = CleanReport::default();
+        total.merge(clean_source_text("a\u{200B}b").1);
+        total.merge(clean_source_text("c\u{200B}\u{00A0}d").1);
+        assert_eq!(total.zero_width, 2);
+        assert_eq!(total.spaces, 1);
+    }
+}
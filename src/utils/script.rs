@@ -0,0 +1,253 @@
+//! Dominant writing system of a text, for sizing its font.
+//!
+//! Dense CJK text needs a larger font than Latin text to be as readable.
+//! The text is sampled, its letters are counted per script, and the script
+//! of most of them decides the font size multiplier. Adaptive sizing only
+//! switches to another script when it clearly takes over, so a streaming
+//! translation or a quoted foreign name doesn't make the font jump.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Characters looked at to measure a text's script mix.
+const SAMPLE_CHARS: usize = 2000;
+
+/// Share of the letters a script needs to count as dominant.
+const DOMINANT_SHARE: f64 = 0.5;
+
+/// How much larger the new dominant script's share must be than the share
+/// of the script in use before the font switches.
+const SWITCH_MARGIN: f64 = 0.2;
+
+/// Writing systems with their own font size multiplier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+    /// Chinese characters, kana and Hangul
+    Cjk,
+}
+
+impl Script {
+    /// All scripts, in display order.
+    pub const ALL: [Script; 8] = [
+        Script::Latin,
+        Script::Cyrillic,
+        Script::Greek,
+        Script::Arabic,
+        Script::Hebrew,
+        Script::Devanagari,
+        Script::Thai,
+        Script::Cjk,
+    ];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            Script::Latin => "Latin",
+            Script::Cyrillic => "Cyrillic",
+            Script::Greek => "Greek",
+            Script::Arabic => "Arabic",
+            Script::Hebrew => "Hebrew",
+            Script::Devanagari => "Devanagari",
+            Script::Thai => "Thai",
+            Script::Cjk => "CJK",
+        }
+    }
+}
+
+/// Script of `c`, or `None` for digits, punctuation, symbols and scripts
+/// without a multiplier.
+pub fn script_of(c: char) -> Option<Script> {
+    let script = match c as u32 {
+        0x0041..=0x005A | 0x0061..=0x007A => Script::Latin,
+        0x00C0..=0x024F | 0x1E00..=0x1EFF if c.is_alphabetic() => Script::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x0400..=0x052F => Script::Cyrillic,
+        0x0590..=0x05FF => Script::Hebrew,
+        0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+        0x0900..=0x097F => Script::Devanagari,
+        0x0E00..=0x0E7F => Script::Thai,
+        0x1100..=0x11FF
+        | 0x3040..=0x30FF
+        | 0x3130..=0x318F
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xAC00..=0xD7AF
+        | 0xF900..=0xFAFF
+        | 0x20000..=0x2FFFF => Script::Cjk,
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// Letters of a text counted per script.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptMix {
+    counts: BTreeMap<Script, usize>,
+    total: usize,
+}
+
+impl ScriptMix {
+    /// Counts the letters of the start of `text`.
+    pub fn measure(text: &str) -> Self {
+        let mut mix = ScriptMix::default();
+        for script in text.chars().take(SAMPLE_CHARS).filter_map(script_of) {
+            *mix.counts.entry(script).or_default() += 1;
+            mix.total += 1;
+        }
+        mix
+    }
+
+    /// Share of the counted letters written in `script`.
+    pub fn share(&self, script: Script) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.counts.get(&script).copied().unwrap_or(0) as f64 / self.total as f64
+    }
+
+    /// The script of most letters, if it has at least half of them.
+    pub fn dominant(&self) -> Option<Script> {
+        self.counts
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(script, _)| *script)
+            .filter(|script| self.share(*script) >= DOMINANT_SHARE)
+    }
+}
+
+/// Default font size multipliers per script.
+pub fn default_font_scales() -> BTreeMap<Script, f32> {
+    Script::ALL
+        .into_iter()
+        .map(|script| {
+            let scale = match script {
+                Script::Cjk => 1.15,
+                Script::Thai | Script::Devanagari | Script::Arabic => 1.1,
+                _ => 1.0,
+            };
+            (script, scale)
+        })
+        .collect()
+}
+
+/// Script whose multiplier sizes a panel's text, kept until another script
+/// clearly takes over.
+#[derive(Debug, Clone, Default)]
+pub struct AdaptiveFont {
+    /// Length of the text last measured
+    measured_len: Option<usize>,
+    script: Option<Script>,
+}
+
+impl AdaptiveFont {
+    /// Measures `text` if it changed and returns the script to size it for.
+    ///
+    /// A text without a dominant script keeps the current one, an empty
+    /// text resets it.
+    pub fn update(&mut self, text: &str) -> Option<Script> {
+        if self.measured_len == Some(text.len()) {
+            return self.script;
+        }
+        self.measured_len = Some(text.len());
+
+        let mix = ScriptMix::measure(text);
+        if mix.total == 0 {
+            self.script = None;
+            return None;
+        }
+        if let Some(dominant) = mix.dominant()
+            && Some(dominant) != self.script
+        {
+            let current_share = self.script.map_or(0.0, |script| mix.share(script));
+            if mix.share(dominant) - current_share > SWITCH_MARGIN {
+                self.script = Some(dominant);
+            }
+        }
+        self.script
+    }
+
+    /// Font size for `text`, `base` times the multiplier of its script.
+    pub fn font_size(&mut self, text: &str, base: f32, scales: &BTreeMap<Script, f32>) -> f32 {
+        let scale = self
+            .update(text)
+            .and_then(|script| scales.get(&script))
+            .copied()
+            .unwrap_or(1.0);
+        base * scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_of() {
+        assert_eq!(script_of('a'), Some(Script::Latin));
+        assert_eq!(script_of('é'), Some(Script::Latin));
+        assert_eq!(script_of('Ж'), Some(Script::Cyrillic));
+        assert_eq!(script_of('λ'), Some(Script::Greek));
+        assert_eq!(script_of('ש'), Some(Script::Hebrew));
+        assert_eq!(script_of('ع'), Some(Script::Arabic));
+        assert_eq!(script_of('क'), Some(Script::Devanagari));
+        assert_eq!(script_of('ก'), Some(Script::Thai));
+        for c in ['中', 'か', 'カ', '한'] {
+            assert_eq!(script_of(c), Some(Script::Cjk), "{}", c);
+        }
+        for c in ['1', ' ', '。', '!', '×', '—'] {
+            assert_eq!(script_of(c), None, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_dominant_script_of_mixed_text() {
+        let mix = ScriptMix::measure("这是一个关于 Rust 编程语言的长句子。");
+        assert_eq!(mix.dominant(), Some(Script::Cjk));
+
+        let mix = ScriptMix::measure("The word 你好 means hello in Chinese.");
+        assert_eq!(mix.dominant(), Some(Script::Latin));
+
+        // No script has half of the letters
+        let mix = ScriptMix::measure("abc абв 中文字");
+        assert_eq!(mix.dominant(), None);
+        assert!((mix.share(Script::Cjk) - 1.0 / 3.0).abs() < 1e-9);
+
+        // Digits and punctuation don't count
+        let mix = ScriptMix::measure("2024-06-01: 東京!!!");
+        assert_eq!(mix.dominant(), Some(Script::Cjk));
+        assert_eq!(ScriptMix::measure("12:30 — 100%").dominant(), None);
+    }
+
+    #[test]
+    fn test_adaptive_font_switches_only_on_clear_change() {
+        let scales = default_font_scales();
+        let mut font = AdaptiveFont::default();
+        assert_eq!(font.font_size("", 16.0, &scales), 16.0);
+
+        let chinese = "这是一段很长的中文翻译文本";
+        assert_eq!(font.update(chinese), Some(Script::Cjk));
+        assert!((font.font_size(chinese, 16.0, &scales) - 18.4).abs() < 1e-4);
+
+        // A Latin name in the Chinese text keeps the CJK size
+        let mixed = format!("{} Rust 和 Cargo", chinese);
+        assert_eq!(font.update(&mixed), Some(Script::Cjk));
+
+        // A slight Latin majority is not enough to switch back
+        let close = "中文字符号 Rustac";
+        assert_eq!(font.update(close), Some(Script::Cjk));
+
+        // Mostly Latin text is
+        let latin = "A translation written mostly in English 中文";
+        assert_eq!(font.update(latin), Some(Script::Latin));
+        assert_eq!(font.font_size(latin, 16.0, &scales), 16.0);
+
+        assert_eq!(font.update(""), None);
+    }
+}
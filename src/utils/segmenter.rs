@@ -0,0 +1,431 @@
+//! Sentence splitting.
+//!
+//! Sentences are found with the Unicode sentence boundary rules (UAX #29),
+//! which already keep decimals such as `3.14` and lowercase continuations
+//! such as `e.g. the` together, and split after `。！？`. Boundaries right
+//! after a known abbreviation (`Dr.`, `e.g.` before a capital) or an initial
+//! are then removed.
+//!
+//! All results are byte ranges into the original text.
+
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Abbreviations that never end a sentence, in any language.
+const ABBREVIATIONS: &[&str] = &[
+    "Mr.", "Mrs.", "Ms.", "Dr.", "Prof.", "Sr.", "Jr.", "St.", "Mt.", "Capt.", "Col.", "Gen.",
+    "Lt.", "Sgt.", "Rev.", "Hon.", "vs.", "e.g.", "i.e.", "E.g.", "I.e.", "cf.", "Cf.", "approx.",
+    "Fig.", "fig.", "Figs.", "No.", "Nos.", "Vol.", "vol.", "pp.", "Eq.", "Sec.", "Ch.",
+];
+
+/// Additional abbreviations for German.
+const GERMAN_ABBREVIATIONS: &[&str] = &[
+    "z.B.", "Z.B.", "bzw.", "ca.", "d.h.", "Nr.", "Hr.", "Fr.", "Str.", "vgl.", "Vgl.", "evtl.",
+    "ggf.", "u.a.",
+];
+
+/// Additional abbreviations for French.
+const FRENCH_ABBREVIATIONS: &[&str] = &["M.", "MM.", "Mme.", "Mlle.", "p.ex.", "env.", "av."];
+
+/// Additional abbreviations for Spanish.
+const SPANISH_ABBREVIATIONS: &[&str] = &["Sra.", "Srta.", "Dra.", "Ud.", "Uds.", "p.ej.", "pág."];
+
+/// Language-specific abbreviations for a language name or code.
+fn language_abbreviations(lang_hint: Option<&str>) -> &'static [&'static str] {
+    let Some(hint) = lang_hint else {
+        return &[];
+    };
+    let hint = hint.to_lowercase();
+    let primary = hint.split(['-', '_']).next().unwrap_or_default();
+    match primary {
+        "de" | "german" | "deutsch" => GERMAN_ABBREVIATIONS,
+        "fr" | "french" | "français" => FRENCH_ABBREVIATIONS,
+        "es" | "spanish" | "español" => SPANISH_ABBREVIATIONS,
+        _ => &[],
+    }
+}
+
+/// Whether `sentence` ends with an abbreviation or an initial like `J.`,
+/// so the boundary after it is not a real one.
+fn ends_with_abbreviation(sentence: &str, extra: &[&str]) -> bool {
+    let last_word = sentence
+        .trim_end()
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(['(', '[', '"', '\'', '“', '‘']);
+
+    let mut chars = last_word.chars();
+    let is_initial = matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(c), Some('.'), None) if c.is_uppercase()
+    );
+
+    is_initial || ABBREVIATIONS.contains(&last_word) || extra.contains(&last_word)
+}
+
+/// Splits `text` into sentences.
+///
+/// `lang_hint` is a language name or code (`"Deutsch"`, `"de"`) that adds
+/// that language's abbreviations; English ones always apply since texts are
+/// often mixed. Ranges exclude surrounding whitespace, and whitespace-only
+/// stretches produce no range.
+pub fn split_sentences(text: &str, lang_hint: Option<&str>) -> Vec<Range<usize>> {
+    let extra = language_abbreviations(lang_hint);
+    let mut sentences: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+
+    for (offset, bound) in text.split_sentence_bound_indices() {
+        let end = offset + bound.len();
+        let candidate = &text[start..end];
+        // A line break is always a boundary, even after an abbreviation
+        if !candidate
+            .trim_end_matches([' ', '\t'])
+            .ends_with(['\n', '\r'])
+            && ends_with_abbreviation(candidate, extra)
+            && end < text.len()
+        {
+            continue;
+        }
+        sentences.push(start..end);
+        start = end;
+    }
+    if start < text.len() {
+        sentences.push(start..text.len());
+    }
+
+    sentences
+        .into_iter()
+        .filter_map(|range| {
+            let sentence = &text[range.clone()];
+            let trimmed = sentence.trim_start();
+            let start = range.start + (sentence.len() - trimmed.len());
+            let end = start + trimmed.trim_end().len();
+            (start < end).then_some(start..end)
+        })
+        .collect()
+}
+
+/// Groups whole sentences into contiguous chunks of up to `max_chars`
+/// characters that together cover all of `text`.
+///
+/// A sentence longer than `max_chars` becomes a chunk of its own.
+pub fn chunk_sentences(text: &str, lang_hint: Option<&str>, max_chars: usize) -> Vec<Range<usize>> {
+    let mut chunks: Vec<Range<usize>> = Vec::new();
+    let mut chunk_start = 0;
+    let mut chunk_chars = 0;
+
+    let sentences = split_sentences(text, lang_hint);
+    for (i, sentence) in sentences.iter().enumerate() {
+        // Each sentence owns the whitespace up to the next one
+        let end = sentences.get(i + 1).map_or(text.len(), |next| next.start);
+        let start = if i == 0 { 0 } else { sentence.start };
+        let chars = text[start..end].chars().count();
+
+        if chunk_chars > 0 && chunk_chars + chars > max_chars {
+            chunks.push(chunk_start..start);
+            chunk_start = start;
+            chunk_chars = 0;
+        }
+        chunk_chars += chars;
+    }
+    if chunk_start < text.len() {
+        chunks.push(chunk_start..text.len());
+    }
+
+    chunks
+}
+
+/// Sentences of a text that arrives in pieces, such as a streaming
+/// translation, handed out as soon as they are complete.
+///
+/// A sentence is complete once the next one has begun or a line break
+/// follows it, so an abbreviation at the end of a piece is not taken for
+/// the end of a sentence. The last sentence is handed out after
+/// [`Self::finish`].
+#[derive(Debug, Default)]
+pub struct SentenceStream {
+    text: String,
+    /// Bytes of `text` already handed out
+    taken: usize,
+    lang_hint: Option<String>,
+    finished: bool,
+}
+
+impl SentenceStream {
+    /// A stream of text in the language `lang_hint`, see [`split_sentences`].
+    pub fn new(lang_hint: Option<&str>) -> Self {
+        SentenceStream {
+            lang_hint: lang_hint.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    /// Appends the next piece of the text.
+    pub fn push(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+    }
+
+    /// Marks the text as complete, so its last sentence is handed out too.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// The next complete sentence, if there is one yet.
+    pub fn next_sentence(&mut self) -> Option<String> {
+        let rest = &self.text[self.taken..];
+        let sentences = split_sentences(rest, self.lang_hint.as_deref());
+        let first = sentences.first()?.clone();
+        let complete = self.finished || sentences.len() > 1 || rest[first.end..].contains('\n');
+        if !complete {
+            return None;
+        }
+        let sentence = rest[first.clone()].to_string();
+        self.taken += first.end;
+        Some(sentence)
+    }
+
+    /// Whether the text is complete and every sentence was handed out.
+    pub fn is_exhausted(&self) -> bool {
+        self.finished && self.text[self.taken..].trim().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences<'a>(text: &'a str, lang_hint: Option<&str>) -> Vec<&'a str> {
+        split_sentences(text, lang_hint)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_simple_sentences() {
+        assert_eq!(
+            sentences("Hello world. How are you? Fine!", None),
+            vec!["Hello world.", "How are you?", "Fine!"]
+        );
+        assert_eq!(
+            sentences("No terminal punctuation", None),
+            vec!["No terminal punctuation"]
+        );
+        assert!(sentences("", None).is_empty());
+        assert!(sentences("   \n\t ", None).is_empty());
+    }
+
+    #[test]
+    fn test_english_abbreviations() {
+        assert_eq!(
+            sentences("Dr. Smith arrived. He was late.", None),
+            vec!["Dr. Smith arrived.", "He was late."]
+        );
+        assert_eq!(
+            sentences("Bring fruit, e.g. Apples or pears. Then leave.", None),
+            vec!["Bring fruit, e.g. Apples or pears.", "Then leave."]
+        );
+        assert_eq!(
+            sentences("Use a tool, i.e. a hammer. Done.", None),
+            vec!["Use a tool, i.e. a hammer.", "Done."]
+        );
+        assert_eq!(
+            sentences(
+                "Mr. and Mrs. Jones met Prof. Lee vs. Team Blue. It was close.",
+                None
+            ),
+            vec![
+                "Mr. and Mrs. Jones met Prof. Lee vs. Team Blue.",
+                "It was close."
+            ]
+        );
+        assert_eq!(
+            sentences("See Fig. 3 for details. Results follow.", None),
+            vec!["See Fig. 3 for details.", "Results follow."]
+        );
+    }
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(
+            sentences("J. K. Rowling wrote it. Everyone read it.", None),
+            vec!["J. K. Rowling wrote it.", "Everyone read it."]
+        );
+    }
+
+    #[test]
+    fn test_abbreviation_at_end_of_text() {
+        assert_eq!(sentences("He met the Dr.", None), vec!["He met the Dr."]);
+    }
+
+    #[test]
+    fn test_decimal_numbers() {
+        assert_eq!(
+            sentences("Pi is about 3.14 in value. Version 2.0.1 shipped.", None),
+            vec!["Pi is about 3.14 in value.", "Version 2.0.1 shipped."]
+        );
+        assert_eq!(
+            sentences("It costs $1,299.99. Buy now.", None),
+            vec!["It costs $1,299.99.", "Buy now."]
+        );
+    }
+
+    #[test]
+    fn test_cjk_punctuation() {
+        assert_eq!(
+            sentences("我爱你。你爱我吗？是的！", None),
+            vec!["我爱你。", "你爱我吗？", "是的！"]
+        );
+        assert_eq!(
+            sentences("今日は晴れです。明日は雨でしょう。", None),
+            vec!["今日は晴れです。", "明日は雨でしょう。"]
+        );
+        assert_eq!(
+            sentences("版本是3.5。很好", None),
+            vec!["版本是3.5。", "很好"]
+        );
+    }
+
+    #[test]
+    fn test_ellipses() {
+        assert_eq!(
+            sentences("He paused... Then he left.", None),
+            vec!["He paused...", "Then he left."]
+        );
+        assert_eq!(
+            sentences("Wait... what happened?", None),
+            vec!["Wait... what happened?"]
+        );
+        assert_eq!(
+            sentences("Well… I don't know.", None),
+            vec!["Well… I don't know."]
+        );
+    }
+
+    #[test]
+    fn test_quotes_and_line_breaks() {
+        assert_eq!(
+            sentences("He said \"Stop.\" Then he ran.", None),
+            vec!["He said \"Stop.\"", "Then he ran."]
+        );
+        assert_eq!(
+            sentences("Title\nFirst line. Second line.", None),
+            vec!["Title", "First line.", "Second line."]
+        );
+        // A line break after an abbreviation still ends the sentence
+        assert_eq!(
+            sentences("Ask the Dr.\nNext item.", None),
+            vec!["Ask the Dr.", "Next item."]
+        );
+    }
+
+    #[test]
+    fn test_mixed_language_paragraph() {
+        assert_eq!(
+            sentences(
+                "The menu said 欢迎光临。It means welcome. 这很有趣！Isn't it?",
+                None
+            ),
+            vec![
+                "The menu said 欢迎光临。",
+                "It means welcome.",
+                "这很有趣！",
+                "Isn't it?"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_language_specific_abbreviations() {
+        let text = "Es gibt Obst, z.B. Äpfel. Das ist gut.";
+        assert_eq!(
+            sentences(text, Some("Deutsch")),
+            vec!["Es gibt Obst, z.B. Äpfel.", "Das ist gut."]
+        );
+        assert_eq!(
+            sentences(text, Some("de-AT")),
+            sentences(text, Some("Deutsch"))
+        );
+        assert_eq!(
+            sentences("Bonjour M. Dupont. Ça va?", Some("fr")),
+            vec!["Bonjour M. Dupont.", "Ça va?"]
+        );
+    }
+
+    #[test]
+    fn test_ranges_point_into_original_text() {
+        let text = "  First one.   Second one.  ";
+        let ranges = split_sentences(text, None);
+        assert_eq!(ranges, vec![2..12, 15..26]);
+    }
+
+    #[test]
+    fn test_chunk_sentences_cover_text() {
+        let text = "One two three. Four five six. Seven eight nine. Ten.";
+        let chunks = chunk_sentences(text, None, 30);
+        assert_eq!(chunks.first().unwrap().start, 0);
+        assert_eq!(chunks.last().unwrap().end, text.len());
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        let texts: Vec<&str> = chunks.iter().map(|c| &text[c.clone()]).collect();
+        assert_eq!(
+            texts,
+            vec!["One two three. Four five six. ", "Seven eight nine. Ten."]
+        );
+
+        // An oversized sentence stays whole
+        let long = format!("{}. Short.", "a".repeat(50));
+        let chunks = chunk_sentences(&long, None, 10);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(&long[chunks[1].clone()], "Short.");
+
+        assert!(chunk_sentences("", None, 10).is_empty());
+    }
+
+    /// Feeds `chunks` one by one, collecting the sentences handed out
+    /// after each.
+    fn stream(chunks: &[&str], lang_hint: Option<&str>) -> Vec<Vec<String>> {
+        let mut stream = SentenceStream::new(lang_hint);
+        let mut steps = Vec::new();
+        for chunk in chunks {
+            stream.push(chunk);
+            steps.push(std::iter::from_fn(|| stream.next_sentence()).collect());
+        }
+        stream.finish();
+        steps.push(std::iter::from_fn(|| stream.next_sentence()).collect());
+        assert!(stream.is_exhausted());
+        steps
+    }
+
+    #[test]
+    fn test_sentence_stream_waits_for_complete_sentences() {
+        let steps = stream(&["Hello wor", "ld. How are", " you? Fi", "ne"], None);
+        assert_eq!(
+            steps,
+            vec![
+                vec![],
+                vec!["Hello world.".to_string()],
+                vec!["How are you?".to_string()],
+                vec![],
+                vec!["Fine".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sentence_stream_abbreviations_and_line_breaks() {
+        // "Dr." at the end of a piece may not end the sentence
+        let steps = stream(&["I met Dr.", " Smith. Then", " we left."], None);
+        assert_eq!(steps[0], Vec::<String>::new());
+        assert_eq!(steps[1], vec!["I met Dr. Smith.".to_string()]);
+        assert_eq!(steps[3], vec!["Then we left.".to_string()]);
+
+        // A line break ends a heading without punctuation
+        let steps = stream(&["Chapter One\n", "第一句。第", "二句。"], Some("中文"));
+        assert_eq!(steps[0], vec!["Chapter One".to_string()]);
+        assert_eq!(steps[1], vec!["第一句。".to_string()]);
+        assert_eq!(steps[3], vec!["第二句。".to_string()]);
+    }
+}
@@ -0,0 +1,363 @@
+//! Source and translation pairs formatted for sharing.
+//!
+//! Chat apps get the pair as a Markdown quote, a one-line "source → translation"
+//! for short texts, or as an image. The image is laid out and rasterized here
+//! with the fonts and colors the UI passes in, so it looks like the app and
+//! shows every script the app can.
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+
+/// Longest text, in characters, shared as a single line.
+const MAX_LINE_CHARS: usize = 120;
+
+/// Space around and between the sections of an image, in pixels.
+const PADDING: u32 = 24;
+
+/// Size of the section labels relative to the text.
+const LABEL_SCALE: f32 = 0.75;
+
+/// Line height relative to the font size.
+const LINE_SPACING: f32 = 1.4;
+
+/// Formats the pair as a Markdown quote with labeled sections.
+pub fn markdown_quote(source: &str, translation: &str, target_language: &str) -> String {
+    let quote = |text: &str| {
+        text.trim()
+            .lines()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    format!(
+        "> **Source**\n{}\n>\n> **Translation ({})**\n{}",
+        quote(source),
+        target_language,
+        quote(translation)
+    )
+}
+
+/// Formats a short pair as `source → translation`, `None` when either side
+/// is long or has several lines.
+pub fn arrow_line(source: &str, translation: &str) -> Option<String> {
+    let (source, translation) = (source.trim(), translation.trim());
+    let fits = |text: &str| {
+        !text.is_empty() && !text.contains('\n') && text.chars().count() <= MAX_LINE_CHARS
+    };
+    (fits(source) && fits(translation)).then(|| format!("{} → {}", source, translation))
+}
+
+/// Colors and sizes of a shared image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageStyle {
+    pub background: [u8; 4],
+    pub text: [u8; 4],
+    /// Color of the section labels
+    pub label: [u8; 4],
+    /// Text size in pixels
+    pub font_size: f32,
+    pub width: u32,
+    /// Height above which the text is cut off with a notice
+    pub max_height: u32,
+}
+
+impl Default for ImageStyle {
+    fn default() -> Self {
+        ImageStyle {
+            background: [27, 27, 27, 255],
+            text: [230, 230, 230, 255],
+            label: [140, 140, 140, 255],
+            font_size: 22.0,
+            width: 800,
+            max_height: 2400,
+        }
+    }
+}
+
+/// A rendered pair.
+pub struct ShareImage {
+    pub image: RgbaImage,
+    /// Whether text was left out to respect the maximum height
+    pub shortened: bool,
+}
+
+/// Splits `text` into lines no wider than `max_width`, measuring characters
+/// with `width_of`.
+///
+/// Lines break after the last space that fits, or between any two
+/// characters when there is none, as in CJK text.
+fn wrap_lines(text: &str, max_width: f32, width_of: impl Fn(char) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut width = 0.0;
+        // Byte offset of the last space in `line`
+        let mut last_space = None;
+        for c in paragraph.chars() {
+            let advance = width_of(c);
+            if width + advance > max_width && !line.is_empty() {
+                match last_space.take() {
+                    Some(space) => {
+                        let rest = line.split_off(space);
+                        lines.push(line.trim_end().to_string());
+                        line = rest.trim_start().to_string();
+                    }
+                    None => lines.push(std::mem::take(&mut line)),
+                }
+                width = line.chars().map(&width_of).sum();
+            }
+            if c == ' ' {
+                last_space = Some(line.len());
+            }
+            line.push(c);
+            width += advance;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Characters laid out with the first font that has them.
+struct Fonts<'a> {
+    fonts: &'a [FontArc],
+    scale: PxScale,
+}
+
+impl Fonts<'_> {
+    fn font_for(&self, c: char) -> Option<&FontArc> {
+        self.fonts
+            .iter()
+            .find(|font| font.glyph_id(c).0 != 0)
+            .or(self.fonts.first())
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        self.font_for(c).map_or(0.0, |font| {
+            let font = font.as_scaled(self.scale);
+            font.h_advance(font.glyph_id(c))
+        })
+    }
+
+    fn ascent(&self) -> f32 {
+        self.fonts
+            .first()
+            .map_or(self.scale.y, |font| font.as_scaled(self.scale).ascent())
+    }
+
+    /// Draws `line` with its top left corner at `(x, y)`.
+    fn draw(&self, image: &mut RgbaImage, line: &str, x: f32, y: f32, color: [u8; 4]) {
+        let baseline = y + self.ascent();
+        let mut caret = x;
+        for c in line.chars() {
+            let Some(font) = self.font_for(c) else {
+                continue;
+            };
+            let scaled = font.as_scaled(self.scale);
+            let glyph = scaled
+                .glyph_id(c)
+                .with_scale_and_position(self.scale, ab_glyph::point(caret, baseline));
+            caret += scaled.h_advance(glyph.id);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px >= 0 && py >= 0 && (px as u32) < image.width() && (py as u32) < image.height()
+                {
+                    blend(image.get_pixel_mut(px as u32, py as u32), color, coverage);
+                }
+            });
+        }
+    }
+}
+
+/// Mixes `color` into `pixel` by `coverage`.
+fn blend(pixel: &mut Rgba<u8>, color: [u8; 4], coverage: f32) {
+    let alpha = coverage.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+    for i in 0..3 {
+        pixel[i] = (pixel[i] as f32 * (1.0 - alpha) + color[i] as f32 * alpha).round() as u8;
+    }
+}
+
+/// Renders the pair as an image in `style`, with `fonts` tried in order for
+/// each character.
+pub fn render_image(
+    source: &str,
+    translation: &str,
+    target_language: &str,
+    fonts: &[FontArc],
+    style: &ImageStyle,
+) -> ShareImage {
+    let text_fonts = Fonts {
+        fonts,
+        scale: PxScale::from(style.font_size),
+    };
+    let label_fonts = Fonts {
+        fonts,
+        scale: PxScale::from(style.font_size * LABEL_SCALE),
+    };
+    let line_height = style.font_size * LINE_SPACING;
+    let label_height = style.font_size * LABEL_SCALE * LINE_SPACING;
+    let text_width = style.width.saturating_sub(2 * PADDING) as f32;
+
+    // Labels and text of both sections
+    let translation_label = format!("Translation · {}", target_language);
+    let sections = [
+        ("Source", source.trim()),
+        (translation_label.as_str(), translation.trim()),
+    ];
+    let mut rows: Vec<(bool, String)> = Vec::new();
+    for (label, text) in sections {
+        rows.push((true, label.to_string()));
+        rows.extend(
+            wrap_lines(text, text_width, |c| text_fonts.advance(c))
+                .into_iter()
+                .map(|line| (false, line)),
+        );
+    }
+
+    // Cut off the rows that don't fit, keeping room for the notice
+    let row_height = |is_label: bool| if is_label { label_height } else { line_height };
+    let fixed = 3.0 * PADDING as f32;
+    let mut height = fixed;
+    let mut shortened = false;
+    let mut shown = 0;
+    for (is_label, _) in &rows {
+        if height + row_height(*is_label) + line_height > style.max_height as f32 {
+            shortened = true;
+            break;
+        }
+        height += row_height(*is_label);
+        shown += 1;
+    }
+    if shortened {
+        rows.truncate(shown);
+        rows.push((
+            true,
+            "… text shortened, copy it to see everything".to_string(),
+        ));
+        height += line_height;
+    }
+
+    let mut image =
+        RgbaImage::from_pixel(style.width, height.ceil() as u32, Rgba(style.background));
+    let mut y = PADDING as f32;
+    let mut first_label = true;
+    for (is_label, line) in &rows {
+        if *is_label {
+            if !first_label {
+                y += PADDING as f32;
+            }
+            first_label = false;
+            label_fonts.draw(&mut image, line, PADDING as f32, y, style.label);
+        } else {
+            text_fonts.draw(&mut image, line, PADDING as f32, y, style.text);
+        }
+        y += row_height(*is_label);
+    }
+
+    ShareImage { image, shortened }
+}
+
+/// Encodes an image as PNG.
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, image::ImageFormat::Png)
+        .map_err(|e| format!("Could not encode the image: {}", e))?;
+    Ok(bytes.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundled_fonts() -> Vec<FontArc> {
+        vec![
+            FontArc::try_from_slice(include_bytes!(
+                "../../fonts/NotoSerifKR-VariableFont_wght.ttf"
+            ))
+            .unwrap(),
+            FontArc::try_from_slice(include_bytes!("../../fonts/STSong.ttf")).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_markdown_quote() {
+        assert_eq!(
+            markdown_quote("Hello\n\nworld", "你好\n\n世界\n", "中文"),
+            "> **Source**\n> Hello\n>\n> world\n>\n> **Translation (中文)**\n> 你好\n>\n> 世界"
+        );
+    }
+
+    #[test]
+    fn test_arrow_line_only_for_short_texts() {
+        assert_eq!(
+            arrow_line(" Good morning ", "Guten Morgen"),
+            Some("Good morning → Guten Morgen".to_string())
+        );
+        assert_eq!(arrow_line("Two\nlines", "Zwei Zeilen"), None);
+        assert_eq!(arrow_line(&"long ".repeat(30), "lang"), None);
+        assert_eq!(arrow_line("Hello", ""), None);
+    }
+
+    #[test]
+    fn test_wrap_lines() {
+        // Every character is one unit wide
+        let wrap = |text: &str, width: f32| wrap_lines(text, width, |_| 1.0);
+
+        assert_eq!(wrap("one two three", 8.0), vec!["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4.0), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(
+            wrap("这是一个很长的句子", 4.0),
+            vec!["这是一个", "很长的句", "子"]
+        );
+        // Paragraphs and blank lines are kept
+        assert_eq!(wrap("a b\n\nc", 10.0), vec!["a b", "", "c"]);
+        // A word longer than the line is broken
+        assert_eq!(
+            wrap("a verylongword", 5.0),
+            vec!["a", "veryl", "ongwo", "rd"]
+        );
+    }
+
+    #[test]
+    fn test_render_image_draws_text_in_the_style() {
+        let style = ImageStyle::default();
+        let shared = render_image("Hello", "你好", "中文", &bundled_fonts(), &style);
+
+        assert!(!shared.shortened);
+        assert_eq!(shared.image.width(), style.width);
+        assert!(shared.image.height() < 300);
+        assert_eq!(shared.image.get_pixel(0, 0).0, style.background);
+        // Some pixels took the text color
+        assert!(
+            shared
+                .image
+                .pixels()
+                .any(|p| p.0 != style.background && p.0[0] > 150)
+        );
+    }
+
+    #[test]
+    fn test_render_image_caps_long_texts() {
+        let style = ImageStyle {
+            max_height: 400,
+            ..ImageStyle::default()
+        };
+        let long = "这是一个很长的翻译。".repeat(200);
+        let shared = render_image("Source", &long, "中文", &bundled_fonts(), &style);
+
+        assert!(shared.shortened);
+        assert!(shared.image.height() <= style.max_height);
+        assert!(encode_png(&shared.image).unwrap().starts_with(b"\x89PNG"));
+    }
+}
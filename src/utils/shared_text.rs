@@ -0,0 +1,259 @@
+//! Text edited in more than one widget at once.
+//!
+//! The source text can be edited both in the sidebar and, in the editable
+//! layout, in the central panel. Each widget edits a [`Replica`] of the
+//! [`SharedText`] instead of the text itself, under these rules:
+//!
+//! - Only the focused widget receives input, so it is the one writer of
+//!   the frame; it [commits](Replica::commit) its edit right after its
+//!   input was handled.
+//! - Every change of the shared text bumps its generation. A replica
+//!   [syncs](Replica::sync) to the latest generation before it is drawn,
+//!   so the other widget shows the edit no later than the next frame.
+//! - An edit made on an older generation than the latest is stale and
+//!   dropped, rather than overwriting the newer text, such as one loaded
+//!   from the history meanwhile.
+//! - While an input method is composing in a widget, its replica is
+//!   neither synced nor committed, so the composition is never replaced
+//!   or spread half-done. The buffer is kept through the frame the input
+//!   method commits in, for the widget to finish the composition in it,
+//!   and is committed then like any edit.
+
+use egui::{Event, ImeEvent};
+
+/// The text of several editors, with a generation that counts its changes.
+#[derive(Debug, Clone, Default)]
+pub struct SharedText {
+    text: String,
+    generation: u64,
+}
+
+impl SharedText {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Counts the changes of the text.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Replaces the text from outside the editors.
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.generation += 1;
+    }
+}
+
+/// One widget's copy of a [`SharedText`].
+#[derive(Debug, Clone, Default)]
+pub struct Replica {
+    buffer: String,
+    /// Generation the buffer was synced to or committed as, `None` before
+    /// the first sync
+    seen: Option<u64>,
+    /// An input method is composing text in the widget
+    composing: bool,
+    /// A composition was going on when the frame began, so the buffer is
+    /// kept for the widget to finish it
+    holding: bool,
+}
+
+impl Replica {
+    /// Notes whether an input method is composing in the widget, from the
+    /// events of this frame.
+    ///
+    /// Call before [`Self::sync`]; only the focused widget receives the
+    /// events, and losing the focus ends a composition.
+    pub fn observe(&mut self, focused: bool, events: &[Event]) {
+        self.holding = self.composing;
+        if !focused {
+            if self.composing {
+                // The abandoned preedit is still in the buffer
+                self.seen = None;
+            }
+            self.composing = false;
+            self.holding = false;
+            return;
+        }
+        for event in events {
+            if let Event::Ime(ime) = event {
+                self.composing = match ime {
+                    ImeEvent::Enabled => true,
+                    // An empty preedit is a composition cleared with backspace
+                    ImeEvent::Preedit(text) => !text.is_empty(),
+                    ImeEvent::Commit(_) | ImeEvent::Disabled => false,
+                };
+            }
+        }
+    }
+
+    pub fn is_composing(&self) -> bool {
+        self.composing
+    }
+
+    /// The text for the widget to show and edit, brought up to date with
+    /// `shared` unless a composition is in progress or ends this frame.
+    pub fn sync(&mut self, shared: &SharedText) -> &mut String {
+        if !self.holding && !self.composing && self.seen != Some(shared.generation) {
+            self.buffer.clone_from(&shared.text);
+            self.seen = Some(shared.generation);
+        }
+        &mut self.buffer
+    }
+
+    /// Makes the widget's edit the shared text.
+    ///
+    /// # Returns
+    ///
+    /// Whether the shared text changed; a stale edit is dropped and the
+    /// replica synced again next time.
+    pub fn commit(&mut self, shared: &mut SharedText) -> bool {
+        self.holding = false;
+        if self.composing || self.buffer == shared.text {
+            return false;
+        }
+        if self.seen != Some(shared.generation) {
+            tracing::debug!(
+                seen = ?self.seen,
+                generation = shared.generation,
+                "Dropping a stale edit of the shared text"
+            );
+            return false;
+        }
+        shared.set(self.buffer.clone());
+        self.seen = Some(shared.generation);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ime(event: ImeEvent) -> Vec<Event> {
+        vec![Event::Ime(event)]
+    }
+
+    /// A frame of a widget: its events, then its edit of the shown text.
+    fn frame(
+        replica: &mut Replica,
+        shared: &mut SharedText,
+        focused: bool,
+        events: &[Event],
+        edit: impl FnOnce(&mut String),
+    ) -> bool {
+        replica.observe(focused, events);
+        edit(replica.sync(shared));
+        replica.commit(shared)
+    }
+
+    #[test]
+    fn test_focused_edit_reaches_the_other_widget() {
+        let mut shared = SharedText::default();
+        shared.set("Hello");
+        let mut sidebar = Replica::default();
+        let mut central = Replica::default();
+        assert_eq!(sidebar.sync(&shared), "Hello");
+        assert_eq!(central.sync(&shared), "Hello");
+
+        // A paste into the central panel while the sidebar is drawn first
+        assert!(!frame(&mut sidebar, &mut shared, false, &[], |_| {}));
+        assert!(frame(&mut central, &mut shared, true, &[], |text| {
+            text.push_str(" world")
+        }));
+        assert_eq!(shared.as_str(), "Hello world");
+        assert_eq!(sidebar.sync(&shared), "Hello world");
+
+        // Unchanged text doesn't count as an edit
+        assert!(!frame(&mut sidebar, &mut shared, true, &[], |_| {}));
+    }
+
+    #[test]
+    fn test_stale_edit_does_not_overwrite() {
+        let mut shared = SharedText::default();
+        shared.set("draft");
+        let mut sidebar = Replica::default();
+        let mut central = Replica::default();
+        sidebar.sync(&shared);
+        central.sync(&shared);
+
+        // Both edited on the same generation, the first commit wins
+        sidebar.sync(&shared).push_str(" one");
+        central.sync(&shared).push_str(" two");
+        assert!(sidebar.commit(&mut shared));
+        assert!(!central.commit(&mut shared));
+        assert_eq!(shared.as_str(), "draft one");
+        assert_eq!(central.sync(&shared), "draft one");
+
+        // Text loaded from elsewhere wins over an edit made before it
+        sidebar.sync(&shared).push_str(" more");
+        shared.set("From the history");
+        assert!(!sidebar.commit(&mut shared));
+        assert_eq!(sidebar.sync(&shared), "From the history");
+    }
+
+    #[test]
+    fn test_composition_is_never_replaced() {
+        let mut shared = SharedText::default();
+        shared.set("我想");
+        let mut sidebar = Replica::default();
+        let mut central = Replica::default();
+        sidebar.sync(&shared);
+        central.sync(&shared);
+
+        // The preedit is in the buffer but not shared
+        let composing = ime(ImeEvent::Preedit("chi".to_string()));
+        assert!(!frame(
+            &mut sidebar,
+            &mut shared,
+            true,
+            &composing,
+            |text| text.push_str("chi")
+        ));
+        assert!(sidebar.is_composing());
+        assert_eq!(central.sync(&shared), "我想");
+
+        // A change from the other widget waits for the composition
+        assert!(frame(&mut central, &mut shared, true, &[], |text| {
+            text.insert_str(0, "嗯，")
+        }));
+        assert_eq!(sidebar.sync(&shared), "我想chi");
+
+        let committed = ime(ImeEvent::Commit("吃".to_string()));
+        assert!(!frame(
+            &mut sidebar,
+            &mut shared,
+            true,
+            &committed,
+            |text| *text = "我想吃".to_string()
+        ));
+        assert!(!sidebar.is_composing());
+        // Made on the older text, so the newer one is kept and shown
+        assert_eq!(shared.as_str(), "嗯，我想");
+        assert_eq!(sidebar.sync(&shared), "嗯，我想");
+
+        // Composing on the latest text commits once done
+        let composing = ime(ImeEvent::Preedit("fan".to_string()));
+        frame(&mut sidebar, &mut shared, true, &composing, |text| {
+            text.push_str("fan")
+        });
+        let committed = ime(ImeEvent::Commit("饭".to_string()));
+        assert!(frame(&mut sidebar, &mut shared, true, &committed, |text| {
+            *text = "嗯，我想饭".to_string()
+        }));
+        assert_eq!(central.sync(&shared), "嗯，我想饭");
+    }
+
+    #[test]
+    fn test_losing_focus_ends_composition() {
+        let mut replica = Replica::default();
+        replica.observe(true, &ime(ImeEvent::Enabled));
+        assert!(replica.is_composing());
+        replica.observe(true, &ime(ImeEvent::Preedit(String::new())));
+        assert!(!replica.is_composing());
+        replica.observe(true, &ime(ImeEvent::Preedit("ka".to_string())));
+        replica.observe(false, &[]);
+        assert!(!replica.is_composing());
+    }
+}
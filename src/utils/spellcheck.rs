@@ -0,0 +1,587 @@
+//! Spell checking of the source text.
+//!
+//! Checking runs on a background thread in chunks, so a long text never
+//! blocks the UI. Every check gets a generation number; results of a check
+//! that has been superseded by newer text are discarded, and chunk results of
+//! the current check are merged into the list as they arrive.
+//!
+//! Hunspell dictionaries (`<lang>.aff` + `<lang>.dic`) are loaded with
+//! `spellbook` when the `spellcheck` feature is enabled. Without the feature,
+//! or without a dictionary for the language, checking is silently disabled.
+//! The `bundled-dictionary` feature builds in an en_US dictionary, used when
+//! none is installed.
+
+use crate::utils::segmenter;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Size of the chunks a text is checked in, in characters.
+const CHUNK_CHARS: usize = 4 * 1024;
+
+/// Language of the dictionary built in with the `bundled-dictionary` feature.
+const BUNDLED_LANGUAGE: &str = "en_US";
+
+/// A word list that can check and correct words.
+pub trait Dictionary: Send + Sync {
+    /// Whether `word` is spelled correctly.
+    fn check(&self, word: &str) -> bool;
+    /// Suggested corrections for `word`, best first.
+    fn suggest(&self, word: &str) -> Vec<String>;
+}
+
+#[cfg(feature = "spellcheck")]
+impl Dictionary for spellbook::Dictionary {
+    fn check(&self, word: &str) -> bool {
+        spellbook::Dictionary::check(self, word)
+    }
+
+    fn suggest(&self, word: &str) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        spellbook::Dictionary::suggest(self, word, &mut suggestions);
+        suggestions
+    }
+}
+
+/// A misspelled word in the checked text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    /// Byte range in the checked text
+    pub range: Range<usize>,
+    pub word: String,
+}
+
+impl Misspelling {
+    /// Whether the misspelling still applies to `text`, which may have been
+    /// edited since it was checked.
+    pub fn matches(&self, text: &str) -> bool {
+        text.get(self.range.clone()) == Some(self.word.as_str())
+    }
+}
+
+/// Results for one chunk of a check.
+#[derive(Debug)]
+pub struct CheckResult {
+    pub generation: u64,
+    /// Byte range of the text that was checked
+    pub range: Range<usize>,
+    pub misspellings: Vec<Misspelling>,
+}
+
+/// Whether a character belongs to a script written without spaces, which
+/// cannot be split into words here.
+fn is_unspaced_script(c: char) -> bool {
+    matches!(c, '\u{2E80}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+/// Byte ranges of the words in `text[range]` that should be checked.
+///
+/// Skips URLs, e-mail addresses, words with digits, acronyms, single
+/// letters, and scripts written without spaces.
+fn words(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut offset = range.start;
+
+    for token in text[range].split_inclusive(char::is_whitespace) {
+        let token_start = offset;
+        offset += token.len();
+        if token.contains("://") || token.contains('@') || token.starts_with("www.") {
+            continue;
+        }
+
+        let mut word_start = None;
+        for (i, c) in token.char_indices().chain([(token.len(), ' ')]) {
+            let in_word = c.is_alphanumeric()
+                || (matches!(c, '\'' | '’') && word_start.is_some_and(|start| i > start));
+            match (word_start, in_word) {
+                (None, true) => word_start = Some(i),
+                (Some(start), false) => {
+                    let word = token[start..i].trim_end_matches(['\'', '’']);
+                    let checkable = word.chars().count() > 1
+                        && !word
+                            .chars()
+                            .any(|c| c.is_numeric() || is_unspaced_script(c))
+                        && !word.chars().all(|c| c.is_uppercase());
+                    if checkable {
+                        words.push(token_start + start..token_start + start + word.len());
+                    }
+                    word_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    words
+}
+
+/// Splits `text` into chunks of up to `max_chars` characters, ending at
+/// sentence boundaries where possible and otherwise at whitespace.
+fn chunk_ranges(text: &str, max_chars: usize) -> Vec<Range<usize>> {
+    segmenter::chunk_sentences(text, None, max_chars)
+        .into_iter()
+        .flat_map(|sentences| split_at_whitespace(text, sentences, max_chars))
+        .collect()
+}
+
+/// Splits `text[range]` into chunks of up to `max_chars` characters, ending
+/// at whitespace.
+fn split_at_whitespace(text: &str, range: Range<usize>, max_chars: usize) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = range.start;
+
+    while start < range.end {
+        let mut end = text[start..range.end]
+            .char_indices()
+            .nth(max_chars.max(1))
+            .map_or(range.end, |(i, _)| start + i);
+        if end < range.end {
+            // Don't split a word, unless it is longer than a whole chunk
+            if let Some(space) = text[start..end].rfind(char::is_whitespace) {
+                end = start + space + 1;
+                while !text.is_char_boundary(end) {
+                    end += 1;
+                }
+            }
+        }
+        chunks.push(start..end);
+        start = end;
+    }
+
+    chunks
+}
+
+/// Checks the words in `text[range]`.
+fn check_range(dictionary: &dyn Dictionary, text: &str, range: Range<usize>) -> Vec<Misspelling> {
+    words(text, range)
+        .into_iter()
+        .filter(|word| !dictionary.check(&text[word.clone()]))
+        .map(|word| Misspelling {
+            word: text[word.clone()].to_string(),
+            range: word,
+        })
+        .collect()
+}
+
+/// Merges the results of a checked chunk into `current`, replacing the
+/// misspellings previously found in that range.
+pub fn merge(current: &mut Vec<Misspelling>, checked: Range<usize>, found: Vec<Misspelling>) {
+    current.retain(|m| !checked.contains(&m.range.start));
+    current.extend(found);
+    current.sort_by_key(|m| m.range.start);
+}
+
+/// Background spell checker for one dictionary.
+pub struct SpellChecker {
+    dictionary: Option<Arc<dyn Dictionary>>,
+    generation: Arc<AtomicU64>,
+    tx: Sender<CheckResult>,
+    rx: Receiver<CheckResult>,
+}
+
+impl SpellChecker {
+    /// Creates a checker, disabled when `dictionary` is `None`.
+    pub fn new(dictionary: Option<Arc<dyn Dictionary>>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        SpellChecker {
+            dictionary,
+            generation: Arc::new(AtomicU64::new(0)),
+            tx,
+            rx,
+        }
+    }
+
+    /// Whether a dictionary is loaded.
+    pub fn is_available(&self) -> bool {
+        self.dictionary.is_some()
+    }
+
+    /// Starts checking `text` in the background, superseding earlier checks.
+    ///
+    /// Returns the generation of the new check.
+    pub fn check(&self, text: String) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let Some(dictionary) = self.dictionary.clone() else {
+            return generation;
+        };
+
+        let current = self.generation.clone();
+        let tx = self.tx.clone();
+        let spawned = std::thread::Builder::new()
+            .name("spellcheck".to_string())
+            .spawn(move || {
+                for range in chunk_ranges(&text, CHUNK_CHARS) {
+                    // Newer text arrived, stop working on this one
+                    if current.load(Ordering::SeqCst) != generation {
+                        return;
+                    }
+                    let misspellings = check_range(dictionary.as_ref(), &text, range.clone());
+                    let result = CheckResult {
+                        generation,
+                        range,
+                        misspellings,
+                    };
+                    if tx.send(result).is_err() {
+                        return;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("Failed to spawn spell check thread: {}", e);
+        }
+
+        generation
+    }
+
+    /// Returns the chunk results that arrived since the last call.
+    pub fn poll(&self) -> Vec<CheckResult> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Suggested corrections for `word`.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        self.dictionary
+            .as_ref()
+            .map(|dictionary| dictionary.suggest(word))
+            .unwrap_or_default()
+    }
+}
+
+/// Directories searched for Hunspell dictionaries, user directory first.
+fn dictionary_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(data_dir) = dirs::data_dir() {
+        dirs.push(data_dir.join("ai-translate").join("dictionaries"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join("Library").join("Spelling"));
+    }
+    for dir in [
+        "/usr/share/hunspell",
+        "/usr/share/myspell",
+        "/usr/share/myspell/dicts",
+        "/Library/Spelling",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// Languages with a dictionary installed, e.g. `en_US`.
+pub fn available_languages() -> Vec<String> {
+    if !cfg!(feature = "spellcheck") {
+        return Vec::new();
+    }
+
+    let mut languages: Vec<String> = dictionary_dirs()
+        .into_iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "dic" || !path.with_extension("aff").exists() {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().to_string())
+        })
+        .collect();
+    if cfg!(feature = "bundled-dictionary") {
+        languages.push(BUNDLED_LANGUAGE.to_string());
+    }
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
+/// Loads the dictionary for `language`, `None` if none is available.
+///
+/// An installed dictionary is preferred over the built-in one.
+#[cfg(feature = "spellcheck")]
+pub fn load_dictionary(language: &str) -> Option<Arc<dyn Dictionary>> {
+    use std::borrow::Cow;
+
+    let dir = dictionary_dirs().into_iter().find(|dir| {
+        dir.join(format!("{}.dic", language)).exists()
+            && dir.join(format!("{}.aff", language)).exists()
+    });
+    let (aff, dic, source) = match dir {
+        Some(dir) => {
+            let aff = std::fs::read_to_string(dir.join(format!("{}.aff", language))).ok()?;
+            let dic = std::fs::read_to_string(dir.join(format!("{}.dic", language))).ok()?;
+            (Cow::Owned(aff), Cow::Owned(dic), format!("{:?}", dir))
+        }
+        None => {
+            let (aff, dic) = bundled_dictionary(language)?;
+            (
+                Cow::Borrowed(aff),
+                Cow::Borrowed(dic),
+                "the app".to_string(),
+            )
+        }
+    };
+    match spellbook::Dictionary::new(&aff, &dic) {
+        Ok(dictionary) => {
+            tracing::info!("Loaded {} dictionary from {}", language, source);
+            Some(Arc::new(dictionary))
+        }
+        Err(e) => {
+            tracing::warn!("Failed to parse {} dictionary: {}", language, e);
+            None
+        }
+    }
+}
+
+/// The `.aff` and `.dic` content of the built-in dictionary for `language`.
+#[cfg(feature = "bundled-dictionary")]
+fn bundled_dictionary(language: &str) -> Option<(&'static str, &'static str)> {
+    (language == BUNDLED_LANGUAGE).then_some((
+        include_str!("../../dictionaries/en_US/en_US.aff"),
+        include_str!("../../dictionaries/en_US/en_US.dic"),
+    ))
+}
+
+/// The `.aff` and `.dic` content of the built-in dictionary for `language`.
+#[cfg(all(feature = "spellcheck", not(feature = "bundled-dictionary")))]
+fn bundled_dictionary(_language: &str) -> Option<(&'static str, &'static str)> {
+    None
+}
+
+/// Loads the dictionary for `language`, `None` if none is available.
+#[cfg(not(feature = "spellcheck"))]
+pub fn load_dictionary(_language: &str) -> Option<Arc<dyn Dictionary>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock_mutex;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    struct WordList(HashSet<&'static str>);
+
+    impl Dictionary for WordList {
+        fn check(&self, word: &str) -> bool {
+            self.0.contains(word.to_lowercase().as_str())
+        }
+
+        fn suggest(&self, word: &str) -> Vec<String> {
+            self.0
+                .iter()
+                .filter(|w| w.len() == word.len())
+                .map(|w| w.to_string())
+                .collect()
+        }
+    }
+
+    fn dictionary() -> Arc<dyn Dictionary> {
+        Arc::new(WordList(
+            ["the", "quick", "brown", "fox", "don't", "jumps"]
+                .into_iter()
+                .collect(),
+        ))
+    }
+
+    fn word_list(text: &str) -> Vec<&str> {
+        words(text, 0..text.len())
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_words_skip_non_words() {
+        assert_eq!(
+            word_list("The quick, brown fox's \"jumps\""),
+            vec!["The", "quick", "brown", "fox's", "jumps"]
+        );
+        assert_eq!(
+            word_list("see https://exmaple.com or mail@exmaple.com"),
+            vec!["see", "or"]
+        );
+        assert_eq!(word_list("a NASA abc123 3rd 中文字 ok"), vec!["ok"]);
+        assert_eq!(word_list("'quoted' don’t"), vec!["quoted", "don’t"]);
+    }
+
+    #[test]
+    fn test_chunk_ranges_cover_text_at_word_boundaries() {
+        let text = "lorem ipsum dolor sit amet ".repeat(100);
+        let chunks = chunk_ranges(&text, 64);
+        assert_eq!(chunks.first().unwrap().start, 0);
+        assert_eq!(chunks.last().unwrap().end, text.len());
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+            assert!(text[..pair[0].end].ends_with(' '));
+        }
+
+        // Multi-byte characters and a word longer than a chunk
+        let text = "ääääääääääääääääääää ü";
+        let chunks = chunk_ranges(text, 5);
+        assert_eq!(chunks.last().unwrap().end, text.len());
+        assert!(chunks.iter().all(|c| text.get(c.clone()).is_some()));
+        // Counted in characters, not bytes
+        assert_eq!(&text[chunks[0].clone()], "äääää");
+
+        // Sentences are kept whole when they fit
+        let text = "First sentence here. Second one is here.";
+        let chunks = chunk_ranges(text, 30);
+        assert_eq!(&text[chunks[0].clone()], "First sentence here. ");
+        assert_eq!(chunks.last().unwrap().end, text.len());
+    }
+
+    #[test]
+    fn test_check_range_finds_misspellings() {
+        let text = "The quikc brown fox jumsp";
+        let found = check_range(dictionary().as_ref(), text, 0..text.len());
+        assert_eq!(
+            found,
+            vec![
+                Misspelling {
+                    range: 4..9,
+                    word: "quikc".to_string()
+                },
+                Misspelling {
+                    range: 20..25,
+                    word: "jumsp".to_string()
+                },
+            ]
+        );
+        assert!(found[0].matches(text));
+        assert!(!found[0].matches("The quick brown fox"));
+    }
+
+    #[test]
+    fn test_merge_replaces_checked_range() {
+        let m = |start: usize, word: &str| Misspelling {
+            range: start..start + word.len(),
+            word: word.to_string(),
+        };
+        let mut current = vec![m(0, "aa"), m(10, "bb"), m(20, "cc")];
+        merge(&mut current, 5..15, vec![m(12, "dd")]);
+        assert_eq!(current, vec![m(0, "aa"), m(12, "dd"), m(20, "cc")]);
+    }
+
+    /// Holds the first check back until the test opens the gate, telling
+    /// the test once it is held.
+    struct Gated {
+        inner: Arc<dyn Dictionary>,
+        gate: Mutex<Option<(Sender<()>, Receiver<()>)>>,
+    }
+
+    impl Gated {
+        /// The dictionary, a receiver telling that a check is held and the
+        /// sender opening the gate.
+        fn new(inner: Arc<dyn Dictionary>) -> (Arc<Self>, Receiver<()>, Sender<()>) {
+            let (held_tx, held_rx) = mpsc::channel();
+            let (open_tx, open_rx) = mpsc::channel();
+            let gated = Gated {
+                inner,
+                gate: Mutex::new(Some((held_tx, open_rx))),
+            };
+            (Arc::new(gated), held_rx, open_tx)
+        }
+    }
+
+    impl Dictionary for Gated {
+        fn check(&self, word: &str) -> bool {
+            let gate = lock_mutex!(self.gate).take();
+            if let Some((held, open)) = gate {
+                held.send(()).unwrap();
+                open.recv().unwrap();
+            }
+            self.inner.check(word)
+        }
+
+        fn suggest(&self, word: &str) -> Vec<String> {
+            self.inner.suggest(word)
+        }
+    }
+
+    /// Waits for the next chunk result, failing the test if none comes.
+    fn next_result(checker: &SpellChecker) -> CheckResult {
+        checker
+            .rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("no check result arrived")
+    }
+
+    #[test]
+    fn test_large_text_checked_in_background() {
+        let (gated, held, open) = Gated::new(dictionary());
+        let checker = SpellChecker::new(Some(gated));
+        let text = "the quick brown fox jumps ".repeat(2000) + "teh";
+        assert!(text.len() > 50 * 1024);
+
+        // Returns while the dictionary holds the check on another thread
+        let generation = checker.check(text.clone());
+        held.recv().unwrap();
+        assert!(checker.poll().is_empty());
+        open.send(()).unwrap();
+
+        let mut misspellings = Vec::new();
+        let mut checked = 0;
+        while checked < text.len() {
+            let result = next_result(&checker);
+            assert_eq!(result.generation, generation);
+            checked += result.range.len();
+            merge(&mut misspellings, result.range, result.misspellings);
+        }
+        assert_eq!(checked, text.len());
+        assert_eq!(misspellings.len(), 1);
+        assert_eq!(misspellings[0].word, "teh");
+        assert!(checker.suggest("teh").contains(&"the".to_string()));
+    }
+
+    #[test]
+    fn test_stale_generation_stops() {
+        let (gated, held, open) = Gated::new(dictionary());
+        let dictionary: Arc<dyn Dictionary> = gated;
+        let checker = SpellChecker::new(Some(dictionary.clone()));
+        let stale_text = "teh ".repeat(50_000);
+        let first = checker.check(stale_text.clone());
+        // Superseded while its first chunk is being checked
+        held.recv().unwrap();
+        let second = checker.check("teh".to_string());
+        assert!(second > first);
+        open.send(()).unwrap();
+
+        let mut results = Vec::new();
+        while !results.iter().any(|r: &CheckResult| r.generation == second) {
+            results.push(next_result(&checker));
+        }
+        // Both threads ended once only the checker and the test hold the dictionary
+        while Arc::strong_count(&dictionary) > 2 {
+            std::thread::yield_now();
+        }
+        results.extend(checker.poll());
+
+        // The superseded check stopped after the chunk it was in
+        let first_chunks = results.iter().filter(|r| r.generation == first).count();
+        assert_eq!(first_chunks, 1);
+        assert!(chunk_ranges(&stale_text, CHUNK_CHARS).len() > 1);
+    }
+
+    #[cfg(feature = "bundled-dictionary")]
+    #[test]
+    fn test_bundled_dictionary_checks_english() {
+        let (aff, dic) = bundled_dictionary(BUNDLED_LANGUAGE).unwrap();
+        let dictionary = spellbook::Dictionary::new(aff, dic).unwrap();
+        assert!(Dictionary::check(&dictionary, "translation"));
+        assert!(!Dictionary::check(&dictionary, "translashun"));
+        assert!(bundled_dictionary("de_DE").is_none());
+        assert!(available_languages().contains(&BUNDLED_LANGUAGE.to_string()));
+    }
+
+    #[test]
+    fn test_without_dictionary_is_disabled() {
+        let checker = SpellChecker::new(None);
+        assert!(!checker.is_available());
+        checker.check("teh".to_string());
+        assert!(checker.poll().is_empty());
+        assert!(checker.suggest("teh").is_empty());
+    }
+}
@@ -0,0 +1,775 @@
+//! String values of JSON and YAML documents, translated in place.
+//!
+//! Localization files such as `{"greeting": "Hello, {{name}}!"}` only need
+//! their values translated. A [`StructuredDocument`] records where each
+//! string value is written in the source, so translations are spliced into
+//! the original text: key order, indentation, compact or pretty layout,
+//! comments and every other value stay exactly as they were.
+//!
+//! Interpolation tokens such as `{{name}}`, `{0}`, `%s` or `%{count}` are
+//! masked before translating and must all come back in the translation;
+//! a value whose tokens went missing is flagged instead of applied.
+
+use crate::utils::list;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+/// Interpolation tokens of the common i18n formats.
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        \{\{[^{}]*\}\}                       # {{name}}, Handlebars and i18next
+        | [%$]\{[^{}]*\}                     # %{name} (Rails), ${name}
+        | \{[A-Za-z0-9_.]*(?:,[^{}]*)?\}     # {0}, {name}, {count, plural}
+        | %\([A-Za-z0-9_]+\)[sdif]           # %(name)s (Python)
+        | %(?:\d+\$)?[-+\x20\#0]*\d*(?:\.\d+)?(?:l{1,2}|h)?[sdiufxXeEgGcp@] # %s, %1$d, %.2f
+        | %%
+        ",
+    )
+    .expect("valid token pattern")
+});
+
+/// Kind of document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// Format of a file, by its extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+        }
+    }
+}
+
+/// How a value is written in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// A JSON string
+    Json,
+    /// A YAML double-quoted scalar
+    DoubleQuoted,
+    /// A YAML single-quoted scalar
+    SingleQuoted,
+    /// A YAML plain scalar
+    Plain,
+}
+
+/// A string value of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringValue {
+    /// Where the value sits, e.g. `menu.items[2].label`
+    pub path: String,
+    /// The value, unescaped
+    pub text: String,
+    /// Where the value is written, quotes included
+    span: Range<usize>,
+    style: Style,
+}
+
+/// A JSON or YAML document and its string values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredDocument {
+    pub format: Format,
+    source: String,
+    values: Vec<StringValue>,
+}
+
+impl StructuredDocument {
+    /// Parses `text` as a JSON object or array, or else as a YAML document.
+    ///
+    /// Only YAML with some structure counts, so that a couple of
+    /// `Subject: text` lines of prose are not taken for a document.
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::parse_as(text, Format::Json).or_else(|| {
+            let document = Self::parse_as(text, Format::Yaml)?;
+            let structured = text.lines().any(|line| {
+                let trimmed = line.trim_start();
+                trimmed.starts_with('#')
+                    || trimmed.starts_with("- ")
+                    || trimmed == "---"
+                    || (trimmed.len() < line.len() && !trimmed.is_empty())
+            });
+            (structured && document.values.len() >= 2).then_some(document)
+        })
+    }
+
+    /// Parses `text` as a document of `format`, e.g. from a file extension.
+    ///
+    /// Returns `None` if it is not one, or has no string values.
+    pub fn parse_as(text: &str, format: Format) -> Option<Self> {
+        let values = match format {
+            Format::Json => {
+                let value: serde_json::Value = serde_json::from_str(text).ok()?;
+                if !value.is_object() && !value.is_array() {
+                    return None;
+                }
+                let mut scanner = JsonScanner {
+                    text,
+                    pos: 0,
+                    values: Vec::new(),
+                };
+                scanner.value(String::new());
+                scanner.values
+            }
+            Format::Yaml => scan_yaml(text)?,
+        };
+        (!values.is_empty()).then(|| StructuredDocument {
+            format,
+            source: text.to_string(),
+            values,
+        })
+    }
+
+    pub fn values(&self) -> &[StringValue] {
+        &self.values
+    }
+
+    /// The document with the values at the indices of `translations`
+    /// replaced, everything else exactly as in the source.
+    pub fn render(&self, translations: &HashMap<usize, String>) -> String {
+        let mut output = String::with_capacity(self.source.len());
+        let mut end = 0;
+        for (i, value) in self.values.iter().enumerate() {
+            let Some(translation) = translations.get(&i) else {
+                continue;
+            };
+            output.push_str(&self.source[end..value.span.start]);
+            output.push_str(&encode(translation, value.style));
+            end = value.span.end;
+        }
+        output.push_str(&self.source[end..]);
+        output
+    }
+}
+
+/// Writes `text` as a value in `style`, switching a YAML value to double
+/// quotes where its style can't hold the text.
+fn encode(text: &str, style: Style) -> String {
+    let json = || serde_json::to_string(text).unwrap_or_default();
+    match style {
+        // JSON escapes are valid in YAML double-quoted scalars
+        Style::Json | Style::DoubleQuoted => json(),
+        Style::SingleQuoted if !text.contains('\n') => format!("'{}'", text.replace('\'', "''")),
+        Style::Plain if is_plain_safe(text) => text.to_string(),
+        Style::SingleQuoted | Style::Plain => json(),
+    }
+}
+
+/// Whether `text` reads back as the same string when written unquoted in YAML.
+fn is_plain_safe(text: &str) -> bool {
+    let Some(first) = text.chars().next() else {
+        return false;
+    };
+    !"-?:,[]{}#&*!|>'\"%@`".contains(first)
+        && text.trim() == text
+        && !text.contains(['\n', '\t'])
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !is_yaml_keyword(text)
+}
+
+/// Whether a plain YAML scalar is not a string but a number, boolean or null.
+fn is_yaml_keyword(text: &str) -> bool {
+    matches!(
+        text.to_ascii_lowercase().as_str(),
+        "~" | "null" | "true" | "false" | "yes" | "no" | "on" | "off" | ".nan" | ".inf" | "-.inf"
+    ) || text.parse::<f64>().is_ok()
+        || text.starts_with("0x")
+}
+
+/// Collects the string values of already validated JSON.
+struct JsonScanner<'a> {
+    text: &'a str,
+    pos: usize,
+    values: Vec<StringValue>,
+}
+
+impl JsonScanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips a string starting at the current position, returning its span.
+    fn string(&mut self) -> Range<usize> {
+        let start = self.pos;
+        self.pos += 1;
+        while let Some(b) = self.peek() {
+            self.pos += if b == b'\\' { 2 } else { 1 };
+            if b == b'"' {
+                break;
+            }
+        }
+        start..self.pos
+    }
+
+    fn value(&mut self, path: String) {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some(b'"') {
+                        // `}` of an empty object
+                        self.pos += 1;
+                        return;
+                    }
+                    let key = self.string();
+                    let key: String = serde_json::from_str(&self.text[key]).unwrap_or_default();
+                    self.skip_whitespace();
+                    // `:`
+                    self.pos += 1;
+                    self.value(join_key(&path, &key));
+                    self.skip_whitespace();
+                    let separator = self.peek();
+                    self.pos += 1;
+                    if separator != Some(b',') {
+                        return;
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return;
+                }
+                for index in 0.. {
+                    self.value(format!("{}[{}]", path, index));
+                    self.skip_whitespace();
+                    let separator = self.peek();
+                    self.pos += 1;
+                    if separator != Some(b',') {
+                        return;
+                    }
+                }
+            }
+            Some(b'"') => {
+                let span = self.string();
+                let text = serde_json::from_str(&self.text[span.clone()]).unwrap_or_default();
+                self.values.push(StringValue {
+                    path,
+                    text,
+                    span,
+                    style: Style::Json,
+                });
+            }
+            // Numbers, booleans and null
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Path of the member `key` of the object at `path`.
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Byte offset of the `: ` (or final `:`) that ends a YAML mapping key,
+/// outside quotes.
+fn key_end(content: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in content.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if i == 0 || content[..i].ends_with(' ') => return None,
+            (None, ':') => {
+                let after = &content[i + 1..];
+                if after.is_empty() || after.starts_with([' ', '\t']) {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A scalar after a key or `- `, relative to the start of `rest`.
+enum Scalar {
+    /// A string value at this span
+    String(Range<usize>, String, Style),
+    /// Nothing: a nested block follows
+    Empty,
+    /// A block scalar, flow collection, alias, number… left alone
+    Other,
+}
+
+/// Reads the scalar that makes up `rest`, up to a trailing comment.
+fn scalar(rest: &str) -> Option<Scalar> {
+    let trimmed = rest.trim_end();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Some(Scalar::Empty);
+    }
+    let comment_free = |after: &str| {
+        let after = after.trim();
+        after.is_empty() || after.starts_with('#')
+    };
+    match trimmed.chars().next()? {
+        '"' => {
+            let mut escaped = false;
+            let close = trimmed[1..].char_indices().find_map(|(i, c)| {
+                let found = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                found.then_some(i + 1)
+            })?;
+            if !comment_free(&trimmed[close + 1..]) {
+                return None;
+            }
+            // Close enough to JSON for the escapes translations contain
+            let text = serde_json::from_str(&trimmed[..=close]).ok();
+            Some(match text {
+                Some(text) => Scalar::String(0..close + 1, text, Style::DoubleQuoted),
+                None => Scalar::Other,
+            })
+        }
+        '\'' => {
+            let body = &trimmed[1..];
+            let mut i = 0;
+            let close = loop {
+                let found = body[i..].find('\'')? + i;
+                if body[found + 1..].starts_with('\'') {
+                    i = found + 2;
+                } else {
+                    break found + 1;
+                }
+            };
+            if !comment_free(&trimmed[close + 1..]) {
+                return None;
+            }
+            let text = trimmed[1..close].replace("''", "'");
+            Some(Scalar::String(0..close + 1, text, Style::SingleQuoted))
+        }
+        '|' | '>' | '[' | '{' | '&' | '*' | '!' => Some(Scalar::Other),
+        _ => {
+            let end = trimmed.find(" #").unwrap_or(trimmed.len());
+            let text = trimmed[..end].trim_end();
+            if is_yaml_keyword(text) {
+                Some(Scalar::Other)
+            } else {
+                Some(Scalar::String(
+                    0..text.len(),
+                    text.to_string(),
+                    Style::Plain,
+                ))
+            }
+        }
+    }
+}
+
+/// Collects the string values of a block-style YAML document.
+///
+/// Covers what localization files use: nested mappings, sequences, quoted
+/// and plain scalars and comments. Block scalars, flow collections,
+/// anchors and tags are left untouched, multi-line plain scalars too.
+/// Returns `None` on a line that is none of these.
+fn scan_yaml(text: &str) -> Option<Vec<StringValue>> {
+    let mut values = Vec::new();
+    // Indentation and path of the blocks the current line may belong to
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut sequence_lengths: HashMap<String, usize> = HashMap::new();
+    // Lines more indented than this continue a value that is left alone
+    let mut skip_deeper: Option<usize> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim_start();
+        let indent = content.len() - trimmed.len();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(limit) = skip_deeper {
+            if indent > limit {
+                continue;
+            }
+            skip_deeper = None;
+        }
+        if indent == 0 && (trimmed.starts_with("---") || trimmed.starts_with("...")) {
+            parents.clear();
+            continue;
+        }
+        if content[..indent].contains('\t') {
+            return None;
+        }
+
+        while parents.last().is_some_and(|(parent, _)| *parent >= indent) {
+            parents.pop();
+        }
+        let mut path = parents
+            .last()
+            .map(|(_, path)| path.clone())
+            .unwrap_or_default();
+        let mut column = indent;
+        let mut rest = trimmed;
+
+        // A sequence item, possibly starting a mapping
+        while rest == "-" || rest.starts_with("- ") {
+            let index = sequence_lengths.entry(path.clone()).or_default();
+            path = format!("{}[{}]", path, index);
+            *index += 1;
+            let after = rest[1..].trim_start();
+            parents.push((column, path.clone()));
+            column += rest.len() - after.len();
+            rest = after;
+        }
+
+        let (value_path, value_column) = match key_end(rest) {
+            Some(end) => {
+                let key = rest[..end].trim();
+                let key = key
+                    .strip_prefix(['"', '\''])
+                    .and_then(|key| key.strip_suffix(['"', '\'']))
+                    .unwrap_or(key);
+                let after = &rest[end + 1..];
+                let value = after.trim_start();
+                (
+                    join_key(&path, key),
+                    column + end + 1 + (after.len() - value.len()),
+                )
+            }
+            // A bare scalar is only valid as a sequence item
+            None if column > indent && !rest.is_empty() => (path.clone(), column),
+            None if rest.is_empty() => continue,
+            None => return None,
+        };
+
+        let value = &content[value_column..];
+        match scalar(value)? {
+            Scalar::Empty => parents.push((column, value_path)),
+            Scalar::Other => skip_deeper = Some(column),
+            Scalar::String(span, text, style) => {
+                let start = line_start + value_column;
+                values.push(StringValue {
+                    path: value_path,
+                    text,
+                    span: start + span.start..start + span.end,
+                    style,
+                });
+                skip_deeper = Some(column);
+            }
+        }
+    }
+    Some(values)
+}
+
+/// Masks the interpolation tokens of `text` as `⟦V1⟧`, `⟦V2⟧`…
+fn mask_tokens(text: &str) -> (String, Vec<String>) {
+    let mut tokens = Vec::new();
+    let masked = TOKEN.replace_all(text, |found: &regex::Captures| {
+        tokens.push(found[0].to_string());
+        format!("⟦V{}⟧", tokens.len())
+    });
+    (masked.into_owned(), tokens)
+}
+
+/// Why the translation of a value can't be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The response had no segment for the value
+    Missing,
+    /// Interpolation tokens were dropped, duplicated or invented
+    Tokens,
+}
+
+impl std::fmt::Display for ValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueError::Missing => write!(f, "Not in the response"),
+            ValueError::Tokens => write!(f, "Placeholders changed"),
+        }
+    }
+}
+
+/// Selected values, masked and numbered for one request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueBatch {
+    /// Index of each value in the document
+    indices: Vec<usize>,
+    /// Masked text and tokens of each value
+    masked: Vec<(String, Vec<String>)>,
+}
+
+impl ValueBatch {
+    /// Prepares the values of `document` at `indices` for translating.
+    pub fn new(document: &StructuredDocument, indices: &[usize]) -> Self {
+        let indices: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|&i| i < document.values.len())
+            .collect();
+        let masked = indices
+            .iter()
+            .map(|&i| mask_tokens(&document.values[i].text))
+            .collect();
+        ValueBatch { indices, masked }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Share of the values a partial response has started on.
+    pub fn progress(&self, response: &str) -> f32 {
+        if self.indices.is_empty() {
+            return 1.0;
+        }
+        let (segments, _) = list::parse_batch(response);
+        segments.len().min(self.indices.len()) as f32 / self.indices.len() as f32
+    }
+
+    /// The request body: `⟦n⟧ text` per value.
+    pub fn text(&self) -> String {
+        self.masked
+            .iter()
+            .enumerate()
+            .map(|(i, (masked, _))| format!("⟦{}⟧ {}", i + 1, masked))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Maps a response back to the values, by their index in the document.
+    pub fn results(&self, response: &str) -> Vec<(usize, Result<String, ValueError>)> {
+        let (segments, _) = list::parse_batch(response);
+        self.indices
+            .iter()
+            .zip(&self.masked)
+            .enumerate()
+            .map(|(i, (&index, (_, tokens)))| {
+                let result = match segments.get(&(i + 1)) {
+                    Some(translated) => restore_tokens(translated, tokens),
+                    None => Err(ValueError::Missing),
+                };
+                (index, result)
+            })
+            .collect()
+    }
+}
+
+/// Puts `tokens` back into `translated`, which must use each exactly once.
+fn restore_tokens(translated: &str, tokens: &[String]) -> Result<String, ValueError> {
+    let mut restored = translated.to_string();
+    for (i, token) in tokens.iter().enumerate() {
+        let placeholder = format!("⟦V{}⟧", i + 1);
+        if restored.matches(&placeholder).count() != 1 {
+            return Err(ValueError::Tokens);
+        }
+        restored = restored.replacen(&placeholder, token, 1);
+    }
+    if restored.contains("⟦V") {
+        return Err(ValueError::Tokens);
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(document: &StructuredDocument) -> Vec<(&str, &str)> {
+        document
+            .values()
+            .iter()
+            .map(|value| (value.path.as_str(), value.text.as_str()))
+            .collect()
+    }
+
+    const PRETTY_JSON: &str = r#"{
+  "title": "Welcome",
+  "count": 3,
+  "enabled": true,
+  "greeting": "Hello, {{name}}!",
+  "menu": {
+    "items": ["Open", "Save \"as\"", null],
+    "empty": {}
+  }
+}
+"#;
+
+    const COMPACT_JSON: &str = r#"{"b":"Second","a":"First","n":[1,2.5e3,"xé"]}"#;
+
+    const YAML: &str = "# Strings of the settings page
+en:
+  settings:
+    title: Settings   # shown in the header
+    save: \"Save %{count} items\"
+    cancel: 'Don''t save'
+    retries: 3
+    enabled: true
+  list:
+    - First step
+    - name: Second
+      hint: Press {0} to continue
+  notes: |
+    A block scalar
+    left as it is
+  after: Done
+";
+
+    #[test]
+    fn test_json_values() {
+        let document = StructuredDocument::parse(PRETTY_JSON).unwrap();
+        assert_eq!(document.format, Format::Json);
+        assert_eq!(
+            paths(&document),
+            vec![
+                ("title", "Welcome"),
+                ("greeting", "Hello, {{name}}!"),
+                ("menu.items[0]", "Open"),
+                ("menu.items[1]", "Save \"as\""),
+            ]
+        );
+
+        let document = StructuredDocument::parse(COMPACT_JSON).unwrap();
+        assert_eq!(
+            paths(&document),
+            vec![("b", "Second"), ("a", "First"), ("n[2]", "xé")]
+        );
+    }
+
+    #[test]
+    fn test_yaml_values() {
+        let document = StructuredDocument::parse(YAML).unwrap();
+        assert_eq!(document.format, Format::Yaml);
+        assert_eq!(
+            paths(&document),
+            vec![
+                ("en.settings.title", "Settings"),
+                ("en.settings.save", "Save %{count} items"),
+                ("en.settings.cancel", "Don't save"),
+                ("en.list[0]", "First step"),
+                ("en.list[1].name", "Second"),
+                ("en.list[1].hint", "Press {0} to continue"),
+                ("en.after", "Done"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nothing_selected_is_byte_identical() {
+        for source in [PRETTY_JSON, COMPACT_JSON, YAML, "[\"a\",\r\n \"b\"]"] {
+            let document = StructuredDocument::parse(source).unwrap();
+            assert_eq!(document.render(&HashMap::new()), source);
+        }
+    }
+
+    #[test]
+    fn test_render_keeps_layout_and_other_values() {
+        let document = StructuredDocument::parse(PRETTY_JSON).unwrap();
+        let translations = HashMap::from([
+            (0, "Willkommen".to_string()),
+            (3, "„Speichern unter“\n".to_string()),
+        ]);
+        let rendered = document.render(&translations);
+        assert_eq!(
+            rendered,
+            PRETTY_JSON
+                .replace("\"Welcome\"", "\"Willkommen\"")
+                .replace(r#""Save \"as\"""#, r#""„Speichern unter“\n""#)
+        );
+        let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(value["menu"]["items"][1], "„Speichern unter“\n");
+
+        let document = StructuredDocument::parse(YAML).unwrap();
+        let translations = HashMap::from([
+            (0, "Einstellungen".to_string()),
+            (2, "Nicht speichern".to_string()),
+            (3, "yes".to_string()),
+            (6, "Fertig: alles".to_string()),
+        ]);
+        let rendered = document.render(&translations);
+        assert!(rendered.contains("    title: Einstellungen   # shown in the header\n"));
+        assert!(rendered.contains("    cancel: 'Nicht speichern'\n"));
+        // Would read back as a boolean or a mapping unquoted
+        assert!(rendered.contains("    - \"yes\"\n"));
+        assert!(rendered.contains("  after: \"Fertig: alles\"\n"));
+        let reparsed = StructuredDocument::parse(&rendered).unwrap();
+        assert_eq!(reparsed.values()[6].text, "Fertig: alles");
+    }
+
+    #[test]
+    fn test_prose_is_not_a_document() {
+        assert_eq!(StructuredDocument::parse("Note: bring snacks"), None);
+        assert_eq!(StructuredDocument::parse("Subject: Hi\nDate: Monday"), None);
+        assert_eq!(
+            StructuredDocument::parse("Dear team:\nThe build is green. Ship it."),
+            None
+        );
+        assert_eq!(StructuredDocument::parse("\"just a string\""), None);
+        assert_eq!(StructuredDocument::parse("[1, 2, 3]"), None);
+    }
+
+    #[test]
+    fn test_tokens_are_masked_and_checked() {
+        let document = StructuredDocument::parse(
+            r#"{"a": "Hi {{name}}, you have %d new %s", "b": "100%% sure"}"#,
+        )
+        .unwrap();
+        let batch = ValueBatch::new(&document, &[0, 1]);
+        assert_eq!(
+            batch.text(),
+            "⟦1⟧ Hi ⟦V1⟧, you have ⟦V2⟧ new ⟦V3⟧\n⟦2⟧ 100⟦V1⟧ sure"
+        );
+
+        let results = batch.results("⟦1⟧ Hallo ⟦V1⟧, du hast ⟦V2⟧ neue ⟦V3⟧\n⟦2⟧ 100⟦V1⟧ sicher");
+        assert_eq!(
+            results,
+            vec![
+                (0, Ok("Hallo {{name}}, du hast %d neue %s".to_string())),
+                (1, Ok("100%% sicher".to_string())),
+            ]
+        );
+
+        let results = batch.results("⟦1⟧ Hallo ⟦V1⟧, du hast neue ⟦V3⟧ ⟦V3⟧");
+        assert_eq!(
+            results,
+            vec![(0, Err(ValueError::Tokens)), (1, Err(ValueError::Missing))]
+        );
+        assert_eq!(batch.progress("⟦1⟧ Hallo ⟦V1⟧"), 0.5);
+        assert_eq!(
+            restore_tokens("⟦V1⟧ ⟦V2⟧", &["{0}".to_string()]),
+            Err(ValueError::Tokens)
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Background jobs running right now, for the task manager.
+//!
+//! Every job spawned onto the runtime registers here and holds the returned
+//! [`TaskGuard`] for as long as it runs. Dropping the guard removes the
+//! entry, also when the job panics or its task is aborted, so the list never
+//! shows a job that is gone. A cancellable job is stopped through the
+//! callback it registered with; its entry stays, marked as cancelling, until
+//! the job has actually ended.
+
+use crate::lock_mutex;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Characters of a text shown in a task's label.
+const LABEL_CHARS: usize = 40;
+
+/// Identifies a registered task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+/// What a task does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    Translation,
+    Explanation,
+    /// Checking which parts of a translation the model is unsure of
+    Confidence,
+    /// Getting the audio of a text ready
+    Speech,
+    /// Translating several values or items at once
+    Batch,
+    /// Reading the text out of a file
+    Extraction,
+    /// Writing the history to a file
+    Export,
+    /// Running the command configured for finished translations
+    Hook,
+}
+
+impl TaskKind {
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskKind::Translation => "Translation",
+            TaskKind::Explanation => "Explanation",
+            TaskKind::Confidence => "Confidence check",
+            TaskKind::Speech => "Speech",
+            TaskKind::Batch => "Batch",
+            TaskKind::Extraction => "Extraction",
+            TaskKind::Export => "Export",
+            TaskKind::Hook => "Completion hook",
+        }
+    }
+}
+
+/// A running task, as listed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    pub kind: TaskKind,
+    pub label: String,
+    pub started: Instant,
+    /// Share done, from 0.0 to 1.0, if the task knows it
+    pub progress: Option<f32>,
+    pub cancellable: bool,
+    /// Cancelling was asked for, the task hasn't ended yet
+    pub cancelling: bool,
+}
+
+impl TaskInfo {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+type CancelFn = Arc<dyn Fn() + Send + Sync>;
+
+struct Entry {
+    info: TaskInfo,
+    cancel: Option<CancelFn>,
+}
+
+#[derive(Default)]
+struct Tasks {
+    next_id: u64,
+    /// By id, which is also the order of registration
+    entries: BTreeMap<TaskId, Entry>,
+}
+
+/// The running tasks, shared by everything that spawns one.
+#[derive(Clone, Default)]
+pub struct TaskRegistry {
+    tasks: Arc<Mutex<Tasks>>,
+}
+
+impl TaskRegistry {
+    /// Registers a task that can't be cancelled.
+    pub fn register(&self, kind: TaskKind, label: impl Into<String>) -> TaskGuard {
+        self.insert(kind, label.into(), None)
+    }
+
+    /// Registers a task that `cancel` asks to stop.
+    ///
+    /// `cancel` runs on the thread asking, so it should only signal the
+    /// task, not wait for it.
+    pub fn register_cancellable(
+        &self,
+        kind: TaskKind,
+        label: impl Into<String>,
+        cancel: impl Fn() + Send + Sync + 'static,
+    ) -> TaskGuard {
+        self.insert(kind, label.into(), Some(Arc::new(cancel)))
+    }
+
+    fn insert(&self, kind: TaskKind, label: String, cancel: Option<CancelFn>) -> TaskGuard {
+        let mut tasks = lock_mutex!(self.tasks);
+        let id = TaskId(tasks.next_id);
+        tasks.next_id += 1;
+        let info = TaskInfo {
+            id,
+            kind,
+            label,
+            started: Instant::now(),
+            progress: None,
+            cancellable: cancel.is_some(),
+            cancelling: false,
+        };
+        tracing::debug!(kind = kind.label(), label = %info.label, "Task started");
+        tasks.entries.insert(id, Entry { info, cancel });
+        TaskGuard {
+            tasks: self.tasks.clone(),
+            id,
+        }
+    }
+
+    /// The running tasks, oldest first.
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        lock_mutex!(self.tasks)
+            .entries
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        lock_mutex!(self.tasks).entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        lock_mutex!(self.tasks).entries.is_empty()
+    }
+
+    /// Asks a task to stop.
+    ///
+    /// # Returns
+    ///
+    /// Whether the task was still running and can be cancelled. A task
+    /// asked before is not asked again.
+    pub fn cancel(&self, id: TaskId) -> bool {
+        let cancel = {
+            let mut tasks = lock_mutex!(self.tasks);
+            let Some(entry) = tasks.entries.get_mut(&id) else {
+                return false;
+            };
+            let Some(cancel) = entry.cancel.clone() else {
+                return false;
+            };
+            if std::mem::replace(&mut entry.info.cancelling, true) {
+                return true;
+            }
+            tracing::info!(kind = entry.info.kind.label(), "Cancelling task");
+            cancel
+        };
+        // Outside the lock, so the callback may use the registry
+        cancel();
+        true
+    }
+}
+
+/// Keeps a task listed while it is alive.
+pub struct TaskGuard {
+    tasks: Arc<Mutex<Tasks>>,
+    id: TaskId,
+}
+
+impl TaskGuard {
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Reports the share of the task done, from 0.0 to 1.0.
+    pub fn set_progress(&self, fraction: f32) {
+        if let Some(entry) = lock_mutex!(self.tasks).entries.get_mut(&self.id) {
+            entry.info.progress = Some(fraction.clamp(0.0, 1.0));
+        }
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        // Released before the entry is dropped, along with what its
+        // cancel callback holds
+        let entry = lock_mutex!(self.tasks).entries.remove(&self.id);
+        if let Some(entry) = entry {
+            tracing::debug!(
+                kind = entry.info.kind.label(),
+                elapsed_ms = entry.info.elapsed().as_millis() as u64,
+                "Task ended"
+            );
+        }
+    }
+}
+
+/// The start of the first line of `text`, to tell tasks apart.
+pub fn label_text(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    let mut label: String = line.chars().take(LABEL_CHARS).collect();
+    if label.len() < line.len() {
+        label.push('…');
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counter() -> (Arc<AtomicUsize>, impl Fn() + Send + Sync + 'static) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let inner = count.clone();
+        (count, move || {
+            inner.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[test]
+    fn test_tasks_are_listed_until_they_end() {
+        let registry = TaskRegistry::default();
+        let first = registry.register(TaskKind::Translation, "Hallo");
+        let second = registry.register(TaskKind::Speech, "Hello");
+        second.set_progress(1.5);
+
+        let tasks = registry.tasks();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].kind, TaskKind::Translation);
+        assert_eq!(tasks[1].progress, Some(1.0));
+        assert!(!tasks[0].cancellable);
+
+        drop(first);
+        assert_eq!(registry.tasks()[0].id, second.id());
+        drop(second);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_asks_once_and_only_running_tasks() {
+        let registry = TaskRegistry::default();
+        let (asked, cancel) = counter();
+        let guard = registry.register_cancellable(TaskKind::Batch, "Values", cancel);
+        let plain = registry.register(TaskKind::Extraction, "file.pdf");
+
+        assert!(!registry.cancel(plain.id()));
+        assert!(registry.cancel(guard.id()));
+        assert!(registry.cancel(guard.id()));
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+        // Listed as cancelling until the task has ended
+        assert!(registry.tasks()[0].cancelling);
+
+        let id = guard.id();
+        drop(guard);
+        assert!(!registry.cancel(id));
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_cancel_callback_may_use_the_registry() {
+        let registry = TaskRegistry::default();
+        let inner = registry.clone();
+        let guard = registry.register_cancellable(TaskKind::Translation, "", move || {
+            assert_eq!(inner.len(), 1);
+            inner.register(TaskKind::Speech, "");
+        });
+        assert!(registry.cancel(guard.id()));
+    }
+
+    #[test]
+    fn test_panicked_task_leaves_no_entry() {
+        let registry = TaskRegistry::default();
+        let guard = registry.register(TaskKind::Extraction, "broken.pdf");
+        let result = std::thread::spawn(move || {
+            let _guard = guard;
+            panic!("extraction failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(registry.is_empty());
+        // Still usable afterwards
+        let _guard = registry.register(TaskKind::Speech, "");
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_aborted_task_leaves_no_entry() {
+        let registry = TaskRegistry::default();
+        let guard = registry.register(TaskKind::Batch, "");
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            std::future::pending::<()>().await;
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(registry.len(), 1);
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_register_cancel_and_end() {
+        let registry = TaskRegistry::default();
+        let (asked, cancel) = counter();
+        let cancel = Arc::new(cancel);
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let registry = registry.clone();
+                let cancel = cancel.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..200 {
+                        let cancel = cancel.clone();
+                        let guard =
+                            registry
+                                .register_cancellable(TaskKind::Translation, "", move || cancel());
+                        std::thread::yield_now();
+                        drop(guard);
+                    }
+                })
+            })
+            .collect();
+        let canceller = {
+            let registry = registry.clone();
+            std::thread::spawn(move || {
+                let mut cancelled = 0;
+                for _ in 0..2000 {
+                    for task in registry.tasks() {
+                        // The task may have ended since it was listed
+                        if registry.cancel(task.id) {
+                            cancelled += 1;
+                        }
+                    }
+                }
+                cancelled
+            })
+        };
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let cancelled = canceller.join().unwrap();
+
+        assert!(registry.is_empty());
+        // Each task was asked at most once, and only while it ran
+        assert!(asked.load(Ordering::SeqCst) <= 8 * 200);
+        assert!(asked.load(Ordering::SeqCst) <= cancelled);
+    }
+
+    #[test]
+    fn test_label_text() {
+        assert_eq!(label_text("  Hello\nworld"), "Hello");
+        assert_eq!(label_text(&"a".repeat(50)), format!("{}…", "a".repeat(40)));
+        assert_eq!(label_text(""), "");
+    }
+}
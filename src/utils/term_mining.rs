@@ -0,0 +1,760 @@
+//! Glossary suggestions mined from the translation history.
+//!
+//! A phrase the user keeps translating the same way is a glossary term
+//! waiting to be written down. [`mine`] counts the recurring phrases of the
+//! source texts of one target language: two to four words, or two to six
+//! characters of scripts written without spaces, never across punctuation.
+//! No model is asked what they were translated as. The translation of a
+//! phrase is the phrase that the translations of its entries have in common
+//! and that is rare in the others, found by comparing the substrings of the
+//! translations the way a reader would notice the correspondence. A pair
+//! that could as well have come together by chance, as in a short history,
+//! is not suggested.
+//!
+//! Memory stays bounded on long histories: only the newest
+//! [`MiningOptions::max_entries`] entries are read, each up to
+//! [`MAX_ENTRY_CHARS`], and the counts forget their rarest phrases whenever
+//! they grow past [`MiningOptions::max_phrases`]. [`TermMining`] runs the
+//! analysis on a background thread and reports its progress.
+
+use crate::utils::alignment;
+use crate::utils::history::{self, HistoryEntry};
+use crate::utils::query;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Characters of each source text and translation that are analysed.
+pub const MAX_ENTRY_CHARS: usize = 1_000;
+
+/// Entries analysed between progress reports.
+const BATCH_SIZE: usize = 128;
+
+/// Most source phrases whose translation is looked for, the most frequent
+/// first.
+const MAX_CANDIDATES: usize = 2_000;
+
+/// Most entries of a source phrase whose translations are compared.
+const MAX_SAMPLE: usize = 32;
+
+/// Share of the entries of a phrase whose translations must contain the
+/// suggested translation.
+const MIN_AGREEMENT: f64 = 0.8;
+
+/// Highest probability that a phrase and its suggested translation are in
+/// as many entries together by chance.
+const MAX_CHANCE: f64 = 1e-6;
+
+/// Most suggestions made.
+const MAX_SUGGESTIONS: usize = 100;
+
+/// Words a source phrase neither starts nor ends with.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "of", "to", "in", "on", "at", "by", "for", "with",
+    "from", "as", "is", "are", "was", "were", "be", "been", "it", "its", "this", "that", "these",
+    "those", "we", "you", "they", "he", "she", "not", "no", "so", "if", "then", "than", "can",
+    "will", "would", "should", "has", "have", "had", "do", "does", "did", "our", "your", "their",
+    "his", "her", "my", "all", "any", "each", "more", "most", "very",
+];
+
+/// Particles a source phrase without spaces neither starts nor ends with.
+const PARTICLES: &[char] = &[
+    '的', '了', '是', '在', '和', '与', '也', '都', '就', '把', '被', 'の', 'は', 'を', 'が', 'に',
+    'で', 'と', 'も', 'へ',
+];
+
+/// How the history is mined.
+#[derive(Debug, Clone)]
+pub struct MiningOptions {
+    /// Entries that must contain a phrase for it to be suggested
+    pub min_entries: usize,
+    /// Newest entries of the language that are analysed
+    pub max_entries: usize,
+    /// Distinct phrases counted before the rarest are dropped
+    pub max_phrases: usize,
+}
+
+impl Default for MiningOptions {
+    fn default() -> Self {
+        MiningOptions {
+            min_entries: 3,
+            max_entries: 5_000,
+            max_phrases: 200_000,
+        }
+    }
+}
+
+/// A phrase of the history and the translation it consistently got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// As first written in the history
+    pub source: String,
+    pub target: String,
+    /// Entries whose source text contains the phrase
+    pub entries: usize,
+    /// Share of those entries whose translation contains the target
+    pub agreement: f64,
+    /// Share of the translations containing the target that are of those
+    /// entries
+    pub specificity: f64,
+}
+
+impl Suggestion {
+    /// Ranks suggestions, the most convincing highest.
+    pub fn score(&self) -> f64 {
+        self.entries as f64 * self.agreement * self.specificity
+    }
+}
+
+/// Which phrases of a text are collected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// Two to four words, not starting or ending with a stopword
+    Source,
+    /// One to four words
+    Target,
+}
+
+/// Collects the distinct phrases of a text, lowercased.
+struct Phrases {
+    side: Side,
+    found: HashSet<String>,
+    /// Words since the last punctuation
+    words: Vec<String>,
+    word: String,
+    /// Characters of a script without spaces since anything else
+    run: Vec<char>,
+}
+
+impl Phrases {
+    fn of(text: &str, side: Side) -> HashSet<String> {
+        let mut phrases = Phrases {
+            side,
+            found: HashSet::new(),
+            words: Vec::new(),
+            word: String::new(),
+            run: Vec::new(),
+        };
+        for c in text.chars().take(MAX_ENTRY_CHARS) {
+            if alignment::is_spaceless(c) {
+                phrases.end_words();
+                phrases.run.push(c);
+                continue;
+            }
+            phrases.end_run();
+            if c.is_alphanumeric() {
+                phrases.word.extend(c.to_lowercase());
+            } else if matches!(c, '\'' | '’' | '-') && !phrases.word.is_empty() {
+                phrases.word.push(c);
+            } else if c.is_whitespace() {
+                phrases.end_word();
+            } else {
+                phrases.end_words();
+            }
+        }
+        phrases.end_words();
+        phrases.end_run();
+        phrases.found
+    }
+
+    fn end_word(&mut self) {
+        let word = std::mem::take(&mut self.word);
+        let word = word.trim_end_matches(['\'', '’', '-']);
+        if word.chars().any(char::is_alphabetic) {
+            self.words.push(word.to_string());
+        } else if !word.is_empty() {
+            // Numbers break phrases like punctuation
+            self.end_words();
+        }
+    }
+
+    fn end_words(&mut self) {
+        if !self.word.is_empty() {
+            self.end_word();
+        }
+        let words = std::mem::take(&mut self.words);
+        let shortest = if self.side == Side::Source { 2 } else { 1 };
+        for n in shortest..=4 {
+            for phrase in words.windows(n) {
+                let edge = |word: &String| word.chars().count() < 2 || STOPWORDS.contains(&&**word);
+                if self.side == Side::Source && (edge(&phrase[0]) || edge(&phrase[n - 1])) {
+                    continue;
+                }
+                self.found.insert(phrase.join(" "));
+            }
+        }
+    }
+
+    fn end_run(&mut self) {
+        let run = std::mem::take(&mut self.run);
+        for n in 2..=6 {
+            for phrase in run.windows(n) {
+                if self.side == Side::Source
+                    && (PARTICLES.contains(&phrase[0]) || PARTICLES.contains(&phrase[n - 1]))
+                {
+                    continue;
+                }
+                self.found.insert(phrase.iter().collect());
+            }
+        }
+    }
+}
+
+/// Counts `phrases` as found in one more entry.
+///
+/// Past `max_phrases` phrases every count is lowered by one, and the
+/// phrases down to zero are forgotten: the frequent ones are kept, with
+/// counts that are then too low. Returns whether that happened.
+fn count(counts: &mut HashMap<String, u32>, phrases: HashSet<String>, max_phrases: usize) -> bool {
+    for phrase in phrases {
+        *counts.entry(phrase).or_insert(0) += 1;
+    }
+    let mut lowered = false;
+    while counts.len() > max_phrases {
+        counts.retain(|_, count| {
+            *count -= 1;
+            *count > 0
+        });
+        lowered = true;
+    }
+    lowered
+}
+
+/// Natural logarithms of the factorials up to `n`.
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut table = Vec::with_capacity(n + 1);
+    let mut sum = 0.0;
+    table.push(sum);
+    for i in 1..=n {
+        sum += (i as f64).ln();
+        table.push(sum);
+    }
+    table
+}
+
+/// Probability that `together` or more of the `sample` entries drawn from
+/// `total` are among `marked` ones, the upper tail of the hypergeometric
+/// distribution; `ln_fact` must reach `total`.
+fn chance(ln_fact: &[f64], total: usize, marked: usize, sample: usize, together: usize) -> f64 {
+    let ln_choose = |n: usize, k: usize| ln_fact[n] - ln_fact[k] - ln_fact[n - k];
+    let ln_all = ln_choose(total, sample);
+    (together..=marked.min(sample))
+        .filter(|&k| sample - k <= total - marked)
+        .map(|k| (ln_choose(marked, k) + ln_choose(total - marked, sample - k) - ln_all).exp())
+        .sum()
+}
+
+/// `phrase` as written in `text`, if it can be found there.
+fn as_written(text: &str, phrase: &str) -> String {
+    query::find_all(text, phrase).first().map_or_else(
+        || phrase.to_string(),
+        |range| text[range.clone()].to_string(),
+    )
+}
+
+/// Suggests glossary terms for `language` from the history `entries`,
+/// oldest first, the most convincing first.
+///
+/// `progress` is told the share of the work done from time to time, and
+/// stops the analysis by returning `false`, in which case `None` is
+/// returned.
+pub fn mine(
+    entries: &[HistoryEntry],
+    language: &str,
+    options: &MiningOptions,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Option<Vec<Suggestion>> {
+    let min_entries = options.min_entries.max(2);
+    let max_phrases = options.max_phrases.max(1);
+    let entries: Vec<&HistoryEntry> = entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.target_language == language)
+        .take(options.max_entries)
+        .collect();
+    let total = entries.len().max(1) as f32;
+
+    // In how many entries each phrase occurs, too few once counts were lowered
+    let mut source_counts = HashMap::new();
+    let mut target_counts = HashMap::new();
+    let mut sources_lowered = false;
+    let mut targets_lowered = false;
+    for (i, entry) in entries.iter().enumerate() {
+        if i % BATCH_SIZE == 0 && !progress(0.5 * i as f32 / total) {
+            return None;
+        }
+        let phrases = Phrases::of(&entry.source_text, Side::Source);
+        sources_lowered |= count(&mut source_counts, phrases, max_phrases);
+        let phrases = Phrases::of(&entry.translation, Side::Target);
+        targets_lowered |= count(&mut target_counts, phrases, max_phrases);
+    }
+    // Counted again exactly below
+    let mut candidates: Vec<(String, u32)> = source_counts
+        .into_iter()
+        .filter(|(_, count)| sources_lowered || *count as usize >= min_entries)
+        .collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    candidates.truncate(MAX_CANDIDATES);
+    let candidate_index: HashMap<&str, usize> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, (phrase, _))| (phrase.as_str(), i))
+        .collect();
+    // Only phrases in enough translations can be the translation of one
+    let targets: Vec<(String, u32)> = target_counts
+        .into_iter()
+        .filter(|(_, count)| targets_lowered || *count as usize >= min_entries)
+        .collect();
+    let target_ids: HashMap<&str, u32> = targets
+        .iter()
+        .enumerate()
+        .map(|(id, (phrase, _))| (phrase.as_str(), id as u32))
+        .collect();
+
+    // The entries of each candidate, with the phrases of their translations
+    let mut occurrences = vec![0; candidates.len()];
+    let mut samples: Vec<Vec<usize>> = vec![Vec::new(); candidates.len()];
+    let mut translations: Vec<Vec<u32>> = vec![Vec::new(); entries.len()];
+    for (i, entry) in entries.iter().enumerate() {
+        if i % BATCH_SIZE == 0 && !progress(0.5 + 0.4 * i as f32 / total) {
+            return None;
+        }
+        let mut sampled = false;
+        for phrase in Phrases::of(&entry.source_text, Side::Source) {
+            if let Some(&candidate) = candidate_index.get(phrase.as_str()) {
+                occurrences[candidate] += 1;
+                if samples[candidate].len() < MAX_SAMPLE {
+                    samples[candidate].push(i);
+                    sampled = true;
+                }
+            }
+        }
+        if sampled {
+            translations[i] = Phrases::of(&entry.translation, Side::Target)
+                .iter()
+                .filter_map(|phrase| target_ids.get(phrase.as_str()).copied())
+                .collect();
+        }
+    }
+
+    let ln_fact = ln_factorials(entries.len());
+    let mut suggestions = Vec::new();
+    for (candidate, (source, _)) in candidates.iter().enumerate() {
+        if candidate % BATCH_SIZE == 0
+            && !progress(0.9 + 0.1 * candidate as f32 / candidates.len() as f32)
+        {
+            return None;
+        }
+        let sample = &samples[candidate];
+        let mut together: HashMap<u32, usize> = HashMap::new();
+        for &i in sample {
+            for &id in &translations[i] {
+                *together.entry(id).or_insert(0) += 1;
+            }
+        }
+        let entries_with_source = occurrences[candidate];
+        if entries_with_source < min_entries {
+            continue;
+        }
+        let best = together
+            .into_iter()
+            .filter_map(|(id, count)| {
+                let (target, in_translations) = &targets[id as usize];
+                let agreement = count as f64 / sample.len() as f64;
+                // Estimated from the sample when the phrase is in more entries
+                let agreeing = (agreement * entries_with_source as f64).round() as usize;
+                let in_translations = (*in_translations as usize).max(agreeing);
+                let specificity = agreeing as f64 / in_translations as f64;
+                let chance = chance(
+                    &ln_fact,
+                    entries.len(),
+                    in_translations,
+                    entries_with_source,
+                    agreeing,
+                );
+                (agreement >= MIN_AGREEMENT
+                    && agreeing >= min_entries
+                    && chance <= MAX_CHANCE
+                    && target != source)
+                    .then_some((target, agreement, specificity))
+            })
+            // The longest of equally good ones, "神经网络" rather than "神经网"
+            .max_by(|a, b| {
+                (a.1 * a.2)
+                    .total_cmp(&(b.1 * b.2))
+                    .then_with(|| a.0.chars().count().cmp(&b.0.chars().count()))
+                    .then_with(|| b.0.cmp(a.0))
+            });
+        if let Some((target, agreement, specificity)) = best {
+            let first = entries[sample[0]];
+            suggestions.push(Suggestion {
+                source: as_written(&first.source_text, source),
+                target: as_written(&first.translation, target),
+                entries: entries_with_source,
+                agreement,
+                specificity,
+            });
+        }
+    }
+
+    let mut suggestions = without_fragments(suggestions);
+    suggestions.sort_by(|a, b| {
+        b.score()
+            .total_cmp(&a.score())
+            .then_with(|| a.source.cmp(&b.source))
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+    progress(1.0);
+    Some(suggestions)
+}
+
+/// Drops the suggestions whose source only occurs as part of a longer
+/// suggested one, like "机器翻" of "机器翻译".
+fn without_fragments(mut suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+    suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.source.chars().count()));
+    let mut kept: Vec<(String, Suggestion)> = Vec::new();
+    for suggestion in suggestions {
+        let source = suggestion.source.to_lowercase();
+        let fragment = kept
+            .iter()
+            .any(|(longer, kept)| kept.entries >= suggestion.entries && longer.contains(&source));
+        if !fragment {
+            kept.push((source, suggestion));
+        }
+    }
+    kept.into_iter().map(|(_, suggestion)| suggestion).collect()
+}
+
+/// What a mining run is doing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiningState {
+    /// Share of the history analysed
+    Progress(f32),
+    Done(Vec<Suggestion>),
+    /// The history could not be read
+    Failed(String),
+}
+
+/// News of a mining run.
+#[derive(Debug)]
+pub struct MiningUpdate {
+    pub generation: u64,
+    pub state: MiningState,
+}
+
+/// Background mining of the translation log.
+pub struct TermMining {
+    generation: Arc<AtomicU64>,
+    tx: Sender<MiningUpdate>,
+    rx: Receiver<MiningUpdate>,
+}
+
+impl Default for TermMining {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        TermMining {
+            generation: Arc::new(AtomicU64::new(0)),
+            tx,
+            rx,
+        }
+    }
+}
+
+impl TermMining {
+    /// Starts mining the log at `log_path` for `language`, stopping earlier
+    /// runs.
+    ///
+    /// Returns the generation of the new run.
+    pub fn start(&self, log_path: PathBuf, language: String, options: MiningOptions) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let tx = self.tx.clone();
+        let spawned = std::thread::Builder::new()
+            .name("term-mining".to_string())
+            .spawn(move || {
+                let send = |state| tx.send(MiningUpdate { generation, state }).is_ok();
+                let entries = match history::load(&log_path) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        tracing::warn!("Failed to read the translation history: {}", e);
+                        send(MiningState::Failed(e.to_string()));
+                        return;
+                    }
+                };
+                let suggestions = mine(&entries, &language, &options, |done| {
+                    // A newer run was started, or this one cancelled
+                    current.load(Ordering::SeqCst) == generation
+                        && send(MiningState::Progress(done))
+                });
+                if let Some(suggestions) = suggestions {
+                    tracing::info!(
+                        language,
+                        suggestions = suggestions.len(),
+                        "Mined glossary suggestions from the history"
+                    );
+                    send(MiningState::Done(suggestions));
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("Failed to spawn term mining thread: {}", e);
+        }
+
+        generation
+    }
+
+    /// Stops the current run.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the updates that arrived since the last call.
+    pub fn poll(&self) -> Vec<MiningUpdate> {
+        self.rx.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::prompt::PromptContext;
+    use crate::utils::logger::Logger;
+    use std::time::{Duration, Instant};
+
+    /// Deterministic pseudo-random numbers for the synthetic history.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) as usize % n
+        }
+
+        fn words(&mut self, vocabulary: &[&str], n: usize) -> Vec<String> {
+            (0..n)
+                .map(|_| vocabulary[self.below(vocabulary.len())].to_string())
+                .collect()
+        }
+    }
+
+    const ENGLISH: &[&str] = &[
+        "river", "market", "yellow", "window", "garden", "quickly", "paper", "silver", "morning",
+        "travel", "pencil", "orange", "bridge", "summer", "coffee", "basket", "planet", "hammer",
+        "little", "forest", "button", "candle", "doctor", "engine", "finger", "guitar", "island",
+        "jacket", "kitten", "ladder",
+    ];
+    const FRENCH: &[&str] = &[
+        "fleuve", "marché", "jaune", "fenêtre", "jardin", "vite", "papier", "argent", "matin",
+        "voyage", "crayon", "orange", "pont", "été", "café", "panier", "planète", "marteau",
+        "petit", "forêt", "bouton", "bougie", "médecin", "moteur", "doigt", "guitare", "île",
+        "veste", "chaton", "échelle",
+    ];
+    const CHINESE: &str = "山水花鸟风云日月星江河湖海草木石竹松梅兰菊春夏秋冬东西南北";
+
+    fn entry(language: &str, source: String, translation: String) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: chrono::NaiveDate::from_ymd_opt(2024, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            source_language: "Auto-detected".to_string(),
+            target_language: language.to_string(),
+            source_text: source,
+            translation,
+            provenance: None,
+        }
+    }
+
+    /// A history of random words in which two English and one Chinese term
+    /// are always translated the same way.
+    fn planted_history() -> Vec<HistoryEntry> {
+        let mut rng = Lcg(7);
+        let mut entries = Vec::new();
+        let planted = [
+            ("Neural Network", "réseau neuronal"),
+            ("load balancer", "répartiteur de charge"),
+        ];
+        for i in 0..120 {
+            let mut source = rng.words(ENGLISH, 6);
+            let mut translation = rng.words(FRENCH, 6);
+            // Most entries have one of the terms, at a random place
+            if let Some((term, target)) = planted.get(i % 3) {
+                source.insert(rng.below(6), term.to_string());
+                translation.insert(rng.below(6), target.to_string());
+            }
+            entries.push(entry(
+                "French",
+                format!("{}.", source.join(" ")),
+                format!("{}.", translation.join(" ")),
+            ));
+        }
+
+        let chinese: Vec<char> = CHINESE.chars().collect();
+        for i in 0..40 {
+            let filler = |rng: &mut Lcg| -> String {
+                (0..8).map(|_| chinese[rng.below(chinese.len())]).collect()
+            };
+            let mut source = filler(&mut rng);
+            let mut translation = rng.words(ENGLISH, 6);
+            if i % 2 == 0 {
+                source.push_str("机器翻译");
+                translation.insert(rng.below(6), "machine translation".to_string());
+            }
+            source.push_str(&filler(&mut rng));
+            source.push('。');
+            entries.push(entry("English", source, translation.join(" ")));
+        }
+        entries
+    }
+
+    #[test]
+    fn test_planted_terms_are_suggested() {
+        let entries = planted_history();
+        let options = MiningOptions::default();
+        let french = mine(&entries, "French", &options, |_| true).unwrap();
+        let pairs: Vec<(&str, &str)> = french
+            .iter()
+            .map(|s| (s.source.as_str(), s.target.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("Neural Network", "réseau neuronal"),
+                ("load balancer", "répartiteur de charge"),
+            ]
+        );
+        assert_eq!(french[0].entries, 40);
+        assert_eq!(french[0].agreement, 1.0);
+
+        let english = mine(&entries, "English", &options, |_| true).unwrap();
+        assert_eq!(english.len(), 1);
+        assert_eq!(english[0].source, "机器翻译");
+        assert_eq!(english[0].target, "machine translation");
+        assert_eq!(english[0].entries, 20);
+    }
+
+    #[test]
+    fn test_inconsistent_translations_are_not_suggested() {
+        let mut rng = Lcg(11);
+        let entries: Vec<HistoryEntry> = (0..30)
+            .map(|i| {
+                let target = ["réseau neuronal", "réseau de neurones", "neural net"][i % 3];
+                let mut translation = rng.words(FRENCH, 5);
+                translation.push(target.to_string());
+                let mut source = rng.words(ENGLISH, 5);
+                source.push("neural network".to_string());
+                entry("French", source.join(" "), translation.join(" "))
+            })
+            .collect();
+        let suggestions = mine(&entries, "French", &MiningOptions::default(), |_| true).unwrap();
+        assert!(suggestions.is_empty(), "{:?}", suggestions);
+    }
+
+    #[test]
+    fn test_limits_bound_the_analysis() {
+        let entries = planted_history();
+
+        // The newest 30 French entries only, of which 10 have each term
+        let options = MiningOptions {
+            min_entries: 12,
+            max_entries: 30,
+            ..MiningOptions::default()
+        };
+        assert!(
+            mine(&entries, "French", &options, |_| true)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Few phrases kept in memory, but the frequent ones survive
+        let options = MiningOptions {
+            max_phrases: 300,
+            ..MiningOptions::default()
+        };
+        let suggestions = mine(&entries, "French", &options, |_| true).unwrap();
+        assert_eq!(suggestions.len(), 2);
+
+        // Stopped early
+        let mut reports = Vec::new();
+        let stopped = mine(&entries, "French", &MiningOptions::default(), |done| {
+            reports.push(done);
+            reports.len() < 2
+        });
+        assert!(stopped.is_none());
+        assert_eq!(reports, vec![0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_chance() {
+        let ln_fact = ln_factorials(40);
+        // 4 of 5 entries among 9 of 40, quite likely in a short history
+        let likely = chance(&ln_fact, 40, 9, 5, 4);
+        assert!((likely - 4032.0 / 658_008.0).abs() < 1e-9);
+        assert!(chance(&ln_fact, 40, 20, 20, 20) < 1e-10);
+        assert!((chance(&ln_fact, 40, 9, 5, 0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_phrases() {
+        let phrases = Phrases::of(
+            "The state-of-the-art model, in 2024: 机器翻译的",
+            Side::Source,
+        );
+        assert!(phrases.contains("state-of-the-art model"));
+        assert!(!phrases.contains("the state-of-the-art"));
+        // Not across punctuation or numbers
+        assert!(!phrases.contains("model in"));
+        assert!(phrases.contains("机器翻译"));
+        assert!(!phrases.contains("翻译的"));
+
+        let phrases = Phrases::of("Le modèle, 2024", Side::Target);
+        assert!(phrases.contains("le"));
+        assert!(phrases.contains("le modèle"));
+        assert_eq!(phrases.len(), 3);
+    }
+
+    #[test]
+    fn test_background_mining_reports_progress() {
+        let dir = std::env::temp_dir().join(format!("ai-translate-mining-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("translations.log");
+        let _ = std::fs::remove_file(&path);
+        let logger = Logger::new(path.to_str().unwrap()).unwrap();
+        for entry in planted_history() {
+            logger.log(
+                &entry.source_language,
+                &entry.target_language,
+                &entry.source_text,
+                &entry.translation,
+                "",
+                &PromptContext::default(),
+                None,
+                None,
+            );
+        }
+        logger.flush();
+
+        let mining = TermMining::default();
+        let generation = mining.start(path, "French".to_string(), MiningOptions::default());
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let mut states = Vec::new();
+        while !matches!(states.last(), Some(MiningState::Done(_))) {
+            assert!(Instant::now() < deadline, "mining never finished");
+            for update in mining.poll() {
+                assert_eq!(update.generation, generation);
+                states.push(update.state);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(states[0], MiningState::Progress(0.0));
+        let Some(MiningState::Done(suggestions)) = states.last() else {
+            unreachable!();
+        };
+        assert_eq!(suggestions[0].target, "réseau neuronal");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
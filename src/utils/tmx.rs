@@ -0,0 +1,337 @@
+//! Export of the translation history as a TMX 1.4 translation memory.
+//!
+//! Every history entry becomes one translation unit with the source text
+//! and the translation, tagged with BCP-47 codes from
+//! [`langcodes`](crate::utils::langcodes). The log only records the source
+//! language when it was picked by hand, so for the others it is guessed
+//! from the text. Characters XML 1.0 can't carry, such as the control
+//! characters a model sometimes emits, are dropped, so the file always
+//! parses. Where the log recorded what a translation was made with, its
+//! unit carries that as `x-` properties.
+
+use crate::utils::history::HistoryEntry;
+use crate::utils::langcodes::{self, UNDETERMINED};
+use crate::utils::langid;
+use crate::utils::version::VERSION;
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use std::fmt::Write;
+
+/// Name of the app in the header of exported files.
+const CREATION_TOOL: &str = "AI Translate";
+
+/// An exported translation memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TmxExport {
+    pub xml: String,
+    /// Translation units written
+    pub units: usize,
+    /// Entries left out for their unknown source language
+    pub skipped: usize,
+}
+
+/// Builds a TMX document of `entries`.
+///
+/// Entries whose source language is unknown are tagged `und`, or left out
+/// if `skip_unknown_source`.
+pub fn export(entries: &[HistoryEntry], skip_unknown_source: bool) -> TmxExport {
+    let mut units = String::new();
+    let mut written = 0;
+    let mut skipped = 0;
+    let mut source_codes = Vec::new();
+    for entry in entries {
+        let source = source_code(entry);
+        if source.is_none() && skip_unknown_source {
+            skipped += 1;
+            continue;
+        }
+        let source = source.unwrap_or(UNDETERMINED);
+        if !source_codes.contains(&source) {
+            source_codes.push(source);
+        }
+        let target = langcodes::bcp47_or_und(&entry.target_language);
+        let _ = write!(
+            units,
+            "    <tu creationdate=\"{}\">\n{}{}{}    </tu>\n",
+            creation_date(entry.timestamp),
+            props(entry),
+            tuv(source, &entry.source_text),
+            tuv(target, &entry.translation),
+        );
+        written += 1;
+    }
+
+    // A single source language is named, mixed ones are allowed by "*all*"
+    let srclang = match source_codes.as_slice() {
+        [code] => code,
+        _ => "*all*",
+    };
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE tmx SYSTEM \"tmx14.dtd\">\n\
+         <tmx version=\"1.4\">\n  \
+         <header creationtool=\"{}\" creationtoolversion=\"{}\" datatype=\"plaintext\" \
+         segtype=\"paragraph\" adminlang=\"en\" srclang=\"{}\" o-tmf=\"ai-translate\"/>\n  \
+         <body>\n{}  </body>\n\
+         </tmx>\n",
+        CREATION_TOOL,
+        escape(VERSION),
+        srclang,
+        units,
+    );
+    TmxExport {
+        xml,
+        units: written,
+        skipped,
+    }
+}
+
+/// The tag of the language `entry` was translated from, `None` if unknown.
+fn source_code(entry: &HistoryEntry) -> Option<&'static str> {
+    langcodes::bcp47(&entry.source_language).or_else(|| {
+        langid::detect(&entry.source_text)
+            .filter(langid::Guess::is_confident)
+            .and_then(|guess| langcodes::bcp47(guess.language))
+    })
+}
+
+/// `<prop>` elements of the provenance of `entry`, if it was recorded.
+fn props(entry: &HistoryEntry) -> String {
+    let Some(provenance) = &entry.provenance else {
+        return String::new();
+    };
+    [
+        ("x-model", &provenance.model),
+        ("x-template", &provenance.template),
+        ("x-glossary", &provenance.glossary_hash),
+        ("x-app-version", &provenance.app_version),
+        ("x-options", &provenance.options),
+    ]
+    .iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(kind, value)| format!("      <prop type=\"{}\">{}</prop>\n", kind, escape(value)))
+    .collect()
+}
+
+/// A `<tuv>` element of `text` in `language`.
+fn tuv(language: &str, text: &str) -> String {
+    format!(
+        "      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n",
+        language,
+        escape(text)
+    )
+}
+
+/// Logged local time in the UTC format of TMX, such as `20240601T120000Z`.
+fn creation_date(timestamp: NaiveDateTime) -> String {
+    // A time skipped by a daylight saving change is taken as UTC
+    let utc = Local
+        .from_local_datetime(&timestamp)
+        .earliest()
+        .map_or(timestamp, |local| local.with_timezone(&Utc).naive_utc());
+    utc.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Whether XML 1.0 allows `c` in a document.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}')
+        || c >= '\u{10000}'
+}
+
+/// Escapes `text` for element content and attribute values, dropping the
+/// characters XML can't carry.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars().filter(|c| is_xml_char(*c)) {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Parsers turn a literal carriage return into a line feed
+            '\r' => escaped.push_str("&#13;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::provenance::Provenance;
+    use xml::reader::{EventReader, XmlEvent};
+
+    fn entry(source_language: &str, target: &str, source: &str, translation: &str) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: chrono::NaiveDate::from_ymd_opt(2024, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            source_language: source_language.to_string(),
+            target_language: target.to_string(),
+            source_text: source.to_string(),
+            translation: translation.to_string(),
+            provenance: None,
+        }
+    }
+
+    /// A translation unit as read back: language and text of each variant.
+    type Unit = Vec<(String, String)>;
+
+    /// Parses `xml` strictly, returning the srclang of the header and the units.
+    fn parse(xml: &str) -> (String, Vec<Unit>) {
+        let mut srclang = String::new();
+        let mut units = Vec::new();
+        let mut language = String::new();
+        let mut text = None;
+        for event in EventReader::from_str(xml) {
+            match event.expect("well-formed TMX") {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    let attribute = |local: &str| {
+                        attributes
+                            .iter()
+                            .find(|a| a.name.local_name == local)
+                            .map(|a| a.value.clone())
+                            .unwrap_or_default()
+                    };
+                    match name.local_name.as_str() {
+                        "header" => srclang = attribute("srclang"),
+                        "tu" => units.push(Vec::new()),
+                        "tuv" => language = attribute("lang"),
+                        "seg" => text = Some(String::new()),
+                        _ => {}
+                    }
+                }
+                XmlEvent::Characters(chars) | XmlEvent::Whitespace(chars) => {
+                    if let Some(text) = &mut text {
+                        text.push_str(&chars);
+                    }
+                }
+                XmlEvent::EndElement { name } if name.local_name == "seg" => {
+                    let unit: &mut Unit = units.last_mut().unwrap();
+                    unit.push((language.clone(), text.take().unwrap()));
+                }
+                _ => {}
+            }
+        }
+        (srclang, units)
+    }
+
+    #[test]
+    fn test_export_round_trips() {
+        let entries = [
+            entry("Auto-detected", "Deutsch", "Hello", "Hallo"),
+            entry("English", "中文", "Tom & Jerry <3", "汤姆和杰瑞 \"<3\""),
+            entry(
+                "Auto-detected",
+                "日本語",
+                "Good morning.\nSee you.",
+                "おはよう。\nまたね。",
+            ),
+        ];
+        let export = export(&entries, false);
+        assert_eq!(export.units, 3);
+        assert_eq!(export.skipped, 0);
+
+        let (srclang, units) = parse(&export.xml);
+        // "Hello" alone is too short to tell its language
+        assert_eq!(srclang, "*all*");
+        assert_eq!(
+            units[0],
+            vec![
+                ("und".to_string(), "Hello".to_string()),
+                ("de".to_string(), "Hallo".to_string()),
+            ]
+        );
+        assert_eq!(units[1][0].1, "Tom & Jerry <3");
+        assert_eq!(
+            units[1][1],
+            ("zh".to_string(), "汤姆和杰瑞 \"<3\"".to_string())
+        );
+        assert_eq!(units[2][1].1, "おはよう。\nまたね。");
+    }
+
+    #[test]
+    fn test_provenance_properties() {
+        let mut recorded = entry("English", "Deutsch", "Hello world", "Hallo Welt");
+        recorded.provenance = Some(Provenance {
+            model: "deepseek-chat".to_string(),
+            template: "Standard".to_string(),
+            options: "thinking=disabled, domain=R&D".to_string(),
+            ..Provenance::default()
+        });
+        let entries = [recorded, entry("English", "Deutsch", "Bye", "Tschüss")];
+        let xml = export(&entries, false).xml;
+        // Still parses, with the same units
+        let (_, units) = parse(&xml);
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0][1].1, "Hallo Welt");
+
+        assert!(xml.contains("<prop type=\"x-model\">deepseek-chat</prop>"));
+        assert!(xml.contains("<prop type=\"x-options\">thinking=disabled, domain=R&amp;D</prop>"));
+        // Empty fields are left out
+        assert!(!xml.contains("x-glossary"));
+        assert_eq!(xml.matches("<prop ").count(), 3);
+    }
+
+    #[test]
+    fn test_control_characters_are_dropped() {
+        let entries = [entry(
+            "English",
+            "Français",
+            "Bell\u{7} and\r\nnull\u{0}",
+            "Cloche\u{1b}[0m \u{FFFE}et\u{1F600}",
+        )];
+        let (_, units) = parse(&export(&entries, false).xml);
+        assert_eq!(units[0][0].1, "Bell and\r\nnull");
+        assert_eq!(units[0][1].1, "Cloche[0m et\u{1F600}");
+    }
+
+    #[test]
+    fn test_unknown_source_languages() {
+        let entries = [
+            entry("Auto-detected", "English", "Danke", "Thanks"),
+            entry(
+                "Auto-detected",
+                "English",
+                "Das Wetter ist heute schön und wir gehen in den Park.",
+                "The weather is nice today and we are going to the park.",
+            ),
+        ];
+        let kept = export(&entries, false);
+        let (srclang, units) = parse(&kept.xml);
+        assert_eq!(srclang, "*all*");
+        assert_eq!(units[0][0].0, "und");
+        // Guessed from the text
+        assert_eq!(units[1][0].0, "de");
+
+        let skipped = export(&entries, true);
+        assert_eq!((skipped.units, skipped.skipped), (1, 1));
+        let (srclang, units) = parse(&skipped.xml);
+        assert_eq!(srclang, "de");
+        assert_eq!(units.len(), 1);
+    }
+
+    #[test]
+    fn test_empty_history_is_valid() {
+        let export = export(&[], false);
+        assert_eq!(export.units, 0);
+        let (srclang, units) = parse(&export.xml);
+        assert_eq!(srclang, "*all*");
+        assert!(units.is_empty());
+    }
+
+    #[test]
+    fn test_creation_date_format() {
+        let entries = [entry("English", "Deutsch", "Hello world", "Hallo Welt")];
+        let xml = export(&entries, false).xml;
+        let start = xml.find("<tu creationdate=\"").unwrap() + 18;
+        let date = &xml[start..start + 16];
+        assert!(date.ends_with('Z'));
+        assert_eq!(&date[8..9], "T");
+        assert!(NaiveDateTime::parse_from_str(date, "%Y%m%dT%H%M%SZ").is_ok());
+    }
+}
@@ -0,0 +1,174 @@
+//! Steady reveal of streamed text.
+//!
+//! Chunks arrive in bursts of several words. [`Typewriter`] hands out the
+//! received text at a fixed rate of characters per second instead, measured
+//! in time rather than frames so it types at the same speed on any display.
+//! When it falls too far behind the stream it catches up at once, so the
+//! view never lags more than a moment.
+
+use std::time::Instant;
+
+/// Seconds of text the reveal may be behind before it catches up at once.
+pub const MAX_LAG_SECS: f64 = 2.0;
+
+/// Longest time counted between two frames, so a stalled frame doesn't
+/// release a burst.
+const MAX_STEP_SECS: f64 = 0.1;
+
+/// Reveals text at a steady rate.
+#[derive(Debug, Clone)]
+pub struct Typewriter {
+    chars_per_sec: f64,
+    /// Byte length of the revealed prefix
+    revealed: usize,
+    /// Characters owed by the time passed, not revealed yet
+    budget: f64,
+    last: Option<Instant>,
+}
+
+impl Typewriter {
+    /// Creates a typewriter revealing `chars_per_sec` characters a second.
+    pub fn new(chars_per_sec: u32) -> Self {
+        Typewriter {
+            chars_per_sec: chars_per_sec.max(1) as f64,
+            revealed: 0,
+            budget: 0.0,
+            last: None,
+        }
+    }
+
+    /// Moves the reveal of `text` forward to `now`.
+    ///
+    /// `text` is everything received so far; if it was replaced by a shorter
+    /// one the reveal is cut back to fit.
+    ///
+    /// # Returns
+    ///
+    /// Byte length of the prefix of `text` to show
+    pub fn advance(&mut self, text: &str, now: Instant) -> usize {
+        let elapsed = self.last.map_or(0.0, |last| {
+            now.saturating_duration_since(last)
+                .as_secs_f64()
+                .min(MAX_STEP_SECS)
+        });
+        self.last = Some(now);
+
+        if self.revealed > text.len() {
+            self.revealed = text.len();
+        }
+        while !text.is_char_boundary(self.revealed) {
+            self.revealed -= 1;
+        }
+
+        let pending = &text[self.revealed..];
+        if pending.is_empty() {
+            // Nothing to type, so idle time doesn't turn into a burst later
+            self.budget = 0.0;
+            return self.revealed;
+        }
+
+        let max_lag = (self.chars_per_sec * MAX_LAG_SECS) as usize;
+        if pending.chars().nth(max_lag).is_some() {
+            self.revealed = text.len();
+            self.budget = 0.0;
+            return self.revealed;
+        }
+
+        self.budget += elapsed * self.chars_per_sec;
+        // Leeway for the rounding of the time steps
+        let count = (self.budget + 1e-6).floor() as usize;
+        if count > 0 {
+            self.budget = (self.budget - count as f64).max(0.0);
+            self.revealed = pending
+                .char_indices()
+                .nth(count)
+                .map_or(text.len(), |(i, _)| self.revealed + i);
+        }
+        self.revealed
+    }
+
+    /// Byte length of the prefix revealed by the last [`advance`](Self::advance).
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    /// Reveals all of `text`, when it is complete.
+    pub fn finish(&mut self, text: &str) {
+        self.revealed = text.len();
+        self.budget = 0.0;
+        self.last = None;
+    }
+
+    /// Starts over for new text.
+    pub fn reset(&mut self) {
+        self.revealed = 0;
+        self.budget = 0.0;
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Advances `frames` frames spread evenly over `secs` seconds.
+    fn run(typewriter: &mut Typewriter, text: &str, start: Instant, secs: f64, frames: u32) {
+        typewriter.advance(text, start);
+        for frame in 1..=frames {
+            let at = start + Duration::from_secs_f64(secs * frame as f64 / frames as f64);
+            typewriter.advance(text, at);
+        }
+    }
+
+    #[test]
+    fn test_rate_is_independent_of_frame_rate() {
+        let text = "a".repeat(80);
+        let start = Instant::now();
+        let mut slow = Typewriter::new(50);
+        let mut fast = Typewriter::new(50);
+        run(&mut slow, &text, start, 1.0, 30);
+        run(&mut fast, &text, start, 1.0, 144);
+        assert_eq!(slow.revealed(), 50);
+        assert_eq!(fast.revealed(), 50);
+    }
+
+    #[test]
+    fn test_large_backlog_is_revealed_at_once() {
+        let mut typewriter = Typewriter::new(10);
+        let start = Instant::now();
+        typewriter.advance("short", start);
+        let long = "x".repeat(100);
+        assert_eq!(typewriter.advance(&long, start), long.len());
+    }
+
+    #[test]
+    fn test_stops_on_char_boundaries() {
+        let text = "翻訳されたテキスト";
+        let mut typewriter = Typewriter::new(10);
+        let start = Instant::now();
+        run(&mut typewriter, text, start, 0.3, 3);
+        assert_eq!(&text[..typewriter.revealed()], "翻訳さ");
+
+        // Replaced by text cutting the revealed prefix mid-character
+        let revealed = typewriter.advance("翻x", start + Duration::from_secs_f64(0.3));
+        assert_eq!(revealed, "翻x".len());
+        let revealed = typewriter.advance("ab", start + Duration::from_secs_f64(0.3));
+        assert_eq!(revealed, 2);
+    }
+
+    #[test]
+    fn test_idle_time_does_not_burst() {
+        let mut typewriter = Typewriter::new(10);
+        let start = Instant::now();
+        typewriter.advance("", start);
+        typewriter.advance("", start + Duration::from_secs(5));
+        let revealed = typewriter.advance("hello world", start + Duration::from_secs(5));
+        assert_eq!(revealed, 0);
+        let revealed = typewriter.advance(
+            "hello world",
+            start + Duration::from_secs(5) + Duration::from_millis(200),
+        );
+        assert_eq!(revealed, 1);
+    }
+}
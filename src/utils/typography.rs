@@ -0,0 +1,581 @@
+//! Punctuation and quote conventions of the target language.
+//!
+//! Models are inconsistent about typography: half-width commas in Chinese,
+//! missing spaces before French question marks, English quotes in German.
+//! Once a translation is complete, [`normalize`] rewrites its punctuation
+//! by the rules of the target language in [`RULES`]. Streamed chunks are
+//! left alone, since pairing quotes needs the whole text. Code, links and
+//! email addresses are never touched.
+
+use crate::utils::links;
+use crate::utils::script::{Script, script_of};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// No-break space, before a French colon.
+const NBSP: char = '\u{00A0}';
+/// Narrow no-break space, before French `;`, `!` and `?` and inside « ».
+const NNBSP: char = '\u{202F}';
+
+/// Quote marks that open or close a quotation, in any style.
+const DOUBLE_QUOTES: [char; 8] = ['"', '“', '”', '„', '«', '»', '「', '」'];
+
+/// How quotations are marked in a normalized translation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QuoteStyle {
+    /// Left as the model wrote them
+    Keep,
+    /// `"…"` and `'`
+    Straight,
+    /// The marks of the target language, such as “…”, „…“ or «…»
+    #[default]
+    Typographic,
+    /// `「…」`
+    Corner,
+}
+
+impl QuoteStyle {
+    /// All styles, in display order.
+    pub const ALL: [QuoteStyle; 4] = [
+        QuoteStyle::Keep,
+        QuoteStyle::Straight,
+        QuoteStyle::Typographic,
+        QuoteStyle::Corner,
+    ];
+
+    /// Human-readable label for the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            QuoteStyle::Keep => "Keep as written",
+            QuoteStyle::Straight => "Straight \"…\"",
+            QuoteStyle::Typographic => "Typographic “…”",
+            QuoteStyle::Corner => "Corner brackets 「…」",
+        }
+    }
+}
+
+/// Half-width punctuation and its full-width form in Chinese.
+const CHINESE_PUNCTUATION: &[(char, char)] = &[
+    (',', '，'),
+    ('.', '。'),
+    ('?', '？'),
+    ('!', '！'),
+    (':', '：'),
+    (';', '；'),
+    ('(', '（'),
+    (')', '）'),
+];
+
+/// Half-width punctuation and its full-width form in Japanese.
+const JAPANESE_PUNCTUATION: &[(char, char)] = &[
+    (',', '、'),
+    ('.', '。'),
+    ('?', '？'),
+    ('!', '！'),
+    (':', '：'),
+    (';', '；'),
+    ('(', '（'),
+    (')', '）'),
+];
+
+/// Typographic conventions of a target language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LanguageRules {
+    pub language: &'static str,
+    /// Opening and closing typographic quote marks
+    pub quotes: (char, char),
+    /// Half-width punctuation to make full-width next to CJK characters
+    pub full_width: &'static [(char, char)],
+    /// No-break spaces before `:;!?` and inside « »
+    pub french_spacing: bool,
+}
+
+/// Rules of the built-in target languages.
+pub const RULES: [LanguageRules; 12] = [
+    LanguageRules {
+        language: "English",
+        quotes: ('“', '”'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "中文",
+        quotes: ('“', '”'),
+        full_width: CHINESE_PUNCTUATION,
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "日本語",
+        quotes: ('「', '」'),
+        full_width: JAPANESE_PUNCTUATION,
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "한국어",
+        quotes: ('“', '”'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "Français",
+        quotes: ('«', '»'),
+        full_width: &[],
+        french_spacing: true,
+    },
+    LanguageRules {
+        language: "Deutsch",
+        quotes: ('„', '“'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "Español",
+        quotes: ('«', '»'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "Português",
+        quotes: ('“', '”'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "Русский",
+        quotes: ('«', '»'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "Italiano",
+        quotes: ('«', '»'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "العربية",
+        quotes: ('«', '»'),
+        full_width: &[],
+        french_spacing: false,
+    },
+    LanguageRules {
+        language: "עברית",
+        quotes: ('“', '”'),
+        full_width: &[],
+        french_spacing: false,
+    },
+];
+
+/// Rules for a language without its own: typographic English quotes only.
+const DEFAULT_RULES: LanguageRules = LanguageRules {
+    language: "",
+    quotes: ('“', '”'),
+    full_width: &[],
+    french_spacing: false,
+};
+
+/// The rules of `language`.
+pub fn rules_for(language: &str) -> &'static LanguageRules {
+    RULES
+        .iter()
+        .find(|rules| rules.language == language)
+        .unwrap_or(&DEFAULT_RULES)
+}
+
+/// A normalized text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    pub text: String,
+    /// Characters replaced, inserted or removed
+    pub adjusted: usize,
+}
+
+/// Rewrites the punctuation of `text` by the rules of `language`.
+pub fn normalize(text: &str, language: &str, quotes: QuoteStyle) -> Normalized {
+    let protected = protected_ranges(text);
+    let chars = text
+        .char_indices()
+        .map(|(i, c)| Char {
+            c,
+            protected: protected.iter().any(|range| range.contains(&i)),
+        })
+        .collect();
+    let mut normalizer = Normalizer {
+        rules: rules_for(language),
+        quotes,
+        adjusted: 0,
+    };
+    let mut chars = normalizer.quotes_pass(chars);
+    if !normalizer.rules.full_width.is_empty() {
+        chars = normalizer.full_width_pass(chars);
+    }
+    if normalizer.rules.french_spacing {
+        chars = normalizer.french_spacing_pass(chars);
+    }
+    Normalized {
+        text: chars.into_iter().map(|ch| ch.c).collect(),
+        adjusted: normalizer.adjusted,
+    }
+}
+
+/// Byte ranges of code, links and email addresses.
+fn protected_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    let mut fence_start = None;
+    for line in text.split_inclusive('\n') {
+        let is_fence = line.trim_start().starts_with("```");
+        match fence_start {
+            Some(start) if is_fence => {
+                ranges.push(start..offset + line.len());
+                fence_start = None;
+            }
+            Some(_) => {}
+            None if is_fence => fence_start = Some(offset),
+            None => {
+                let mut rest = 0;
+                while let Some(open) = line[rest..].find('`').map(|i| rest + i) {
+                    let Some(close) = line[open + 1..].find('`').map(|i| open + 1 + i) else {
+                        break;
+                    };
+                    ranges.push(offset + open..offset + close + 1);
+                    rest = close + 1;
+                }
+            }
+        }
+        offset += line.len();
+    }
+    if let Some(start) = fence_start {
+        ranges.push(start..text.len());
+    }
+    ranges.extend(links::find_links(text).into_iter().map(|link| link.range));
+    ranges
+}
+
+fn is_cjk(c: char) -> bool {
+    script_of(c) == Some(Script::Cjk)
+}
+
+/// Whether `c` continues a word, for telling apostrophes from quotes.
+fn is_letter(c: Option<char>) -> bool {
+    c.is_some_and(char::is_alphanumeric)
+}
+
+/// Marks that may directly follow the end of a sentence.
+fn is_closer(c: char) -> bool {
+    matches!(c, ')' | '”' | '’' | '」' | '』' | '"' | '\'')
+}
+
+/// Edits to turn the spaces `found` into the single `space`.
+fn respace_cost(found: &[char], space: char) -> usize {
+    match found {
+        [] => 1,
+        [first, rest @ ..] => usize::from(*first != space) + rest.len(),
+    }
+}
+
+/// A character of the text, and whether it is in code or a link.
+#[derive(Debug, Clone, Copy)]
+struct Char {
+    c: char,
+    protected: bool,
+}
+
+impl Char {
+    fn new(c: char) -> Self {
+        Char {
+            c,
+            protected: false,
+        }
+    }
+}
+
+struct Normalizer {
+    rules: &'static LanguageRules,
+    quotes: QuoteStyle,
+    adjusted: usize,
+}
+
+impl Normalizer {
+    /// Replaces `from` by `to`, counting the change.
+    fn replace(&mut self, from: char, to: char) -> char {
+        if from != to {
+            self.adjusted += 1;
+        }
+        to
+    }
+
+    /// Gives quotation marks and apostrophes the chosen style.
+    fn quotes_pass(&mut self, mut chars: Vec<Char>) -> Vec<Char> {
+        let (open, close) = match self.quotes {
+            QuoteStyle::Keep => return chars,
+            QuoteStyle::Straight => ('"', '"'),
+            QuoteStyle::Corner => ('「', '」'),
+            QuoteStyle::Typographic => self.rules.quotes,
+        };
+        // Inside a quotation, so the next ambiguous mark closes it
+        let mut in_quote = false;
+        for i in 0..chars.len() {
+            let Char { c, protected } = chars[i];
+            if c == '\n' {
+                // An unpaired mark doesn't flip the quotes of the next lines
+                in_quote = false;
+            }
+            if protected {
+                continue;
+            }
+            let before = i.checked_sub(1).map(|i| chars[i].c);
+            let after = chars.get(i + 1).map(|ch| ch.c);
+            chars[i].c = if DOUBLE_QUOTES.contains(&c) {
+                let opens = match c {
+                    '„' | '「' => true,
+                    '」' => false,
+                    _ => !in_quote,
+                };
+                in_quote = opens;
+                self.replace(c, if opens { open } else { close })
+            } else if (c == '\'' || c == '’') && is_letter(before) && is_letter(after) {
+                match self.quotes {
+                    QuoteStyle::Straight => self.replace(c, '\''),
+                    QuoteStyle::Typographic => self.replace(c, '’'),
+                    QuoteStyle::Keep | QuoteStyle::Corner => c,
+                }
+            } else {
+                c
+            };
+        }
+        chars
+    }
+
+    /// Makes punctuation written next to CJK characters full-width,
+    /// dropping the spaces around it.
+    fn full_width_pass(&mut self, chars: Vec<Char>) -> Vec<Char> {
+        let mut output: Vec<Char> = Vec::with_capacity(chars.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let ch = chars[i];
+            i += 1;
+            let full = self
+                .rules
+                .full_width
+                .iter()
+                .find(|(half, _)| *half == ch.c)
+                .map(|(_, full)| *full);
+            let (Some(full), false) = (full, ch.protected) else {
+                output.push(ch);
+                continue;
+            };
+            let before = output.last().map(|ch| ch.c);
+            let after = chars.get(i).map(|ch| ch.c);
+            let ends_here = after.is_none_or(|a| a.is_whitespace() || is_cjk(a) || is_closer(a));
+            let applies = match ch.c {
+                '(' => after.is_some_and(is_cjk),
+                // Not an ellipsis
+                '.' => before.is_some_and(is_cjk) && after != Some('.') && ends_here,
+                _ => before.is_some_and(is_cjk) && ends_here,
+            };
+            if !applies {
+                output.push(ch);
+                continue;
+            }
+            if full == '（' {
+                while output.last().is_some_and(|ch| ch.c == ' ' && !ch.protected) {
+                    output.pop();
+                    self.adjusted += 1;
+                }
+            }
+            output.push(Char::new(self.replace(ch.c, full)));
+            if full != '（' {
+                while chars.get(i).is_some_and(|ch| ch.c == ' ' && !ch.protected) {
+                    self.adjusted += 1;
+                    i += 1;
+                }
+            }
+        }
+        output
+    }
+
+    /// Puts the French no-break spaces before `:;!?»` and after `«`.
+    fn french_spacing_pass(&mut self, chars: Vec<Char>) -> Vec<Char> {
+        let is_space = |ch: &Char| !ch.protected && matches!(ch.c, ' ' | NBSP | NNBSP);
+        let mut output: Vec<Char> = Vec::with_capacity(chars.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let ch = chars[i];
+            i += 1;
+            if ch.protected {
+                output.push(ch);
+                continue;
+            }
+            let after = chars.get(i).map(|ch| ch.c);
+            // A colon or semicolon inside a time, a number or a URL stays
+            let ends_clause = after.is_none_or(|a| a.is_whitespace() || "!?»)".contains(a));
+            let space = match ch.c {
+                ':' if ends_clause => Some(NBSP),
+                ';' if ends_clause => Some(NNBSP),
+                '!' | '?' | '»' => Some(NNBSP),
+                _ => None,
+            };
+            if let Some(space) = space {
+                let mut found = Vec::new();
+                while output.last().is_some_and(is_space) {
+                    found.push(output.pop().map_or(' ', |ch| ch.c));
+                }
+                found.reverse();
+                // "?!" keeps its marks together, and a line starting with
+                // one needs no space
+                let needs_space = output
+                    .last()
+                    .is_some_and(|previous| previous.c != '\n' && !"!?".contains(previous.c));
+                if needs_space {
+                    self.adjusted += respace_cost(&found, space);
+                    output.push(Char::new(space));
+                } else {
+                    self.adjusted += found.len();
+                }
+                output.push(ch);
+                continue;
+            }
+            output.push(ch);
+            if ch.c == '«' {
+                let start = i;
+                while chars.get(i).is_some_and(is_space) {
+                    i += 1;
+                }
+                if i < chars.len() {
+                    let found: Vec<char> = chars[start..i].iter().map(|ch| ch.c).collect();
+                    self.adjusted += respace_cost(&found, NNBSP);
+                    output.push(Char::new(NNBSP));
+                } else {
+                    self.adjusted += i - start;
+                }
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typographic(text: &str, language: &str) -> Normalized {
+        normalize(text, language, QuoteStyle::Typographic)
+    }
+
+    #[test]
+    fn test_chinese_full_width_punctuation() {
+        let normalized = typographic("你好, 世界! 他说: \"今天很好.\" (真的)", "中文");
+        assert_eq!(normalized.text, "你好，世界！他说：“今天很好。”（真的）");
+        // Numbers, ellipses and Latin words keep their punctuation
+        assert_eq!(
+            typographic("版本 3.14 发布了... 支持 Rust, Go.", "中文").text,
+            "版本 3.14 发布了... 支持 Rust, Go."
+        );
+    }
+
+    #[test]
+    fn test_japanese_full_width_punctuation_and_quotes() {
+        let normalized = typographic("はい, そうです. 彼は“ありがとう”と言った!", "日本語");
+        assert_eq!(
+            normalized.text,
+            "はい、そうです。彼は「ありがとう」と言った！"
+        );
+        assert_eq!(normalized.adjusted, 7);
+    }
+
+    #[test]
+    fn test_french_spacing() {
+        let normalized = typographic("Vraiment? Oui! Attention : voici \"le texte\";", "Français");
+        assert_eq!(
+            normalized.text,
+            "Vraiment\u{202F}? Oui\u{202F}! Attention\u{A0}: voici «\u{202F}le texte\u{202F}»\u{202F};"
+        );
+        // Already correct text is unchanged
+        let again = typographic(&normalized.text, "Français");
+        assert_eq!(again.text, normalized.text);
+        assert_eq!(again.adjusted, 0);
+        // Times, URLs and "?!" are left alone
+        assert_eq!(
+            typographic("À 10:30, vraiment ?!", "Français").text,
+            "À 10:30, vraiment\u{202F}?!"
+        );
+    }
+
+    #[test]
+    fn test_quotes_per_language() {
+        let text = "He said \"yes\" and \"no\". It's fine.";
+        assert_eq!(
+            typographic(text, "English").text,
+            "He said “yes” and “no”. It’s fine."
+        );
+        assert_eq!(
+            typographic("Er sagte “ja”.", "Deutsch").text,
+            "Er sagte „ja“."
+        );
+        assert_eq!(
+            typographic("Он сказал \"да\".", "Русский").text,
+            "Он сказал «да»."
+        );
+        assert_eq!(typographic("Dijo \"sí\".", "Español").text, "Dijo «sí».");
+        assert_eq!(
+            typographic("Disse “ciao”.", "Italiano").text,
+            "Disse «ciao»."
+        );
+        // A language without rules gets English quotes
+        assert_eq!(typographic("\"x\"", "Klingon").text, "“x”");
+    }
+
+    #[test]
+    fn test_quote_styles() {
+        let text = "Er sagte „ja“ und it’s „gut“.";
+        assert_eq!(
+            normalize(text, "Deutsch", QuoteStyle::Straight).text,
+            "Er sagte \"ja\" und it's \"gut\"."
+        );
+        assert_eq!(
+            normalize(text, "Deutsch", QuoteStyle::Corner).text,
+            "Er sagte 「ja」 und it’s 「gut」."
+        );
+        let kept = normalize(text, "Deutsch", QuoteStyle::Keep);
+        assert_eq!(kept.text, text);
+        assert_eq!(kept.adjusted, 0);
+    }
+
+    #[test]
+    fn test_unpaired_quote_only_affects_its_line() {
+        assert_eq!(
+            typographic("A 12\" screen\n\"Hi\"", "English").text,
+            "A 12“ screen\n“Hi”"
+        );
+    }
+
+    #[test]
+    fn test_code_and_links_are_left_alone() {
+        let text = "见 `a, b`: https://example.com/a?b=1. 然后, 运行\n```\nx = \"y\"\n```";
+        assert_eq!(
+            typographic(text, "中文").text,
+            "见 `a, b`: https://example.com/a?b=1. 然后，运行\n```\nx = \"y\"\n```"
+        );
+        assert_eq!(
+            typographic("Écrivez à a@b.fr: merci", "Français").text,
+            "Écrivez à a@b.fr\u{A0}: merci"
+        );
+    }
+
+    #[test]
+    fn test_languages_without_punctuation_rules() {
+        for language in [
+            "English",
+            "한국어",
+            "Deutsch",
+            "Português",
+            "العربية",
+            "עברית",
+        ] {
+            let text = "Hi, there? 10:30!";
+            assert_eq!(typographic(text, language).text, text, "{}", language);
+        }
+        assert!(RULES.iter().all(|rules| rules_for(rules.language) == rules));
+    }
+}
@@ -0,0 +1,160 @@
+//! Soft-delete support for destructive actions.
+//!
+//! A destructive action hands what it removed to the [`UndoManager`] instead
+//! of deleting it permanently. The caller shows an "Undo" button while the
+//! item is pending, and performs the permanent deletion only once the item
+//! expires or the app exits.
+//!
+//! The app routes clearing the translation and audio caches, discarding the
+//! offline queue and removing glossary terms through it. The translation
+//! history is an append-only log with no delete action, and the retention
+//! cleanup deletes old files by an explicit policy, so neither is covered.
+
+use std::time::{Duration, Instant};
+
+/// Identifies a pending deletion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoId(u64);
+
+struct Pending<T> {
+    id: UndoId,
+    item: T,
+    expires_at: Instant,
+}
+
+/// Deletions that can still be undone, oldest first.
+pub struct UndoManager<T> {
+    pending: Vec<Pending<T>>,
+    next_id: u64,
+    window: Duration,
+}
+
+impl<T> Default for UndoManager<T> {
+    fn default() -> Self {
+        Self::new(UndoManager::<T>::WINDOW)
+    }
+}
+
+impl<T> UndoManager<T> {
+    /// How long a deletion can be undone by default.
+    pub const WINDOW: Duration = Duration::from_secs(10);
+
+    /// Creates a manager whose deletions can be undone for `window`.
+    pub fn new(window: Duration) -> Self {
+        UndoManager {
+            pending: Vec::new(),
+            next_id: 0,
+            window,
+        }
+    }
+
+    /// How long a deletion can be undone.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a deletion made at `now`.
+    pub fn push(&mut self, item: T, now: Instant) -> UndoId {
+        let id = UndoId(self.next_id);
+        self.next_id += 1;
+        self.pending.push(Pending {
+            id,
+            item,
+            expires_at: now + self.window,
+        });
+        id
+    }
+
+    /// Takes back a pending deletion, `None` if it has already been finalized.
+    pub fn undo(&mut self, id: UndoId) -> Option<T> {
+        let index = self.pending.iter().position(|pending| pending.id == id)?;
+        Some(self.pending.remove(index).item)
+    }
+
+    /// Removes the deletions whose window has passed at `now`.
+    ///
+    /// The caller must delete the returned items permanently.
+    pub fn expire(&mut self, now: Instant) -> Vec<T> {
+        let (expired, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| pending.expires_at <= now);
+        self.pending = pending;
+        expired.into_iter().map(|pending| pending.item).collect()
+    }
+
+    /// Removes all pending deletions, e.g. when the app exits.
+    ///
+    /// The caller must delete the returned items permanently.
+    pub fn drain(&mut self) -> Vec<T> {
+        self.pending.drain(..).map(|pending| pending.item).collect()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_undo_within_window() {
+        let start = Instant::now();
+        let mut undo = UndoManager::default();
+        let id = undo.push("history", start);
+
+        assert!(undo.expire(start + secs(5)).is_empty());
+        assert_eq!(undo.undo(id), Some("history"));
+        assert_eq!(undo.undo(id), None);
+        assert!(undo.is_empty());
+    }
+
+    #[test]
+    fn test_expiry_finalizes() {
+        let start = Instant::now();
+        let mut undo = UndoManager::new(secs(10));
+        let id = undo.push("cache", start);
+
+        assert!(undo.expire(start + secs(9)).is_empty());
+        assert_eq!(undo.expire(start + secs(10)), vec!["cache"]);
+        // Too late to undo
+        assert_eq!(undo.undo(id), None);
+        assert!(undo.expire(start + secs(20)).is_empty());
+    }
+
+    #[test]
+    fn test_stacked_undos() {
+        let start = Instant::now();
+        let mut undo = UndoManager::new(secs(10));
+        let first = undo.push(1, start);
+        let second = undo.push(2, start + secs(4));
+        let third = undo.push(3, start + secs(8));
+
+        // Undo out of order
+        assert_eq!(undo.undo(second), Some(2));
+
+        // Only the first one has expired at 12 s
+        assert_eq!(undo.expire(start + secs(12)), vec![1]);
+        assert_eq!(undo.undo(first), None);
+        assert_eq!(undo.undo(third), Some(3));
+        assert!(undo.is_empty());
+    }
+
+    #[test]
+    fn test_drain_on_exit_before_expiry() {
+        let start = Instant::now();
+        let mut undo = UndoManager::new(secs(10));
+        let id = undo.push("a", start);
+        undo.push("b", start + secs(1));
+
+        assert_eq!(undo.drain(), vec!["a", "b"]);
+        assert!(undo.is_empty());
+        assert_eq!(undo.undo(id), None);
+    }
+}
@@ -0,0 +1,211 @@
+//! Version of the running build and checks for newer releases.
+//!
+//! Releases are looked up on GitHub only when the user asks, and only the
+//! tag, notes and page of the latest release are read; nothing is
+//! downloaded.
+
+use crate::api::transport::shared_client;
+use crate::error::Result;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// Version of this build.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the commit this build was made from, "unknown" outside a
+/// git checkout.
+pub const GIT_HASH: &str = env!("GIT_HASH");
+
+/// GitHub API endpoint of the latest release.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/AnlangA/ai-T/releases/latest";
+
+/// How long an update check may take.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One dot-separated part of a pre-release tag.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    // Declared first: numeric parts sort before alphanumeric ones
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+/// A semantic version, as in `v1.4.0-beta.2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    /// Pre-release identifiers, empty for a release
+    pre: Vec<Identifier>,
+}
+
+impl Version {
+    /// Parses a version or release tag.
+    ///
+    /// A leading `v` and build metadata after `+` are ignored, and a missing
+    /// minor or patch number counts as 0.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim();
+        let tag = tag.strip_prefix(['v', 'V']).unwrap_or(tag);
+        let tag = tag.split_once('+').map_or(tag, |(version, _)| version);
+        let (core, pre) = match tag.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (tag, None),
+        };
+
+        let mut numbers = core.split('.').map(|part| part.parse::<u64>().ok());
+        let major = numbers.next()??;
+        let minor = numbers.next().unwrap_or(Some(0))?;
+        let patch = numbers.next().unwrap_or(Some(0))?;
+        if numbers.next().is_some() {
+            return None;
+        }
+
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|part| match part.parse() {
+                    Ok(number) => Some(Identifier::Numeric(number)),
+                    Err(_) if !part.is_empty() => Some(Identifier::Alphanumeric(part.to_string())),
+                    Err(_) => None,
+                })
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        Some(Version {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A release comes after its pre-releases
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Whether the release `tag` is newer than version `current`.
+///
+/// Tags that are not versions are never newer.
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    match (Version::parse(tag), Version::parse(current)) {
+        (Some(tag), Some(current)) => tag > current,
+        _ => false,
+    }
+}
+
+/// A published release.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Release {
+    /// Tag of the release, e.g. `v0.2.0`
+    #[serde(rename = "tag_name")]
+    pub tag: String,
+    /// Page of the release
+    #[serde(rename = "html_url")]
+    pub url: String,
+    /// Release notes, in Markdown
+    #[serde(rename = "body", default)]
+    pub notes: Option<String>,
+}
+
+/// Looks up the latest release, returning it if it is newer than this build.
+pub async fn check_for_update() -> Result<Option<Release>> {
+    let release: Release = shared_client()
+        .get(LATEST_RELEASE_URL)
+        .header(
+            "User-Agent",
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        )
+        .header("Accept", "application/vnd.github+json")
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    tracing::info!(latest = %release.tag, current = VERSION, "Checked for updates");
+    Ok(is_newer(&release.tag, VERSION).then_some(release))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_tags() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.1.1", "0.1.0"));
+        assert!(is_newer("v1.0", "0.9.12"));
+        assert!(is_newer("v0.10.0", "0.9.0"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.0.9", "0.1.0"));
+        assert!(!is_newer("v0.1.0+build.5", "0.1.0"));
+    }
+
+    #[test]
+    fn test_pre_release_tags() {
+        // A release is newer than its pre-releases, not the other way round
+        assert!(is_newer("v1.0.0", "1.0.0-rc.1"));
+        assert!(!is_newer("v1.0.0-rc.1", "1.0.0"));
+        assert!(is_newer("v1.0.0-rc.1", "0.9.0"));
+
+        // SemVer's example order
+        let order = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in order.windows(2) {
+            assert!(is_newer(pair[1], pair[0]), "{} > {}", pair[1], pair[0]);
+            assert!(!is_newer(pair[0], pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_tags_that_are_not_versions() {
+        assert_eq!(Version::parse("nightly"), None);
+        assert_eq!(Version::parse("v1.2.3.4"), None);
+        assert_eq!(Version::parse("v1.0.0-"), None);
+        assert_eq!(Version::parse("v1.0.0-rc..1"), None);
+        assert!(!is_newer("nightly", "0.1.0"));
+        assert!(!is_newer("v9.0.0", "dev"));
+        assert!(Version::parse(VERSION).is_some());
+    }
+
+    #[test]
+    fn test_release_from_github() {
+        let json = r#"{
+            "tag_name": "v0.2.0",
+            "html_url": "https://github.com/AnlangA/ai-T/releases/tag/v0.2.0",
+            "body": null,
+            "draft": false
+        }"#;
+        let release: Release = serde_json::from_str(json).unwrap();
+        assert_eq!(release.tag, "v0.2.0");
+        assert_eq!(release.notes, None);
+    }
+}
@@ -0,0 +1,255 @@
+//! How history entries were last looked at.
+//!
+//! Reopening a long translation from the history returns to where it was
+//! scrolled to, with the same sections open. The states are kept in their
+//! own small file next to the history, keyed by a hash of the entry, so the
+//! log and anything exported from it never contain them. A state whose
+//! entry is gone from the log is dropped.
+
+use crate::utils::history::HistoryEntry;
+use crate::utils::paths;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// Most entries whose view is remembered; the least recently viewed go first.
+pub const MAX_STATES: usize = 500;
+
+/// Most sections remembered per entry.
+const MAX_SECTIONS: usize = 16;
+
+/// Longest section name kept, in bytes.
+const MAX_SECTION_NAME: usize = 32;
+
+/// What the display looked like for an entry.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    /// How far down the translation was scrolled, from 0.0 to 1.0
+    pub scroll_fraction: f32,
+    /// Whether each collapsible section was open, by name
+    pub sections: BTreeMap<String, bool>,
+}
+
+impl ViewState {
+    /// The state within the size limits.
+    fn capped(mut self) -> Self {
+        self.scroll_fraction = if self.scroll_fraction.is_finite() {
+            self.scroll_fraction.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.sections = std::mem::take(&mut self.sections)
+            .into_iter()
+            .filter(|(name, _)| name.len() <= MAX_SECTION_NAME)
+            .take(MAX_SECTIONS)
+            .collect();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredView {
+    state: ViewState,
+    /// Order of the last visit, for dropping the oldest
+    visited: u64,
+}
+
+/// Identifies a history entry across restarts.
+pub fn entry_key(entry: &HistoryEntry) -> String {
+    let mut hasher = DefaultHasher::new();
+    entry.timestamp.hash(&mut hasher);
+    entry.target_language.hash(&mut hasher);
+    entry.source_text.hash(&mut hasher);
+    entry.translation.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+/// The remembered views, stored in a file.
+pub struct ViewStates {
+    states: HashMap<String, StoredView>,
+    file: PathBuf,
+}
+
+impl ViewStates {
+    /// Loads the views from `file`, starting empty if it is missing or
+    /// unreadable.
+    pub fn new(file: PathBuf) -> Self {
+        let states = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        ViewStates { states, file }
+    }
+
+    /// Returns the default file in the data directory.
+    pub fn default_path() -> PathBuf {
+        paths::data_dir().join("history_views.json")
+    }
+
+    /// The view of `entry` when it was last left, if it is remembered.
+    pub fn get(&self, entry: &HistoryEntry) -> Option<ViewState> {
+        self.states
+            .get(&entry_key(entry))
+            .map(|stored| stored.state.clone())
+    }
+
+    /// Remembers the view of `entry` and saves the views.
+    pub fn set(&mut self, entry: &HistoryEntry, state: ViewState) {
+        let visited = self
+            .states
+            .values()
+            .map(|stored| stored.visited + 1)
+            .max()
+            .unwrap_or(0);
+        let state = state.capped();
+        if state == ViewState::default() {
+            self.states.remove(&entry_key(entry));
+        } else {
+            self.states
+                .insert(entry_key(entry), StoredView { state, visited });
+        }
+        if self.states.len() > MAX_STATES {
+            let mut visits: Vec<u64> = self.states.values().map(|s| s.visited).collect();
+            visits.sort_unstable();
+            let cutoff = visits[self.states.len() - MAX_STATES];
+            self.states.retain(|_, stored| stored.visited >= cutoff);
+        }
+        self.save();
+    }
+
+    /// Drops the views of entries no longer in the history.
+    pub fn retain_entries(&mut self, entries: &[HistoryEntry]) {
+        let live: HashSet<String> = entries.iter().map(entry_key).collect();
+        let before = self.states.len();
+        self.states.retain(|key, _| live.contains(key));
+        if self.states.len() != before {
+            tracing::debug!(
+                "Dropped {} views of deleted history entries",
+                before - self.states.len()
+            );
+            self.save();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Writes the views to disk (best effort).
+    fn save(&self) {
+        let result = serde_json::to_string(&self.states)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(&self.file, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save history views: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn entry(n: u32) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, n % 60)
+                .unwrap(),
+            source_language: "Deutsch".to_string(),
+            target_language: "English".to_string(),
+            source_text: format!("Quelle {}", n),
+            translation: format!("Source {}", n),
+            provenance: None,
+        }
+    }
+
+    fn temp_states(name: &str) -> ViewStates {
+        let file = std::env::temp_dir().join(format!("test_view_states_{}.json", name));
+        let _ = fs::remove_file(&file);
+        ViewStates::new(file)
+    }
+
+    #[test]
+    fn test_view_is_remembered_across_restarts() {
+        let mut states = temp_states("restart");
+        let state = ViewState {
+            scroll_fraction: 0.4,
+            sections: BTreeMap::from([("explanation".to_string(), false)]),
+        };
+        states.set(&entry(1), state.clone());
+        assert_eq!(states.get(&entry(2)), None);
+
+        let reloaded = ViewStates::new(states.file.clone());
+        assert_eq!(reloaded.get(&entry(1)), Some(state));
+        let _ = fs::remove_file(&states.file);
+    }
+
+    #[test]
+    fn test_old_files_and_fields_load_with_defaults() {
+        let state: ViewState = serde_json::from_str(r#"{"scroll_fraction":0.5}"#).unwrap();
+        assert!(state.sections.is_empty());
+        let state: ViewState = serde_json::from_str(r#"{"future_field":true}"#).unwrap();
+        assert_eq!(state, ViewState::default());
+    }
+
+    #[test]
+    fn test_states_are_capped() {
+        let mut states = temp_states("capped");
+        let sections = (0..40)
+            .map(|i| (format!("section {}", i), true))
+            .chain([("x".repeat(100), true)])
+            .collect();
+        states.set(
+            &entry(0),
+            ViewState {
+                scroll_fraction: 7.0,
+                sections,
+            },
+        );
+        let state = states.get(&entry(0)).unwrap();
+        assert_eq!(state.scroll_fraction, 1.0);
+        assert_eq!(state.sections.len(), MAX_SECTIONS);
+
+        // A default view needs no entry
+        states.set(&entry(0), ViewState::default());
+        assert!(states.is_empty());
+
+        let scrolled = ViewState {
+            scroll_fraction: 0.5,
+            ..ViewState::default()
+        };
+        for n in 0..=MAX_STATES as u32 {
+            states.set(&entry(n + 100), scrolled.clone());
+        }
+        assert_eq!(states.len(), MAX_STATES);
+        // The least recently viewed went first
+        assert_eq!(states.get(&entry(100)), None);
+        assert!(states.get(&entry(101)).is_some());
+        let _ = fs::remove_file(&states.file);
+    }
+
+    #[test]
+    fn test_views_of_deleted_entries_are_dropped() {
+        let mut states = temp_states("deleted");
+        let scrolled = ViewState {
+            scroll_fraction: 0.5,
+            ..ViewState::default()
+        };
+        states.set(&entry(1), scrolled.clone());
+        states.set(&entry(2), scrolled);
+        states.retain_entries(&[entry(2), entry(3)]);
+        assert_eq!(states.get(&entry(1)), None);
+        assert!(states.get(&entry(2)).is_some());
+        let _ = fs::remove_file(&states.file);
+    }
+}
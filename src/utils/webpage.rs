@@ -0,0 +1,634 @@
+//! Readable text of a web page, for translating an article from its link.
+//!
+//! A page is fetched without running anything on it: scripts, styles and
+//! embedded frames are dropped unseen, and so are the parts of a page that
+//! surround an article, such as navigation, headers, footers and forms.
+//! When the page marks up an `<article>` or `<main>` element only its
+//! content is kept. What remains is turned into paragraphs of plain text,
+//! with the page title as a heading.
+//!
+//! Downloads are capped at [`MAX_PAGE_BYTES`], and only HTML and plain
+//! text are accepted.
+
+use crate::api::transport::shared_client;
+use crate::utils::links;
+use std::time::Duration;
+
+/// Largest page downloaded.
+pub const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// How long fetching a page may take.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// An article or main element with less text than this is taken as a teaser
+/// and the whole body is read instead.
+const MIN_ARTICLE_CHARS: usize = 200;
+
+/// Elements whose content is never text of the page.
+const HIDDEN: [&str; 8] = [
+    "script", "style", "noscript", "template", "svg", "iframe", "object", "canvas",
+];
+
+/// Elements around an article rather than in it; the title is read apart.
+const BOILERPLATE: [&str; 10] = [
+    "title", "nav", "header", "footer", "aside", "form", "button", "select", "menu", "dialog",
+];
+
+/// Elements that start a paragraph of their own.
+const BLOCKS: [&str; 22] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "ul",
+    "ol",
+    "blockquote",
+    "pre",
+    "table",
+    "tr",
+    "dl",
+    "dt",
+    "dd",
+    "figcaption",
+];
+
+/// Elements without content or closing tag.
+const VOID: [&str; 10] = [
+    "br", "img", "hr", "input", "meta", "link", "source", "wbr", "area", "col",
+];
+
+/// Why a page gave no text.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PageError {
+    /// The request failed or timed out
+    #[error("Could not load the page: {0}")]
+    Request(String),
+
+    /// The server answered with an error status
+    #[error("The server answered {0}")]
+    Status(u16),
+
+    /// The link leads to something other than a web page
+    #[error("The link leads to {0}, not to a web page")]
+    NotWebPage(String),
+
+    /// The page is larger than [`MAX_PAGE_BYTES`]
+    #[error("The page is larger than {} MB", MAX_PAGE_BYTES / (1024 * 1024))]
+    TooLarge,
+
+    /// Nothing readable was found on the page
+    #[error("No readable text was found on the page")]
+    NoText,
+}
+
+/// Readable content of a page.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Article {
+    pub title: Option<String>,
+    /// Paragraphs separated by blank lines
+    pub text: String,
+}
+
+impl Article {
+    /// The article as a source text, headed by its title.
+    pub fn source_text(&self) -> String {
+        match &self.title {
+            Some(title) => format!("# {}\n\n{}", title, self.text),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// The address if `text` is nothing but a web link.
+pub fn single_url(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    match links::find_links(text).as_slice() {
+        [link] if link.range == (0..text.len()) && link.target.starts_with("http") => {
+            Some(link.target.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Host of `url`, to name the page while it loads.
+pub fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+/// Downloads the page at `url` and extracts its text, calling `progress`
+/// with the bytes received so far.
+pub async fn fetch(url: &str, progress: impl Fn(usize)) -> Result<Article, PageError> {
+    let mut response = shared_client()
+        .get(url)
+        .header(
+            "User-Agent",
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        )
+        .header("Accept", "text/html,application/xhtml+xml,text/plain;q=0.8")
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| PageError::Request(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(PageError::Status(status.as_u16()));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase()
+        });
+    let is_html = match content_type.as_deref() {
+        // Servers that don't say mostly serve HTML
+        None | Some("text/html" | "application/xhtml+xml") => true,
+        Some("text/plain") => false,
+        Some(other) => return Err(PageError::NotWebPage(other.to_string())),
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_PAGE_BYTES as u64)
+    {
+        return Err(PageError::TooLarge);
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| PageError::Request(e.to_string()))?
+    {
+        if body.len() + chunk.len() > MAX_PAGE_BYTES {
+            return Err(PageError::TooLarge);
+        }
+        body.extend_from_slice(&chunk);
+        progress(body.len());
+    }
+    let body = String::from_utf8_lossy(&body);
+    tracing::info!(bytes = body.len(), url, "Fetched page");
+
+    let article = if is_html {
+        extract(&body)
+    } else {
+        Article {
+            title: None,
+            text: body.trim().to_string(),
+        }
+    };
+    if article.text.is_empty() {
+        return Err(PageError::NoText);
+    }
+    Ok(article)
+}
+
+/// A piece of an HTML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Text(String),
+    /// Opening tag, by its lowercased name
+    Open(String),
+    /// Closing tag, by its lowercased name
+    Close(String),
+}
+
+/// Splits `html` into tags and text, dropping comments, declarations and
+/// the content of hidden elements.
+fn tokens(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let lower = html.to_ascii_lowercase();
+    let mut i = 0;
+    while i < html.len() {
+        let Some(offset) = html[i..].find('<') else {
+            tokens.push(Token::Text(html[i..].to_string()));
+            break;
+        };
+        if offset > 0 {
+            tokens.push(Token::Text(html[i..i + offset].to_string()));
+        }
+        i += offset;
+
+        let rest = &html[i..];
+        if rest.starts_with("<!--") {
+            i = rest.find("-->").map_or(html.len(), |end| i + end + 3);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            i = rest.find('>').map_or(html.len(), |end| i + end + 1);
+            continue;
+        }
+
+        let closing = rest.starts_with("</");
+        let name_start = i + if closing { 2 } else { 1 };
+        let name_len = html[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(html.len() - name_start);
+        if name_len == 0 {
+            // A lone "<" in the text
+            tokens.push(Token::Text("<".to_string()));
+            i += 1;
+            continue;
+        }
+        let name = lower[name_start..name_start + name_len].to_string();
+        let end = tag_end(html, name_start + name_len);
+        let self_closing = html[..end].ends_with("/>");
+        i = end;
+
+        if closing {
+            tokens.push(Token::Close(name));
+        } else if HIDDEN.contains(&name.as_str()) && !self_closing {
+            // Skipped unparsed, so markup in scripts can't end them early
+            let close = format!("</{}", name);
+            i = lower[i..]
+                .find(&close)
+                .map_or(html.len(), |start| tag_end(html, i + start + close.len()));
+        } else if name == "title" {
+            let text_end = lower[i..]
+                .find("</title")
+                .map_or(html.len(), |start| i + start);
+            tokens.push(Token::Open(name.clone()));
+            tokens.push(Token::Text(html[i..text_end].to_string()));
+            tokens.push(Token::Close(name));
+            i = tag_end(html, text_end);
+        } else {
+            tokens.push(Token::Open(name.clone()));
+            if self_closing && !VOID.contains(&name.as_str()) {
+                tokens.push(Token::Close(name));
+            }
+        }
+    }
+    tokens
+}
+
+/// Byte offset after the `>` ending the tag that continues at `from`,
+/// skipping quoted attribute values.
+fn tag_end(html: &str, from: usize) -> usize {
+    let mut quote = None;
+    for (offset, c) in html[from..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return from + offset + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// Decodes character references in `text`.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "copy" => Some('©'),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Collapses runs of whitespace into single spaces.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The tokens inside the first `name` element, if it holds enough text.
+fn element_content<'a>(tokens: &'a [Token], name: &str) -> Option<&'a [Token]> {
+    let start = tokens
+        .iter()
+        .position(|token| matches!(token, Token::Open(open) if open == name))?;
+    let mut depth = 0;
+    let mut end = tokens.len();
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Open(open) if open == name => depth += 1,
+            Token::Close(close) if close == name => {
+                depth -= 1;
+                if depth == 0 {
+                    end = i;
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let content = &tokens[start + 1..end];
+    let chars: usize = content
+        .iter()
+        .map(|token| match token {
+            Token::Text(text) => text.trim().chars().count(),
+            _ => 0,
+        })
+        .sum();
+    (chars >= MIN_ARTICLE_CHARS).then_some(content)
+}
+
+/// Extracts the title and the readable paragraphs of an HTML page.
+pub fn extract(html: &str) -> Article {
+    let tokens = tokens(html);
+    let title_text = |name: &str| {
+        let start = tokens
+            .iter()
+            .position(|token| matches!(token, Token::Open(open) if open == name))?;
+        let text: String = tokens[start + 1..]
+            .iter()
+            .take_while(|token| !matches!(token, Token::Close(close) if close == name))
+            .filter_map(|token| match token {
+                Token::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        Some(collapse_whitespace(&decode_entities(&text))).filter(|title| !title.is_empty())
+    };
+    let title = title_text("h1").or_else(|| title_text("title"));
+
+    let content = element_content(&tokens, "article")
+        .or_else(|| element_content(&tokens, "main"))
+        .or_else(|| element_content(&tokens, "body"))
+        .unwrap_or(&tokens);
+
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut prefix = "";
+    let mut skipped_depth = 0usize;
+    let mut pre_depth = 0usize;
+    let mut flush = |current: &mut String, prefix: &str| {
+        let text = current.trim();
+        if !text.is_empty() {
+            paragraphs.push(format!("{}{}", prefix, text));
+        }
+        current.clear();
+    };
+    for token in content {
+        match token {
+            Token::Open(name) if BOILERPLATE.contains(&name.as_str()) => skipped_depth += 1,
+            Token::Close(name) if BOILERPLATE.contains(&name.as_str()) => {
+                skipped_depth = skipped_depth.saturating_sub(1);
+            }
+            _ if skipped_depth > 0 => {}
+            Token::Text(text) => {
+                let text = decode_entities(text);
+                if pre_depth > 0 {
+                    current.push_str(&text);
+                } else {
+                    let collapsed = collapse_whitespace(&text);
+                    if !collapsed.is_empty() {
+                        if text.starts_with(char::is_whitespace) && !current.ends_with([' ', '\n'])
+                        {
+                            current.push(' ');
+                        }
+                        current.push_str(&collapsed);
+                        if text.ends_with(char::is_whitespace) {
+                            current.push(' ');
+                        }
+                    }
+                }
+            }
+            Token::Open(name) if name == "br" => {
+                current.truncate(current.trim_end_matches(' ').len());
+                current.push('\n');
+            }
+            Token::Open(name) | Token::Close(name) if BLOCKS.contains(&name.as_str()) => {
+                flush(&mut current, prefix);
+                let opening = matches!(token, Token::Open(_));
+                prefix = match name.as_str() {
+                    "h2" | "h3" | "h4" | "h5" | "h6" if opening => "## ",
+                    "li" if opening => "- ",
+                    _ => "",
+                };
+                if name == "pre" {
+                    pre_depth = if opening {
+                        pre_depth + 1
+                    } else {
+                        pre_depth.saturating_sub(1)
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut current, prefix);
+
+    // The title is the heading already
+    if paragraphs
+        .first()
+        .is_some_and(|first| Some(first) == title.as_ref())
+    {
+        paragraphs.remove(0);
+    }
+    Article {
+        title,
+        text: paragraphs.join("\n\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/html")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_news_article() {
+        let article = extract(&fixture("news_article.html"));
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Rivers return to the valley after a dry decade")
+        );
+        let paragraphs: Vec<&str> = article.text.split("\n\n").collect();
+        assert!(paragraphs[0].starts_with("After ten years of drought"));
+        assert!(paragraphs.contains(&"## What comes next"));
+        assert!(paragraphs.contains(&"- Restore the wetlands"));
+        // Entities decoded, inline markup joined into the sentence
+        assert!(article.text.contains("farmers’ fields & orchards"));
+        assert!(article.text.contains("the river is back, says the mayor"));
+        // Navigation, ads, scripts and the comment form are gone
+        for hidden in [
+            "Subscribe",
+            "Home",
+            "trackVisit",
+            "Leave a comment",
+            "Related",
+        ] {
+            assert!(
+                !article.text.contains(hidden),
+                "{} in {}",
+                hidden,
+                article.text
+            );
+        }
+        assert!(
+            article
+                .source_text()
+                .starts_with("# Rivers return to the valley after a dry decade\n\nAfter ten")
+        );
+    }
+
+    #[test]
+    fn test_page_without_article_element() {
+        let article = extract(&fixture("blog_post.html"));
+        // Without an h1 the document title is used
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Notes on sourdough — Kitchen Log")
+        );
+        let paragraphs: Vec<&str> = article.text.split("\n\n").collect();
+        assert_eq!(paragraphs[0], "Day one: the starter.");
+        assert!(paragraphs[1].contains("Mix flour\nand water"));
+        // Preformatted text keeps its layout
+        assert!(article.text.contains("flour  500 g\nwater  350 g"));
+        assert!(!article.text.contains("© 2024"));
+        assert!(!article.text.contains("<b>"));
+    }
+
+    #[test]
+    fn test_scripts_cannot_end_early() {
+        let html = r#"<html><body><p>Before</p><script>if (a < b) { document.write("</p><p>Injected"); }</script>
+            <style>p > a { color: red }</style><p>After</p><!-- <p>Commented</p> --></body></html>"#;
+        let article = extract(html);
+        assert_eq!(article.text, "Before\n\nAfter");
+        assert_eq!(article.title, None);
+    }
+
+    #[test]
+    fn test_entities_and_broken_markup() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &#233;t&#xE9; &unknown; & done"),
+            "a & b <c> été &unknown; & done"
+        );
+        let article = extract("<p title='a > b'>3 < 4 and &quot;5&quot;<p>unclosed");
+        assert_eq!(article.text, "3 < 4 and \"5\"\n\nunclosed");
+    }
+
+    #[test]
+    fn test_single_url() {
+        assert_eq!(
+            single_url("  https://example.com/news/story?id=4 \n").as_deref(),
+            Some("https://example.com/news/story?id=4")
+        );
+        assert_eq!(
+            single_url("www.example.com/a").as_deref(),
+            Some("https://www.example.com/a")
+        );
+        assert_eq!(single_url("Read https://example.com"), None);
+        assert_eq!(single_url("mail@example.com"), None);
+        assert_eq!(single_url(""), None);
+        assert_eq!(host("https://example.com:8080/a?b"), "example.com:8080");
+        assert_eq!(host("example.com"), "example.com");
+    }
+
+    /// Serves one HTTP response on a local port, returning the page URL.
+    async fn serve_once(response: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            let _ = socket.write_all(&response).await;
+        });
+        url
+    }
+
+    fn response(content_type: &str, body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            content_type,
+            body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_type_and_size() {
+        let html = fixture("news_article.html");
+        let url = serve_once(response("text/html; charset=utf-8", html.as_bytes())).await;
+        let received = std::sync::atomic::AtomicUsize::new(0);
+        let article = fetch(&url, |bytes| {
+            received.store(bytes, std::sync::atomic::Ordering::Relaxed)
+        })
+        .await
+        .unwrap();
+        assert!(article.title.is_some());
+        assert_eq!(
+            received.load(std::sync::atomic::Ordering::Relaxed),
+            html.len()
+        );
+
+        let url = serve_once(response("application/pdf", b"%PDF-1.4")).await;
+        assert_eq!(
+            fetch(&url, |_| {}).await,
+            Err(PageError::NotWebPage("application/pdf".to_string()))
+        );
+
+        let url = serve_once(response("text/html", &vec![b'a'; MAX_PAGE_BYTES + 1])).await;
+        assert_eq!(fetch(&url, |_| {}).await, Err(PageError::TooLarge));
+    }
+}
@@ -0,0 +1,437 @@
+//! End-to-end tests of the streaming pipeline against a local SSE server.
+//!
+//! Each test plays a scenario from `tests/fixtures/sse/` through the real
+//! HTTP transport, `ApiClient`, `Translator` and `TranslationSession`, and
+//! checks the exact events a frontend receives and what ends up in the cache.
+
+mod support;
+
+use ai_translate::api::client::{ApiClient, ChatMessage, RetryPolicy, Role, ThinkingMode};
+use ai_translate::api::prompt::PromptContext;
+use ai_translate::api::request::{InFlightRequest, TranslationRequest};
+use ai_translate::api::session::{SessionOptions, StreamEvent, TranslationSession};
+use ai_translate::api::translator::Translator;
+use ai_translate::error::TranslationError;
+use ai_translate::utils::cache::TranslationCache;
+use ai_translate::utils::config::AppConfig;
+use ai_translate::utils::logger::Logger;
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use support::mock_server::{MockServer, Scenario};
+
+/// Starts a server playing `scenario` and a session pointed at it.
+async fn start(scenario: &str) -> (MockServer, TranslationSession, Arc<TranslationCache>) {
+    start_with(scenario, |client| client).await
+}
+
+/// Like [`start`], with the client set up further by `configure`.
+async fn start_with(
+    scenario: &str,
+    configure: impl FnOnce(ApiClient) -> ApiClient,
+) -> (MockServer, TranslationSession, Arc<TranslationCache>) {
+    let server = MockServer::start(Scenario::load(scenario)).await;
+    let cache_file = std::env::temp_dir().join(format!("test_streaming_{}.json", scenario));
+    let _ = std::fs::remove_file(&cache_file);
+    let _ = std::fs::remove_file(cache_file.with_extension("journal"));
+    let cache = Arc::new(TranslationCache::new(cache_file));
+    let client = configure(ApiClient::new("test_key".to_string()).with_base_url(server.base_url()));
+    let session = TranslationSession::new(
+        Translator::with_client(client, cache.clone()),
+        SessionOptions::default(),
+    );
+    (server, session, cache)
+}
+
+fn request(text: &str) -> TranslationRequest {
+    TranslationRequest {
+        source_text: text.to_string(),
+        target_language: "Deutsch".to_string(),
+        enable_keyword_analysis: false,
+        thinking: ThinkingMode::Disabled,
+        code_language: None,
+        list_mode: false,
+        pivot_language: None,
+        context: PromptContext::default(),
+        show_alternatives: false,
+        max_tokens: None,
+        temperature: None,
+        fast_path: false,
+    }
+}
+
+/// The events of a request, written out so sequences compare at a glance.
+///
+/// Throughput depends on timing and is left out.
+async fn translate(session: &TranslationSession, text: &str) -> Vec<String> {
+    session
+        .translate(request(text), None)
+        .filter_map(|event| {
+            std::future::ready(match event {
+                StreamEvent::Chunk(chunk) => Some(format!("chunk {:?}", chunk)),
+                StreamEvent::Completed => Some("completed".to_string()),
+                StreamEvent::Truncated => Some("truncated".to_string()),
+                StreamEvent::Cancelled => Some("cancelled".to_string()),
+                StreamEvent::Failed(TranslationError::ApiError(message)) => {
+                    Some(format!("failed {}", message))
+                }
+                StreamEvent::Failed(e @ TranslationError::HttpStatus(_)) => {
+                    Some(format!("failed {}", e))
+                }
+                StreamEvent::Failed(TranslationError::StreamError(_)) => {
+                    Some("failed stream".to_string())
+                }
+                StreamEvent::Failed(e) => Some(format!("failed {:?}", e)),
+                StreamEvent::Metrics(metrics) => Some(format!("metrics {:?}", metrics.outcome)),
+                StreamEvent::Throughput(_) => None,
+                event => Some(format!("{:?}", event)),
+            })
+        })
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn test_normal_stream() {
+    let (server, session, cache) = start("normal").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![
+            r#"chunk "Hallo""#,
+            r#"chunk " Welt""#,
+            "completed",
+            "metrics Completed"
+        ]
+    );
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["stream"], true);
+    assert_eq!(
+        requests[0]["messages"][1]["content"],
+        "Translate the following text to Deutsch:\n\nHello world"
+    );
+    assert_eq!(
+        cache.get("Hello world", "Deutsch", false),
+        Some(("Hallo Welt".to_string(), None))
+    );
+
+    // The second time it comes from the cache
+    let events = translate(&session, "Hello world").await;
+    assert_eq!(
+        events,
+        vec![r#"chunk "Hallo Welt""#, "completed", "metrics Completed"]
+    );
+    assert_eq!(server.requests().len(), 1);
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_requests_go_to_the_configured_endpoint() {
+    let server = MockServer::start(Scenario::load("normal")).await;
+    let config = AppConfig {
+        api_base_url: format!(" {}/v1// ", server.base_url()),
+        model: "glm-4-flash".to_string(),
+        ..AppConfig::default()
+    };
+    let client = ApiClient::new("test_key".to_string())
+        .with_base_url(&config.base_url())
+        .with_model(config.chat_model());
+
+    let mut stream = client
+        .stream_chat(
+            vec![ChatMessage {
+                role: Role::User,
+                content: "Hello world".to_string(),
+            }],
+            ThinkingMode::Disabled,
+        )
+        .await;
+    let mut response = String::new();
+    while let Some(chunk) = stream.recv().await {
+        response.push_str(&chunk.unwrap());
+    }
+
+    assert_eq!(response, "Hallo Welt");
+    assert_eq!(server.paths(), ["/v1/chat/completions"]);
+    assert_eq!(server.requests()[0]["model"], "glm-4-flash");
+}
+
+#[tokio::test]
+async fn test_unanswered_request_times_out() {
+    let (server, session, cache) = start_with("no_response", |client| {
+        client.with_timeout(Duration::from_millis(300))
+    })
+    .await;
+
+    let started = Instant::now();
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(events, vec!["failed stream", "metrics Failed"]);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(cache.get("Hello world", "Deutsch", false), None);
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_stalled_stream_times_out() {
+    let (_server, session, cache) = start_with("stalled", |client| {
+        client.with_timeout(Duration::from_millis(300))
+    })
+    .await;
+
+    let started = Instant::now();
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![r#"chunk "Hallo""#, "failed stream", "metrics Failed"]
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(cache.get("Hello world", "Deutsch", false), None);
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_early_disconnect() {
+    let (_server, session, cache) = start("early_disconnect").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![r#"chunk "Hallo""#, "failed stream", "metrics Failed"]
+    );
+    assert_eq!(cache.get("Hello world", "Deutsch", false), None);
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_rate_limited_then_success() {
+    let (server, session, cache) = start_with("rate_limited", |client| {
+        client.with_retry(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        })
+    })
+    .await;
+
+    let events = translate(&session, "Hello").await;
+    assert_eq!(
+        events,
+        vec![
+            "Retrying { attempt: 2, max_attempts: 3 }",
+            r#"chunk "Hallo""#,
+            "completed",
+            "metrics Completed"
+        ]
+    );
+    assert_eq!(server.requests().len(), 2);
+    assert_eq!(
+        cache.get("Hello", "Deutsch", false),
+        Some(("Hallo".to_string(), None))
+    );
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_unauthorized_fails_without_retrying() {
+    let (server, session, cache) = start_with("unauthorized", |client| {
+        client.with_retry(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        })
+    })
+    .await;
+
+    let events = translate(&session, "Hello").await;
+    assert_eq!(
+        events,
+        vec!["failed API error: 401 Unauthorized", "metrics Failed"]
+    );
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(cache.get("Hello", "Deutsch", false), None);
+
+    // Only a new request is sent again
+    let events = translate(&session, "Hello").await;
+    assert_eq!(
+        events,
+        vec![r#"chunk "Hallo""#, "completed", "metrics Completed"]
+    );
+    assert_eq!(server.requests().len(), 2);
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_malformed_chunk_is_skipped() {
+    let (_server, session, cache) = start("malformed_chunk").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![
+            r#"chunk "Hallo""#,
+            r#"chunk " Welt""#,
+            "completed",
+            "metrics Completed"
+        ]
+    );
+    assert_eq!(
+        cache.get("Hello world", "Deutsch", false),
+        Some(("Hallo Welt".to_string(), None))
+    );
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_variant_chunk_shapes_are_read() {
+    let (_server, session, cache) = start("variant_chunks").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![
+            r#"chunk "Hallo""#,
+            r#"chunk " schöne""#,
+            r#"chunk " Welt""#,
+            "completed",
+            "metrics Completed"
+        ]
+    );
+    assert_eq!(
+        cache.get("Hello world", "Deutsch", false),
+        Some(("Hallo schöne Welt".to_string(), None))
+    );
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_repeated_malformed_chunks_are_reported() {
+    let (_server, session, cache) = start("repeated_malformed").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    // The warning is a note apart from the text, so it may come at any point
+    let warning = r#"MalformedChunks { provider: "127.0.0.1" }"#;
+    assert_eq!(events.iter().filter(|event| *event == warning).count(), 1);
+    let rest: Vec<&String> = events.iter().filter(|event| *event != warning).collect();
+    assert_eq!(
+        rest,
+        vec![
+            r#"chunk "Hallo""#,
+            r#"chunk " Welt""#,
+            "completed",
+            "metrics Completed"
+        ]
+    );
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_missing_done_completes_at_end_of_body() {
+    let (_server, session, cache) = start("missing_done").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![r#"chunk "Hallo Welt""#, "completed", "metrics Completed"]
+    );
+    assert_eq!(
+        cache.get("Hello world", "Deutsch", false),
+        Some(("Hallo Welt".to_string(), None))
+    );
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_slow_chunks_arrive_in_order() {
+    let (_server, session, cache) = start("slow_chunks").await;
+
+    let mut first_content_ms = None;
+    let mut events = Vec::new();
+    let mut stream = session.translate(request("Hello world"), None);
+    while let Some(event) = stream.next().await {
+        match event {
+            StreamEvent::Chunk(chunk) => events.push(chunk),
+            StreamEvent::Metrics(metrics) => first_content_ms = metrics.first_content_ms,
+            _ => {}
+        }
+    }
+
+    assert_eq!(events, vec!["Hallo", " Welt"]);
+    // The first delta was held back for 300 ms
+    assert!(first_content_ms.is_some_and(|ms| ms >= 300));
+    assert_eq!(
+        cache.get("Hello world", "Deutsch", false),
+        Some(("Hallo Welt".to_string(), None))
+    );
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_length_limit_is_truncated_and_not_cached() {
+    let (_server, session, cache) = start("length_limit").await;
+
+    let events = translate(&session, "Hello world").await;
+
+    assert_eq!(
+        events,
+        vec![r#"chunk "Hallo""#, "truncated", "metrics Completed"]
+    );
+    assert_eq!(cache.get("Hello world", "Deutsch", false), None);
+    cache.clear();
+}
+
+#[tokio::test]
+async fn test_language_change_mid_stream_keeps_the_requested_language() {
+    let (_server, session, cache) = start("slow_chunks").await;
+    let log_dir = std::env::temp_dir().join("test_streaming_language_change");
+    let _ = std::fs::remove_dir_all(&log_dir);
+    std::fs::create_dir_all(&log_dir).unwrap();
+    let log_path = log_dir.join("translations.log");
+    let logger = Logger::new(log_path.to_str().unwrap()).unwrap();
+
+    // The request is recorded with the language selected when it starts
+    let mut config = AppConfig {
+        target_language: "English".to_string(),
+        ..AppConfig::default()
+    };
+    let in_flight = InFlightRequest::new(
+        TranslationRequest {
+            target_language: config.target_language.clone(),
+            ..request("Bonjour le monde")
+        },
+        "mock-model",
+    );
+
+    let mut translation = String::new();
+    let mut stream = session.translate(in_flight.request.clone(), None);
+    while let Some(event) = stream.next().await {
+        if let StreamEvent::Chunk(chunk) = event {
+            translation.push_str(&chunk);
+            // The combo is flipped while the text streams
+            config.target_language = "日本語".to_string();
+        }
+    }
+    in_flight.log_completion(&logger, &translation, None);
+    logger.flush();
+
+    assert_eq!(config.target_language, "日本語");
+    assert_eq!(
+        cache.get("Bonjour le monde", "English", false),
+        Some(("Hallo Welt".to_string(), None))
+    );
+    assert_eq!(cache.get("Bonjour le monde", "日本語", false), None);
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("Target Language: English\n"), "{}", log);
+    assert!(log.contains("Model: mock-model\n"), "{}", log);
+    assert!(!log.contains("日本語"), "{}", log);
+    cache.clear();
+    let _ = std::fs::remove_dir_all(&log_dir);
+}
@@ -0,0 +1,316 @@
+//! Local HTTP server speaking the provider's SSE protocol.
+//!
+//! The server answers chat requests from a [`Scenario`], so the whole
+//! pipeline from `ApiClient` to `StreamEvent`s runs against real sockets,
+//! chunked transfer encoding and connection failures, without the network.
+//!
+//! # Scenario files
+//!
+//! Scenarios live in `tests/fixtures/sse/*.sse`. Every line is sent as is,
+//! so a response body captured with `RUST_LOG=trace` can be pasted in to
+//! reproduce a bug. A blank line ends an SSE event, and each event goes out
+//! in its own HTTP chunk. Lines starting with `@` control the response:
+//!
+//! - `@status 429` answers with that status instead of 200, the body lines
+//!   become the error body
+//! - `@delay 300` waits that many milliseconds before the next event
+//! - `@disconnect` drops the connection without finishing the response
+//! - `@silent` never answers, keeping the connection open until the
+//!   client closes it
+//! - `@next` starts the response to the next request; the last response
+//!   answers every request after it
+//!
+//! Lines starting with `#` are comments.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// One step of a scripted response.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// An SSE event, sent in one chunk
+    Event(String),
+    Delay(Duration),
+    Disconnect,
+}
+
+/// Scripted answer to one request.
+#[derive(Debug, Clone, PartialEq)]
+struct Response {
+    status: u16,
+    steps: Vec<Step>,
+    /// Nothing is sent, not even the status line
+    silent: bool,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Response {
+            status: 200,
+            steps: Vec::new(),
+            silent: false,
+        }
+    }
+}
+
+/// Answers to successive requests, parsed from a scenario file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    responses: Vec<Response>,
+}
+
+impl Scenario {
+    /// Parses a scenario, see the [module documentation](self) for the format.
+    pub fn parse(text: &str) -> Scenario {
+        let mut responses = vec![Response::default()];
+        let mut event = String::new();
+
+        fn flush(event: &mut String, response: &mut Response) {
+            if !event.is_empty() {
+                event.push('\n');
+                response.steps.push(Step::Event(std::mem::take(event)));
+            }
+        }
+
+        for line in text.lines() {
+            let response = responses.last_mut().expect("at least one response");
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some(directive) = line.strip_prefix('@') {
+                flush(&mut event, response);
+                let (name, value) = directive.split_once(' ').unwrap_or((directive, ""));
+                match name {
+                    "status" => response.status = value.trim().parse().expect("status code"),
+                    "delay" => {
+                        let millis = value.trim().parse().expect("delay in milliseconds");
+                        response
+                            .steps
+                            .push(Step::Delay(Duration::from_millis(millis)));
+                    }
+                    "disconnect" => response.steps.push(Step::Disconnect),
+                    "silent" => response.silent = true,
+                    "next" => responses.push(Response::default()),
+                    _ => panic!("unknown directive @{}", name),
+                }
+            } else if line.is_empty() {
+                flush(&mut event, response);
+            } else {
+                event.push_str(line);
+                event.push('\n');
+            }
+        }
+        let response = responses.last_mut().expect("at least one response");
+        flush(&mut event, response);
+
+        Scenario { responses }
+    }
+
+    /// Loads `tests/fixtures/sse/<name>.sse`.
+    pub fn load(name: &str) -> Scenario {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/sse")
+            .join(format!("{}.sse", name));
+        let text = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+        Scenario::parse(&text)
+    }
+}
+
+/// Running mock server, stopped when dropped.
+pub struct MockServer {
+    base_url: String,
+    requests: Arc<Mutex<Vec<serde_json::Value>>>,
+    paths: Arc<Mutex<Vec<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a server on a free local port that answers with `scenario`.
+    pub async fn start(scenario: Scenario) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let base_url = format!("http://{}", listener.local_addr().expect("address"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let scenario = Arc::new(scenario);
+        let served = Arc::new(AtomicUsize::new(0));
+
+        let task = {
+            let requests = requests.clone();
+            let paths = paths.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let scenario = scenario.clone();
+                    let requests = requests.clone();
+                    let paths = paths.clone();
+                    let served = served.clone();
+                    tokio::spawn(async move {
+                        serve(stream, &scenario, &requests, &paths, &served).await;
+                    });
+                }
+            })
+        };
+
+        MockServer {
+            base_url,
+            requests,
+            paths,
+            task,
+        }
+    }
+
+    /// Base URL to point an `ApiClient` at.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Bodies of the requests received so far, as JSON.
+    pub fn requests(&self) -> Vec<serde_json::Value> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Paths of the requests received so far.
+    pub fn paths(&self) -> Vec<String> {
+        self.paths.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Reads one request from `stream` and plays the matching response.
+async fn serve(
+    stream: TcpStream,
+    scenario: &Scenario,
+    requests: &Mutex<Vec<serde_json::Value>>,
+    paths: &Mutex<Vec<String>>,
+    served: &AtomicUsize,
+) {
+    let mut reader = BufReader::new(stream);
+
+    // Request line, of which only the path matters
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+        return;
+    }
+    if let Some(path) = line.split_whitespace().nth(1) {
+        paths.lock().unwrap().push(path.to_string());
+    }
+
+    // Headers, only the body length matters
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    let mut body = vec![0; content_length];
+    if reader.read_exact(&mut body).await.is_err() {
+        return;
+    }
+    if let Ok(json) = serde_json::from_slice(&body) {
+        requests.lock().unwrap().push(json);
+    }
+
+    let index = served.fetch_add(1, Ordering::SeqCst);
+    let response = &scenario.responses[index.min(scenario.responses.len() - 1)];
+    if response.silent {
+        let _ = reader.read_to_end(&mut Vec::new()).await;
+        return;
+    }
+    let mut stream = reader.into_inner();
+
+    if response.status != 200 {
+        let body: String = response
+            .steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Event(event) => Some(event.as_str()),
+                _ => None,
+            })
+            .collect();
+        let head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            body.len()
+        );
+        let _ = stream.write_all(head.as_bytes()).await;
+        let _ = stream.write_all(body.as_bytes()).await;
+        return;
+    }
+
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n";
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    for step in &response.steps {
+        match step {
+            Step::Event(event) => {
+                let chunk = format!("{:x}\r\n{}\r\n", event.len(), event);
+                if stream.write_all(chunk.as_bytes()).await.is_err() {
+                    return;
+                }
+                let _ = stream.flush().await;
+            }
+            Step::Delay(delay) => tokio::time::sleep(*delay).await,
+            // Dropping the stream without the last chunk cuts the body short
+            Step::Disconnect => return,
+        }
+    }
+    let _ = stream.write_all(b"0\r\n\r\n").await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = Scenario::parse(
+            "# comment\ndata: one\n\n@delay 5\ndata: two\nid: 2\n\n@disconnect\n@next\n@status 429\n{\"error\":1}\n@next\n@silent\n",
+        );
+        assert_eq!(
+            scenario.responses,
+            vec![
+                Response {
+                    status: 200,
+                    steps: vec![
+                        Step::Event("data: one\n\n".to_string()),
+                        Step::Delay(Duration::from_millis(5)),
+                        Step::Event("data: two\nid: 2\n\n".to_string()),
+                        Step::Disconnect,
+                    ],
+                    silent: false,
+                },
+                Response {
+                    status: 429,
+                    steps: vec![Step::Event("{\"error\":1}\n\n".to_string())],
+                    silent: false,
+                },
+                Response {
+                    status: 200,
+                    steps: Vec::new(),
+                    silent: true,
+                },
+            ]
+        );
+    }
+}